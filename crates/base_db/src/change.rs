@@ -3,20 +3,95 @@
 
 use std::{fmt, sync::Arc};
 
-use crate::{CrateGraph, SourceDatabaseExt, SourceRoot, SourceRootId};
-use rustc_hash::FxHashSet;
+use crate::{
+    AnchoredPath, CrateGraph, CrateId, FileLoader, FileLoaderDelegate, FileSource, LazyTextSource,
+    SourceDatabase, SourceDatabaseExt, SourceRoot, SourceRootId,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
 use salsa::Durability;
 use serde::{Deserialize, Serialize};
 use vfs::FileId;
 
 /// Encapsulate a bunch of raw `.set` calls on the database.
-#[derive(Serialize, Deserialize, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
 pub struct Change {
     pub roots: Option<Vec<SourceRoot>>,
     pub files_changed: Vec<(FileId, Option<Arc<String>>)>,
     pub crate_graph: Option<CrateGraph>,
+    /// Files whose text is only materialized when first requested.
+    #[serde(skip)]
+    pub lazy_files: Option<LazyFiles>,
+    /// See [`SourceDatabase::active_targets`].
+    #[serde(skip)]
+    pub active_targets: Option<FxHashMap<FileId, String>>,
 }
 
+/// A set of files backed by a [`LazyTextSource`].
+#[derive(Debug, Clone)]
+pub struct LazyFiles {
+    pub source: Arc<dyn LazyTextSource>,
+    pub files: Vec<FileId>,
+}
+
+impl Eq for LazyFiles {}
+impl PartialEq for LazyFiles {
+    fn eq(&self, other: &LazyFiles) -> bool {
+        Arc::ptr_eq(&self.source, &other.source) && self.files == other.files
+    }
+}
+
+/// Error produced when deserializing a [`Change`], pointing at the part of the
+/// input which failed.
+#[derive(Debug)]
+pub enum ChangeDeserError {
+    /// The input is not well-formed JSON.
+    Syntax(String),
+    /// A source root or its file set is malformed.
+    Roots { path: String, message: String },
+    /// A changed file or its text is malformed.
+    FilesChanged { path: String, message: String },
+    /// The crate graph (crate ids, crate data, dependencies) is malformed.
+    CrateGraph { path: String, message: String },
+}
+
+impl ChangeDeserError {
+    pub(crate) fn from_path_error(err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = err.path().to_string();
+        let field = match err.path().iter().next() {
+            Some(serde_path_to_error::Segment::Map { key }) => key.clone(),
+            _ => String::new(),
+        };
+        let err = err.into_inner();
+        let message = err.to_string();
+        match field.as_str() {
+            _ if err.is_syntax() || err.is_eof() => ChangeDeserError::Syntax(message),
+            "roots" => ChangeDeserError::Roots { path, message },
+            "files_changed" | "files" => ChangeDeserError::FilesChanged { path, message },
+            "crate_graph" => ChangeDeserError::CrateGraph { path, message },
+            _ => ChangeDeserError::Syntax(message),
+        }
+    }
+}
+
+impl fmt::Display for ChangeDeserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeDeserError::Syntax(message) => write!(f, "malformed change: {}", message),
+            ChangeDeserError::Roots { path, message } => {
+                write!(f, "invalid source roots at `{}`: {}", path, message)
+            }
+            ChangeDeserError::FilesChanged { path, message } => {
+                write!(f, "invalid changed files at `{}`: {}", path, message)
+            }
+            ChangeDeserError::CrateGraph { path, message } => {
+                write!(f, "invalid crate graph at `{}`: {}", path, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChangeDeserError {}
+
 impl fmt::Debug for Change {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         let mut d = fmt.debug_struct("Change");
@@ -29,6 +104,12 @@ impl fmt::Debug for Change {
         if self.crate_graph.is_some() {
             d.field("crate_graph", &self.crate_graph);
         }
+        if let Some(lazy_files) = &self.lazy_files {
+            d.field("lazy_files", &lazy_files.files.len());
+        }
+        if let Some(active_targets) = &self.active_targets {
+            d.field("active_targets", active_targets);
+        }
         d.finish()
     }
 }
@@ -38,6 +119,12 @@ impl Change {
         Change::default()
     }
 
+    /// Deserializes a change serialized with `serde_json`.
+    pub fn from_json(json: &str) -> Result<Change, ChangeDeserError> {
+        let de = &mut serde_json::Deserializer::from_str(json);
+        serde_path_to_error::deserialize(de).map_err(ChangeDeserError::from_path_error)
+    }
+
     pub fn set_roots(&mut self, roots: Vec<SourceRoot>) {
         self.roots = Some(roots);
     }
@@ -50,6 +137,15 @@ impl Change {
         self.crate_graph = Some(graph);
     }
 
+    /// Registers `files` whose text will be requested from `source` lazily.
+    pub fn set_lazy_files(&mut self, source: Arc<dyn LazyTextSource>, files: Vec<FileId>) {
+        self.lazy_files = Some(LazyFiles { source, files });
+    }
+
+    pub fn set_active_targets(&mut self, active_targets: FxHashMap<FileId, String>) {
+        self.active_targets = Some(active_targets);
+    }
+
     pub fn apply(self, db: &mut dyn SourceDatabaseExt) {
         let _p = profile::span("RootDatabase::apply_change");
         // db.request_cancellation();
@@ -80,11 +176,154 @@ impl Change {
             let durability = durability(&source_root);
             // XXX: can't actually remove the file, just reset the text
             let text = text.unwrap_or_default();
-            db.set_file_text_with_durability(file_id, text, durability)
+            db.set_file_source_with_durability(file_id, FileSource::Text(text), durability)
+        }
+        if let Some(LazyFiles { source, files }) = self.lazy_files {
+            for file_id in files {
+                let source_root_id = db.file_source_root(file_id);
+                let durability = durability(&db.source_root(source_root_id));
+                db.set_file_source_with_durability(
+                    file_id,
+                    FileSource::Lazy(source.clone()),
+                    durability,
+                )
+            }
         }
         if let Some(crate_graph) = self.crate_graph {
             db.set_crate_graph_with_durability(Arc::new(crate_graph), Durability::HIGH)
         }
+        if let Some(active_targets) = self.active_targets {
+            db.set_active_targets_with_durability(Arc::new(active_targets), Durability::MEDIUM)
+        }
+    }
+}
+
+/// The first difference found by [`roundtrip_check`].
+#[derive(Debug)]
+pub enum Mismatch {
+    /// The serialized change could not be read back.
+    Deserialize(ChangeDeserError),
+    /// Applying the round-tripped change produced different source roots.
+    Roots { root: SourceRootId, expected: SourceRoot, actual: SourceRoot },
+    /// Applying the round-tripped change produced a different file text.
+    FileText { file_id: FileId, expected: Arc<String>, actual: Arc<String> },
+    /// Applying the round-tripped change produced a different crate graph.
+    CrateGraph { expected: serde_json::Value, actual: serde_json::Value },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Deserialize(err) => write!(f, "failed to deserialize change: {}", err),
+            Mismatch::Roots { root, expected, actual } => {
+                write!(f, "source root {:?} differs: {:?} != {:?}", root, expected, actual)
+            }
+            Mismatch::FileText { file_id, expected, actual } => {
+                write!(f, "text of {:?} differs: {:?} != {:?}", file_id, expected, actual)
+            }
+            Mismatch::CrateGraph { expected, actual } => {
+                write!(f, "crate graph differs:\n{}\n!=\n{}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+/// Serializes `change` to JSON, reads it back, applies both versions to empty
+/// databases and checks that the resulting inputs are the same.
+///
+/// Proc macro expanders are not serialized, so crate graphs are compared by
+/// their serialized form rather than with `==`.
+pub fn roundtrip_check(change: &Change) -> Result<(), Mismatch> {
+    let json = serde_json::to_string(change).expect("serialization of change must work");
+    let roundtripped = Change::from_json(&json).map_err(Mismatch::Deserialize)?;
+
+    let mut expected_db = RoundtripDb::default();
+    change.clone().apply(&mut expected_db);
+    let mut actual_db = RoundtripDb::default();
+    roundtripped.apply(&mut actual_db);
+
+    for (idx, root) in change.roots.iter().flatten().enumerate() {
+        let root_id = SourceRootId(idx as u32);
+        let actual = actual_db.source_root(root_id);
+        if *root != *actual {
+            return Err(Mismatch::Roots {
+                root: root_id,
+                expected: root.clone(),
+                actual: (*actual).clone(),
+            });
+        }
+    }
+    for (file_id, _) in &change.files_changed {
+        let expected = SourceDatabaseExt::file_text(&expected_db, *file_id);
+        let actual = SourceDatabaseExt::file_text(&actual_db, *file_id);
+        if expected != actual {
+            return Err(Mismatch::FileText { file_id: *file_id, expected, actual });
+        }
+    }
+    if change.crate_graph.is_some() {
+        let expected = canonicalize(serde_json::to_value(&*expected_db.crate_graph()).unwrap());
+        let actual = canonicalize(serde_json::to_value(&*actual_db.crate_graph()).unwrap());
+        if expected != actual {
+            return Err(Mismatch::CrateGraph { expected, actual });
+        }
+    }
+    Ok(())
+}
+
+/// Sorts the sets in `value`, recursively. The atoms of `CfgOptions` serialize
+/// in iteration order, which differs between equal sets. Other arrays, like
+/// dependencies, are lists whose order matters, and maps become JSON objects,
+/// which compare regardless of order.
+fn canonicalize(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(canonicalize).collect())
+        }
+        serde_json::Value::Object(fields) => serde_json::Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = match (key.as_str(), canonicalize(value)) {
+                        ("enabled", serde_json::Value::Array(mut atoms)) => {
+                            atoms.sort_by_cached_key(|it| it.to_string());
+                            serde_json::Value::Array(atoms)
+                        }
+                        (_, value) => value,
+                    };
+                    (key, value)
+                })
+                .collect(),
+        ),
+        it => it,
+    }
+}
+
+/// The smallest database `Change::apply` can be run against.
+#[salsa::database(crate::SourceDatabaseStorage, crate::SourceDatabaseExtStorage)]
+#[derive(Default)]
+pub(crate) struct RoundtripDb {
+    storage: salsa::Storage<RoundtripDb>,
+}
+
+impl salsa::Database for RoundtripDb {}
+
+impl fmt::Debug for RoundtripDb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoundtripDb").finish()
+    }
+}
+
+impl FileLoader for RoundtripDb {
+    fn file_text(&self, file_id: FileId) -> Arc<String> {
+        FileLoaderDelegate(self).file_text(file_id)
+    }
+    fn resolve_path(&self, path: AnchoredPath) -> Option<FileId> {
+        FileLoaderDelegate(self).resolve_path(path)
+    }
+    fn relevant_crates(&self, file_id: FileId) -> Arc<FxHashSet<CrateId>> {
+        FileLoaderDelegate(self).relevant_crates(file_id)
     }
 }
 
@@ -95,3 +334,166 @@ fn durability(source_root: &SourceRoot) -> Durability {
         Durability::LOW
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use cfg::CfgOptions;
+    use oorandom::Rand32;
+    use vfs::{file_set::FileSet, AbsPathBuf, VfsPath};
+
+    use crate::{CrateDisplayName, CrateName, Edition, Env};
+
+    use super::*;
+
+    #[test]
+    fn canonicalize_keeps_list_order() {
+        let canonical = |value| canonicalize(value).to_string();
+        let cfg = |atoms| serde_json::json!({ "cfg_options": { "enabled": atoms } });
+        assert_eq!(
+            canonical(cfg(serde_json::json!(["b", "a"]))),
+            canonical(cfg(serde_json::json!(["a", "b"])))
+        );
+        let deps = |deps| serde_json::json!({ "dependencies": deps });
+        assert_ne!(
+            canonical(deps(serde_json::json!([1, 2]))),
+            canonical(deps(serde_json::json!([2, 1])))
+        );
+    }
+
+    /// Generates random source roots, crate graphs and file edits and checks
+    /// that they survive a serialization round trip.
+    #[test]
+    fn fuzz_roundtrip() {
+        let seed = std::env::var("RA_FUZZ_SEED").ok().and_then(|it| it.parse().ok()).unwrap_or(92);
+        let mut rng = Rand32::new(seed);
+        for _ in 0..64 {
+            let change = random_change(&mut rng);
+            if let Err(mismatch) = roundtrip_check(&change) {
+                panic!("seed {}: {}\n{:?}", seed, mismatch, change);
+            }
+        }
+    }
+
+    fn random_change(rng: &mut Rand32) -> Change {
+        let n_files = rng.rand_range(1..24);
+        let n_roots = rng.rand_range(1..4);
+
+        let mut file_sets = vec![FileSet::default(); n_roots as usize];
+        for file in 0..n_files {
+            let root = rng.rand_range(0..n_roots);
+            let path = if cfg!(windows) {
+                format!(r"C:\root{}\file{}.rs", root, file)
+            } else {
+                format!("/root{}/file{}.rs", root, file)
+            };
+            let path = VfsPath::from(AbsPathBuf::assert(PathBuf::from(path)));
+            file_sets[root as usize].insert(FileId(file), path);
+        }
+        let roots = file_sets
+            .into_iter()
+            .map(|file_set| {
+                if rng.rand_range(0..2) == 0 {
+                    SourceRoot::new_local(file_set)
+                } else {
+                    SourceRoot::new_library(file_set)
+                }
+            })
+            .collect();
+
+        let mut change = Change::new();
+        change.set_roots(roots);
+        for file in 0..n_files {
+            let text = match rng.rand_range(0..8) {
+                0 => None,
+                1 => Some(String::new()),
+                _ => Some(random_text(rng)),
+            };
+            change.change_file(FileId(file), text.map(Arc::new));
+        }
+        if rng.rand_range(0..4) != 0 {
+            change.set_crate_graph(random_crate_graph(rng, n_files));
+        }
+        change
+    }
+
+    fn random_crate_graph(rng: &mut Rand32, n_files: u32) -> CrateGraph {
+        let mut graph = CrateGraph::default();
+        let mut crates: Vec<CrateId> = Vec::new();
+        for idx in 0..rng.rand_range(0..n_files + 1) {
+            let display_name = if rng.rand_range(0..3) == 0 {
+                None
+            } else {
+                Some(CrateDisplayName::from_canonical_name(format!("crate-{}", idx)))
+            };
+            let edition = match rng.rand_range(0..3) {
+                0 => Edition::Edition2015,
+                1 => Edition::Edition2018,
+                _ => Edition::Edition2021,
+            };
+            let cfg_options = random_cfg(rng);
+            let mut potential_cfg_options = cfg_options.clone();
+            potential_cfg_options.insert_key_value("feature".into(), "extra".into());
+            let env: Env = (0..rng.rand_range(0..3))
+                .map(|it| (format!("VAR_{}", it), random_text(rng)))
+                .collect();
+            let krate = graph.add_crate_root(
+                FileId(rng.rand_range(0..n_files)),
+                edition,
+                display_name,
+                cfg_options,
+                potential_cfg_options,
+                env,
+                Vec::new(),
+            );
+            // Only depend on earlier crates, so that the graph stays acyclic.
+            for _ in 0..rng.rand_range(0..3) {
+                if crates.is_empty() {
+                    break;
+                }
+                let dep = crates[rng.rand_range(0..crates.len() as u32) as usize];
+                let name = CrateName::normalize_dashes(&format!("dep-{}", dep.0));
+                graph.add_dep(krate, name, dep).unwrap();
+            }
+            crates.push(krate);
+        }
+        graph
+    }
+
+    fn random_cfg(rng: &mut Rand32) -> CfgOptions {
+        let mut cfg = CfgOptions::default();
+        for _ in 0..rng.rand_range(0..5) {
+            let atom =
+                ["test", "unix", "windows", "debug_assertions"][rng.rand_range(0..4) as usize];
+            cfg.insert_atom(atom.into());
+        }
+        for _ in 0..rng.rand_range(0..3) {
+            let value = ["serde", "std", "with \"quotes\""][rng.rand_range(0..3) as usize];
+            cfg.insert_key_value("feature".into(), value.into());
+        }
+        cfg
+    }
+
+    fn random_text(rng: &mut Rand32) -> String {
+        const PIECES: &[&str] =
+            &["fn main() {}", "\n", "\"", "\\", "ünïcödé", "// comment", "\t", "{}", "🦀"];
+        (0..rng.rand_range(1..16))
+            .map(|_| PIECES[rng.rand_range(0..PIECES.len() as u32) as usize])
+            .collect()
+    }
+
+    #[test]
+    fn reports_malformed_field() {
+        let err =
+            Change::from_json(r#"{"roots":null,"files_changed":[["x",null]],"crate_graph":null}"#)
+                .unwrap_err();
+        assert!(matches!(err, ChangeDeserError::FilesChanged { .. }), "{:?}", err);
+
+        let err = Change::from_json(
+            r#"{"roots":null,"files_changed":[],"crate_graph":{"arena":{"nope":{}}}}"#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ChangeDeserError::CrateGraph { .. }), "{:?}", err);
+    }
+}
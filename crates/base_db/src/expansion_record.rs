@@ -0,0 +1,276 @@
+//! Recording and replaying of proc macro expansions.
+//!
+//! Proc macros can't run without the compiled proc macro dylibs, which are not
+//! part of a snapshot. Instead, the expansions of attribute and derive macros
+//! are recorded while the snapshot is created (see
+//! [`crate::CrateGraph::record_proc_macro_expansions`]) and stored next to the
+//! crate graph. A consumer of the snapshot replays them with
+//! [`crate::CrateGraph::replay_proc_macro_expansions`].
+//!
+//! Expansions are keyed by a hash of the macro name and its input token trees,
+//! including token ids. The inputs are computed from the source text, so the
+//! same source produces the same keys in both processes.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use rustc_hash::{FxHashMap, FxHasher};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tt::{
+    Delimiter, DelimiterKind, ExpansionError, Ident, Leaf, Literal, Punct, SmolStr, Spacing,
+    Subtree, TokenId, TokenTree,
+};
+
+use crate::{Env, ProcMacro, ProcMacroExpander};
+
+/// A thread-safe map from proc macro inputs to their expansions.
+#[derive(Default)]
+pub struct ExpansionRecord {
+    expansions: Mutex<FxHashMap<u64, Subtree>>,
+}
+
+impl ExpansionRecord {
+    pub fn len(&self) -> usize {
+        self.expansions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, key: u64) -> Option<Subtree> {
+        self.expansions.lock().unwrap().get(&key).cloned()
+    }
+
+    fn insert(&self, key: u64, expansion: Subtree) {
+        self.expansions.lock().unwrap().insert(key, expansion);
+    }
+}
+
+impl fmt::Debug for ExpansionRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpansionRecord").field("len", &self.len()).finish()
+    }
+}
+
+fn key(name: &str, subtree: &Subtree, attrs: Option<&Subtree>) -> u64 {
+    let mut hasher = FxHasher::default();
+    name.hash(&mut hasher);
+    subtree.hash(&mut hasher);
+    attrs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Wraps `proc_macro`'s expander so that every successful expansion ends up
+/// in `record`.
+pub(crate) fn recording_expander(
+    proc_macro: &ProcMacro,
+    record: &Arc<ExpansionRecord>,
+) -> Arc<dyn ProcMacroExpander> {
+    Arc::new(RecordingExpander {
+        name: proc_macro.name.clone(),
+        record: record.clone(),
+        inner: proc_macro.expander.clone(),
+    })
+}
+
+/// Creates an expander which looks up expansions of `name` in `record`.
+pub(crate) fn replay_expander(
+    name: &SmolStr,
+    record: &Arc<ExpansionRecord>,
+) -> Arc<dyn ProcMacroExpander> {
+    Arc::new(ReplayExpander { name: name.clone(), record: record.clone() })
+}
+
+#[derive(Debug)]
+struct RecordingExpander {
+    name: SmolStr,
+    record: Arc<ExpansionRecord>,
+    inner: Arc<dyn ProcMacroExpander>,
+}
+
+impl ProcMacroExpander for RecordingExpander {
+    fn expand(
+        &self,
+        subtree: &Subtree,
+        attrs: Option<&Subtree>,
+        env: &Env,
+    ) -> Result<Subtree, ExpansionError> {
+        let res = self.inner.expand(subtree, attrs, env)?;
+        self.record.insert(key(&self.name, subtree, attrs), res.clone());
+        Ok(res)
+    }
+}
+
+#[derive(Debug)]
+struct ReplayExpander {
+    name: SmolStr,
+    record: Arc<ExpansionRecord>,
+}
+
+impl ProcMacroExpander for ReplayExpander {
+    fn expand(
+        &self,
+        subtree: &Subtree,
+        attrs: Option<&Subtree>,
+        _env: &Env,
+    ) -> Result<Subtree, ExpansionError> {
+        self.record.get(key(&self.name, subtree, attrs)).ok_or_else(|| {
+            ExpansionError::Unknown(format!(
+                "expansion of proc macro `{}` was not recorded in the snapshot",
+                self.name
+            ))
+        })
+    }
+}
+
+impl Serialize for ExpansionRecord {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let expansions = self.expansions.lock().unwrap();
+        let mut entries = expansions
+            .iter()
+            .map(|(key, subtree)| (*key, SubtreeRepr::from(subtree)))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(key, _)| *key);
+        entries.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExpansionRecord {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let entries = Vec::<(u64, SubtreeRepr)>::deserialize(deserializer)?;
+        let expansions = entries.into_iter().map(|(key, repr)| (key, repr.into())).collect();
+        Ok(ExpansionRecord { expansions: Mutex::new(expansions) })
+    }
+}
+
+/// `tt` doesn't depend on serde, so token trees are stored through this
+/// mirror.
+#[derive(Serialize, Deserialize)]
+struct SubtreeRepr {
+    delimiter: Option<(u32, char)>,
+    token_trees: Vec<TokenTreeRepr>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum TokenTreeRepr {
+    Subtree(SubtreeRepr),
+    Literal(SmolStr, u32),
+    Punct(char, bool, u32),
+    Ident(SmolStr, u32),
+}
+
+impl From<&Subtree> for SubtreeRepr {
+    fn from(subtree: &Subtree) -> SubtreeRepr {
+        let delimiter = subtree.delimiter.map(|it| {
+            let kind = match it.kind {
+                DelimiterKind::Parenthesis => '(',
+                DelimiterKind::Brace => '{',
+                DelimiterKind::Bracket => '[',
+            };
+            (it.id.0, kind)
+        });
+        let token_trees = subtree
+            .token_trees
+            .iter()
+            .map(|tt| match tt {
+                TokenTree::Subtree(it) => TokenTreeRepr::Subtree(it.into()),
+                TokenTree::Leaf(Leaf::Literal(it)) => {
+                    TokenTreeRepr::Literal(it.text.clone(), it.id.0)
+                }
+                TokenTree::Leaf(Leaf::Punct(it)) => {
+                    TokenTreeRepr::Punct(it.char, it.spacing == Spacing::Joint, it.id.0)
+                }
+                TokenTree::Leaf(Leaf::Ident(it)) => TokenTreeRepr::Ident(it.text.clone(), it.id.0),
+            })
+            .collect();
+        SubtreeRepr { delimiter, token_trees }
+    }
+}
+
+impl From<SubtreeRepr> for Subtree {
+    fn from(repr: SubtreeRepr) -> Subtree {
+        let delimiter = repr.delimiter.map(|(id, kind)| Delimiter {
+            id: TokenId(id),
+            kind: match kind {
+                '(' => DelimiterKind::Parenthesis,
+                '{' => DelimiterKind::Brace,
+                _ => DelimiterKind::Bracket,
+            },
+        });
+        let token_trees = repr
+            .token_trees
+            .into_iter()
+            .map(|tt| match tt {
+                TokenTreeRepr::Subtree(it) => TokenTree::Subtree(it.into()),
+                TokenTreeRepr::Literal(text, id) => {
+                    Leaf::from(Literal { text, id: TokenId(id) }).into()
+                }
+                TokenTreeRepr::Punct(char, joint, id) => {
+                    let spacing = if joint { Spacing::Joint } else { Spacing::Alone };
+                    Leaf::from(Punct { char, spacing, id: TokenId(id) }).into()
+                }
+                TokenTreeRepr::Ident(text, id) => {
+                    Leaf::from(Ident { text, id: TokenId(id) }).into()
+                }
+            })
+            .collect();
+        Subtree { delimiter, token_trees }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Identity;
+
+    impl ProcMacroExpander for Identity {
+        fn expand(
+            &self,
+            subtree: &Subtree,
+            _attrs: Option<&Subtree>,
+            _env: &Env,
+        ) -> Result<Subtree, ExpansionError> {
+            Ok(subtree.clone())
+        }
+    }
+
+    fn subtree() -> Subtree {
+        let ident = Leaf::from(Ident { text: "Foo".into(), id: TokenId(1) });
+        let punct = Leaf::from(Punct { char: ';', spacing: Spacing::Alone, id: TokenId(2) });
+        Subtree {
+            delimiter: Some(Delimiter { id: TokenId(0), kind: DelimiterKind::Brace }),
+            token_trees: vec![ident.into(), punct.into()],
+        }
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let record = Arc::new(ExpansionRecord::default());
+        let recording = RecordingExpander {
+            name: "derive".into(),
+            record: record.clone(),
+            inner: Arc::new(Identity),
+        };
+        let input = subtree();
+        recording.expand(&input, None, &Env::default()).unwrap();
+        assert_eq!(record.len(), 1);
+
+        let json = serde_json::to_string(&*record).unwrap();
+        let replayed: ExpansionRecord = serde_json::from_str(&json).unwrap();
+        let replay = ReplayExpander { name: "derive".into(), record: Arc::new(replayed) };
+        assert_eq!(replay.expand(&input, None, &Env::default()).unwrap(), input);
+        assert!(replay.expand(&Subtree::default(), None, &Env::default()).is_err());
+    }
+}
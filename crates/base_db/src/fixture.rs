@@ -90,6 +90,7 @@ impl ChangeFixture {
         let mut crate_deps = Vec::new();
         let mut default_crate_root: Option<FileId> = None;
         let mut default_cfg = CfgOptions::default();
+        let mut default_potential_cfg = CfgOptions::default();
 
         let mut file_set = FileSet::default();
         let source_root_prefix = "/".to_string();
@@ -128,21 +129,25 @@ impl ChangeFixture {
                     file_id,
                     meta.edition,
                     Some(crate_name.clone().into()),
-                    meta.cfg.clone(),
                     meta.cfg,
+                    meta.potential_cfg,
                     meta.env,
                     Default::default(),
                 );
                 let prev = crates.insert(crate_name.clone(), crate_id);
                 assert!(prev.is_none());
                 for dep in meta.deps {
-                    let dep = CrateName::normalize_dashes(&dep);
-                    crate_deps.push((crate_name.clone(), dep))
+                    // `alias=krate` depends on `krate` under another name.
+                    let (name, dep) = dep.split_once('=').unwrap_or((dep.as_str(), dep.as_str()));
+                    let name = CrateName::normalize_dashes(name);
+                    let dep = CrateName::normalize_dashes(dep);
+                    crate_deps.push((crate_name.clone(), name, dep))
                 }
             } else if meta.path == "/main.rs" || meta.path == "/lib.rs" {
                 assert!(default_crate_root.is_none());
                 default_crate_root = Some(file_id);
                 default_cfg = meta.cfg;
+                default_potential_cfg = meta.potential_cfg;
             }
 
             change.change_file(file_id, Some(Arc::new(text)));
@@ -158,16 +163,16 @@ impl ChangeFixture {
                 crate_root,
                 Edition::CURRENT,
                 Some(CrateName::new("test").unwrap().into()),
-                default_cfg.clone(),
                 default_cfg,
+                default_potential_cfg,
                 Env::default(),
                 Default::default(),
             );
         } else {
-            for (from, to) in crate_deps {
+            for (from, name, to) in crate_deps {
                 let from_id = crates[&from];
                 let to_id = crates[&to];
-                crate_graph.add_dep(from_id, CrateName::new(&to).unwrap(), to_id).unwrap();
+                crate_graph.add_dep(from_id, name, to_id).unwrap();
             }
         }
 
@@ -211,6 +216,7 @@ struct FileMeta {
     krate: Option<String>,
     deps: Vec<String>,
     cfg: CfgOptions,
+    potential_cfg: CfgOptions,
     edition: Edition,
     env: Env,
     introduce_new_source_root: bool,
@@ -221,12 +227,17 @@ impl From<Fixture> for FileMeta {
         let mut cfg = CfgOptions::default();
         f.cfg_atoms.iter().for_each(|it| cfg.insert_atom(it.into()));
         f.cfg_key_values.iter().for_each(|(k, v)| cfg.insert_key_value(k.into(), v.into()));
+        let mut potential_cfg = cfg.clone();
+        f.features
+            .iter()
+            .for_each(|it| potential_cfg.insert_key_value("feature".into(), it.into()));
 
         FileMeta {
             path: f.path,
             krate: f.krate,
             deps: f.deps,
             cfg,
+            potential_cfg,
             edition: f.edition.as_ref().map_or(Edition::CURRENT, |v| Edition::from_str(v).unwrap()),
             env: f.env.into_iter().collect(),
             introduce_new_source_root: f.introduce_new_source_root,
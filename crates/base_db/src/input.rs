@@ -10,11 +10,13 @@ use std::{fmt, iter::FromIterator, ops, panic::RefUnwindSafe, str::FromStr, sync
 
 use cfg::CfgOptions;
 use rustc_hash::{FxHashMap, FxHashSet};
-use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 use syntax::SmolStr;
 use tt::{ExpansionError, Subtree};
 use vfs::{file_set::FileSet, FileId, VfsPath};
 
+use crate::expansion_record::{self, ExpansionRecord};
+
 /// Files are grouped into source roots. A source root is a directory on the
 /// file systems which is watched for changes. Typically it corresponds to a
 /// Rust crate. Source roots *might* be nested: in this case, a file belongs to
@@ -53,6 +55,42 @@ impl SourceRoot {
     }
 }
 
+/// Where the text of a file comes from.
+///
+/// Most files are fed to the database as an in-memory `String`, but texts of
+/// files loaded from a snapshot are only materialized once somebody asks for
+/// them, see [`LazyTextSource`].
+#[derive(Debug, Clone)]
+pub enum FileSource {
+    Text(Arc<String>),
+    Lazy(Arc<dyn LazyTextSource>),
+}
+
+impl From<Arc<String>> for FileSource {
+    fn from(text: Arc<String>) -> FileSource {
+        FileSource::Text(text)
+    }
+}
+
+impl Eq for FileSource {}
+impl PartialEq for FileSource {
+    fn eq(&self, other: &FileSource) -> bool {
+        match (self, other) {
+            (FileSource::Text(it), FileSource::Text(other)) => it == other,
+            (FileSource::Lazy(it), FileSource::Lazy(other)) => Arc::ptr_eq(it, other),
+            _ => false,
+        }
+    }
+}
+
+/// A store of file texts which are produced on demand, for example by slicing
+/// a memory-mapped snapshot.
+pub trait LazyTextSource: fmt::Debug + Send + Sync + RefUnwindSafe {
+    /// Returns the text of `file_id`, or `None` if the store doesn't know
+    /// about the file or its contents are not valid UTF-8.
+    fn text(&self, file_id: FileId) -> Option<String>;
+}
+
 /// `CrateGraph` is a bit of information which turns a set of text files into a
 /// number of Rust crates.
 ///
@@ -92,7 +130,9 @@ impl<'de> Deserialize<'de> for CrateId {
         D: Deserializer<'de>,
     {
         let s: &str = Deserialize::deserialize(deserializer)?;
-        let id = s.parse::<u32>().unwrap();
+        let id = s
+            .parse::<u32>()
+            .map_err(|err| de::Error::custom(format!("invalid crate id {:?}: {}", s, err)))?;
         Ok(CrateId(id))
     }
 }
@@ -164,6 +204,12 @@ impl CrateDisplayName {
         let crate_name = CrateName::normalize_dashes(&canonical_name);
         CrateDisplayName { crate_name, canonical_name }
     }
+
+    /// For library targets named differently from their package, like
+    /// `[lib] name = "foo_impl"` in the `foo` package.
+    pub fn with_canonical_name(crate_name: CrateName, canonical_name: String) -> CrateDisplayName {
+        CrateDisplayName { crate_name, canonical_name }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -205,11 +251,41 @@ impl Serialize for ProcMacro {
 }
 
 impl<'de> Deserialize<'de> for ProcMacro {
-    fn deserialize<D>(_deserializer: D) -> Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        unimplemented!()
+        #[derive(Deserialize)]
+        #[serde(rename = "ProcMacro")]
+        struct Repr {
+            name: SmolStr,
+            kind: ProcMacroKind,
+        }
+
+        let Repr { name, kind } = Repr::deserialize(deserializer)?;
+        let expander = Arc::new(UnavailableExpander { name: name.clone() });
+        Ok(ProcMacro { name, kind, expander })
+    }
+}
+
+/// Stands in for the expander of a deserialized proc macro: the dylib which
+/// implements it is not part of the serialized data.
+#[derive(Debug)]
+struct UnavailableExpander {
+    name: SmolStr,
+}
+
+impl ProcMacroExpander for UnavailableExpander {
+    fn expand(
+        &self,
+        _subtree: &Subtree,
+        _attrs: Option<&Subtree>,
+        _env: &Env,
+    ) -> Result<Subtree, ExpansionError> {
+        Err(ExpansionError::Unknown(format!(
+            "proc macro `{}` is not available in deserialized crate graphs",
+            self.name
+        )))
     }
 }
 
@@ -236,6 +312,21 @@ pub struct CrateData {
     pub env: Env,
     pub dependencies: Vec<Dependency>,
     pub proc_macro: Vec<ProcMacro>,
+    /// The target the crate is compiled for, when the graph holds crates for
+    /// several targets. `None` for the crates of the default target.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// The package the crate belongs to, for crates of Cargo packages.
+    #[serde(default)]
+    pub package: Option<PackageInfo>,
+}
+
+/// Fields of a package manifest shown to the user.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PackageInfo {
+    pub version: String,
+    pub description: Option<String>,
+    pub repository: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -280,6 +371,8 @@ impl CrateGraph {
             env,
             proc_macro,
             dependencies: Vec::new(),
+            target: None,
+            package: None,
         };
         let crate_id = CrateId(self.arena.len() as u32);
         let prev = self.arena.insert(crate_id, data);
@@ -304,6 +397,15 @@ impl CrateGraph {
         Ok(())
     }
 
+    /// Marks `crate_id` as a crate of the non-default `target`.
+    pub fn set_target(&mut self, crate_id: CrateId, target: Option<String>) {
+        self.arena.get_mut(&crate_id).unwrap().target = target;
+    }
+
+    pub fn set_package(&mut self, crate_id: CrateId, package: Option<PackageInfo>) {
+        self.arena.get_mut(&crate_id).unwrap().package = package;
+    }
+
     pub fn is_empty(&self) -> bool {
         self.arena.is_empty()
     }
@@ -356,6 +458,27 @@ impl CrateGraph {
         rev_deps.into_iter()
     }
 
+    /// Groups the crates of the graph into layers, such that all dependencies of a crate live in
+    /// earlier layers. Crates within one layer are independent of each other.
+    pub fn crates_in_topological_layers(&self) -> Vec<Vec<CrateId>> {
+        let mut depth = FxHashMap::default();
+        let mut layers: Vec<Vec<CrateId>> = Vec::new();
+        for krate in self.crates_in_topological_order() {
+            let layer = self[krate]
+                .dependencies
+                .iter()
+                .map(|dep| depth[&dep.crate_id] + 1)
+                .max()
+                .unwrap_or(0);
+            depth.insert(krate, layer);
+            if layers.len() <= layer {
+                layers.resize_with(layer + 1, Vec::new);
+            }
+            layers[layer].push(krate);
+        }
+        layers
+    }
+
     /// Returns all crates in the graph, sorted in topological order (ie. dependencies of a crate
     /// come before the crate itself).
     pub fn crates_in_topological_order(&self) -> Vec<CrateId> {
@@ -408,6 +531,45 @@ impl CrateGraph {
         start
     }
 
+    /// Like [`CrateGraph::extend`], but reuses the crates of this graph which
+    /// are identical to crates of `other`, like the sysroot crates of two
+    /// workspaces. Crates are identical if they have the same root file,
+    /// configuration, proc macros (by name) and dependencies.
+    ///
+    /// Returns the new ids of the crates of `other`.
+    pub fn extend_deduplicated(&mut self, other: CrateGraph) -> FxHashMap<CrateId, CrateId> {
+        let mut by_root: FxHashMap<FileId, Vec<CrateId>> = FxHashMap::default();
+        for (&crate_id, data) in self.arena.iter() {
+            by_root.entry(data.root_file_id).or_default().push(crate_id);
+        }
+
+        let mut mapping = FxHashMap::default();
+        // Dependencies come first, so that they are already mapped when
+        // comparing their dependents.
+        let order = other.crates_in_topological_order();
+        let mut other = other.arena;
+        for old_id in order {
+            let mut data = other.remove(&old_id).unwrap();
+            for dep in &mut data.dependencies {
+                dep.crate_id = mapping[&dep.crate_id];
+            }
+            let candidates = by_root.entry(data.root_file_id).or_default();
+            let existing =
+                candidates.iter().copied().find(|it| is_same_crate(&self.arena[it], &data));
+            let new_id = match existing {
+                Some(it) => it,
+                None => {
+                    let new_id = CrateId(self.arena.len() as u32);
+                    candidates.push(new_id);
+                    self.arena.insert(new_id, data);
+                    new_id
+                }
+            };
+            mapping.insert(old_id, new_id);
+        }
+        mapping
+    }
+
     fn dfs_find(&self, target: CrateId, from: CrateId, visited: &mut FxHashSet<CrateId>) -> bool {
         if !visited.insert(from) {
             return false;
@@ -445,6 +607,28 @@ impl CrateGraph {
         }
     }
 
+    /// Wraps the expanders of all attribute and derive proc macros, so that
+    /// every successful expansion ends up in `record`.
+    pub fn record_proc_macro_expansions(&mut self, record: &Arc<ExpansionRecord>) {
+        for data in self.arena.values_mut() {
+            for proc_macro in &mut data.proc_macro {
+                if matches!(proc_macro.kind, ProcMacroKind::Attr | ProcMacroKind::CustomDerive) {
+                    proc_macro.expander = expansion_record::recording_expander(proc_macro, record);
+                }
+            }
+        }
+    }
+
+    /// Replaces the expanders of all proc macros with ones that look up the
+    /// expansion in `record`.
+    pub fn replay_proc_macro_expansions(&mut self, record: &Arc<ExpansionRecord>) {
+        for data in self.arena.values_mut() {
+            for proc_macro in &mut data.proc_macro {
+                proc_macro.expander = expansion_record::replay_expander(&proc_macro.name, record);
+            }
+        }
+    }
+
     fn hacky_find_crate(&self, display_name: &str) -> Option<CrateId> {
         self.iter().find(|it| self[*it].display_name.as_deref() == Some(display_name))
     }
@@ -457,6 +641,36 @@ impl ops::Index<CrateId> for CrateGraph {
     }
 }
 
+fn is_same_crate(a: &CrateData, b: &CrateData) -> bool {
+    // Proc macro expanders of two workspaces are loaded separately, so they are
+    // never pointer-equal.
+    let same_proc_macros = a.proc_macro.len() == b.proc_macro.len()
+        && a.proc_macro
+            .iter()
+            .zip(&b.proc_macro)
+            .all(|(a, b)| a.name == b.name && a.kind == b.kind);
+    a.root_file_id == b.root_file_id
+        && a.edition == b.edition
+        && a.display_name == b.display_name
+        && a.cfg_options == b.cfg_options
+        && a.potential_cfg_options == b.potential_cfg_options
+        && a.env == b.env
+        && a.package == b.package
+        && same_dependencies(&a.dependencies, &b.dependencies)
+        && same_proc_macros
+}
+
+/// Workspaces may add the same dependencies in a different order, which
+/// doesn't change name resolution as long as their names are unique.
+fn same_dependencies(a: &[Dependency], b: &[Dependency]) -> bool {
+    fn sorted(deps: &[Dependency]) -> Vec<(&str, CrateId)> {
+        let mut deps = deps.iter().map(|dep| (&*dep.name, dep.crate_id)).collect::<Vec<_>>();
+        deps.sort_unstable();
+        deps
+    }
+    a.len() == b.len() && sorted(a) == sorted(b)
+}
+
 impl CrateId {
     pub fn shift(self, amount: u32) -> CrateId {
         CrateId(self.0 + amount)
@@ -544,7 +758,10 @@ impl fmt::Display for CyclicDependenciesError {
 
 #[cfg(test)]
 mod tests {
-    use super::{CfgOptions, CrateGraph, CrateName, Dependency, Edition::Edition2018, Env, FileId};
+    use super::{
+        same_dependencies, CfgOptions, CrateGraph, CrateId, CrateName, Dependency,
+        Edition::Edition2018, Env, FileId,
+    };
 
     #[test]
     fn detect_cyclic_dependency_indirect() {
@@ -672,4 +889,113 @@ mod tests {
             }]
         );
     }
+
+    #[test]
+    fn extend_deduplicated_reuses_identical_crates() {
+        fn workspace(member: u32) -> CrateGraph {
+            let mut graph = CrateGraph::default();
+            let core = graph.add_crate_root(
+                FileId(0u32),
+                Edition2018,
+                None,
+                CfgOptions::default(),
+                CfgOptions::default(),
+                Env::default(),
+                Default::default(),
+            );
+            let member = graph.add_crate_root(
+                FileId(member),
+                Edition2018,
+                None,
+                CfgOptions::default(),
+                CfgOptions::default(),
+                Env::default(),
+                Default::default(),
+            );
+            graph.add_dep(member, CrateName::new("core").unwrap(), core).unwrap();
+            graph
+        }
+
+        let mut graph = workspace(1);
+        let mapping = graph.extend_deduplicated(workspace(2));
+        assert_eq!(graph.iter().count(), 3);
+        let core = graph.crate_id_for_crate_root(FileId(0u32)).unwrap();
+        let member = mapping[&workspace(2).crate_id_for_crate_root(FileId(2u32)).unwrap()];
+        assert_eq!(
+            graph[member].dependencies,
+            vec![Dependency { crate_id: core, name: CrateName::new("core").unwrap() }]
+        );
+
+        let mut with_cfg = workspace(3);
+        let core = with_cfg.crate_id_for_crate_root(FileId(0u32)).unwrap();
+        with_cfg.arena.get_mut(&core).unwrap().cfg_options.insert_atom("test".into());
+        graph.extend_deduplicated(with_cfg);
+        assert_eq!(graph.iter().count(), 5);
+    }
+
+    #[test]
+    fn extend_deduplicated_ignores_dependency_order() {
+        fn workspace(deps: &[u32]) -> CrateGraph {
+            let mut graph = CrateGraph::default();
+            let mut add = |file_id: u32| {
+                graph.add_crate_root(
+                    FileId(file_id),
+                    Edition2018,
+                    None,
+                    CfgOptions::default(),
+                    CfgOptions::default(),
+                    Env::default(),
+                    Default::default(),
+                )
+            };
+            let krate = add(0);
+            let deps = deps.iter().map(|&it| (it, add(it))).collect::<Vec<_>>();
+            for (file_id, dep) in deps {
+                let name = CrateName::new(&format!("dep{}", file_id)).unwrap();
+                graph.add_dep(krate, name, dep).unwrap();
+            }
+            graph
+        }
+
+        let mut graph = workspace(&[1, 2]);
+        graph.extend_deduplicated(workspace(&[2, 1]));
+        assert_eq!(graph.iter().count(), 3);
+    }
+
+    #[test]
+    fn same_dependencies_counts_duplicates() {
+        let dep = |name: &str, krate: u32| Dependency {
+            crate_id: CrateId(krate),
+            name: CrateName::new(name).unwrap(),
+        };
+        let (a, b) = (dep("a", 1), dep("b", 2));
+        assert!(same_dependencies(&[a.clone(), b.clone()], &[b.clone(), a.clone()]));
+        assert!(!same_dependencies(&[a.clone(), a.clone(), b.clone()], &[a.clone(), b.clone(), b]));
+        assert!(!same_dependencies(&[a.clone()], &[dep("a", 2)]));
+    }
+
+    #[test]
+    fn topological_layers() {
+        let mut graph = CrateGraph::default();
+        let mut add = |file_id: u32| {
+            graph.add_crate_root(
+                FileId(file_id),
+                Edition2018,
+                None,
+                CfgOptions::default(),
+                CfgOptions::default(),
+                Env::default(),
+                Default::default(),
+            )
+        };
+        let (core, alloc, log, app) = (add(1), add(2), add(3), add(4));
+        graph.add_dep(alloc, CrateName::new("core").unwrap(), core).unwrap();
+        graph.add_dep(log, CrateName::new("core").unwrap(), core).unwrap();
+        graph.add_dep(app, CrateName::new("alloc").unwrap(), alloc).unwrap();
+        graph.add_dep(app, CrateName::new("core").unwrap(), core).unwrap();
+
+        let mut layers = graph.crates_in_topological_layers();
+        layers.iter_mut().for_each(|layer| layer.sort());
+        assert_eq!(layers, vec![vec![core], vec![alloc, log], vec![app]]);
+    }
 }
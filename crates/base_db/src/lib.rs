@@ -1,18 +1,22 @@
 //! base_db defines basic database traits. The concrete DB is defined by ide.
 mod input;
-mod change;
+pub mod change;
+mod expansion_record;
 pub mod fixture;
+pub mod snapshot;
 
 use std::{panic, sync::Arc};
 
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use syntax::{ast, Parse, SourceFile, TextRange, TextSize};
 
 pub use crate::{
-    change::Change,
+    change::{Change, ChangeDeserError, LazyFiles},
+    expansion_record::ExpansionRecord,
     input::{
-        CrateData, CrateDisplayName, CrateGraph, CrateId, CrateName, Dependency, Edition, Env,
-        ProcMacro, ProcMacroExpander, ProcMacroId, ProcMacroKind, SourceRoot, SourceRootId,
+        CrateData, CrateDisplayName, CrateGraph, CrateId, CrateName, CyclicDependenciesError,
+        Dependency, Edition, Env, FileSource, LazyTextSource, PackageInfo, ProcMacro,
+        ProcMacroExpander, ProcMacroId, ProcMacroKind, SourceRoot, SourceRootId,
     },
 };
 pub use salsa::{self, Cancelled};
@@ -68,6 +72,11 @@ pub trait SourceDatabase: FileLoader + std::fmt::Debug {
     /// The crate graph.
     #[salsa::input]
     fn crate_graph(&self) -> Arc<CrateGraph>;
+
+    /// The target picked for the files of the crate graph compiled for
+    /// several targets. Files not in the map use the default target.
+    #[salsa::input]
+    fn active_targets(&self) -> Arc<FxHashMap<FileId, String>>;
 }
 
 fn parse_query(db: &dyn SourceDatabase, file_id: FileId) -> Parse<ast::SourceFile> {
@@ -80,7 +89,10 @@ fn parse_query(db: &dyn SourceDatabase, file_id: FileId) -> Parse<ast::SourceFil
 /// methods into a separate DB.
 #[salsa::query_group(SourceDatabaseExtStorage)]
 pub trait SourceDatabaseExt: SourceDatabase {
+    /// Where the text of the file comes from, see [`FileSource`].
     #[salsa::input]
+    fn file_source(&self, file_id: FileId) -> FileSource;
+    /// Text of the file, materialized from its [`FileSource`] on first access.
     fn file_text(&self, file_id: FileId) -> Arc<String>;
     /// Path to a file, relative to the root of its source root.
     /// Source root of the file.
@@ -93,6 +105,16 @@ pub trait SourceDatabaseExt: SourceDatabase {
     fn source_root_crates(&self, id: SourceRootId) -> Arc<FxHashSet<CrateId>>;
 }
 
+fn file_text(db: &dyn SourceDatabaseExt, file_id: FileId) -> Arc<String> {
+    match db.file_source(file_id) {
+        FileSource::Text(text) => text,
+        FileSource::Lazy(source) => {
+            let _p = profile::span("file_text::materialize");
+            Arc::new(source.text(file_id).unwrap_or_default())
+        }
+    }
+}
+
 fn source_root_crates(db: &dyn SourceDatabaseExt, id: SourceRootId) -> Arc<FxHashSet<CrateId>> {
     let graph = db.crate_graph();
     let res = graph
@@ -122,6 +144,78 @@ impl<T: SourceDatabaseExt> FileLoader for FileLoaderDelegate<&'_ T> {
     fn relevant_crates(&self, file_id: FileId) -> Arc<FxHashSet<CrateId>> {
         let _p = profile::span("relevant_crates");
         let source_root = self.0.file_source_root(file_id);
-        self.0.source_root_crates(source_root)
+        let crates = self.0.source_root_crates(source_root);
+        let graph = self.0.crate_graph();
+        if crates.iter().all(|&krate| graph[krate].target.is_none()) {
+            return crates;
+        }
+
+        let active_targets = self.0.active_targets();
+        let active = active_targets.get(&file_id);
+        let for_target = |target: Option<&String>| {
+            crates
+                .iter()
+                .copied()
+                .filter(|&krate| graph[krate].target.as_ref() == target)
+                .collect::<FxHashSet<_>>()
+        };
+        let res = for_target(active);
+        // The picked target might be gone after a reload.
+        let res = if res.is_empty() && active.is_some() { for_target(None) } else { res };
+        Arc::new(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cfg::CfgOptions;
+
+    use crate::change::RoundtripDb;
+
+    use super::*;
+
+    #[test]
+    fn relevant_crates_of_active_target() {
+        let file_id = FileId(0);
+        let mut file_set = FileSet::default();
+        file_set.insert(file_id, VfsPath::new_virtual_path("/lib.rs".to_string()));
+        let mut graph = CrateGraph::default();
+        let mut add = |target: Option<&str>| {
+            let krate = graph.add_crate_root(
+                file_id,
+                Edition::CURRENT,
+                None,
+                CfgOptions::default(),
+                CfgOptions::default(),
+                Env::default(),
+                Vec::new(),
+            );
+            graph.set_target(krate, target.map(ToOwned::to_owned));
+            krate
+        };
+        let (host, wasm) = (add(None), add(Some("wasm32-unknown-unknown")));
+
+        let mut db = RoundtripDb::default();
+        let mut change = Change::new();
+        change.set_roots(vec![SourceRoot::new_local(file_set)]);
+        change.change_file(file_id, Some(Arc::new(String::new())));
+        change.set_crate_graph(graph);
+        change.set_active_targets(FxHashMap::default());
+        change.apply(&mut db);
+
+        let mut relevant_crates = |target: Option<&str>| {
+            let mut active_targets = FxHashMap::default();
+            active_targets.extend(target.map(|it| (file_id, it.to_string())));
+            let mut change = Change::new();
+            change.set_active_targets(active_targets);
+            change.apply(&mut db);
+            let mut res = db.relevant_crates(file_id).iter().copied().collect::<Vec<_>>();
+            res.sort();
+            res
+        };
+        assert_eq!(relevant_crates(None), [host]);
+        assert_eq!(relevant_crates(Some("wasm32-unknown-unknown")), [wasm]);
+        // A target which is no longer in the graph falls back to the default one.
+        assert_eq!(relevant_crates(Some("riscv64gc-unknown-none-elf")), [host]);
     }
 }
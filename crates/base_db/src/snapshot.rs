@@ -0,0 +1,217 @@
+//! A compact on-disk representation of a [`Change`], used to start analysis
+//! without going through cargo.
+//!
+//! The layout is:
+//!
+//! ```text
+//! b"RASNAP01" | header length (u64, LE) | JSON header | file texts
+//! ```
+//!
+//! The header contains the source roots, the crate graph, the recorded proc
+//! macro expansions (see [`ExpansionRecord`]) and the byte range of every file
+//! inside the text blob. File texts are concatenated as is, so a
+//! reader which keeps the bytes around (for example, a memory map) can hand
+//! them out one by one, see [`decode`].
+
+use std::{convert::TryInto, fmt, panic::RefUnwindSafe, sync::Arc};
+
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use vfs::FileId;
+
+use crate::{Change, ChangeDeserError, CrateGraph, ExpansionRecord, LazyTextSource, SourceRoot};
+
+const MAGIC: &[u8; 8] = b"RASNAP01";
+const PREFIX_LEN: usize = MAGIC.len() + 8;
+
+#[derive(Deserialize)]
+struct Header {
+    roots: Option<Vec<SourceRoot>>,
+    crate_graph: Option<CrateGraph>,
+    /// `(file, offset, len)`, with the offset relative to the start of the blob.
+    files: Vec<(FileId, u64, u64)>,
+    #[serde(default)]
+    proc_macro_expansions: ExpansionRecord,
+}
+
+/// The borrowed counterpart of [`Header`], used for writing.
+#[derive(Serialize)]
+struct HeaderRef<'a> {
+    roots: &'a Option<Vec<SourceRoot>>,
+    crate_graph: &'a Option<CrateGraph>,
+    files: Vec<(FileId, u64, u64)>,
+    proc_macro_expansions: &'a ExpansionRecord,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The data doesn't start with the snapshot magic bytes.
+    NotASnapshot,
+    /// The data ends before the header or one of the file texts.
+    Truncated,
+    /// The header is not valid JSON or doesn't describe a `Change`.
+    Header(ChangeDeserError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::NotASnapshot => f.write_str("not a rust-analyzer snapshot"),
+            SnapshotError::Truncated => f.write_str("snapshot is truncated"),
+            SnapshotError::Header(err) => write!(f, "invalid snapshot header: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Serializes `change` into the snapshot format.
+///
+/// Deleted files (`None` texts) are skipped: a snapshot describes a state, not
+/// a diff.
+pub fn encode(change: &Change) -> Vec<u8> {
+    encode_with_expansions(change, &ExpansionRecord::default())
+}
+
+/// Like [`encode`], but also stores proc macro expansions, so that readers of
+/// the snapshot can expand attributes and derives without the proc macro
+/// server.
+pub fn encode_with_expansions(change: &Change, expansions: &ExpansionRecord) -> Vec<u8> {
+    let mut blob = Vec::new();
+    let mut files = Vec::with_capacity(change.files_changed.len());
+    for (file_id, text) in &change.files_changed {
+        if let Some(text) = text {
+            files.push((*file_id, blob.len() as u64, text.len() as u64));
+            blob.extend_from_slice(text.as_bytes());
+        }
+    }
+    let header = HeaderRef {
+        roots: &change.roots,
+        crate_graph: &change.crate_graph,
+        files,
+        proc_macro_expansions: expansions,
+    };
+    let header = serde_json::to_vec(&header).expect("serialization of snapshot header must work");
+
+    let mut res = Vec::with_capacity(PREFIX_LEN + header.len() + blob.len());
+    res.extend_from_slice(MAGIC);
+    res.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    res.extend_from_slice(&header);
+    res.extend_from_slice(&blob);
+    res
+}
+
+/// Reads a snapshot, keeping `bytes` alive for the lifetime of the resulting
+/// [`Change`].
+///
+/// Only the header is deserialized eagerly. File texts are registered as
+/// [`Change::lazy_files`] and are copied out of `bytes` the first time the
+/// database asks for them. Proc macros in the crate graph replay the recorded
+/// expansions.
+pub fn decode<B>(bytes: B) -> Result<Change, SnapshotError>
+where
+    B: AsRef<[u8]> + Send + Sync + RefUnwindSafe + 'static,
+{
+    let data = bytes.as_ref();
+    if data.len() < PREFIX_LEN || &data[..MAGIC.len()] != MAGIC {
+        return Err(SnapshotError::NotASnapshot);
+    }
+    let header_len = u64::from_le_bytes(data[MAGIC.len()..PREFIX_LEN].try_into().unwrap());
+    let blob_start = (header_len as usize)
+        .checked_add(PREFIX_LEN)
+        .filter(|&it| it <= data.len())
+        .ok_or(SnapshotError::Truncated)?;
+    let header: Header = {
+        let de = &mut serde_json::Deserializer::from_slice(&data[PREFIX_LEN..blob_start]);
+        serde_path_to_error::deserialize(de)
+            .map_err(|err| SnapshotError::Header(ChangeDeserError::from_path_error(err)))?
+    };
+
+    let blob_len = data.len() - blob_start;
+    let mut ranges = FxHashMap::default();
+    for (file_id, offset, len) in header.files {
+        let (offset, len) = (offset as usize, len as usize);
+        if offset.checked_add(len).map_or(true, |end| end > blob_len) {
+            return Err(SnapshotError::Truncated);
+        }
+        ranges.insert(file_id, (blob_start + offset, len));
+    }
+
+    let mut change = Change::new();
+    change.roots = header.roots;
+    change.crate_graph = header.crate_graph;
+    if let Some(crate_graph) = &mut change.crate_graph {
+        crate_graph.replay_proc_macro_expansions(&Arc::new(header.proc_macro_expansions));
+    }
+    let mut files = ranges.keys().copied().collect::<Vec<_>>();
+    files.sort();
+    change.set_lazy_files(Arc::new(SnapshotTexts { bytes, ranges }), files);
+    Ok(change)
+}
+
+struct SnapshotTexts<B> {
+    bytes: B,
+    /// Absolute byte ranges of file texts in `bytes`.
+    ranges: FxHashMap<FileId, (usize, usize)>,
+}
+
+impl<B> fmt::Debug for SnapshotTexts<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotTexts").field("n_files", &self.ranges.len()).finish()
+    }
+}
+
+impl<B> LazyTextSource for SnapshotTexts<B>
+where
+    B: AsRef<[u8]> + Send + Sync + RefUnwindSafe,
+{
+    fn text(&self, file_id: FileId) -> Option<String> {
+        let &(start, len) = self.ranges.get(&file_id)?;
+        let bytes = &self.bytes.as_ref()[start..start + len];
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use vfs::{file_set::FileSet, AbsPathBuf, VfsPath};
+
+    use super::*;
+
+    /// Snapshots are written from workspaces on disk, virtual paths don't
+    /// survive serialization.
+    fn path(name: &str) -> VfsPath {
+        let path = if cfg!(windows) { format!(r"C:\{}", name) } else { format!("/{}", name) };
+        VfsPath::from(AbsPathBuf::assert(PathBuf::from(path)))
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut file_set = FileSet::default();
+        file_set.insert(FileId(0), path("main.rs"));
+        file_set.insert(FileId(1), path("foo.rs"));
+
+        let mut change = Change::new();
+        change.set_roots(vec![SourceRoot::new_local(file_set)]);
+        change.change_file(FileId(0), Some(Arc::new("mod foo;".to_string())));
+        change.change_file(FileId(1), Some(Arc::new("fn föö() {}".to_string())));
+
+        let decoded = decode(encode(&change)).unwrap();
+        assert_eq!(decoded.roots, change.roots);
+        assert!(decoded.files_changed.is_empty());
+        let lazy = decoded.lazy_files.unwrap();
+        assert_eq!(lazy.files, vec![FileId(0), FileId(1)]);
+        assert_eq!(lazy.source.text(FileId(1)).as_deref(), Some("fn föö() {}"));
+        assert_eq!(lazy.source.text(FileId(2)), None);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(matches!(decode(b"not a snapshot".to_vec()), Err(SnapshotError::NotASnapshot)));
+        let mut bytes = encode(&Change::new());
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(decode(bytes), Err(SnapshotError::Truncated)));
+    }
+}
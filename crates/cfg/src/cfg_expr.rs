@@ -59,6 +59,26 @@ pub enum CfgExpr {
     Not(Box<CfgExpr>),
 }
 
+impl fmt::Display for CfgExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, exprs) = match self {
+            CfgExpr::Invalid => return write!(f, "<invalid>"),
+            CfgExpr::Atom(atom) => return atom.fmt(f),
+            CfgExpr::All(exprs) => ("all", exprs.as_slice()),
+            CfgExpr::Any(exprs) => ("any", exprs.as_slice()),
+            CfgExpr::Not(expr) => ("not", std::slice::from_ref(&**expr)),
+        };
+        write!(f, "{}(", name)?;
+        for (i, expr) in exprs.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            expr.fmt(f)?;
+        }
+        write!(f, ")")
+    }
+}
+
 impl From<CfgAtom> for CfgExpr {
     fn from(atom: CfgAtom) -> Self {
         CfgExpr::Atom(atom)
@@ -83,6 +103,18 @@ impl CfgExpr {
             CfgExpr::Not(pred) => pred.fold(query).map(|s| !s),
         }
     }
+
+    /// Calls `f` with every atom of the cfg, negated ones included.
+    pub fn walk_atoms(&self, f: &mut dyn FnMut(&CfgAtom)) {
+        match self {
+            CfgExpr::Invalid => (),
+            CfgExpr::Atom(atom) => f(atom),
+            CfgExpr::All(preds) | CfgExpr::Any(preds) => {
+                preds.iter().for_each(|pred| pred.walk_atoms(f))
+            }
+            CfgExpr::Not(pred) => pred.walk_atoms(f),
+        }
+    }
 }
 
 fn next_cfg_expr(it: &mut SliceIter<tt::TokenTree>) -> Option<CfgExpr> {
@@ -52,6 +52,15 @@ impl CfgOptions {
         }
     }
 
+    /// Returns the diff which turns `self` into `other`.
+    pub fn diff(&self, other: &CfgOptions) -> CfgDiff {
+        let mut enable = other.enabled.difference(&self.enabled).cloned().collect::<Vec<_>>();
+        let mut disable = self.enabled.difference(&other.enabled).cloned().collect::<Vec<_>>();
+        enable.sort();
+        disable.sort();
+        CfgDiff { enable, disable }
+    }
+
     pub fn get_cfg_keys(&self) -> Vec<&SmolStr> {
         self.enabled
             .iter()
@@ -99,6 +108,10 @@ impl CfgDiff {
     pub fn len(&self) -> usize {
         self.enable.len() + self.disable.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl fmt::Display for CfgDiff {
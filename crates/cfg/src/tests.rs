@@ -82,6 +82,22 @@ fn test_cfg_expr_parser() {
     );
 }
 
+#[test]
+fn test_walk_atoms() {
+    let (tt, _) = {
+        let source_file = ast::SourceFile::parse(
+            r#"#![cfg(any(unix, not(feature = "a"), all(x, feature = "b")))]"#,
+        )
+        .ok()
+        .unwrap();
+        let tt = source_file.syntax().descendants().find_map(ast::TokenTree::cast).unwrap();
+        ast_to_token_tree(&tt)
+    };
+    let mut atoms = Vec::new();
+    CfgExpr::parse(&tt).walk_atoms(&mut |atom| atoms.push(atom.to_string()));
+    assert_eq!(atoms, ["unix", r#"feature = "a""#, "x", r#"feature = "b""#]);
+}
+
 #[test]
 fn smoke() {
     check_dnf("#![cfg(test)]", expect![[r#"#![cfg(test)]"#]]);
@@ -191,3 +207,18 @@ fn why_inactive() {
         expect![["test and test2 are enabled and a is disabled"]],
     );
 }
+
+#[test]
+fn diff() {
+    let mut before = CfgOptions::default();
+    before.insert_atom("test".into());
+    before.insert_key_value("feature".into(), "std".into());
+    let mut after = CfgOptions::default();
+    after.insert_key_value("feature".into(), "std".into());
+    after.insert_key_value("feature".into(), "alloc".into());
+
+    let diff = before.diff(&after);
+    assert_eq!(diff.len(), 2);
+    assert_eq!(diff.to_string(), r#"enable feature = "alloc"; disable test"#);
+    assert!(after.diff(&after).is_empty());
+}
@@ -4,8 +4,8 @@
 
 use std::{
     fmt,
-    io::{self, BufRead, BufReader},
-    process::{self, Command, Stdio},
+    io::{self, BufRead, BufReader, Read},
+    process::{Command, Stdio},
     time::Duration,
 };
 
@@ -33,14 +33,29 @@ pub enum FlycheckConfig {
     CustomCommand {
         command: String,
         args: Vec<String>,
+        env: Vec<(String, String)>,
+        /// Defaults to the root of the workspace or package being checked.
+        cwd: Option<AbsPathBuf>,
+        parser: DiagnosticsParser,
     },
 }
 
+/// Where a check command writes its diagnostics to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticsParser {
+    /// JSON messages of cargo or rustc on stdout, like
+    /// `cargo check --message-format=json`.
+    Cargo,
+    /// JSON diagnostics on stderr, like `rustc --error-format=json` or build
+    /// systems forwarding the output of rustc.
+    Rustc,
+}
+
 impl fmt::Display for FlycheckConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             FlycheckConfig::CargoCommand { command, .. } => write!(f, "cargo {}", command),
-            FlycheckConfig::CustomCommand { command, args } => {
+            FlycheckConfig::CustomCommand { command, args, .. } => {
                 write!(f, "{} {}", command, args.join(" "))
             }
         }
@@ -164,9 +179,18 @@ impl FlycheckActor {
 
                     let mut command = self.check_command();
                     log::info!("restart flycheck {:?}", command);
-                    command.stdout(Stdio::piped()).stderr(Stdio::null()).stdin(Stdio::null());
+                    let parser = self.parser();
+                    match parser {
+                        DiagnosticsParser::Cargo => {
+                            command.stdout(Stdio::piped()).stderr(Stdio::null())
+                        }
+                        DiagnosticsParser::Rustc => {
+                            command.stdout(Stdio::null()).stderr(Stdio::piped())
+                        }
+                    };
+                    command.stdin(Stdio::null());
                     if let Ok(child) = command.spawn().map(JodChild) {
-                        self.cargo_handle = Some(CargoHandle::spawn(child));
+                        self.cargo_handle = Some(CargoHandle::spawn(child, parser));
                         self.progress(Progress::DidStart);
                     }
                 }
@@ -189,8 +213,9 @@ impl FlycheckActor {
                     }
 
                     CargoMessage::Diagnostic(msg) => {
+                        // Paths in the diagnostics are relative to where the command ran.
                         self.send(Message::AddDiagnostic {
-                            workspace_root: self.workspace_root.clone(),
+                            workspace_root: self.command_dir().clone(),
                             diagnostic: msg,
                         });
                     }
@@ -205,6 +230,18 @@ impl FlycheckActor {
             self.progress(Progress::DidCancel);
         }
     }
+    fn parser(&self) -> DiagnosticsParser {
+        match &self.config {
+            FlycheckConfig::CargoCommand { .. } => DiagnosticsParser::Cargo,
+            FlycheckConfig::CustomCommand { parser, .. } => *parser,
+        }
+    }
+    fn command_dir(&self) -> &AbsPathBuf {
+        match &self.config {
+            FlycheckConfig::CustomCommand { cwd: Some(cwd), .. } => cwd,
+            _ => &self.workspace_root,
+        }
+    }
     fn check_command(&self) -> Command {
         let mut cmd = match &self.config {
             FlycheckConfig::CargoCommand {
@@ -242,13 +279,16 @@ impl FlycheckActor {
                 cmd.args(extra_args);
                 cmd
             }
-            FlycheckConfig::CustomCommand { command, args } => {
+            FlycheckConfig::CustomCommand { command, args, env, .. } => {
                 let mut cmd = Command::new(command);
                 cmd.args(args);
+                for (key, value) in env {
+                    cmd.env(key, value);
+                }
                 cmd
             }
         };
-        cmd.current_dir(&self.workspace_root);
+        cmd.current_dir(self.command_dir());
         cmd
     }
 
@@ -265,10 +305,13 @@ struct CargoHandle {
 }
 
 impl CargoHandle {
-    fn spawn(mut child: JodChild) -> CargoHandle {
-        let child_stdout = child.stdout.take().unwrap();
+    fn spawn(mut child: JodChild, parser: DiagnosticsParser) -> CargoHandle {
+        let output: Box<dyn Read + Send> = match parser {
+            DiagnosticsParser::Cargo => Box::new(child.stdout.take().unwrap()),
+            DiagnosticsParser::Rustc => Box::new(child.stderr.take().unwrap()),
+        };
         let (sender, receiver) = unbounded();
-        let actor = CargoActor::new(child_stdout, sender);
+        let actor = CargoActor::new(output, sender);
         let thread = jod_thread::Builder::new()
             .name("CargoHandle".to_owned())
             .spawn(move || actor.run())
@@ -296,13 +339,14 @@ impl CargoHandle {
 }
 
 struct CargoActor {
-    child_stdout: process::ChildStdout,
+    /// The stdout or stderr of the command, depending on the parser.
+    output: Box<dyn Read + Send>,
     sender: Sender<CargoMessage>,
 }
 
 impl CargoActor {
-    fn new(child_stdout: process::ChildStdout, sender: Sender<CargoMessage>) -> CargoActor {
-        CargoActor { child_stdout, sender }
+    fn new(output: Box<dyn Read + Send>, sender: Sender<CargoMessage>) -> CargoActor {
+        CargoActor { output, sender }
     }
     fn run(self) -> io::Result<bool> {
         // We manually read a line at a time, instead of using serde's
@@ -313,7 +357,7 @@ impl CargoActor {
         // Because cargo only outputs one JSON object per line, we can
         // simply skip a line if it doesn't parse, which just ignores any
         // erroneus output.
-        let stdout = BufReader::new(self.child_stdout);
+        let stdout = BufReader::new(self.output);
         let mut read_at_least_one_message = false;
         for message in stdout.lines() {
             let message = match message {
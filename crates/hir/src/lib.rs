@@ -37,9 +37,9 @@ use arrayvec::ArrayVec;
 use base_db::{CrateDisplayName, CrateId, Edition, FileId};
 use either::Either;
 use hir_def::{
-    adt::{ReprKind, VariantData},
+    adt::{ReprData, VariantData},
     body::{BodyDiagnostic, SyntheticSyntax},
-    expr::{BindingAnnotation, LabelId, Pat, PatId},
+    expr::{BindingAnnotation, Expr, LabelId, Pat, PatId},
     item_tree::ItemTreeNode,
     lang_item::LangItemTarget,
     nameres,
@@ -118,7 +118,7 @@ pub use {
         name::{known, Name},
         ExpandResult, HirFileId, InFile, MacroFile, Origin,
     },
-    hir_ty::display::HirDisplay,
+    hir_ty::{consteval::ComputedExpr, display::HirDisplay, layout::Layout},
 };
 
 // These are negative re-exports: pub using these names is forbidden, they
@@ -681,6 +681,17 @@ impl Field {
     pub fn parent_def(&self, _db: &dyn HirDatabase) -> VariantDef {
         self.parent
     }
+
+    /// The offset of the field in its struct or union, unless that is generic.
+    pub fn offset(&self, db: &dyn HirDatabase) -> Option<u64> {
+        let adt = match self.parent {
+            VariantDef::Struct(it) => Adt::from(it),
+            VariantDef::Union(it) => Adt::from(it),
+            VariantDef::Variant(_) => return None,
+        };
+        let idx = self.parent.variant_data(db).fields().iter().position(|(id, _)| id == self.id)?;
+        adt.layout(db)?.field_offsets.get(idx).copied()
+    }
 }
 
 impl HasVisibility for Field {
@@ -719,7 +730,7 @@ impl Struct {
         Type::from_def(db, self.id.lookup(db.upcast()).container.krate(), self.id)
     }
 
-    pub fn repr(self, db: &dyn HirDatabase) -> Option<ReprKind> {
+    pub fn repr(self, db: &dyn HirDatabase) -> Option<ReprData> {
         db.struct_data(self.id).repr.clone()
     }
 
@@ -839,6 +850,12 @@ impl Variant {
     pub(crate) fn variant_data(self, db: &dyn HirDatabase) -> Arc<VariantData> {
         db.enum_data(self.parent.id).variants[self.id].variant_data.clone()
     }
+
+    /// The discriminant, if the explicit discriminants up to this variant are
+    /// integer literals.
+    pub fn discriminant(self, db: &dyn HirDatabase) -> Option<i128> {
+        hir_ty::consteval::eval_enum_discriminant(db, self.into())
+    }
 }
 
 /// A Data Type
@@ -864,6 +881,11 @@ impl Adt {
         Type::from_def(db, id.module(db.upcast()).krate(), id)
     }
 
+    /// The memory layout of the ADT, unless it is generic.
+    pub fn layout(self, db: &dyn HirDatabase) -> Option<Layout> {
+        self.ty(db).layout(db)
+    }
+
     pub fn module(self, db: &dyn HirDatabase) -> Module {
         match self {
             Adt::Struct(s) => s.module(db),
@@ -1024,6 +1046,29 @@ impl Function {
         db.function_data(self.id).is_async()
     }
 
+    /// Returns the functions called from this function's body, in the order
+    /// of their first call. Calls through function pointers and closures are
+    /// not included, trait method calls resolve to the trait's method.
+    pub fn callees(self, db: &dyn HirDatabase) -> Vec<Function> {
+        let body = db.body(self.id.into());
+        let infer = db.infer(self.id.into());
+        let mut res: Vec<Function> = Vec::new();
+        for (expr_id, expr) in body.exprs.iter() {
+            let callee = match expr {
+                Expr::Call { callee, .. } => infer[*callee].as_fn_def(db),
+                Expr::MethodCall { .. } => infer.method_resolution(expr_id).map(|(it, _)| it),
+                _ => None,
+            };
+            if let Some(callee) = callee {
+                let callee = Function::from(callee);
+                if !res.contains(&callee) {
+                    res.push(callee);
+                }
+            }
+        }
+        res
+    }
+
     pub fn diagnostics(self, db: &dyn HirDatabase, acc: &mut Vec<AnyDiagnostic>) {
         let krate = self.module(db).id.krate();
 
@@ -1346,6 +1391,20 @@ impl Const {
     pub fn type_ref(self, db: &dyn HirDatabase) -> TypeRef {
         db.const_data(self.id).type_ref.as_ref().clone()
     }
+
+    pub fn ty(self, db: &dyn HirDatabase) -> Type {
+        let resolver = self.id.resolver(db.upcast());
+        let krate = self.module(db).krate().id;
+        let ctx = hir_ty::TyLoweringContext::new(db, &resolver);
+        let ty = ctx.lower_ty(&db.const_data(self.id).type_ref);
+        Type::new_with_resolver_inner(db, krate, &resolver, ty)
+    }
+
+    /// The value of the constant, for the simple integer, boolean and
+    /// character expressions which can be evaluated.
+    pub fn eval(self, db: &dyn HirDatabase) -> Option<ComputedExpr> {
+        hir_ty::consteval::eval_const(db, self.id)
+    }
 }
 
 impl HasVisibility for Const {
@@ -1373,6 +1432,14 @@ impl Static {
     pub fn is_mut(self, db: &dyn HirDatabase) -> bool {
         db.static_data(self.id).mutable
     }
+
+    pub fn ty(self, db: &dyn HirDatabase) -> Type {
+        let resolver = self.id.resolver(db.upcast());
+        let krate = self.module(db).krate().id;
+        let ctx = hir_ty::TyLoweringContext::new(db, &resolver);
+        let ty = ctx.lower_ty(&db.static_data(self.id).type_ref);
+        Type::new_with_resolver_inner(db, krate, &resolver, ty)
+    }
 }
 
 impl HasVisibility for Static {
@@ -1406,6 +1473,14 @@ impl Trait {
     pub fn is_unsafe(&self, db: &dyn HirDatabase) -> bool {
         db.trait_data(self.id).is_unsafe
     }
+
+    /// The traits in the bounds on `Self`, without their own supertraits.
+    pub fn direct_supertraits(self, db: &dyn HirDatabase) -> Vec<Trait> {
+        hir_ty::direct_super_traits(db.upcast(), self.id)
+            .into_iter()
+            .map(|id| Trait { id })
+            .collect()
+    }
 }
 
 impl HasVisibility for Trait {
@@ -2253,11 +2328,16 @@ impl Type {
 
         let adt = adt_id.into();
         match adt {
-            Adt::Struct(s) => matches!(s.repr(db), Some(ReprKind::Packed)),
+            Adt::Struct(s) => s.repr(db).map_or(false, |repr| repr.packed.is_some()),
             _ => false,
         }
     }
 
+    /// The memory layout of the type for the target of its crate.
+    pub fn layout(&self, db: &dyn HirDatabase) -> Option<Layout> {
+        hir_ty::layout::layout_of(db, &self.ty, self.krate)
+    }
+
     pub fn is_raw_ptr(&self) -> bool {
         matches!(&self.ty.kind(&Interner), TyKind::Raw(..))
     }
@@ -6,6 +6,7 @@ use std::{cell::RefCell, fmt, iter::successors};
 
 use base_db::{FileId, FileRange};
 use hir_def::{
+    attr::Attrs,
     body,
     resolver::{self, HasResolver, Resolver, TypeNs},
     AsMacroCall, FunctionId, TraitId, VariantId,
@@ -24,9 +25,9 @@ use crate::{
     db::HirDatabase,
     semantics::source_to_def::{ChildContainer, SourceToDefCache, SourceToDefCtx},
     source_analyzer::{resolve_hir_path, SourceAnalyzer},
-    Access, AssocItem, Callable, ConstParam, Crate, Field, Function, HirFileId, Impl, InFile,
-    Label, LifetimeParam, Local, MacroDef, Module, ModuleDef, Name, Path, ScopeDef, Trait, Type,
-    TypeAlias, TypeParam, VariantDef,
+    Access, AssocItem, Callable, CfgExpr, ConstParam, Crate, Field, Function, HirFileId, Impl,
+    InFile, Label, LifetimeParam, Local, MacroDef, Module, ModuleDef, Name, Origin, Path, ScopeDef,
+    Trait, Type, TypeAlias, TypeParam, VariantDef,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -120,6 +121,11 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
         self.imp.expand(macro_call)
     }
 
+    /// The index of the `macro_rules!` arm `macro_call` expands with.
+    pub fn macro_call_rule(&self, macro_call: &ast::MacroCall) -> Option<usize> {
+        self.imp.macro_call_rule(macro_call)
+    }
+
     /// If `item` has an attribute macro attached to it, expands it.
     pub fn expand_attr_macro(&self, item: &ast::Item) -> Option<SyntaxNode> {
         self.imp.expand_attr_macro(item)
@@ -154,6 +160,19 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
         self.imp.original_range(node)
     }
 
+    /// The range of `node` in the source, if it was written there rather
+    /// than produced by a macro.
+    pub fn original_range_opt(&self, node: &SyntaxNode) -> Option<FileRange> {
+        self.imp.original_range_opt(node)
+    }
+
+    /// Whether a token of a macro expansion was passed in by the macro call or
+    /// written in the macro definition. `None` for tokens outside of macro
+    /// expansions and those the macro made up.
+    pub fn token_origin(&self, token: &SyntaxToken) -> Option<Origin> {
+        self.imp.token_origin(token)
+    }
+
     pub fn diagnostics_display_range(&self, diagnostics: InFile<SyntaxNodePtr>) -> FileRange {
         self.imp.diagnostics_display_range(diagnostics)
     }
@@ -327,6 +346,17 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
     pub fn is_unsafe_ident_pat(&self, ident_pat: &ast::IdentPat) -> bool {
         self.imp.is_unsafe_ident_pat(ident_pat)
     }
+
+    /// Whether `node` is in code removed by a `#[cfg]`, on itself or one of
+    /// its ancestors, which is disabled for the crate of its file.
+    pub fn is_inactive_code(&self, node: &SyntaxNode) -> bool {
+        self.imp.inactive_cfg(node).is_some()
+    }
+
+    /// The `#[cfg]` disabling `node`, if it is in inactive code.
+    pub fn inactive_cfg(&self, node: &SyntaxNode) -> Option<CfgExpr> {
+        self.imp.inactive_cfg(node)
+    }
 }
 
 impl<'db> SemanticsImpl<'db> {
@@ -353,6 +383,12 @@ impl<'db> SemanticsImpl<'db> {
         Some(node)
     }
 
+    fn macro_call_rule(&self, macro_call: &ast::MacroCall) -> Option<usize> {
+        let sa = self.analyze(macro_call.syntax());
+        let file_id = sa.expand(self.db, InFile::new(sa.file_id, macro_call))?;
+        file_id.expansion_info(self.db.upcast())?.matched_rule()
+    }
+
     fn expand_attr_macro(&self, item: &ast::Item) -> Option<SyntaxNode> {
         let sa = self.analyze(item.syntax());
         let src = InFile::new(sa.file_id, item.clone());
@@ -482,6 +518,22 @@ impl<'db> SemanticsImpl<'db> {
         node.as_ref().original_file_range(self.db.upcast())
     }
 
+    fn original_range_opt(&self, node: &SyntaxNode) -> Option<FileRange> {
+        let node = self.find_file(node.clone());
+        node.as_ref().original_file_range_opt(self.db.upcast())
+    }
+
+    fn token_origin(&self, token: &SyntaxToken) -> Option<Origin> {
+        let file_id = self.find_file(token.parent()?).file_id;
+        let mut cache = self.expansion_info_cache.borrow_mut();
+        let info = cache
+            .entry(file_id)
+            .or_insert_with(|| file_id.expansion_info(self.db.upcast()))
+            .as_ref()?;
+        let (_, origin) = info.map_token_up(InFile::new(file_id, token))?;
+        Some(origin)
+    }
+
     fn diagnostics_display_range(&self, src: InFile<SyntaxNodePtr>) -> FileRange {
         let root = self.db.parse_or_expand(src.file_id).unwrap();
         let node = src.value.to_node(&root);
@@ -833,6 +885,32 @@ impl<'db> SemanticsImpl<'db> {
             .map(|ty| ty.is_packed(self.db))
             .unwrap_or(false)
     }
+
+    fn inactive_cfg(&self, node: &SyntaxNode) -> Option<CfgExpr> {
+        let file_id = self.find_file(node.clone()).file_id;
+        let krate = self.to_module_def(file_id.original_file(self.db.upcast())).next()?.krate().id;
+        let cfg_options = &self.db.crate_graph()[krate].cfg_options;
+        node.ancestors().find_map(|node| {
+            let owner = match_ast! {
+                match node {
+                    ast::Item(it) => ast::AttrsOwnerNode::new(it),
+                    ast::Variant(it) => ast::AttrsOwnerNode::new(it),
+                    ast::RecordField(it) => ast::AttrsOwnerNode::new(it),
+                    ast::TupleField(it) => ast::AttrsOwnerNode::new(it),
+                    ast::Param(it) => ast::AttrsOwnerNode::new(it),
+                    ast::LetStmt(it) => ast::AttrsOwnerNode::new(it),
+                    ast::ExprStmt(it) => ast::AttrsOwnerNode::new(it),
+                    ast::Expr(it) => ast::AttrsOwnerNode::new(it),
+                    ast::MatchArm(it) => ast::AttrsOwnerNode::new(it),
+                    ast::RecordExprField(it) => ast::AttrsOwnerNode::new(it),
+                    _ => return None,
+                }
+            };
+            let owner = InFile::new(file_id, &owner as &dyn ast::AttrsOwner);
+            let attrs = Attrs::from_syntax(self.db.upcast(), owner, krate);
+            attrs.cfg().filter(|cfg| cfg_options.check(cfg) == Some(false))
+        })
+    }
 }
 
 pub trait ToDef: AstNode + Clone {
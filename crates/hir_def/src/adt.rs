@@ -14,6 +14,7 @@ use tt::{Delimiter, DelimiterKind, Leaf, Subtree, TokenTree};
 
 use crate::{
     body::{CfgExpander, LowerCtx},
+    builtin_type::{BuiltinInt, BuiltinUint},
     db::DefDatabase,
     intern::Interned,
     item_tree::{AttrOwner, Field, Fields, ItemTree, ModItem, RawVisibilityId},
@@ -31,7 +32,7 @@ use cfg::CfgOptions;
 pub struct StructData {
     pub name: Name,
     pub variant_data: Arc<VariantData>,
-    pub repr: Option<ReprKind>,
+    pub repr: Option<ReprData>,
     pub visibility: RawVisibility,
 }
 
@@ -39,6 +40,7 @@ pub struct StructData {
 pub struct EnumData {
     pub name: Name,
     pub variants: Arena<EnumVariantData>,
+    pub repr: Option<ReprData>,
     pub visibility: RawVisibility,
 }
 
@@ -63,10 +65,16 @@ pub struct FieldData {
     pub visibility: RawVisibility,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ReprKind {
-    Packed,
-    Other,
+/// The options of the `#[repr]` attributes of an item.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReprData {
+    pub c: bool,
+    pub transparent: bool,
+    /// The maximal alignment of the fields, `1` for a plain `packed`.
+    pub packed: Option<u64>,
+    pub align: Option<u64>,
+    /// The type of the discriminant of an enum.
+    pub int: Option<Either<BuiltinInt, BuiltinUint>>,
 }
 
 fn repr_from_value(
@@ -74,20 +82,57 @@ fn repr_from_value(
     krate: CrateId,
     item_tree: &ItemTree,
     of: AttrOwner,
-) -> Option<ReprKind> {
-    item_tree.attrs(db, krate, of).by_key("repr").tt_values().find_map(parse_repr_tt)
+) -> Option<ReprData> {
+    let mut res = None;
+    for tt in item_tree.attrs(db, krate, of).by_key("repr").tt_values() {
+        parse_repr_tt(tt, res.get_or_insert_with(ReprData::default));
+    }
+    res
 }
 
-fn parse_repr_tt(tt: &Subtree) -> Option<ReprKind> {
+/// Parses `(C, packed(2))`, where the options are separated by commas and may
+/// have a parenthesized argument.
+fn parse_repr_tt(tt: &Subtree, res: &mut ReprData) {
     match tt.delimiter {
         Some(Delimiter { kind: DelimiterKind::Parenthesis, .. }) => {}
-        _ => return None,
+        _ => return,
     }
 
-    let mut it = tt.token_trees.iter();
-    match it.next()? {
-        TokenTree::Leaf(Leaf::Ident(ident)) if ident.text == "packed" => Some(ReprKind::Packed),
-        _ => Some(ReprKind::Other),
+    let mut it = tt.token_trees.iter().peekable();
+    while let Some(tt) = it.next() {
+        let ident = match tt {
+            TokenTree::Leaf(Leaf::Ident(ident)) => ident,
+            _ => continue,
+        };
+        let arg = match it.peek() {
+            Some(TokenTree::Subtree(arg)) => {
+                it.next();
+                match arg.token_trees.first() {
+                    Some(TokenTree::Leaf(Leaf::Literal(lit))) => lit.text.parse::<u64>().ok(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match &*ident.text {
+            "C" => res.c = true,
+            "transparent" => res.transparent = true,
+            // Packing and alignment are powers of two, rustc rejects anything
+            // else.
+            "packed" => match arg {
+                None => res.packed = Some(1),
+                Some(pack) if pack.is_power_of_two() => res.packed = Some(pack),
+                Some(_) => {}
+            },
+            "align" => res.align = arg.filter(|it| it.is_power_of_two()),
+            int => {
+                if let Some(int) = BuiltinInt::from_suffix(int) {
+                    res.int = Some(Either::Left(int));
+                } else if let Some(uint) = BuiltinUint::from_suffix(int) {
+                    res.int = Some(Either::Right(uint));
+                }
+            }
+        }
     }
 }
 
@@ -132,6 +177,7 @@ impl EnumData {
         let loc = e.lookup(db);
         let krate = loc.container.krate;
         let item_tree = loc.id.item_tree(db);
+        let repr = repr_from_value(db, krate, &item_tree, ModItem::from(loc.id.value).into());
         let cfg_options = db.crate_graph()[krate].cfg_options.clone();
 
         let enum_ = &item_tree[loc.id.value];
@@ -158,6 +204,7 @@ impl EnumData {
         Arc::new(EnumData {
             name: enum_.name.clone(),
             variants,
+            repr,
             visibility: item_tree[enum_.visibility].clone(),
         })
     }
@@ -197,6 +197,16 @@ impl RawAttrs {
 impl Attrs {
     pub const EMPTY: Self = Self(RawAttrs::EMPTY);
 
+    /// The attributes of a node which needn't have been lowered, like one in
+    /// code removed by a `#[cfg]`.
+    pub fn from_syntax(
+        db: &dyn DefDatabase,
+        owner: InFile<&dyn ast::AttrsOwner>,
+        krate: CrateId,
+    ) -> Attrs {
+        RawAttrs::from_attrs_owner(db, owner).filter(db, krate)
+    }
+
     pub(crate) fn variants_attrs_query(
         db: &dyn DefDatabase,
         e: EnumId,
@@ -761,6 +771,14 @@ impl<'a> AttrQuery<'a> {
         })
     }
 
+    /// The argument of an attribute like `#[attr(42)]`.
+    pub fn int_value(self) -> Option<u128> {
+        self.tt_values().find_map(|tt| match &*tt.token_trees {
+            [tt::TokenTree::Leaf(tt::Leaf::Literal(lit))] => lit.text.parse().ok(),
+            _ => None,
+        })
+    }
+
     pub fn exists(self) -> bool {
         self.attrs().next().is_some()
     }
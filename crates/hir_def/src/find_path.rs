@@ -584,6 +584,24 @@ pub struct S;
         );
     }
 
+    #[test]
+    fn different_crate_renamed_dependency() {
+        check_found_path(
+            r#"
+//- /main.rs crate:main deps:serde_renamed=serde
+$0
+//- /serde.rs crate:serde
+pub mod ser {
+    pub trait Serialize {}
+}
+        "#,
+            "serde_renamed::ser::Serialize",
+            "serde_renamed::ser::Serialize",
+            "serde_renamed::ser::Serialize",
+            "serde_renamed::ser::Serialize",
+        );
+    }
+
     #[test]
     fn partially_imported() {
         cov_mark::check!(partially_imported);
@@ -384,6 +384,26 @@ struct Arc;
     );
 }
 
+#[test]
+fn renamed_dependency() {
+    check(
+        r#"
+//- /main.rs crate:main deps:serde_renamed=serde
+use serde_renamed::Serialize;
+use serde::Deserialize;
+
+//- /lib.rs crate:serde
+pub trait Serialize {}
+pub trait Deserialize {}
+"#,
+        expect![[r#"
+            crate
+            Deserialize: _
+            Serialize: t
+        "#]],
+    );
+}
+
 #[test]
 fn extern_crate_rename_2015_edition() {
     check(
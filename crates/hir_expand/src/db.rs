@@ -62,6 +62,18 @@ impl TokenExpander {
         }
     }
 
+    /// The rule of a declarative macro the argument `tt` expands with.
+    pub(crate) fn matched_rule(&self, tt: &tt::Subtree) -> Option<usize> {
+        match self {
+            TokenExpander::MacroRules { mac, .. } => mac.matched_rule(tt),
+            TokenExpander::MacroDef { mac, .. } => mac.matched_rule(tt),
+            TokenExpander::Builtin(..)
+            | TokenExpander::BuiltinAttr(..)
+            | TokenExpander::BuiltinDerive(..)
+            | TokenExpander::ProcMacro(..) => None,
+        }
+    }
+
     pub(crate) fn map_id_down(&self, id: tt::TokenId) -> tt::TokenId {
         match self {
             TokenExpander::MacroRules { mac, .. } => mac.map_id_down(id),
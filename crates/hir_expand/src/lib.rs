@@ -163,6 +163,10 @@ impl HirFileId {
         }
     }
 
+    pub fn is_macro(self) -> bool {
+        matches!(self.0, HirFileIdRepr::MacroFile(_))
+    }
+
     /// Return whether this file is an include macro
     pub fn is_include_macro(&self, db: &dyn db::AstDatabase) -> bool {
         match self.0 {
@@ -345,6 +349,12 @@ impl ExpansionInfo {
         Some(self.arg.with_value(self.arg.value.parent()?))
     }
 
+    /// The index of the rule of the declarative macro this expansion comes
+    /// from.
+    pub fn matched_rule(&self) -> Option<usize> {
+        self.macro_def.matched_rule(&self.macro_arg.0)
+    }
+
     pub fn map_token_down(&self, token: InFile<&SyntaxToken>) -> Option<InFile<SyntaxToken>> {
         assert_eq!(token.file_id, self.arg.file_id);
         let range = token.value.text_range().checked_sub(self.arg.value.text_range().start())?;
@@ -481,6 +491,20 @@ impl<'a> InFile<&'a SyntaxNode> {
         assert_eq!(node.file_id, orig_file.into());
         FileRange { file_id: orig_file, range: node.value.text_range() }
     }
+
+    /// Like [`InFile::original_file_range`], but without the fall back to the
+    /// whole macro call: nodes of macro expansions only have a range if all
+    /// their tokens were passed in by the calls.
+    pub fn original_file_range_opt(self, db: &dyn db::AstDatabase) -> Option<FileRange> {
+        if let HirFileIdRepr::FileId(file_id) = self.file_id.0 {
+            return Some(FileRange { file_id, range: self.value.text_range() });
+        }
+        let range = original_range_opt(db, self)?;
+        match range.file_id.0 {
+            HirFileIdRepr::FileId(file_id) => Some(FileRange { file_id, range: range.value }),
+            HirFileIdRepr::MacroFile(_) => None,
+        }
+    }
 }
 
 fn original_range_opt(
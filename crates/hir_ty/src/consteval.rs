@@ -1,14 +1,32 @@
 //! Constant evaluation details
 
-use std::convert::TryInto;
+use std::{
+    cmp,
+    convert::{TryFrom, TryInto},
+    fmt,
+    sync::Arc,
+};
 
 use hir_def::{
+    body::Body,
     builtin_type::BuiltinUint,
-    expr::{Expr, Literal},
+    expr::{
+        ArithOp, BinaryOp, CmpOp, Expr, ExprId, Literal, LogicOp, Ordering, Pat, PatId, Statement,
+        UnaryOp,
+    },
+    resolver::{resolver_for_expr, ValueNs},
+    src::HasSource,
     type_ref::ConstScalar,
+    ConstId, DefWithBodyId, EnumVariantId, HasModule, Lookup,
 };
+use hir_expand::name::AsName;
+use rustc_hash::FxHashMap;
+use syntax::ast::{self, NameOwner};
 
-use crate::{Const, ConstData, ConstValue, Interner, TyKind};
+use crate::{
+    db::HirDatabase, layout::layout_of, Const, ConstData, ConstValue, InferenceResult, Interner,
+    Scalar, Ty, TyKind,
+};
 
 /// Extension trait for [`Const`]
 pub trait ConstExt {
@@ -53,3 +71,340 @@ pub fn usize_const(value: Option<u64>) -> Const {
     }
     .intern(&Interner)
 }
+
+/// The value of a constant, as far as it can be computed without a full
+/// interpreter: integers, booleans and characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComputedExpr {
+    Bool(bool),
+    Char(char),
+    /// `u128` values above `i128::MAX` are not supported.
+    Int(i128),
+}
+
+impl fmt::Display for ComputedExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComputedExpr::Bool(it) => write!(f, "{}", it),
+            ComputedExpr::Char(it) => write!(f, "{:?}", it),
+            ComputedExpr::Int(it) => write!(f, "{}", it),
+        }
+    }
+}
+
+/// Calls and constants nested deeper than that are assumed to be infinitely
+/// recursive.
+const DEPTH_LIMIT: usize = 32;
+/// The number of expressions evaluated before giving up, `const fn`s can be
+/// arbitrarily expensive.
+const STEP_LIMIT: usize = 10_000;
+
+/// Evaluates the initializer of `konst`: literals, arithmetic, comparisons,
+/// other constants, enum discriminants, `if`s, `let`s and calls of `const fn`s
+/// over those.
+pub fn eval_const(db: &dyn HirDatabase, konst: ConstId) -> Option<ComputedExpr> {
+    let _p = profile::span("eval_const");
+    let mut steps = 0;
+    ConstEvalCtx::new(db, konst.into(), 0, &mut steps).eval_body()
+}
+
+/// The discriminant of an enum variant, when it and the explicit
+/// discriminants before it are integer literals.
+pub fn eval_enum_discriminant(db: &dyn HirDatabase, variant: EnumVariantId) -> Option<i128> {
+    let enum_data = db.enum_data(variant.parent);
+    let name = &enum_data.variants[variant.local_id].name;
+    let source = variant.parent.lookup(db.upcast()).source(db.upcast());
+    let mut discriminant: Option<i128> = None;
+    for it in source.value.variant_list()?.variants() {
+        let it_name = it.name()?.as_name();
+        // Variants disabled by a `#[cfg]` are not in `enum_data` and don't get
+        // a discriminant.
+        if enum_data.variant(&it_name).is_none() {
+            continue;
+        }
+        let value = match it.expr() {
+            Some(expr) => int_literal(&expr)?,
+            None => discriminant.map_or(Some(0), |it| it.checked_add(1))?,
+        };
+        if &it_name == name {
+            return Some(value);
+        }
+        discriminant = Some(value);
+    }
+    None
+}
+
+/// The value of `1`, `-1` or `0x1`.
+fn int_literal(expr: &ast::Expr) -> Option<i128> {
+    match expr {
+        ast::Expr::Literal(lit) => match lit.kind() {
+            ast::LiteralKind::IntNumber(it) => it.value()?.try_into().ok(),
+            _ => None,
+        },
+        ast::Expr::PrefixExpr(prefix) if prefix.op_kind() == Some(ast::PrefixOp::Neg) => {
+            int_literal(&prefix.expr()?)?.checked_neg()
+        }
+        ast::Expr::ParenExpr(it) => int_literal(&it.expr()?),
+        _ => None,
+    }
+}
+
+fn literal_int(lit: &Literal) -> Option<i128> {
+    match lit {
+        Literal::Int(it, _) => Some(*it),
+        Literal::Uint(it, _) => i128::try_from(*it).ok(),
+        _ => None,
+    }
+}
+
+struct ConstEvalCtx<'a> {
+    db: &'a dyn HirDatabase,
+    owner: DefWithBodyId,
+    body: Arc<Body>,
+    infer: Arc<InferenceResult>,
+    locals: FxHashMap<PatId, ComputedExpr>,
+    depth: usize,
+    steps: &'a mut usize,
+}
+
+impl<'a> ConstEvalCtx<'a> {
+    fn new(
+        db: &'a dyn HirDatabase,
+        owner: DefWithBodyId,
+        depth: usize,
+        steps: &'a mut usize,
+    ) -> ConstEvalCtx<'a> {
+        ConstEvalCtx {
+            db,
+            owner,
+            body: db.body(owner),
+            infer: db.infer(owner),
+            locals: FxHashMap::default(),
+            depth,
+            steps,
+        }
+    }
+
+    fn eval_body(&mut self) -> Option<ComputedExpr> {
+        if self.depth > DEPTH_LIMIT {
+            return None;
+        }
+        self.eval(self.body.body_expr)
+    }
+
+    fn eval(&mut self, expr: ExprId) -> Option<ComputedExpr> {
+        *self.steps += 1;
+        if *self.steps > STEP_LIMIT {
+            return None;
+        }
+        let body = self.body.clone();
+        match &body[expr] {
+            Expr::Literal(Literal::Bool(it)) => Some(ComputedExpr::Bool(*it)),
+            Expr::Literal(Literal::Char(it)) => Some(ComputedExpr::Char(*it)),
+            Expr::Literal(lit) => self.int(expr, literal_int(lit)?),
+            Expr::UnaryOp { expr: operand, op: UnaryOp::Neg } => match &body[*operand] {
+                // `-128i8` is fine even though `128i8` overflows.
+                Expr::Literal(lit) => self.int(expr, literal_int(lit)?.checked_neg()?),
+                _ => match self.eval(*operand)? {
+                    ComputedExpr::Int(it) => self.int(expr, it.checked_neg()?),
+                    _ => None,
+                },
+            },
+            Expr::UnaryOp { expr: operand, op } => match (op, self.eval(*operand)?) {
+                (UnaryOp::Not, ComputedExpr::Int(it)) => self.wrapping_int(expr, !it),
+                (UnaryOp::Not, ComputedExpr::Bool(it)) => Some(ComputedExpr::Bool(!it)),
+                _ => None,
+            },
+            Expr::BinaryOp { lhs, rhs, op: Some(op) } => self.eval_binary_op(expr, *lhs, *rhs, *op),
+            Expr::Cast { expr: operand, .. } => {
+                let value = match self.eval_path(*operand) {
+                    Some(ValueNs::EnumVariantId(variant)) => {
+                        eval_enum_discriminant(self.db, variant)?
+                    }
+                    _ => match self.eval(*operand)? {
+                        ComputedExpr::Int(it) => it,
+                        ComputedExpr::Bool(it) => it as i128,
+                        ComputedExpr::Char(it) => it as i128,
+                    },
+                };
+                self.wrapping_int(expr, value)
+            }
+            Expr::Path(_) => match self.eval_path(expr)? {
+                ValueNs::LocalBinding(pat) => self.locals.get(&pat).copied(),
+                ValueNs::ConstId(konst) => {
+                    ConstEvalCtx::new(self.db, konst.into(), self.depth + 1, self.steps).eval_body()
+                }
+                _ => None,
+            },
+            Expr::If { condition, then_branch, else_branch } => match self.eval(*condition)? {
+                ComputedExpr::Bool(true) => self.eval(*then_branch),
+                ComputedExpr::Bool(false) => self.eval((*else_branch)?),
+                _ => None,
+            },
+            Expr::Block { statements, tail, .. } => {
+                for statement in statements {
+                    match statement {
+                        Statement::Let { pat, initializer: Some(initializer), .. } => {
+                            let value = self.eval(*initializer)?;
+                            self.bind(*pat, value)?;
+                        }
+                        _ => return None,
+                    }
+                }
+                self.eval((*tail)?)
+            }
+            Expr::Unsafe { body } | Expr::Const { body } => self.eval(*body),
+            Expr::Call { callee, args } => {
+                let func = match self.eval_path(*callee)? {
+                    ValueNs::FunctionId(it) if self.db.function_data(it).is_const() => it,
+                    _ => return None,
+                };
+                let args = args.iter().map(|&arg| self.eval(arg)).collect::<Option<Vec<_>>>()?;
+                let mut ctx = ConstEvalCtx::new(self.db, func.into(), self.depth + 1, self.steps);
+                if ctx.body.params.len() != args.len() {
+                    return None;
+                }
+                for (param, arg) in ctx.body.params.clone().into_iter().zip(args) {
+                    ctx.bind(param, arg)?;
+                }
+                ctx.eval_body()
+            }
+            _ => None,
+        }
+    }
+
+    fn eval_binary_op(
+        &mut self,
+        expr: ExprId,
+        lhs: ExprId,
+        rhs: ExprId,
+        op: BinaryOp,
+    ) -> Option<ComputedExpr> {
+        if let BinaryOp::LogicOp(op) = op {
+            let lhs = match self.eval(lhs)? {
+                ComputedExpr::Bool(it) => it,
+                _ => return None,
+            };
+            return match (op, lhs) {
+                (LogicOp::And, false) => Some(ComputedExpr::Bool(false)),
+                (LogicOp::Or, true) => Some(ComputedExpr::Bool(true)),
+                _ => self.eval(rhs),
+            };
+        }
+
+        let (lhs, rhs) = (self.eval(lhs)?, self.eval(rhs)?);
+        match op {
+            BinaryOp::CmpOp(CmpOp::Eq { negated }) => {
+                Some(ComputedExpr::Bool((lhs == rhs) != negated))
+            }
+            BinaryOp::CmpOp(CmpOp::Ord { ordering, strict }) => {
+                let ord = match (lhs, rhs) {
+                    (ComputedExpr::Int(lhs), ComputedExpr::Int(rhs)) => lhs.cmp(&rhs),
+                    (ComputedExpr::Char(lhs), ComputedExpr::Char(rhs)) => lhs.cmp(&rhs),
+                    (ComputedExpr::Bool(lhs), ComputedExpr::Bool(rhs)) => lhs.cmp(&rhs),
+                    _ => return None,
+                };
+                let res = match ordering {
+                    Ordering::Less => ord == cmp::Ordering::Less,
+                    Ordering::Greater => ord == cmp::Ordering::Greater,
+                } || (!strict && ord == cmp::Ordering::Equal);
+                Some(ComputedExpr::Bool(res))
+            }
+            BinaryOp::ArithOp(op) => match (lhs, rhs) {
+                (ComputedExpr::Int(lhs), ComputedExpr::Int(rhs)) => {
+                    self.eval_arith_op(expr, lhs, rhs, op)
+                }
+                (ComputedExpr::Bool(lhs), ComputedExpr::Bool(rhs)) => match op {
+                    ArithOp::BitAnd => Some(ComputedExpr::Bool(lhs & rhs)),
+                    ArithOp::BitOr => Some(ComputedExpr::Bool(lhs | rhs)),
+                    ArithOp::BitXor => Some(ComputedExpr::Bool(lhs ^ rhs)),
+                    _ => None,
+                },
+                _ => None,
+            },
+            BinaryOp::LogicOp(_) | BinaryOp::Assignment { .. } => None,
+        }
+    }
+
+    fn eval_arith_op(
+        &mut self,
+        expr: ExprId,
+        lhs: i128,
+        rhs: i128,
+        op: ArithOp,
+    ) -> Option<ComputedExpr> {
+        match op {
+            ArithOp::Add => self.int(expr, lhs.checked_add(rhs)?),
+            ArithOp::Sub => self.int(expr, lhs.checked_sub(rhs)?),
+            ArithOp::Mul => self.int(expr, lhs.checked_mul(rhs)?),
+            ArithOp::Div => self.int(expr, lhs.checked_div(rhs)?),
+            ArithOp::Rem => self.int(expr, lhs.checked_rem(rhs)?),
+            ArithOp::BitAnd => self.int(expr, lhs & rhs),
+            ArithOp::BitOr => self.int(expr, lhs | rhs),
+            ArithOp::BitXor => self.int(expr, lhs ^ rhs),
+            // Shifting out bits is fine, shifting by the width or more is not.
+            ArithOp::Shl | ArithOp::Shr => {
+                let (_, _, bits) = self.int_bounds(&self.infer[expr])?;
+                let rhs = u32::try_from(rhs).ok().filter(|&it| it < bits)?;
+                let value = if op == ArithOp::Shl { lhs << rhs } else { lhs >> rhs };
+                self.wrapping_int(expr, value)
+            }
+        }
+    }
+
+    fn eval_path(&self, expr: ExprId) -> Option<ValueNs> {
+        let path = match &self.body[expr] {
+            Expr::Path(it) => it,
+            _ => return None,
+        };
+        let resolver = resolver_for_expr(self.db.upcast(), self.owner, expr);
+        resolver.resolve_path_in_value_ns_fully(self.db.upcast(), path.mod_path())
+    }
+
+    fn bind(&mut self, pat: PatId, value: ComputedExpr) -> Option<()> {
+        match &self.body[pat] {
+            Pat::Bind { subpat: None, .. } => {
+                self.locals.insert(pat, value);
+                Some(())
+            }
+            Pat::Wild => Some(()),
+            _ => None,
+        }
+    }
+
+    /// `value` as the type of `expr`, `None` if it overflows.
+    fn int(&self, expr: ExprId, value: i128) -> Option<ComputedExpr> {
+        let (min, max, _) = self.int_bounds(&self.infer[expr])?;
+        (min..=max).contains(&value).then(|| ComputedExpr::Int(value))
+    }
+
+    /// `value` truncated to the type of `expr`, like an `as` cast.
+    fn wrapping_int(&self, expr: ExprId, value: i128) -> Option<ComputedExpr> {
+        let (min, _, bits) = self.int_bounds(&self.infer[expr])?;
+        if bits >= 128 {
+            return Some(ComputedExpr::Int(value));
+        }
+        let unsigned = value & ((1 << bits) - 1);
+        let value =
+            if min < 0 && unsigned >= 1 << (bits - 1) { unsigned - (1 << bits) } else { unsigned };
+        Some(ComputedExpr::Int(value))
+    }
+
+    /// The minimum, maximum and width of an integer type.
+    fn int_bounds(&self, ty: &Ty) -> Option<(i128, i128, u32)> {
+        let signed = match ty.kind(&Interner) {
+            TyKind::Scalar(Scalar::Int(_)) => true,
+            TyKind::Scalar(Scalar::Uint(_)) => false,
+            _ => return None,
+        };
+        let krate = self.owner.module(self.db.upcast()).krate();
+        let bits = layout_of(self.db, ty, krate)?.size as u32 * 8;
+        let bounds = match (signed, bits) {
+            (true, 128) => (i128::MIN, i128::MAX, 128),
+            (false, 128) => (0, i128::MAX, 128),
+            (true, bits) => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1, bits),
+            (false, bits) => (0, (1 << bits) - 1, bits),
+        };
+        Some(bounds)
+    }
+}
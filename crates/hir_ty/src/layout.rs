@@ -0,0 +1,394 @@
+//! Computes the memory layout of types: their size, their alignment and the
+//! offsets of their fields.
+//!
+//! This follows rustc for the common cases, but doesn't replace it: for types
+//! which can't be laid out faithfully, like closures or generic types, no
+//! layout is computed at all.
+
+use std::cmp;
+
+use base_db::CrateId;
+use chalk_ir::{FloatTy, IntTy, UintTy};
+use hir_def::{
+    adt::ReprData, builtin_type::BuiltinType, type_ref::ConstScalar, AdtId, AttrDefId, EnumId,
+    EnumVariantId, VariantId,
+};
+
+use crate::{
+    consteval::eval_enum_discriminant, db::HirDatabase, Interner, Scalar, Substitution, Ty,
+    TyBuilder, TyKind,
+};
+
+/// The layout of a type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub size: u64,
+    pub align: u64,
+    /// The offsets of the fields of a struct, union or tuple, in the order of
+    /// their declaration.
+    pub field_offsets: Vec<u64>,
+    niche: Option<Niche>,
+}
+
+/// Invalid values of a scalar in a type, that enums containing the type can
+/// use for their discriminant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Niche {
+    offset: u64,
+    available: u128,
+}
+
+impl Layout {
+    fn primitive(size: u64, align: u64) -> Layout {
+        Layout { size, align, field_offsets: Vec::new(), niche: None }
+    }
+
+    fn with_niche(self, available: u128) -> Layout {
+        Layout { niche: Some(Niche { offset: 0, available }), ..self }
+    }
+}
+
+/// The layout of `ty` when compiled for the target of `krate`.
+pub fn layout_of(db: &dyn HirDatabase, ty: &Ty, krate: CrateId) -> Option<Layout> {
+    let _p = profile::span("layout_of");
+    LayoutCx { db, target: TargetDataLayout::of(db, krate) }.layout_of(ty, 0)
+}
+
+/// The parts of the data layout of a target which differ between the targets.
+#[derive(Debug, Clone, Copy)]
+struct TargetDataLayout {
+    pointer_size: u64,
+    /// The alignment of the 64 bit integers and floats.
+    align_64: u64,
+    align_128: u64,
+}
+
+impl TargetDataLayout {
+    fn of(db: &dyn HirDatabase, krate: CrateId) -> TargetDataLayout {
+        let crate_graph = db.crate_graph();
+        let data = &crate_graph[krate];
+        let arch = match &data.target {
+            Some(triple) => triple.split('-').next().map(ToString::to_string),
+            None => data.cfg_options.get_cfg_values("target_arch").first().map(|it| it.to_string()),
+        };
+        let arch = arch.unwrap_or_else(|| "x86_64".to_string());
+        let is_x86 = matches!(&*arch, "x86" | "i386" | "i586" | "i686");
+        let pointer_size = match &*arch {
+            "avr" | "msp430" => 2,
+            _ if arch.contains("64") || arch == "s390x" => 8,
+            _ => 4,
+        };
+        TargetDataLayout {
+            pointer_size,
+            align_64: if is_x86 { 4 } else { cmp::min(pointer_size * 2, 8) },
+            align_128: if is_x86 || pointer_size == 8 { 16 } else { 8 },
+        }
+    }
+}
+
+/// Types nested deeper than that are assumed to be infinitely recursive.
+const DEPTH_LIMIT: usize = 64;
+
+struct LayoutCx<'a> {
+    db: &'a dyn HirDatabase,
+    target: TargetDataLayout,
+}
+
+impl LayoutCx<'_> {
+    fn layout_of(&self, ty: &Ty, depth: usize) -> Option<Layout> {
+        if depth > DEPTH_LIMIT {
+            return None;
+        }
+        let depth = depth + 1;
+        let pointer_size = self.target.pointer_size;
+        let res = match ty.kind(&Interner) {
+            TyKind::Scalar(Scalar::Bool) => Layout::primitive(1, 1).with_niche(254),
+            TyKind::Scalar(Scalar::Char) => {
+                Layout::primitive(4, 4).with_niche((1 << 32) - 0x11_0000)
+            }
+            TyKind::Scalar(Scalar::Int(int)) => self.integer(int_size(*int, pointer_size)),
+            TyKind::Scalar(Scalar::Uint(uint)) => self.integer(uint_size(*uint, pointer_size)),
+            TyKind::Scalar(Scalar::Float(FloatTy::F32)) => self.integer(4),
+            TyKind::Scalar(Scalar::Float(FloatTy::F64)) => self.integer(8),
+            TyKind::Never | TyKind::FnDef(..) => Layout::primitive(0, 1),
+            TyKind::Function(_) => Layout::primitive(pointer_size, pointer_size).with_niche(1),
+            TyKind::Ref(_, _, pointee) => self.pointer(pointee, depth).with_niche(1),
+            TyKind::Raw(_, pointee) => self.pointer(pointee, depth),
+            TyKind::Array(elem, len) => {
+                let len = match &len.data(&Interner).value {
+                    chalk_ir::ConstValue::Concrete(chalk_ir::ConcreteConst {
+                        interned: ConstScalar::Usize(len),
+                    }) => *len,
+                    _ => return None,
+                };
+                let elem = self.layout_of(elem, depth)?;
+                Layout {
+                    size: elem.size.checked_mul(len)?,
+                    align: elem.align,
+                    field_offsets: Vec::new(),
+                    niche: elem.niche.filter(|_| len != 0),
+                }
+            }
+            TyKind::Tuple(_, subst) => {
+                let fields = subst
+                    .iter(&Interner)
+                    .map(|it| self.layout_of(it.assert_ty_ref(&Interner), depth))
+                    .collect::<Option<Vec<_>>>()?;
+                self.univariant(&fields, &ReprData::default(), None)
+            }
+            TyKind::Adt(crate::AdtId(adt), subst) => self.adt(*adt, subst, depth)?,
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    fn integer(&self, size: u64) -> Layout {
+        let align = match size {
+            8 => self.target.align_64,
+            16 => self.target.align_128,
+            _ => size,
+        };
+        Layout::primitive(size, align)
+    }
+
+    /// Pointers to dynamically sized types carry their length or vtable.
+    fn pointer(&self, pointee: &Ty, depth: usize) -> Layout {
+        let pointer_size = self.target.pointer_size;
+        let size = if self.is_unsized(pointee, depth) { 2 * pointer_size } else { pointer_size };
+        Layout::primitive(size, pointer_size)
+    }
+
+    fn is_unsized(&self, ty: &Ty, depth: usize) -> bool {
+        match ty.kind(&Interner) {
+            TyKind::Str | TyKind::Slice(_) | TyKind::Dyn(_) => true,
+            TyKind::Adt(crate::AdtId(AdtId::StructId(id)), subst) if depth <= DEPTH_LIMIT => {
+                let field_types = self.db.field_types((*id).into());
+                match field_types.iter().last() {
+                    Some((_, ty)) => {
+                        self.is_unsized(&ty.clone().substitute(&Interner, subst), depth + 1)
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn adt(&self, adt: AdtId, subst: &Substitution, depth: usize) -> Option<Layout> {
+        let layout = match adt {
+            AdtId::StructId(id) => {
+                let repr = self.db.struct_data(id).repr.clone().unwrap_or_default();
+                let fields = self.fields(id.into(), subst, depth)?;
+                self.univariant(&fields, &repr, None)
+            }
+            AdtId::UnionId(id) => {
+                let repr = self.db.union_data(id).repr.clone().unwrap_or_default();
+                let fields = self.fields(id.into(), subst, depth)?;
+                self.union(&fields, &repr)
+            }
+            AdtId::EnumId(id) => self.enum_(id, subst, depth)?,
+        };
+        // `NonNull` and the `NonZero` integers exclude zero this way.
+        let attrs = self.db.attrs(AttrDefId::AdtId(adt));
+        Some(match attrs.by_key("rustc_layout_scalar_valid_range_start").int_value() {
+            Some(start) => layout.with_niche(start),
+            None => layout,
+        })
+    }
+
+    fn fields(
+        &self,
+        variant: VariantId,
+        subst: &Substitution,
+        depth: usize,
+    ) -> Option<Vec<Layout>> {
+        let field_types = self.db.field_types(variant);
+        field_types
+            .iter()
+            .map(|(_, ty)| self.layout_of(&ty.clone().substitute(&Interner, subst), depth))
+            .collect()
+    }
+
+    /// Lays out the fields of a struct or a tuple, or those of an enum variant
+    /// after the `tag` of the enum.
+    fn univariant(&self, fields: &[Layout], repr: &ReprData, tag: Option<&Layout>) -> Layout {
+        let field_align = |field: &Layout| match repr.packed {
+            Some(pack) => cmp::min(field.align, pack),
+            None => field.align,
+        };
+        let mut order = (0..fields.len()).collect::<Vec<_>>();
+        if !repr.c && repr.int.is_none() {
+            // Zero-sized fields go first, then the others by alignment: from the
+            // largest for structs and from the smallest for variants, so that
+            // they fill the space after the tag.
+            match tag {
+                None => order.sort_by_key(|&it| {
+                    (fields[it].size != 0, cmp::Reverse(field_align(&fields[it])))
+                }),
+                Some(_) => {
+                    order.sort_by_key(|&it| (fields[it].size != 0, field_align(&fields[it])))
+                }
+            }
+        }
+
+        let (mut offset, mut align) = tag.map_or((0, 1), |tag| (tag.size, tag.align));
+        let mut field_offsets = vec![0; fields.len()];
+        let mut niche: Option<Niche> = None;
+        for idx in order {
+            let field = &fields[idx];
+            offset = align_to(offset, field_align(field));
+            field_offsets[idx] = offset;
+            if let Some(field_niche) = field.niche {
+                if niche.map_or(true, |it| it.available < field_niche.available) {
+                    niche = Some(Niche { offset: offset + field_niche.offset, ..field_niche });
+                }
+            }
+            offset += field.size;
+            align = cmp::max(align, field_align(field));
+        }
+        if let Some(repr_align) = repr.align {
+            align = cmp::max(align, repr_align);
+        }
+        Layout { size: align_to(offset, align), align, field_offsets, niche }
+    }
+
+    fn union(&self, fields: &[Layout], repr: &ReprData) -> Layout {
+        let field_align = |field: &Layout| match repr.packed {
+            Some(pack) => cmp::min(field.align, pack),
+            None => field.align,
+        };
+        let size = fields.iter().map(|it| it.size).max().unwrap_or(0);
+        let align = fields.iter().map(field_align).chain(repr.align).max().unwrap_or(1);
+        Layout {
+            size: align_to(size, align),
+            align,
+            field_offsets: vec![0; fields.len()],
+            niche: None,
+        }
+    }
+
+    fn enum_(&self, id: EnumId, subst: &Substitution, depth: usize) -> Option<Layout> {
+        let data = self.db.enum_data(id);
+        let repr = data.repr.clone().unwrap_or_default();
+        let variants = data
+            .variants
+            .iter()
+            .map(|(local_id, _)| {
+                self.fields(EnumVariantId { parent: id, local_id }.into(), subst, depth)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if variants.is_empty() {
+            return Some(Layout::primitive(0, 1));
+        }
+        let discriminants = data
+            .variants
+            .iter()
+            .map(|(local_id, _)| {
+                eval_enum_discriminant(self.db, EnumVariantId { parent: id, local_id })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let is_implicit = discriminants.iter().zip(0..).all(|(&it, idx)| it == idx);
+        let min = discriminants.iter().copied().min()?;
+        let max = discriminants.iter().copied().max()?;
+
+        if !repr.c && repr.int.is_none() {
+            if let [fields] = &*variants {
+                let layout = self.univariant(fields, &repr, None);
+                return Some(Layout { field_offsets: Vec::new(), ..layout });
+            }
+        }
+        if !repr.c && repr.int.is_none() && is_implicit {
+            // The discriminant of the other variants can be stored in a niche
+            // of the only variant with data.
+            let mut dataful = variants.iter().filter(|it| it.iter().any(|field| field.size != 0));
+            if let (Some(fields), None) = (dataful.next(), dataful.next()) {
+                let layout = self.univariant(fields, &repr, None);
+                let needed = variants.len() as u128 - 1;
+                if let Some(niche) = layout.niche.filter(|it| it.available >= needed) {
+                    let niche = Niche { available: niche.available - needed, ..niche };
+                    return Some(Layout {
+                        field_offsets: Vec::new(),
+                        niche: Some(niche),
+                        ..layout
+                    });
+                }
+            }
+        }
+
+        let tag = match repr.int {
+            Some(int) => {
+                let ty = TyBuilder::builtin(int.either(BuiltinType::Int, BuiltinType::Uint));
+                self.layout_of(&ty, depth)?
+            }
+            None => {
+                // The smallest integer holding all discriminants, at least a
+                // C `int` for `repr(C)`.
+                let fits = |size: u64| {
+                    let bits = 8 * size as u32;
+                    match bits {
+                        128 => true,
+                        _ if min < 0 => -(1 << (bits - 1)) <= min && max < 1 << (bits - 1),
+                        _ => max < 1 << bits,
+                    }
+                };
+                let min_size = if repr.c { 4 } else { 1 };
+                let size =
+                    [1, 2, 4, 8, 16].iter().copied().find(|&it| it >= min_size && fits(it))?;
+                self.integer(size)
+            }
+        };
+        if repr.c {
+            // Laid out like `struct { tag, union { variants } }`.
+            let variants = variants
+                .iter()
+                .map(|fields| self.univariant(fields, &repr, None))
+                .collect::<Vec<_>>();
+            let variants = self.union(&variants, &ReprData::default());
+            let layout = self.univariant(&[tag, variants], &repr, None);
+            return Some(Layout { field_offsets: Vec::new(), niche: None, ..layout });
+        }
+
+        let mut size = tag.size;
+        let mut align = tag.align;
+        for fields in &variants {
+            let layout = self.univariant(fields, &repr, Some(&tag));
+            size = cmp::max(size, layout.size);
+            align = cmp::max(align, layout.align);
+        }
+        let tag_values = u128::MAX >> (128 - 8 * tag.size);
+        let used = max.checked_sub(min)? as u128;
+        let niche = Niche { offset: 0, available: tag_values.checked_sub(used)? };
+        Some(Layout {
+            size: align_to(size, align),
+            align,
+            field_offsets: Vec::new(),
+            niche: Some(niche).filter(|it| it.available != 0),
+        })
+    }
+}
+
+fn align_to(offset: u64, align: u64) -> u64 {
+    (offset + align - 1) / align * align
+}
+
+fn int_size(int: IntTy, pointer_size: u64) -> u64 {
+    match int {
+        IntTy::Isize => pointer_size,
+        IntTy::I8 => 1,
+        IntTy::I16 => 2,
+        IntTy::I32 => 4,
+        IntTy::I64 => 8,
+        IntTy::I128 => 16,
+    }
+}
+
+fn uint_size(uint: UintTy, pointer_size: u64) -> u64 {
+    match uint {
+        UintTy::Usize => pointer_size,
+        UintTy::U8 => 1,
+        UintTy::U16 => 2,
+        UintTy::U32 => 4,
+        UintTy::U64 => 8,
+        UintTy::U128 => 16,
+    }
+}
@@ -22,6 +22,7 @@ mod walk;
 pub mod db;
 pub mod diagnostics;
 pub mod display;
+pub mod layout;
 pub mod method_resolution;
 pub mod primitive;
 pub mod traits;
@@ -61,7 +62,7 @@ pub use mapping::{
     to_foreign_def_id, to_placeholder_idx,
 };
 pub use traits::TraitEnvironment;
-pub use utils::all_super_traits;
+pub use utils::{all_super_traits, direct_super_traits};
 pub use walk::TypeWalk;
 
 pub use chalk_ir::{
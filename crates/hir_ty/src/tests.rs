@@ -568,7 +568,7 @@ fn salsa_bug() {
     "
     .to_string();
 
-    db.set_file_text(pos.file_id, Arc::new(new_text));
+    db.set_file_source(pos.file_id, Arc::new(new_text).into());
 
     let module = db.module_for_file(pos.file_id);
     let crate_def_map = module.def_map(&db);
@@ -36,7 +36,7 @@ fn typing_whitespace_inside_a_function_should_not_invalidate_types() {
     "
     .to_string();
 
-    db.set_file_text(pos.file_id, Arc::new(new_text));
+    db.set_file_source(pos.file_id, Arc::new(new_text).into());
 
     {
         let events = db.log_executed(|| {
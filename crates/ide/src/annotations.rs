@@ -1,5 +1,5 @@
 use either::Either;
-use hir::{HasSource, InFile, Semantics};
+use hir::{HasSource, HasVisibility, InFile, Semantics};
 use ide_db::{
     base_db::{FileId, FilePosition, FileRange},
     helpers::visit_file_defs,
@@ -18,7 +18,8 @@ use crate::{
 // Feature: Annotations
 //
 // Provides user with annotations above items for looking up references or impl blocks
-// and running/debugging binaries.
+// and running/debugging binaries. Traits and trait methods show their implementations,
+// types, constants and public items show their references.
 //
 // image::https://user-images.githubusercontent.com/48062697/113020672-b7c34f00-917a-11eb-8f6e-858735660a0e.png[]
 #[derive(Debug)]
@@ -79,6 +80,15 @@ pub(crate) fn annotations(
                 hir::ModuleDef::Adt(hir::Adt::Union(union)) => {
                     union.source(db).and_then(|node| name_range(&node, file_id))
                 }
+                hir::ModuleDef::Function(func) if is_public(db, func) => {
+                    func.source(db).and_then(|node| name_range(&node, file_id))
+                }
+                hir::ModuleDef::Static(static_) if is_public(db, static_) => {
+                    static_.source(db).and_then(|node| name_range(&node, file_id))
+                }
+                hir::ModuleDef::TypeAlias(alias) if is_public(db, alias) => {
+                    alias.source(db).and_then(|node| name_range(&node, file_id))
+                }
                 _ => None,
             };
             let (range, offset) = match range {
@@ -86,7 +96,9 @@ pub(crate) fn annotations(
                 None => return,
             };
 
-            if config.annotate_impls && !matches!(def, hir::ModuleDef::Const(_)) {
+            if config.annotate_impls
+                && matches!(def, hir::ModuleDef::Trait(_) | hir::ModuleDef::Adt(_))
+            {
                 annotations.push(Annotation {
                     range,
                     kind: AnnotationKind::HasImpls {
@@ -95,6 +107,25 @@ pub(crate) fn annotations(
                     },
                 });
             }
+            if let (true, hir::ModuleDef::Trait(trait_)) = (config.annotate_impls, def) {
+                for item in trait_.items(db) {
+                    let range = match item {
+                        hir::AssocItem::Function(func) => {
+                            func.source(db).and_then(|node| name_range(&node, file_id))
+                        }
+                        _ => None,
+                    };
+                    if let Some(range) = range {
+                        annotations.push(Annotation {
+                            range,
+                            kind: AnnotationKind::HasImpls {
+                                position: FilePosition { file_id, offset: range.start() },
+                                data: None,
+                            },
+                        });
+                    }
+                }
+            }
             if config.annotate_references {
                 annotations.push(Annotation {
                     range,
@@ -118,15 +149,24 @@ pub(crate) fn annotations(
     });
 
     if config.annotate_method_references {
-        annotations.extend(find_all_methods(db, file_id).into_iter().map(
-            |FileRange { file_id, range }| Annotation {
-                range,
-                kind: AnnotationKind::HasReferences {
-                    position: FilePosition { file_id, offset: range.start() },
-                    data: None,
-                },
-            },
-        ));
+        // Public functions already got a references annotation above.
+        let annotated: Vec<TextRange> = annotations
+            .iter()
+            .filter(|it| matches!(it.kind, AnnotationKind::HasReferences { .. }))
+            .map(|it| it.range)
+            .collect();
+        annotations.extend(
+            find_all_methods(db, file_id)
+                .into_iter()
+                .filter(|it| !annotated.contains(&it.range))
+                .map(|FileRange { file_id, range }| Annotation {
+                    range,
+                    kind: AnnotationKind::HasReferences {
+                        position: FilePosition { file_id, offset: range.start() },
+                        data: None,
+                    },
+                }),
+        );
     }
 
     annotations
@@ -155,6 +195,10 @@ pub(crate) fn resolve_annotation(db: &RootDatabase, mut annotation: Annotation)
     annotation
 }
 
+fn is_public(db: &RootDatabase, def: impl HasVisibility) -> bool {
+    matches!(def.visibility(db), hir::Visibility::Public)
+}
+
 fn should_skip_runnable(kind: &RunnableKind, binary_target: bool) -> bool {
     match kind {
         RunnableKind::Bin => !binary_target,
@@ -169,19 +213,24 @@ mod tests {
     use crate::{fixture, Annotation, AnnotationConfig};
 
     fn check(ra_fixture: &str, expect: Expect) {
+        check_with_config(
+            AnnotationConfig {
+                binary_target: true,
+                annotate_runnables: true,
+                annotate_impls: true,
+                annotate_references: true,
+                annotate_method_references: true,
+            },
+            ra_fixture,
+            expect,
+        )
+    }
+
+    fn check_with_config(config: AnnotationConfig, ra_fixture: &str, expect: Expect) {
         let (analysis, file_id) = fixture::file(ra_fixture);
 
         let annotations: Vec<Annotation> = analysis
-            .annotations(
-                &AnnotationConfig {
-                    binary_target: true,
-                    annotate_runnables: true,
-                    annotate_impls: true,
-                    annotate_references: true,
-                    annotate_method_references: true,
-                },
-                file_id,
-            )
+            .annotations(&config, file_id)
             .unwrap()
             .into_iter()
             .map(|annotation| analysis.resolve_annotation(annotation).unwrap())
@@ -512,6 +561,105 @@ fn main() {
         );
     }
 
+    #[test]
+    fn trait_method_impls_annotations() {
+        check_with_config(
+            AnnotationConfig {
+                binary_target: true,
+                annotate_runnables: false,
+                annotate_impls: true,
+                annotate_references: false,
+                annotate_method_references: false,
+            },
+            r#"
+trait Tr {
+    fn m(&self);
+}
+struct S;
+impl Tr for S {
+    fn m(&self) {}
+}
+"#,
+            expect![[r#"
+                [
+                    Annotation {
+                        range: 6..8,
+                        kind: HasImpls {
+                            position: FilePosition {
+                                file_id: FileId(
+                                    0,
+                                ),
+                                offset: 6,
+                            },
+                            data: Some(
+                                [
+                                    NavigationTarget {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        full_range: 40..76,
+                                        focus_range: 52..53,
+                                        name: "impl",
+                                        kind: Impl,
+                                    },
+                                ],
+                            ),
+                        },
+                    },
+                    Annotation {
+                        range: 18..19,
+                        kind: HasImpls {
+                            position: FilePosition {
+                                file_id: FileId(
+                                    0,
+                                ),
+                                offset: 18,
+                            },
+                            data: Some(
+                                [
+                                    NavigationTarget {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        full_range: 60..74,
+                                        focus_range: 63..64,
+                                        name: "m",
+                                        kind: Function,
+                                        description: "fn m(&self)",
+                                    },
+                                ],
+                            ),
+                        },
+                    },
+                    Annotation {
+                        range: 37..38,
+                        kind: HasImpls {
+                            position: FilePosition {
+                                file_id: FileId(
+                                    0,
+                                ),
+                                offset: 37,
+                            },
+                            data: Some(
+                                [
+                                    NavigationTarget {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        full_range: 40..76,
+                                        focus_range: 52..53,
+                                        name: "impl",
+                                        kind: Impl,
+                                    },
+                                ],
+                            ),
+                        },
+                    },
+                ]
+            "#]],
+        );
+    }
+
     #[test]
     fn runnable_annotation() {
         check(
@@ -0,0 +1,204 @@
+//! Function call graphs of whole crates.
+
+use dot::{Id, LabelText};
+use hir::{
+    AsAssocItem, AssocItem, AssocItemContainer, Crate, Function, HirDisplay, Module, ModuleDef,
+};
+use ide_db::{base_db::CrateId, RootDatabase};
+use rustc_hash::FxHashMap;
+
+use crate::{display::TryToNav, NavigationTarget};
+
+#[derive(Debug, Clone)]
+pub struct CallGraphFunction {
+    /// The path of the function, like `krate::module::Type::method`.
+    pub path: String,
+    /// Whether the function belongs to one of the crates the graph was
+    /// computed for. Calls are only collected for those.
+    pub local: bool,
+    pub nav: Option<NavigationTarget>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    pub functions: Vec<CallGraphFunction>,
+    /// Pairs of caller and callee, as indices into `functions`.
+    pub calls: Vec<(usize, usize)>,
+}
+
+impl CallGraph {
+    /// Renders the graph to GraphViz "dot" syntax.
+    pub fn to_dot(&self) -> String {
+        let mut dot = Vec::new();
+        dot::render(self, &mut dot).unwrap();
+        String::from_utf8(dot).unwrap()
+    }
+}
+
+pub(crate) fn call_graph(db: &RootDatabase, crates: &[CrateId]) -> CallGraph {
+    let mut builder = Builder { db, graph: CallGraph::default(), ids: FxHashMap::default() };
+
+    let crates = crates.iter().map(|&it| Crate::from(it)).collect::<Vec<_>>();
+    let functions = crates.iter().flat_map(|&krate| crate_functions(db, krate)).collect::<Vec<_>>();
+
+    for &caller in &functions {
+        let caller_idx = builder.node(caller, true);
+        for callee in caller.callees(db) {
+            let local = crates.contains(&callee.module(db).krate());
+            let callee_idx = builder.node(callee, local);
+            builder.graph.calls.push((caller_idx, callee_idx));
+        }
+    }
+    builder.graph
+}
+
+/// All functions of `krate`, including associated functions of traits and
+/// impls.
+pub(crate) fn crate_functions(db: &RootDatabase, krate: Crate) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let mut worklist: Vec<Module> = vec![krate.root_module(db)];
+    while let Some(module) = worklist.pop() {
+        worklist.extend(module.children(db));
+        for decl in module.declarations(db) {
+            match decl {
+                ModuleDef::Function(it) => functions.push(it),
+                ModuleDef::Trait(it) => functions.extend(assoc_functions(it.items(db))),
+                _ => (),
+            }
+        }
+        for impl_ in module.impl_defs(db) {
+            functions.extend(assoc_functions(impl_.items(db)));
+        }
+    }
+    functions
+}
+
+fn assoc_functions(items: Vec<AssocItem>) -> impl Iterator<Item = Function> {
+    items.into_iter().filter_map(|it| match it {
+        AssocItem::Function(it) => Some(it),
+        _ => None,
+    })
+}
+
+struct Builder<'a> {
+    db: &'a RootDatabase,
+    graph: CallGraph,
+    ids: FxHashMap<Function, usize>,
+}
+
+impl Builder<'_> {
+    fn node(&mut self, func: Function, local: bool) -> usize {
+        if let Some(&idx) = self.ids.get(&func) {
+            return idx;
+        }
+        let idx = self.graph.functions.len();
+        self.graph.functions.push(CallGraphFunction {
+            path: function_path(self.db, func),
+            local,
+            nav: func.try_to_nav(self.db),
+        });
+        self.ids.insert(func, idx);
+        idx
+    }
+}
+
+pub(crate) fn function_path(db: &RootDatabase, func: Function) -> String {
+    let module = func.module(db);
+    let mut segments = Vec::new();
+    segments.extend(module.krate().display_name(db).map(|it| it.to_string()));
+    segments.extend(
+        module.path_to_root(db).into_iter().rev().filter_map(|it| Some(it.name(db)?.to_string())),
+    );
+    match func.as_assoc_item(db).map(|it| it.container(db)) {
+        Some(AssocItemContainer::Trait(it)) => segments.push(it.name(db).to_string()),
+        Some(AssocItemContainer::Impl(it)) => segments.push(it.self_ty(db).display(db).to_string()),
+        None => (),
+    }
+    segments.push(func.name(db).to_string());
+    segments.join("::")
+}
+
+impl<'a> dot::GraphWalk<'a, usize, (usize, usize)> for CallGraph {
+    fn nodes(&'a self) -> dot::Nodes<'a, usize> {
+        (0..self.functions.len()).collect()
+    }
+
+    fn edges(&'a self) -> dot::Edges<'a, (usize, usize)> {
+        self.calls.as_slice().into()
+    }
+
+    fn source(&'a self, edge: &(usize, usize)) -> usize {
+        edge.0
+    }
+
+    fn target(&'a self, edge: &(usize, usize)) -> usize {
+        edge.1
+    }
+}
+
+impl<'a> dot::Labeller<'a, usize, (usize, usize)> for CallGraph {
+    fn graph_id(&'a self) -> Id<'a> {
+        Id::new("rust_analyzer_call_graph").unwrap()
+    }
+
+    fn node_id(&'a self, n: &usize) -> Id<'a> {
+        Id::new(format!("_{}", n)).unwrap()
+    }
+
+    fn node_shape(&'a self, n: &usize) -> Option<LabelText<'a>> {
+        let shape = if self.functions[*n].local { "box" } else { "ellipse" };
+        Some(LabelText::LabelStr(shape.into()))
+    }
+
+    fn node_label(&'a self, n: &usize) -> LabelText<'a> {
+        LabelText::LabelStr(self.functions[*n].path.as_str().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use crate::fixture;
+
+    #[test]
+    fn call_graph_of_crate() {
+        let (analysis, file_id) = fixture::file(
+            r#"
+//- /main.rs crate:main deps:dep
+fn main() {
+    foo();
+    S.bar();
+    dep::baz();
+}
+fn foo() { foo(); }
+struct S;
+impl S {
+    fn bar(&self) { foo() }
+}
+//- /dep.rs crate:dep
+pub fn baz() {}
+"#,
+        );
+        let krate = analysis.crate_for(file_id).unwrap()[0];
+        let graph = analysis.call_graph(&[krate]).unwrap();
+        let mut calls = graph
+            .calls
+            .iter()
+            .map(|&(caller, callee)| {
+                let callee = &graph.functions[callee];
+                let marker = if callee.local { "" } else { " (external)" };
+                format!("{} -> {}{}\n", graph.functions[caller].path, callee.path, marker)
+            })
+            .collect::<Vec<_>>();
+        calls.sort();
+        expect![[r#"
+            main::S::bar -> main::foo
+            main::foo -> main::foo
+            main::main -> dep::baz (external)
+            main::main -> main::S::bar
+            main::main -> main::foo
+        "#]]
+        .assert_eq(&calls.concat());
+    }
+}
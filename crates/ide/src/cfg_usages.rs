@@ -0,0 +1,249 @@
+//! Finds the `#[cfg]`s, `#[cfg_attr]`s and `cfg!`s of the workspace crates
+//! which mention a cargo feature, for when a feature is removed or renamed.
+//!
+//! Inactive code is not in the def maps, so the files are searched
+//! syntactically, including the code disabled by the feature itself. Files of
+//! modules declared in inactive code are found through the source roots.
+
+use cfg::{CfgAtom, CfgExpr};
+use hir::{Crate, Module};
+use ide_db::{
+    base_db::{CrateId, FileId, FileRange, SourceDatabase, SourceDatabaseExt},
+    RootDatabase,
+};
+use rustc_hash::FxHashSet;
+use syntax::{ast, AstNode, SyntaxKind, TextRange, T};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgUsageKind {
+    /// `#[cfg(..)]`
+    Cfg,
+    /// `#[cfg_attr(.., ..)]`
+    CfgAttr,
+    /// `cfg!(..)`
+    CfgMacro,
+}
+
+#[derive(Debug)]
+pub struct CfgUsage {
+    /// The string literal naming the feature, or the whole predicate if it
+    /// can't be found.
+    pub range: FileRange,
+    pub kind: CfgUsageKind,
+}
+
+/// The usages of a feature in the files of one crate.
+#[derive(Debug)]
+pub struct CrateCfgUsages {
+    pub krate: CrateId,
+    pub crate_name: Option<String>,
+    pub usages: Vec<CfgUsage>,
+}
+
+pub(crate) fn cfg_feature_usages(db: &RootDatabase, feature: &str) -> Vec<CrateCfgUsages> {
+    let _p = profile::span("cfg_feature_usages");
+    let crate_graph = db.crate_graph();
+    let local_crates = crate_graph.iter().filter(|&krate| {
+        let root_file = crate_graph[krate].root_file_id;
+        !db.source_root(db.file_source_root(root_file)).is_library
+    });
+
+    // Files shared by several crates are only reported for the first.
+    let mut seen_files = FxHashSet::default();
+    let mut crate_files = local_crates
+        .map(|krate| {
+            let files = module_files(db, Crate::from(krate))
+                .into_iter()
+                .filter(|&file_id| seen_files.insert(file_id))
+                .collect::<Vec<_>>();
+            (krate, files)
+        })
+        .collect::<Vec<_>>();
+    for (krate, files) in &mut crate_files {
+        let source_root = db.source_root(db.file_source_root(crate_graph[*krate].root_file_id));
+        files.extend(source_root.iter().filter(|&file_id| {
+            let is_rs = source_root
+                .path_for_file(&file_id)
+                .and_then(|path| path.name_and_extension())
+                .map_or(false, |(_, ext)| ext == Some("rs"));
+            is_rs && seen_files.insert(file_id)
+        }));
+        files.sort();
+    }
+
+    crate_files
+        .into_iter()
+        .filter_map(|(krate, files)| {
+            let usages = files
+                .into_iter()
+                .flat_map(|file_id| file_usages(db, file_id, feature))
+                .collect::<Vec<_>>();
+            if usages.is_empty() {
+                return None;
+            }
+            let crate_name = crate_graph[krate].display_name.as_ref().map(|it| it.to_string());
+            Some(CrateCfgUsages { krate, crate_name, usages })
+        })
+        .collect()
+}
+
+fn module_files(db: &RootDatabase, krate: Crate) -> Vec<FileId> {
+    let mut files = Vec::new();
+    let mut modules: Vec<Module> = vec![krate.root_module(db)];
+    while let Some(module) = modules.pop() {
+        modules.extend(module.children(db));
+        let file_id = module.definition_source(db).file_id.original_file(db);
+        if !files.contains(&file_id) {
+            files.push(file_id);
+        }
+    }
+    files
+}
+
+fn file_usages(db: &RootDatabase, file_id: FileId, feature: &str) -> Vec<CfgUsage> {
+    let source_file = db.parse(file_id).tree();
+    let mut res = Vec::new();
+    for node in source_file.syntax().descendants() {
+        let (kind, tt) = if let Some(attr) = ast::Attr::cast(node.clone()) {
+            match attr.as_simple_call() {
+                Some((name, tt)) if name == "cfg" => (CfgUsageKind::Cfg, tt),
+                Some((name, tt)) if name == "cfg_attr" => (CfgUsageKind::CfgAttr, tt),
+                _ => continue,
+            }
+        } else if let Some(call) = ast::MacroCall::cast(node) {
+            let is_cfg = call.path().and_then(|it| it.segment()).map_or(false, |it| {
+                it.name_ref().map_or(false, |name_ref| name_ref.text() == "cfg")
+            });
+            match call.token_tree() {
+                Some(tt) if is_cfg => (CfgUsageKind::CfgMacro, tt),
+                _ => continue,
+            }
+        } else {
+            continue;
+        };
+
+        // `CfgExpr::parse` stops after the first predicate, which skips the
+        // attributes of a `cfg_attr`.
+        let cfg = CfgExpr::parse(&mbe::ast_to_token_tree(&tt).0);
+        let mut mentioned = false;
+        cfg.walk_atoms(&mut |atom| match atom {
+            CfgAtom::KeyValue { key, value } if key == "feature" && value == feature => {
+                mentioned = true
+            }
+            _ => (),
+        });
+        if !mentioned {
+            continue;
+        }
+        let ranges = feature_literals(&tt, feature);
+        if ranges.is_empty() {
+            res.push(CfgUsage {
+                range: FileRange { file_id, range: tt.syntax().text_range() },
+                kind,
+            });
+        }
+        res.extend(
+            ranges.into_iter().map(|range| CfgUsage { range: FileRange { file_id, range }, kind }),
+        );
+    }
+    res
+}
+
+/// The ranges of the `"feature"` literals in `feature = "feature"` predicates.
+fn feature_literals(tt: &ast::TokenTree, feature: &str) -> Vec<TextRange> {
+    // The predicate of a `cfg_attr` ends at the first comma of its arguments.
+    let end = tt
+        .syntax()
+        .children_with_tokens()
+        .find(|it| it.kind() == T![,])
+        .map_or(tt.syntax().text_range().end(), |it| it.text_range().start());
+    tt.syntax()
+        .descendants_with_tokens()
+        .filter_map(|it| it.into_token())
+        .filter(|token| token.kind() == SyntaxKind::STRING && token.text_range().end() <= end)
+        .filter(|token| token.text().trim_matches('"') == feature)
+        .filter(|token| {
+            let mut prev = std::iter::successors(token.prev_token(), |it| it.prev_token())
+                .filter(|it| !it.kind().is_trivia());
+            matches!(prev.next(), Some(eq) if eq.kind() == T![=])
+                && matches!(prev.next(), Some(key) if key.text() == "feature")
+        })
+        .map(|token| token.text_range())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+
+    use crate::fixture;
+
+    fn check(ra_fixture: &str, feature: &str, expect: Expect) {
+        let (analysis, _) = fixture::file(ra_fixture);
+        let usages = analysis.cfg_feature_usages(feature).unwrap();
+        let actual = usages
+            .iter()
+            .map(|krate| {
+                let usages = krate
+                    .usages
+                    .iter()
+                    .map(|it| {
+                        format!("  {:?} {:?} {:?}\n", it.kind, it.range.file_id, it.range.range)
+                    })
+                    .collect::<String>();
+                format!("{}\n{}", krate.crate_name.as_deref().unwrap_or("?"), usages)
+            })
+            .collect::<String>();
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn finds_cfg_cfg_attr_and_cfg_macro() {
+        check(
+            r#"
+//- /main.rs crate:main deps:dep
+#[cfg(feature = "serde")]
+mod ser;
+#[derive(Debug)]
+#[cfg_attr(all(unix, not(feature = "serde")), derive(Clone))]
+struct S;
+fn f() -> bool {
+    cfg!(any(feature = "serde", feature = "json"))
+}
+//- /ser.rs
+#[cfg(all(feature = "serde", feature = "json"))]
+fn g() {}
+//- /dep.rs crate:dep
+#[cfg(feature = "serde")]
+pub fn ser() {}
+"#,
+            "serde",
+            expect![[r#"
+                main
+                  Cfg FileId(0) 16..23
+                  CfgAttr FileId(0) 87..94
+                  CfgMacro FileId(0) 164..171
+                  Cfg FileId(1) 20..27
+                dep
+                  Cfg FileId(2) 16..23
+            "#]],
+        );
+    }
+
+    #[test]
+    fn ignores_other_keys_and_cfg_attr_attributes() {
+        check(
+            r#"
+//- /main.rs crate:main
+#[cfg(serde)]
+fn f() {}
+#[cfg(target_feature = "serde")]
+fn g() {}
+#[cfg_attr(test, feature = "serde")]
+fn h() {}
+"#,
+            "serde",
+            expect![[r#""#]],
+        );
+    }
+}
@@ -6,3 +6,12 @@ pub(crate) mod navigation_target;
 pub(crate) use navigation_target::{ToNav, TryToNav};
 
 pub(crate) use syntax::display::macro_label;
+
+/// Renders the value of a constant, with the integers which are likely bit
+/// patterns in hexadecimal too.
+pub(crate) fn computed_expr_label(value: hir::ComputedExpr) -> String {
+    match value {
+        hir::ComputedExpr::Int(it) if it >= 10 => format!("{} ({:#X})", it, it),
+        it => it.to_string(),
+    }
+}
@@ -21,10 +21,28 @@ use ide_db::{
 };
 use syntax::{ast, match_ast, AstNode, SyntaxKind::*, SyntaxNode, TextRange, T};
 
-use crate::{FilePosition, Semantics};
+use crate::{display::TryToNav, FileId, FilePosition, NavigationTarget, Semantics};
 
 pub(crate) type DocumentationLink = String;
 
+/// An intra-doc link in a doc comment, like ``[`Vec`]`` or `[foo](crate::foo)`.
+#[derive(Debug, Clone)]
+pub struct IntraDocLink {
+    /// The range of the link in the file.
+    pub range: TextRange,
+    /// The link target as written, without namespace disambiguators.
+    pub link: String,
+    /// `None` if the link doesn't resolve.
+    pub resolved: Option<ResolvedDocLink>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedDocLink {
+    /// The full path of the target, starting with the crate name.
+    pub path: String,
+    pub nav: Option<NavigationTarget>,
+}
+
 /// Rewrite documentation links in markdown to point to an online host (e.g. docs.rs)
 pub(crate) fn rewrite_links(db: &RootDatabase, markdown: &str, definition: &Definition) -> String {
     let mut cb = broken_link_clone_cb;
@@ -155,6 +173,59 @@ pub(crate) fn extract_definitions_from_markdown(
     .collect()
 }
 
+/// Finds and resolves all intra-doc links in the doc comments of `file_id`.
+///
+/// Links to URLs and to rustdoc's HTML files are skipped, as they can't be
+/// checked without the rendered documentation.
+pub(crate) fn intra_doc_links(db: &RootDatabase, file_id: FileId) -> Vec<IntraDocLink> {
+    let sema = Semantics::new(db);
+    let file = sema.parse(file_id);
+    let mut res = Vec::new();
+    for node in file.syntax().descendants() {
+        let (attrs, def) = match doc_attributes(&sema, &node) {
+            Some(it) => it,
+            None => continue,
+        };
+        let (docs, doc_mapping) = match attrs.docs_with_rangemap(db) {
+            Some(it) => it,
+            None => continue,
+        };
+        for (range, link, ns) in extract_definitions_from_markdown(docs.as_str()) {
+            if link.is_empty()
+                || link.contains("://")
+                || link.starts_with('#')
+                || link.contains(".html")
+                || link.contains('/')
+            {
+                continue;
+            }
+            // The docs of out-of-line modules are spread over two files, only
+            // report the links in this one.
+            let range = match doc_mapping.map(range) {
+                Some(hir::InFile { file_id: it, value }) if it == file_id.into() => value,
+                _ => continue,
+            };
+            let resolved = resolve_doc_path_for_def(db, def, &link, ns).map(|target| {
+                // `ModuleDef::module` is the parent of a module, which the crate root lacks.
+                let krate = match target {
+                    hir::ModuleDef::Module(it) => Some(it.krate()),
+                    _ => target.module(db).map(|it| it.krate()),
+                };
+                let crate_name = krate.and_then(|it| it.display_name(db)).map(|it| it.to_string());
+                let path = crate_name
+                    .into_iter()
+                    .chain(target.canonical_path(db))
+                    .collect::<Vec<_>>()
+                    .join("::");
+                ResolvedDocLink { path, nav: target.try_to_nav(db) }
+            });
+            res.push(IntraDocLink { range, link, resolved });
+        }
+    }
+    res.sort_by_key(|it| it.range.start());
+    res
+}
+
 pub(crate) fn resolve_doc_path_for_def(
     db: &dyn HirDatabase,
     def: Definition,
@@ -672,4 +743,34 @@ fn foo() {
             expect![[r#"https://docs.rs/test/*/test/wrapper/struct.Item.html"#]],
         )
     }
+
+    #[test]
+    fn intra_doc_links_in_file() {
+        let (analysis, file_id) = fixture::file(
+            r#"
+//! See [`Foo`] and [missing].
+
+/// Unlike [`Bar`], lives in [the root](crate). See <https://example.com> or [docs](#foo).
+pub struct Foo;
+pub struct Bar;
+"#,
+        );
+        let text = analysis.file_text(file_id).unwrap();
+        let links = analysis
+            .intra_doc_links(file_id)
+            .unwrap()
+            .into_iter()
+            .map(|it| {
+                let target = it.resolved.map_or_else(|| "unresolved".to_string(), |it| it.path);
+                format!("{} {} -> {}\n", &text[it.range], it.link, target)
+            })
+            .collect::<String>();
+        expect![[r#"
+            [`Foo`] Foo -> test::Foo
+            [missing] missing -> unresolved
+            [`Bar`] Bar -> test::Bar
+            [the root](crate) crate -> test
+        "#]]
+        .assert_eq(&links);
+    }
 }
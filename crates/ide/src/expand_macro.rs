@@ -1,7 +1,8 @@
 use std::iter;
 
-use hir::Semantics;
-use ide_db::{helpers::pick_best_token, RootDatabase};
+use hir::{Origin, Semantics};
+use ide_db::{base_db::FileRange, helpers::pick_best_token, RootDatabase};
+use itertools::Itertools;
 use syntax::{ast, ted, AstNode, NodeOrToken, SyntaxKind, SyntaxKind::*, SyntaxNode, WalkEvent, T};
 
 use crate::FilePosition;
@@ -11,6 +12,29 @@ pub struct ExpandedMacro {
     pub expansion: String,
 }
 
+/// One level of a stepwise macro expansion.
+pub struct ExpansionStep {
+    /// The expansion with the macro calls of this and all previous steps
+    /// expanded.
+    pub expansion: String,
+    /// The macro calls expanded in this step.
+    pub calls: Vec<ExpandedCall>,
+}
+
+pub struct ExpandedCall {
+    pub name: String,
+    /// Where the call is in the source. A call written in the definition of
+    /// another macro maps back to the call of that macro.
+    pub range: FileRange,
+    /// The identifiers of the expansion written in the macro definition
+    /// rather than passed in by the call. Local variables among them are
+    /// hygienic, they don't clash with the ones at the call site.
+    pub def_site_idents: Vec<String>,
+}
+
+/// Recursive macros are only cut off by the limit of the expansion queue.
+const STEP_LIMIT: usize = 64;
+
 // Feature: Expand Macro Recursively
 //
 // Shows the full macro expansion of the macro at current cursor.
@@ -23,6 +47,16 @@ pub struct ExpandedMacro {
 //
 // image::https://user-images.githubusercontent.com/48062697/113020648-b3973180-917a-11eb-84a9-ecb921293dc5.gif[]
 pub(crate) fn expand_macro(db: &RootDatabase, position: FilePosition) -> Option<ExpandedMacro> {
+    expand_macro_with_depth(db, position, None)
+}
+
+/// Like [`expand_macro`], but stops after `depth` levels of nested macro calls
+/// if given. A depth of 1 expands only the macro call at `position`.
+pub(crate) fn expand_macro_with_depth(
+    db: &RootDatabase,
+    position: FilePosition,
+    depth: Option<usize>,
+) -> Option<ExpandedMacro> {
     let sema = Semantics::new(db);
     let file = sema.parse(position.file_id);
 
@@ -45,7 +79,7 @@ pub(crate) fn expand_macro(db: &RootDatabase, position: FilePosition) -> Option<
 
         if let Some(mac) = ast::MacroCall::cast(node) {
             name = Some(mac.path()?.segment()?.name_ref()?.to_string());
-            expanded = expand_macro_recur(&sema, &mac);
+            expanded = expand_macro_recur(&sema, &mac, depth);
             break;
         }
     }
@@ -57,17 +91,92 @@ pub(crate) fn expand_macro(db: &RootDatabase, position: FilePosition) -> Option<
     Some(ExpandedMacro { name: name?, expansion })
 }
 
+/// Expands the macro call at `position` one level of nested macro calls at a
+/// time, stopping when no calls are left.
+pub(crate) fn expand_macro_steps(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<Vec<ExpansionStep>> {
+    let sema = Semantics::new(db);
+    let file = sema.parse(position.file_id);
+
+    let tok = pick_best_token(file.syntax().token_at_offset(position.offset), |kind| match kind {
+        SyntaxKind::IDENT => 1,
+        _ => 0,
+    })?;
+    let mac = tok.ancestors().find_map(ast::MacroCall::cast)?;
+
+    let mut steps = Vec::new();
+    let mut calls = vec![mac.clone()];
+    while !calls.is_empty() && steps.len() < STEP_LIMIT {
+        let mut expanded_calls = Vec::new();
+        let mut next_calls = Vec::new();
+        for call in calls {
+            let expansion = match sema.expand(&call) {
+                Some(it) => it,
+                None => continue,
+            };
+            expanded_calls.push(ExpandedCall {
+                name: macro_name(&call),
+                range: sema.original_range(call.syntax()),
+                def_site_idents: def_site_idents(&sema, &expansion),
+            });
+            next_calls.extend(expansion.descendants().filter_map(ast::MacroCall::cast));
+        }
+        if expanded_calls.is_empty() {
+            break;
+        }
+        let expansion = expand_macro_recur(&sema, &mac, Some(steps.len() + 1))?;
+        steps.push(ExpansionStep {
+            expansion: insert_whitespaces(expansion),
+            calls: expanded_calls,
+        });
+        calls = next_calls;
+    }
+    Some(steps)
+}
+
+/// The expansion of the attribute macro on `item`, formatted the way the
+/// expand macro command shows it.
+pub(crate) fn expand_attr_macro(
+    sema: &Semantics<RootDatabase>,
+    item: &ast::Item,
+) -> Option<String> {
+    sema.expand_attr_macro(item).map(insert_whitespaces)
+}
+
+fn macro_name(call: &ast::MacroCall) -> String {
+    call.path()
+        .and_then(|path| path.segment()?.name_ref())
+        .map_or_else(|| "?".to_string(), |it| it.to_string())
+}
+
+fn def_site_idents(sema: &Semantics<RootDatabase>, expansion: &SyntaxNode) -> Vec<String> {
+    expansion
+        .descendants_with_tokens()
+        .filter_map(NodeOrToken::into_token)
+        .filter(|token| token.kind() == IDENT && sema.token_origin(token) == Some(Origin::Def))
+        .map(|token| token.text().to_string())
+        .unique()
+        .collect()
+}
+
 fn expand_macro_recur(
     sema: &Semantics<RootDatabase>,
     macro_call: &ast::MacroCall,
+    depth: Option<usize>,
 ) -> Option<SyntaxNode> {
     let expanded = sema.expand(macro_call)?.clone_for_update();
+    let depth = depth.map(|it| it.saturating_sub(1));
+    if depth == Some(0) {
+        return Some(expanded);
+    }
 
     let children = expanded.descendants().filter_map(ast::MacroCall::cast);
     let mut replacements = Vec::new();
 
     for child in children {
-        if let Some(new_node) = expand_macro_recur(sema, &child) {
+        if let Some(new_node) = expand_macro_recur(sema, &child, depth) {
             // check if the whole original syntax is replaced
             if expanded == *child.syntax() {
                 return Some(new_node);
@@ -155,6 +264,7 @@ fn insert_whitespaces(syn: SyntaxNode) -> String {
 #[cfg(test)]
 mod tests {
     use expect_test::{expect, Expect};
+    use stdx::format_to;
 
     use crate::fixture;
 
@@ -165,6 +275,26 @@ mod tests {
         expect.assert_eq(&actual);
     }
 
+    fn check_steps(ra_fixture: &str, expect: Expect) {
+        let (analysis, pos) = fixture::position(ra_fixture);
+        let steps = analysis.expand_macro_steps(pos).unwrap().unwrap();
+        let mut actual = String::new();
+        for (i, step) in steps.iter().enumerate() {
+            format_to!(actual, "// Step {}\n", i + 1);
+            for call in &step.calls {
+                format_to!(
+                    actual,
+                    "// {}! at {:?}, from definition: [{}]\n",
+                    call.name,
+                    call.range.range,
+                    call.def_site_idents.join(", ")
+                );
+            }
+            format_to!(actual, "{}\n", step.expansion);
+        }
+        expect.assert_eq(&actual);
+    }
+
     #[test]
     fn macro_expand_recursive_expansion() {
         check(
@@ -187,6 +317,51 @@ f$0oo!();
         );
     }
 
+    #[test]
+    fn macro_expand_with_depth() {
+        let (analysis, pos) = fixture::position(
+            r#"
+macro_rules! bar {
+    () => { fn  b() {} }
+}
+macro_rules! foo {
+    () => { bar!(); }
+}
+f$0oo!();
+"#,
+        );
+        let expansion = analysis.expand_macro_with_depth(pos, Some(1)).unwrap().unwrap();
+        expect![[r#"
+            bar!();
+        "#]]
+        .assert_eq(&expansion.expansion);
+    }
+
+    #[test]
+    fn macro_expand_steps() {
+        check_steps(
+            r#"
+macro_rules! bar {
+    ($i:ident) => { fn $i() {} }
+}
+macro_rules! foo {
+    ($x:ident) => { bar!($x); }
+}
+f$0oo!(value);
+"#,
+            expect![[r#"
+                // Step 1
+                // foo! at 107..119, from definition: [bar]
+                bar!(value);
+
+                // Step 2
+                // bar! at 107..119, from definition: []
+                fn value(){}
+
+            "#]],
+        );
+    }
+
     #[test]
     fn macro_expand_multiple_lines() {
         check(
@@ -8,7 +8,10 @@ use ide_db::{
     helpers::pick_best_token,
     RootDatabase,
 };
-use syntax::{ast, match_ast, AstNode, AstToken, SyntaxKind::*, SyntaxToken, TextRange, T};
+use syntax::{
+    algo::find_node_at_range, ast, match_ast, AstNode, AstToken, SyntaxKind::*, SyntaxToken,
+    TextRange, T,
+};
 
 use crate::{
     display::{ToNav, TryToNav},
@@ -20,6 +23,9 @@ use crate::{
 //
 // Navigates to the definition of an identifier.
 //
+// On the name of a macro call, it navigates to the arm of the `macro_rules!`
+// the call expands with, if the macro has several.
+//
 // |===
 // | Editor  | Shortcut
 //
@@ -58,7 +64,8 @@ pub(crate) fn goto_definition(
     let navs = match_ast! {
         match parent {
             ast::NameRef(name_ref) => {
-                reference_definition(&sema, Either::Right(&name_ref))
+                try_lookup_macro_rule(&sema, &name_ref)
+                    .unwrap_or_else(|| reference_definition(&sema, Either::Right(&name_ref)))
             },
             ast::Name(name) => {
                 match NameClass::classify(&sema, &name)? {
@@ -112,6 +119,68 @@ fn try_lookup_include_path(
     }])
 }
 
+/// The arm of the macro `name_ref` calls which the call expands with.
+fn try_lookup_macro_rule(
+    sema: &Semantics<RootDatabase>,
+    name_ref: &ast::NameRef,
+) -> Option<Vec<NavigationTarget>> {
+    let path = name_ref.syntax().parent().and_then(ast::PathSegment::cast)?.parent_path();
+    let call = path.syntax().parent().and_then(ast::MacroCall::cast)?;
+    let mac = match NameRefClass::classify(sema, name_ref)? {
+        NameRefClass::Definition(Definition::Macro(it)) => it,
+        _ => return None,
+    };
+    let rule = sema.macro_call_rule(&call)?;
+    let nav = mac.try_to_nav(sema.db)?;
+    let source_file = sema.parse(nav.file_id);
+    let def = find_node_at_range::<ast::Macro>(source_file.syntax(), nav.full_range)?;
+    let rules = macro_rules(&def);
+    if rules.len() < 2 {
+        return None;
+    }
+    let (matcher, full_range) = *rules.get(rule)?;
+    Some(vec![NavigationTarget { full_range, focus_range: Some(matcher), ..nav }])
+}
+
+/// The ranges of the matchers and whole arms of a macro with several arms.
+fn macro_rules(mac: &ast::Macro) -> Vec<(TextRange, TextRange)> {
+    let body = match mac {
+        ast::Macro::MacroRules(it) => it.token_tree(),
+        // `macro m($x:expr) { .. }` has a single arm.
+        ast::Macro::MacroDef(it) => {
+            let mut tts = it.syntax().children().filter_map(ast::TokenTree::cast);
+            match (tts.next(), tts.next()) {
+                (Some(body), None) => Some(body),
+                _ => None,
+            }
+        }
+    };
+    let elements = match body {
+        Some(body) => body
+            .syntax()
+            .children_with_tokens()
+            .filter(|it| !it.kind().is_trivia())
+            .collect::<Vec<_>>(),
+        None => return Vec::new(),
+    };
+    // Token trees aren't parsed, so `=>` shows up as `=` and `>`.
+    elements
+        .windows(4)
+        .filter_map(|window| match window {
+            [lhs, eq, gt, rhs]
+                if lhs.kind() == TOKEN_TREE
+                    && eq.kind() == T![=]
+                    && gt.kind() == T![>]
+                    && rhs.kind() == TOKEN_TREE =>
+            {
+                let full_range = lhs.text_range().cover(rhs.text_range());
+                Some((lhs.text_range(), full_range))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 /// finds the trait definition of an impl'd item
 /// e.g.
 /// ```rust
@@ -315,6 +384,36 @@ fn bar() {
         );
     }
 
+    #[test]
+    fn goto_def_for_macros_goes_to_matched_arm() {
+        check(
+            r#"
+macro_rules! foo {
+    () => { () };
+    ($e:expr) => { $e };
+  //^^^^^^^^^
+    ($e:expr, $($rest:tt)*) => { ($e, foo!($($rest)*)) };
+}
+fn bar() {
+    $0foo!(92);
+}
+"#,
+        );
+        check(
+            r#"
+macro_rules! foo {
+    () => { () };
+    ($e:expr) => { $e };
+    ($e:expr, $($rest:tt)*) => { ($e, foo!($($rest)*)) };
+  //^^^^^^^^^^^^^^^^^^^^^^^
+}
+fn bar() {
+    $0foo!(1, 2);
+}
+"#,
+        );
+    }
+
     #[test]
     fn goto_def_for_macros_from_other_crates() {
         check(
@@ -8,7 +8,7 @@ use ide_db::{
 };
 use syntax::{
     ast::{self, LoopBodyOwner},
-    match_ast, AstNode, SyntaxNode, SyntaxToken, TextRange, T,
+    match_ast, AstNode, SyntaxNode, SyntaxToken, TextRange, TokenAtOffset, T,
 };
 
 use crate::{display::TryToNav, references, NavigationTarget};
@@ -23,7 +23,7 @@ pub struct HighlightedRange {
 // Highlights constructs related to the thing under the cursor:
 // - if on an identifier, highlights all references to that identifier in the current file
 // - if on an `async` or `await token, highlights all yield points for that async context
-// - if on a `return` token, `?` character or `->` return type arrow, highlights all exit points for that context
+// - if on a `fn` or `return` token, `?` character, `->` return type arrow or in a tail expression, highlights all exit points for that context
 // - if on a `break`, `loop`, `while` or `for` token, highlights all break points for that loop or block context
 pub(crate) fn highlight_related(
     sema: &Semantics<RootDatabase>,
@@ -41,15 +41,18 @@ pub(crate) fn highlight_related(
         | T![loop]
         | T![for]
         | T![while]
+        | T![fn]
         | T![->] => 1,
         _ => 0,
     })?;
 
     match token.kind() {
         T![return] | T![?] | T![->] => highlight_exit_points(sema, token),
+        T![fn] => highlight_fn_exit_points(sema, token),
         T![await] | T![async] => highlight_yield_points(token),
         T![break] | T![loop] | T![for] | T![while] => highlight_break_points(token),
-        _ => highlight_references(sema, &syntax, position),
+        _ => highlight_references(sema, &syntax, position)
+            .or_else(|| highlight_tail_exit_points(sema, syntax.token_at_offset(position.offset))),
     }
 }
 
@@ -153,6 +156,31 @@ fn highlight_exit_points(
     None
 }
 
+/// Only the `fn` of a function definition, not the one of a function pointer type.
+fn highlight_fn_exit_points(
+    sema: &Semantics<RootDatabase>,
+    token: SyntaxToken,
+) -> Option<Vec<HighlightedRange>> {
+    ast::Fn::cast(token.parent()?)?;
+    let mut highlights = highlight_exit_points(sema, token.clone())?;
+    highlights.push(HighlightedRange { access: None, range: token.text_range() });
+    Some(highlights)
+}
+
+/// Tokens of a tail expression without a definition to highlight the references
+/// of, like literals and operators, highlight the exit points they belong to.
+fn highlight_tail_exit_points(
+    sema: &Semantics<RootDatabase>,
+    tokens: TokenAtOffset<SyntaxToken>,
+) -> Option<Vec<HighlightedRange>> {
+    // The cursor may be right after the expression, next to the whitespace.
+    tokens.filter(|token| !token.kind().is_trivia()).find_map(|token| {
+        let range = token.text_range();
+        let highlights = highlight_exit_points(sema, token)?;
+        highlights.iter().any(|it| it.range.contains_range(range)).then(|| highlights)
+    })
+}
+
 fn highlight_break_points(token: SyntaxToken) -> Option<Vec<HighlightedRange>> {
     fn hl(
         token: Option<SyntaxToken>,
@@ -450,6 +478,54 @@ fn foo() ->$0 u32 {
         );
     }
 
+    #[test]
+    fn test_hl_exit_points_on_fn() {
+        check(
+            r#"
+struct S;
+impl S {
+    fn$0 foo() -> u32 {
+ // ^^
+        let f = |x: u32| -> u32 { return x; };
+        if true {
+            return 0;
+         // ^^^^^^
+        }
+
+        0?;
+      // ^
+        0xDEAD_BEEF
+     // ^^^^^^^^^^^
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_on_tail_expr() {
+        check(
+            r#"
+fn foo() -> u32 {
+    if true {
+        return 0;
+     // ^^^^^^
+    }
+
+    0?;
+  // ^
+    if false {
+        1$0
+     // ^
+    } else {
+        2
+     // ^
+    }
+}
+"#,
+        );
+    }
+
     #[test]
     fn test_hl_prefer_ref_over_tail_exit() {
         check(
@@ -12,16 +12,20 @@ use ide_db::{
 use itertools::Itertools;
 use stdx::format_to;
 use syntax::{
-    algo, ast, display::fn_as_proc_macro_label, match_ast, AstNode, AstToken, Direction,
-    SyntaxKind::*, SyntaxToken, T,
+    algo, ast,
+    display::fn_as_proc_macro_label,
+    match_ast, AstNode, AstToken, Direction,
+    SyntaxKind::{self, *},
+    SyntaxNode, SyntaxToken, T,
 };
 
 use crate::{
-    display::{macro_label, TryToNav},
+    display::{computed_expr_label, macro_label, TryToNav},
     doc_links::{
         doc_attributes, extract_definitions_from_markdown, remove_links, resolve_doc_path_for_def,
         rewrite_links,
     },
+    expand_macro,
     markdown_remove::remove_markdown,
     markup::Markup,
     runnables::{runnable_fn, runnable_mod},
@@ -32,6 +36,7 @@ use crate::{
 pub struct HoverConfig {
     pub links_in_hover: bool,
     pub documentation: Option<HoverDocFormat>,
+    pub memory_layout: bool,
 }
 
 impl HoverConfig {
@@ -80,13 +85,8 @@ pub(crate) fn hover(
 ) -> Option<RangeInfo<HoverResult>> {
     let sema = hir::Semantics::new(db);
     let file = sema.parse(position.file_id).syntax().clone();
-    let token = pick_best_token(file.token_at_offset(position.offset), |kind| match kind {
-        IDENT | INT_NUMBER | LIFETIME_IDENT | T![self] | T![super] | T![crate] => 3,
-        T!['('] | T![')'] => 2,
-        kind if kind.is_trivia() => 0,
-        _ => 1,
-    })?;
-    let token = sema.descend_into_macros(token);
+    let original_token = pick_best_token(file.token_at_offset(position.offset), token_priority)?;
+    let token = sema.descend_into_macros(original_token.clone());
 
     let mut res = HoverResult::default();
 
@@ -147,6 +147,14 @@ pub(crate) fn hover(
         };
         if let Some(markup) = hover_for_definition(db, definition, famous_defs.as_ref(), config) {
             res.markup = process_markup(sema.db, definition, &markup, config);
+            if let Some(preview) = attr_macro_preview(&sema, &original_token, definition) {
+                res.markup = if config.markdown() {
+                    format!("{}\n___\n\n{}", res.markup, Markup::fenced_block(&preview))
+                } else {
+                    format!("{}\n\n{}", res.markup, preview)
+                }
+                .into();
+            }
             if let Some(action) = show_implementations_action(db, definition) {
                 res.actions.push(action);
             }
@@ -172,6 +180,40 @@ pub(crate) fn hover(
         return res;
     }
 
+    let (node, ty) = type_of_enclosing_expr_or_pat(&sema, &token)?;
+    res.markup = if config.markdown() {
+        Markup::fenced_block(&ty.display(db))
+    } else {
+        ty.display(db).to_string().into()
+    };
+    let range = sema.original_range(&node).range;
+    Some(RangeInfo::new(range, res))
+}
+
+/// Returns the inferred type of the expression at `position`, the way hover
+/// shows it for expressions which don't refer to a definition.
+pub(crate) fn type_at(db: &RootDatabase, position: FilePosition) -> Option<RangeInfo<String>> {
+    let sema = hir::Semantics::new(db);
+    let file = sema.parse(position.file_id).syntax().clone();
+    let token = pick_best_token(file.token_at_offset(position.offset), token_priority)?;
+    let token = sema.descend_into_macros(token);
+    let (node, ty) = type_of_enclosing_expr_or_pat(&sema, &token)?;
+    Some(RangeInfo::new(sema.original_range(&node).range, ty.display(db).to_string()))
+}
+
+fn token_priority(kind: SyntaxKind) -> usize {
+    match kind {
+        IDENT | INT_NUMBER | LIFETIME_IDENT | T![self] | T![super] | T![crate] => 3,
+        T!['('] | T![')'] => 2,
+        kind if kind.is_trivia() => 0,
+        _ => 1,
+    }
+}
+
+fn type_of_enclosing_expr_or_pat(
+    sema: &Semantics<RootDatabase>,
+    token: &SyntaxToken,
+) -> Option<(SyntaxNode, hir::Type)> {
     let node = token
         .ancestors()
         .take_while(|it| !ast::Item::can_cast(it.kind()))
@@ -187,14 +229,35 @@ pub(crate) fn hover(
             _ => return None,
         }
     };
+    Some((node, ty))
+}
 
-    res.markup = if config.markdown() {
-        Markup::fenced_block(&ty.display(db))
-    } else {
-        ty.display(db).to_string().into()
-    };
-    let range = sema.original_range(&node).range;
-    Some(RangeInfo::new(range, res))
+/// How many lines of an attribute macro expansion hover shows.
+const ATTR_MACRO_PREVIEW_LINES: usize = 20;
+
+/// The start of the expansion of the attribute macro `def`, if `token` is in the
+/// path of an attribute invoking it.
+fn attr_macro_preview(
+    sema: &Semantics<RootDatabase>,
+    token: &SyntaxToken,
+    def: Definition,
+) -> Option<String> {
+    match def {
+        Definition::Macro(it) if it.kind() == hir::MacroKind::Attr => (),
+        _ => return None,
+    }
+    let attr = token.ancestors().find_map(ast::Attr::cast)?;
+    if !attr.path()?.syntax().text_range().contains_range(token.text_range()) {
+        return None;
+    }
+    let item = attr.syntax().parent().and_then(ast::Item::cast)?;
+    let expansion = expand_macro::expand_attr_macro(sema, &item)?;
+    let mut lines = expansion.lines();
+    let mut preview = lines.by_ref().take(ATTR_MACRO_PREVIEW_LINES).join("\n");
+    if lines.next().is_some() {
+        preview.push_str("\n// ...");
+    }
+    Some(preview)
 }
 
 fn try_hover_for_attribute(token: &SyntaxToken) -> Option<RangeInfo<HoverResult>> {
@@ -428,11 +491,31 @@ fn hover_for_definition(
         ),
         Definition::Field(def) => label_and_docs(db, def),
         Definition::ModuleDef(it) => match it {
-            hir::ModuleDef::Module(it) => label_and_docs(db, it),
+            hir::ModuleDef::Module(it) => {
+                let (label, docs) = label_and_docs(db, it);
+                (label, crate_root_docs(db, it, docs))
+            }
             hir::ModuleDef::Function(it) => label_and_docs(db, it),
             hir::ModuleDef::Adt(it) => label_and_docs(db, it),
-            hir::ModuleDef::Variant(it) => label_and_docs(db, it),
-            hir::ModuleDef::Const(it) => label_and_docs(db, it),
+            hir::ModuleDef::Variant(it) => {
+                let (label, docs) = label_and_docs(db, it);
+                let is_fieldless = it
+                    .parent_enum(db)
+                    .variants(db)
+                    .iter()
+                    .all(|variant| variant.kind(db) == hir::StructKind::Unit);
+                match it.discriminant(db).filter(|_| is_fieldless) {
+                    Some(discriminant) => (format!("{} = {}", label, discriminant), docs),
+                    None => (label, docs),
+                }
+            }
+            hir::ModuleDef::Const(it) => {
+                let (label, docs) = label_and_docs(db, it);
+                match it.eval(db) {
+                    Some(value) => (format!("{} = {}", label, computed_expr_label(value)), docs),
+                    None => (label, docs),
+                }
+            }
             hir::ModuleDef::Static(it) => label_and_docs(db, it),
             hir::ModuleDef::Trait(it) => label_and_docs(db, it),
             hir::ModuleDef::TypeAlias(it) => label_and_docs(db, it),
@@ -449,6 +532,14 @@ fn hover_for_definition(
         Definition::GenericParam(it) => label_and_docs(db, it),
         Definition::Label(it) => return Some(Markup::fenced_block(&it.name(db))),
     };
+    let label = match def.cfg(db) {
+        Some(cfg) => format!("#[cfg({})]\n{}", cfg, label),
+        None => label,
+    };
+    let label = match definition_layout(db, &def).filter(|_| config.memory_layout) {
+        Some(layout) => format!("{}\n{}", label, layout),
+        None => label,
+    };
 
     return hover_markup(
         docs.filter(|_| config.documentation.is_some()).map(Into::into),
@@ -466,6 +557,55 @@ fn hover_for_definition(
     }
 }
 
+/// The size and alignment of a type definition or a field, and the offset of
+/// the latter, as a comment.
+fn definition_layout(db: &RootDatabase, def: &Definition) -> Option<String> {
+    let (layout, offset) = match *def {
+        Definition::ModuleDef(hir::ModuleDef::Adt(it)) => (it.layout(db)?, None),
+        Definition::ModuleDef(hir::ModuleDef::TypeAlias(it)) => (it.ty(db).layout(db)?, None),
+        Definition::Field(it) => (it.ty(db).layout(db)?, it.offset(db)),
+        _ => return None,
+    };
+    let mut res = format!("// size = {}, align = {}", layout.size, layout.align);
+    if let Some(offset) = offset {
+        format_to!(res, ", offset = {}", offset);
+    }
+    Some(res)
+}
+
+/// Crate roots describe the package they belong to before their own docs.
+fn crate_root_docs(
+    db: &RootDatabase,
+    module: hir::Module,
+    docs: Option<hir::Documentation>,
+) -> Option<hir::Documentation> {
+    if module.parent(db).is_some() {
+        return docs;
+    }
+    let crate_graph = db.crate_graph();
+    let krate = &crate_graph[module.krate().into()];
+    let package = match &krate.package {
+        Some(it) => it,
+        None => return docs,
+    };
+    let mut buf = String::new();
+    if let Some(name) = &krate.display_name {
+        format_to!(buf, "`{} {}`", name, package.version);
+    } else {
+        format_to!(buf, "`{}`", package.version);
+    }
+    if let Some(repository) = &package.repository {
+        format_to!(buf, " · [repository]({})", repository);
+    }
+    if let Some(description) = &package.description {
+        format_to!(buf, "\n\n{}", description);
+    }
+    if let Some(docs) = docs {
+        format_to!(buf, "\n\n---\n\n{}", docs.as_str());
+    }
+    Some(hir::Documentation::new(buf))
+}
+
 fn hover_for_local(it: hir::Local, db: &RootDatabase) -> Option<Markup> {
     let ty = it.ty(db);
     let ty = ty.display(db);
@@ -531,9 +671,10 @@ fn find_std_module(famous_defs: &FamousDefs, name: &str) -> Option<hir::Module>
 #[cfg(test)]
 mod tests {
     use expect_test::{expect, Expect};
-    use ide_db::base_db::FileLoader;
+    use hir::db::DefDatabase;
+    use ide_db::base_db::{fixture::ChangeFixture, FileLoader};
 
-    use crate::{fixture, hover::HoverDocFormat, HoverConfig};
+    use crate::{fixture, hover::HoverDocFormat, AnalysisHost, FilePosition, HoverConfig};
 
     fn check_hover_no_result(ra_fixture: &str) {
         let (analysis, position) = fixture::position(ra_fixture);
@@ -542,6 +683,7 @@ mod tests {
                 &HoverConfig {
                     links_in_hover: true,
                     documentation: Some(HoverDocFormat::Markdown),
+                    memory_layout: false,
                 },
                 position,
             )
@@ -556,6 +698,28 @@ mod tests {
                 &HoverConfig {
                     links_in_hover: true,
                     documentation: Some(HoverDocFormat::Markdown),
+                    memory_layout: false,
+                },
+                position,
+            )
+            .unwrap()
+            .unwrap();
+
+        let content = analysis.db.file_text(position.file_id);
+        let hovered_element = &content[hover.range];
+
+        let actual = format!("*{}*\n{}\n", hovered_element, hover.info.markup);
+        expect.assert_eq(&actual)
+    }
+
+    fn check_hover_layout(ra_fixture: &str, expect: Expect) {
+        let (analysis, position) = fixture::position(ra_fixture);
+        let hover = analysis
+            .hover(
+                &HoverConfig {
+                    links_in_hover: true,
+                    documentation: Some(HoverDocFormat::Markdown),
+                    memory_layout: true,
                 },
                 position,
             )
@@ -576,6 +740,7 @@ mod tests {
                 &HoverConfig {
                     links_in_hover: false,
                     documentation: Some(HoverDocFormat::Markdown),
+                    memory_layout: false,
                 },
                 position,
             )
@@ -596,6 +761,7 @@ mod tests {
                 &HoverConfig {
                     links_in_hover: true,
                     documentation: Some(HoverDocFormat::PlainText),
+                    memory_layout: false,
                 },
                 position,
             )
@@ -616,6 +782,7 @@ mod tests {
                 &HoverConfig {
                     links_in_hover: true,
                     documentation: Some(HoverDocFormat::Markdown),
+                    memory_layout: false,
                 },
                 position,
             )
@@ -643,6 +810,23 @@ fn main() {
         );
     }
 
+    #[test]
+    fn type_at_expression() {
+        let (analysis, position) = fixture::position(
+            r#"
+pub fn foo() -> u32 { 1 }
+
+fn main() {
+    let foo_test = (foo(), "");
+    foo_test$0;
+}
+"#,
+        );
+        let info = analysis.type_at(position).unwrap().unwrap();
+        assert_eq!(&analysis.db.file_text(position.file_id)[info.range], "foo_test");
+        assert_eq!(info.info, "(u32, &str)");
+    }
+
     #[test]
     fn hover_remove_markdown_if_configured() {
         check_hover_no_markdown(
@@ -743,6 +927,192 @@ fn main() { let foo_test = fo$0o(); }
         );
     }
 
+    #[test]
+    fn hover_shows_accumulated_cfg() {
+        check(
+            r#"
+//- /main.rs crate:main cfg:unix,feature=serde
+#[cfg(feature = "serde")]
+mod ser {
+    #[cfg(unix)]
+    pub fn to_string() {}
+}
+fn main() { ser::to_string$0(); }
+"#,
+            expect![[r#"
+                *to_string*
+
+                ```rust
+                main::ser
+                ```
+
+                ```rust
+                #[cfg(all(feature = "serde", unix))]
+                pub fn to_string()
+                ```
+            "#]],
+        );
+    }
+
+    #[test]
+    fn hover_shows_struct_layout() {
+        check_hover_layout(
+            r#"
+struct Fo$0o { a: u8, b: u32, c: u16 }
+"#,
+            expect![[r#"
+                *Foo*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                struct Foo
+                // size = 8, align = 4
+                ```
+            "#]],
+        );
+        check_hover_layout(
+            r#"
+#[repr(C)]
+struct Fo$0o { a: u8, b: u32, c: u16 }
+"#,
+            expect![[r#"
+                *Foo*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                struct Foo
+                // size = 12, align = 4
+                ```
+            "#]],
+        );
+    }
+
+    #[test]
+    fn hover_layout_ignores_invalid_packing() {
+        check_hover_layout(
+            r#"
+#[repr(packed(0))]
+struct Fo$0o { a: u8, b: u32 }
+"#,
+            expect![[r#"
+                *Foo*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                struct Foo
+                // size = 8, align = 4
+                ```
+            "#]],
+        );
+        check_hover_layout(
+            r#"
+#[repr(packed(3))]
+struct Fo$0o { a: u8, b: u32 }
+"#,
+            expect![[r#"
+                *Foo*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                struct Foo
+                // size = 8, align = 4
+                ```
+            "#]],
+        );
+    }
+
+    #[test]
+    fn hover_shows_field_offset() {
+        check_hover_layout(
+            r#"
+struct Foo { a: u8, b: u32, c$0: u16 }
+"#,
+            expect![[r#"
+                *c*
+
+                ```rust
+                test::Foo
+                ```
+
+                ```rust
+                c: u16
+                // size = 2, align = 2, offset = 4
+                ```
+            "#]],
+        );
+    }
+
+    #[test]
+    fn hover_shows_niche_optimized_enum_layout() {
+        check_hover_layout(
+            r#"
+enum Ma$0ybeRef { Nothing, Just(&'static u32) }
+"#,
+            expect![[r#"
+                *MaybeRef*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                enum MaybeRef
+                // size = 8, align = 8
+                ```
+            "#]],
+        );
+    }
+
+    #[test]
+    fn hover_shows_enum_layout_with_explicit_discriminants() {
+        check_hover_layout(
+            r#"
+enum Fo$0o { A = 1, B = 300 }
+"#,
+            expect![[r#"
+                *Foo*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                enum Foo
+                // size = 2, align = 2
+                ```
+            "#]],
+        );
+        check_hover_layout(
+            r#"
+enum Fo$0o { A = -1, B = 127 }
+"#,
+            expect![[r#"
+                *Foo*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                enum Foo
+                // size = 1, align = 1
+                ```
+            "#]],
+        );
+    }
+
     #[test]
     fn hover_shows_fn_signature_with_type_params() {
         check(
@@ -913,7 +1283,7 @@ fn main() {
                 ```
 
                 ```rust
-                const foo: u32
+                const foo: u32 = 123 (0x7B)
                 ```
             "#]],
         );
@@ -933,6 +1303,89 @@ fn main() {
         );
     }
 
+    #[test]
+    fn hover_const_eval() {
+        check(
+            r#"
+const fn bit(n: u32) -> u32 {
+    if n < 32 { 1 << n } else { 0 }
+}
+const READ: u32 = bit(0);
+const WRITE: u32 = bit(1);
+const FLAGS$0: u32 = READ | WRITE | 1 << 4;
+"#,
+            expect![[r#"
+                *FLAGS*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                const FLAGS: u32 = 19 (0x13)
+                ```
+            "#]],
+        );
+        check(
+            r#"
+enum E { A = 4, B }
+const C$0: i8 = -(E::B as i8) * 2 - 118;
+"#,
+            expect![[r#"
+                *C*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                const C: i8 = -128
+                ```
+            "#]],
+        );
+        // Overflows are compile errors, no value is shown.
+        check(
+            r#"const C$0: u8 = 255 + 1;"#,
+            expect![[r#"
+                *C*
+
+                ```rust
+                test
+                ```
+
+                ```rust
+                const C: u8
+                ```
+            "#]],
+        );
+    }
+
+    #[test]
+    fn hover_enum_discriminant() {
+        check(
+            r#"
+enum E {
+    A = -2,
+    #[cfg(never)]
+    Disabled,
+    B,
+    C$0,
+}
+"#,
+            expect![[r#"
+                *C*
+
+                ```rust
+                test::E
+                ```
+
+                ```rust
+                C = 0
+                ```
+            "#]],
+        );
+    }
+
     #[test]
     fn hover_default_generic_types() {
         check(
@@ -1008,7 +1461,7 @@ enum Option<T> {
                 ```
 
                 ```rust
-                None
+                None = 0
                 ```
 
                 ---
@@ -1196,7 +1649,7 @@ fn main() {
                 ```
 
                 ```rust
-                const C: u32
+                const C: u32 = 1
                 ```
             "#]],
         )
@@ -1492,6 +1945,51 @@ fn foo() {
         );
     }
 
+    #[test]
+    fn hover_attr_macro_shows_expansion() {
+        let mut host = AnalysisHost::default();
+        host.raw_database_mut().set_enable_proc_attr_macros(true);
+        let change_fixture = ChangeFixture::parse(
+            r#"
+#[rustc_builtin_macro]
+pub macro test($item:item) {}
+
+#[crate::te$0st]
+fn foo() {}
+"#,
+        );
+        host.apply_change(change_fixture.change);
+        let (file_id, offset) = change_fixture.file_position.unwrap();
+        let position = FilePosition { file_id, offset: offset.expect_offset() };
+        let hover = host
+            .analysis()
+            .hover(
+                &HoverConfig {
+                    links_in_hover: true,
+                    documentation: Some(HoverDocFormat::Markdown),
+                    memory_layout: false,
+                },
+                position,
+            )
+            .unwrap()
+            .unwrap();
+        expect![[r#"
+
+            ```rust
+            test
+            ```
+
+            ```rust
+            pub macro test
+            ```
+            ___
+
+            ```rust
+            fn foo(){}
+            ```"#]]
+        .assert_eq(hover.info.markup.as_str());
+    }
+
     #[test]
     fn test_hover_through_literal_string_in_builtin_macro() {
         check_hover_no_result(
@@ -3758,7 +4256,7 @@ fn foo() {
                 ```
 
                 ```rust
-                const FOO: usize
+                const FOO: usize = 3
                 ```
 
                 ---
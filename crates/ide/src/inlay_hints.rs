@@ -1,5 +1,5 @@
 use either::Either;
-use hir::{known, Callable, HirDisplay, Semantics};
+use hir::{known, Callable, HirDisplay, PathResolution, Semantics};
 use ide_db::helpers::FamousDefs;
 use ide_db::RootDatabase;
 use stdx::to_lower_snake_case;
@@ -8,13 +8,14 @@ use syntax::{
     match_ast, Direction, NodeOrToken, SmolStr, SyntaxKind, TextRange, T,
 };
 
-use crate::FileId;
+use crate::{display::computed_expr_label, FileId};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct InlayHintsConfig {
     pub type_hints: bool,
     pub parameter_hints: bool,
     pub chaining_hints: bool,
+    pub const_value_hints: bool,
     pub max_length: Option<usize>,
 }
 
@@ -23,6 +24,7 @@ pub enum InlayKind {
     TypeHint,
     ParameterHint,
     ChainingHint,
+    ConstValueHint,
 }
 
 #[derive(Debug)]
@@ -42,6 +44,7 @@ pub struct InlayHint {
 // * types of local variables
 // * names of function arguments
 // * types of chained expressions
+// * values of constants and of array lengths given by constants (disabled by default)
 //
 // **Note:** VS Code does not have native support for inlay hints https://github.com/microsoft/vscode/issues/16221[yet] and the hints are implemented using decorations.
 // This approach has limitations, the caret movement and bracket highlighting near the edges of the hint may be weird:
@@ -74,6 +77,13 @@ pub(crate) fn inlay_hints(
                 ast::CallExpr(it) => { get_param_name_hints(&mut res, &sema, config, ast::Expr::from(it)); },
                 ast::MethodCallExpr(it) => { get_param_name_hints(&mut res, &sema, config, ast::Expr::from(it)); },
                 ast::IdentPat(it) => { get_bind_pat_hints(&mut res, &sema, config, it); },
+                ast::Const(it) => { get_const_value_hints(&mut res, &sema, config, it.body(), Some(it)); },
+                ast::ArrayType(it) => { get_const_value_hints(&mut res, &sema, config, it.expr(), None); },
+                ast::ArrayExpr(it) => {
+                    if let ast::ArrayExprKind::Repeat { repeat, .. } = it.kind() {
+                        get_const_value_hints(&mut res, &sema, config, repeat, None);
+                    }
+                },
                 _ => (),
             }
         }
@@ -81,6 +91,49 @@ pub(crate) fn inlay_hints(
     res
 }
 
+/// Shows the value of the initializer of `konst`, or of `expr` when it's a
+/// path to a constant, like the length of `[u8; LEN]`.
+fn get_const_value_hints(
+    acc: &mut Vec<InlayHint>,
+    sema: &Semantics<RootDatabase>,
+    config: &InlayHintsConfig,
+    expr: Option<ast::Expr>,
+    konst: Option<ast::Const>,
+) -> Option<()> {
+    if !config.const_value_hints {
+        return None;
+    }
+    let expr = expr?;
+    // The value of a literal is already in plain sight.
+    if matches!(expr, ast::Expr::Literal(_)) {
+        return None;
+    }
+    let konst = match konst {
+        Some(it) => sema.to_def(&it)?,
+        None => {
+            let path = match &expr {
+                ast::Expr::PathExpr(it) => it.path()?,
+                _ => return None,
+            };
+            // Path expressions outside of bodies, like the length of an array
+            // type in a struct, are only resolved through the scope.
+            let resolution = sema
+                .resolve_path(&path)
+                .or_else(|| sema.scope(path.syntax()).speculative_resolve(&path));
+            match resolution? {
+                PathResolution::Def(hir::ModuleDef::Const(it)) => it,
+                _ => return None,
+            }
+        }
+    };
+    acc.push(InlayHint {
+        range: expr.syntax().text_range(),
+        kind: InlayKind::ConstValueHint,
+        label: computed_expr_label(konst.eval(sema.db)?).into(),
+    });
+    Some(())
+}
+
 fn get_chaining_hints(
     acc: &mut Vec<InlayHint>,
     sema: &Semantics<RootDatabase>,
@@ -442,6 +495,7 @@ mod tests {
         type_hints: true,
         parameter_hints: true,
         chaining_hints: true,
+        const_value_hints: false,
         max_length: None,
     };
 
@@ -455,6 +509,7 @@ mod tests {
                 parameter_hints: true,
                 type_hints: false,
                 chaining_hints: false,
+                const_value_hints: false,
                 max_length: None,
             },
             ra_fixture,
@@ -467,6 +522,7 @@ mod tests {
                 parameter_hints: false,
                 type_hints: true,
                 chaining_hints: false,
+                const_value_hints: false,
                 max_length: None,
             },
             ra_fixture,
@@ -479,6 +535,7 @@ mod tests {
                 parameter_hints: false,
                 type_hints: false,
                 chaining_hints: true,
+                const_value_hints: false,
                 max_length: None,
             },
             ra_fixture,
@@ -507,6 +564,7 @@ mod tests {
                 type_hints: false,
                 parameter_hints: false,
                 chaining_hints: false,
+                const_value_hints: false,
                 max_length: None,
             },
             r#"
@@ -1198,6 +1256,7 @@ fn main() {
                 parameter_hints: false,
                 type_hints: false,
                 chaining_hints: true,
+                const_value_hints: false,
                 max_length: None,
             },
             r#"
@@ -1254,6 +1313,7 @@ fn main() {
                 parameter_hints: false,
                 type_hints: false,
                 chaining_hints: true,
+                const_value_hints: false,
                 max_length: None,
             },
             r#"
@@ -1298,6 +1358,7 @@ fn main() {
                 parameter_hints: false,
                 type_hints: false,
                 chaining_hints: true,
+                const_value_hints: false,
                 max_length: None,
             },
             r#"
@@ -1343,6 +1404,7 @@ fn main() {
                 parameter_hints: false,
                 type_hints: false,
                 chaining_hints: true,
+                const_value_hints: false,
                 max_length: None,
             },
             r#"
@@ -1392,4 +1454,35 @@ fn main() {
             "#]],
         );
     }
+
+    // Const value hint tests
+
+    #[test]
+    fn const_value_hints() {
+        check_with_config(
+            InlayHintsConfig {
+                parameter_hints: false,
+                type_hints: false,
+                chaining_hints: false,
+                const_value_hints: true,
+                max_length: None,
+            },
+            r#"
+const BASE: usize = 4;
+const LEN: usize = BASE * 8;
+                 //^^^^^^^^ 32 (0x20)
+const ONE: usize = 1;
+struct S {
+    buf: [u8; LEN],
+            //^^^ 32 (0x20)
+    literal: [u8; 16],
+}
+fn f(n: usize) {
+    let _ = [0; LEN];
+              //^^^ 32 (0x20)
+    let _ = [0u8; ONE + 1];
+}
+"#,
+        );
+    }
 }
@@ -23,7 +23,9 @@ mod prime_caches;
 mod display;
 
 mod annotations;
+mod call_graph;
 mod call_hierarchy;
+mod cfg_usages;
 mod doc_links;
 mod highlight_related;
 mod expand_macro;
@@ -40,15 +42,18 @@ mod inlay_hints;
 mod join_lines;
 mod markdown_remove;
 mod matching_brace;
+mod moniker;
 mod move_item;
 mod parent_module;
 mod references;
 mod rename;
 mod runnables;
+mod snapshot_builder;
 mod ssr;
 mod status;
 mod syntax_highlighting;
 mod syntax_tree;
+mod type_hierarchy;
 mod typing;
 mod view_crate_graph;
 mod view_hir;
@@ -56,24 +61,27 @@ mod view_item_tree;
 
 use std::sync::Arc;
 
-use cfg::CfgOptions;
 use ide_db::{
     base_db::{
         salsa::{self, ParallelDatabase},
-        Env, FileLoader, FileSet, SourceDatabase, VfsPath,
+        FileLoader, FileSet, SourceDatabase, VfsPath,
     },
     symbol_index::{self, FileSymbol},
     LineIndexDatabase,
 };
+use rustc_hash::FxHashMap;
 use syntax::SourceFile;
 
 use crate::display::ToNav;
 
 pub use crate::{
     annotations::{Annotation, AnnotationConfig, AnnotationKind},
+    call_graph::{CallGraph, CallGraphFunction},
     call_hierarchy::CallItem,
+    cfg_usages::{CfgUsage, CfgUsageKind, CrateCfgUsages},
     display::navigation_target::NavigationTarget,
-    expand_macro::ExpandedMacro,
+    doc_links::{IntraDocLink, ResolvedDocLink},
+    expand_macro::{ExpandedCall, ExpandedMacro, ExpansionStep},
     file_structure::{StructureNode, StructureNodeKind},
     folding_ranges::{Fold, FoldKind},
     highlight_related::HighlightedRange,
@@ -81,16 +89,20 @@ pub use crate::{
     inlay_hints::{InlayHint, InlayHintsConfig, InlayKind},
     join_lines::JoinLinesConfig,
     markup::Markup,
+    moniker::{moniker_identifier, MonikerIdentifier, MonikerKind, MonikerResult},
     move_item::Direction,
     prime_caches::PrimeCachesProgress,
     references::ReferenceSearchResult,
     rename::RenameError,
     runnables::{Runnable, RunnableKind, TestId},
+    snapshot_builder::SnapshotBuilder,
     syntax_highlighting::{
         tags::{Highlight, HlMod, HlMods, HlOperator, HlPunct, HlTag},
         HlRange,
     },
+    view_crate_graph::{CrateGraphConfig, CrateGraphEdge, CrateGraphNode, ExportedCrateGraph},
 };
+pub use cfg::CfgOptions;
 pub use hir::{Documentation, Semantics};
 pub use ide_assists::{
     Assist, AssistConfig, AssistId, AssistKind, AssistResolveStrategy, SingleResolve,
@@ -100,8 +112,8 @@ pub use ide_completion::{
 };
 pub use ide_db::{
     base_db::{
-        Cancelled, Change, CrateGraph, CrateId, Edition, FileId, FilePosition, FileRange,
-        SourceRoot, SourceRootId,
+        Cancelled, Change, CrateGraph, CrateId, CyclicDependenciesError, Edition, Env, FileId,
+        FilePosition, FileRange, SourceRoot, SourceRootId,
     },
     call_info::CallInfo,
     label::Label,
@@ -109,7 +121,7 @@ pub use ide_db::{
     search::{ReferenceAccess, SearchScope},
     source_change::{FileSystemEdit, SourceChange},
     symbol_index::Query,
-    RootDatabase, SymbolKind,
+    KeyMemoryUsage, MemoryUsageReport, QueryMemoryUsage, RootDatabase, SymbolKind,
 };
 pub use ide_diagnostics::{Diagnostic, DiagnosticsConfig, Severity};
 pub use ide_ssr::SsrError;
@@ -162,8 +174,8 @@ impl AnalysisHost {
         self.db.collect_garbage();
     }
     /// NB: this clears the database
-    pub fn per_query_memory_usage(&mut self) -> Vec<(String, profile::Bytes)> {
-        self.db.per_query_memory_usage()
+    pub fn per_query_memory_usage(&mut self, n_largest_keys: usize) -> MemoryUsageReport {
+        self.db.per_query_memory_usage(n_largest_keys)
     }
     pub fn request_cancellation(&mut self) {
         self.db.request_cancellation();
@@ -235,11 +247,13 @@ impl Analysis {
         self.with_db(|db| status::status(&*db, file_id))
     }
 
-    pub fn prime_caches<F>(&self, cb: F) -> Cancellable<()>
+    /// Computes the def maps and import maps of all crates, indexing
+    /// independent crates on up to `num_worker_threads` threads.
+    pub fn prime_caches<F>(&self, num_worker_threads: usize, cb: F) -> Cancellable<()>
     where
         F: Fn(PrimeCachesProgress) + Sync + std::panic::UnwindSafe,
     {
-        self.with_db(move |db| prime_caches::prime_caches(db, &cb))
+        self.with_db(move |db| prime_caches::prime_caches(db, num_worker_threads, &cb))
     }
 
     /// Gets the text of the source file.
@@ -293,19 +307,53 @@ impl Analysis {
         self.with_db(|db| view_hir::view_hir(db, position))
     }
 
+    /// Returns the paths and the HIR of the functions with the given path.
+    pub fn view_hir_of_path(&self, path: &str) -> Cancellable<Vec<(String, String)>> {
+        self.with_db(|db| view_hir::view_hir_of_path(db, path))
+    }
+
     pub fn view_item_tree(&self, file_id: FileId) -> Cancellable<String> {
         self.with_db(|db| view_item_tree::view_item_tree(db, file_id))
     }
 
+    /// Computes the function call graph of `crates`.
+    pub fn call_graph(&self, crates: &[CrateId]) -> Cancellable<CallGraph> {
+        self.with_db(|db| call_graph::call_graph(db, crates))
+    }
+
     /// Renders the crate graph to GraphViz "dot" syntax.
     pub fn view_crate_graph(&self, full: bool) -> Cancellable<Result<String, String>> {
         self.with_db(|db| view_crate_graph::view_crate_graph(db, full))
     }
 
+    /// Returns the loaded crate graph, filtered and merged according to `config`.
+    pub fn export_crate_graph(&self, config: &CrateGraphConfig) -> Cancellable<ExportedCrateGraph> {
+        self.with_db(|db| view_crate_graph::export_crate_graph(db, config))
+    }
+
     pub fn expand_macro(&self, position: FilePosition) -> Cancellable<Option<ExpandedMacro>> {
         self.with_db(|db| expand_macro::expand_macro(db, position))
     }
 
+    /// Expands the macro at `position`, following at most `depth` levels of
+    /// nested macro calls.
+    pub fn expand_macro_with_depth(
+        &self,
+        position: FilePosition,
+        depth: Option<usize>,
+    ) -> Cancellable<Option<ExpandedMacro>> {
+        self.with_db(|db| expand_macro::expand_macro_with_depth(db, position, depth))
+    }
+
+    /// Expands the macro at `position` one level of nested macro calls at a
+    /// time, with the calls expanded in each step.
+    pub fn expand_macro_steps(
+        &self,
+        position: FilePosition,
+    ) -> Cancellable<Option<Vec<ExpansionStep>>> {
+        self.with_db(|db| expand_macro::expand_macro_steps(db, position))
+    }
+
     /// Returns an edit to remove all newlines in the range, cleaning up minor
     /// stuff like trailing commas.
     pub fn join_lines(&self, config: &JoinLinesConfig, frange: FileRange) -> Cancellable<TextEdit> {
@@ -400,6 +448,14 @@ impl Analysis {
         self.with_db(|db| goto_type_definition::goto_type_definition(db, position))
     }
 
+    /// Returns the stable identifiers of the definitions at `position`.
+    pub fn moniker(
+        &self,
+        position: FilePosition,
+    ) -> Cancellable<Option<RangeInfo<Vec<MonikerResult>>>> {
+        self.with_db(|db| moniker::moniker(db, position))
+    }
+
     /// Finds all usages of the reference at point.
     pub fn find_all_refs(
         &self,
@@ -423,6 +479,11 @@ impl Analysis {
         self.with_db(|db| hover::hover(db, position, config))
     }
 
+    /// Returns the inferred type of the expression or pattern at position.
+    pub fn type_at(&self, position: FilePosition) -> Cancellable<Option<RangeInfo<String>>> {
+        self.with_db(|db| hover::type_at(db, position))
+    }
+
     /// Return URL(s) for the documentation of the symbol under the cursor.
     pub fn external_docs(
         &self,
@@ -431,6 +492,17 @@ impl Analysis {
         self.with_db(|db| doc_links::external_docs(db, &position))
     }
 
+    /// Finds and resolves the intra-doc links in the doc comments of a file.
+    pub fn intra_doc_links(&self, file_id: FileId) -> Cancellable<Vec<IntraDocLink>> {
+        self.with_db(|db| doc_links::intra_doc_links(db, file_id))
+    }
+
+    /// Returns the cfgs of the workspace crates mentioning `feature`, grouped
+    /// by crate.
+    pub fn cfg_feature_usages(&self, feature: &str) -> Cancellable<Vec<CrateCfgUsages>> {
+        self.with_db(|db| cfg_usages::cfg_feature_usages(db, feature))
+    }
+
     /// Computes parameter information for the given call expression.
     pub fn call_info(&self, position: FilePosition) -> Cancellable<Option<CallInfo>> {
         self.with_db(|db| ide_db::call_info::call_info(db, position))
@@ -454,6 +526,24 @@ impl Analysis {
         self.with_db(|db| call_hierarchy::outgoing_calls(db, position))
     }
 
+    /// Computes type hierarchy candidates for the given file position.
+    pub fn type_hierarchy(
+        &self,
+        position: FilePosition,
+    ) -> Cancellable<Option<RangeInfo<Vec<NavigationTarget>>>> {
+        self.with_db(|db| type_hierarchy::type_hierarchy(db, position))
+    }
+
+    /// Computes the supertypes of the type or trait at the given file position.
+    pub fn supertypes(&self, position: FilePosition) -> Cancellable<Option<Vec<NavigationTarget>>> {
+        self.with_db(|db| type_hierarchy::supertypes(db, position))
+    }
+
+    /// Computes the subtypes of the type or trait at the given file position.
+    pub fn subtypes(&self, position: FilePosition) -> Cancellable<Option<Vec<NavigationTarget>>> {
+        self.with_db(|db| type_hierarchy::subtypes(db, position))
+    }
+
     /// Returns a `mod name;` declaration which created the current module.
     pub fn parent_module(&self, position: FilePosition) -> Cancellable<Vec<NavigationTarget>> {
         self.with_db(|db| parent_module::parent_module(db, position))
@@ -464,6 +554,26 @@ impl Analysis {
         self.with_db(|db| parent_module::crate_for(db, file_id))
     }
 
+    /// Returns the non-default targets the file is compiled for, which can be
+    /// picked with [`Change::set_active_targets`].
+    pub fn file_targets(&self, file_id: FileId) -> Cancellable<Vec<String>> {
+        use ide_db::base_db::SourceDatabaseExt;
+        self.with_db(|db| {
+            let graph = db.crate_graph();
+            let crates = db.source_root_crates(db.file_source_root(file_id));
+            let mut res =
+                crates.iter().filter_map(|&krate| graph[krate].target.clone()).collect::<Vec<_>>();
+            res.sort();
+            res.dedup();
+            res
+        })
+    }
+
+    /// Returns the target picked for the file, `None` for the default one.
+    pub fn active_target(&self, file_id: FileId) -> Cancellable<Option<String>> {
+        self.with_db(|db| db.active_targets().get(&file_id).cloned())
+    }
+
     /// Returns the edition of the given crate.
     pub fn crate_edition(&self, crate_id: CrateId) -> Cancellable<Edition> {
         self.with_db(|db| db.crate_graph()[crate_id].edition)
@@ -513,6 +623,19 @@ impl Analysis {
         self.with_db(|db| syntax_highlighting::highlight_as_html(db, file_id, rainbow))
     }
 
+    /// Like [`Analysis::highlight_as_html`], but links references to their
+    /// definitions in the pages listed in `file_urls`.
+    pub fn highlight_as_linked_html(
+        &self,
+        file_id: FileId,
+        rainbow: bool,
+        file_urls: &FxHashMap<FileId, String>,
+    ) -> Cancellable<String> {
+        self.with_db(|db| {
+            syntax_highlighting::highlight_as_linked_html(db, file_id, rainbow, file_urls)
+        })
+    }
+
     /// Computes completions at the given position.
     pub fn completions(
         &self,
@@ -0,0 +1,263 @@
+//! Stable, cross-crate identifiers of definitions ("monikers"), which let
+//! code-intel tools link navigation across repositories and published crates.
+//!
+//! The identifiers are the global symbols of the SCIP exporter, so the ones
+//! reported to the editor match the ones in an index.
+
+use std::fmt;
+
+use hir::{AsAssocItem, AssocItemContainer, ModuleDef, Semantics};
+use ide_db::{
+    base_db::SourceDatabase,
+    defs::{Definition, NameClass, NameRefClass},
+    RootDatabase,
+};
+use syntax::{ast, match_ast, AstNode, SyntaxKind::*, T};
+
+use crate::{FilePosition, RangeInfo};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonikerResult {
+    pub identifier: MonikerIdentifier,
+    pub kind: MonikerKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonikerKind {
+    /// The definition comes from another crate.
+    Import,
+    /// The definition is in the crate of the file.
+    Export,
+}
+
+/// Identifies a definition by the package it is in and its path in there.
+/// Displays as a SCIP symbol, like `rust-analyzer cargo foo 1.0.0 bar/Baz#quux().`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MonikerIdentifier {
+    pub package: String,
+    pub version: Option<String>,
+    /// SCIP descriptors of the path to the definition, like `bar/Baz#quux().`.
+    pub descriptors: String,
+}
+
+impl fmt::Display for MonikerIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rust-analyzer cargo {} {} {}",
+            self.package.replace(' ', "  "),
+            self.version.as_deref().map_or_else(|| ".".to_string(), |it| it.replace(' ', "  ")),
+            self.descriptors
+        )
+    }
+}
+
+// Feature: Moniker
+//
+// Reports a stable identifier of the definition at the cursor, made of its
+// crate name, version and path, for tools that navigate across repositories.
+pub(crate) fn moniker(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<MonikerResult>>> {
+    let sema = Semantics::new(db);
+    let file = sema.parse(position.file_id).syntax().clone();
+    let current_crate = sema.to_module_def(position.file_id)?.krate();
+    let original_token = file
+        .token_at_offset(position.offset)
+        .find(|it| matches!(it.kind(), IDENT | INT_NUMBER | T![self] | T![super] | T![crate]))?;
+    let token = sema.descend_into_macros(original_token.clone());
+    let parent = token.parent()?;
+    let defs = match_ast! {
+        match parent {
+            ast::NameRef(name_ref) => match NameRefClass::classify(&sema, &name_ref)? {
+                NameRefClass::Definition(it) => vec![it],
+                NameRefClass::FieldShorthand { local_ref, field_ref } => {
+                    vec![Definition::Local(local_ref), Definition::Field(field_ref)]
+                }
+            },
+            ast::Name(name) => match NameClass::classify(&sema, &name)? {
+                NameClass::Definition(it) | NameClass::ConstReference(it) => vec![it],
+                NameClass::PatFieldShorthand { local_def, field_ref } => {
+                    vec![Definition::Local(local_def), Definition::Field(field_ref)]
+                }
+            },
+            _ => return None,
+        }
+    };
+    let monikers = defs
+        .into_iter()
+        .filter_map(|def| {
+            let identifier = moniker_identifier(db, def)?;
+            let kind = if def_crate(db, def) == Some(current_crate) {
+                MonikerKind::Export
+            } else {
+                MonikerKind::Import
+            };
+            Some(MonikerResult { identifier, kind })
+        })
+        .collect::<Vec<_>>();
+    if monikers.is_empty() {
+        return None;
+    }
+    Some(RangeInfo::new(original_token.text_range(), monikers))
+}
+
+fn def_crate(db: &RootDatabase, def: Definition) -> Option<hir::Crate> {
+    match def {
+        Definition::ModuleDef(ModuleDef::Module(it)) => Some(it.krate()),
+        _ => def.module(db).map(|it| it.krate()),
+    }
+}
+
+/// The identifier of `def`, if it can be named from other crates. Locals and
+/// generic parameters don't have one.
+pub fn moniker_identifier(db: &RootDatabase, def: Definition) -> Option<MonikerIdentifier> {
+    let module = match def {
+        Definition::ModuleDef(ModuleDef::Module(it)) => it,
+        _ => def.module(db)?,
+    };
+    let krate = module.krate();
+    let package = krate.display_name(db)?.to_string();
+    let version = db.crate_graph()[krate.into()].package.as_ref().map(|it| it.version.clone());
+
+    let mut descriptors = String::new();
+    for module in module.path_to_root(db).into_iter().rev() {
+        if let Some(name) = module.name(db) {
+            descriptors.push_str(&format!("{}/", escape(&name.to_string())));
+        }
+    }
+    let name = |name: hir::Name| escape(&name.to_string());
+    // Associated items are nested in their trait or self type.
+    let container = |def: ModuleDef| {
+        let assoc = def.as_assoc_item(db)?;
+        let container_name = match assoc.container(db) {
+            AssocItemContainer::Trait(it) => it.name(db),
+            AssocItemContainer::Impl(it) => it.self_ty(db).as_adt()?.name(db),
+        };
+        Some(format!("{}#", escape(&container_name.to_string())))
+    };
+    match def {
+        Definition::ModuleDef(ModuleDef::Module(_)) => {}
+        Definition::ModuleDef(ModuleDef::BuiltinType(_)) => return None,
+        Definition::ModuleDef(ModuleDef::Function(it)) => {
+            descriptors.push_str(&container(it.into()).unwrap_or_default());
+            descriptors.push_str(&format!("{}().", name(it.name(db))));
+        }
+        Definition::ModuleDef(it @ ModuleDef::Const(_)) => {
+            descriptors.push_str(&container(it).unwrap_or_default());
+            descriptors.push_str(&format!("{}.", name(it.name(db)?)));
+        }
+        Definition::ModuleDef(it @ ModuleDef::TypeAlias(_)) => {
+            descriptors.push_str(&container(it).unwrap_or_default());
+            descriptors.push_str(&format!("{}#", name(it.name(db)?)));
+        }
+        Definition::ModuleDef(ModuleDef::Variant(it)) => {
+            descriptors.push_str(&format!(
+                "{}#{}.",
+                name(it.parent_enum(db).name(db)),
+                name(it.name(db))
+            ));
+        }
+        Definition::ModuleDef(it @ ModuleDef::Static(_)) => {
+            descriptors.push_str(&format!("{}.", name(it.name(db)?)));
+        }
+        Definition::ModuleDef(it) => descriptors.push_str(&format!("{}#", name(it.name(db)?))),
+        Definition::Field(it) => {
+            descriptors.push_str(&format!(
+                "{}#{}.",
+                name(it.parent_def(db).name(db)),
+                name(it.name(db))
+            ));
+        }
+        Definition::Macro(it) => descriptors.push_str(&format!("{}!", name(it.name(db)?))),
+        Definition::SelfType(_)
+        | Definition::Local(_)
+        | Definition::GenericParam(_)
+        | Definition::Label(_) => return None,
+    }
+    if descriptors.is_empty() {
+        // The crate root module.
+        descriptors.push_str(&format!("{}/", escape(&package)));
+    }
+
+    Some(MonikerIdentifier { package, version, descriptors })
+}
+
+/// Wraps `name` in backticks unless it is a simple identifier.
+fn escape(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '$')) {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixture;
+
+    use super::{escape, MonikerKind};
+
+    #[track_caller]
+    fn check(ra_fixture: &str, identifier: &str, kind: MonikerKind) {
+        let (analysis, position) = fixture::position(ra_fixture);
+        let monikers = analysis.moniker(position).unwrap().expect("no moniker found").info;
+        assert_eq!(monikers.len(), 1, "{:?}", monikers);
+        assert_eq!(monikers[0].identifier.to_string(), identifier);
+        assert_eq!(monikers[0].kind, kind);
+    }
+
+    #[test]
+    fn moniker_of_definition() {
+        check(
+            r#"
+//- /lib.rs crate:main
+mod foo {
+    pub struct Bar;
+    impl Bar {
+        pub fn ne$0w() -> Bar { Bar }
+    }
+}
+"#,
+            "rust-analyzer cargo main . foo/Bar#new().",
+            MonikerKind::Export,
+        );
+    }
+
+    #[test]
+    fn moniker_of_reference_to_dependency() {
+        check(
+            r#"
+//- /main.rs crate:main deps:dep
+fn main() {
+    let _ = dep::module::Ty$0pe { field: 0 };
+}
+//- /lib.rs crate:dep
+pub mod module {
+    pub struct Type { pub field: u32 }
+}
+"#,
+            "rust-analyzer cargo dep . module/Type#",
+            MonikerKind::Import,
+        );
+    }
+
+    #[test]
+    fn no_moniker_for_locals() {
+        let (analysis, position) = fixture::position(
+            r#"
+fn main() {
+    let x$0 = 92;
+}
+"#,
+        );
+        assert!(analysis.moniker(position).unwrap().is_none());
+    }
+
+    #[test]
+    fn escapes_descriptors() {
+        assert_eq!(escape("foo_bar"), "foo_bar");
+        assert_eq!(escape("r#fn"), "`r#fn`");
+    }
+}
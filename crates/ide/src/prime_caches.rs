@@ -3,8 +3,14 @@
 //! request takes longer to compute. This modules implemented prepopulation of
 //! various caches, it's not really advanced at the moment.
 
+use std::{
+    panic,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
 use hir::db::DefDatabase;
-use ide_db::base_db::SourceDatabase;
+use ide_db::base_db::{salsa::ParallelDatabase, CrateId, SourceDatabase};
 
 use crate::RootDatabase;
 
@@ -21,27 +27,84 @@ pub enum PrimeCachesProgress {
     Finished,
 }
 
-pub(crate) fn prime_caches(db: &RootDatabase, cb: &(dyn Fn(PrimeCachesProgress) + Sync)) {
+enum WorkerEvent {
+    Started(String),
+    Done,
+}
+
+/// Computes the def maps and import maps of all crates.
+///
+/// Crates are processed layer by layer (see
+/// [`ide_db::base_db::CrateGraph::crates_in_topological_layers`]), so that
+/// the crates of a layer can be indexed on up to `num_worker_threads` threads,
+/// each with its own database snapshot. If a worker is cancelled, the
+/// cancellation is re-raised on the calling thread once the layer is drained.
+pub(crate) fn prime_caches(
+    db: &RootDatabase,
+    num_worker_threads: usize,
+    cb: &(dyn Fn(PrimeCachesProgress) + Sync),
+) {
     let _p = profile::span("prime_caches");
     let graph = db.crate_graph();
-    let topo = &graph.crates_in_topological_order();
+    let layers = graph.crates_in_topological_layers();
+    let n_total = layers.iter().map(|layer| layer.len()).sum();
 
     cb(PrimeCachesProgress::Started);
     // Take care to emit the finish signal even when the computation is canceled.
     let _d = stdx::defer(|| cb(PrimeCachesProgress::Finished));
 
-    // FIXME: This would be easy to parallelize, since it's in the ideal ordering for that.
-    // Unfortunately rayon prevents panics from propagation out of a `scope`, which breaks
-    // cancellation, so we cannot use rayon.
-    for (i, &crate_id) in topo.iter().enumerate() {
-        let crate_name = graph[crate_id].display_name.as_deref().unwrap_or_default().to_string();
-
-        cb(PrimeCachesProgress::StartedOnCrate {
-            on_crate: crate_name,
-            n_done: i,
-            n_total: topo.len(),
-        });
-        db.crate_def_map(crate_id);
-        db.import_map(crate_id);
+    let mut n_done = 0;
+    for layer in layers {
+        let queue: Vec<(CrateId, String)> = layer
+            .into_iter()
+            .map(|crate_id| {
+                let name = graph[crate_id].display_name.as_deref().unwrap_or_default().to_string();
+                (crate_id, name)
+            })
+            .collect();
+        let n_workers = num_worker_threads.max(1).min(queue.len());
+        let queue = Arc::new(Mutex::new(queue));
+        let (sender, receiver) = mpsc::channel();
+
+        // Rayon doesn't propagate panics out of a `scope` faithfully, which breaks
+        // cancellation, so we manage the worker threads ourselves.
+        let workers: Vec<_> = (0..n_workers)
+            .map(|_| {
+                let snap = db.snapshot();
+                let queue = queue.clone();
+                let sender = sender.clone();
+                thread::spawn(move || loop {
+                    let next = queue.lock().unwrap().pop();
+                    let (crate_id, name) = match next {
+                        Some(it) => it,
+                        None => break,
+                    };
+                    sender.send(WorkerEvent::Started(name)).unwrap();
+                    snap.crate_def_map(crate_id);
+                    snap.import_map(crate_id);
+                    sender.send(WorkerEvent::Done).unwrap();
+                })
+            })
+            .collect();
+        drop(sender);
+
+        for event in receiver {
+            match event {
+                WorkerEvent::Started(on_crate) => {
+                    cb(PrimeCachesProgress::StartedOnCrate { on_crate, n_done, n_total })
+                }
+                WorkerEvent::Done => n_done += 1,
+            }
+        }
+
+        let mut panic = None;
+        for worker in workers {
+            if let Err(payload) = worker.join() {
+                panic.get_or_insert(payload);
+            }
+        }
+        if let Some(payload) = panic {
+            panic::resume_unwind(payload);
+        }
     }
 }
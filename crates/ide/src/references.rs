@@ -29,6 +29,9 @@ use crate::{display::TryToNav, FilePosition, NavigationTarget};
 pub struct ReferenceSearchResult {
     pub declaration: Option<Declaration>,
     pub references: FxHashMap<FileId, Vec<(TextRange, Option<ReferenceAccess>)>>,
+    /// References with the name of the definition in code disabled by a
+    /// `#[cfg]`, which can't be resolved.
+    pub inactive_references: FxHashMap<FileId, Vec<TextRange>>,
 }
 
 #[derive(Debug, Clone)]
@@ -69,7 +72,8 @@ pub(crate) fn find_all_refs(
         find_def(sema, &syntax, position.offset)?
     };
 
-    let mut usages = def.usages(sema).set_scope(search_scope).include_self_refs().all();
+    let mut usages =
+        def.usages(sema).set_scope(search_scope).include_self_refs().include_inactive_code().all();
     let declaration = match def {
         Definition::ModuleDef(hir::ModuleDef::Module(module)) => {
             Some(NavigationTarget::from_module_to_decl(sema.db, module))
@@ -84,14 +88,19 @@ pub(crate) fn find_all_refs(
         retain_adt_literal_usages(&mut usages, def, sema);
     }
 
-    let references = usages
-        .into_iter()
-        .map(|(file_id, refs)| {
-            (file_id, refs.into_iter().map(|file_ref| (file_ref.range, file_ref.access)).collect())
-        })
-        .collect();
+    let mut references = FxHashMap::<_, Vec<_>>::default();
+    let mut inactive_references = FxHashMap::<_, Vec<_>>::default();
+    for (file_id, refs) in usages {
+        for file_ref in refs {
+            if file_ref.inactive {
+                inactive_references.entry(file_id).or_default().push(file_ref.range);
+            } else {
+                references.entry(file_id).or_default().push((file_ref.range, file_ref.access));
+            }
+        }
+    }
 
-    Some(ReferenceSearchResult { declaration, references })
+    Some(ReferenceSearchResult { declaration, references, inactive_references })
 }
 
 pub(crate) fn find_def(
@@ -600,6 +609,25 @@ fn main(s: Foo) {
         );
     }
 
+    #[test]
+    fn test_find_all_refs_in_inactive_code() {
+        check(
+            r#"
+fn foo$0() {}
+#[cfg(not(windows))]
+fn active() { foo(); }
+#[cfg(windows)]
+fn inactive() { foo(); }
+"#,
+            expect![[r#"
+                foo Function FileId(0) 0..11 3..6
+
+                FileId(0) 47..50
+                FileId(0) 88..91 inactive
+            "#]],
+        );
+    }
+
     #[test]
     fn test_find_all_refs_impl_item_name() {
         check(
@@ -1085,6 +1113,11 @@ impl Foo {
                 actual += "\n";
             }
         }
+        for (file_id, references) in refs.inactive_references {
+            for range in references {
+                format_to!(actual, "{:?} {:?} inactive\n", file_id, range);
+            }
+        }
         expect.assert_eq(actual.trim_start())
     }
 
@@ -33,7 +33,9 @@ pub(crate) fn prepare_rename(
 
     let (name_like, def) = find_definition(&sema, syntax, position)?;
     if def.range_for_rename(&sema).is_none() {
-        bail!("No references found at position")
+        return Err(def
+            .macro_rename_error(&sema)
+            .unwrap_or_else(|| format_err!("No references found at position")));
     }
 
     let frange = sema.original_range(name_like.syntax());
@@ -747,6 +749,25 @@ fn baz(j: i32) -> Self {
         );
     }
 
+    #[test]
+    fn test_rename_leaves_inactive_code_alone() {
+        // Names in inactive code can't be resolved, so they might refer to
+        // something else.
+        check(
+            "bar",
+            r#"
+fn foo$0() {}
+#[cfg(windows)]
+fn inactive() { foo(); }
+"#,
+            r#"
+fn bar() {}
+#[cfg(windows)]
+fn inactive() { foo(); }
+"#,
+        );
+    }
+
     #[test]
     fn test_rename_mod() {
         check_expect(
@@ -1805,8 +1826,7 @@ fn f() { <()>::BAR$0; }"#,
     }
 
     #[test]
-    fn macros_are_broken_lol() {
-        cov_mark::check!(macros_are_broken_lol);
+    fn test_rename_fn_defined_by_macro() {
         check(
             "lol",
             r#"
@@ -1814,11 +1834,54 @@ macro_rules! m { () => { fn f() {} } }
 m!();
 fn main() { f$0()  }
 "#,
+            "error: Cannot rename `f`, its definition is produced by a macro",
+        );
+        check_prepare(
             r#"
 macro_rules! m { () => { fn f() {} } }
-lol
-fn main() { lol()  }
+m!();
+fn main() { f$0()  }
 "#,
-        )
+            expect![[r#"Cannot rename `f`, its definition is produced by a macro"#]],
+        );
+    }
+
+    #[test]
+    fn test_rename_usage_produced_by_macro() {
+        check(
+            "bar",
+            r#"
+macro_rules! call_foo { () => { foo() } }
+fn foo$0() {}
+fn main() {
+    foo();
+    call_foo!();
+}
+"#,
+            "error: Cannot rename `foo`, it is produced by macros in /main.rs:5:5",
+        );
+    }
+
+    #[test]
+    fn test_rename_usage_passed_through_nested_macros() {
+        check(
+            "bar",
+            r#"
+macro_rules! inner { ($e:expr) => { $e } }
+macro_rules! outer { ($e:expr) => { inner!($e) } }
+fn foo$0() {}
+fn main() {
+    outer!(foo());
+}
+"#,
+            r#"
+macro_rules! inner { ($e:expr) => { $e } }
+macro_rules! outer { ($e:expr) => { inner!($e) } }
+fn bar() {}
+fn main() {
+    outer!(bar());
+}
+"#,
+        );
     }
 }
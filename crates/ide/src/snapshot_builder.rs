@@ -0,0 +1,140 @@
+//! Programmatic construction of analysis snapshots.
+//!
+//! `rust-analyzer json-change --snapshot` creates snapshots from cargo
+//! workspaces. [`SnapshotBuilder`] is for everything else: playgrounds, test
+//! fixtures and other tools which know their source files and crates up front.
+
+use std::sync::Arc;
+
+use cfg::CfgOptions;
+use ide_db::base_db::{
+    snapshot, Change, CrateDisplayName, CrateGraph, CrateId, CrateName, CyclicDependenciesError,
+    Edition, Env, FileId, FileSet, SourceRoot, SourceRootId, VfsPath,
+};
+
+/// Assembles source roots, files and crates into a [`Change`] or into the
+/// serialized snapshot format read by [`snapshot::decode`].
+///
+/// File ids are allocated sequentially, starting from zero.
+#[derive(Debug, Default)]
+pub struct SnapshotBuilder {
+    roots: Vec<(bool, FileSet)>,
+    files: Vec<(FileId, Arc<String>)>,
+    crate_graph: CrateGraph,
+}
+
+impl SnapshotBuilder {
+    pub fn new() -> SnapshotBuilder {
+        SnapshotBuilder::default()
+    }
+
+    /// Adds an empty source root. Library roots are assumed to never change.
+    pub fn add_source_root(&mut self, is_library: bool) -> SourceRootId {
+        self.roots.push((is_library, FileSet::default()));
+        SourceRootId(self.roots.len() as u32 - 1)
+    }
+
+    /// Adds a file to `root`.
+    ///
+    /// `path` is a `/`-separated path, like `/src/main.rs`. Files which refer
+    /// to each other via `mod` declarations must live in the same root. Outside
+    /// of wasm, snapshot readers treat the path as an absolute file system path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `root` was not created by this builder or if `path` does not
+    /// start with `/`.
+    pub fn add_file(&mut self, root: SourceRootId, path: &str, text: impl Into<String>) -> FileId {
+        let file_id = FileId(self.files.len() as u32);
+        let path = VfsPath::new_virtual_path(path.to_string());
+        self.roots[root.0 as usize].1.insert(file_id, path);
+        self.files.push((file_id, Arc::new(text.into())));
+        file_id
+    }
+
+    /// Adds a crate whose root module is `root_file`.
+    pub fn add_crate(
+        &mut self,
+        root_file: FileId,
+        name: &str,
+        edition: Edition,
+        cfg_options: CfgOptions,
+        env: Env,
+    ) -> CrateId {
+        self.crate_graph.add_crate_root(
+            root_file,
+            edition,
+            Some(CrateDisplayName::from_canonical_name(name.to_string())),
+            cfg_options.clone(),
+            cfg_options,
+            env,
+            Vec::new(),
+        )
+    }
+
+    /// Makes `dep` available to `from` as an extern crate called `name`.
+    pub fn add_dep(
+        &mut self,
+        from: CrateId,
+        name: &str,
+        dep: CrateId,
+    ) -> Result<(), CyclicDependenciesError> {
+        self.crate_graph.add_dep(from, CrateName::normalize_dashes(name), dep)
+    }
+
+    pub fn into_change(self) -> Change {
+        let mut change = Change::new();
+        change.set_roots(
+            self.roots
+                .into_iter()
+                .map(|(is_library, file_set)| {
+                    if is_library {
+                        SourceRoot::new_library(file_set)
+                    } else {
+                        SourceRoot::new_local(file_set)
+                    }
+                })
+                .collect(),
+        );
+        for (file_id, text) in self.files {
+            change.change_file(file_id, Some(text));
+        }
+        change.set_crate_graph(self.crate_graph);
+        change
+    }
+
+    /// Serializes the snapshot, see [`snapshot::encode`].
+    pub fn finish(self) -> Vec<u8> {
+        snapshot::encode(&self.into_change())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AnalysisHost;
+
+    use super::*;
+
+    #[test]
+    fn builds_two_crate_snapshot() {
+        let mut builder = SnapshotBuilder::new();
+        let local = builder.add_source_root(false);
+        let library = builder.add_source_root(true);
+        let main = builder.add_file(local, "/main.rs", "fn main() { dep::foo(); }");
+        let dep = builder.add_file(library, "/dep/lib.rs", "pub fn foo() {}");
+        let mut cfg = CfgOptions::default();
+        cfg.insert_atom("test".into());
+        let main = builder.add_crate(main, "main", Edition::CURRENT, cfg, Env::default());
+        let dep =
+            builder.add_crate(dep, "dep", Edition::CURRENT, CfgOptions::default(), Env::default());
+        builder.add_dep(main, "dep", dep).unwrap();
+        assert!(builder.add_dep(dep, "main", main).is_err());
+
+        let mut host = AnalysisHost::default();
+        host.apply_change(builder.into_change());
+        let analysis = host.analysis();
+        assert_eq!(analysis.file_text(FileId(1)).unwrap().as_str(), "pub fn foo() {}");
+        assert!(analysis.is_library_file(FileId(1)).unwrap());
+        assert_eq!(analysis.crate_for(FileId(0)).unwrap(), vec![main]);
+    }
+}
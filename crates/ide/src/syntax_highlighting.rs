@@ -30,7 +30,7 @@ use crate::{
     FileId, HlMod, HlTag,
 };
 
-pub(crate) use html::highlight_as_html;
+pub(crate) use html::{highlight_as_html, highlight_as_linked_html};
 
 #[derive(Debug, Clone, Copy)]
 pub struct HlRange {
@@ -2,12 +2,38 @@
 
 use ide_db::base_db::SourceDatabase;
 use oorandom::Rand32;
+use rustc_hash::FxHashMap;
 use stdx::format_to;
 use syntax::AstNode;
 
-use crate::{syntax_highlighting::highlight, FileId, RootDatabase};
+use crate::{
+    goto_definition::goto_definition,
+    syntax_highlighting::{highlight, HlRange},
+    FileId, FilePosition, HlMod, HlTag, RootDatabase,
+};
 
 pub(crate) fn highlight_as_html(db: &RootDatabase, file_id: FileId, rainbow: bool) -> String {
+    render(db, file_id, rainbow, None)
+}
+
+/// Like [`highlight_as_html`], but definitions get anchors and references link
+/// to them. `file_urls` maps files to the URLs of their rendered pages,
+/// references to other files are not linked.
+pub(crate) fn highlight_as_linked_html(
+    db: &RootDatabase,
+    file_id: FileId,
+    rainbow: bool,
+    file_urls: &FxHashMap<FileId, String>,
+) -> String {
+    render(db, file_id, rainbow, Some(file_urls))
+}
+
+fn render(
+    db: &RootDatabase,
+    file_id: FileId,
+    rainbow: bool,
+    file_urls: Option<&FxHashMap<FileId, String>>,
+) -> String {
     let parse = db.parse(file_id);
 
     fn rainbowify(seed: u64) -> String {
@@ -24,6 +50,9 @@ pub(crate) fn highlight_as_html(db: &RootDatabase, file_id: FileId, rainbow: boo
     let text = parse.tree().syntax().to_string();
     let mut buf = String::new();
     buf.push_str(STYLE);
+    if file_urls.is_some() {
+        buf.push_str(LINK_STYLE);
+    }
     buf.push_str("<pre><code>");
     for r in &hl_ranges {
         let chunk = html_escape(&text[r.range]);
@@ -33,18 +62,52 @@ pub(crate) fn highlight_as_html(db: &RootDatabase, file_id: FileId, rainbow: boo
         }
 
         let class = r.highlight.to_string().replace('.', " ");
-        let color = match (rainbow, r.binding_hash) {
+        let mut color = match (rainbow, r.binding_hash) {
             (true, Some(hash)) => {
                 format!(" data-binding-hash=\"{}\" style=\"color: {};\"", hash, rainbowify(hash))
             }
             _ => "".into(),
         };
-        format_to!(buf, "<span class=\"{}\"{}>{}</span>", class, color, chunk);
+        let href = match file_urls {
+            Some(_) if r.highlight.mods.contains(HlMod::Definition) => {
+                format_to!(color, " id=\"o{}\"", u32::from(r.range.start()));
+                None
+            }
+            Some(file_urls) => link_target(db, file_urls, file_id, r),
+            None => None,
+        };
+        match href {
+            Some(href) => format_to!(
+                buf,
+                "<a href=\"{}\"><span class=\"{}\"{}>{}</span></a>",
+                href,
+                class,
+                color,
+                chunk
+            ),
+            None => format_to!(buf, "<span class=\"{}\"{}>{}</span>", class, color, chunk),
+        }
     }
     buf.push_str("</code></pre>");
     buf
 }
 
+fn link_target(
+    db: &RootDatabase,
+    file_urls: &FxHashMap<FileId, String>,
+    file_id: FileId,
+    range: &HlRange,
+) -> Option<String> {
+    if !matches!(range.highlight.tag, HlTag::Symbol(_)) {
+        return None;
+    }
+    let position = FilePosition { file_id, offset: range.range.start() };
+    let nav = goto_definition(db, position)?.info.into_iter().next()?;
+    let url = file_urls.get(&nav.file_id)?;
+    let offset = nav.focus_range.unwrap_or(nav.full_range).start();
+    Some(format!("{}#o{}", url, u32::from(offset)))
+}
+
 //FIXME: like, real html escaping
 fn html_escape(text: &str) -> String {
     text.replace("<", "&lt;").replace(">", "&gt;")
@@ -91,3 +154,10 @@ pre                 { color: #DCDCCC; background: #3F3F3F; font-size: 22px; padd
 .unresolved_reference { color: #FC5555; text-decoration: wavy underline; }
 </style>
 ";
+
+const LINK_STYLE: &str = "
+<style>
+a                   { color: inherit; text-decoration: none; }
+a:hover             { text-decoration: underline; }
+</style>
+";
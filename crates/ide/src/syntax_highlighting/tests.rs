@@ -2,6 +2,7 @@ use std::time::Instant;
 
 use expect_test::{expect_file, ExpectFile};
 use ide_db::SymbolKind;
+use rustc_hash::FxHashMap;
 use test_utils::{bench, bench_fixture, skip_slow_tests, AssertLinear};
 
 use crate::{fixture, FileRange, HlTag, TextRange};
@@ -310,6 +311,20 @@ fn bar() {
     );
 }
 
+#[test]
+fn test_linked_html() {
+    let (analysis, file_id) = fixture::file("fn foo() {}\nfn main() { foo(); }");
+    let mut file_urls = FxHashMap::default();
+    file_urls.insert(file_id, "main.html".to_string());
+    let html = analysis.highlight_as_linked_html(file_id, false, &file_urls).unwrap();
+    assert!(html.contains(r#"<span class="function declaration" id="o3">foo</span>"#), "{}", html);
+    assert!(
+        html.contains(r#"<a href="main.html#o3"><span class="function">foo</span></a>"#),
+        "{}",
+        html
+    );
+}
+
 #[test]
 fn benchmark_syntax_highlighting_long_struct() {
     if skip_slow_tests() {
@@ -0,0 +1,214 @@
+//! Entry point for type-hierarchy
+//!
+//! Rust has no subtyping between types, the hierarchy is made up of traits:
+//! the supertypes of a type are the traits it implements, those of a trait its
+//! supertraits. The subtypes of a trait are its implementors and subtraits.
+
+use hir::{Semantics, Trait};
+use ide_db::{defs::Definition, RootDatabase};
+use itertools::Itertools;
+use syntax::{ast, AstNode};
+
+use crate::{
+    display::TryToNav, goto_definition, references, FilePosition, NavigationTarget, RangeInfo,
+};
+
+pub(crate) fn type_hierarchy(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    goto_definition::goto_definition(db, position)
+}
+
+pub(crate) fn supertypes(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<Vec<NavigationTarget>> {
+    let sema = Semantics::new(db);
+    let syntax = sema.parse(position.file_id).syntax().clone();
+    let traits = match references::find_def(&sema, &syntax, position.offset)? {
+        // `all_for_type` matches impls for any instantiation of the type, but
+        // also the ones for references to it, which aren't impls of the type.
+        Definition::ModuleDef(hir::ModuleDef::Adt(adt)) => hir::Impl::all_for_type(db, adt.ty(db))
+            .into_iter()
+            .filter(|it| it.self_ty(db).as_adt() == Some(adt))
+            .filter_map(|it| it.trait_(db))
+            .collect(),
+        Definition::ModuleDef(hir::ModuleDef::Trait(trait_)) => trait_.direct_supertraits(db),
+        _ => return None,
+    };
+    Some(traits.into_iter().unique().filter_map(|it| it.try_to_nav(db)).collect())
+}
+
+pub(crate) fn subtypes(db: &RootDatabase, position: FilePosition) -> Option<Vec<NavigationTarget>> {
+    let sema = Semantics::new(db);
+    let syntax = sema.parse(position.file_id).syntax().clone();
+    let trait_ = match references::find_def(&sema, &syntax, position.offset)? {
+        Definition::ModuleDef(hir::ModuleDef::Adt(_)) => return Some(Vec::new()),
+        Definition::ModuleDef(hir::ModuleDef::Trait(it)) => it,
+        _ => return None,
+    };
+    // Implementors without an item of their own, like primitives, references,
+    // trait objects or type parameters of blanket impls, are shown as the impl.
+    let mut implementors = hir::Impl::all_for_trait(db, trait_)
+        .into_iter()
+        .map(|it| it.self_ty(db).as_adt().ok_or(it))
+        .unique()
+        .filter_map(|it| match it {
+            Ok(adt) => adt.try_to_nav(db),
+            Err(impl_) => impl_.try_to_nav(db),
+        })
+        .collect::<Vec<_>>();
+    let mut subtraits =
+        subtraits(&sema, trait_).into_iter().filter_map(|it| it.try_to_nav(db)).collect::<Vec<_>>();
+    implementors.sort_by_key(|it| (it.file_id, it.full_range.start()));
+    subtraits.sort_by_key(|it| (it.file_id, it.full_range.start()));
+    implementors.extend(subtraits);
+    Some(implementors)
+}
+
+/// The traits naming `trait_` in their bounds on `Self`. Only the traits around
+/// references to `trait_` can, which are found by the text search of the
+/// references instead of going through all items of all dependent crates. Like
+/// for the references, names imported under an alias aren't found.
+fn subtraits(sema: &Semantics<RootDatabase>, trait_: Trait) -> Vec<Trait> {
+    let usages = Definition::ModuleDef(trait_.into()).usages(sema).all();
+    let mut res = Vec::new();
+    for (_, references) in usages.references {
+        for reference in references {
+            let it = match reference.name.syntax().ancestors().find_map(ast::Trait::cast) {
+                Some(it) => it,
+                None => continue,
+            };
+            if let Some(it) = sema.to_def(&it) {
+                if it.direct_supertraits(sema.db).contains(&trait_) {
+                    res.push(it);
+                }
+            }
+        }
+    }
+    res.into_iter().unique().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ide_db::base_db::FilePosition;
+
+    use crate::fixture;
+
+    fn check_hierarchy(
+        ra_fixture: &str,
+        expected: &str,
+        expected_supertypes: &[&str],
+        expected_subtypes: &[&str],
+    ) {
+        let (analysis, pos) = fixture::position(ra_fixture);
+
+        let mut navs = analysis.type_hierarchy(pos).unwrap().unwrap().info;
+        assert_eq!(navs.len(), 1);
+        let nav = navs.pop().unwrap();
+        nav.assert_match(expected);
+
+        let item_pos =
+            FilePosition { file_id: nav.file_id, offset: nav.focus_or_full_range().start() };
+        let supertypes = analysis.supertypes(item_pos).unwrap().unwrap();
+        let supertypes = supertypes.iter().map(|it| it.debug_render()).collect::<Vec<_>>();
+        assert_eq!(supertypes, expected_supertypes);
+
+        let subtypes = analysis.subtypes(item_pos).unwrap().unwrap();
+        let subtypes = subtypes.iter().map(|it| it.debug_render()).collect::<Vec<_>>();
+        assert_eq!(subtypes, expected_subtypes);
+    }
+
+    #[test]
+    fn test_type_hierarchy_of_struct() {
+        check_hierarchy(
+            r#"
+trait Shape {}
+trait Named {}
+struct Circ$0le;
+impl Shape for Circle {}
+impl Named for Circle {}
+impl Circle {}
+"#,
+            "Circle Struct FileId(0) 30..44 37..43",
+            &["Shape Trait FileId(0) 0..14 6..11", "Named Trait FileId(0) 15..29 21..26"],
+            &[],
+        );
+    }
+
+    #[test]
+    fn test_type_hierarchy_of_trait() {
+        check_hierarchy(
+            r#"
+trait Shape {}
+trait Sha$0ded: Shape {}
+trait Solid: Shaded {}
+struct Ball;
+impl Shaded for Ball {}
+"#,
+            "Shaded Trait FileId(0) 15..37 21..27",
+            &["Shape Trait FileId(0) 0..14 6..11"],
+            &["Ball Struct FileId(0) 61..73 68..72", "Solid Trait FileId(0) 38..60 44..49"],
+        );
+    }
+
+    #[test]
+    fn test_type_hierarchy_of_generic_struct() {
+        check_hierarchy(
+            r#"
+trait Shape {}
+trait Named {}
+trait Debug {}
+struct Wrap$0per<T>(T);
+impl Shape for Wrapper<i32> {}
+impl<T> Named for Wrapper<T> {}
+impl Debug for &Wrapper<u8> {}
+"#,
+            "Wrapper Struct FileId(0) 45..66 52..59",
+            &["Shape Trait FileId(0) 0..14 6..11", "Named Trait FileId(0) 15..29 21..26"],
+            &[],
+        );
+    }
+
+    #[test]
+    fn test_type_hierarchy_of_trait_with_non_adt_implementors() {
+        check_hierarchy(
+            r#"
+trait Sha$0pe {}
+trait Other {}
+struct Circle;
+impl Shape for Circle {}
+impl Shape for &Circle {}
+impl Shape for i32 {}
+impl Shape for dyn Other {}
+"#,
+            "Shape Trait FileId(0) 0..14 6..11",
+            &[],
+            &[
+                "Circle Struct FileId(0) 30..44 37..43",
+                "impl Impl FileId(0) 70..95 85..92",
+                "impl Impl FileId(0) 96..117 111..114",
+                "impl Impl FileId(0) 118..145 133..142",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_subtraits_in_other_crates() {
+        check_hierarchy(
+            r#"
+//- /lib.rs crate:lib
+pub trait Sha$0pe {}
+//- /main.rs crate:main deps:lib
+use lib::Shape;
+trait Solid: Shape {}
+trait Unrelated {}
+fn f(_: &dyn Shape) {}
+"#,
+            "Shape Trait FileId(0) 0..18 10..15",
+            &[],
+            &["Solid Trait FileId(1) 16..37 22..27"],
+        );
+    }
+}
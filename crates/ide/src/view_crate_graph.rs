@@ -1,11 +1,53 @@
-use std::sync::Arc;
-
 use dot::{Id, LabelText};
 use ide_db::{
-    base_db::{CrateGraph, CrateId, Dependency, SourceDatabase, SourceDatabaseExt},
+    base_db::{CrateId, FileId, PackageInfo, SourceDatabase, SourceDatabaseExt},
     RootDatabase,
 };
-use rustc_hash::FxHashSet;
+use rustc_hash::FxHashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct CrateGraphConfig {
+    /// Leave out crates.io dependencies and sysroot crates.
+    pub workspace_only: bool,
+    /// Show library crates with the same root file as a single node. The
+    /// sysroot crates are loaded once per workspace, and dependencies once per
+    /// feature set, so these would otherwise appear several times.
+    pub merge_duplicates: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CrateGraphNode {
+    pub name: String,
+    pub is_library: bool,
+    pub package: Option<PackageInfo>,
+    /// The crates of the crate graph this node stands for, more than one only
+    /// with [`CrateGraphConfig::merge_duplicates`].
+    pub crates: Vec<CrateId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CrateGraphEdge {
+    /// Indices into [`ExportedCrateGraph::nodes`].
+    pub from: usize,
+    pub to: usize,
+    /// The name under which `from` refers to `to`.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportedCrateGraph {
+    pub nodes: Vec<CrateGraphNode>,
+    pub edges: Vec<CrateGraphEdge>,
+}
+
+impl ExportedCrateGraph {
+    /// Renders the graph to GraphViz "dot" syntax.
+    pub fn to_dot(&self) -> String {
+        let mut dot = Vec::new();
+        dot::render(self, &mut dot).unwrap();
+        String::from_utf8(dot).unwrap()
+    }
+}
 
 // Feature: View Crate Graph
 //
@@ -20,75 +62,133 @@ use rustc_hash::FxHashSet;
 // | VS Code | **Rust Analyzer: View Crate Graph**
 // |===
 pub(crate) fn view_crate_graph(db: &RootDatabase, full: bool) -> Result<String, String> {
+    let config = CrateGraphConfig { workspace_only: !full, merge_duplicates: false };
+    Ok(export_crate_graph(db, &config).to_dot())
+}
+
+pub(crate) fn export_crate_graph(
+    db: &RootDatabase,
+    config: &CrateGraphConfig,
+) -> ExportedCrateGraph {
     let crate_graph = db.crate_graph();
-    let crates_to_render = crate_graph
-        .iter()
-        .filter(|krate| {
-            if full {
-                true
-            } else {
-                // Only render workspace crates
-                let root_id = db.file_source_root(crate_graph[*krate].root_file_id);
-                !db.source_root(root_id).is_library
-            }
-        })
-        .collect();
-    let graph = DotCrateGraph { graph: crate_graph, crates_to_render };
+    let is_library = |krate: CrateId| {
+        let root_id = db.file_source_root(crate_graph[krate].root_file_id);
+        db.source_root(root_id).is_library
+    };
 
-    let mut dot = Vec::new();
-    dot::render(&graph, &mut dot).unwrap();
-    Ok(String::from_utf8(dot).unwrap())
-}
+    let mut crates = crate_graph.iter().collect::<Vec<_>>();
+    crates.sort();
+    let mut res = ExportedCrateGraph::default();
+    let mut node_of_crate: FxHashMap<CrateId, usize> = FxHashMap::default();
+    let mut node_of_root: FxHashMap<FileId, usize> = FxHashMap::default();
+    for krate in crates {
+        let is_library = is_library(krate);
+        if config.workspace_only && is_library {
+            continue;
+        }
+        let root_file_id = crate_graph[krate].root_file_id;
+        if config.merge_duplicates && is_library {
+            if let Some(&node) = node_of_root.get(&root_file_id) {
+                res.nodes[node].crates.push(krate);
+                node_of_crate.insert(krate, node);
+                continue;
+            }
+        }
+        let name = crate_graph[krate]
+            .display_name
+            .as_ref()
+            .map_or_else(|| "(unnamed crate)".to_string(), |it| it.to_string());
+        node_of_crate.insert(krate, res.nodes.len());
+        node_of_root.insert(root_file_id, res.nodes.len());
+        let package = crate_graph[krate].package.clone();
+        res.nodes.push(CrateGraphNode { name, is_library, package, crates: vec![krate] });
+    }
 
-struct DotCrateGraph {
-    graph: Arc<CrateGraph>,
-    crates_to_render: FxHashSet<CrateId>,
+    for (&krate, &from) in node_of_crate.iter() {
+        for dep in &crate_graph[krate].dependencies {
+            let to = match node_of_crate.get(&dep.crate_id) {
+                Some(&it) => it,
+                None => continue,
+            };
+            res.edges.push(CrateGraphEdge { from, to, name: dep.name.to_string() });
+        }
+    }
+    res.edges.sort_by(|a, b| (a.from, a.to, &a.name).cmp(&(b.from, b.to, &b.name)));
+    res.edges.dedup_by(|a, b| (a.from, a.to, &a.name) == (b.from, b.to, &b.name));
+    res
 }
 
-type Edge<'a> = (CrateId, &'a Dependency);
+type Edge<'a> = &'a CrateGraphEdge;
 
-impl<'a> dot::GraphWalk<'a, CrateId, Edge<'a>> for DotCrateGraph {
-    fn nodes(&'a self) -> dot::Nodes<'a, CrateId> {
-        self.crates_to_render.iter().copied().collect()
+impl<'a> dot::GraphWalk<'a, usize, Edge<'a>> for ExportedCrateGraph {
+    fn nodes(&'a self) -> dot::Nodes<'a, usize> {
+        (0..self.nodes.len()).collect()
     }
 
     fn edges(&'a self) -> dot::Edges<'a, Edge<'a>> {
-        self.crates_to_render
-            .iter()
-            .flat_map(|krate| {
-                self.graph[*krate]
-                    .dependencies
-                    .iter()
-                    .filter(|dep| self.crates_to_render.contains(&dep.crate_id))
-                    .map(move |dep| (*krate, dep))
-            })
-            .collect()
+        self.edges.iter().collect()
     }
 
-    fn source(&'a self, edge: &Edge<'a>) -> CrateId {
-        edge.0
+    fn source(&'a self, edge: &Edge<'a>) -> usize {
+        edge.from
     }
 
-    fn target(&'a self, edge: &Edge<'a>) -> CrateId {
-        edge.1.crate_id
+    fn target(&'a self, edge: &Edge<'a>) -> usize {
+        edge.to
     }
 }
 
-impl<'a> dot::Labeller<'a, CrateId, Edge<'a>> for DotCrateGraph {
+impl<'a> dot::Labeller<'a, usize, Edge<'a>> for ExportedCrateGraph {
     fn graph_id(&'a self) -> Id<'a> {
         Id::new("rust_analyzer_crate_graph").unwrap()
     }
 
-    fn node_id(&'a self, n: &CrateId) -> Id<'a> {
-        Id::new(format!("_{}", n.0)).unwrap()
+    fn node_id(&'a self, n: &usize) -> Id<'a> {
+        Id::new(format!("_{}", n)).unwrap()
     }
 
-    fn node_shape(&'a self, _node: &CrateId) -> Option<LabelText<'a>> {
+    fn node_shape(&'a self, _node: &usize) -> Option<LabelText<'a>> {
         Some(LabelText::LabelStr("box".into()))
     }
 
-    fn node_label(&'a self, n: &CrateId) -> LabelText<'a> {
-        let name = self.graph[*n].display_name.as_ref().map_or("(unnamed crate)", |name| &*name);
-        LabelText::LabelStr(name.into())
+    fn node_label(&'a self, n: &usize) -> LabelText<'a> {
+        let node = &self.nodes[*n];
+        match &node.package {
+            Some(package) => {
+                LabelText::LabelStr(format!("{} {}", node.name, package.version).into())
+            }
+            None => LabelText::LabelStr(node.name.as_str().into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use crate::{fixture, CrateGraphConfig};
+
+    #[test]
+    fn export_workspace_crates() {
+        let (analysis, _) = fixture::file(
+            r#"
+//- /main.rs crate:main deps:dep,lib
+//- /lib.rs crate:lib deps:dep
+//- /dep.rs crate:dep
+"#,
+        );
+        let config = CrateGraphConfig { workspace_only: false, merge_duplicates: true };
+        let graph = analysis.export_crate_graph(&config).unwrap();
+        let edges = graph
+            .edges
+            .iter()
+            .map(|it| format!("{} -> {}\n", graph.nodes[it.from].name, graph.nodes[it.to].name))
+            .collect::<String>();
+        expect![[r#"
+            main -> lib
+            main -> dep
+            lib -> dep
+        "#]]
+        .assert_eq(&edges);
     }
 }
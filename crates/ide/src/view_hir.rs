@@ -1,8 +1,10 @@
-use hir::{Function, Semantics};
+use hir::{Crate, Function, Semantics};
 use ide_db::base_db::FilePosition;
 use ide_db::RootDatabase;
 use syntax::{algo::find_node_at_offset, ast, AstNode};
 
+use crate::call_graph::{crate_functions, function_path};
+
 // Feature: View Hir
 //
 // |===
@@ -24,3 +26,43 @@ fn body_hir(db: &RootDatabase, position: FilePosition) -> Option<String> {
     let function: Function = sema.to_def(&function)?;
     Some(function.debug_hir(db))
 }
+
+/// Finds the functions with the given path, like `krate::module::Type::method`,
+/// and returns their paths along with their HIR. Methods of different impls
+/// for the same type share a path, so there may be more than one.
+pub(crate) fn view_hir_of_path(db: &RootDatabase, path: &str) -> Vec<(String, String)> {
+    let crate_name = path.split("::").next().unwrap_or_default();
+    Crate::all(db)
+        .into_iter()
+        .filter(|krate| krate.display_name(db).map_or(false, |it| it.to_string() == crate_name))
+        .flat_map(|krate| crate_functions(db, krate))
+        .filter(|&func| function_path(db, func) == path)
+        .map(|func| (function_path(db, func), func.debug_hir(db)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixture;
+
+    #[test]
+    fn view_hir_by_path() {
+        let (analysis, _) = fixture::file(
+            r#"
+//- /main.rs crate:main
+mod m {
+    pub struct S;
+    impl S {
+        pub fn new() -> S { S }
+    }
+}
+fn new() {}
+"#,
+        );
+        let res = analysis.view_hir_of_path("main::m::S::new").unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].0, "main::m::S::new");
+        assert!(analysis.view_hir_of_path("main::m::new").unwrap().is_empty());
+        assert!(analysis.view_hir_of_path("other::new").unwrap().is_empty());
+    }
+}
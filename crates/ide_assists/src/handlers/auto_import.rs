@@ -1,9 +1,18 @@
-use ide_db::helpers::{
-    import_assets::{ImportAssets, ImportCandidate},
-    insert_use::{insert_use, ImportScope},
-    mod_path_to_ast,
+use hir::Crate;
+use ide_db::{
+    helpers::{
+        import_assets::{FeatureGatedImport, ImportAssets, ImportCandidate},
+        insert_use::{insert_use, ImportScope},
+        mod_path_to_ast,
+    },
+    RootDatabase,
+};
+use itertools::Itertools;
+use stdx::format_to;
+use syntax::{
+    ast::{self, make},
+    AstNode, SyntaxNode,
 };
-use syntax::{ast, AstNode, SyntaxNode};
 
 use crate::{AssistContext, AssistId, AssistKind, Assists, GroupLabel};
 
@@ -61,6 +70,13 @@ use crate::{AssistContext, AssistId, AssistKind, Assists, GroupLabel};
 //
 // In `VS Code` the configuration for this is `rust-analyzer.assist.importPrefix`.
 //
+// .Conditional Items
+//
+// Items of the current crate which only exist under a `#[cfg]` that doesn't hold for the code
+// importing them, like the test helpers in a `#[cfg(test)]` module, are proposed last, with the
+// cfg they need. When the only items with the name are removed by a `#[cfg]` on disabled
+// features, they are proposed with the features to enable.
+//
 // image::https://user-images.githubusercontent.com/48062697/113020673-b85be580-917a-11eb-9022-59585f35d4f8.gif[]
 
 // Assist: auto_import
@@ -84,9 +100,14 @@ use crate::{AssistContext, AssistId, AssistKind, Assists, GroupLabel};
 // ```
 pub(crate) fn auto_import(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
     let (import_assets, syntax_under_caret) = find_importable_node(ctx)?;
-    let mut proposed_imports =
-        import_assets.search_for_imports(&ctx.sema, ctx.config.insert_use.prefix_kind);
-    if proposed_imports.is_empty() {
+    let prefix_kind = ctx.config.insert_use.prefix_kind;
+    let mut proposed_imports = import_assets.search_for_imports(&ctx.sema, prefix_kind);
+    let feature_gated_imports = if proposed_imports.is_empty() {
+        import_assets.search_for_feature_gated_imports(&ctx.sema, prefix_kind)
+    } else {
+        Vec::new()
+    };
+    if proposed_imports.is_empty() && feature_gated_imports.is_empty() {
         return None;
     }
     // we aren't interested in different namespaces
@@ -95,11 +116,12 @@ pub(crate) fn auto_import(acc: &mut Assists, ctx: &AssistContext) -> Option<()>
     let range = ctx.sema.original_range(&syntax_under_caret).range;
     let group_label = group_label(import_assets.import_candidate());
     let scope = ImportScope::find_insert_use_container_with_macros(&syntax_under_caret, &ctx.sema)?;
-    for import in proposed_imports {
+    let current_crate = ctx.sema.scope(&syntax_under_caret).module()?.krate();
+    let mut add_import = |label: String, path: ast::Path| {
         acc.add_group(
             &group_label,
             AssistId("auto_import", AssistKind::QuickFix),
-            format!("Import `{}`", import.import_path),
+            label,
             range,
             |builder| {
                 let scope = match scope.clone() {
@@ -107,9 +129,20 @@ pub(crate) fn auto_import(acc: &mut Assists, ctx: &AssistContext) -> Option<()>
                     ImportScope::Module(it) => ImportScope::Module(builder.make_mut(it)),
                     ImportScope::Block(it) => ImportScope::Block(builder.make_mut(it)),
                 };
-                insert_use(&scope, mod_path_to_ast(&import.import_path), &ctx.config.insert_use);
+                insert_use(&scope, path, &ctx.config.insert_use);
             },
-        );
+        )
+    };
+    for import in proposed_imports {
+        let label = match import_assets.required_cfg(&ctx.sema, &import) {
+            Some(cfg) => format!("Import `{}` (needs `#[cfg({})]`)", import.import_path, cfg),
+            None => format!("Import `{}`", import.import_path),
+        };
+        add_import(label, mod_path_to_ast(&import.import_path));
+    }
+    for import in feature_gated_imports {
+        let label = feature_gated_label(ctx.db(), current_crate, &import);
+        add_import(label, make::path_from_text(&import.import_path));
     }
     Some(())
 }
@@ -133,6 +166,23 @@ pub(super) fn find_importable_node(ctx: &AssistContext) -> Option<(ImportAssets,
     }
 }
 
+fn feature_gated_label(
+    db: &RootDatabase,
+    current_crate: Crate,
+    import: &FeatureGatedImport,
+) -> String {
+    let features = import.features.iter().map(|it| format!("`{}`", it)).join(", ");
+    let noun = if import.features.len() == 1 { "feature" } else { "features" };
+    let mut label = format!("Import `{}` (enable {} {}", import.import_path, noun, features);
+    if import.krate != current_crate {
+        if let Some(name) = import.krate.display_name(db) {
+            format_to!(label, " of `{}`", name);
+        }
+    }
+    label.push(')');
+    label
+}
+
 fn group_label(import_candidate: &ImportCandidate) -> GroupLabel {
     let name = match import_candidate {
         ImportCandidate::Path(candidate) => format!("Import {}", candidate.name.text()),
@@ -150,7 +200,10 @@ fn group_label(import_candidate: &ImportCandidate) -> GroupLabel {
 mod tests {
     use super::*;
 
-    use crate::tests::{check_assist, check_assist_not_applicable, check_assist_target};
+    use crate::tests::{
+        check_assist, check_assist_by_label, check_assist_not_applicable, check_assist_order,
+        check_assist_target,
+    };
 
     #[test]
     fn applicable_when_found_an_import_partial() {
@@ -865,6 +918,108 @@ fn main() {
         );
     }
 
+    #[test]
+    fn cfg_gated_import_is_proposed_last() {
+        check_assist_order(
+            auto_import,
+            r"
+//- /main.rs crate:main cfg:test
+fn main() {
+    Helper$0
+}
+#[cfg(test)]
+mod tests {
+    pub struct Helper;
+}
+mod util {
+    pub struct Helper;
+}
+",
+            &["Import `util::Helper`", "Import `tests::Helper` (needs `#[cfg(test)]`)"],
+        );
+    }
+
+    #[test]
+    fn cfg_gated_import_shows_cfg() {
+        check_assist_by_label(
+            auto_import,
+            r"
+//- /main.rs crate:main cfg:test
+fn main() {
+    Helper$0
+}
+#[cfg(test)]
+mod tests {
+    pub struct Helper;
+}
+",
+            r"
+use tests::Helper;
+
+fn main() {
+    Helper
+}
+#[cfg(test)]
+mod tests {
+    pub struct Helper;
+}
+",
+            "Import `tests::Helper` (needs `#[cfg(test)]`)",
+        );
+    }
+
+    #[test]
+    fn feature_gated_import() {
+        check_assist_by_label(
+            auto_import,
+            r#"
+//- /lib.rs crate:dep features:serde
+#[cfg(feature = "serde")]
+pub mod ser {
+    pub struct Serializer;
+}
+
+//- /main.rs crate:main deps:dep
+fn main() {
+    Serializer$0
+}
+"#,
+            r"use dep::ser::Serializer;
+
+fn main() {
+    Serializer
+}
+",
+            "Import `dep::ser::Serializer` (enable feature `serde` of `dep`)",
+        );
+    }
+
+    #[test]
+    fn feature_gated_import_any() {
+        check_assist_by_label(
+            auto_import,
+            r#"
+//- /lib.rs crate:dep features:std
+#[cfg(any(feature = "undeclared", feature = "std"))]
+pub mod io {
+    pub struct Error;
+}
+
+//- /main.rs crate:main deps:dep
+fn main() {
+    Error$0
+}
+"#,
+            r"use dep::io::Error;
+
+fn main() {
+    Error
+}
+",
+            "Import `dep::io::Error` (enable feature `std` of `dep`)",
+        );
+    }
+
     #[test]
     fn whole_segment() {
         // Tests that only imports whose last segment matches the identifier get suggested.
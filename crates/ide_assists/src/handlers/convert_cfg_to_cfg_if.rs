@@ -0,0 +1,478 @@
+use itertools::Itertools;
+use stdx::format_to;
+use syntax::{
+    ast::{
+        self,
+        edit::{AstNodeEdit, IndentLevel},
+        AttrsOwner, ModuleItemOwner,
+    },
+    ted, AstNode, Direction, SyntaxElement, SyntaxKind, SyntaxNode, T,
+};
+
+use crate::{AssistContext, AssistId, AssistKind, Assists};
+
+// Assist: convert_cfg_to_cfg_if
+//
+// Merges adjacent items gated on complementary `#[cfg]`s into a `cfg_if!`
+// block from the `cfg-if` crate.
+//
+// ```
+// $0#[cfg(unix)]
+// fn platform() -> &'static str { "unix" }
+// #[cfg(not(unix))]
+// fn platform() -> &'static str { "other" }
+// ```
+// ->
+// ```
+// cfg_if::cfg_if! {
+//     if #[cfg(unix)] {
+//         fn platform() -> &'static str { "unix" }
+//     } else {
+//         fn platform() -> &'static str { "other" }
+//     }
+// }
+// ```
+pub(crate) fn convert_cfg_to_cfg_if(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let item = ctx.find_node_at_offset::<ast::Item>()?;
+    cfg_predicate(&item)?;
+
+    let gated = |node: SyntaxNode| {
+        let item = ast::Item::cast(node)?;
+        let predicate = cfg_predicate(&item)?;
+        Some((item, predicate))
+    };
+    let mut run = item
+        .syntax()
+        .siblings(Direction::Prev)
+        .skip(1)
+        .map(gated)
+        .take_while(Option::is_some)
+        .flatten()
+        .collect::<Vec<_>>();
+    run.reverse();
+    let cursor = run.len();
+    run.extend(
+        item.syntax().siblings(Direction::Next).map(gated).take_while(Option::is_some).flatten(),
+    );
+
+    // Consecutive items with the same predicate make up a branch.
+    let branches = run
+        .into_iter()
+        .group_by(|(_, predicate)| predicate.key.clone())
+        .into_iter()
+        .map(|(_, group)| group.collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let cursor_branch = {
+        let mut len = 0;
+        branches.iter().position(|branch| {
+            len += branch.len();
+            len > cursor
+        })?
+    };
+    let (first, last) = (cursor_branch..branches.len()).find_map(|last| {
+        let first = else_branch_start(&branches[..=last])?;
+        (first <= cursor_branch).then(|| (first, last))
+    })?;
+    let branches = &branches[first..=last];
+
+    let first_item = &branches.first()?.first()?.0;
+    let last_item = &branches.last()?.last()?.0;
+    let target = first_item.syntax().text_range().cover(last_item.syntax().text_range());
+    acc.add(
+        AssistId("convert_cfg_to_cfg_if", AssistKind::RefactorRewrite),
+        "Convert to `cfg_if!`",
+        target,
+        |builder| {
+            let indent = IndentLevel::from_node(first_item.syntax());
+            let item_indent = indent + 2;
+            let mut buf = format!("cfg_if::cfg_if! {{\n{}", indent + 1);
+            for (i, branch) in branches.iter().enumerate() {
+                if i == 0 {
+                    format_to!(buf, "if #[cfg({})] {{", branch[0].1.text);
+                } else if i == branches.len() - 1 {
+                    buf.push_str(" else {");
+                } else {
+                    format_to!(buf, " else if #[cfg({})] {{", branch[0].1.text);
+                }
+                let mut prev: Option<&ast::Item> = None;
+                for (item, _) in branch {
+                    let newlines = prev.map_or(1, |prev| newlines_between(prev, item));
+                    buf.push_str(&"\n".repeat(newlines));
+                    format_to!(buf, "{}{}", item_indent, without_cfg(item, item_indent));
+                    prev = Some(item);
+                }
+                format_to!(buf, "\n{}}}", indent + 1);
+            }
+            format_to!(buf, "\n{}}}", indent);
+            builder.replace(target, buf);
+        },
+    )
+}
+
+// Assist: convert_cfg_if_to_cfg
+//
+// Unfolds a `cfg_if!` block into items gated on `#[cfg]` attributes.
+//
+// ```
+// cfg_if::cfg_if!$0 {
+//     if #[cfg(unix)] {
+//         fn platform() -> &'static str { "unix" }
+//     } else {
+//         fn platform() -> &'static str { "other" }
+//     }
+// }
+// ```
+// ->
+// ```
+// #[cfg(unix)]
+// fn platform() -> &'static str { "unix" }
+//
+// #[cfg(not(unix))]
+// fn platform() -> &'static str { "other" }
+// ```
+pub(crate) fn convert_cfg_if_to_cfg(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let call = ctx.find_node_at_offset::<ast::MacroCall>()?;
+    let name = call.path()?.segment()?.name_ref()?;
+    if name.text() != "cfg_if" {
+        return None;
+    }
+    let branches = cfg_if_branches(&call.token_tree()?)?;
+
+    let target = call.syntax().text_range();
+    acc.add(
+        AssistId("convert_cfg_if_to_cfg", AssistKind::RefactorRewrite),
+        "Convert to `#[cfg]` attributes",
+        target,
+        |builder| {
+            let indent = IndentLevel::from_node(call.syntax());
+            let mut conditions: Vec<&str> = Vec::new();
+            let mut chunks = Vec::new();
+            for (condition, body) in &branches {
+                let previous = match conditions.as_slice() {
+                    [] => None,
+                    [it] => Some(format!("not({})", it)),
+                    it => Some(format!("not(any({}))", it.iter().join(", "))),
+                };
+                let predicate = match (previous, condition) {
+                    (None, Some(condition)) => condition.to_string(),
+                    (Some(previous), Some(condition)) => {
+                        format!("all({}, {})", previous, condition)
+                    }
+                    (Some(previous), None) => previous,
+                    (None, None) => continue,
+                };
+                conditions.extend(condition.as_deref());
+
+                let body = ast::SourceFile::parse(body).tree();
+                let mut chunk = String::new();
+                let mut prev: Option<ast::Item> = None;
+                for item in body.items() {
+                    if let Some(prev) = &prev {
+                        chunk.push_str(&"\n".repeat(newlines_between(prev, &item)));
+                        format_to!(chunk, "{}", indent);
+                    }
+                    let text = item.dedent(IndentLevel::from_node(item.syntax())).indent(indent);
+                    format_to!(chunk, "#[cfg({})]\n{}{}", predicate, indent, text);
+                    prev = Some(item);
+                }
+                if !chunk.is_empty() {
+                    chunks.push(chunk);
+                }
+            }
+            builder.replace(target, chunks.join(&format!("\n\n{}", indent)));
+        },
+    )
+}
+
+struct CfgPredicate {
+    /// The source text of the predicate.
+    text: String,
+    /// The predicate without whitespace, to compare predicates.
+    key: String,
+}
+
+/// The predicate of the only `#[cfg]` of `item`.
+fn cfg_predicate(item: &ast::Item) -> Option<CfgPredicate> {
+    let (attr,) = item.attrs().filter(is_cfg).collect_tuple()?;
+    let (_, tt) = attr.as_simple_call()?;
+    predicate_of(&tt)
+}
+
+fn is_cfg(attr: &ast::Attr) -> bool {
+    matches!(attr.as_simple_call(), Some((name, _)) if name == "cfg")
+}
+
+/// The predicate inside the parentheses of `tt`.
+fn predicate_of(tt: &ast::TokenTree) -> Option<CfgPredicate> {
+    let text = tt.syntax().text().to_string();
+    let text = text.strip_prefix('(')?.strip_suffix(')')?.trim().to_string();
+    let key = text.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    if key.is_empty() {
+        return None;
+    }
+    Some(CfgPredicate { text, key })
+}
+
+/// Where the branches ending with `branches`' last one start, if the last is
+/// the complement of the preceding ones: `not(a)` after `a`, or
+/// `not(any(a, b))` after `a` and `b`.
+fn else_branch_start(branches: &[Vec<(ast::Item, CfgPredicate)>]) -> Option<usize> {
+    let (else_branch, preceding) = branches.split_last()?;
+    let negated = else_branch[0].1.key.strip_prefix("not(")?.strip_suffix(')')?;
+    let keys = preceding.iter().map(|branch| branch[0].1.key.as_str()).collect::<Vec<_>>();
+    if keys.last() == Some(&negated) {
+        return Some(preceding.len() - 1);
+    }
+    let alternatives = negated.strip_prefix("any(")?.strip_suffix(')')?;
+    let alternatives = split_top_level(alternatives);
+    if alternatives.len() < 2 || alternatives.len() > keys.len() {
+        return None;
+    }
+    let start = keys.len() - alternatives.len();
+    (keys[start..] == alternatives[..]).then(|| start)
+}
+
+/// Splits `a,b(c,d),` at the commas outside of parentheses.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                res.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    if start < text.len() {
+        res.push(&text[start..]);
+    }
+    res
+}
+
+/// The text of `item` without its `#[cfg]`, indented to `indent`.
+fn without_cfg(item: &ast::Item, indent: IndentLevel) -> ast::Item {
+    let item = item.dedent(IndentLevel::from_node(item.syntax())).clone_for_update();
+    if let Some(attr) = item.attrs().find(is_cfg) {
+        let start = SyntaxElement::from(attr.syntax().clone());
+        let end = match attr.syntax().next_sibling_or_token() {
+            Some(ws) if ws.kind() == SyntaxKind::WHITESPACE => ws,
+            _ => start.clone(),
+        };
+        ted::remove_all(start..=end);
+    }
+    item.indent(indent)
+}
+
+/// The number of line breaks between two sibling items, to keep blank lines.
+fn newlines_between(prev: &ast::Item, next: &ast::Item) -> usize {
+    let newlines = prev
+        .syntax()
+        .siblings_with_tokens(Direction::Next)
+        .skip(1)
+        .take_while(|it| it.as_node() != Some(next.syntax()))
+        .filter(|it| it.kind() == SyntaxKind::WHITESPACE)
+        .map(|it| it.to_string().matches('\n').count())
+        .sum::<usize>();
+    newlines.max(1)
+}
+
+/// The `#[cfg]` predicates and bodies of the `if #[cfg(..)] { .. } else ..`
+/// chain of a `cfg_if!`, the predicate of the `else` branch is `None`.
+fn cfg_if_branches(tt: &ast::TokenTree) -> Option<Vec<(Option<String>, String)>> {
+    let mut elements =
+        tt.syntax().children_with_tokens().filter(|it| !it.kind().is_trivia()).skip(1);
+    let mut branches = Vec::new();
+    let mut next = elements.next()?;
+    loop {
+        let condition = if next.kind() == T![if] {
+            Some(cfg_condition(elements.next()?, elements.next()?)?)
+        } else if branches.is_empty() {
+            return None;
+        } else {
+            None
+        };
+        let body = if condition.is_some() { elements.next()? } else { next };
+        let body = ast::TokenTree::cast(body.into_node()?)?.syntax().text().to_string();
+        let body = body.strip_prefix('{')?.strip_suffix('}')?.to_string();
+        let is_else = condition.is_none();
+        branches.push((condition, body));
+
+        match elements.next()? {
+            it if it.kind() == T![else] && !is_else => next = elements.next()?,
+            it if matches!(it.kind(), T!['}'] | T![')'] | T![']']) => return Some(branches),
+            _ => return None,
+        }
+    }
+}
+
+/// The predicate of `#[cfg(..)]`, given as its `#` and `[..]`.
+fn cfg_condition(hash: SyntaxElement, attr: SyntaxElement) -> Option<String> {
+    if hash.kind() != T![#] {
+        return None;
+    }
+    let attr = ast::TokenTree::cast(attr.into_node()?)?;
+    let (_, name, args, _) =
+        attr.syntax().children_with_tokens().filter(|it| !it.kind().is_trivia()).next_tuple()?;
+    if name.as_token()?.text() != "cfg" {
+        return None;
+    }
+    Some(predicate_of(&ast::TokenTree::cast(args.into_node()?)?)?.text)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn converts_cfg_not_pair() {
+        check_assist(
+            convert_cfg_to_cfg_if,
+            r#"
+mod m {
+    #[cfg(unix)]
+    use std::os::unix::ffi::OsStrExt;
+
+    /// Docs
+    #[cfg(u$0nix)]
+    fn f() {
+        unix()
+    }
+    #[cfg(not(unix))]
+    fn f() {
+        other()
+    }
+}
+"#,
+            r#"
+mod m {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::os::unix::ffi::OsStrExt;
+
+            /// Docs
+            fn f() {
+                unix()
+            }
+        } else {
+            fn f() {
+                other()
+            }
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn converts_cfg_not_any_chain() {
+        check_assist(
+            convert_cfg_to_cfg_if,
+            r#"
+#[cfg(feature = "std")]
+fn f() {}
+#[cfg(unix)]
+fn g() {}
+#[cfg(windows)]
+fn g() {}
+#[cfg(not(any(unix, windows)))]
+fn g$0() {}
+"#,
+            r#"
+#[cfg(feature = "std")]
+fn f() {}
+cfg_if::cfg_if! {
+    if #[cfg(unix)] {
+        fn g() {}
+    } else if #[cfg(windows)] {
+        fn g() {}
+    } else {
+        fn g() {}
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_without_complement() {
+        check_assist_not_applicable(
+            convert_cfg_to_cfg_if,
+            r#"
+#[cfg(unix)]
+fn f$0() {}
+#[cfg(not(windows))]
+fn f() {}
+"#,
+        );
+        check_assist_not_applicable(
+            convert_cfg_to_cfg_if,
+            r#"
+#[cfg(unix)]
+#[cfg(test)]
+fn f$0() {}
+#[cfg(not(unix))]
+fn f() {}
+"#,
+        );
+    }
+
+    #[test]
+    fn unfolds_cfg_if_chain() {
+        check_assist(
+            convert_cfg_if_to_cfg,
+            r#"
+mod m {
+    cfg_if::cfg_if$0! {
+        if #[cfg(unix)] {
+            fn f() {
+                unix()
+            }
+            fn g() {}
+        } else if #[cfg(windows)] {
+            fn f() {}
+        } else {
+            fn f() {}
+        }
+    }
+}
+"#,
+            r#"
+mod m {
+    #[cfg(unix)]
+    fn f() {
+        unix()
+    }
+    #[cfg(unix)]
+    fn g() {}
+
+    #[cfg(all(not(unix), windows))]
+    fn f() {}
+
+    #[cfg(not(any(unix, windows)))]
+    fn f() {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_other_macros() {
+        check_assist_not_applicable(
+            convert_cfg_if_to_cfg,
+            r#"
+foo$0! {
+    if #[cfg(unix)] {
+        fn f() {}
+    }
+}
+"#,
+        );
+    }
+}
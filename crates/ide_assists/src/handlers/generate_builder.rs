@@ -0,0 +1,334 @@
+use ide_db::helpers::FamousDefs;
+use itertools::Itertools;
+use stdx::format_to;
+use syntax::{
+    ast::{self, AttrsOwner, GenericParamsOwner, NameOwner, StructKind, VisibilityOwner},
+    AstNode,
+};
+
+use crate::{
+    utils::{
+        find_struct_impl, generate_companion_impl_text, generate_impl_text, generic_args_text,
+    },
+    AssistContext, AssistId, AssistKind, Assists,
+};
+
+// Assist: generate_builder
+//
+// Generates a builder for a struct with named fields. Fields whose type
+// implements `Default` can be left unset, `build` returns an error if any
+// other field is missing.
+//
+// ```
+// # //- minicore: default
+// # struct Url;
+// # struct Headers;
+// # impl Default for Headers { fn default() -> Self { Headers } }
+// struct Request {
+//     url: Url,$0
+//     headers: Headers,
+// }
+// ```
+// ->
+// ```
+// # struct Url;
+// # struct Headers;
+// # impl Default for Headers { fn default() -> Self { Headers } }
+// struct Request {
+//     url: Url,
+//     headers: Headers,
+// }
+//
+// struct ${1:RequestBuilder} {
+//     url: Option<Url>,
+//     headers: Option<Headers>,
+// }
+//
+// impl Request {
+//     fn builder() -> ${1:RequestBuilder} {
+//         ${1:RequestBuilder} { url: None, headers: None }
+//     }
+// }
+//
+// impl ${1:RequestBuilder} {
+//     fn url(mut self, url: Url) -> Self {
+//         self.url = Some(url);
+//         self
+//     }
+//
+//     fn headers(mut self, headers: Headers) -> Self {
+//         self.headers = Some(headers);
+//         self
+//     }
+//
+//     fn build(self) -> Result<Request, &'static str> {
+//         Ok(Request {
+//             url: self.url.ok_or("`url` is not set")?,
+//             headers: self.headers.unwrap_or_default(),
+//         })
+//     }
+// }$0
+// ```
+pub(crate) fn generate_builder(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let strukt = ctx.find_node_at_offset::<ast::Struct>()?;
+    let field_list = match strukt.kind() {
+        StructKind::Record(named) => named,
+        _ => return None,
+    };
+    let adt = ast::Adt::Struct(strukt.clone());
+    // Not applicable if there is a `builder` fn already.
+    find_struct_impl(ctx, &adt, "builder")?;
+
+    let default_trait = ctx.sema.to_def(&strukt).and_then(|it| {
+        FamousDefs(&ctx.sema, Some(it.module(ctx.db()).krate())).core_default_Default()
+    });
+    let mut fields = Vec::new();
+    for field in field_list.fields() {
+        let name = field.name()?;
+        let ty = field.ty()?;
+        let has_default = default_trait.map_or(false, |default_trait| {
+            ctx.sema
+                .to_def(&field)
+                .map_or(false, |it| it.ty(ctx.db()).impls_trait(ctx.db(), default_trait, &[]))
+        });
+        fields.push((name, ty, has_default));
+    }
+
+    let strukt_name = strukt.name()?.to_string();
+    let target = strukt.syntax().text_range();
+    acc.add(
+        AssistId("generate_builder", AssistKind::Generate),
+        "Generate a builder",
+        target,
+        |builder| {
+            let builder_name = match ctx.config.snippet_cap {
+                Some(_) => format!("${{1:{}Builder}}", strukt_name),
+                None => format!("{}Builder", strukt_name),
+            };
+            let vis = strukt.visibility().map_or(String::new(), |v| format!("{} ", v));
+            let generic_args = generic_args_text(&adt);
+
+            let mut buf = String::with_capacity(512);
+            buf.push_str("\n\n");
+            for attr in strukt
+                .attrs()
+                .filter(|attr| attr.as_simple_call().map_or(false, |(name, _)| name == "cfg"))
+            {
+                format_to!(buf, "{}\n", attr);
+            }
+            format_to!(buf, "{}struct {}", vis, builder_name);
+            if let Some(generic_params) = strukt.generic_param_list() {
+                format_to!(buf, "{}", generic_params);
+            }
+            match strukt.where_clause() {
+                Some(where_clause) => format_to!(buf, "\n{}\n{{\n", where_clause),
+                None => buf.push_str(" {\n"),
+            }
+            for (name, ty, _) in &fields {
+                format_to!(buf, "    {}: Option<{}>,\n", name, ty);
+            }
+            buf.push('}');
+
+            let unset = fields.iter().map(|(name, _, _)| format!("{}: None", name)).format(", ");
+            let builder_fn = format!(
+                "    {}fn builder() -> {}{} {{\n        {} {{ {} }}\n    }}",
+                vis, builder_name, generic_args, builder_name, unset
+            );
+            buf.push_str(&generate_impl_text(&adt, &builder_fn));
+
+            let mut methods = String::new();
+            for (name, ty, _) in &fields {
+                format_to!(
+                    methods,
+                    "    {}fn {}(mut self, {}: {}) -> Self {{\n",
+                    vis,
+                    name,
+                    name,
+                    ty
+                );
+                format_to!(methods, "        self.{} = Some({});\n", name, name);
+                methods.push_str("        self\n    }\n\n");
+            }
+            let all_default = fields.iter().all(|(_, _, has_default)| *has_default);
+            let field_values = fields
+                .iter()
+                .map(|(name, _, has_default)| {
+                    if *has_default {
+                        format!("{}: self.{}.unwrap_or_default()", name, name)
+                    } else {
+                        format!("{}: self.{}.ok_or(\"`{}` is not set\")?", name, name, name)
+                    }
+                })
+                .collect::<Vec<_>>();
+            let self_ty = format!("{}{}", strukt_name, generic_args);
+            let (ret_ty, wrap) = if all_default {
+                (self_ty, "")
+            } else {
+                (format!("Result<{}, &'static str>", self_ty), "Ok(")
+            };
+            format_to!(methods, "    {}fn build(self) -> {} {{\n", vis, ret_ty);
+            format_to!(methods, "        {}{} {{\n", wrap, strukt_name);
+            for value in &field_values {
+                format_to!(methods, "            {},\n", value);
+            }
+            format_to!(methods, "        }}{}\n    }}", if all_default { "" } else { ")" });
+            buf.push_str(&generate_companion_impl_text(&adt, &builder_name, &methods));
+
+            let offset = strukt.syntax().text_range().end();
+            match ctx.config.snippet_cap {
+                Some(cap) => {
+                    buf.push_str("$0");
+                    builder.insert_snippet(cap, offset, buf);
+                }
+                None => builder.insert(offset, buf),
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn builder_with_required_and_default_fields() {
+        check_assist(
+            generate_builder,
+            r#"
+//- minicore: default
+struct Name;
+impl Default for Name {
+    fn default() -> Self { Name }
+}
+pub struct Config$0 {
+    name: Name,
+    id: Id,
+}
+struct Id;
+"#,
+            r#"
+struct Name;
+impl Default for Name {
+    fn default() -> Self { Name }
+}
+pub struct Config {
+    name: Name,
+    id: Id,
+}
+
+pub struct ${1:ConfigBuilder} {
+    name: Option<Name>,
+    id: Option<Id>,
+}
+
+impl Config {
+    pub fn builder() -> ${1:ConfigBuilder} {
+        ${1:ConfigBuilder} { name: None, id: None }
+    }
+}
+
+impl ${1:ConfigBuilder} {
+    pub fn name(mut self, name: Name) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn build(self) -> Result<Config, &'static str> {
+        Ok(Config {
+            name: self.name.unwrap_or_default(),
+            id: self.id.ok_or("`id` is not set")?,
+        })
+    }
+}$0
+struct Id;
+"#,
+        );
+    }
+
+    #[test]
+    fn builder_without_required_fields_returns_the_struct() {
+        check_assist(
+            generate_builder,
+            r#"
+//- minicore: default
+struct Name;
+impl Default for Name {
+    fn default() -> Self { Name }
+}
+struct Config<T> where T: Clone {$0
+    name: Name,
+}
+"#,
+            r#"
+struct Name;
+impl Default for Name {
+    fn default() -> Self { Name }
+}
+struct Config<T> where T: Clone {
+    name: Name,
+}
+
+struct ${1:ConfigBuilder}<T>
+where T: Clone
+{
+    name: Option<Name>,
+}
+
+impl<T> Config<T>
+where T: Clone
+{
+    fn builder() -> ${1:ConfigBuilder}<T> {
+        ${1:ConfigBuilder} { name: None }
+    }
+}
+
+impl<T> ${1:ConfigBuilder}<T>
+where T: Clone
+{
+    fn name(mut self, name: Name) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    fn build(self) -> Config<T> {
+        Config {
+            name: self.name.unwrap_or_default(),
+        }
+    }
+}$0
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_with_existing_builder_fn() {
+        check_assist_not_applicable(
+            generate_builder,
+            r#"
+struct Config$0 {
+    id: u32,
+}
+impl Config {
+    fn builder() {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_tuple_structs() {
+        check_assist_not_applicable(
+            generate_builder,
+            r#"
+struct Config$0(u32);
+"#,
+        );
+    }
+}
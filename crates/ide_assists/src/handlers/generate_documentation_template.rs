@@ -0,0 +1,321 @@
+use hir::{AsAssocItem, AssocItemContainer};
+use ide_db::helpers::FamousDefs;
+use stdx::format_to;
+use syntax::{
+    ast::{self, edit::IndentLevel, AttrsOwner, DocCommentsOwner, VisibilityOwner},
+    AstNode, SyntaxKind, TextRange,
+};
+
+use crate::{AssistContext, AssistId, AssistKind, Assists};
+
+// Assist: generate_documentation_template
+//
+// Adds a documentation comment to an undocumented function or struct, with the
+// sections its signature and body call for: `# Errors` for a `Result` return
+// type, `# Panics` if the body can panic, `# Safety` for `unsafe` functions and
+// `# Examples` for public items.
+//
+// ```
+// pub fn parse_$0len(input: &str) -> usize {
+//     input.parse().unwrap()
+// }
+// ```
+// ->
+// ```
+// /// ${0:Parse len.}
+// ///
+// /// # Panics
+// ///
+// /// Panics if .
+// ///
+// /// # Examples
+// ///
+// /// ```
+// /// use test::parse_len;
+// /// ```
+// pub fn parse_len(input: &str) -> usize {
+//     input.parse().unwrap()
+// }
+// ```
+pub(crate) fn generate_documentation_template(
+    acc: &mut Assists,
+    ctx: &AssistContext,
+) -> Option<()> {
+    let name = ctx.find_node_at_offset::<ast::Name>()?;
+    let item = name.syntax().parent()?;
+    let (sections, use_path, target) = if let Some(func) = ast::Fn::cast(item.clone()) {
+        if is_documented(&func) {
+            return None;
+        }
+        let sections = fn_sections(ctx, &func);
+        (sections, use_path(ctx, &func), func.syntax().text_range())
+    } else if let Some(strukt) = ast::Struct::cast(item.clone()) {
+        if is_documented(&strukt) {
+            return None;
+        }
+        let path = is_public(&strukt)
+            .then(|| item_path(ctx, ctx.sema.to_def(&strukt)?.module(ctx.db()), &name.text()))
+            .flatten();
+        (Vec::new(), path, strukt.syntax().text_range())
+    } else {
+        return None;
+    };
+
+    acc.add(
+        AssistId("generate_documentation_template", AssistKind::Generate),
+        "Generate a documentation template",
+        TextRange::new(target.start(), name.syntax().text_range().end()),
+        |builder| {
+            let indent = IndentLevel::from_node(&item);
+            let summary = sentence(&name.text());
+            let mut lines = vec![match ctx.config.snippet_cap {
+                Some(_) => format!("${{0:{}}}", summary),
+                None => summary,
+            }];
+            for (title, text) in sections {
+                lines.extend(vec![
+                    String::new(),
+                    format!("# {}", title),
+                    String::new(),
+                    text.into(),
+                ]);
+            }
+            if let Some(path) = use_path {
+                lines.extend(["", "# Examples", "", "```"].iter().map(|it| it.to_string()));
+                lines.push(format!("use {};", path));
+                lines.push("```".to_string());
+            }
+
+            let mut buf = String::new();
+            for line in lines {
+                let line = format!("/// {}", line);
+                format_to!(buf, "{}\n{}", line.trim_end(), indent);
+            }
+            let offset = item.text_range().start();
+            match ctx.config.snippet_cap {
+                Some(cap) => builder.insert_snippet(cap, offset, buf),
+                None => builder.insert(offset, buf),
+            }
+        },
+    )
+}
+
+fn is_documented(item: &(impl DocCommentsOwner + AttrsOwner)) -> bool {
+    item.doc_comments().next().is_some()
+        || item.attrs().any(|attr| attr.simple_name().as_deref() == Some("doc"))
+}
+
+fn is_public(item: &impl VisibilityOwner) -> bool {
+    item.visibility().map_or(false, |it| it.syntax().text() == "pub")
+}
+
+/// The sections the documentation of `func` needs, with their placeholder text.
+fn fn_sections(ctx: &AssistContext, func: &ast::Fn) -> Vec<(&'static str, &'static str)> {
+    let mut sections = Vec::new();
+    let returns_result = ctx.sema.to_def(func).map_or(false, |func| {
+        let result =
+            FamousDefs(&ctx.sema, Some(func.module(ctx.db()).krate())).core_result_Result();
+        let ret_type = func.ret_type(ctx.db());
+        result.is_some() && ret_type.as_adt() == result.map(hir::Adt::Enum)
+    });
+    if returns_result {
+        sections.push(("Errors", "This function will return an error if ."));
+    }
+    if func.body().map_or(false, |body| can_panic(&body)) {
+        sections.push(("Panics", "Panics if ."));
+    }
+    if func.unsafe_token().is_some() {
+        sections.push(("Safety", "."));
+    }
+    sections
+}
+
+/// Whether `body` calls a panicking macro, or `unwrap`/`expect`.
+fn can_panic(body: &ast::BlockExpr) -> bool {
+    body.syntax().descendants().any(|node| match node.kind() {
+        SyntaxKind::MACRO_CALL => ast::MacroCall::cast(node)
+            .and_then(|call| call.path())
+            .and_then(|path| path.segment())
+            .and_then(|segment| segment.name_ref())
+            .map_or(false, |name| {
+                matches!(
+                    name.text().as_str(),
+                    "panic"
+                        | "unreachable"
+                        | "todo"
+                        | "unimplemented"
+                        | "assert"
+                        | "assert_eq"
+                        | "assert_ne"
+                )
+            }),
+        SyntaxKind::METHOD_CALL_EXPR => ast::MethodCallExpr::cast(node)
+            .and_then(|call| call.name_ref())
+            .map_or(false, |name| matches!(name.text().as_str(), "unwrap" | "expect")),
+        _ => false,
+    })
+}
+
+/// The path to import in the example of a public function: the function
+/// itself, or the type of its `impl`.
+fn use_path(ctx: &AssistContext, func: &ast::Fn) -> Option<String> {
+    if !is_public(func) {
+        return None;
+    }
+    let def = ctx.sema.to_def(func)?;
+    match def.as_assoc_item(ctx.db()).map(|it| it.container(ctx.db())) {
+        Some(AssocItemContainer::Impl(impl_)) => {
+            let adt = impl_.self_ty(ctx.db()).as_adt()?;
+            item_path(ctx, adt.module(ctx.db()), &adt.name(ctx.db()).to_string())
+        }
+        Some(AssocItemContainer::Trait(_)) => None,
+        None => item_path(ctx, def.module(ctx.db()), &def.name(ctx.db()).to_string()),
+    }
+}
+
+/// `krate::module::name`, for an item `name` in `module`.
+fn item_path(ctx: &AssistContext, module: hir::Module, name: &str) -> Option<String> {
+    let krate = module.krate().display_name(ctx.db())?.to_string().replace('-', "_");
+    let mut segments = vec![krate];
+    for module in module.path_to_root(ctx.db()).into_iter().rev() {
+        if let Some(name) = module.name(ctx.db()) {
+            segments.push(name.to_string());
+        }
+    }
+    segments.push(name.to_string());
+    Some(segments.join("::"))
+}
+
+/// `parse_len` and `ParseLen` become `Parse len.`
+fn sentence(name: &str) -> String {
+    let mut words = Vec::new();
+    for part in name.split('_').filter(|it| !it.is_empty()) {
+        let mut word = String::new();
+        for c in part.chars() {
+            if c.is_uppercase() && !word.is_empty() {
+                words.push(word);
+                word = String::new();
+            }
+            word.push(c.to_ascii_lowercase());
+        }
+        words.push(word);
+    }
+    let mut sentence = words.join(" ");
+    if let Some(first) = sentence.get(..1).map(str::to_uppercase) {
+        sentence.replace_range(..1, &first);
+    }
+    sentence.push('.');
+    sentence
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn private_fn_without_sections() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+fn add_$0one(x: u32) -> u32 {
+    x + 1
+}
+"#,
+            r#"
+/// ${0:Add one.}
+fn add_one(x: u32) -> u32 {
+    x + 1
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn errors_and_safety_sections() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+//- minicore: result
+mod m {
+    pub struct Error;
+    impl Error {
+        pub unsafe fn from_$0raw(raw: *const u8) -> Result<Error, ()> {
+            Ok(Error)
+        }
+    }
+}
+"#,
+            r#"
+mod m {
+    pub struct Error;
+    impl Error {
+        /// ${0:From raw.}
+        ///
+        /// # Errors
+        ///
+        /// This function will return an error if .
+        ///
+        /// # Safety
+        ///
+        /// .
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use test::m::Error;
+        /// ```
+        pub unsafe fn from_raw(raw: *const u8) -> Result<Error, ()> {
+            Ok(Error)
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn public_struct() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+#[derive(Debug)]
+pub struct Http$0Client {}
+"#,
+            r#"
+/// ${0:Http client.}
+///
+/// # Examples
+///
+/// ```
+/// use test::HttpClient;
+/// ```
+#[derive(Debug)]
+pub struct HttpClient {}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_documented_items() {
+        check_assist_not_applicable(
+            generate_documentation_template,
+            r#"
+/// Adds one.
+fn add_$0one(x: u32) -> u32 {
+    x + 1
+}
+"#,
+        );
+        check_assist_not_applicable(
+            generate_documentation_template,
+            r#"
+#[doc = "Adds one."]
+fn add_$0one(x: u32) -> u32 {
+    x + 1
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use hir::{Adt, Field, StructKind, Variant};
+use ide_db::{base_db::Edition, helpers::FamousDefs, RootDatabase};
+use itertools::Itertools;
+use stdx::format_to;
+use syntax::ast::{self, AstNode};
+
+use crate::{
+    utils::generate_trait_impl_text, AssistContext, AssistId, AssistKind, Assists, GroupLabel,
+};
+
+// Assist: generate_from_impl_for_similar_type
+//
+// Adds a `From` impl converting from another struct or enum in scope which
+// shares fields or variants with this one. Fields are mapped by name and type,
+// the others are left as `todo!()`. When not all variants of the other enum
+// have a counterpart, a `TryFrom` impl is offered too, which fails for those
+// variants and returns the value as the error.
+//
+// ```
+// struct Point2 { x: f32, y: f32 }
+// struct $0Point3 { x: f32, y: f32, z: f32 }
+// ```
+// ->
+// ```
+// struct Point2 { x: f32, y: f32 }
+// struct Point3 { x: f32, y: f32, z: f32 }
+//
+// impl From<Point2> for Point3 {
+//     fn from(value: Point2) -> Self {
+//         Self {
+//             x: value.x,
+//             y: value.y,
+//             z: todo!(),
+//         }
+//     }
+// }
+// ```
+pub(crate) fn generate_from_impl_for_similar_type(
+    acc: &mut Assists,
+    ctx: &AssistContext,
+) -> Option<()> {
+    let name = ctx.find_node_at_offset::<ast::Name>()?;
+    let adt = ast::Adt::cast(name.syntax().parent()?)?;
+    let self_def = match &adt {
+        ast::Adt::Struct(it) => Adt::Struct(ctx.sema.to_def(it)?),
+        ast::Adt::Enum(it) => Adt::Enum(ctx.sema.to_def(it)?),
+        ast::Adt::Union(_) => return None,
+    };
+    let scope = ctx.sema.scope(adt.syntax());
+    let krate = scope.module()?.krate();
+    let db = ctx.db();
+
+    let mut candidates = Vec::new();
+    scope.process_all_names(&mut |name, def| {
+        if let hir::ScopeDef::ModuleDef(hir::ModuleDef::Adt(other)) = def {
+            if other != self_def && hir::GenericDef::from(other).params(db).is_empty() {
+                candidates.push((name.to_string(), other));
+            }
+        }
+    });
+    candidates.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let from_trait = FamousDefs(&ctx.sema, Some(krate)).core_convert_From();
+    let try_from_trait =
+        if krate.edition(db) >= Edition::Edition2021 { "TryFrom" } else { "std::convert::TryFrom" };
+    let group = GroupLabel("Generate conversion from a similar type".to_owned());
+    let target = adt.syntax().text_range();
+    for (other_name, other) in candidates {
+        if let Some(from_trait) = from_trait {
+            if self_def.ty(db).impls_trait(db, from_trait, &[other.ty(db)]) {
+                continue;
+            }
+        }
+        let conversion = match (self_def, other) {
+            (Adt::Struct(it), Adt::Struct(other)) => {
+                struct_conversion(db, it.kind(db), &it.fields(db), &other.fields(db))
+            }
+            (Adt::Enum(it), Adt::Enum(other)) => {
+                enum_conversion(db, &other_name, &it.variants(db), &other.variants(db))
+            }
+            _ => None,
+        };
+        let conversion = match conversion {
+            Some(it) => it,
+            None => continue,
+        };
+
+        // A `TryFrom` fails for the unmatched variants instead of leaving
+        // `todo!()`s for them, so it comes first.
+        if let Conversion::Enum { unmatched: true, .. } = conversion {
+            acc.add_group(
+                &group,
+                AssistId("generate_from_impl_for_similar_type", AssistKind::Generate),
+                format!("Generate `TryFrom<{}>` for `{}`", other_name, name),
+                target,
+                |edit| {
+                    let mut code = format!("    type Error = {};\n\n", other_name);
+                    format_to!(code, "    fn try_from(value: {}) ", other_name);
+                    format_to!(code, "-> Result<Self, Self::Error> {{\n");
+                    format_to!(code, "        {}\n    }}", conversion.try_from_body());
+                    let trait_text = format!("{}<{}>", try_from_trait, other_name);
+                    edit.insert(target.end(), generate_trait_impl_text(&adt, &trait_text, &code));
+                },
+            );
+        }
+
+        acc.add_group(
+            &group,
+            AssistId("generate_from_impl_for_similar_type", AssistKind::Generate),
+            format!("Generate `From<{}>` for `{}`", other_name, name),
+            target,
+            |edit| {
+                let code = format!(
+                    "    fn from(value: {}) -> Self {{\n        {}\n    }}",
+                    other_name,
+                    conversion.from_body(),
+                );
+                let from_trait = format!("From<{}>", other_name);
+                edit.insert(target.end(), generate_trait_impl_text(&adt, &from_trait, &code));
+            },
+        );
+    }
+    Some(())
+}
+
+enum Conversion {
+    /// The expression building `Self` from `value`.
+    Struct(String),
+    /// The arms matching on `value`, with the expression building `Self`, and
+    /// whether some variants of the other enum have no counterpart.
+    Enum { arms: Vec<(String, Option<String>)>, unmatched: bool },
+}
+
+impl Conversion {
+    fn from_body(&self) -> String {
+        match self {
+            Conversion::Struct(expr) => expr.clone(),
+            Conversion::Enum { arms, .. } => {
+                let arms = arms.iter().map(|(pat, expr)| {
+                    format!("{} => {},", pat, expr.as_deref().unwrap_or("todo!()"))
+                });
+                format!("match value {{\n            {}\n        }}", arms.format("\n            "))
+            }
+        }
+    }
+
+    /// Converts the matched variants, the others are returned as `Err(value)`.
+    fn try_from_body(&self) -> String {
+        let arms = match self {
+            Conversion::Struct(expr) => return format!("Ok({})", expr),
+            Conversion::Enum { arms, .. } => arms,
+        };
+        let arms = arms
+            .iter()
+            .filter_map(|(pat, expr)| Some(format!("{} => Ok({}),", pat, expr.as_deref()?)))
+            .chain(Some("value => Err(value),".to_string()));
+        format!("match value {{\n            {}\n        }}", arms.format("\n            "))
+    }
+}
+
+/// Whether each of `fields` has a field with the same name and type in
+/// `other_fields`.
+fn matching_fields(db: &RootDatabase, fields: &[Field], other_fields: &[Field]) -> Vec<bool> {
+    fields
+        .iter()
+        .map(|field| {
+            let ty = field.ty(db);
+            other_fields.iter().any(|other| {
+                other.name(db) == field.name(db) && !ty.is_unknown() && other.ty(db) == ty
+            })
+        })
+        .collect()
+}
+
+fn struct_conversion(
+    db: &RootDatabase,
+    kind: StructKind,
+    fields: &[Field],
+    other_fields: &[Field],
+) -> Option<Conversion> {
+    let matching = matching_fields(db, fields, other_fields);
+    if !matching.contains(&true) {
+        return None;
+    }
+    let values = fields.iter().zip(matching).map(|(field, matches)| {
+        if matches {
+            format!("value.{}", field.name(db))
+        } else {
+            "todo!()".to_string()
+        }
+    });
+    let expr = match kind {
+        StructKind::Record => {
+            let fields = fields
+                .iter()
+                .zip(values)
+                .map(|(field, value)| format!("            {}: {},\n", field.name(db), value));
+            format!("Self {{\n{}        }}", fields.format(""))
+        }
+        StructKind::Tuple => format!("Self({})", values.format(", ")),
+        StructKind::Unit => return None,
+    };
+    Some(Conversion::Struct(expr))
+}
+
+fn enum_conversion(
+    db: &RootDatabase,
+    other_name: &str,
+    variants: &[Variant],
+    other_variants: &[Variant],
+) -> Option<Conversion> {
+    let mut arms = Vec::new();
+    let mut unmatched = false;
+    for other in other_variants {
+        let name = other.name(db);
+        let kind = other.kind(db);
+        let counterpart =
+            variants.iter().find(|it| it.name(db) == name && it.kind(db) == kind).copied();
+        let variant = match counterpart {
+            Some(it) => it,
+            None => {
+                unmatched = true;
+                let pat = match kind {
+                    StructKind::Record => format!("{}::{} {{ .. }}", other_name, name),
+                    StructKind::Tuple => format!("{}::{}(..)", other_name, name),
+                    StructKind::Unit => format!("{}::{}", other_name, name),
+                };
+                arms.push((pat, None));
+                continue;
+            }
+        };
+
+        let fields = variant.fields(db);
+        let other_fields = other.fields(db);
+        let matching = matching_fields(db, &fields, &other_fields);
+        let (pat, expr) = match kind {
+            StructKind::Record => {
+                let bound = fields
+                    .iter()
+                    .zip(&matching)
+                    .filter(|(_, &matches)| matches)
+                    .map(|(field, _)| field.name(db).to_string())
+                    .collect::<Vec<_>>();
+                let rest = if bound.len() < other_fields.len() { Some("..") } else { None };
+                let pat = bound.iter().map(String::as_str).chain(rest).format(", ");
+                let values = fields.iter().zip(&matching).map(|(field, &matches)| {
+                    if matches {
+                        field.name(db).to_string()
+                    } else {
+                        format!("{}: todo!()", field.name(db))
+                    }
+                });
+                (
+                    format!("{}::{} {{ {} }}", other_name, name, pat),
+                    format!("Self::{} {{ {} }}", name, values.format(", ")),
+                )
+            }
+            StructKind::Tuple => {
+                let pat = (0..other_fields.len()).map(|idx| match matching.get(idx) {
+                    Some(true) => format!("f{}", idx),
+                    _ => "_".to_string(),
+                });
+                let values = matching.iter().enumerate().map(|(idx, &matches)| {
+                    if matches {
+                        format!("f{}", idx)
+                    } else {
+                        "todo!()".to_string()
+                    }
+                });
+                (
+                    format!("{}::{}({})", other_name, name, pat.format(", ")),
+                    format!("Self::{}({})", name, values.format(", ")),
+                )
+            }
+            StructKind::Unit => (format!("{}::{}", other_name, name), format!("Self::{}", name)),
+        };
+        arms.push((pat, Some(expr)));
+    }
+    if arms.iter().all(|(_, expr)| expr.is_none()) {
+        return None;
+    }
+    Some(Conversion::Enum { arms, unmatched })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_by_label, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn struct_with_missing_field() {
+        check_assist(
+            generate_from_impl_for_similar_type,
+            r#"
+struct Point2 { x: f32, y: f32 }
+struct $0Point3 { x: f32, y: f32, z: f32 }
+"#,
+            r#"
+struct Point2 { x: f32, y: f32 }
+struct Point3 { x: f32, y: f32, z: f32 }
+
+impl From<Point2> for Point3 {
+    fn from(value: Point2) -> Self {
+        Self {
+            x: value.x,
+            y: value.y,
+            z: todo!(),
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn struct_field_with_other_type() {
+        check_assist(
+            generate_from_impl_for_similar_type,
+            r#"
+struct Row { id: i64, name: u8 }
+struct $0User { id: u32, name: u8 }
+"#,
+            r#"
+struct Row { id: i64, name: u8 }
+struct User { id: u32, name: u8 }
+
+impl From<Row> for User {
+    fn from(value: Row) -> Self {
+        Self {
+            id: todo!(),
+            name: value.name,
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn tuple_struct() {
+        check_assist(
+            generate_from_impl_for_similar_type,
+            r#"
+struct Meters(f64, u8);
+struct $0Feet(f64, u16);
+"#,
+            r#"
+struct Meters(f64, u8);
+struct Feet(f64, u16);
+
+impl From<Meters> for Feet {
+    fn from(value: Meters) -> Self {
+        Self(value.0, todo!())
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn enum_with_all_variants() {
+        check_assist(
+            generate_from_impl_for_similar_type,
+            r#"
+enum Status { Active, Blocked { reason: u32, since: u64 }, Renamed(u8, u16) }
+enum $0ApiStatus { Active, Blocked { reason: u32 }, Renamed(u8, u32) }
+"#,
+            r#"
+enum Status { Active, Blocked { reason: u32, since: u64 }, Renamed(u8, u16) }
+enum ApiStatus { Active, Blocked { reason: u32 }, Renamed(u8, u32) }
+
+impl From<Status> for ApiStatus {
+    fn from(value: Status) -> Self {
+        match value {
+            Status::Active => Self::Active,
+            Status::Blocked { reason, .. } => Self::Blocked { reason },
+            Status::Renamed(f0, _) => Self::Renamed(f0, todo!()),
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn enum_with_unmatched_variant() {
+        check_assist_by_label(
+            generate_from_impl_for_similar_type,
+            r#"
+enum Status { Active, Deleted }
+enum $0ApiStatus { Active }
+"#,
+            r#"
+enum Status { Active, Deleted }
+enum ApiStatus { Active }
+
+impl From<Status> for ApiStatus {
+    fn from(value: Status) -> Self {
+        match value {
+            Status::Active => Self::Active,
+            Status::Deleted => todo!(),
+        }
+    }
+}
+"#,
+            "Generate `From<Status>` for `ApiStatus`",
+        );
+    }
+
+    #[test]
+    fn enum_try_from() {
+        check_assist_by_label(
+            generate_from_impl_for_similar_type,
+            r#"
+enum Status { Active, Deleted }
+enum $0ApiStatus { Active }
+"#,
+            r#"
+enum Status { Active, Deleted }
+enum ApiStatus { Active }
+
+impl std::convert::TryFrom<Status> for ApiStatus {
+    type Error = Status;
+
+    fn try_from(value: Status) -> Result<Self, Self::Error> {
+        match value {
+            Status::Active => Ok(Self::Active),
+            value => Err(value),
+        }
+    }
+}
+"#,
+            "Generate `TryFrom<Status>` for `ApiStatus`",
+        );
+    }
+
+    #[test]
+    fn not_applicable_without_shared_fields() {
+        check_assist_not_applicable(
+            generate_from_impl_for_similar_type,
+            r#"
+struct Point2 { x: f32, y: f32 }
+struct $0Color { r: u8, g: u8, b: u8 }
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_when_from_exists() {
+        check_assist_not_applicable(
+            generate_from_impl_for_similar_type,
+            r#"
+//- minicore: from
+struct Point2 { x: f32, y: f32 }
+struct $0Point3 { x: f32, y: f32, z: f32 }
+impl From<Point2> for Point3 {
+    fn from(value: Point2) -> Self { todo!() }
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use hir::{AsAssocItem, HasSource, PathResolution};
+use ide_db::defs::Definition;
+use rustc_hash::FxHashMap;
+use syntax::{
+    ast::{self, make, GenericParamsOwner, NameOwner},
+    ted, AstNode, SyntaxKind, SyntaxNode,
+};
+
+use crate::{
+    assist_context::{AssistBuilder, AssistContext, Assists},
+    AssistId, AssistKind,
+};
+
+// Assist: inline_type_alias
+//
+// Replaces a type alias with the type it stands for.
+//
+// ```
+// type Pair<T> = (T, T);
+// fn swap(pair: Pai$0r<u32>) {}
+// ```
+// ->
+// ```
+// type Pair<T> = (T, T);
+// fn swap(pair: (u32, u32)) {}
+// ```
+pub(crate) fn inline_type_alias(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let name_ref = ctx.find_node_at_offset::<ast::NameRef>()?;
+    let path = name_ref.syntax().parent().and_then(ast::PathSegment::cast)?.parent_path();
+    let alias = resolve_alias(ctx, &path)?;
+    let usage = Usage::classify(path)?;
+    if let Usage::Import(_) = usage {
+        return None;
+    }
+    let alias = alias.source(ctx.db())?.value;
+    alias.ty()?;
+
+    let target = usage.syntax().text_range();
+    acc.add(
+        AssistId("inline_type_alias", AssistKind::RefactorInline),
+        "Inline type alias",
+        target,
+        |builder| {
+            usage.make_mut(builder).apply(&alias);
+        },
+    )
+}
+
+// Assist: inline_type_alias_uses
+//
+// Replaces every use of a type alias with the type it stands for, and removes
+// the alias.
+//
+// ```
+// type $0Pair<T> = (T, T);
+// fn swap(pair: Pair<u32>) -> Pair<u32> { (pair.1, pair.0) }
+// ```
+// ->
+// ```
+// fn swap(pair: (u32, u32)) -> (u32, u32) { (pair.1, pair.0) }
+// ```
+pub(crate) fn inline_type_alias_uses(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let (alias, target) = if let Some(name) = ctx.find_node_at_offset::<ast::Name>() {
+        let alias = name.syntax().parent().and_then(ast::TypeAlias::cast)?;
+        (ctx.sema.to_def(&alias)?, name.syntax().text_range())
+    } else {
+        let name_ref = ctx.find_node_at_offset::<ast::NameRef>()?;
+        let path = name_ref.syntax().parent().and_then(ast::PathSegment::cast)?.parent_path();
+        (resolve_alias(ctx, &path)?, path.syntax().text_range())
+    };
+    if alias.as_assoc_item(ctx.db()).is_some() {
+        return None;
+    }
+    let source = alias.source(ctx.db())?;
+    if source.file_id.is_macro() {
+        return None;
+    }
+    let alias_file = source.file_id.original_file(ctx.db());
+    let alias_node = source.value;
+    alias_node.ty()?;
+
+    let usages = Definition::ModuleDef(hir::ModuleDef::TypeAlias(alias)).usages(&ctx.sema).all();
+    let usages = usages
+        .into_iter()
+        .map(|(file_id, refs)| {
+            let mut usages = refs
+                .into_iter()
+                .map(|it| match it.name {
+                    ast::NameLike::NameRef(name_ref) => {
+                        let segment =
+                            name_ref.syntax().parent().and_then(ast::PathSegment::cast)?;
+                        Usage::classify(segment.parent_path())
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            // Inline the uses nested in the generic arguments of other uses
+            // first, so that the outer ones pick up the inlined arguments.
+            usages.sort_by_key(|it| std::cmp::Reverse(it.syntax().text_range().start()));
+            Some((file_id, usages))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    acc.add(
+        AssistId("inline_type_alias_uses", AssistKind::RefactorInline),
+        "Inline type alias into all uses",
+        target,
+        |builder| {
+            let mut alias_removed = false;
+            for (file_id, usages) in usages {
+                builder.edit_file(file_id);
+                let usages: Vec<_> = usages.into_iter().map(|it| it.make_mut(builder)).collect();
+                let alias_mut =
+                    (file_id == alias_file).then(|| builder.make_mut(alias_node.clone()));
+                for usage in usages {
+                    usage.apply(&alias_node);
+                }
+                if let Some(alias_mut) = alias_mut {
+                    remove_item(alias_mut.syntax());
+                    alias_removed = true;
+                }
+            }
+            if !alias_removed {
+                builder.edit_file(alias_file);
+                remove_item(builder.make_mut(alias_node.clone()).syntax());
+            }
+        },
+    )
+}
+
+fn resolve_alias(ctx: &AssistContext, path: &ast::Path) -> Option<hir::TypeAlias> {
+    match ctx.sema.resolve_path(path)? {
+        PathResolution::Def(hir::ModuleDef::TypeAlias(it))
+            if it.as_assoc_item(ctx.db()).is_none() =>
+        {
+            Some(it)
+        }
+        _ => None,
+    }
+}
+
+/// A path naming the alias, in a position where it can be inlined.
+enum Usage {
+    /// `Alias<T>` as a type.
+    Type(ast::PathType),
+    /// `Alias<T>` as the qualifier of another path, like in `Alias::new()`.
+    Qualifier(ast::Path),
+    /// `use module::Alias;`, which goes away with the alias.
+    Import(ast::UseTree),
+}
+
+impl Usage {
+    fn classify(path: ast::Path) -> Option<Usage> {
+        let parent = path.syntax().parent()?;
+        if let Some(it) = ast::PathType::cast(parent.clone()) {
+            return Some(Usage::Type(it));
+        }
+        if let Some(it) = ast::UseTree::cast(parent.clone()) {
+            let is_plain =
+                it.use_tree_list().is_none() && it.star_token().is_none() && it.rename().is_none();
+            return is_plain.then(|| Usage::Import(it));
+        }
+        let in_use_tree = path.top_path().syntax().parent().map_or(false, |it| {
+            matches!(it.kind(), SyntaxKind::USE_TREE | SyntaxKind::USE_TREE_LIST)
+        });
+        (ast::Path::can_cast(parent.kind()) && !in_use_tree).then(|| Usage::Qualifier(path))
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Usage::Type(it) => it.syntax(),
+            Usage::Qualifier(it) => it.syntax(),
+            Usage::Import(it) => it.syntax(),
+        }
+    }
+
+    fn make_mut(self, builder: &mut AssistBuilder) -> Usage {
+        match self {
+            Usage::Type(it) => Usage::Type(builder.make_mut(it)),
+            Usage::Qualifier(it) => Usage::Qualifier(builder.make_mut(it)),
+            Usage::Import(it) => Usage::Import(builder.make_mut(it)),
+        }
+    }
+
+    fn apply(self, alias: &ast::TypeAlias) -> Option<()> {
+        match self {
+            Usage::Type(it) => {
+                let ty = instantiate(alias, it.path()?.segment()?.generic_arg_list())?;
+                ted::replace(it.syntax(), ty.syntax());
+            }
+            Usage::Qualifier(it) => {
+                let ty = instantiate(alias, it.segment()?.generic_arg_list())?;
+                // `Alias::new()` can only become `Type::new()` if `Type` is a
+                // plain path, otherwise it needs to be `<Type<T>>::new()`.
+                let path = match &ty {
+                    ast::Type::PathType(ty)
+                        if !ty
+                            .syntax()
+                            .descendants()
+                            .any(|it| ast::GenericArgList::can_cast(it.kind())) =>
+                    {
+                        ty.path()?.clone_for_update()
+                    }
+                    _ => make::path_from_text(&format!("<{}>", ty)).clone_for_update(),
+                };
+                ted::replace(it.syntax(), path.syntax());
+            }
+            Usage::Import(it) => match it.syntax().parent().and_then(ast::Use::cast) {
+                Some(use_) => use_.remove(),
+                None => it.remove(),
+            },
+        }
+        Some(())
+    }
+}
+
+/// The aliased type, with the generic parameters of the alias replaced by
+/// `args`.
+fn instantiate(alias: &ast::TypeAlias, args: Option<ast::GenericArgList>) -> Option<ast::Type> {
+    let ty = alias.ty()?.clone_for_update();
+    let params = match alias.generic_param_list() {
+        Some(it) => it,
+        None => return Some(ty),
+    };
+
+    let mut lifetime_args = Vec::new();
+    let mut type_args = Vec::new();
+    for arg in args.into_iter().flat_map(|it| it.generic_args()) {
+        match arg {
+            ast::GenericArg::LifetimeArg(it) => lifetime_args.push(it.lifetime()?.to_string()),
+            ast::GenericArg::TypeArg(it) => type_args.push(it.ty()?.to_string()),
+            _ => return None,
+        }
+    }
+    let mut lifetime_args = lifetime_args.into_iter();
+    let mut type_args = type_args.into_iter();
+    let mut lifetimes = FxHashMap::default();
+    let mut types = FxHashMap::default();
+    for param in params.generic_params() {
+        match param {
+            ast::GenericParam::LifetimeParam(it) => {
+                let arg = lifetime_args.next().unwrap_or_else(|| "'_".to_string());
+                lifetimes.insert(it.lifetime()?.to_string(), arg);
+            }
+            ast::GenericParam::TypeParam(it) => {
+                let arg = type_args
+                    .next()
+                    .or_else(|| it.default_type().map(|it| it.to_string()))
+                    .unwrap_or_else(|| "_".to_string());
+                types.insert(it.name()?.to_string(), arg);
+            }
+            ast::GenericParam::ConstParam(_) => return None,
+        }
+    }
+
+    let param_name = |ty: &ast::PathType| {
+        let path = ty.path()?;
+        let segment = path.segment()?;
+        if path.qualifier().is_some() || segment.generic_arg_list().is_some() {
+            return None;
+        }
+        types.get(&segment.name_ref()?.to_string())
+    };
+    if let ast::Type::PathType(it) = &ty {
+        if let Some(arg) = param_name(it) {
+            return Some(make::ty(arg).clone_for_update());
+        }
+    }
+    let type_params: Vec<_> = ty
+        .syntax()
+        .descendants()
+        .filter_map(ast::PathType::cast)
+        .filter_map(|it| Some((param_name(&it)?.clone(), it)))
+        .collect();
+    for (arg, it) in type_params {
+        ted::replace(it.syntax(), make::ty(&arg).clone_for_update().syntax());
+    }
+    let lifetime_params: Vec<_> = ty
+        .syntax()
+        .descendants()
+        .filter_map(ast::Lifetime::cast)
+        .filter_map(|it| Some((lifetimes.get(&it.to_string())?.clone(), it)))
+        .collect();
+    for (arg, it) in lifetime_params {
+        ted::replace(it.syntax(), make::lifetime(&arg).clone_for_update().syntax());
+    }
+    Some(ty)
+}
+
+/// Removes `item` together with the whitespace after it.
+fn remove_item(item: &SyntaxNode) {
+    if let Some(ws) = item
+        .next_sibling_or_token()
+        .and_then(|it| it.into_token())
+        .filter(|it| it.kind() == SyntaxKind::WHITESPACE)
+    {
+        ted::remove(ws);
+    }
+    ted::remove(item);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn inline_simple_alias() {
+        check_assist(
+            inline_type_alias,
+            r#"
+type Id = u32;
+fn get(id: I$0d) {}
+"#,
+            r#"
+type Id = u32;
+fn get(id: u32) {}
+"#,
+        );
+    }
+
+    #[test]
+    fn inline_alias_with_generics() {
+        check_assist(
+            inline_type_alias,
+            r#"
+struct Map<K, V>(K, V);
+type Table<'a, V, K = u32> = &'a Map<K, V>;
+fn get<'t>(table: Tab$0le<'t, String>) {}
+"#,
+            r#"
+struct Map<K, V>(K, V);
+type Table<'a, V, K = u32> = &'a Map<K, V>;
+fn get<'t>(table: &'t Map<u32, String>) {}
+"#,
+        );
+    }
+
+    #[test]
+    fn inline_alias_used_as_qualifier() {
+        check_assist(
+            inline_type_alias,
+            r#"
+struct Vec<T>(T);
+impl<T> Vec<T> {
+    fn new() -> Self { loop {} }
+}
+type Bytes = Vec<u8>;
+fn main() {
+    let bytes = Byt$0es::new();
+}
+"#,
+            r#"
+struct Vec<T>(T);
+impl<T> Vec<T> {
+    fn new() -> Self { loop {} }
+}
+type Bytes = Vec<u8>;
+fn main() {
+    let bytes = <Vec<u8>>::new();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_assoc_types() {
+        check_assist_not_applicable(
+            inline_type_alias,
+            r#"
+trait Tr { type Out; }
+struct S;
+impl Tr for S { type Out = u32; }
+fn f() -> <S as Tr>::Ou$0t { 0 }
+"#,
+        );
+    }
+
+    #[test]
+    fn inline_all_uses_and_remove_alias() {
+        check_assist(
+            inline_type_alias_uses,
+            r#"
+//- /lib.rs
+mod ids;
+use ids::Id;
+fn get(id: Id) -> Option<Id> { None }
+//- /ids.rs
+pub type I$0d = u32;
+
+pub fn next(id: Id) -> Id { id + 1 }
+"#,
+            r#"
+//- /lib.rs
+mod ids;
+fn get(id: u32) -> Option<u32> { None }
+//- /ids.rs
+pub fn next(id: u32) -> u32 { id + 1 }
+"#,
+        );
+    }
+
+    #[test]
+    fn inline_all_uses_with_nested_uses() {
+        check_assist(
+            inline_type_alias_uses,
+            r#"
+type Pair<T> = (T, T);
+fn f(p: Pa$0ir<Pair<u8>>) {}
+"#,
+            r#"
+fn f(p: ((u8, u8), (u8, u8))) {}
+"#,
+        );
+    }
+}
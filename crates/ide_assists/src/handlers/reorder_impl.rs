@@ -12,43 +12,48 @@ use crate::{AssistContext, AssistId, AssistKind, Assists};
 
 // Assist: reorder_impl
 //
-// Reorder the methods of an `impl Trait`. The methods will be ordered
-// in the same order as in the trait definition.
+// Reorder the items of an `impl Trait`. The functions, constants and types
+// will be ordered in the same order as in the trait definition, together with
+// their comments and attributes.
 //
 // ```
 // trait Foo {
+//     type T;
+//     const C: u32;
 //     fn a() {}
 //     fn b() {}
-//     fn c() {}
 // }
 //
 // struct Bar;
 // $0impl Foo for Bar {
 //     fn b() {}
-//     fn c() {}
 //     fn a() {}
+//     const C: u32 = 0;
+//     type T = ();
 // }
 // ```
 // ->
 // ```
 // trait Foo {
+//     type T;
+//     const C: u32;
 //     fn a() {}
 //     fn b() {}
-//     fn c() {}
 // }
 //
 // struct Bar;
 // impl Foo for Bar {
+//     type T = ();
+//     const C: u32 = 0;
 //     fn a() {}
 //     fn b() {}
-//     fn c() {}
 // }
 // ```
 //
 pub(crate) fn reorder_impl(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
     let impl_ast = ctx.find_node_at_offset::<ast::Impl>()?;
     let items = impl_ast.assoc_item_list()?;
-    let methods = get_methods(&items);
+    let assoc_items = get_items(&items);
 
     let path = impl_ast
         .trait_()
@@ -58,17 +63,19 @@ pub(crate) fn reorder_impl(acc: &mut Assists, ctx: &AssistContext) -> Option<()>
         })?
         .path()?;
 
-    let ranks = compute_method_ranks(&path, ctx)?;
-    let sorted: Vec<_> = methods
+    let ranks = compute_item_ranks(&path, ctx)?;
+    let sorted: Vec<_> = assoc_items
         .iter()
         .cloned()
-        .sorted_by_key(|f| {
-            f.name().and_then(|n| ranks.get(&n.to_string()).copied()).unwrap_or(usize::max_value())
+        .sorted_by_key(|item| {
+            item_name(item)
+                .and_then(|n| ranks.get(&n.to_string()).copied())
+                .unwrap_or(usize::max_value())
         })
         .collect();
 
-    // Don't edit already sorted methods:
-    if methods == sorted {
+    // Don't edit already sorted items:
+    if assoc_items == sorted {
         cov_mark::hit!(not_applicable_if_sorted);
         return None;
     }
@@ -76,11 +83,12 @@ pub(crate) fn reorder_impl(acc: &mut Assists, ctx: &AssistContext) -> Option<()>
     let target = items.syntax().text_range();
     acc.add(
         AssistId("reorder_impl", AssistKind::RefactorRewrite),
-        "Sort methods",
+        "Sort items by trait definition",
         target,
         |builder| {
-            let methods = methods.into_iter().map(|fn_| builder.make_mut(fn_)).collect::<Vec<_>>();
-            methods
+            let assoc_items =
+                assoc_items.into_iter().map(|item| builder.make_mut(item)).collect::<Vec<_>>();
+            assoc_items
                 .into_iter()
                 .zip(sorted)
                 .for_each(|(old, new)| ted::replace(old.syntax(), new.clone_for_update().syntax()));
@@ -88,18 +96,15 @@ pub(crate) fn reorder_impl(acc: &mut Assists, ctx: &AssistContext) -> Option<()>
     )
 }
 
-fn compute_method_ranks(path: &ast::Path, ctx: &AssistContext) -> Option<FxHashMap<String, usize>> {
+fn compute_item_ranks(path: &ast::Path, ctx: &AssistContext) -> Option<FxHashMap<String, usize>> {
     let td = trait_definition(path, &ctx.sema)?;
 
     Some(
         td.items(ctx.db())
             .iter()
-            .flat_map(|i| match i {
-                hir::AssocItem::Function(f) => Some(f),
-                _ => None,
-            })
+            .flat_map(|i| i.name(ctx.db()))
             .enumerate()
-            .map(|(idx, func)| (func.name(ctx.db()).to_string(), idx))
+            .map(|(idx, name)| (name.to_string(), idx))
             .collect(),
     )
 }
@@ -111,15 +116,18 @@ fn trait_definition(path: &ast::Path, sema: &Semantics<RootDatabase>) -> Option<
     }
 }
 
-fn get_methods(items: &ast::AssocItemList) -> Vec<ast::Fn> {
-    items
-        .assoc_items()
-        .flat_map(|i| match i {
-            ast::AssocItem::Fn(f) => Some(f),
-            _ => None,
-        })
-        .filter(|f| f.name().is_some())
-        .collect()
+/// The named items of the impl, macro calls stay where they are.
+fn get_items(items: &ast::AssocItemList) -> Vec<ast::AssocItem> {
+    items.assoc_items().filter(|item| item_name(item).is_some()).collect()
+}
+
+fn item_name(item: &ast::AssocItem) -> Option<ast::Name> {
+    match item {
+        ast::AssocItem::Fn(it) => it.name(),
+        ast::AssocItem::Const(it) => it.name(),
+        ast::AssocItem::TypeAlias(it) => it.name(),
+        ast::AssocItem::MacroCall(_) => None,
+    }
 }
 
 #[cfg(test)]
@@ -200,6 +208,55 @@ impl Bar for Foo {
         )
     }
 
+    #[test]
+    fn reorder_impl_trait_items_with_comments_and_attributes() {
+        check_assist(
+            reorder_impl,
+            r#"
+trait Bar {
+    type T;
+    const C: u32;
+    fn a(&self);
+    fn b(&self);
+}
+
+struct Foo;
+$0impl Bar for Foo {
+    /// Calls `a`.
+    #[inline]
+    fn b(&self) { self.a() }
+    // Does nothing.
+    fn a(&self) {}
+    m!();
+    const C: u32 = 0;
+    #[allow(unused)]
+    type T = ();
+}
+"#,
+            r#"
+trait Bar {
+    type T;
+    const C: u32;
+    fn a(&self);
+    fn b(&self);
+}
+
+struct Foo;
+impl Bar for Foo {
+    #[allow(unused)]
+    type T = ();
+    const C: u32 = 0;
+    m!();
+    // Does nothing.
+    fn a(&self) {}
+    /// Calls `a`.
+    #[inline]
+    fn b(&self) { self.a() }
+}
+"#,
+        )
+    }
+
     #[test]
     fn reorder_impl_trait_methods_uneven_ident_lengths() {
         check_assist(
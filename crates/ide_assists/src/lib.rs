@@ -55,6 +55,7 @@ mod handlers {
     mod apply_demorgan;
     mod auto_import;
     mod change_visibility;
+    mod convert_cfg_to_cfg_if;
     mod convert_integer_literal;
     mod convert_comment_block;
     mod convert_iter_for_each_to_for;
@@ -71,14 +72,17 @@ mod handlers {
     mod flip_binexpr;
     mod flip_comma;
     mod flip_trait_bound;
+    mod generate_builder;
     mod generate_default_from_enum_variant;
     mod generate_default_from_new;
     mod generate_is_empty_from_len;
     mod generate_deref;
+    mod generate_documentation_template;
     mod generate_derive;
     mod generate_enum_is_method;
     mod generate_enum_projection_method;
     mod generate_from_impl_for_enum;
+    mod generate_from_impl_for_similar_type;
     mod generate_function;
     mod generate_getter;
     mod generate_impl;
@@ -87,6 +91,7 @@ mod handlers {
     mod infer_function_return_type;
     mod inline_call;
     mod inline_local_variable;
+    mod inline_type_alias;
     mod introduce_named_lifetime;
     mod invert_if;
     mod merge_imports;
@@ -124,6 +129,8 @@ mod handlers {
             apply_demorgan::apply_demorgan,
             auto_import::auto_import,
             change_visibility::change_visibility,
+            convert_cfg_to_cfg_if::convert_cfg_if_to_cfg,
+            convert_cfg_to_cfg_if::convert_cfg_to_cfg_if,
             convert_integer_literal::convert_integer_literal,
             convert_comment_block::convert_comment_block,
             convert_iter_for_each_to_for::convert_iter_for_each_to_for,
@@ -138,15 +145,18 @@ mod handlers {
             flip_binexpr::flip_binexpr,
             flip_comma::flip_comma,
             flip_trait_bound::flip_trait_bound,
+            generate_builder::generate_builder,
             generate_default_from_enum_variant::generate_default_from_enum_variant,
             generate_default_from_new::generate_default_from_new,
             generate_is_empty_from_len::generate_is_empty_from_len,
             generate_deref::generate_deref,
+            generate_documentation_template::generate_documentation_template,
             generate_derive::generate_derive,
             generate_enum_is_method::generate_enum_is_method,
             generate_enum_projection_method::generate_enum_as_method,
             generate_enum_projection_method::generate_enum_try_into_method,
             generate_from_impl_for_enum::generate_from_impl_for_enum,
+            generate_from_impl_for_similar_type::generate_from_impl_for_similar_type,
             generate_function::generate_function,
             generate_getter::generate_getter,
             generate_getter::generate_getter_mut,
@@ -156,6 +166,8 @@ mod handlers {
             infer_function_return_type::infer_function_return_type,
             inline_call::inline_call,
             inline_local_variable::inline_local_variable,
+            inline_type_alias::inline_type_alias,
+            inline_type_alias::inline_type_alias_uses,
             introduce_named_lifetime::introduce_named_lifetime,
             invert_if::invert_if,
             merge_imports::merge_imports,
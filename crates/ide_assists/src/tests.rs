@@ -68,6 +68,18 @@ pub(crate) fn check_assist_not_applicable(assist: Handler, ra_fixture: &str) {
     check(assist, ra_fixture, ExpectedResult::NotApplicable, None);
 }
 
+/// Checks the labels of the assists, in the order they are offered.
+#[track_caller]
+pub(crate) fn check_assist_order(assist: Handler, ra_fixture: &str, labels: &[&str]) {
+    let (db, file_id, range_or_offset) = RootDatabase::with_range_or_offset(ra_fixture);
+    let frange = FileRange { file_id, range: range_or_offset.into() };
+    let ctx = AssistContext::new(Semantics::new(&db), &TEST_CONFIG, frange);
+    let mut acc = Assists::new(&ctx, AssistResolveStrategy::None);
+    assist(&mut acc, &ctx);
+    let actual = acc.finish().into_iter().map(|it| it.label.to_string()).collect::<Vec<_>>();
+    assert_eq!(actual, labels);
+}
+
 /// Check assist in unresolved state. Useful to check assists for lazy computation.
 #[track_caller]
 pub(crate) fn check_assist_unresolved(assist: Handler, ra_fixture: &str) {
@@ -222,6 +234,7 @@ fn assist_order_field_struct() {
     assert_eq!(assists.next().expect("expected assist").label, "Generate a getter method");
     assert_eq!(assists.next().expect("expected assist").label, "Generate a mut getter method");
     assert_eq!(assists.next().expect("expected assist").label, "Generate a setter method");
+    assert_eq!(assists.next().expect("expected assist").label, "Generate a builder");
     assert_eq!(assists.next().expect("expected assist").label, "Add `#[derive]`");
 }
 
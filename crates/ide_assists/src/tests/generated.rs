@@ -191,6 +191,51 @@ pub(crate) fn frobnicate() {}
     )
 }
 
+#[test]
+fn doctest_convert_cfg_if_to_cfg() {
+    check_doc_test(
+        "convert_cfg_if_to_cfg",
+        r#####"
+cfg_if::cfg_if!$0 {
+    if #[cfg(unix)] {
+        fn platform() -> &'static str { "unix" }
+    } else {
+        fn platform() -> &'static str { "other" }
+    }
+}
+"#####,
+        r#####"
+#[cfg(unix)]
+fn platform() -> &'static str { "unix" }
+
+#[cfg(not(unix))]
+fn platform() -> &'static str { "other" }
+"#####,
+    )
+}
+
+#[test]
+fn doctest_convert_cfg_to_cfg_if() {
+    check_doc_test(
+        "convert_cfg_to_cfg_if",
+        r#####"
+$0#[cfg(unix)]
+fn platform() -> &'static str { "unix" }
+#[cfg(not(unix))]
+fn platform() -> &'static str { "other" }
+"#####,
+        r#####"
+cfg_if::cfg_if! {
+    if #[cfg(unix)] {
+        fn platform() -> &'static str { "unix" }
+    } else {
+        fn platform() -> &'static str { "other" }
+    }
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_convert_integer_literal() {
     check_doc_test(
@@ -525,6 +570,62 @@ fn foo<T: Copy + Clone>() { }
     )
 }
 
+#[test]
+fn doctest_generate_builder() {
+    check_doc_test(
+        "generate_builder",
+        r#####"
+//- minicore: default
+struct Url;
+struct Headers;
+impl Default for Headers { fn default() -> Self { Headers } }
+struct Request {
+    url: Url,$0
+    headers: Headers,
+}
+"#####,
+        r#####"
+struct Url;
+struct Headers;
+impl Default for Headers { fn default() -> Self { Headers } }
+struct Request {
+    url: Url,
+    headers: Headers,
+}
+
+struct ${1:RequestBuilder} {
+    url: Option<Url>,
+    headers: Option<Headers>,
+}
+
+impl Request {
+    fn builder() -> ${1:RequestBuilder} {
+        ${1:RequestBuilder} { url: None, headers: None }
+    }
+}
+
+impl ${1:RequestBuilder} {
+    fn url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    fn headers(mut self, headers: Headers) -> Self {
+        self.headers = Some(headers);
+        self
+    }
+
+    fn build(self) -> Result<Request, &'static str> {
+        Ok(Request {
+            url: self.url.ok_or("`url` is not set")?,
+            headers: self.headers.unwrap_or_default(),
+        })
+    }
+}$0
+"#####,
+    )
+}
+
 #[test]
 fn doctest_generate_default_from_enum_variant() {
     check_doc_test(
@@ -630,6 +731,34 @@ struct Point {
     )
 }
 
+#[test]
+fn doctest_generate_documentation_template() {
+    check_doc_test(
+        "generate_documentation_template",
+        r#####"
+pub fn parse_$0len(input: &str) -> usize {
+    input.parse().unwrap()
+}
+"#####,
+        r#####"
+/// ${0:Parse len.}
+///
+/// # Panics
+///
+/// Panics if .
+///
+/// # Examples
+///
+/// ```
+/// use test::parse_len;
+/// ```
+pub fn parse_len(input: &str) -> usize {
+    input.parse().unwrap()
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_generate_enum_as_method() {
     check_doc_test(
@@ -735,6 +864,31 @@ impl From<u32> for A {
     )
 }
 
+#[test]
+fn doctest_generate_from_impl_for_similar_type() {
+    check_doc_test(
+        "generate_from_impl_for_similar_type",
+        r#####"
+struct Point2 { x: f32, y: f32 }
+struct $0Point3 { x: f32, y: f32, z: f32 }
+"#####,
+        r#####"
+struct Point2 { x: f32, y: f32 }
+struct Point3 { x: f32, y: f32, z: f32 }
+
+impl From<Point2> for Point3 {
+    fn from(value: Point2) -> Self {
+        Self {
+            x: value.x,
+            y: value.y,
+            z: todo!(),
+        }
+    }
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_generate_function() {
     check_doc_test(
@@ -957,6 +1111,35 @@ fn main() {
     )
 }
 
+#[test]
+fn doctest_inline_type_alias() {
+    check_doc_test(
+        "inline_type_alias",
+        r#####"
+type Pair<T> = (T, T);
+fn swap(pair: Pai$0r<u32>) {}
+"#####,
+        r#####"
+type Pair<T> = (T, T);
+fn swap(pair: (u32, u32)) {}
+"#####,
+    )
+}
+
+#[test]
+fn doctest_inline_type_alias_uses() {
+    check_doc_test(
+        "inline_type_alias_uses",
+        r#####"
+type $0Pair<T> = (T, T);
+fn swap(pair: Pair<u32>) -> Pair<u32> { (pair.1, pair.0) }
+"#####,
+        r#####"
+fn swap(pair: (u32, u32)) -> (u32, u32) { (pair.1, pair.0) }
+"#####,
+    )
+}
+
 #[test]
 fn doctest_introduce_named_lifetime() {
     check_doc_test(
@@ -1302,30 +1485,34 @@ fn doctest_reorder_impl() {
         "reorder_impl",
         r#####"
 trait Foo {
+    type T;
+    const C: u32;
     fn a() {}
     fn b() {}
-    fn c() {}
 }
 
 struct Bar;
 $0impl Foo for Bar {
     fn b() {}
-    fn c() {}
     fn a() {}
+    const C: u32 = 0;
+    type T = ();
 }
 "#####,
         r#####"
 trait Foo {
+    type T;
+    const C: u32;
     fn a() {}
     fn b() {}
-    fn c() {}
 }
 
 struct Bar;
 impl Foo for Bar {
+    type T = ();
+    const C: u32 = 0;
     fn a() {}
     fn b() {}
-    fn c() {}
 }
 "#####,
     )
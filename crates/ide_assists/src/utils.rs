@@ -402,16 +402,27 @@ pub(crate) fn find_impl_block_end(impl_def: ast::Impl, buf: &mut String) -> Opti
 // Generates the surrounding `impl Type { <code> }` including type and lifetime
 // parameters
 pub(crate) fn generate_impl_text(adt: &ast::Adt, code: &str) -> String {
-    generate_impl_text_inner(adt, None, code)
+    generate_impl_text_inner(adt, None, &adt.name().unwrap().text(), code)
+}
+
+// Generates `impl Name { <code> }` for a type declared with the same generic
+// parameters as `adt`
+pub(crate) fn generate_companion_impl_text(adt: &ast::Adt, name: &str, code: &str) -> String {
+    generate_impl_text_inner(adt, None, name, code)
 }
 
 // Generates the surrounding `impl <trait> for Type { <code> }` including type
 // and lifetime parameters
 pub(crate) fn generate_trait_impl_text(adt: &ast::Adt, trait_text: &str, code: &str) -> String {
-    generate_impl_text_inner(adt, Some(trait_text), code)
+    generate_impl_text_inner(adt, Some(trait_text), &adt.name().unwrap().text(), code)
 }
 
-fn generate_impl_text_inner(adt: &ast::Adt, trait_text: Option<&str>, code: &str) -> String {
+fn generate_impl_text_inner(
+    adt: &ast::Adt,
+    trait_text: Option<&str>,
+    self_name: &str,
+    code: &str,
+) -> String {
     let generic_params = adt.generic_param_list();
     let mut buf = String::with_capacity(code.len());
     buf.push_str("\n\n");
@@ -443,22 +454,8 @@ fn generate_impl_text_inner(adt: &ast::Adt, trait_text: Option<&str>, code: &str
         buf.push_str(trait_text);
         buf.push_str(" for ");
     }
-    buf.push_str(&adt.name().unwrap().text());
-    if let Some(generic_params) = generic_params {
-        let lifetime_params = generic_params
-            .lifetime_params()
-            .filter_map(|it| it.lifetime())
-            .map(|it| SmolStr::from(it.text()));
-        let type_params = generic_params
-            .type_params()
-            .filter_map(|it| it.name())
-            .map(|it| SmolStr::from(it.text()));
-        let const_params = generic_params
-            .const_params()
-            .filter_map(|it| it.name())
-            .map(|it| SmolStr::from(it.text()));
-        format_to!(buf, "<{}>", lifetime_params.chain(type_params).chain(const_params).format(", "))
-    }
+    buf.push_str(self_name);
+    buf.push_str(&generic_args_text(adt));
 
     match adt.where_clause() {
         Some(where_clause) => {
@@ -472,6 +469,23 @@ fn generate_impl_text_inner(adt: &ast::Adt, trait_text: Option<&str>, code: &str
     buf
 }
 
+// Generates the `<'a, T, N>` arguments naming the generic parameters of `adt`
+pub(crate) fn generic_args_text(adt: &ast::Adt) -> String {
+    let generic_params = match adt.generic_param_list() {
+        Some(it) => it,
+        None => return String::new(),
+    };
+    let lifetime_params = generic_params
+        .lifetime_params()
+        .filter_map(|it| it.lifetime())
+        .map(|it| SmolStr::from(it.text()));
+    let type_params =
+        generic_params.type_params().filter_map(|it| it.name()).map(|it| SmolStr::from(it.text()));
+    let const_params =
+        generic_params.const_params().filter_map(|it| it.name()).map(|it| SmolStr::from(it.text()));
+    format!("<{}>", lifetime_params.chain(type_params).chain(const_params).format(", "))
+}
+
 pub(crate) fn add_method_to_adt(
     builder: &mut AssistBuilder,
     adt: &ast::Adt,
@@ -2,6 +2,7 @@
 
 pub(crate) mod attribute;
 pub(crate) mod dot;
+pub(crate) mod file_path;
 pub(crate) mod flyimport;
 pub(crate) mod fn_param;
 pub(crate) mod keyword;
@@ -17,7 +18,7 @@ pub(crate) mod unqualified_path;
 
 use std::iter;
 
-use hir::known;
+use hir::{known, HasVisibility};
 use ide_db::SymbolKind;
 
 use crate::{
@@ -25,7 +26,7 @@ use crate::{
     render::{
         const_::render_const,
         enum_variant::render_variant,
-        function::{render_fn, render_method},
+        function::{render_fn, render_method, render_qualified_fn},
         macro_::render_macro,
         pattern::{render_struct_pat, render_variant_pat},
         render_field, render_resolution, render_tuple_field,
@@ -66,6 +67,10 @@ impl Completions {
         }
     }
 
+    pub(crate) fn retain(&mut self, f: impl FnMut(&CompletionItem) -> bool) {
+        self.buf.retain(f)
+    }
+
     pub(crate) fn add_all<I>(&mut self, items: I)
     where
         I: IntoIterator,
@@ -111,6 +116,15 @@ impl Completions {
         self.add_opt(render_fn(RenderContext::new(ctx), None, local_name, func));
     }
 
+    pub(crate) fn add_qualified_function(
+        &mut self,
+        ctx: &CompletionContext,
+        func: hir::Function,
+        path: hir::ModPath,
+    ) {
+        self.add_opt(render_qualified_fn(RenderContext::new(ctx), path, func));
+    }
+
     pub(crate) fn add_method(
         &mut self,
         ctx: &CompletionContext,
@@ -254,3 +268,40 @@ fn enum_variants_with_paths(
         }
     }
 }
+
+/// Calls `cb` with the associated functions of `adt` which construct it, like
+/// `new`, together with the path of `adt` from the completion site.
+fn constructors_with_paths(
+    acc: &mut Completions,
+    ctx: &CompletionContext,
+    adt: hir::Adt,
+    cb: impl Fn(&mut Completions, &CompletionContext, hir::Function, hir::ModPath),
+) {
+    let module = match ctx.scope.module() {
+        Some(it) => it,
+        None => return,
+    };
+    let adt_path = match module.find_use_path(ctx.db, hir::ModuleDef::Adt(adt)) {
+        Some(it) => it,
+        None => return,
+    };
+
+    for impl_ in hir::Impl::all_for_type(ctx.db, adt.ty(ctx.db)) {
+        if impl_.trait_(ctx.db).is_some() {
+            continue;
+        }
+        for item in impl_.items(ctx.db) {
+            let func = match item {
+                hir::AssocItem::Function(it) => it,
+                _ => continue,
+            };
+            if func.self_param(ctx.db).is_some() || !func.is_visible_from(ctx.db, module) {
+                continue;
+            }
+            if func.ret_type(ctx.db).as_adt() != Some(adt) {
+                continue;
+            }
+            cb(acc, ctx, func, adt_path.clone());
+        }
+    }
+}
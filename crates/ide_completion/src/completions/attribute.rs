@@ -34,14 +34,28 @@ pub(crate) fn complete_attribute(acc: &mut Completions, ctx: &CompletionContext)
             "cfg" => {
                 cfg::complete_cfg(acc, ctx);
             }
+            "cfg_attr" if in_cfg_attr_predicate(ctx, &token_tree) => {
+                cfg::complete_cfg(acc, ctx);
+            }
             _ => (),
         },
         (None, Some(_)) => (),
+        // The value of `#[path = "…"]` and the like.
+        (Some(_), None) if ctx.original_token.kind() == SyntaxKind::STRING => (),
         _ => complete_new_attribute(acc, ctx, attribute),
     }
     Some(())
 }
 
+/// The predicate of `cfg_attr` is the part before the first comma.
+fn in_cfg_attr_predicate(ctx: &CompletionContext, token_tree: &ast::TokenTree) -> bool {
+    token_tree
+        .syntax()
+        .children_with_tokens()
+        .find(|it| it.kind() == T![,])
+        .map_or(true, |comma| ctx.position.offset <= comma.text_range().start())
+}
+
 fn complete_new_attribute(acc: &mut Completions, ctx: &CompletionContext, attribute: &ast::Attr) {
     let is_inner = attribute.kind() == ast::AttrKind::Inner;
     let attribute_annotated_item_kind =
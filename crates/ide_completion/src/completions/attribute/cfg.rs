@@ -2,7 +2,8 @@
 
 use std::iter;
 
-use syntax::SyntaxKind;
+use itertools::Itertools;
+use syntax::{SyntaxKind, SyntaxToken, TextRange, TextSize, T};
 
 use crate::{
     completions::Completions, context::CompletionContext, item::CompletionKind, CompletionItem,
@@ -10,56 +11,115 @@ use crate::{
 };
 
 pub(crate) fn complete_cfg(acc: &mut Completions, ctx: &CompletionContext) {
-    let add_completion = |item: &&str| {
-        let mut completion =
-            CompletionItem::new(CompletionKind::Attribute, ctx.source_range(), *item);
-        completion.insert_text(format!(r#""{}""#, item));
-        completion.kind(CompletionItemKind::Attribute);
-        acc.add(completion.build());
+    // Inside `key = "…"` the contents of the string literal are replaced.
+    let in_string = ctx.original_token.kind() == SyntaxKind::STRING;
+    let range = if in_string { string_contents(&ctx.original_token) } else { ctx.source_range() };
+    // The tokens of an unclosed attribute aren't all siblings, so this walks
+    // the tokens instead.
+    let prev_token = |token: &SyntaxToken| {
+        iter::successors(token.prev_token(), |it| it.prev_token()).find(|it| !it.kind().is_trivia())
     };
+    let eq = match ctx.original_token.kind() {
+        T![=] => Some(ctx.original_token.clone()),
+        _ => prev_token(&ctx.original_token).filter(|it| it.kind() == T![=]),
+    };
+    let key = eq.and_then(|eq| prev_token(&eq)).filter(|it| it.kind() == SyntaxKind::IDENT);
+    let potential_cfg = ctx.krate.map(|krate| krate.potential_cfg(ctx.db)).unwrap_or_default();
 
-    let previous = iter::successors(ctx.original_token.prev_token(), |t| {
-        (matches!(t.kind(), SyntaxKind::EQ) || t.kind().is_trivia())
-            .then(|| t.prev_token())
-            .flatten()
-    })
-    .find(|t| matches!(t.kind(), SyntaxKind::IDENT));
-
-    match previous.as_ref().map(|p| p.text()) {
-        Some("target_arch") => KNOWN_ARCH.iter().for_each(add_completion),
-        Some("target_env") => KNOWN_ENV.iter().for_each(add_completion),
-        Some("target_os") => KNOWN_OS.iter().for_each(add_completion),
-        Some("target_vendor") => KNOWN_VENDOR.iter().for_each(add_completion),
-        Some("target_endian") => ["little", "big"].iter().for_each(add_completion),
-        Some(name) => {
-            ctx.krate.map(|krate| {
-                krate.potential_cfg(ctx.db).get_cfg_values(&name).iter().for_each(|s| {
-                    let mut item = CompletionItem::new(
-                        CompletionKind::Attribute,
-                        ctx.source_range(),
-                        s.as_str(),
-                    );
-                    item.insert_text(format!(r#""{}""#, s));
-
-                    acc.add(item.build());
-                })
-            });
+    match key {
+        Some(key) => {
+            let key = key.text();
+            let mut potential_values = potential_cfg.get_cfg_values(key);
+            potential_values.sort();
+            let known_values = known_values(key).iter().copied();
+            let values = known_values.chain(potential_values.iter().map(|it| it.as_str())).unique();
+            for value in values {
+                let mut item = CompletionItem::new(CompletionKind::Attribute, range, value);
+                if !in_string {
+                    item.insert_text(format!(r#""{}""#, value));
+                }
+                item.kind(CompletionItemKind::Attribute);
+                acc.add(item.build());
+            }
         }
         None => {
-            ctx.krate.map(|krate| {
-                krate.potential_cfg(ctx.db).get_cfg_keys().iter().for_each(|s| {
-                    let item = CompletionItem::new(
-                        CompletionKind::Attribute,
-                        ctx.source_range(),
-                        s.as_str(),
-                    );
-                    acc.add(item.build());
-                })
-            });
+            let potential_keys = potential_cfg.get_cfg_keys().into_iter().map(|it| it.as_str());
+            let mut keys = potential_keys.chain(KNOWN_KEYS.iter().copied()).collect::<Vec<_>>();
+            keys.sort_unstable();
+            keys.dedup();
+            for key in keys {
+                let mut item = CompletionItem::new(CompletionKind::Attribute, range, key);
+                item.kind(CompletionItemKind::Attribute);
+                acc.add(item.build());
+            }
+            for predicate in &["all", "any", "not"] {
+                let mut item = CompletionItem::new(
+                    CompletionKind::Attribute,
+                    range,
+                    format!("{}(…)", predicate),
+                );
+                item.kind(CompletionItemKind::Attribute).lookup_by(*predicate);
+                match ctx.config.snippet_cap {
+                    Some(cap) => item.insert_snippet(cap, format!("{}($0)", predicate)),
+                    None => item.insert_text(format!("{}()", predicate)),
+                };
+                acc.add(item.build());
+            }
         }
     };
 }
 
+/// The range between the quotes of a, possibly unterminated, string literal.
+fn string_contents(token: &SyntaxToken) -> TextRange {
+    let range = token.text_range();
+    let text = token.text();
+    let start = range.start() + TextSize::of('"');
+    let end = if text.len() > 1 && text.ends_with('"') {
+        range.end() - TextSize::of('"')
+    } else {
+        range.end()
+    };
+    TextRange::new(start, end)
+}
+
+fn known_values(key: &str) -> &'static [&'static str] {
+    match key {
+        "target_arch" => &KNOWN_ARCH,
+        "target_env" => &KNOWN_ENV,
+        "target_os" => &KNOWN_OS,
+        "target_vendor" => &KNOWN_VENDOR,
+        "target_endian" => &["little", "big"],
+        "target_family" => &["unix", "wasm", "windows"],
+        "target_pointer_width" => &["16", "32", "64"],
+        "target_has_atomic" => &["8", "16", "32", "64", "128", "ptr"],
+        "panic" => &["abort", "unwind"],
+        _ => &[],
+    }
+}
+
+/// Keys set by the compiler, or by cargo in the case of `feature`, which may
+/// be absent from the options of the crate.
+const KNOWN_KEYS: [&str; 18] = [
+    "debug_assertions",
+    "doc",
+    "doctest",
+    "feature",
+    "panic",
+    "proc_macro",
+    "target_arch",
+    "target_endian",
+    "target_env",
+    "target_family",
+    "target_feature",
+    "target_has_atomic",
+    "target_os",
+    "target_pointer_width",
+    "target_vendor",
+    "test",
+    "unix",
+    "windows",
+];
+
 const KNOWN_ARCH: [&'static str; 19] = [
     "aarch64",
     "arm",
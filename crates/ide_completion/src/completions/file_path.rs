@@ -0,0 +1,248 @@
+//! Completes file paths in `include!`, `include_str!`, `include_bytes!` and
+//! `#[path = "…"]`.
+//!
+//! The files are those of the VFS, no IO is done: paths are relative to the
+//! file containing the literal, or to `OUT_DIR` for
+//! `concat!(env!("OUT_DIR"), "/…")`.
+
+use ide_db::base_db::{CrateId, SourceDatabase, SourceDatabaseExt};
+use rustc_hash::FxHashSet;
+use syntax::{
+    algo::non_trivia_sibling,
+    ast::{self, AstToken, IsString},
+    AstNode, Direction, SyntaxElement, SyntaxKind, TextRange, TextSize, T,
+};
+
+use crate::{
+    context::CompletionContext,
+    item::{CompletionItem, CompletionItemKind, CompletionKind},
+    Completions,
+};
+
+/// Where the path in the literal is relative to.
+enum Base {
+    File,
+    OutDir,
+}
+
+pub(crate) fn complete_file_path(acc: &mut Completions, ctx: &CompletionContext) -> Option<()> {
+    let string = ast::String::cast(ctx.original_token.clone())?;
+    let contents = string.text_range_between_quotes()?;
+    if !contents.contains_inclusive(ctx.position.offset) {
+        return None;
+    }
+    let (base, only_rust_files) = path_literal_kind(&string)?;
+
+    let _p = profile::span("completion::complete_file_path");
+
+    let token_start = string.syntax().text_range().start();
+    let typed = &string.text()[TextRange::new(contents.start(), ctx.position.offset) - token_start];
+    let (typed_dir, typed_name) = match typed.rfind('/') {
+        Some(idx) => (&typed[..idx], &typed[idx + 1..]),
+        None => ("", typed),
+    };
+    let range = TextRange::new(ctx.position.offset - TextSize::of(typed_name), ctx.position.offset);
+
+    let file_id = ctx.position.file_id;
+    let source_root = ctx.db.source_root(ctx.db.file_source_root(file_id));
+    let dir = match base {
+        Base::File => {
+            let dir = source_root.path_for_file(&file_id)?.parent()?;
+            let typed_dir = typed_dir.trim_start_matches("./");
+            if typed_dir.is_empty() || typed_dir == "." {
+                dir.to_string()
+            } else {
+                // `join` only resolves `..` followed by a slash.
+                dir.join(&format!("{}/", typed_dir))?.to_string()
+            }
+        }
+        Base::OutDir => {
+            let krate = CrateId::from(ctx.krate?);
+            let out_dir = ctx.db.crate_graph()[krate].env.get("OUT_DIR")?;
+            format!("{}{}", out_dir.trim_end_matches('/'), typed_dir)
+        }
+    };
+    let prefix = format!("{}/", dir.trim_end_matches('/'));
+
+    let mut folders = FxHashSet::default();
+    let mut entries = source_root
+        .iter()
+        .filter_map(|it| {
+            let path = source_root.path_for_file(&it)?.to_string();
+            let rest = path.strip_prefix(&prefix)?;
+            match rest.split_once('/') {
+                Some((folder, _)) => {
+                    folders.insert(folder.to_string()).then(|| (format!("{}/", folder), true))
+                }
+                None if it == file_id || (only_rust_files && !rest.ends_with(".rs")) => None,
+                None => Some((rest.to_string(), false)),
+            }
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    for (label, is_folder) in entries {
+        let mut item = CompletionItem::new(CompletionKind::Magic, range, &label);
+        item.kind(if is_folder { CompletionItemKind::Folder } else { CompletionItemKind::File });
+        item.add_to(acc);
+    }
+    Some(())
+}
+
+/// Whether the literal is a path, and whether it can only name Rust files.
+fn path_literal_kind(string: &ast::String) -> Option<(Base, bool)> {
+    let parent = string.syntax().parent()?;
+    if let Some(literal) = ast::Literal::cast(parent.clone()) {
+        let meta = literal.syntax().parent().and_then(ast::Meta::cast)?;
+        let attr = meta.syntax().parent().and_then(ast::Attr::cast)?;
+        let is_path = attr.path()?.as_single_name_ref()?.text() == "path";
+        let on_module = attr.syntax().parent().map_or(false, |it| it.kind() == SyntaxKind::MODULE);
+        return (is_path && on_module).then(|| (Base::File, true));
+    }
+
+    let tt = ast::TokenTree::cast(parent)?;
+    let (base, tt) = if is_out_dir_concat(string, &tt) {
+        (Base::OutDir, tt.syntax().parent().and_then(ast::TokenTree::cast)?)
+    } else {
+        (Base::File, tt)
+    };
+    let call = tt.syntax().parent().and_then(ast::MacroCall::cast)?;
+    match call.path()?.segment()?.name_ref()?.text().as_str() {
+        "include" => Some((base, true)),
+        "include_str" | "include_bytes" => Some((base, false)),
+        _ => None,
+    }
+}
+
+/// `concat!(env!("OUT_DIR"), "…")`
+fn is_out_dir_concat(string: &ast::String, tt: &ast::TokenTree) -> bool {
+    let prev = |it: SyntaxElement| non_trivia_sibling(it, Direction::Prev);
+    let is_token = |it: Option<SyntaxElement>, kind: SyntaxKind, text: &str| {
+        it.and_then(|it| it.into_token())
+            .map_or(false, |it| it.kind() == kind && (text.is_empty() || it.text() == text))
+    };
+
+    let comma = prev(string.syntax().clone().into());
+    if !is_token(comma.clone(), T![,], "") {
+        return false;
+    }
+    let env_args = comma.and_then(prev);
+    let is_out_dir = env_args.as_ref().and_then(|it| it.as_node()).map_or(false, |it| {
+        it.kind() == SyntaxKind::TOKEN_TREE
+            && it.children_with_tokens().any(|it| it.to_string() == r#""OUT_DIR""#)
+    });
+    let env_bang = env_args.and_then(prev);
+    if !is_out_dir
+        || !is_token(env_bang.clone(), T![!], "")
+        || !is_token(env_bang.and_then(prev), SyntaxKind::IDENT, "env")
+    {
+        return false;
+    }
+    let concat_bang = prev(tt.syntax().clone().into());
+    is_token(concat_bang.clone(), T![!], "")
+        && is_token(concat_bang.and_then(prev), SyntaxKind::IDENT, "concat")
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+
+    use crate::tests::completion_list;
+
+    fn check(ra_fixture: &str, expect: Expect) {
+        let actual = completion_list(ra_fixture);
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn completes_files_in_include_str() {
+        check(
+            r#"
+//- /lib.rs
+const S: &str = include_str!("$0");
+//- /data.txt
+//- /foo.rs
+//- /assets/logo.svg
+//- /assets/icon.svg
+"#,
+            expect![[r#"
+                fo assets/
+                fi data.txt
+                fi foo.rs
+            "#]],
+        );
+    }
+
+    #[test]
+    fn completes_files_in_subdirectories() {
+        check(
+            r#"
+//- /lib.rs
+const S: &[u8] = include_bytes!("assets/lo$0");
+//- /assets/logo.svg
+//- /assets/icon.svg
+//- /assets/fonts/mono.ttf
+"#,
+            expect![[r#"
+                fo fonts/
+                fi icon.svg
+                fi logo.svg
+            "#]],
+        );
+    }
+
+    #[test]
+    fn completes_only_rust_files_in_include_and_path() {
+        check(
+            r#"
+//- /lib.rs
+include!("$0");
+//- /gen.rs
+//- /data.txt
+"#,
+            expect![[r#"
+                fi gen.rs
+            "#]],
+        );
+        check(
+            r#"
+//- /src/lib.rs crate:foo
+#[path = "../$0"]
+mod foo;
+//- /other.rs
+//- /data.txt
+"#,
+            expect![[r#"
+                fi other.rs
+                fo src/
+            "#]],
+        );
+    }
+
+    #[test]
+    fn completes_files_in_out_dir() {
+        check(
+            r#"
+//- /lib.rs crate:foo env:OUT_DIR=/out
+include!(concat!(env!("OUT_DIR"), "/$0"));
+//- /out/bindings.rs
+//- /out/build.txt
+"#,
+            expect![[r#"
+                fi bindings.rs
+            "#]],
+        );
+    }
+
+    #[test]
+    fn no_completion_in_other_strings() {
+        check(
+            r#"
+//- /lib.rs
+const S: &str = concat!("$0");
+//- /data.txt
+"#,
+            expect![[r#""#]],
+        );
+    }
+}
@@ -55,13 +55,17 @@ pub(crate) fn complete_unqualified_path(acc: &mut Completions, ctx: &CompletionC
     }
 
     if !ctx.expects_type() {
-        if let Some(hir::Adt::Enum(e)) =
-            ctx.expected_type.as_ref().and_then(|ty| ty.strip_references().as_adt())
-        {
+        let expected_adt = ctx.expected_type.as_ref().and_then(|ty| ty.strip_references().as_adt());
+        if let Some(hir::Adt::Enum(e)) = expected_adt {
             super::enum_variants_with_paths(acc, ctx, e, |acc, ctx, variant, path| {
                 acc.add_qualified_enum_variant(ctx, variant, path)
             });
         }
+        if let Some(adt) = expected_adt.filter(|_| ctx.expects_expression()) {
+            super::constructors_with_paths(acc, ctx, adt, |acc, ctx, func, path| {
+                acc.add_qualified_function(ctx, func, path)
+            });
+        }
     }
 
     if let Some(ImmediateLocation::GenericArgList(arg_list)) = &ctx.completion_location {
@@ -601,4 +605,26 @@ fn f() -> m::E { V$0 }
             "#]],
         )
     }
+
+    #[test]
+    fn restricts_values_to_expected_type() {
+        check_with_config(
+            CompletionConfig { restrict_to_expected_type: true, ..TEST_CONFIG },
+            r#"
+struct Foo;
+impl Foo { fn new() -> Foo { Foo } }
+const LIMIT: u32 = 0;
+fn make() -> Foo { Foo }
+fn main() {
+    let local = 92;
+    let foo: Foo = $0;
+}
+"#,
+            expect![[r#"
+                fn Foo::new() fn() -> Foo
+                st Foo
+                fn make()     fn() -> Foo
+            "#]],
+        )
+    }
 }
@@ -223,6 +223,8 @@ pub enum CompletionItemKind {
     Attribute,
     Binding,
     BuiltinType,
+    File,
+    Folder,
     Keyword,
     Method,
     Snippet,
@@ -260,6 +262,8 @@ impl CompletionItemKind {
             CompletionItemKind::Attribute => "at",
             CompletionItemKind::Binding => "bn",
             CompletionItemKind::BuiltinType => "bt",
+            CompletionItemKind::File => "fi",
+            CompletionItemKind::Folder => "fo",
             CompletionItemKind::Keyword => "kw",
             CompletionItemKind::Method => "me",
             CompletionItemKind::Snippet => "sn",
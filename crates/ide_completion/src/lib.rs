@@ -17,7 +17,7 @@ use ide_db::{
         import_assets::{LocatedImport, NameToImport},
         insert_use::ImportScope,
     },
-    items_locator, RootDatabase,
+    items_locator, RootDatabase, SymbolKind,
 };
 use text_edit::TextEdit;
 
@@ -160,13 +160,46 @@ pub fn completions(
     completions::postfix::complete_postfix(&mut acc, &ctx);
     completions::trait_impl::complete_trait_impl(&mut acc, &ctx);
     completions::mod_::complete_mod(&mut acc, &ctx);
+    completions::file_path::complete_file_path(&mut acc, &ctx);
     completions::flyimport::import_on_the_fly(&mut acc, &ctx);
     completions::lifetime::complete_lifetime(&mut acc, &ctx);
     completions::lifetime::complete_label(&mut acc, &ctx);
 
+    if config.restrict_to_expected_type {
+        restrict_to_expected_type(&mut acc, &ctx);
+    }
+
     Some(acc)
 }
 
+/// Drops the values whose type doesn't fit the expected type of the expression
+/// being completed, keeping keywords, snippets, types and the like.
+fn restrict_to_expected_type(acc: &mut Completions, ctx: &CompletionContext) {
+    let has_expected_type =
+        ctx.expected_type.as_ref().map_or(false, |ty| !ty.is_unit() && !ty.is_unknown());
+    if !ctx.expects_expression() || !has_expected_type {
+        return;
+    }
+    acc.retain(|item| {
+        let is_value = matches!(
+            item.kind(),
+            Some(
+                CompletionItemKind::Method
+                    | CompletionItemKind::SymbolKind(
+                        SymbolKind::Local
+                            | SymbolKind::ValueParam
+                            | SymbolKind::Const
+                            | SymbolKind::Static
+                            | SymbolKind::Function
+                            | SymbolKind::Field
+                            | SymbolKind::Variant
+                    )
+            )
+        );
+        !is_value || item.relevance().type_match.is_some() || item.ref_match().is_some()
+    });
+}
+
 /// Resolves additional completion data at the position given.
 pub fn resolve_completion_edits(
     db: &RootDatabase,
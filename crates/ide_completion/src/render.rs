@@ -230,7 +230,17 @@ fn render_resolution_(
         if let Some(ref_match) = compute_ref_match(ctx.completion, &ty) {
             item.ref_match(ref_match);
         }
-    };
+    } else if let Some(ty) = scope_def_value_ty(&ctx, resolution) {
+        item.set_relevance(CompletionRelevance {
+            type_match: compute_type_match(ctx.completion, &ty),
+            exact_name_match: compute_exact_name_match(ctx.completion, &local_name),
+            ..CompletionRelevance::default()
+        });
+
+        if let Some(ref_match) = compute_ref_match(ctx.completion, &ty) {
+            item.ref_match(ref_match);
+        }
+    }
 
     // Add `<>` for generic types
     if matches!(
@@ -259,6 +269,22 @@ fn render_resolution_(
     Some(item.build())
 }
 
+/// The type of the value `resolution` evaluates to when used as an expression.
+fn scope_def_value_ty(ctx: &RenderContext<'_>, resolution: &hir::ScopeDef) -> Option<hir::Type> {
+    use hir::ModuleDef::*;
+    let db = ctx.db();
+    match resolution {
+        hir::ScopeDef::ModuleDef(Const(it)) => Some(it.ty(db)),
+        hir::ScopeDef::ModuleDef(Static(it)) => Some(it.ty(db)),
+        hir::ScopeDef::ModuleDef(Adt(hir::Adt::Struct(it)))
+            if ctx.completion.expects_expression() && it.kind(db) == hir::StructKind::Unit =>
+        {
+            Some(it.ty(db))
+        }
+        _ => None,
+    }
+}
+
 fn scope_def_docs(db: &RootDatabase, resolution: &hir::ScopeDef) -> Option<hir::Documentation> {
     use hir::ModuleDef::*;
     match resolution {
@@ -1086,6 +1112,7 @@ fn main() {
                 lc s [name+local]
                 lc &mut s [type+name+local]
                 st S []
+                st &mut S [type]
                 fn main() []
                 fn foo(…) []
             "#]],
@@ -1101,7 +1128,7 @@ fn main() {
             "#,
             expect![[r#"
                 lc s [type+name+local]
-                st S []
+                st S [type]
                 fn main() []
                 fn foo(…) []
             "#]],
@@ -1139,6 +1166,7 @@ fn main() {
                 lc &t [type+local]
                 st T []
                 st S []
+                st &S [type]
                 fn main() []
                 fn foo(…) []
                 md core []
@@ -1184,6 +1212,7 @@ fn main() {
                 lc &mut t [type+local]
                 st T []
                 st S []
+                st &mut S [type]
                 fn main() []
                 fn foo(…) []
                 md core []
@@ -1278,6 +1307,7 @@ fn main() {
             expect![[r#"
                 st T []
                 st S []
+                st &S [type]
                 fn main() []
                 fn bar() []
                 fn &bar() [type]
@@ -1395,4 +1425,65 @@ fn main() {
             "#]],
         );
     }
+
+    #[test]
+    fn const_and_static_type_match() {
+        check_relevance(
+            r#"
+const ANSWER: u32 = 42;
+static NAME: &str = "";
+fn takes(x: u32) {}
+fn main() { takes($0); }
+"#,
+            expect![[r#"
+                ct ANSWER [type]
+                fn takes(…) []
+                fn main() []
+                sc NAME []
+            "#]],
+        );
+    }
+
+    #[test]
+    fn unit_struct_type_match() {
+        check_relevance(
+            r#"
+struct Unit;
+struct Other;
+fn takes(x: Unit) {}
+fn main() { takes($0); }
+"#,
+            expect![[r#"
+                st Unit [type]
+                fn takes(…) []
+                st Other []
+                fn main() []
+            "#]],
+        );
+    }
+
+    #[test]
+    fn completes_constructors_of_expected_type() {
+        check_relevance(
+            r#"
+mod m {
+    pub struct Foo {}
+    impl Foo {
+        pub fn new() -> Foo { Foo {} }
+        fn private() -> Foo { Foo {} }
+        pub fn method(&self) -> Foo { Foo {} }
+        pub fn other() -> u32 { 0 }
+    }
+}
+fn takes(foo: m::Foo) {}
+fn main() { takes($0); }
+"#,
+            expect![[r#"
+                fn m::Foo::new() [type]
+                fn takes(…) []
+                md m []
+                fn main() []
+            "#]],
+        );
+    }
 }
@@ -9,7 +9,8 @@ use syntax::{
 
 use crate::{
     item::{CompletionItem, CompletionKind},
-    render::RenderContext,
+    render::{compute_exact_name_match, compute_ref_match, compute_type_match, RenderContext},
+    CompletionRelevance,
 };
 
 pub(crate) fn render_const<'a>(
@@ -46,6 +47,16 @@ impl<'a> ConstRender<'a> {
             )
             .detail(detail);
 
+        let ty = self.const_.ty(self.ctx.db());
+        item.set_relevance(CompletionRelevance {
+            type_match: compute_type_match(self.ctx.completion, &ty),
+            exact_name_match: compute_exact_name_match(self.ctx.completion, &name),
+            ..CompletionRelevance::default()
+        });
+        if let Some(ref_match) = compute_ref_match(self.ctx.completion, &ty) {
+            item.ref_match(ref_match);
+        }
+
         let db = self.ctx.db();
         if let Some(actm) = self.const_.as_assoc_item(db) {
             if let Some(trt) = actm.containing_trait_or_trait_impl(db) {
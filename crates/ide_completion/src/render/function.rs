@@ -23,6 +23,18 @@ pub(crate) fn render_fn<'a>(
     Some(FunctionRender::new(ctx, None, local_name, fn_, false)?.render(import_to_add))
 }
 
+/// Renders an associated function as `Type::function`.
+pub(crate) fn render_qualified_fn<'a>(
+    ctx: RenderContext<'a>,
+    path: hir::ModPath,
+    fn_: hir::Function,
+) -> Option<CompletionItem> {
+    let _p = profile::span("render_qualified_fn");
+    let mut render = FunctionRender::new(ctx, None, None, fn_, false)?;
+    render.qualifier = Some(path);
+    Some(render.render(None))
+}
+
 pub(crate) fn render_method<'a>(
     ctx: RenderContext<'a>,
     import_to_add: Option<ImportEdit>,
@@ -39,6 +51,7 @@ struct FunctionRender<'a> {
     ctx: RenderContext<'a>,
     name: String,
     receiver: Option<hir::Name>,
+    qualifier: Option<hir::ModPath>,
     func: hir::Function,
     ast_node: Fn,
     is_method: bool,
@@ -55,13 +68,23 @@ impl<'a> FunctionRender<'a> {
         let name = local_name.unwrap_or_else(|| fn_.name(ctx.db())).to_string();
         let ast_node = fn_.source(ctx.db())?.value;
 
-        Some(FunctionRender { ctx, name, receiver, func: fn_, ast_node, is_method })
+        Some(FunctionRender {
+            ctx,
+            name,
+            receiver,
+            qualifier: None,
+            func: fn_,
+            ast_node,
+            is_method,
+        })
     }
 
     fn render(self, import_to_add: Option<ImportEdit>) -> CompletionItem {
         let params = self.params();
         let call = if let Some(receiver) = &self.receiver {
             format!("{}.{}", receiver, &self.name)
+        } else if let Some(qualifier) = &self.qualifier {
+            format!("{}::{}", qualifier, &self.name)
         } else {
             self.name.clone()
         };
@@ -84,7 +107,8 @@ impl<'a> FunctionRender<'a> {
             }
         }
 
-        item.add_import(import_to_add).lookup_by(self.name);
+        let lookup = if self.qualifier.is_some() { call.clone() } else { self.name.clone() };
+        item.add_import(import_to_add).lookup_by(lookup);
 
         let ret_type = self.func.ret_type(self.ctx.db());
         item.set_relevance(CompletionRelevance {
@@ -35,7 +35,32 @@ use self as this;
 
 #[test]
 fn inside_nested_attr() {
-    check(r#"#[cfg($0)]"#, expect![[]])
+    check(
+        r#"#[cfg($0)]"#,
+        expect![[r#"
+            at debug_assertions
+            at doc
+            at doctest
+            at feature
+            at panic
+            at proc_macro
+            at target_arch
+            at target_endian
+            at target_env
+            at target_family
+            at target_feature
+            at target_has_atomic
+            at target_os
+            at target_pointer_width
+            at target_vendor
+            at test
+            at unix
+            at windows
+            at all(…)
+            at any(…)
+            at not(…)
+        "#]],
+    )
 }
 
 #[test]
@@ -553,6 +578,89 @@ mod cfg {
 "#]],
         );
     }
+
+    #[test]
+    fn cfg_declared_features() {
+        check(
+            r#"
+//- /main.rs crate:main cfg:feature=std,feature=serde
+#[cfg(feature = $0)]
+fn f() {}
+"#,
+            expect![[r#"
+                at serde
+                at std
+            "#]],
+        );
+    }
+
+    #[test]
+    fn cfg_crate_keys() {
+        check(
+            r#"
+//- /main.rs crate:main cfg:gpu,target_os=linux
+#[cfg(any(unix, $0))]
+fn f() {}
+"#,
+            expect![[r#"
+                at debug_assertions
+                at doc
+                at doctest
+                at feature
+                at gpu
+                at panic
+                at proc_macro
+                at target_arch
+                at target_endian
+                at target_env
+                at target_family
+                at target_feature
+                at target_has_atomic
+                at target_os
+                at target_pointer_width
+                at target_vendor
+                at test
+                at unix
+                at windows
+                at all(…)
+                at any(…)
+                at not(…)
+            "#]],
+        );
+    }
+
+    #[test]
+    fn cfg_value_in_string() {
+        check_edit(
+            "std",
+            r#"
+//- /main.rs crate:main cfg:feature=std
+#[cfg(feature = "s$0")]
+fn f() {}
+"#,
+            r#"
+#[cfg(feature = "std")]
+fn f() {}
+"#,
+        );
+    }
+
+    #[test]
+    fn cfg_attr_predicate() {
+        check(
+            r#"#[cfg_attr(target_pointer_width = $0, derive(Debug))] struct S;"#,
+            expect![[r#"
+                at 16
+                at 32
+                at 64
+            "#]],
+        );
+    }
+
+    #[test]
+    fn cfg_attr_after_predicate() {
+        check(r#"#[cfg_attr(unix, $0)] struct S;"#, expect![[]]);
+    }
 }
 
 mod derive {
@@ -1,9 +1,12 @@
 //! Applies changes to the IDE state transactionally.
 
-use std::{fmt, sync::Arc};
+use std::{cmp::Reverse, fmt, sync::Arc};
 
 use base_db::{
-    salsa::{Database, Durability, SweepStrategy},
+    salsa::{
+        debug::{DebugQueryTable, TableEntry},
+        Database, Durability, SweepStrategy,
+    },
     Change, FileId, SourceRootId,
 };
 use profile::{memory_usage, Bytes};
@@ -39,6 +42,47 @@ impl fmt::Debug for RootChange {
     }
 }
 
+/// Memory attributed to the queries of the database, see
+/// [`RootDatabase::per_query_memory_usage`].
+#[derive(Debug, Default)]
+pub struct MemoryUsageReport {
+    /// Sorted by size, largest first.
+    pub queries: Vec<QueryMemoryUsage>,
+    /// The largest individual query values, largest first.
+    pub largest_keys: Vec<KeyMemoryUsage>,
+}
+
+#[derive(Debug)]
+pub struct QueryMemoryUsage {
+    /// The query group (database trait) which defines the query.
+    pub group: &'static str,
+    pub query: String,
+    pub bytes: Bytes,
+}
+
+#[derive(Debug)]
+pub struct KeyMemoryUsage {
+    pub query: String,
+    /// `Debug` representation of the key, truncated.
+    pub key: String,
+    pub bytes: Bytes,
+}
+
+impl MemoryUsageReport {
+    /// Sums up the memory usage of each query group, largest first.
+    pub fn per_group(&self) -> Vec<(&'static str, Bytes)> {
+        let mut groups: Vec<(&'static str, Bytes)> = Vec::new();
+        for query in &self.queries {
+            match groups.iter_mut().find(|(group, _)| *group == query.group) {
+                Some((_, bytes)) => *bytes += query.bytes,
+                None => groups.push((query.group, query.bytes)),
+            }
+        }
+        groups.sort_by_key(|&(_, bytes)| Reverse(bytes));
+        groups
+    }
+}
+
 impl RootDatabase {
     pub fn request_cancellation(&mut self) {
         let _p = profile::span("RootDatabase::request_cancellation");
@@ -102,115 +146,171 @@ impl RootDatabase {
     // | VS Code | **Rust Analyzer: Memory Usage (Clears Database)**
     // |===
     // image::https://user-images.githubusercontent.com/48062697/113065592-08559f00-91b1-11eb-8c96-64b88068ec02.gif[]
-    pub fn per_query_memory_usage(&mut self) -> Vec<(String, Bytes)> {
-        let mut acc: Vec<(String, Bytes)> = vec![];
+    // Feature: Memory Usage
+    //
+    // Clears rust-analyzer's internal database and prints memory usage statistics.
+    //
+    // |===
+    // | Editor  | Action Name
+    //
+    // | VS Code | **Rust Analyzer: Memory Usage (Clears Database)**
+    // |===
+    // image::https://user-images.githubusercontent.com/48062697/113065592-08559f00-91b1-11eb-8c96-64b88068ec02.gif[]
+    /// If `n_largest_keys` is non-zero, the values of every query are also
+    /// measured one by one, which is slow, and the largest ones are reported.
+    pub fn per_query_memory_usage(&mut self, n_largest_keys: usize) -> MemoryUsageReport {
+        const MAX_KEY_LEN: usize = 120;
+
+        let mut acc = MemoryUsageReport::default();
         let sweep = SweepStrategy::default().discard_values().sweep_all_revisions();
         macro_rules! sweep_each_query {
-            ($($q:path)*) => {$(
+            ($($group:literal: [$($q:path)*])*) => {$($(
+                let q: $q = Default::default();
+                let name = format!("{:?}", q);
+
+                // Take a copy of all values, so that their memory can be
+                // measured one by one after the database has dropped its own.
                 let before = memory_usage().allocated;
+                let entries: Vec<TableEntry<_, _>> =
+                    if n_largest_keys > 0 { $q.in_db(self).entries() } else { Vec::new() };
                 $q.in_db(self).sweep(sweep);
+                for entry in entries {
+                    let before = memory_usage().allocated;
+                    {
+                        let _value = entry.value;
+                    }
+                    let bytes = before - memory_usage().allocated;
+                    let is_large = acc.largest_keys.len() < n_largest_keys
+                        || acc.largest_keys.last().map_or(false, |it| it.bytes < bytes);
+                    if is_large {
+                        let mut key = format!("{:?}", entry.key);
+                        if key.len() > MAX_KEY_LEN {
+                            let end = (0..=MAX_KEY_LEN).rev().find(|&it| key.is_char_boundary(it));
+                            key.truncate(end.unwrap_or(0));
+                            key.push_str("...");
+                        }
+                        acc.largest_keys.push(KeyMemoryUsage { query: name.clone(), key, bytes });
+                        acc.largest_keys.sort_by_key(|it| Reverse(it.bytes));
+                        acc.largest_keys.truncate(n_largest_keys);
+                    }
+                }
                 let after = memory_usage().allocated;
-                let q: $q = Default::default();
-                let name = format!("{:?}", q);
-                acc.push((name, before - after));
+                acc.queries.push(QueryMemoryUsage {
+                    group: $group,
+                    query: name.clone(),
+                    bytes: before - after,
+                });
 
                 let before = memory_usage().allocated;
                 $q.in_db(self).sweep(sweep.discard_everything());
                 let after = memory_usage().allocated;
-                let q: $q = Default::default();
-                let name = format!("{:?} (deps)", q);
-                acc.push((name, before - after));
+                acc.queries.push(QueryMemoryUsage {
+                    group: $group,
+                    query: format!("{} (deps)", name),
+                    bytes: before - after,
+                });
 
                 let before = memory_usage().allocated;
                 $q.in_db(self).purge();
                 let after = memory_usage().allocated;
-                let q: $q = Default::default();
-                let name = format!("{:?} (purge)", q);
-                acc.push((name, before - after));
-            )*}
+                acc.queries.push(QueryMemoryUsage {
+                    group: $group,
+                    query: format!("{} (purge)", name),
+                    bytes: before - after,
+                });
+            )*)*}
         }
         sweep_each_query![
-            // SourceDatabase
-            base_db::ParseQuery
-            base_db::CrateGraphQuery
-
-            // SourceDatabaseExt
-            base_db::FileTextQuery
-            base_db::FileSourceRootQuery
-            base_db::SourceRootQuery
-            base_db::SourceRootCratesQuery
-
-            // AstDatabase
-            hir::db::AstIdMapQuery
-            hir::db::MacroArgTextQuery
-            hir::db::MacroDefQuery
-            hir::db::ParseMacroExpansionQuery
-            hir::db::MacroExpandQuery
-            hir::db::HygieneFrameQuery
-
-            // DefDatabase
-            hir::db::FileItemTreeQuery
-            hir::db::BlockDefMapQuery
-            hir::db::CrateDefMapQueryQuery
-            hir::db::FieldsAttrsQuery
-            hir::db::VariantsAttrsQuery
-            hir::db::FieldsAttrsSourceMapQuery
-            hir::db::VariantsAttrsSourceMapQuery
-            hir::db::StructDataQuery
-            hir::db::UnionDataQuery
-            hir::db::EnumDataQuery
-            hir::db::ImplDataQuery
-            hir::db::TraitDataQuery
-            hir::db::TypeAliasDataQuery
-            hir::db::FunctionDataQuery
-            hir::db::ConstDataQuery
-            hir::db::StaticDataQuery
-            hir::db::BodyWithSourceMapQuery
-            hir::db::BodyQuery
-            hir::db::ExprScopesQuery
-            hir::db::GenericParamsQuery
-            hir::db::AttrsQuery
-            hir::db::CrateLangItemsQuery
-            hir::db::LangItemQuery
-            hir::db::ImportMapQuery
-
-            // HirDatabase
-            hir::db::InferQueryQuery
-            hir::db::TyQuery
-            hir::db::ValueTyQuery
-            hir::db::ImplSelfTyQuery
-            hir::db::ImplTraitQuery
-            hir::db::FieldTypesQuery
-            hir::db::CallableItemSignatureQuery
-            hir::db::GenericPredicatesForParamQuery
-            hir::db::GenericPredicatesQuery
-            hir::db::GenericDefaultsQuery
-            hir::db::InherentImplsInCrateQuery
-            hir::db::TraitEnvironmentQuery
-            hir::db::TraitImplsInCrateQuery
-            hir::db::TraitImplsInDepsQuery
-            hir::db::AssociatedTyDataQuery
-            hir::db::AssociatedTyDataQuery
-            hir::db::TraitDatumQuery
-            hir::db::StructDatumQuery
-            hir::db::ImplDatumQuery
-            hir::db::FnDefDatumQuery
-            hir::db::ReturnTypeImplTraitsQuery
-            hir::db::InternCallableDefQuery
-            hir::db::InternTypeParamIdQuery
-            hir::db::InternImplTraitIdQuery
-            hir::db::InternClosureQuery
-            hir::db::AssociatedTyValueQuery
-            hir::db::TraitSolveQueryQuery
-
-            // SymbolsDatabase
-            crate::symbol_index::FileSymbolsQuery
-            crate::symbol_index::LibrarySymbolsQuery
-            crate::symbol_index::LocalRootsQuery
-            crate::symbol_index::LibraryRootsQuery
-
-            // LineIndexDatabase
-            crate::LineIndexQuery
+            "SourceDatabase": [
+                base_db::ParseQuery
+                base_db::CrateGraphQuery
+                base_db::ActiveTargetsQuery
+            ]
+
+            "SourceDatabaseExt": [
+                base_db::FileSourceQuery
+                base_db::FileTextQuery
+                base_db::FileSourceRootQuery
+                base_db::SourceRootQuery
+                base_db::SourceRootCratesQuery
+            ]
+
+            "AstDatabase": [
+                hir::db::AstIdMapQuery
+                hir::db::MacroArgTextQuery
+                hir::db::MacroDefQuery
+                hir::db::ParseMacroExpansionQuery
+                hir::db::MacroExpandQuery
+                hir::db::HygieneFrameQuery
+            ]
+
+            "DefDatabase": [
+                hir::db::FileItemTreeQuery
+                hir::db::BlockDefMapQuery
+                hir::db::CrateDefMapQueryQuery
+                hir::db::FieldsAttrsQuery
+                hir::db::VariantsAttrsQuery
+                hir::db::FieldsAttrsSourceMapQuery
+                hir::db::VariantsAttrsSourceMapQuery
+                hir::db::StructDataQuery
+                hir::db::UnionDataQuery
+                hir::db::EnumDataQuery
+                hir::db::ImplDataQuery
+                hir::db::TraitDataQuery
+                hir::db::TypeAliasDataQuery
+                hir::db::FunctionDataQuery
+                hir::db::ConstDataQuery
+                hir::db::StaticDataQuery
+                hir::db::BodyWithSourceMapQuery
+                hir::db::BodyQuery
+                hir::db::ExprScopesQuery
+                hir::db::GenericParamsQuery
+                hir::db::AttrsQuery
+                hir::db::CrateLangItemsQuery
+                hir::db::LangItemQuery
+                hir::db::ImportMapQuery
+            ]
+
+            "HirDatabase": [
+                hir::db::InferQueryQuery
+                hir::db::TyQuery
+                hir::db::ValueTyQuery
+                hir::db::ImplSelfTyQuery
+                hir::db::ImplTraitQuery
+                hir::db::FieldTypesQuery
+                hir::db::CallableItemSignatureQuery
+                hir::db::GenericPredicatesForParamQuery
+                hir::db::GenericPredicatesQuery
+                hir::db::GenericDefaultsQuery
+                hir::db::InherentImplsInCrateQuery
+                hir::db::TraitEnvironmentQuery
+                hir::db::TraitImplsInCrateQuery
+                hir::db::TraitImplsInDepsQuery
+                hir::db::AssociatedTyDataQuery
+                hir::db::AssociatedTyDataQuery
+                hir::db::TraitDatumQuery
+                hir::db::StructDatumQuery
+                hir::db::ImplDatumQuery
+                hir::db::FnDefDatumQuery
+                hir::db::ReturnTypeImplTraitsQuery
+                hir::db::InternCallableDefQuery
+                hir::db::InternTypeParamIdQuery
+                hir::db::InternImplTraitIdQuery
+                hir::db::InternClosureQuery
+                hir::db::AssociatedTyValueQuery
+                hir::db::TraitSolveQueryQuery
+            ]
+
+            "SymbolsDatabase": [
+                crate::symbol_index::FileSymbolsQuery
+                crate::symbol_index::LibrarySymbolsQuery
+                crate::symbol_index::LocalRootsQuery
+                crate::symbol_index::LibraryRootsQuery
+            ]
+
+            "LineIndexDatabase": [
+                crate::LineIndexQuery
+            ]
         ];
 
         // To collect interned data, we need to bump the revision counter by performing a synthetic
@@ -220,25 +320,28 @@ impl RootDatabase {
         self.salsa_runtime_mut().synthetic_write(Durability::HIGH);
 
         sweep_each_query![
-            // AstDatabase
-            hir::db::InternMacroQuery
-
-            // InternDatabase
-            hir::db::InternFunctionQuery
-            hir::db::InternStructQuery
-            hir::db::InternUnionQuery
-            hir::db::InternEnumQuery
-            hir::db::InternConstQuery
-            hir::db::InternStaticQuery
-            hir::db::InternTraitQuery
-            hir::db::InternTypeAliasQuery
-            hir::db::InternImplQuery
-
-            // HirDatabase
-            hir::db::InternTypeParamIdQuery
+            "AstDatabase": [
+                hir::db::InternMacroQuery
+            ]
+
+            "InternDatabase": [
+                hir::db::InternFunctionQuery
+                hir::db::InternStructQuery
+                hir::db::InternUnionQuery
+                hir::db::InternEnumQuery
+                hir::db::InternConstQuery
+                hir::db::InternStaticQuery
+                hir::db::InternTraitQuery
+                hir::db::InternTypeAliasQuery
+                hir::db::InternImplQuery
+            ]
+
+            "HirDatabase": [
+                hir::db::InternTypeParamIdQuery
+            ]
         ];
 
-        acc.sort_by_key(|it| std::cmp::Reverse(it.1));
+        acc.queries.sort_by_key(|it| Reverse(it.bytes));
         acc
     }
 }
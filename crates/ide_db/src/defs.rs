@@ -5,9 +5,11 @@
 
 // FIXME: this badly needs rename/rewrite (matklad, 2020-02-06).
 
+use std::iter::successors;
+
 use hir::{
-    Field, GenericParam, HasVisibility, Impl, Label, Local, MacroDef, Module, ModuleDef, Name,
-    PathResolution, Semantics, Visibility,
+    AsAssocItem, CfgExpr, Field, GenericParam, HasAttrs, HasVisibility, Impl, Label, Local,
+    MacroDef, Module, ModuleDef, Name, PathResolution, Semantics, Visibility,
 };
 use syntax::{
     ast::{self, AstNode, PathSegmentKind},
@@ -91,6 +93,68 @@ impl Definition {
         };
         Some(name)
     }
+
+    /// The cfg under which the definition exists, made up of its own one and those of the
+    /// items and modules containing it.
+    pub fn cfg(&self, db: &RootDatabase) -> Option<CfgExpr> {
+        let mut attrs = Vec::new();
+        let module = match *self {
+            Definition::Macro(it) => {
+                attrs.push(it.attrs(db));
+                it.module(db)
+            }
+            Definition::Field(it) => {
+                attrs.push(it.attrs(db));
+                match it.parent_def(db) {
+                    hir::VariantDef::Struct(it) => attrs.push(it.attrs(db)),
+                    hir::VariantDef::Union(it) => attrs.push(it.attrs(db)),
+                    hir::VariantDef::Variant(it) => {
+                        attrs.push(it.attrs(db));
+                        attrs.push(it.parent_enum(db).attrs(db));
+                    }
+                }
+                self.module(db)
+            }
+            Definition::ModuleDef(hir::ModuleDef::Module(it)) => Some(it),
+            Definition::ModuleDef(it) => {
+                match it {
+                    hir::ModuleDef::Function(it) => attrs.push(it.attrs(db)),
+                    hir::ModuleDef::Adt(it) => attrs.push(it.attrs(db)),
+                    hir::ModuleDef::Variant(it) => {
+                        attrs.push(it.attrs(db));
+                        attrs.push(it.parent_enum(db).attrs(db));
+                    }
+                    hir::ModuleDef::Const(it) => attrs.push(it.attrs(db)),
+                    hir::ModuleDef::Static(it) => attrs.push(it.attrs(db)),
+                    hir::ModuleDef::Trait(it) => attrs.push(it.attrs(db)),
+                    hir::ModuleDef::TypeAlias(it) => attrs.push(it.attrs(db)),
+                    hir::ModuleDef::Module(_) | hir::ModuleDef::BuiltinType(_) => return None,
+                }
+                match it.as_assoc_item(db).map(|it| it.container(db)) {
+                    Some(hir::AssocItemContainer::Trait(it)) => attrs.push(it.attrs(db)),
+                    Some(hir::AssocItemContainer::Impl(it)) => attrs.push(it.attrs(db)),
+                    None => (),
+                }
+                it.module(db)
+            }
+            Definition::SelfType(it) => {
+                attrs.push(it.attrs(db));
+                Some(it.module(db))
+            }
+            Definition::Local(_) | Definition::GenericParam(_) | Definition::Label(_) => {
+                return None
+            }
+        };
+        attrs.extend(successors(module, |it| it.parent(db)).map(|it| it.attrs(db)));
+
+        let mut cfgs = attrs.iter().rev().filter_map(|it| it.cfg()).collect::<Vec<_>>();
+        cfgs.dedup();
+        match cfgs.len() {
+            0 => None,
+            1 => cfgs.pop(),
+            _ => Some(CfgExpr::All(cfgs)),
+        }
+    }
 }
 
 /// On a first blush, a single `ast::Name` defines a single definition at some
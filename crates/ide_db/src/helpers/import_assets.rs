@@ -1,18 +1,26 @@
 //! Look up accessible paths for items.
+use std::iter;
+
 use hir::{
-    AsAssocItem, AssocItem, AssocItemContainer, Crate, ItemInNs, MacroDef, ModPath, Module,
-    ModuleDef, PathResolution, PrefixKind, ScopeDef, Semantics, Type,
+    AsAssocItem, AssocItem, AssocItemContainer, CfgAtom, CfgExpr, CfgOptions, Crate, ItemInNs,
+    MacroDef, ModPath, Module, ModuleDef, PathKind, PathResolution, PrefixKind, ScopeDef,
+    Semantics, Type,
 };
 use itertools::Itertools;
 use rustc_hash::FxHashSet;
 use syntax::{
     ast::{self, NameOwner},
+    match_ast,
     utils::path_to_string_stripping_turbo_fish,
-    AstNode, SyntaxNode,
+    AstNode, SmolStr,
+    SyntaxKind::{ITEM_LIST, MODULE, SOURCE_FILE},
+    SyntaxNode,
 };
 
 use crate::{
+    defs::Definition,
     items_locator::{self, AssocItemSearch, DEFAULT_QUERY_SEARCH_LIMIT},
+    symbol_index::{self, FileSymbol},
     RootDatabase,
 };
 
@@ -193,6 +201,19 @@ impl LocatedImport {
     }
 }
 
+/// An import of an item which is removed by a `#[cfg]` on features of its crate
+/// that aren't enabled. The item is not known to the name resolution, so it is
+/// only found by its syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureGatedImport {
+    /// The path to the closest module of the item which is part of the crate,
+    /// followed by the names of the removed ones.
+    pub import_path: String,
+    /// The crate declaring the features.
+    pub krate: Crate,
+    pub features: Vec<SmolStr>,
+}
+
 impl ImportAssets {
     pub fn import_candidate(&self) -> &ImportCandidate {
         &self.import_candidate
@@ -221,6 +242,7 @@ impl ImportAssets {
         let _p = profile::span("import_assets::search_for");
 
         let scope_definitions = self.scope_definitions(sema);
+        let scope_cfg = self.scope_cfg(sema);
         let current_crate = self.module_with_candidate.krate();
         let mod_path = |item| {
             get_mod_path(
@@ -231,7 +253,7 @@ impl ImportAssets {
             )
         };
 
-        match &self.import_candidate {
+        let mut res = match &self.import_candidate {
             ImportCandidate::Path(path_candidate) => {
                 path_applicable_imports(sema, current_crate, path_candidate, mod_path)
             }
@@ -245,8 +267,138 @@ impl ImportAssets {
         .into_iter()
         .filter(|import| import.import_path.len() > 1)
         .filter(|import| !scope_definitions.contains(&ScopeDef::from(import.item_to_import)))
-        .sorted_by_key(|import| import.import_path.clone())
-        .collect()
+        .collect::<Vec<_>>();
+        res.sort_by_cached_key(|import| {
+            let required_cfg = self.required_cfg_in_scope(sema, import, scope_cfg.as_ref());
+            (required_cfg.is_some(), import.import_path.clone())
+        });
+        res
+    }
+
+    /// The part of the cfg of the imported item which doesn't hold for the code
+    /// around the candidate: the import breaks the build when it is disabled.
+    /// Such imports are proposed after the other ones.
+    pub fn required_cfg(
+        &self,
+        sema: &Semantics<RootDatabase>,
+        import: &LocatedImport,
+    ) -> Option<CfgExpr> {
+        self.required_cfg_in_scope(sema, import, self.scope_cfg(sema).as_ref())
+    }
+
+    fn required_cfg_in_scope(
+        &self,
+        sema: &Semantics<RootDatabase>,
+        import: &LocatedImport,
+        scope_cfg: Option<&CfgExpr>,
+    ) -> Option<CfgExpr> {
+        // Items of other crates are only resolved when active for their crate,
+        // the cfgs of the current one don't apply to them.
+        let item = item_definition(import.item_to_import);
+        if item.module(sema.db)?.krate() != self.module_with_candidate.krate() {
+            return None;
+        }
+        let present = scope_cfg.cloned().map(conjuncts).unwrap_or_default();
+        let mut required = conjuncts(item.cfg(sema.db)?);
+        required.retain(|cfg| !present.contains(cfg));
+        match required.len() {
+            0 => None,
+            1 => required.pop(),
+            _ => Some(CfgExpr::All(required)),
+        }
+    }
+
+    /// The cfg under which the code around the candidate exists.
+    fn scope_cfg(&self, sema: &Semantics<RootDatabase>) -> Option<CfgExpr> {
+        let _p = profile::span("import_assets::scope_cfg");
+        let container = self.candidate_node.ancestors().find_map(|node| {
+            match_ast! {
+                match node {
+                    ast::Fn(it) => sema.to_def(&it).map(|it| Definition::ModuleDef(it.into())),
+                    ast::Const(it) => sema.to_def(&it).map(|it| Definition::ModuleDef(it.into())),
+                    ast::Static(it) => sema.to_def(&it).map(|it| Definition::ModuleDef(it.into())),
+                    ast::Trait(it) => sema.to_def(&it).map(|it| Definition::ModuleDef(it.into())),
+                    ast::Impl(it) => sema.to_def(&it).map(Definition::SelfType),
+                    _ => None,
+                }
+            }
+        });
+        container
+            .unwrap_or_else(|| Definition::ModuleDef(self.module_with_candidate.into()))
+            .cfg(sema.db)
+    }
+
+    /// Imports for an unqualified path candidate of items which only exist
+    /// with features of their crate, that aren't enabled, and are therefore
+    /// missing from [`Self::search_for_imports`].
+    pub fn search_for_feature_gated_imports(
+        &self,
+        sema: &Semantics<RootDatabase>,
+        prefix_kind: PrefixKind,
+    ) -> Vec<FeatureGatedImport> {
+        let _p = profile::span("import_assets::search_for_feature_gated_imports");
+        let name = match &self.import_candidate {
+            ImportCandidate::Path(PathImportCandidate {
+                qualifier: None,
+                name: NameToImport::Exact(name),
+            }) => name,
+            _ => return Vec::new(),
+        };
+
+        let current_crate = self.module_with_candidate.krate();
+        let crates = iter::once(current_crate)
+            .chain(current_crate.dependencies(sema.db).into_iter().map(|dep| dep.krate));
+        let mut res = Vec::new();
+        for krate in crates {
+            let mut query = symbol_index::Query::new(name.clone());
+            query.exact();
+            query.limit(DEFAULT_QUERY_SEARCH_LIMIT);
+            res.extend(
+                symbol_index::crate_symbols(sema.db, krate.into(), query).iter().filter_map(
+                    |symbol| self.feature_gated_import(sema, krate, symbol, prefix_kind),
+                ),
+            );
+        }
+        res.sort_by(|a, b| a.import_path.cmp(&b.import_path));
+        res.dedup();
+        res
+    }
+
+    fn feature_gated_import(
+        &self,
+        sema: &Semantics<RootDatabase>,
+        krate: Crate,
+        symbol: &FileSymbol,
+        prefix_kind: PrefixKind,
+    ) -> Option<FeatureGatedImport> {
+        let node = symbol.ptr.to_node(sema.parse(symbol.file_id).syntax());
+        // Items of functions, impls and traits can't be imported.
+        if !node.ancestors().skip(1).all(|it| matches!(it.kind(), ITEM_LIST | MODULE | SOURCE_FILE))
+        {
+            return None;
+        }
+        let cfg = sema.inactive_cfg(&node)?;
+        let features = missing_features(&cfg, &krate.cfg(sema.db), &krate.potential_cfg(sema.db))?;
+
+        let file_module = sema.to_module_defs(symbol.file_id).find(|it| it.krate() == krate)?;
+        let module_path = get_mod_path(
+            sema.db,
+            ItemInNs::from(ModuleDef::from(file_module)),
+            &self.module_with_candidate,
+            Some(prefix_kind),
+        )?;
+        let mut segments = vec![symbol.name.to_string()];
+        for module in node.ancestors().skip(1).filter_map(ast::Module::cast) {
+            segments.push(module.name()?.to_string());
+        }
+        if module_path.kind != PathKind::Plain || !module_path.segments().is_empty() {
+            segments.push(module_path.to_string());
+        }
+        if segments.len() < 2 {
+            return None;
+        }
+        let import_path = segments.iter().rev().join("::");
+        Some(FeatureGatedImport { import_path, krate, features })
     }
 
     fn scope_definitions(&self, sema: &Semantics<RootDatabase>) -> FxHashSet<ScopeDef> {
@@ -619,6 +771,71 @@ fn path_import_candidate(
     })
 }
 
+fn item_definition(item: ItemInNs) -> Definition {
+    match item {
+        ItemInNs::Types(module_def_id) | ItemInNs::Values(module_def_id) => {
+            Definition::ModuleDef(module_def_id.into())
+        }
+        ItemInNs::Macros(macro_def_id) => Definition::Macro(macro_def_id.into()),
+    }
+}
+
+fn conjuncts(cfg: CfgExpr) -> Vec<CfgExpr> {
+    match cfg {
+        CfgExpr::All(cfgs) => cfgs.into_iter().flat_map(conjuncts).collect(),
+        cfg => vec![cfg],
+    }
+}
+
+/// The features which `cfg` needs on top of the `enabled` options, if enabling
+/// some of the features in the `declared` options is enough for it to hold.
+/// Of the alternatives of an `any`, the one needing the fewest features wins.
+pub fn missing_features(
+    cfg: &CfgExpr,
+    enabled: &CfgOptions,
+    declared: &CfgOptions,
+) -> Option<Vec<SmolStr>> {
+    let is_enabled = |atom: &CfgAtom| enabled.check(&atom.clone().into()) == Some(true);
+    let is_declared = |atom: &CfgAtom| declared.check(&atom.clone().into()) == Some(true);
+    let mut res = required_features(cfg, &is_enabled, &is_declared)?;
+    res.sort();
+    res.dedup();
+    // The features needed by one part may switch off a `not` in another one.
+    let holds = cfg.fold(&|atom| {
+        is_enabled(atom)
+            || matches!(atom, CfgAtom::KeyValue { key, value } if key == "feature" && res.contains(value))
+    });
+    Some(res).filter(|it| holds == Some(true) && !it.is_empty())
+}
+
+fn required_features(
+    cfg: &CfgExpr,
+    is_enabled: &dyn Fn(&CfgAtom) -> bool,
+    is_declared: &dyn Fn(&CfgAtom) -> bool,
+) -> Option<Vec<SmolStr>> {
+    match cfg {
+        CfgExpr::Invalid => None,
+        CfgExpr::Atom(atom) if is_enabled(atom) => Some(Vec::new()),
+        CfgExpr::Atom(atom @ CfgAtom::KeyValue { key, value })
+            if key == "feature" && is_declared(atom) =>
+        {
+            Some(vec![value.clone()])
+        }
+        CfgExpr::Atom(_) => None,
+        CfgExpr::All(cfgs) => cfgs
+            .iter()
+            .map(|cfg| required_features(cfg, is_enabled, is_declared))
+            .collect::<Option<Vec<_>>>()
+            .map(|it| it.concat()),
+        CfgExpr::Any(cfgs) => cfgs
+            .iter()
+            .filter_map(|cfg| required_features(cfg, is_enabled, is_declared))
+            .min_by_key(Vec::len),
+        // Features can only be enabled, so a negation has to hold already.
+        CfgExpr::Not(cfg) => (cfg.fold(is_enabled) == Some(false)).then(Vec::new),
+    }
+}
+
 fn item_as_assoc(db: &RootDatabase, item: ItemInNs) -> Option<AssocItem> {
     item.as_module_def_id()
         .and_then(|module_def_id| ModuleDef::from(module_def_id).as_assoc_item(db))
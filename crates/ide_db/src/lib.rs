@@ -33,6 +33,8 @@ use crate::{line_index::LineIndex, symbol_index::SymbolsDatabase};
 /// `base_db` is normally also needed in places where `ide_db` is used, so this re-export is for convenience.
 pub use base_db;
 
+pub use crate::apply_change::{KeyMemoryUsage, MemoryUsageReport, QueryMemoryUsage};
+
 #[salsa::database(
     base_db::SourceDatabaseStorage,
     base_db::SourceDatabaseExtStorage,
@@ -95,6 +97,7 @@ impl RootDatabase {
     pub fn new(lru_capacity: Option<usize>) -> RootDatabase {
         let mut db = RootDatabase { storage: salsa::Storage::default() };
         db.set_crate_graph_with_durability(Default::default(), Durability::HIGH);
+        db.set_active_targets_with_durability(Default::default(), Durability::MEDIUM);
         db.set_local_roots_with_durability(Default::default(), Durability::HIGH);
         db.set_library_roots_with_durability(Default::default(), Durability::HIGH);
         db.set_enable_proc_attr_macros(Default::default());
@@ -18,25 +18,27 @@
 //! }
 //! ```
 //!
-//! The correct behavior in such cases is probably to show a dialog to the user.
-//! Our current behavior is ¯\_(ツ)_/¯.
+//! Occurrences in macro calls are renamed if the identifier was passed in by
+//! the call, so that it can be mapped back to the source. The rename fails
+//! for the ones produced by the macro itself, listing where they are, as
+//! renaming only the other occurrences would break the code.
 use std::fmt;
 
-use base_db::{AnchoredPathBuf, FileId, FileRange};
+use base_db::{AnchoredPathBuf, FileId, FileRange, SourceDatabaseExt};
 use either::Either;
 use hir::{AsAssocItem, FieldSource, HasSource, InFile, ModuleSource, Semantics};
 use stdx::never;
 use syntax::{
     ast::{self, NameOwner},
-    lex_single_syntax_kind, AstNode, SyntaxKind, TextRange, T,
+    lex_single_syntax_kind, AstNode, SyntaxKind, SyntaxNode, TextRange, T,
 };
 use text_edit::TextEdit;
 
 use crate::{
     defs::Definition,
-    search::FileReference,
+    search::{FileReference, UsageSearchResult},
     source_change::{FileSystemEdit, SourceChange},
-    RootDatabase,
+    LineIndexDatabase, RootDatabase,
 };
 
 pub type Result<T, E = RenameError> = std::result::Result<T, E>;
@@ -79,16 +81,29 @@ impl Definition {
 
     /// Textual range of the identifier which will change when renaming this
     /// `Definition`. Note that some definitions, like buitin types, can't be
-    /// renamed.
+    /// renamed, neither can the ones whose name was produced by a macro
+    /// instead of being passed in by its call.
     pub fn range_for_rename(self, sema: &Semantics<RootDatabase>) -> Option<FileRange> {
-        // FIXME: the `original_file_range` calls here are wrong -- they never fail,
-        // and _fall back_ to the entirety of the macro call. Such fall back is
-        // incorrect for renames. The safe behavior would be to return an error for
-        // such cases. The correct behavior would be to return an auxiliary list of
-        // "can't rename these occurrences in macros" items, and then show some kind
-        // of a dialog to the user. See:
-        cov_mark::hit!(macros_are_broken_lol);
+        let name = self.name_for_rename(sema)?;
+        name.as_ref().original_file_range_opt(sema.db)
+    }
+
+    /// The error explaining why [`Definition::range_for_rename`] has no range,
+    /// if that's because a macro produced the name.
+    pub fn macro_rename_error(self, sema: &Semantics<RootDatabase>) -> Option<RenameError> {
+        let name = self.name_for_rename(sema)?;
+        if !name.file_id.is_macro() || name.as_ref().original_file_range_opt(sema.db).is_some() {
+            return None;
+        }
+        Some(format_err!(
+            "Cannot rename `{}`, its definition is produced by a macro",
+            name.value.text()
+        ))
+    }
 
+    /// The name (or lifetime) of the definition, which may be in a macro
+    /// expansion.
+    fn name_for_rename(self, sema: &Semantics<RootDatabase>) -> Option<InFile<SyntaxNode>> {
         let res = match self {
             Definition::Macro(mac) => {
                 let src = mac.source(sema.db)?;
@@ -96,7 +111,7 @@ impl Definition {
                     Either::Left(it) => it.name()?,
                     Either::Right(it) => it.name()?,
                 };
-                src.with_value(name.syntax()).original_file_range(sema.db)
+                src.with_value(name.syntax().clone())
             }
             Definition::Field(field) => {
                 let src = field.source(sema.db)?;
@@ -104,7 +119,7 @@ impl Definition {
                 match &src.value {
                     FieldSource::Named(record_field) => {
                         let name = record_field.name()?;
-                        src.with_value(name.syntax()).original_file_range(sema.db)
+                        src.with_value(name.syntax().clone())
                     }
                     FieldSource::Pos(_) => {
                         return None;
@@ -115,19 +130,19 @@ impl Definition {
                 hir::ModuleDef::Module(module) => {
                     let src = module.declaration_source(sema.db)?;
                     let name = src.value.name()?;
-                    src.with_value(name.syntax()).original_file_range(sema.db)
+                    src.with_value(name.syntax().clone())
                 }
-                hir::ModuleDef::Function(it) => name_range(it, sema)?,
+                hir::ModuleDef::Function(it) => name_node(it, sema)?,
                 hir::ModuleDef::Adt(adt) => match adt {
-                    hir::Adt::Struct(it) => name_range(it, sema)?,
-                    hir::Adt::Union(it) => name_range(it, sema)?,
-                    hir::Adt::Enum(it) => name_range(it, sema)?,
+                    hir::Adt::Struct(it) => name_node(it, sema)?,
+                    hir::Adt::Union(it) => name_node(it, sema)?,
+                    hir::Adt::Enum(it) => name_node(it, sema)?,
                 },
-                hir::ModuleDef::Variant(it) => name_range(it, sema)?,
-                hir::ModuleDef::Const(it) => name_range(it, sema)?,
-                hir::ModuleDef::Static(it) => name_range(it, sema)?,
-                hir::ModuleDef::Trait(it) => name_range(it, sema)?,
-                hir::ModuleDef::TypeAlias(it) => name_range(it, sema)?,
+                hir::ModuleDef::Variant(it) => name_node(it, sema)?,
+                hir::ModuleDef::Const(it) => name_node(it, sema)?,
+                hir::ModuleDef::Static(it) => name_node(it, sema)?,
+                hir::ModuleDef::Trait(it) => name_node(it, sema)?,
+                hir::ModuleDef::TypeAlias(it) => name_node(it, sema)?,
                 hir::ModuleDef::BuiltinType(_) => return None,
             },
             Definition::SelfType(_) => return None,
@@ -137,7 +152,7 @@ impl Definition {
                     Either::Left(bind_pat) => bind_pat.name()?,
                     Either::Right(_) => return None,
                 };
-                src.with_value(name.syntax()).original_file_range(sema.db)
+                src.with_value(name.syntax().clone())
             }
             Definition::GenericParam(generic_param) => match generic_param {
                 hir::GenericParam::TypeParam(type_param) => {
@@ -146,32 +161,31 @@ impl Definition {
                         Either::Left(type_param) => type_param.name()?,
                         Either::Right(_trait) => return None,
                     };
-                    src.with_value(name.syntax()).original_file_range(sema.db)
+                    src.with_value(name.syntax().clone())
                 }
                 hir::GenericParam::LifetimeParam(lifetime_param) => {
                     let src = lifetime_param.source(sema.db)?;
                     let lifetime = src.value.lifetime()?;
-                    src.with_value(lifetime.syntax()).original_file_range(sema.db)
+                    src.with_value(lifetime.syntax().clone())
                 }
-                hir::GenericParam::ConstParam(it) => name_range(it, sema)?,
+                hir::GenericParam::ConstParam(it) => name_node(it, sema)?,
             },
             Definition::Label(label) => {
                 let src = label.source(sema.db);
                 let lifetime = src.value.lifetime()?;
-                src.with_value(lifetime.syntax()).original_file_range(sema.db)
+                src.with_value(lifetime.syntax().clone())
             }
         };
         return Some(res);
 
-        fn name_range<D>(def: D, sema: &Semantics<RootDatabase>) -> Option<FileRange>
+        fn name_node<D>(def: D, sema: &Semantics<RootDatabase>) -> Option<InFile<SyntaxNode>>
         where
             D: HasSource,
             D::Ast: ast::NameOwner,
         {
             let src = def.source(sema.db)?;
             let name = src.value.name()?;
-            let res = src.with_value(name.syntax()).original_file_range(sema.db);
-            Some(res)
+            Some(src.with_value(name.syntax().clone()))
         }
     }
 }
@@ -212,7 +226,8 @@ fn rename_mod(
         }
     }
     let def = Definition::ModuleDef(hir::ModuleDef::Module(module));
-    let usages = def.usages(sema).all();
+    let usages = def.usages(sema).include_macro_produced_refs().all();
+    check_references_in_macros(sema, &usages)?;
     let ref_edits = usages.iter().map(|(&file_id, references)| {
         (file_id, source_edit_from_references(references, def, new_name))
     });
@@ -280,12 +295,13 @@ fn rename_reference(
             .unwrap_or(def),
         _ => def,
     };
-    let usages = def.usages(sema).all();
+    let usages = def.usages(sema).include_macro_produced_refs().all();
 
     if !usages.is_empty() && ident_kind == IdentifierKind::Underscore {
         cov_mark::hit!(rename_underscore_multiple);
         bail!("Cannot rename reference to `_` as it is being referenced multiple times");
     }
+    check_references_in_macros(sema, &usages)?;
     let mut source_change = SourceChange::default();
     source_change.extend(usages.iter().map(|(&file_id, references)| {
         (file_id, source_edit_from_references(references, def, new_name))
@@ -296,6 +312,44 @@ fn rename_reference(
     Ok(source_change)
 }
 
+/// Fails for references whose identifier was produced by a macro, as they
+/// can't be edited without editing the macro.
+fn check_references_in_macros(
+    sema: &Semantics<RootDatabase>,
+    usages: &UsageSearchResult,
+) -> Result<()> {
+    let mut name = None;
+    let mut locations = Vec::new();
+    for (&file_id, references) in usages.iter() {
+        for reference in references {
+            if sema.original_range_opt(reference.name.syntax()).is_none() {
+                name = Some(reference.name.syntax().text().to_string());
+                let location =
+                    display_location(sema.db, FileRange { file_id, range: reference.range });
+                if !locations.contains(&location) {
+                    locations.push(location);
+                }
+            }
+        }
+    }
+    match name {
+        Some(name) => {
+            bail!("Cannot rename `{}`, it is produced by macros in {}", name, locations.join(", "))
+        }
+        None => Ok(()),
+    }
+}
+
+fn display_location(db: &RootDatabase, frange: FileRange) -> String {
+    let line_col = db.line_index(frange.file_id).line_col(frange.range.start());
+    let source_root = db.source_root(db.file_source_root(frange.file_id));
+    let path = match source_root.path_for_file(&frange.file_id) {
+        Some(path) => path.to_string(),
+        None => format!("{:?}", frange.file_id),
+    };
+    format!("{}:{}:{}", path, line_col.line + 1, line_col.col + 1)
+}
+
 pub fn source_edit_from_references(
     references: &[FileReference],
     def: Definition,
@@ -412,9 +466,10 @@ fn source_edit_from_def(
     def: Definition,
     new_name: &str,
 ) -> Result<(FileId, TextEdit)> {
-    let frange = def
-        .range_for_rename(sema)
-        .ok_or_else(|| format_err!("No identifier available to rename"))?;
+    let frange = def.range_for_rename(sema).ok_or_else(|| {
+        def.macro_rename_error(sema)
+            .unwrap_or_else(|| format_err!("No identifier available to rename"))
+    })?;
 
     let mut replacement_text = String::new();
     let mut repl_range = frange.range;
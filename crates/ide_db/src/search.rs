@@ -8,12 +8,12 @@ use std::{convert::TryInto, mem};
 
 use base_db::{FileId, FileRange, SourceDatabase, SourceDatabaseExt};
 use hir::{
-    AsAssocItem, DefWithBody, HasAttrs, HasSource, InFile, ModuleDef, ModuleSource, Semantics,
-    Visibility,
+    AsAssocItem, DefWithBody, HasAttrs, HasSource, InFile, ModuleDef, ModuleSource, Origin,
+    Semantics, Visibility,
 };
 use once_cell::unsync::Lazy;
-use rustc_hash::FxHashMap;
-use syntax::{ast, match_ast, AstNode, TextRange, TextSize};
+use rustc_hash::{FxHashMap, FxHashSet};
+use syntax::{ast, match_ast, AstNode, SyntaxNode, TextRange, TextSize};
 
 use crate::{
     defs::{Definition, NameClass, NameRefClass},
@@ -59,6 +59,9 @@ pub struct FileReference {
     pub range: TextRange,
     pub name: ast::NameLike,
     pub access: Option<ReferenceAccess>,
+    /// The reference is in code disabled by a `#[cfg]`, which can't be
+    /// resolved, so it only matched by name.
+    pub inactive: bool,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -311,6 +314,8 @@ impl Definition {
             scope: None,
             include_self_kw_refs: None,
             search_self_mod: false,
+            include_inactive_code: false,
+            include_macro_produced_refs: false,
         }
     }
 }
@@ -321,6 +326,8 @@ pub struct FindUsages<'a> {
     scope: Option<SearchScope>,
     include_self_kw_refs: Option<hir::Type>,
     search_self_mod: bool,
+    include_inactive_code: bool,
+    include_macro_produced_refs: bool,
 }
 
 impl<'a> FindUsages<'a> {
@@ -331,6 +338,21 @@ impl<'a> FindUsages<'a> {
         self
     }
 
+    /// Also report name references with the name of the definition in code
+    /// disabled by a `#[cfg]`.
+    pub fn include_inactive_code(mut self) -> FindUsages<'a> {
+        self.include_inactive_code = true;
+        self
+    }
+
+    /// Also report name references which `macro_rules!` macros produce from
+    /// an identifier in their definition. The range of those is the one of
+    /// the macro call.
+    pub fn include_macro_produced_refs(mut self) -> FindUsages<'a> {
+        self.include_macro_produced_refs = true;
+        self
+    }
+
     pub fn in_scope(self, scope: SearchScope) -> FindUsages<'a> {
         self.set_scope(Some(scope))
     }
@@ -383,6 +405,7 @@ impl<'a> FindUsages<'a> {
             None => return,
         };
         let name = name.as_str();
+        let mut searched_macros = FxHashSet::default();
 
         for (file_id, search_range) in search_scope {
             let text = sema.db.file_text(file_id);
@@ -397,14 +420,24 @@ impl<'a> FindUsages<'a> {
                     continue;
                 }
 
-                if let Some(name) = sema.find_node_at_offset_with_descend(&tree, offset) {
-                    if match name {
+                if let Some(name_like) = sema.find_node_at_offset_with_descend(&tree, offset) {
+                    if match name_like {
+                        ast::NameLike::NameRef(name_ref)
+                            if self.include_inactive_code
+                                && sema.is_inactive_code(name_ref.syntax()) =>
+                        {
+                            self.found_inactive_name_ref(name, &name_ref, sink)
+                        }
                         ast::NameLike::NameRef(name_ref) => self.found_name_ref(&name_ref, sink),
                         ast::NameLike::Name(name) => self.found_name(&name, sink),
                         ast::NameLike::Lifetime(lifetime) => self.found_lifetime(&lifetime, sink),
                     } {
                         return;
                     }
+                } else if self.include_macro_produced_refs
+                    && self.found_in_macro_def(name, &tree, offset, &mut searched_macros, sink)
+                {
+                    return;
                 }
             }
             if let Some(self_ty) = &self.include_self_kw_refs {
@@ -476,6 +509,7 @@ impl<'a> FindUsages<'a> {
                     range,
                     name: ast::NameLike::NameRef(name_ref.clone()),
                     access: None,
+                    inactive: false,
                 };
                 sink(file_id, reference)
             }
@@ -495,6 +529,7 @@ impl<'a> FindUsages<'a> {
                     range,
                     name: ast::NameLike::NameRef(name_ref.clone()),
                     access: None,
+                    inactive: false,
                 };
                 sink(file_id, reference)
             }
@@ -514,6 +549,7 @@ impl<'a> FindUsages<'a> {
                     range,
                     name: ast::NameLike::Lifetime(lifetime.clone()),
                     access: None,
+                    inactive: false,
                 };
                 sink(file_id, reference)
             }
@@ -521,6 +557,72 @@ impl<'a> FindUsages<'a> {
         }
     }
 
+    fn found_inactive_name_ref(
+        &self,
+        name: &str,
+        name_ref: &ast::NameRef,
+        sink: &mut dyn FnMut(FileId, FileReference) -> bool,
+    ) -> bool {
+        if name_ref.text() != name {
+            return false;
+        }
+        let FileRange { file_id, range } = self.sema.original_range(name_ref.syntax());
+        let reference = FileReference {
+            range,
+            name: ast::NameLike::NameRef(name_ref.clone()),
+            access: None,
+            inactive: true,
+        };
+        sink(file_id, reference)
+    }
+
+    /// Looks for the name references a `macro_rules!` macro produces from the
+    /// identifier at `offset` in its definition, in the expansions of its calls.
+    fn found_in_macro_def(
+        &self,
+        name: &str,
+        tree: &SyntaxNode,
+        offset: TextSize,
+        searched_macros: &mut FxHashSet<hir::MacroDef>,
+        sink: &mut dyn FnMut(FileId, FileReference) -> bool,
+    ) -> bool {
+        let token = match tree.token_at_offset(offset).right_biased() {
+            Some(it) if it.text() == name => it,
+            _ => return false,
+        };
+        let mac = match token.ancestors().find_map(ast::Macro::cast) {
+            Some(it) => it,
+            None => return false,
+        };
+        let mac = match self.sema.to_def(&mac) {
+            Some(it) if searched_macros.insert(it) => it,
+            _ => return false,
+        };
+        let calls = Definition::Macro(mac).usages(self.sema).all();
+        for reference in calls.references.values().flatten() {
+            let call = match &reference.name {
+                ast::NameLike::NameRef(name_ref) => {
+                    name_ref.syntax().ancestors().find_map(ast::MacroCall::cast)
+                }
+                _ => None,
+            };
+            let expansion = match call.and_then(|call| self.sema.expand(&call)) {
+                Some(it) => it,
+                None => continue,
+            };
+            for name_ref in expansion.descendants().filter_map(ast::NameRef::cast) {
+                let from_def = name_ref.text() == name
+                    && name_ref
+                        .ident_token()
+                        .map_or(false, |it| self.sema.token_origin(&it) == Some(Origin::Def));
+                if from_def && self.found_name_ref(&name_ref, sink) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
     fn found_name_ref(
         &self,
         name_ref: &ast::NameRef,
@@ -533,6 +635,7 @@ impl<'a> FindUsages<'a> {
                     range,
                     name: ast::NameLike::NameRef(name_ref.clone()),
                     access: reference_access(&def, name_ref),
+                    inactive: false,
                 };
                 sink(file_id, reference)
             }
@@ -543,6 +646,7 @@ impl<'a> FindUsages<'a> {
                         range,
                         name: ast::NameLike::NameRef(name_ref.clone()),
                         access: reference_access(&def, name_ref),
+                        inactive: false,
                     };
                     sink(file_id, reference)
                 } else {
@@ -559,8 +663,12 @@ impl<'a> FindUsages<'a> {
                     }
                     _ => return false,
                 };
-                let reference =
-                    FileReference { range, name: ast::NameLike::NameRef(name_ref.clone()), access };
+                let reference = FileReference {
+                    range,
+                    name: ast::NameLike::NameRef(name_ref.clone()),
+                    access,
+                    inactive: false,
+                };
                 sink(file_id, reference)
             }
             _ => false,
@@ -584,13 +692,18 @@ impl<'a> FindUsages<'a> {
                     name: ast::NameLike::Name(name.clone()),
                     // FIXME: mutable patterns should have `Write` access
                     access: Some(ReferenceAccess::Read),
+                    inactive: false,
                 };
                 sink(file_id, reference)
             }
             Some(NameClass::ConstReference(def)) if self.def == def => {
                 let FileRange { file_id, range } = self.sema.original_range(name.syntax());
-                let reference =
-                    FileReference { range, name: ast::NameLike::Name(name.clone()), access: None };
+                let reference = FileReference {
+                    range,
+                    name: ast::NameLike::Name(name.clone()),
+                    access: None,
+                    inactive: false,
+                };
                 sink(file_id, reference)
             }
             // Resolve trait impl function definitions to the trait definition's version if self.def is the trait definition's
@@ -615,6 +728,7 @@ impl<'a> FindUsages<'a> {
                             range,
                             name: ast::NameLike::Name(name.clone()),
                             access: None,
+                            inactive: false,
                         };
                         sink(file_id, reference)
                     })
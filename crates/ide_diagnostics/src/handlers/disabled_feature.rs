@@ -0,0 +1,197 @@
+use hir::{Module, ModuleDef, ModuleSource, PathResolution, ScopeDef};
+use ide_db::helpers::import_assets::missing_features;
+use itertools::Itertools;
+use stdx::format_to;
+use syntax::{
+    algo::find_node_at_range,
+    ast::{self, ModuleItemOwner},
+    AstNode, SmolStr,
+};
+
+use crate::{Diagnostic, DiagnosticsContext};
+
+// Diagnostic: disabled-feature
+//
+// This diagnostic is triggered instead of `unresolved-import` when the imported
+// item exists, but is removed by a `#[cfg]` on cargo features of its crate
+// which aren't enabled.
+pub(crate) fn disabled_feature(
+    ctx: &DiagnosticsContext<'_>,
+    d: &hir::UnresolvedImport,
+) -> Option<Diagnostic> {
+    if d.decl.file_id.is_macro() {
+        return None;
+    }
+    let db = ctx.sema.db;
+    let root = ctx.sema.parse(d.decl.file_id.original_file(db));
+    let use_tree = d.decl.value.to_node(root.syntax());
+
+    let segments = use_tree_segments(&use_tree);
+    let (first, rest) = segments.split_first()?;
+    let mut module = match ctx.sema.resolve_path(&first.parent_path())? {
+        PathResolution::Def(ModuleDef::Module(it)) => it,
+        _ => return None,
+    };
+    let mut missing = None;
+    for segment in rest {
+        let name = segment.name_ref()?;
+        let child = module.scope(db, None).into_iter().find_map(|(it, def)| match def {
+            ScopeDef::ModuleDef(ModuleDef::Module(child)) if it.to_string() == name.text() => {
+                Some(child)
+            }
+            _ => None,
+        });
+        match child {
+            Some(it) => module = it,
+            None => {
+                missing = Some(name);
+                break;
+            }
+        }
+    }
+    let missing = missing?;
+    let features = gating_features(ctx, module, &missing.text())?;
+
+    let noun = if features.len() == 1 { "feature" } else { "features" };
+    let mut message = format!(
+        "`{}` needs {} {}",
+        missing,
+        noun,
+        features.iter().map(|it| format!("`{}`", it)).join(", ")
+    );
+    let krate = module.krate();
+    let current_crate =
+        ctx.sema.to_module_def(d.decl.file_id.original_file(db)).map(|it| it.krate());
+    if current_crate != Some(krate) {
+        if let Some(name) = krate.display_name(db) {
+            format_to!(message, " of `{}`", name);
+        }
+    }
+    message.push_str(if features.len() == 1 {
+        ", which is disabled"
+    } else {
+        ", which are disabled"
+    });
+
+    Some(Diagnostic::new(
+        "disabled-feature",
+        message,
+        ctx.sema.diagnostics_display_range(d.decl.clone().map(|it| it.into())).range,
+    ))
+}
+
+/// The segments of the path imported by `use_tree`, including the prefixes of
+/// the use trees it is nested in.
+fn use_tree_segments(use_tree: &ast::UseTree) -> Vec<ast::PathSegment> {
+    let mut trees = vec![use_tree.clone()];
+    while let Some(list) =
+        trees.last().and_then(|it| it.syntax().parent()).and_then(ast::UseTreeList::cast)
+    {
+        trees.push(list.parent_use_tree());
+    }
+    trees.iter().rev().filter_map(|it| it.path()).flat_map(|it| it.segments()).collect()
+}
+
+/// The disabled features declared by the crate of `module` which would enable
+/// an item named `name` in it.
+fn gating_features(
+    ctx: &DiagnosticsContext<'_>,
+    module: Module,
+    name: &str,
+) -> Option<Vec<SmolStr>> {
+    let db = ctx.sema.db;
+    let source = module.definition_source(db);
+    if source.file_id.is_macro() {
+        return None;
+    }
+    let file = ctx.sema.parse(source.file_id.original_file(db));
+    let items = match source.value {
+        ModuleSource::SourceFile(_) => file.items().collect::<Vec<_>>(),
+        ModuleSource::Module(it) => {
+            let it = find_node_at_range::<ast::Module>(file.syntax(), it.syntax().text_range())?;
+            it.item_list()?.items().collect()
+        }
+        ModuleSource::BlockExpr(_) => return None,
+    };
+
+    let krate = module.krate();
+    let enabled = krate.cfg(db);
+    let declared = krate.potential_cfg(db);
+    items
+        .into_iter()
+        .filter(|item| {
+            item.syntax().children().find_map(ast::Name::cast).map_or(false, |it| it.text() == name)
+        })
+        .find_map(|item| {
+            let cfg = ctx.sema.inactive_cfg(item.syntax())?;
+            missing_features(&cfg, &enabled, &declared)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::check_diagnostics_with_config, DiagnosticsConfig};
+
+    fn check_diagnostics(ra_fixture: &str) {
+        let mut config = DiagnosticsConfig::default();
+        config.disabled.insert("inactive-code".to_string());
+        check_diagnostics_with_config(config, ra_fixture)
+    }
+
+    #[test]
+    fn import_of_item_behind_disabled_feature() {
+        check_diagnostics(
+            r#"
+//- /main.rs crate:main deps:dep
+use dep::{ser::Serializer, json};
+        //^^^^^^^^^^^^^^^ error: `ser` needs feature `serde` of `dep`, which is disabled
+                         //^^^^ error: `json` needs features `json`, `serde` of `dep`, which are disabled
+//- /lib.rs crate:dep features:serde,json
+#[cfg(feature = "serde")]
+pub mod ser {
+    pub struct Serializer;
+}
+#[cfg(all(feature = "serde", feature = "json"))]
+pub mod json {}
+"#,
+        );
+    }
+
+    #[test]
+    fn import_of_item_behind_any_feature() {
+        check_diagnostics(
+            r#"
+//- /main.rs crate:main deps:dep
+use dep::{Alloc, NoStd, Vendored};
+        //^^^^^ error: `Alloc` needs feature `alloc` of `dep`, which is disabled
+               //^^^^^ error: `NoStd` needs feature `alloc` of `dep`, which is disabled
+                      //^^^^^^^^ error: `Vendored` needs feature `vendored` of `dep`, which is disabled
+//- /lib.rs crate:dep features:alloc,std,vendored
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub struct Alloc;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+pub struct NoStd;
+#[cfg(any(feature = "undeclared", all(feature = "vendored", feature = "std"), feature = "vendored"))]
+pub struct Vendored;
+"#,
+        );
+    }
+
+    #[test]
+    fn import_of_item_behind_undeclared_cfg() {
+        check_diagnostics(
+            r#"
+//- /main.rs crate:main deps:dep
+use dep::Unix;
+  //^^^^^^^^^ error: unresolved import
+use dep::Extra;
+  //^^^^^^^^^^ error: unresolved import
+//- /lib.rs crate:dep features:serde
+#[cfg(unix)]
+pub struct Unix;
+#[cfg(feature = "extra")]
+pub struct Extra;
+"#,
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Reports `pub` items no crate of the workspace uses.
+
+use hir::ModuleDef;
+use ide_db::{base_db::FileId, defs::Definition, search::FileReference};
+use syntax::{
+    ast::{self, AttrsOwner, NameOwner, VisibilityOwner},
+    AstNode, SyntaxKind,
+};
+
+use crate::{Diagnostic, DiagnosticsContext, Severity};
+
+// Diagnostic: unused-pub-item
+//
+// This diagnostic is shown for `pub` items which are not used by the crate
+// defining them nor any crate depending on it. Unlike rustc's `dead_code`
+// lint, this covers the whole workspace. Only references which resolve to the
+// item count as uses, so the names in code disabled by a `#[cfg]` don't.
+//
+// Searching all dependent crates for each item is slow, so the diagnostic has
+// to be enabled with `rust-analyzer.diagnostics.unusedPubItems.enable`.
+pub(crate) fn unused_pub_items(
+    ctx: &DiagnosticsContext<'_>,
+    acc: &mut Vec<Diagnostic>,
+    file_id: FileId,
+) {
+    let source_file = ctx.sema.parse(file_id);
+    let items = source_file.syntax().descendants().filter_map(ast::Item::cast).filter(|item| {
+        let parent = item.syntax().parent().map(|it| it.kind());
+        matches!(parent, Some(SyntaxKind::SOURCE_FILE | SyntaxKind::ITEM_LIST))
+    });
+    for item in items {
+        if let Some(d) = unused_pub_item(ctx, &item) {
+            acc.push(d);
+        }
+    }
+}
+
+fn unused_pub_item(ctx: &DiagnosticsContext<'_>, item: &ast::Item) -> Option<Diagnostic> {
+    let sema = &ctx.sema;
+    let (def, name): (ModuleDef, _) = match item {
+        ast::Item::Fn(it) if is_pub(it) && !is_entry_point(it) => {
+            (sema.to_def(it)?.into(), it.name()?)
+        }
+        ast::Item::Struct(it) if is_pub(it) => {
+            (hir::Adt::from(sema.to_def(it)?).into(), it.name()?)
+        }
+        ast::Item::Enum(it) if is_pub(it) => (hir::Adt::from(sema.to_def(it)?).into(), it.name()?),
+        ast::Item::Union(it) if is_pub(it) => (hir::Adt::from(sema.to_def(it)?).into(), it.name()?),
+        ast::Item::Const(it) if is_pub(it) => (sema.to_def(it)?.into(), it.name()?),
+        ast::Item::Static(it) if is_pub(it) => (sema.to_def(it)?.into(), it.name()?),
+        ast::Item::Trait(it) if is_pub(it) => (sema.to_def(it)?.into(), it.name()?),
+        ast::Item::TypeAlias(it) if is_pub(it) => (sema.to_def(it)?.into(), it.name()?),
+        _ => return None,
+    };
+
+    let usages = Definition::ModuleDef(def).usages(sema).all();
+    let item_range = sema.original_range(item.syntax());
+    let is_used = usages
+        .iter()
+        .flat_map(|(&file_id, refs)| refs.iter().map(move |it| (file_id, it)))
+        .any(|(file_id, reference)| {
+            !(file_id == item_range.file_id && item_range.range.contains_range(reference.range))
+                && !is_impl_self_ty(reference)
+        });
+    if is_used {
+        return None;
+    }
+
+    let range = sema.original_range(name.syntax()).range;
+    let d = Diagnostic::new(
+        "unused-pub-item",
+        format!("`{}` is not used by any crate of the workspace", name),
+        range,
+    )
+    .with_unused(true)
+    .severity(Severity::WeakWarning);
+    Some(d)
+}
+
+/// Whether the item is visible outside of its crate, `pub(crate)` items are
+/// covered by rustc.
+fn is_pub(item: &dyn VisibilityOwner) -> bool {
+    item.visibility().map_or(false, |vis| vis.syntax().text() == "pub")
+}
+
+/// Functions which are called without being referenced in Rust code.
+fn is_entry_point(fn_: &ast::Fn) -> bool {
+    fn_.name().map_or(false, |name| name.text() == "main")
+        || fn_.attrs().filter_map(|attr| attr.simple_name()).any(|name| {
+            matches!(
+                name.as_str(),
+                "test"
+                    | "bench"
+                    | "no_mangle"
+                    | "export_name"
+                    | "proc_macro"
+                    | "proc_macro_derive"
+                    | "proc_macro_attribute"
+            )
+        })
+}
+
+/// `impl Foo {}` alone doesn't use `Foo`.
+fn is_impl_self_ty(reference: &FileReference) -> bool {
+    let name_ref = match &reference.name {
+        ast::NameLike::NameRef(it) => it,
+        _ => return false,
+    };
+    let path_ty = match name_ref.syntax().ancestors().find_map(ast::PathType::cast) {
+        Some(it) => it,
+        None => return false,
+    };
+    path_ty
+        .syntax()
+        .parent()
+        .and_then(ast::Impl::cast)
+        .and_then(|impl_| impl_.self_ty())
+        .map_or(false, |self_ty| self_ty.syntax() == path_ty.syntax())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::check_diagnostics_with_config, DiagnosticsConfig};
+
+    fn check(ra_fixture: &str) {
+        let mut config = DiagnosticsConfig::default();
+        config.unused_pub_items = true;
+        config.disabled.insert("inactive-code".to_string());
+        check_diagnostics_with_config(config, ra_fixture)
+    }
+
+    #[test]
+    fn reports_pub_items_unused_in_the_workspace() {
+        check(
+            r#"
+//- /main.rs crate:main deps:lib
+fn main() {
+    lib::used();
+    let _: lib::Used;
+}
+//- /lib.rs crate:lib
+pub fn used() {}
+pub struct Used;
+impl Used {}
+pub fn unused() { unused() }
+     //^^^^^^ weak: `unused` is not used by any crate of the workspace
+pub struct Unused;
+         //^^^^^^ weak: `Unused` is not used by any crate of the workspace
+impl Unused {}
+pub(crate) fn crate_private() {}
+fn private() {}
+"#,
+        );
+    }
+
+    #[test]
+    fn ignores_names_in_inactive_code() {
+        check(
+            r#"
+//- /main.rs crate:main deps:lib
+#[cfg(feature = "serde")]
+fn f() {
+    lib::only_with_serde();
+}
+fn main() {}
+//- /lib.rs crate:lib
+pub fn only_with_serde() {}
+     //^^^^^^^^^^^^^^^ weak: `only_with_serde` is not used by any crate of the workspace
+"#,
+        );
+    }
+}
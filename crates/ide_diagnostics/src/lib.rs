@@ -25,6 +25,7 @@
 
 mod handlers {
     pub(crate) mod break_outside_of_loop;
+    pub(crate) mod disabled_feature;
     pub(crate) mod inactive_code;
     pub(crate) mod incorrect_case;
     pub(crate) mod macro_error;
@@ -47,6 +48,7 @@ mod handlers {
     pub(crate) mod field_shorthand;
     pub(crate) mod useless_braces;
     pub(crate) mod unlinked_file;
+    pub(crate) mod unused_pub_item;
 }
 
 #[cfg(test)]
@@ -130,6 +132,8 @@ pub enum Severity {
 pub struct DiagnosticsConfig {
     pub disable_experimental: bool,
     pub disabled: FxHashSet<String>,
+    /// Search the workspace for users of each `pub` item, see `unused-pub-item`.
+    pub unused_pub_items: bool,
 }
 
 struct DiagnosticsContext<'a> {
@@ -167,6 +171,12 @@ pub fn diagnostics(
     if module.is_none() {
         handlers::unlinked_file::unlinked_file(&ctx, &mut res, file_id);
     }
+    if module.is_some()
+        && ctx.config.unused_pub_items
+        && !ctx.config.disabled.contains("unused-pub-item")
+    {
+        handlers::unused_pub_item::unused_pub_items(&ctx, &mut res, file_id);
+    }
 
     let mut diags = Vec::new();
     if let Some(m) = module {
@@ -189,7 +199,10 @@ pub fn diagnostics(
             AnyDiagnostic::ReplaceFilterMapNextWithFindMap(d) => handlers::replace_filter_map_next_with_find_map::replace_filter_map_next_with_find_map(&ctx, &d),
             AnyDiagnostic::UnimplementedBuiltinMacro(d) => handlers::unimplemented_builtin_macro::unimplemented_builtin_macro(&ctx, &d),
             AnyDiagnostic::UnresolvedExternCrate(d) => handlers::unresolved_extern_crate::unresolved_extern_crate(&ctx, &d),
-            AnyDiagnostic::UnresolvedImport(d) => handlers::unresolved_import::unresolved_import(&ctx, &d),
+            AnyDiagnostic::UnresolvedImport(d) => match handlers::disabled_feature::disabled_feature(&ctx, &d) {
+                Some(it) => it,
+                None => handlers::unresolved_import::unresolved_import(&ctx, &d),
+            },
             AnyDiagnostic::UnresolvedMacroCall(d) => handlers::unresolved_macro_call::unresolved_macro_call(&ctx, &d),
             AnyDiagnostic::UnresolvedModule(d) => handlers::unresolved_module::unresolved_module(&ctx, &d),
             AnyDiagnostic::UnresolvedProcMacro(d) => handlers::unresolved_proc_macro::unresolved_proc_macro(&ctx, &d),
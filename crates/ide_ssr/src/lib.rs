@@ -37,9 +37,14 @@
 // | Constraint    | Restricts placeholder
 //
 // | kind(literal) | Is a literal (e.g. `42` or `"forty two"`)
+// | type(path)    | Has the struct, enum or union at `path` as its type, ignoring references (e.g. `type(Result)`)
+// | impl(path)    | Has a type implementing the trait at `path`, which takes no generic arguments (e.g. `impl(Iterator)`)
 // | not(a)        | Negates the constraint `a`
 // |===
 //
+// For example, `${r:type(Result)}.unwrap() ==>> $r.expect("todo")` only rewrites calls of
+// `Result::unwrap`, leaving those of `Option::unwrap` alone.
+//
 // Available via the command `rust-analyzer.ssr`.
 //
 // ```rust
@@ -343,6 +348,19 @@ impl Match {
     pub fn matched_text(&self) -> String {
         self.matched_node.text().to_string()
     }
+
+    pub fn range(&self) -> FileRange {
+        self.range
+    }
+
+    /// Returns the name of each placeholder in the pattern, without the `$`,
+    /// together with the code it was bound to, sorted by name.
+    pub fn placeholder_ranges(&self) -> Vec<(&str, FileRange)> {
+        let mut res: Vec<_> =
+            self.placeholder_values.iter().map(|(var, it)| (var.0.as_str(), it.range)).collect();
+        res.sort_by_key(|&(name, _)| name);
+        res
+    }
 }
 
 impl std::error::Error for SsrError {}
@@ -324,6 +324,24 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
             Constraint::Kind(kind) => {
                 kind.matches(code)?;
             }
+            Constraint::Type(path) => {
+                let adt = match self.rule.constraint_paths.get(path) {
+                    Some(hir::ModuleDef::Adt(adt)) => *adt,
+                    _ => fail_match!("Unresolved type constraint `{}`", path),
+                };
+                if self.type_of(code)?.strip_references().as_adt() != Some(adt) {
+                    fail_match!("Code '{}' isn't of type `{}`", code.text(), path);
+                }
+            }
+            Constraint::Impl(path) => {
+                let trait_ = match self.rule.constraint_paths.get(path) {
+                    Some(hir::ModuleDef::Trait(trait_)) => *trait_,
+                    _ => fail_match!("Unresolved trait constraint `{}`", path),
+                };
+                if !self.type_of(code)?.impls_trait(self.sema.db, trait_, &[]) {
+                    fail_match!("Type of code '{}' doesn't implement `{}`", code.text(), path);
+                }
+            }
             Constraint::Not(sub) => {
                 if self.check_constraint(&*sub, code).is_ok() {
                     fail_match!("Constraint {:?} failed for '{}'", constraint, code.text());
@@ -333,6 +351,20 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         Ok(())
     }
 
+    /// The type of `code`, which may be an expression, a pattern or a type.
+    fn type_of(&self, code: &SyntaxNode) -> Result<hir::Type, MatchFailed> {
+        let ty = if let Some(expr) = ast::Expr::cast(code.clone()) {
+            self.sema.type_of_expr(&expr)
+        } else if let Some(pat) = ast::Pat::cast(code.clone()) {
+            self.sema.type_of_pat(&pat)
+        } else if let Some(ty) = ast::Type::cast(code.clone()) {
+            self.sema.resolve_type(&ty)
+        } else {
+            None
+        };
+        ty.ok_or_else(|| match_error!("Couldn't determine the type of '{}'", code.text()))
+    }
+
     /// Paths are matched based on whether they refer to the same thing, even if they're written
     /// differently.
     fn attempt_match_path(
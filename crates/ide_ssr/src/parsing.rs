@@ -46,6 +46,10 @@ pub(crate) struct Var(pub(crate) String);
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) enum Constraint {
     Kind(NodeKind),
+    /// The matched code has the struct, enum or union at this path as its type.
+    Type(SmolStr),
+    /// The type of the matched code implements the trait at this path.
+    Impl(SmolStr),
     Not(Box<Constraint>),
 }
 
@@ -327,6 +331,14 @@ fn parse_constraint(tokens: &mut std::vec::IntoIter<Token>) -> Result<Constraint
             expect_token(tokens, ")")?;
             Ok(Constraint::Kind(NodeKind::from(&t.text)?))
         }
+        "type" => {
+            expect_token(tokens, "(")?;
+            Ok(Constraint::Type(parse_constraint_path(tokens)?))
+        }
+        "impl" => {
+            expect_token(tokens, "(")?;
+            Ok(Constraint::Impl(parse_constraint_path(tokens)?))
+        }
         "not" => {
             expect_token(tokens, "(")?;
             let sub = parse_constraint(tokens)?;
@@ -337,6 +349,27 @@ fn parse_constraint(tokens: &mut std::vec::IntoIter<Token>) -> Result<Constraint
     }
 }
 
+/// Parses the path of a `type` or `impl` constraint, up to and including the closing parenthesis.
+/// The path is resolved together with the rest of the rule.
+fn parse_constraint_path(tokens: &mut std::vec::IntoIter<Token>) -> Result<SmolStr, SsrError> {
+    let mut path = String::new();
+    loop {
+        let t = tokens
+            .next()
+            .ok_or_else(|| SsrError::new("Unexpected end of constraint while looking for path"))?;
+        match t.kind {
+            T![')'] => break,
+            SyntaxKind::WHITESPACE => {}
+            SyntaxKind::IDENT | T![:] | T![crate] | T![self] | T![super] => path.push_str(&t.text),
+            _ => bail!("Unexpected token '{}' while parsing constraint path", t.text),
+        }
+    }
+    if path.is_empty() {
+        bail!("Expected path, found ')' while parsing constraint");
+    }
+    Ok(SmolStr::new(path))
+}
+
 fn expect_token(tokens: &mut std::vec::IntoIter<Token>, expected: &str) -> Result<(), SsrError> {
     if let Some(t) = tokens.next() {
         if t.text == expected {
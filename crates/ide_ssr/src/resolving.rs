@@ -1,9 +1,9 @@
 //! This module is responsible for resolving paths within rules.
 
-use crate::errors::error;
+use crate::errors::{bail, error};
 use crate::{parsing, SsrError};
 use ide_db::base_db::FilePosition;
-use parsing::Placeholder;
+use parsing::{Constraint, Placeholder};
 use rustc_hash::FxHashMap;
 use syntax::{ast, SmolStr, SyntaxKind, SyntaxNode, SyntaxToken};
 
@@ -16,6 +16,8 @@ pub(crate) struct ResolvedRule {
     pub(crate) pattern: ResolvedPattern,
     pub(crate) template: Option<ResolvedPattern>,
     pub(crate) index: usize,
+    /// What the paths in `type` and `impl` constraints of placeholders resolve to.
+    pub(crate) constraint_paths: FxHashMap<SmolStr, hir::ModuleDef>,
 }
 
 pub(crate) struct ResolvedPattern {
@@ -56,6 +58,7 @@ impl ResolvedRule {
             pattern: resolver.resolve_pattern_tree(rule.pattern)?,
             template: resolved_template,
             index,
+            constraint_paths: resolver.resolve_constraint_paths()?,
         })
     }
 
@@ -111,6 +114,44 @@ impl Resolver<'_, '_> {
         })
     }
 
+    fn resolve_constraint_paths(&self) -> Result<FxHashMap<SmolStr, hir::ModuleDef>, SsrError> {
+        let mut res = FxHashMap::default();
+        for placeholder in self.placeholders_by_stand_in.values() {
+            for constraint in &placeholder.constraints {
+                self.resolve_constraint_path(constraint, &mut res)?;
+            }
+        }
+        Ok(res)
+    }
+
+    fn resolve_constraint_path(
+        &self,
+        constraint: &Constraint,
+        resolved: &mut FxHashMap<SmolStr, hir::ModuleDef>,
+    ) -> Result<(), SsrError> {
+        let (path_text, wants_trait) = match constraint {
+            Constraint::Kind(_) => return Ok(()),
+            Constraint::Type(path) => (path, false),
+            Constraint::Impl(path) => (path, true),
+            Constraint::Not(sub) => return self.resolve_constraint_path(sub, resolved),
+        };
+        let resolution = ast::Path::parse(path_text)
+            .ok()
+            .and_then(|path| self.resolution_scope.resolve_path(&path))
+            .ok_or_else(|| error!("Failed to resolve path `{}` in constraint", path_text))?;
+        match resolution {
+            hir::PathResolution::Def(def @ hir::ModuleDef::Trait(_)) if wants_trait => {
+                resolved.insert(path_text.clone(), def);
+            }
+            hir::PathResolution::Def(def @ hir::ModuleDef::Adt(_)) if !wants_trait => {
+                resolved.insert(path_text.clone(), def);
+            }
+            _ if wants_trait => bail!("`{}` is not a trait", path_text),
+            _ => bail!("`{}` is not a struct, enum or union", path_text),
+        }
+        Ok(())
+    }
+
     fn resolve(
         &self,
         node: SyntaxNode,
@@ -1,4 +1,4 @@
-use crate::{MatchFinder, SsrRule};
+use crate::{MatchFinder, SsrError, SsrRule};
 use expect_test::{expect, Expect};
 use ide_db::base_db::{salsa::Durability, FileId, FilePosition, FileRange, SourceDatabaseExt};
 use rustc_hash::FxHashSet;
@@ -524,6 +524,53 @@ fn literal_constraint() {
     assert_matches("Some(${a:not(kind(literal))})", code, &["Some(x1)", "Some(40 + 2)"]);
 }
 
+#[test]
+fn type_constraint() {
+    let code = r#"
+        enum Option<T> { Some(T), None }
+        enum Result<T, E> { Ok(T), Err(E) }
+        impl<T> Option<T> { fn unwrap(self) -> T { loop {} } }
+        impl<T, E> Result<T, E> { fn unwrap(self) -> T { loop {} } }
+        fn f1(a: Option<i32>, b: Result<i32, ()>, c: &Result<i32, ()>) {
+            a.unwrap();
+            b.unwrap();
+            c.unwrap();
+        }
+        "#;
+    assert_matches("${r:type(Result)}.unwrap()", code, &["b.unwrap()", "c.unwrap()"]);
+    assert_matches("${r:not(type(Result))}.unwrap()", code, &["a.unwrap()"]);
+}
+
+#[test]
+fn impl_constraint() {
+    let code = r#"
+        trait Shape {}
+        struct Circle;
+        struct Line;
+        impl Shape for Circle {}
+        fn draw<T>(t: T) {}
+        fn f1() {
+            draw(Circle);
+            draw(Line);
+        }
+        "#;
+    assert_matches("draw(${s:impl(Shape)})", code, &["draw(Circle)"]);
+}
+
+#[test]
+fn type_constraint_with_unresolved_path() {
+    let (db, position, selections) = single_file("fn f() {}");
+    let mut match_finder = MatchFinder::in_context(&db, position, selections);
+    assert_eq!(
+        match_finder.add_search_pattern("${a:type(Missing)}".parse().unwrap()),
+        Err(SsrError::new("Failed to resolve path `Missing` in constraint"))
+    );
+    assert_eq!(
+        match_finder.add_search_pattern("${a:impl(f)}".parse().unwrap()),
+        Err(SsrError::new("`f` is not a trait"))
+    );
+}
+
 #[test]
 fn match_reordered_struct_instantiation() {
     assert_matches(
@@ -1369,3 +1416,21 @@ fn replace_autoref_mut() {
         "#]],
     );
 }
+
+#[test]
+fn placeholder_ranges() {
+    let (db, position, selections) =
+        single_file("fn f(a: i32, b: i32) {} fn main() { f(1 + 2, 3); }");
+    let mut match_finder = MatchFinder::in_context(&db, position, selections);
+    match_finder.add_search_pattern("f($b, $a)".parse().unwrap()).unwrap();
+    let matches = match_finder.matches().flattened().matches;
+    assert_eq!(matches.len(), 1);
+    let text = db.file_text(position.file_id);
+    let placeholders: Vec<(&str, &str)> = matches[0]
+        .placeholder_ranges()
+        .into_iter()
+        .map(|(name, range)| (name, &text[range.range]))
+        .collect();
+    assert_eq!(placeholders, vec![("a", "3"), ("b", "1 + 2")]);
+    assert_eq!(&text[matches[0].range().range], "f(1 + 2, 3)");
+}
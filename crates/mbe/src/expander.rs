@@ -14,8 +14,21 @@ pub(crate) fn expand_rules(
     rules: &[crate::Rule],
     input: &tt::Subtree,
 ) -> ExpandResult<tt::Subtree> {
-    let mut match_: Option<(matcher::Match, &crate::Rule)> = None;
-    for rule in rules {
+    expand_rules_with_index(rules, input).0
+}
+
+/// The index of the rule `input` is expanded with, if any matches at least
+/// partially.
+pub(crate) fn matched_rule(rules: &[crate::Rule], input: &tt::Subtree) -> Option<usize> {
+    expand_rules_with_index(rules, input).1
+}
+
+fn expand_rules_with_index(
+    rules: &[crate::Rule],
+    input: &tt::Subtree,
+) -> (ExpandResult<tt::Subtree>, Option<usize>) {
+    let mut match_: Option<(matcher::Match, usize)> = None;
+    for (idx, rule) in rules.iter().enumerate() {
         let new_match = matcher::match_(&rule.lhs, input);
 
         if new_match.err.is_none() {
@@ -25,7 +38,7 @@ pub(crate) fn expand_rules(
             let ExpandResult { value, err: transcribe_err } =
                 transcriber::transcribe(&rule.rhs, &new_match.bindings);
             if transcribe_err.is_none() {
-                return ExpandResult::ok(value);
+                return (ExpandResult::ok(value), Some(idx));
             }
         }
         // Use the rule if we matched more tokens, or bound variables count
@@ -33,19 +46,19 @@ pub(crate) fn expand_rules(
             if (new_match.unmatched_tts, -(new_match.bound_count as i32))
                 < (prev_match.unmatched_tts, -(prev_match.bound_count as i32))
             {
-                match_ = Some((new_match, rule));
+                match_ = Some((new_match, idx));
             }
         } else {
-            match_ = Some((new_match, rule));
+            match_ = Some((new_match, idx));
         }
     }
-    if let Some((match_, rule)) = match_ {
+    if let Some((match_, idx)) = match_ {
         // if we got here, there was no match without errors
         let ExpandResult { value, err: transcribe_err } =
-            transcriber::transcribe(&rule.rhs, &match_.bindings);
-        ExpandResult { value, err: match_.err.or(transcribe_err) }
+            transcriber::transcribe(&rules[idx].rhs, &match_.bindings);
+        (ExpandResult { value, err: match_.err.or(transcribe_err) }, Some(idx))
     } else {
-        ExpandResult::only_err(ExpandError::NoMatchingRule)
+        (ExpandResult::only_err(ExpandError::NoMatchingRule), None)
     }
 }
 
@@ -202,6 +202,13 @@ impl MacroRules {
         expander::expand_rules(&self.rules, &tt)
     }
 
+    /// The index of the rule a call with the argument `tt` expands with.
+    pub fn matched_rule(&self, tt: &tt::Subtree) -> Option<usize> {
+        let mut tt = tt.clone();
+        self.shift.shift_all(&mut tt);
+        expander::matched_rule(&self.rules, &tt)
+    }
+
     pub fn map_id_down(&self, id: tt::TokenId) -> tt::TokenId {
         self.shift.shift(id)
     }
@@ -255,6 +262,13 @@ impl MacroDef {
         expander::expand_rules(&self.rules, &tt)
     }
 
+    /// The index of the rule a call with the argument `tt` expands with.
+    pub fn matched_rule(&self, tt: &tt::Subtree) -> Option<usize> {
+        let mut tt = tt.clone();
+        self.shift.shift_all(&mut tt);
+        expander::matched_rule(&self.rules, &tt)
+    }
+
     pub fn map_id_down(&self, id: tt::TokenId) -> tt::TokenId {
         self.shift.shift(id)
     }
@@ -34,6 +34,15 @@ macro_rules! impl_fixture {
                 self.rules.expand(&invocation_tt).result()
             }
 
+            #[allow(unused)]
+            fn matched_rule(&self, invocation: &str) -> Option<usize> {
+                let source_file = ast::SourceFile::parse(invocation).tree();
+                let macro_invocation =
+                    source_file.syntax().descendants().find_map(ast::MacroCall::cast).unwrap();
+                let (invocation_tt, _) = ast_to_token_tree(&macro_invocation.token_tree().unwrap());
+                self.rules.matched_rule(&invocation_tt)
+            }
+
             #[allow(unused)]
             fn assert_expand_err(&self, invocation: &str, err: &ExpandError) {
                 assert_eq!(self.try_expand_tt(invocation).as_ref(), Err(err));
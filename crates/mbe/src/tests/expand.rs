@@ -1830,6 +1830,26 @@ fn test_repeat_bad_var() {
     .assert_expand_items("foo!(b0 b1);", "b0 b1");
 }
 
+#[test]
+fn test_matched_rule() {
+    let fixture = parse_macro(
+        r#"
+        macro_rules! foo {
+            () => {};
+            ($a:ident) => { $a };
+            ($a:ident, $b:expr) => { $a + $b };
+            ($( $b:ident )+) => { $( $c )+ };
+            ($( $b:ident )+) => { $( $b )+ };
+        }
+    "#,
+    );
+    assert_eq!(fixture.matched_rule("foo!();"), Some(0));
+    assert_eq!(fixture.matched_rule("foo!(a);"), Some(1));
+    assert_eq!(fixture.matched_rule("foo!(a, 1 + 1);"), Some(2));
+    // The fourth rule fails to transcribe.
+    assert_eq!(fixture.matched_rule("foo!(a b);"), Some(4));
+}
+
 #[test]
 fn test_no_space_after_semi_colon() {
     let expanded = parse_macro(
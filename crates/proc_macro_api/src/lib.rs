@@ -88,35 +88,60 @@ impl ProcMacroClient {
 
     pub fn by_dylib_path(&self, dylib_path: &AbsPath) -> Vec<ProcMacro> {
         let _p = profile::span("ProcMacroClient::by_dylib_path");
-        match version::read_dylib_info(dylib_path) {
+        let info = match version::read_dylib_info(dylib_path) {
             Ok(info) => {
                 if info.version.0 < 1 || info.version.1 < 47 {
-                    eprintln!("proc-macro {} built by {:#?} is not supported by Rust Analyzer, please update your rust version.", dylib_path.display(), info);
+                    log::error!(
+                        "proc-macro {} built by {:?} is not supported by rust-analyzer, please update your rust version",
+                        dylib_path.display(),
+                        info
+                    );
+                    return vec![];
                 }
+                Some(info)
             }
             Err(err) => {
-                eprintln!(
+                log::warn!(
                     "proc-macro {} failed to find the given version. Reason: {}",
                     dylib_path.display(),
                     err
                 );
+                None
             }
-        }
+        };
 
-        let macros = match self
+        let result = match self
             .process
             .lock()
             .unwrap_or_else(|e| e.into_inner())
             .find_proc_macros(dylib_path)
         {
             Err(err) => {
-                eprintln!("Failed to find proc macros. Error: {:#?}", err);
+                log::error!("Failed to find proc macros in {}: {}", dylib_path.display(), err);
                 return vec![];
             }
-            Ok(macros) => macros,
+            Ok(result) => result,
         };
 
-        macros
+        if let (Some(info), Some(abi)) = (&info, &result.abi) {
+            let abi_minor = abi.split('.').nth(1).and_then(|it| it.parse::<usize>().ok());
+            match abi_minor {
+                // Nightlies of the next release often still use the newest ABI.
+                Some(abi_minor) if info.version.1 > abi_minor + 1 => log::warn!(
+                    "proc-macro {} was built by rustc {}.{}.{}, which is newer than the {} ABI \
+                     rust-analyzer supports, expansion may fail",
+                    dylib_path.display(),
+                    info.version.0,
+                    info.version.1,
+                    info.version.2,
+                    abi
+                ),
+                _ => log::info!("proc-macro {} uses the {} ABI", dylib_path.display(), abi),
+            }
+        }
+
+        result
+            .macros
             .into_iter()
             .map(|(name, kind)| {
                 let name = SmolStr::new(&name);
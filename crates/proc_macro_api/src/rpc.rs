@@ -28,6 +28,10 @@ pub enum ProcMacroKind {
 #[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct ListMacrosResult {
     pub macros: Vec<(String, ProcMacroKind)>,
+    /// The version of the `proc_macro` ABI the server picked for the dylib,
+    /// like `1.55`. Not sent by older servers.
+    #[serde(default)]
+    pub abi: Option<String>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -19,8 +19,8 @@
 //! Then you'll need to add a branch to the `Abi` enum and an implementation of
 //! `Abi::expand`, `Abi::list_macros` and `Abi::from_lib` for the new ABI. See
 //! `proc_macro_srv/src/abis/abi_1_47/mod.rs` for an example. Finally you'll
-//! need to update the conditionals in `Abi::from_lib` to return your new ABI
-//! for the relevant versions of the rust compiler
+//! need to add a variant to `AbiVersion` and an entry to `ABIS` with the first
+//! version of the rust compiler using the new ABI.
 //!
 
 // pub(crate) so tests can use the TokenStream, more notes in test/utils.rs
@@ -48,6 +48,40 @@ pub(crate) enum Abi {
     Abi1_55(Abi_1_55),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AbiVersion {
+    V1_47,
+    V1_55,
+}
+
+/// The first compiler version using each ABI, newest first. The bridge
+/// changes on nightly, so the first nightly with a new ABI usually belongs to
+/// the stable release before the one the ABI is named after.
+const ABIS: &[((usize, usize), AbiVersion)] =
+    &[((1, 54), AbiVersion::V1_55), ((1, 47), AbiVersion::V1_47)];
+
+impl AbiVersion {
+    /// Picks the ABI of the compiler which built a dylib. Dylibs built by
+    /// compilers newer than all known ABIs get the newest one, which keeps
+    /// working as long as the bridge didn't change.
+    pub(crate) fn for_rustc(info: &RustCInfo) -> Result<AbiVersion, LoadProcMacroDylibError> {
+        let (major, minor, patch) = info.version;
+        ABIS.iter()
+            .find(|&&(first, _)| major == first.0 && minor >= first.1)
+            .map(|&(_, abi)| abi)
+            .ok_or_else(|| {
+                LoadProcMacroDylibError::UnsupportedABI(format!("{}.{}.{}", major, minor, patch))
+            })
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            AbiVersion::V1_47 => "1.47",
+            AbiVersion::V1_55 => "1.55",
+        }
+    }
+}
+
 impl Abi {
     /// Load a new ABI.
     ///
@@ -63,16 +97,15 @@ impl Abi {
         symbol_name: String,
         info: RustCInfo,
     ) -> Result<Abi, LoadProcMacroDylibError> {
-        if info.version.0 != 1 {
-            Err(LoadProcMacroDylibError::UnsupportedABI)
-        } else if info.version.1 < 47 {
-            Err(LoadProcMacroDylibError::UnsupportedABI)
-        } else if info.version.1 < 54 {
-            let inner = unsafe { Abi_1_47::from_lib(lib, symbol_name) }?;
-            Ok(Abi::Abi1_47(inner))
-        } else {
-            let inner = unsafe { Abi_1_55::from_lib(lib, symbol_name) }?;
-            Ok(Abi::Abi1_55(inner))
+        match AbiVersion::for_rustc(&info)? {
+            AbiVersion::V1_47 => {
+                let inner = unsafe { Abi_1_47::from_lib(lib, symbol_name) }?;
+                Ok(Abi::Abi1_47(inner))
+            }
+            AbiVersion::V1_55 => {
+                let inner = unsafe { Abi_1_55::from_lib(lib, symbol_name) }?;
+                Ok(Abi::Abi1_55(inner))
+            }
         }
     }
 
@@ -94,4 +127,12 @@ impl Abi {
             Self::Abi1_55(abi) => abi.list_macros(),
         }
     }
+
+    /// The rustc version whose `proc_macro` crate this ABI was copied from.
+    pub fn version(&self) -> &'static str {
+        match self {
+            Self::Abi1_47(_) => AbiVersion::V1_47.as_str(),
+            Self::Abi1_55(_) => AbiVersion::V1_55.as_str(),
+        }
+    }
 }
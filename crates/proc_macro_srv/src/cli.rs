@@ -1,8 +1,12 @@
 //! Driver for proc macro server
 
-use crate::ProcMacroSrv;
-use proc_macro_api::msg::{self, Message};
-use std::io;
+use crate::{dylib, ProcMacroSrv};
+use paths::AbsPath;
+use proc_macro_api::{
+    msg::{self, Message},
+    read_dylib_info, ProcMacroKind,
+};
+use std::{io, path::Path};
 
 pub fn run() -> io::Result<()> {
     let mut srv = ProcMacroSrv::default();
@@ -31,6 +35,90 @@ pub fn run() -> io::Result<()> {
     Ok(())
 }
 
+/// Loads the proc macro dylib at `path` the same way the server would, prints
+/// the rustc version it was compiled with, the ABI used to talk to it and its
+/// macros, and expands every macro once with a trivial input.
+///
+/// Fails if the dylib can't be loaded or if any expansion fails.
+pub fn probe(path: &Path) -> io::Result<()> {
+    let (report, ok) = probe_report(path);
+    print!("{}", report);
+    if ok {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, "proc macro dylib is not usable"))
+    }
+}
+
+pub(crate) fn probe_report(path: &Path) -> (String, bool) {
+    let mut lines = Vec::new();
+    let ok = probe_into(path, &mut lines);
+    lines.push(String::new());
+    (lines.join("\n"), ok)
+}
+
+fn probe_into(path: &Path, lines: &mut Vec<String>) -> bool {
+    let path = match path.canonicalize() {
+        Ok(it) => it,
+        Err(err) => {
+            lines.push(format!("cannot access {}: {}", path.display(), err));
+            return false;
+        }
+    };
+    lines.push(format!("dylib: {}", path.display()));
+
+    match read_dylib_info(AbsPath::assert(&path)) {
+        Ok(info) => {
+            let (major, minor, patch) = info.version;
+            let channel =
+                if info.channel.is_empty() { String::new() } else { format!("-{}", info.channel) };
+            lines.push(format!(
+                "compiled with: rustc {}.{}.{}{} ({} {})",
+                major, minor, patch, channel, info.commit, info.date
+            ));
+        }
+        Err(err) => {
+            lines.push(format!("cannot read rustc version: {}", err));
+            return false;
+        }
+    }
+
+    let expander = match dylib::Expander::new(&path) {
+        Ok(it) => it,
+        Err(err) => {
+            lines.push(format!("cannot load dylib: {}", err));
+            return false;
+        }
+    };
+    lines.push(format!("ABI: {}", expander.abi_version()));
+
+    let macros = expander.list_macros();
+    lines.push(format!("macros: {}", macros.len()));
+    let mut ok = true;
+    for (name, kind) in macros {
+        let (input, attr) = match kind {
+            ProcMacroKind::CustomDerive => ("struct S;", None),
+            ProcMacroKind::Attr => ("fn f() {}", Some("")),
+            ProcMacroKind::FuncLike => ("", None),
+        };
+        let input = token_tree(input);
+        let attr = attr.map(token_tree);
+        let res = match expander.expand(&name, &input, attr.as_ref()) {
+            Ok(expansion) => format!("ok, {} token trees", expansion.token_trees.len()),
+            Err(err) => {
+                ok = false;
+                format!("failed: {}", err)
+            }
+        };
+        lines.push(format!("  {} [{:?}]: {}", name, kind, res));
+    }
+    ok
+}
+
+fn token_tree(text: &str) -> tt::Subtree {
+    mbe::parse_to_token_tree(text).map(|(subtree, _)| subtree).unwrap_or_default()
+}
+
 fn read_request(buf: &mut String) -> io::Result<Option<msg::Request>> {
     msg::Request::read(&mut io::stdin().lock(), buf)
 }
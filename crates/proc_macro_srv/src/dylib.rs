@@ -81,14 +81,19 @@ fn load_library(file: &Path) -> Result<Library, libloading::Error> {
 pub enum LoadProcMacroDylibError {
     Io(io::Error),
     LibLoading(libloading::Error),
-    UnsupportedABI,
+    /// Built by the given rustc version, older than all supported ABIs.
+    UnsupportedABI(String),
 }
 
 impl fmt::Display for LoadProcMacroDylibError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Io(e) => e.fmt(f),
-            Self::UnsupportedABI => write!(f, "unsupported ABI version"),
+            Self::UnsupportedABI(version) => write!(
+                f,
+                "unsupported ABI of rustc {}, proc macros built by rustc 1.47 or newer are supported",
+                version
+            ),
             Self::LibLoading(e) => e.fmt(f),
         }
     }
@@ -159,6 +164,10 @@ impl Expander {
     pub fn list_macros(&self) -> Vec<(String, ProcMacroKind)> {
         self.inner.abi.list_macros()
     }
+
+    pub fn abi_version(&self) -> &'static str {
+        self.inner.abi.version()
+    }
 }
 
 /// Copy the dylib to temp directory to prevent locking in Windows
@@ -55,7 +55,10 @@ impl ProcMacroSrv {
 
     pub fn list_macros(&mut self, task: &ListMacrosTask) -> Result<ListMacrosResult, String> {
         let expander = self.expander(task.lib.as_ref())?;
-        Ok(ListMacrosResult { macros: expander.list_macros() })
+        Ok(ListMacrosResult {
+            macros: expander.list_macros(),
+            abi: Some(expander.abi_version().to_string()),
+        })
     }
 
     fn expander(&mut self, path: &Path) -> Result<&dylib::Expander, String> {
@@ -65,7 +68,7 @@ impl ProcMacroSrv {
 
         Ok(match self.expanders.entry((path.to_path_buf(), time)) {
             Entry::Vacant(v) => v.insert(dylib::Expander::new(path).map_err(|err| {
-                format!("Cannot create expander for {}: {:?}", path.display(), err)
+                format!("Cannot create expander for {}: {}", path.display(), err)
             })?),
             Entry::Occupied(e) => e.into_mut(),
         })
@@ -100,3 +100,37 @@ fn test_version_check() {
     let info = proc_macro_api::read_dylib_info(&path).unwrap();
     assert!(info.version.1 >= 50);
 }
+
+#[test]
+fn probe_test_macros() {
+    let (report, ok) = crate::cli::probe_report(&fixtures::proc_macro_test_dylib_path());
+    assert!(!ok, "the panicking test macros should fail the smoke test");
+    assert!(report.contains("compiled with: rustc "));
+    assert!(report.contains("macros: 10"));
+    assert!(report.contains("  fn_like_noop [FuncLike]: ok"));
+    assert!(report.contains("  attr_noop [Attr]: ok"));
+    assert!(report.contains("  DeriveEmpty [CustomDerive]: ok"));
+    assert!(report.contains("  fn_like_panic [FuncLike]: failed: "));
+}
+
+#[test]
+fn test_abi_for_rustc() {
+    use crate::abis::AbiVersion;
+    use proc_macro_api::RustCInfo;
+
+    let abi = |version| {
+        let info = RustCInfo {
+            version,
+            channel: String::new(),
+            commit: String::new(),
+            date: String::new(),
+        };
+        AbiVersion::for_rustc(&info).ok()
+    };
+    assert_eq!(abi((1, 46, 0)), None);
+    assert_eq!(abi((1, 47, 0)), Some(AbiVersion::V1_47));
+    assert_eq!(abi((1, 53, 0)), Some(AbiVersion::V1_47));
+    assert_eq!(abi((1, 54, 0)), Some(AbiVersion::V1_55));
+    assert_eq!(abi((1, 56, 1)), Some(AbiVersion::V1_55));
+    assert_eq!(abi((2, 0, 0)), None);
+}
@@ -87,10 +87,18 @@ fn memusage_linux() -> MemoryUsage {
     }
 }
 
-#[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct Bytes(isize);
 
 impl Bytes {
+    pub fn new(bytes: isize) -> Bytes {
+        Bytes(bytes)
+    }
+
+    pub fn bytes(self) -> isize {
+        self.0
+    }
+
     pub fn megabytes(self) -> isize {
         self.0 / 1024 / 1024
     }
@@ -119,6 +127,12 @@ impl std::ops::AddAssign<usize> for Bytes {
     }
 }
 
+impl std::ops::AddAssign for Bytes {
+    fn add_assign(&mut self, x: Bytes) {
+        self.0 += x.0;
+    }
+}
+
 impl std::ops::Sub for Bytes {
     type Output = Bytes;
     fn sub(self, rhs: Bytes) -> Bytes {
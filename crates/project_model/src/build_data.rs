@@ -1,9 +1,19 @@
 //! Handles build script specific information
 
 use std::{
-    path::PathBuf,
+    collections::hash_map::DefaultHasher,
+    env,
+    ffi::OsString,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Result;
@@ -12,12 +22,12 @@ use cargo_metadata::camino::Utf8Path;
 use cargo_metadata::{BuildScript, Message};
 use paths::{AbsPath, AbsPathBuf};
 use rustc_hash::FxHashMap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use stdx::format_to;
 
-use crate::{cfg_flag::CfgFlag, CargoConfig};
+use crate::{cfg_flag::CfgFlag, CargoConfig, ProgressEvent};
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct PackageBuildData {
     /// List of config flags defined by this package's build script
     pub(crate) cfgs: Vec<CfgFlag>,
@@ -30,6 +40,32 @@ pub(crate) struct PackageBuildData {
     pub(crate) out_dir: Option<AbsPathBuf>,
     /// Path to the proc-macro library file if this package exposes proc-macros
     pub(crate) proc_macro_dylib_path: Option<AbsPathBuf>,
+    /// How rustc was actually invoked for the targets of the package, if
+    /// the rustc wrapper recorded it.
+    #[serde(default)]
+    pub(crate) targets: Vec<TargetBuildData>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TargetBuildData {
+    /// The root module of the target.
+    pub(crate) root: AbsPathBuf,
+    pub(crate) edition: Option<String>,
+    /// All `--cfg` flags, including features and the cfgs of build scripts.
+    pub(crate) cfgs: Vec<CfgFlag>,
+    /// The `CARGO_*` variables cargo set for this target.
+    pub(crate) envs: Vec<(String, String)>,
+    /// Whether this is the invocation for `#[cfg(test)]`.
+    pub(crate) is_test: bool,
+}
+
+/// An invocation of rustc, recorded by the rustc wrapper into the directory
+/// in [`RustcInvocation::DIR_ENV`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RustcInvocation {
+    pub cwd: PathBuf,
+    pub args: Vec<String>,
+    pub envs: Vec<(String, String)>,
 }
 
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
@@ -48,6 +84,8 @@ pub(crate) struct BuildDataConfig {
     cargo_toml: AbsPathBuf,
     cargo_features: CargoConfig,
     packages: Arc<Vec<cargo_metadata::Package>>,
+    workspace_root: AbsPathBuf,
+    target_dir: AbsPathBuf,
 }
 
 impl PartialEq for BuildDataConfig {
@@ -58,6 +96,22 @@ impl PartialEq for BuildDataConfig {
 
 impl Eq for BuildDataConfig {}
 
+const CANCELLED: &str = "cancelled, the build data is incomplete";
+
+/// Stops a running [`BuildDataCollector::collect`], killing `cargo check`.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 #[derive(Debug)]
 pub struct BuildDataCollector {
     wrap_rustc: bool,
@@ -73,16 +127,42 @@ impl BuildDataCollector {
         self.configs.insert(workspace_root.to_path_buf(), config);
     }
 
-    pub fn collect(&mut self, progress: &dyn Fn(String)) -> Result<BuildDataResult> {
+    /// Once cancelled, the data collected so far is returned, with an error
+    /// for the workspaces which are incomplete.
+    pub fn collect(
+        &mut self,
+        cancel: &CancellationToken,
+        progress: &dyn Fn(ProgressEvent),
+    ) -> Result<BuildDataResult> {
         let mut res = BuildDataResult::default();
         for (path, config) in self.configs.iter() {
-            let workspace_build_data = WorkspaceBuildData::collect(
-                &config.cargo_toml,
-                &config.cargo_features,
-                &config.packages,
-                self.wrap_rustc,
-                progress,
-            )?;
+            if cancel.is_cancelled() {
+                let build_data = WorkspaceBuildData {
+                    error: Some(CANCELLED.to_string()),
+                    ..WorkspaceBuildData::default()
+                };
+                res.per_workspace.insert(path.clone(), build_data);
+                continue;
+            }
+            let workspace_build_data = match load_cached(config) {
+                Some(it) => it,
+                None => {
+                    let build_data = WorkspaceBuildData::collect(
+                        &config.cargo_toml,
+                        &config.cargo_features,
+                        &config.packages,
+                        self.wrap_rustc.then(|| invocations_dir(config)),
+                        cancel,
+                        progress,
+                    )?;
+                    if build_data.error.is_none() {
+                        if let Err(err) = store_cached(config, &build_data) {
+                            log::warn!("failed to cache build data: {}", err);
+                        }
+                    }
+                    build_data
+                }
+            };
             res.per_workspace.insert(path.clone(), workspace_build_data);
         }
         Ok(res)
@@ -95,6 +175,65 @@ impl WorkspaceBuildData {
     }
 }
 
+impl PackageBuildData {
+    /// The recorded invocation for the target with the given root module,
+    /// preferring the one not compiling tests.
+    pub(crate) fn target(&self, root: &AbsPath) -> Option<&TargetBuildData> {
+        let mut targets = self.targets.iter().filter(|it| &*it.root == root);
+        let first = targets.next()?;
+        Some(if first.is_test { targets.find(|it| !it.is_test).unwrap_or(first) } else { first })
+    }
+}
+
+impl RustcInvocation {
+    pub const DIR_ENV: &'static str = "RA_RUSTC_WRAPPER_INVOCATIONS";
+
+    pub fn current(args: &[OsString]) -> io::Result<RustcInvocation> {
+        let args = args.iter().map(|it| it.to_string_lossy().into_owned()).collect();
+        // Variables set by build scripts are known from their output already.
+        let envs =
+            env::vars().filter(|(key, _)| key.starts_with("CARGO_") || key == "OUT_DIR").collect();
+        Ok(RustcInvocation { cwd: env::current_dir()?, args, envs })
+    }
+
+    /// Writes the invocation to its own file in `dir`, as rustc is run for
+    /// several crates in parallel.
+    pub fn record(&self, dir: &Path) -> io::Result<()> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let path = dir.join(format!("{}-{}.json", std::process::id(), nanos));
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    fn manifest_dir(&self) -> Option<&str> {
+        self.envs.iter().find(|(key, _)| key == "CARGO_MANIFEST_DIR").map(|(_, it)| it.as_str())
+    }
+
+    fn to_target_build_data(&self) -> Option<TargetBuildData> {
+        let mut root = None;
+        let mut edition = None;
+        let mut cfgs = Vec::new();
+        let mut is_test = false;
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--cfg" {
+                cfgs.push(args.next()?.parse().ok()?);
+            } else if let Some(cfg) = arg.strip_prefix("--cfg=") {
+                cfgs.push(cfg.parse().ok()?);
+            } else if arg == "--edition" {
+                edition = args.next().cloned();
+            } else if let Some(it) = arg.strip_prefix("--edition=") {
+                edition = Some(it.to_string());
+            } else if arg == "--test" {
+                is_test = true;
+            } else if root.is_none() && !arg.starts_with('-') && arg.ends_with(".rs") {
+                root = Some(AbsPathBuf::assert(self.cwd.join(arg)).normalize());
+            }
+        }
+        let envs = self.envs.iter().filter(|(key, _)| key != "OUT_DIR").cloned().collect();
+        Some(TargetBuildData { root: root?, edition, cfgs, envs, is_test })
+    }
+}
+
 impl BuildDataResult {
     pub(crate) fn get(&self, workspace_root: &AbsPath) -> Option<&WorkspaceBuildData> {
         self.per_workspace.get(workspace_root)
@@ -119,8 +258,10 @@ impl BuildDataConfig {
         cargo_toml: AbsPathBuf,
         cargo_features: CargoConfig,
         packages: Arc<Vec<cargo_metadata::Package>>,
+        workspace_root: AbsPathBuf,
+        target_dir: AbsPathBuf,
     ) -> Self {
-        Self { cargo_toml, cargo_features, packages }
+        Self { cargo_toml, cargo_features, packages, workspace_root, target_dir }
     }
 }
 
@@ -129,21 +270,26 @@ impl WorkspaceBuildData {
         cargo_toml: &AbsPath,
         cargo_features: &CargoConfig,
         packages: &Vec<cargo_metadata::Package>,
-        wrap_rustc: bool,
-        progress: &dyn Fn(String),
+        invocations_dir: Option<AbsPathBuf>,
+        cancel: &CancellationToken,
+        progress: &dyn Fn(ProgressEvent),
     ) -> Result<WorkspaceBuildData> {
         let mut cmd = Command::new(toolchain::cargo());
 
-        if wrap_rustc {
+        if let Some(dir) = &invocations_dir {
             // Setup RUSTC_WRAPPER to point to `rust-analyzer` binary itself. We use
             // that to compile only proc macros and build scripts during the initial
-            // `cargo check`.
+            // `cargo check`, and to record how the other crates would be compiled.
             let myself = std::env::current_exe()?;
             cmd.env("RUSTC_WRAPPER", myself);
             cmd.env("RA_RUSTC_WRAPPER", "1");
+            let _ = fs::remove_dir_all(dir);
+            fs::create_dir_all(dir)?;
+            cmd.env(RustcInvocation::DIR_ENV, dir.as_os_str());
         }
 
-        cmd.current_dir(cargo_toml.parent().unwrap());
+        let cargo_dir = cargo_toml.parent().unwrap();
+        cmd.current_dir(cargo_dir);
         cmd.args(&["check", "--quiet", "--workspace", "--message-format=json", "--manifest-path"])
             .arg(cargo_toml.as_ref());
 
@@ -156,6 +302,10 @@ impl WorkspaceBuildData {
             cmd.args(&["--target", target]);
         }
 
+        if cargo_features.offline {
+            cmd.arg("--frozen");
+        }
+
         if cargo_features.all_features {
             cmd.arg("--all-features");
         } else {
@@ -164,9 +314,10 @@ impl WorkspaceBuildData {
                 // https://github.com/oli-obk/cargo_metadata/issues/79
                 cmd.arg("--no-default-features");
             }
-            if !cargo_features.features.is_empty() {
+            let features = cargo_features.feature_flags();
+            if !features.is_empty() {
                 cmd.arg("--features");
-                cmd.arg(cargo_features.features.join(" "));
+                cmd.arg(features.join(" "));
             }
         }
 
@@ -177,6 +328,7 @@ impl WorkspaceBuildData {
         let mut callback_err = None;
         let output = stdx::process::streaming_output(
             cmd,
+            Some(cancel.0.clone()),
             &mut |line| {
                 if callback_err.is_some() {
                     return;
@@ -217,9 +369,10 @@ impl WorkspaceBuildData {
                             res.per_package.entry(package_id.repr).or_default();
                         // cargo_metadata crate returns default (empty) path for
                         // older cargos, which is not absolute, so work around that.
+                        // Paths in a relative `CARGO_TARGET_DIR` are relative to
+                        // the directory cargo runs in.
                         if !out_dir.as_str().is_empty() {
-                            let out_dir =
-                                AbsPathBuf::assert(PathBuf::from(out_dir.into_os_string()));
+                            let out_dir = cargo_dir.join(&out_dir).normalize();
                             package_build_data.out_dir = Some(out_dir);
                             package_build_data.cfgs = cfgs;
                         }
@@ -227,7 +380,7 @@ impl WorkspaceBuildData {
                         package_build_data.envs = env;
                     }
                     Message::CompilerArtifact(message) => {
-                        progress(format!("metadata {}", message.target.name));
+                        progress(ProgressEvent::BuildingCrate { crate_name: message.target.name });
 
                         if message.target.kind.iter().any(|k| k == "proc-macro") {
                             let package_id = message.package_id;
@@ -235,7 +388,7 @@ impl WorkspaceBuildData {
                             if let Some(filename) =
                                 message.filenames.iter().find(|name| is_dylib(name))
                             {
-                                let filename = AbsPathBuf::assert(PathBuf::from(&filename));
+                                let filename = cargo_dir.join(filename).normalize();
                                 let package_build_data =
                                     res.per_package.entry(package_id.repr).or_default();
                                 package_build_data.proc_macro_dylib_path = Some(filename);
@@ -243,7 +396,9 @@ impl WorkspaceBuildData {
                         }
                     }
                     Message::CompilerMessage(message) => {
-                        progress(message.target.name);
+                        progress(ProgressEvent::CompilerMessage {
+                            crate_name: message.target.name,
+                        });
                     }
                     Message::BuildFinished(_) => {}
                     Message::TextLine(_) => {}
@@ -253,6 +408,10 @@ impl WorkspaceBuildData {
             &mut |_| (),
         )?;
 
+        if let Some(dir) = &invocations_dir {
+            res.add_invocations(packages, dir);
+        }
+
         for package in packages {
             let package_build_data = res.per_package.entry(package.id.repr.clone()).or_default();
             inject_cargo_env(package, package_build_data);
@@ -264,7 +423,9 @@ impl WorkspaceBuildData {
             }
         }
 
-        if !output.status.success() {
+        if cancel.is_cancelled() {
+            res.error = Some(CANCELLED.to_string());
+        } else if !output.status.success() {
             let mut stderr = String::from_utf8(output.stderr).unwrap_or_default();
             if stderr.is_empty() {
                 stderr = "cargo check failed".to_string();
@@ -276,6 +437,171 @@ impl WorkspaceBuildData {
     }
 }
 
+impl WorkspaceBuildData {
+    /// Assigns the invocations recorded in `dir` to their packages. Crates
+    /// which were fresh weren't compiled, so they have none.
+    fn add_invocations(&mut self, packages: &[cargo_metadata::Package], dir: &AbsPath) {
+        let package_ids = packages
+            .iter()
+            .filter_map(|package| {
+                let manifest_dir = package.manifest_path.parent()?;
+                Some((manifest_dir.as_str(), package.id.repr.as_str()))
+            })
+            .collect::<FxHashMap<_, _>>();
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let invocation = match fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|text| serde_json::from_str::<RustcInvocation>(&text).ok())
+            {
+                Some(it) => it,
+                None => continue,
+            };
+            let package_id = match invocation.manifest_dir().and_then(|it| package_ids.get(it)) {
+                Some(it) => *it,
+                None => continue,
+            };
+            match invocation.to_target_build_data() {
+                Some(target) => {
+                    self.per_package.entry(package_id.to_string()).or_default().targets.push(target)
+                }
+                None => log::warn!("failed to parse rustc invocation {:?}", invocation.args),
+            }
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+fn invocations_dir(config: &BuildDataConfig) -> AbsPathBuf {
+    config.target_dir.join("rust-analyzer").join("rustc-invocations")
+}
+
+/// Bumped whenever the format of [`BuildDataCache`] changes.
+const CACHE_VERSION: u32 = 2;
+
+/// Build data of a workspace, stored in its target directory so that running
+/// build scripts can be skipped on the next start.
+///
+/// The cache is used only if none of the fingerprints changed. Packages from
+/// registries are identified by their id alone, local packages also by the
+/// modification times of their manifest and, if they contain build scripts
+/// or proc macros, of all of their files.
+#[derive(Serialize, Deserialize)]
+struct BuildDataCache {
+    version: u32,
+    fingerprint: u64,
+    packages: FxHashMap<String, CachedPackageBuildData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedPackageBuildData {
+    fingerprint: u64,
+    #[serde(flatten)]
+    data: PackageBuildData,
+}
+
+fn cache_path(config: &BuildDataConfig) -> AbsPathBuf {
+    config.target_dir.join("rust-analyzer").join("build-data.json")
+}
+
+fn load_cached(config: &BuildDataConfig) -> Option<WorkspaceBuildData> {
+    let text = fs::read_to_string(cache_path(config)).ok()?;
+    let mut cache: BuildDataCache = serde_json::from_str(&text).ok()?;
+    if cache.version != CACHE_VERSION || cache.fingerprint != workspace_fingerprint(config) {
+        return None;
+    }
+    let mut res = WorkspaceBuildData::default();
+    for package in config.packages.iter() {
+        let cached = cache.packages.remove(&package.id.repr)?;
+        if cached.fingerprint != package_fingerprint(package) {
+            return None;
+        }
+        // Outputs might have been removed by `cargo clean`.
+        let data = cached.data;
+        let exists = |path: &Option<AbsPathBuf>| {
+            path.as_ref().map_or(true, |it| mtime(it.as_ref()).is_some())
+        };
+        if !exists(&data.out_dir) || !exists(&data.proc_macro_dylib_path) {
+            return None;
+        }
+        res.per_package.insert(package.id.repr.clone(), data);
+    }
+    log::info!("reusing cached build data for {}", config.workspace_root.display());
+    Some(res)
+}
+
+fn store_cached(config: &BuildDataConfig, build_data: &WorkspaceBuildData) -> Result<()> {
+    let packages = config
+        .packages
+        .iter()
+        .filter_map(|package| {
+            let data = build_data.per_package.get(&package.id.repr)?.clone();
+            let cached = CachedPackageBuildData { fingerprint: package_fingerprint(package), data };
+            Some((package.id.repr.clone(), cached))
+        })
+        .collect();
+    let cache = BuildDataCache {
+        version: CACHE_VERSION,
+        fingerprint: workspace_fingerprint(config),
+        packages,
+    };
+    let path = cache_path(config);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, serde_json::to_string(&cache)?)?;
+    Ok(())
+}
+
+/// Covers the flags build scripts run with and the resolved dependencies.
+fn workspace_fingerprint(config: &BuildDataConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let cargo = &config.cargo_features;
+    (cargo.all_features, cargo.no_default_features, &cargo.target).hash(&mut hasher);
+    cargo.feature_flags().hash(&mut hasher);
+    mtime(config.workspace_root.join("Cargo.lock").as_ref()).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn package_fingerprint(package: &cargo_metadata::Package) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    package.id.repr.hash(&mut hasher);
+    if package.source.is_some() {
+        return hasher.finish();
+    }
+    let manifest_path: &Path = package.manifest_path.as_ref();
+    mtime(manifest_path).hash(&mut hasher);
+    let builds_code = package
+        .targets
+        .iter()
+        .any(|target| target.kind.iter().any(|it| it == "custom-build" || it == "proc-macro"));
+    if builds_code {
+        if let Some(root) = manifest_path.parent() {
+            let mut files = Vec::new();
+            let mut dirs = vec![root.to_path_buf()];
+            while let Some(dir) = dirs.pop() {
+                for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+                    let name = entry.file_name();
+                    if name.to_string_lossy().starts_with('.') || name == "target" {
+                        continue;
+                    }
+                    match entry.file_type() {
+                        Ok(it) if it.is_dir() => dirs.push(entry.path()),
+                        Ok(_) => files.push((entry.path(), mtime(&entry.path()))),
+                        Err(_) => {}
+                    }
+                }
+            }
+            files.sort();
+            files.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|it| it.modified()).ok()
+}
+
 // FIXME: File a better way to know if it is a dylib
 fn is_dylib(path: &Utf8Path) -> bool {
     match path.extension().map(|e| e.to_string().to_lowercase()) {
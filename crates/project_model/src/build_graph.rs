@@ -0,0 +1,251 @@
+//! `rust-build-graph.json` file format.
+//!
+//! Build systems like Bazel or Buck know the exact graph of Rust targets they
+//! build, including the outputs of build steps: generated sources in
+//! `OUT_DIR`s and compiled proc-macro dylibs. This format describes such a
+//! graph. Unlike `rust-project.json`, crates refer to each other by label, so
+//! that build rules can emit one entry per target without knowing the position
+//! of their dependencies in the final file.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, format_err, Result};
+use base_db::{CrateDisplayName, CrateName, Edition};
+use paths::{AbsPath, AbsPathBuf};
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+
+use crate::{cfg_flag::CfgFlag, project_json::EditionData};
+
+/// The only version of the format understood so far.
+const VERSION: u32 = 1;
+
+/// Crates of a build graph, with dependencies resolved to indices.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildGraph {
+    pub(crate) sysroot_src: Option<AbsPathBuf>,
+    pub(crate) target: Option<String>,
+    root: AbsPathBuf,
+    crates: Vec<BuildCrate>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildCrate {
+    pub(crate) label: String,
+    pub(crate) display_name: CrateDisplayName,
+    pub(crate) root_module: AbsPathBuf,
+    pub(crate) edition: Edition,
+    pub(crate) kind: BuildCrateKind,
+    /// Indices into [`BuildGraph::crates`].
+    pub(crate) deps: Vec<(CrateName, usize)>,
+    pub(crate) cfg: Vec<CfgFlag>,
+    pub(crate) target: Option<String>,
+    /// Includes `OUT_DIR`, if the crate has one.
+    pub(crate) env: FxHashMap<String, String>,
+    pub(crate) proc_macro_dylib: Option<AbsPathBuf>,
+    pub(crate) is_workspace_member: bool,
+    pub(crate) include: Vec<AbsPathBuf>,
+    pub(crate) exclude: Vec<AbsPathBuf>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildCrateKind {
+    Lib,
+    Bin,
+    Test,
+    ProcMacro,
+    BuildScript,
+}
+
+impl Default for BuildCrateKind {
+    fn default() -> BuildCrateKind {
+        BuildCrateKind::Lib
+    }
+}
+
+impl BuildGraph {
+    /// Resolves the labels of `data`, relative paths are relative to `base`.
+    pub fn new(base: &AbsPath, data: BuildGraphData) -> Result<BuildGraph> {
+        if data.version != VERSION {
+            bail!("unsupported build graph version {}, expected {}", data.version, VERSION);
+        }
+        let mut indices = FxHashMap::default();
+        for (idx, krate) in data.crates.iter().enumerate() {
+            if indices.insert(krate.label.as_str(), idx).is_some() {
+                bail!("duplicate crate label `{}`", krate.label);
+            }
+        }
+
+        let absolutize = |it: PathBuf| base.join(it).normalize();
+        let mut crates = Vec::with_capacity(data.crates.len());
+        for krate in &data.crates {
+            let mut deps = Vec::new();
+            for dep in &krate.deps {
+                let idx = *indices.get(dep.label.as_str()).ok_or_else(|| {
+                    format_err!("`{}` depends on unknown crate `{}`", krate.label, dep.label)
+                })?;
+                let name = dep.name.as_deref().unwrap_or(&data.crates[idx].name);
+                deps.push((CrateName::normalize_dashes(name), idx));
+            }
+
+            let root_module = absolutize(krate.root_module.clone());
+            let out_dir = krate.out_dir.clone().map(absolutize);
+            let is_workspace_member = krate.is_workspace_member.unwrap_or_else(|| {
+                krate.root_module.is_relative() && !krate.root_module.starts_with("..")
+                    || krate.root_module.starts_with(base)
+            });
+            let (mut include, exclude) = match &krate.source {
+                Some(src) => (
+                    src.include_dirs.iter().cloned().map(absolutize).collect(),
+                    src.exclude_dirs.iter().cloned().map(absolutize).collect(),
+                ),
+                None => (vec![root_module.parent().unwrap().to_path_buf()], Vec::new()),
+            };
+            // Generated sources are included from the `OUT_DIR`.
+            include.extend(out_dir.clone());
+
+            let mut env = krate.env.clone();
+            if let Some(out_dir) = &out_dir {
+                env.insert("OUT_DIR".to_string(), out_dir.display().to_string());
+            }
+
+            crates.push(BuildCrate {
+                label: krate.label.clone(),
+                display_name: CrateDisplayName::from_canonical_name(krate.name.clone()),
+                root_module,
+                edition: krate.edition.clone().into(),
+                kind: krate.kind,
+                deps,
+                cfg: krate.cfg.clone(),
+                target: krate.target.clone(),
+                env,
+                proc_macro_dylib: krate.proc_macro_dylib.clone().map(absolutize),
+                is_workspace_member,
+                include,
+                exclude,
+            });
+        }
+
+        Ok(BuildGraph {
+            sysroot_src: data.sysroot_src.map(absolutize),
+            target: data.target,
+            root: base.to_path_buf(),
+            crates,
+        })
+    }
+
+    pub fn n_crates(&self) -> usize {
+        self.crates.len()
+    }
+
+    pub fn crates(&self) -> impl Iterator<Item = (usize, &BuildCrate)> + '_ {
+        self.crates.iter().enumerate()
+    }
+
+    /// The directory containing the build graph file.
+    pub fn path(&self) -> &AbsPath {
+        &self.root
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BuildGraphData {
+    version: u32,
+    sysroot_src: Option<PathBuf>,
+    target: Option<String>,
+    crates: Vec<BuildCrateData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BuildCrateData {
+    label: String,
+    name: String,
+    root_module: PathBuf,
+    edition: EditionData,
+    #[serde(default)]
+    kind: BuildCrateKind,
+    #[serde(default)]
+    deps: Vec<BuildDepData>,
+    #[serde(default)]
+    cfg: Vec<CfgFlag>,
+    target: Option<String>,
+    #[serde(default)]
+    env: FxHashMap<String, String>,
+    out_dir: Option<PathBuf>,
+    proc_macro_dylib: Option<PathBuf>,
+    is_workspace_member: Option<bool>,
+    source: Option<BuildSourceData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BuildDepData {
+    label: String,
+    /// Defaults to the name of the dependency.
+    name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BuildSourceData {
+    include_dirs: Vec<PathBuf>,
+    #[serde(default)]
+    exclude_dirs: Vec<PathBuf>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(fixture: &str) -> Result<BuildGraph> {
+        // Paths in the fixture start with `$ROOT$`, which becomes an absolute path.
+        let root = if cfg!(windows) { "C:\\ROOT\\" } else { "/ROOT/" };
+        let fixture = fixture.replace("$ROOT$", &root.replace('\\', "\\\\"));
+        let data = serde_json::from_str(&fixture).unwrap();
+        BuildGraph::new(&AbsPathBuf::assert(root.into()), data)
+    }
+
+    #[test]
+    fn resolves_labels() {
+        let graph = load(include_str!("../test_data/build_graph.json")).unwrap();
+        let labels = graph.crates().map(|(_, it)| it.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, ["//app:app", "//lib:foo-bar", "//third_party:serde", "//vendor:rand"]);
+
+        let app = &graph.crates[0];
+        let deps = app.deps.iter().map(|(name, idx)| (name.to_string(), *idx)).collect::<Vec<_>>();
+        assert_eq!(deps, [("foo_bar".to_string(), 1), ("serde_renamed".to_string(), 2)]);
+        assert_eq!(app.root_module, graph.path().join("app/src/main.rs"));
+        let out_dir = graph.path().join("out/app");
+        assert_eq!(app.env["OUT_DIR"], out_dir.display().to_string());
+        assert_eq!(app.include, [graph.path().join("app/src"), out_dir]);
+    }
+
+    #[test]
+    fn workspace_members() {
+        let graph = load(include_str!("../test_data/build_graph.json")).unwrap();
+        let members = graph.crates().map(|(_, it)| it.is_workspace_member).collect::<Vec<_>>();
+        // Relative paths inside of the root and absolute ones below it are
+        // members, paths leaving the root and explicit opt-outs aren't.
+        assert_eq!(members, [true, true, false, false]);
+    }
+
+    #[test]
+    fn invalid_graphs() {
+        let krate = |label: &str, dep: &str| {
+            format!(
+                r#"{{ "label": "{}", "name": "a", "root_module": "a.rs", "edition": "2018",
+                      "deps": [{}] }}"#,
+                label,
+                if dep.is_empty() { String::new() } else { format!(r#"{{ "label": "{}" }}"#, dep) }
+            )
+        };
+        let error = |version: u32, crates: &[String]| {
+            let fixture =
+                format!(r#"{{ "version": {}, "crates": [{}] }}"#, version, crates.join(","));
+            load(&fixture).unwrap_err().to_string()
+        };
+
+        assert_eq!(error(2, &[]), "unsupported build graph version 2, expected 1");
+        assert_eq!(error(1, &[krate("//a", ""), krate("//a", "")]), "duplicate crate label `//a`");
+        assert_eq!(error(1, &[krate("//a", "//b")]), "`//a` depends on unknown crate `//b`");
+    }
+}
@@ -0,0 +1,200 @@
+//! Settings from the `.cargo/config.toml` files applying to a workspace which
+//! change how its crates are compiled: the `[env]` table and the rustflags.
+//!
+//! Cargo merges the config files of the workspace directory, its ancestors
+//! and `CARGO_HOME` itself, so it is asked for the merged config with
+//! `cargo config get`, once per workspace.
+
+use std::{env, path::Path, process::Command};
+
+use paths::AbsPath;
+use rustc_hash::FxHashMap;
+use serde_json::Value;
+
+use crate::{cargo_workspace::rustc_discover_host_triple, cfg_flag::CfgFlag, utf8_stdout};
+
+#[derive(Debug, Default)]
+pub(crate) struct CargoConfigSettings {
+    /// The `[env]` table, with `relative` paths resolved.
+    pub(crate) env: Vec<(String, String)>,
+    /// The `--cfg` options in the rustflags.
+    pub(crate) cfgs: Vec<CfgFlag>,
+}
+
+pub(crate) fn load(cargo_toml: &AbsPath, target: Option<&str>) -> CargoConfigSettings {
+    let _p = profile::span("cargo_config::load");
+    let config = config_json(cargo_toml).unwrap_or(Value::Null);
+    let cfgs = rustflags(cargo_toml, &config, target)
+        .map(|flags| cfgs_from_rustflags(&flags))
+        .unwrap_or_default();
+    CargoConfigSettings { env: config_env(cargo_toml, &config), cfgs }
+}
+
+fn config_env(cargo_toml: &AbsPath, config: &Value) -> Vec<(String, String)> {
+    let table = match config.get("env") {
+        Some(Value::Object(it)) => it.clone(),
+        _ => return Vec::new(),
+    };
+    // Only `--show-origin` tells where values come from, which is needed for
+    // relative ones alone.
+    let has_relative = table.values().any(|it| it.get("relative") == Some(&Value::Bool(true)));
+    let origins = if has_relative { env_origins(cargo_toml) } else { FxHashMap::default() };
+    let mut res = Vec::new();
+    for (key, value) in table {
+        let (value, relative) = match value {
+            Value::String(it) => (it, false),
+            Value::Object(mut it) => match it.remove("value") {
+                Some(Value::String(value)) => {
+                    (value, it.get("relative").and_then(Value::as_bool).unwrap_or(false))
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let value = if relative {
+            resolve_relative(origins.get(&key).map(String::as_str), &value)
+        } else {
+            value
+        };
+        res.push((key, value));
+    }
+    res.sort();
+    res
+}
+
+/// Relative values are relative to the directory containing the `.cargo`
+/// directory they are configured in.
+fn resolve_relative(origin: Option<&str>, value: &str) -> String {
+    let base = origin.and_then(|it| Path::new(it).parent()?.parent());
+    match base {
+        Some(base) => base.join(value).display().to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// The rustflags cargo passes to rustc, from the first source setting them:
+/// the environment, `target.<triple>.rustflags` or `build.rustflags`.
+fn rustflags(cargo_toml: &AbsPath, config: &Value, target: Option<&str>) -> Option<Vec<String>> {
+    if let Ok(flags) = env::var("CARGO_ENCODED_RUSTFLAGS") {
+        return Some(flags.split('\x1f').map(ToOwned::to_owned).collect());
+    }
+    if let Ok(flags) = env::var("RUSTFLAGS") {
+        return Some(flags.split_whitespace().map(ToOwned::to_owned).collect());
+    }
+    let triple = match target {
+        Some(it) => Some(it.to_string()),
+        None => config
+            .get("build")
+            .and_then(|it| it.get("target")?.as_str())
+            .map(ToOwned::to_owned)
+            .or_else(|| rustc_discover_host_triple(cargo_toml)),
+    };
+    // `target.'cfg(..)'.rustflags` are not supported, they'd need the cfgs of
+    // the target, which in turn depend on the rustflags.
+    triple
+        .and_then(|triple| config.get("target")?.get(triple)?.get("rustflags"))
+        .or_else(|| config.get("build")?.get("rustflags"))
+        .map(|flags| match flags {
+            Value::Array(flags) => {
+                flags.iter().filter_map(|it| it.as_str().map(ToOwned::to_owned)).collect()
+            }
+            Value::String(flags) => flags.split_whitespace().map(ToOwned::to_owned).collect(),
+            _ => Vec::new(),
+        })
+}
+
+fn cfgs_from_rustflags(flags: &[String]) -> Vec<CfgFlag> {
+    let mut res = Vec::new();
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        let cfg = match flag.strip_prefix("--cfg") {
+            Some("") => flags.next().map(String::as_str),
+            Some(it) => it.strip_prefix('='),
+            None => None,
+        };
+        match cfg.map(str::parse::<CfgFlag>) {
+            Some(Ok(it)) => res.push(it),
+            Some(Err(err)) => log::warn!("invalid cfg in rustflags: {}", err),
+            None => (),
+        }
+    }
+    res
+}
+
+/// The merged config as a JSON object.
+fn config_json(cargo_toml: &AbsPath) -> Option<Value> {
+    let mut cargo_config = config_get(cargo_toml);
+    cargo_config.args(&["--format", "json"]);
+    log::debug!("Discovering cargo config by {:?}", cargo_config);
+    let stdout = utf8_stdout(cargo_config).ok()?;
+    serde_json::from_str(&stdout).ok()
+}
+
+/// The config files the `[env]` values are set in.
+fn env_origins(cargo_toml: &AbsPath) -> FxHashMap<String, String> {
+    let mut cargo_config = config_get(cargo_toml);
+    cargo_config.args(&["--show-origin", "env"]);
+    log::debug!("Discovering origins of the cargo config env by {:?}", cargo_config);
+    utf8_stdout(cargo_config).map(|stdout| parse_env_origins(&stdout)).unwrap_or_default()
+}
+
+/// Parses lines like `env.KEY.value = "value" # /path/to/.cargo/config.toml`.
+fn parse_env_origins(stdout: &str) -> FxHashMap<String, String> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (key, origin) = line.split_once(" = ")?;
+            let key = key.strip_prefix("env.")?;
+            let key = key.strip_suffix(".value").unwrap_or(key);
+            let origin = origin.rsplit_once(" # ")?.1.trim();
+            // Values from the environment or the command line have no file.
+            Path::new(origin).is_absolute().then(|| (key.to_string(), origin.to_string()))
+        })
+        .collect()
+}
+
+fn config_get(cargo_toml: &AbsPath) -> Command {
+    let mut cargo_config = Command::new(toolchain::cargo());
+    cargo_config
+        .current_dir(cargo_toml.parent().unwrap())
+        .args(&["-Z", "unstable-options", "config", "get"])
+        .env("RUSTC_BOOTSTRAP", "1");
+    cargo_config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_env() {
+        let origins = parse_env_origins(
+            "env.DATA.relative = true # /ws/.cargo/config.toml\n\
+             env.DATA.value = \"data\" # /ws/.cargo/config.toml\n\
+             env.FOO = \"bar # baz\" # /ws/crate/.cargo/config.toml\n\
+             env.CLI.value = \"cli\" # --config cli option\n",
+        );
+        assert_eq!(origins["DATA"], "/ws/.cargo/config.toml");
+        assert_eq!(origins["FOO"], "/ws/crate/.cargo/config.toml");
+        assert!(!origins.contains_key("CLI"));
+
+        let resolved = resolve_relative(Some("/ws/.cargo/config.toml"), "data");
+        assert_eq!(Path::new(&resolved), Path::new("/ws/data"));
+        assert_eq!(resolve_relative(None, "data"), "data");
+    }
+
+    #[test]
+    fn rustflags_cfgs() {
+        let flags = ["-Copt-level=3", "--cfg=x", "--cfg", "y", "--cfg", "feature=\"z\"", "--cfg"];
+        let flags = flags.iter().map(|it| it.to_string()).collect::<Vec<_>>();
+        let cfgs = cfgs_from_rustflags(&flags);
+        assert_eq!(
+            cfgs,
+            [
+                CfgFlag::Atom("x".into()),
+                CfgFlag::Atom("y".into()),
+                CfgFlag::KeyValue { key: "feature".into(), value: "z".into() },
+            ]
+        );
+    }
+}
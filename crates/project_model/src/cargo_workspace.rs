@@ -1,10 +1,10 @@
 //! See [`CargoWorkspace`].
 
 use std::iter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{convert::TryInto, ops, process::Command, sync::Arc};
 
-use anyhow::{Context, Result};
+use anyhow::{format_err, Context, Result};
 use base_db::Edition;
 use cargo_metadata::{CargoOpt, MetadataCommand};
 use la_arena::{Arena, Idx};
@@ -13,8 +13,8 @@ use rustc_hash::FxHashMap;
 use serde::Deserialize;
 use serde_json::from_value;
 
-use crate::CfgOverrides;
-use crate::{build_data::BuildDataConfig, utf8_stdout};
+use crate::{build_data::BuildDataConfig, metadata_cache, utf8_stdout, ProgressEvent};
+use crate::{cargo_config, cfg_flag::CfgFlag, workspace::uses_rustc_private, CfgOverrides};
 
 /// [`CargoWorkspace`] represents the logical structure of, well, a Cargo
 /// workspace. It pretty closely mirrors `cargo metadata` output.
@@ -31,6 +31,11 @@ pub struct CargoWorkspace {
     packages: Arena<PackageData>,
     targets: Arena<TargetData>,
     workspace_root: AbsPathBuf,
+    /// The effective target directory, as resolved by cargo from
+    /// `CARGO_TARGET_DIR` or `build.target-dir`.
+    target_dir: AbsPathBuf,
+    /// Cargo puts the artifacts of an explicit `--target` in a subdirectory.
+    target: Option<String>,
     build_data_config: BuildDataConfig,
 }
 
@@ -69,21 +74,97 @@ pub struct CargoConfig {
     /// This will be ignored if `cargo_all_features` is true.
     pub features: Vec<String>,
 
+    /// Features to activate for single workspace members, by package name.
+    /// The `all` feature activates all features of the package.
+    pub package_features: FxHashMap<String, Vec<String>>,
+
     /// rustc target
     pub target: Option<String>,
 
+    /// Targets to load the workspace for in addition to `target`.
+    pub extra_targets: Vec<String>,
+
     /// Don't load sysroot crates (`std`, `core` & friends). Might be useful
     /// when debugging isolated issues.
     pub no_sysroot: bool,
 
+    /// Sysroot to use instead of the one of the toolchain.
+    pub sysroot: Option<AbsPathBuf>,
+
+    /// Directory containing the sources of the sysroot crates, the `library`
+    /// directory of the Rust sources. Takes precedence over `sysroot`.
+    pub sysroot_src: Option<AbsPathBuf>,
+
     /// rustc private crate source
     pub rustc_source: Option<RustcSource>,
 
     /// crates to disable `#[cfg(test)]` on
     pub unset_test_crates: Vec<String>,
+
+    /// Whether the crates of a package are `#![no_std]`, by package name,
+    /// instead of looking at their crate roots.
+    pub no_std_overrides: FxHashMap<String, bool>,
+
+    /// Additional cfg options for the crates of a package, by package name,
+    /// `*` applies to all packages.
+    pub extra_cfgs: FxHashMap<String, Vec<String>>,
+
+    /// Additional environment variables for the crates of a package, by
+    /// package name, `*` applies to all packages.
+    pub extra_env: FxHashMap<String, FxHashMap<String, String>>,
+
+    /// Run cargo with `--frozen`, so that it neither accesses the network nor
+    /// updates `Cargo.lock`.
+    pub offline: bool,
 }
 
 impl CargoConfig {
+    /// `features` and the `package_features`, in the `package/feature` form
+    /// understood by cargo.
+    pub(crate) fn feature_flags(&self) -> Vec<String> {
+        let mut package_features = self
+            .package_features
+            .iter()
+            .flat_map(|(package, features)| {
+                features
+                    .iter()
+                    .filter(|it| *it != "all")
+                    .map(move |feature| format!("{}/{}", package, feature))
+            })
+            .collect::<Vec<_>>();
+        package_features.sort();
+        self.features.iter().cloned().chain(package_features).collect()
+    }
+
+    /// The `extra_cfgs` of all packages and of the package `name`.
+    fn package_extra_cfgs(&self, name: &str) -> Vec<CfgFlag> {
+        ["*", name]
+            .iter()
+            .filter_map(|it| self.extra_cfgs.get(*it))
+            .flatten()
+            .filter_map(|cfg| match cfg.parse::<CfgFlag>() {
+                Ok(it) => Some(it),
+                Err(err) => {
+                    log::error!("invalid extra cfg for `{}`: {}", name, err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The `extra_env` of all packages and of the package `name`, which takes
+    /// precedence.
+    fn package_extra_env(&self, name: &str) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+        for vars in ["*", name].iter().filter_map(|it| self.extra_env.get(*it)) {
+            // Sorted to keep the crate graph stable across reloads.
+            let start = env.len();
+            env.extend(vars.iter().map(|(key, value)| (key.clone(), value.clone())));
+            env[start..].sort();
+        }
+        env
+    }
+
     pub fn cfg_overrides(&self) -> CfgOverrides {
         self.unset_test_crates
             .iter()
@@ -104,6 +185,10 @@ pub type Target = Idx<TargetData>;
 pub struct PackageData {
     /// Version given in the `Cargo.toml`
     pub version: String,
+    /// Description given in the `Cargo.toml`
+    pub description: Option<String>,
+    /// Repository URL given in the `Cargo.toml`
+    pub repository: Option<String>,
     /// Name as given in the `Cargo.toml`
     pub name: String,
     /// Path containing the `Cargo.toml`
@@ -112,8 +197,13 @@ pub struct PackageData {
     pub targets: Vec<Target>,
     /// Is this package a member of the current workspace
     pub is_member: bool,
+    /// Does this package come from a local path rather than a registry or
+    /// git, like path dependencies and crates `[patch]`ed with a path
+    pub is_local: bool,
     /// List of packages this package depends on
     pub dependencies: Vec<PackageDependency>,
+    /// Binaries of other packages this package depends on
+    pub artifact_dependencies: Vec<ArtifactDependency>,
     /// Rust edition for this package
     pub edition: Edition,
     /// Features provided by the crate, mapped to the features required by that feature.
@@ -122,8 +212,17 @@ pub struct PackageData {
     pub active_features: Vec<String>,
     // String representation of package id
     pub id: String,
-    // The contents of [package.metadata.rust-analyzer]
+    // The contents of [package.metadata.rust-analyzer], `rustc_private` is
+    // also set for local packages enabling `#![feature(rustc_private)]`
     pub metadata: RustAnalyzerPackageMetaData,
+    /// Whether the crates of this package are `#![no_std]`, if configured
+    pub no_std: Option<bool>,
+    /// Cfg options from the rustflags of the cargo config and the configured
+    /// ones, in addition to the ones of the build
+    pub extra_cfgs: Vec<CfgFlag>,
+    /// The `[env]` of the cargo config and the configured environment
+    /// variables, overriding the ones of the build
+    pub extra_env: Vec<(String, String)>,
 }
 
 #[derive(Deserialize, Default, Debug, Clone, Eq, PartialEq)]
@@ -138,6 +237,18 @@ pub struct PackageDependency {
     pub kind: DepKind,
 }
 
+/// A dependency on the binaries of a package, declared with
+/// `artifact = "bin"` under `-Z bindeps`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArtifactDependency {
+    pub pkg: Package,
+    /// Name as given in the `Cargo.toml`, used in the `CARGO_BIN_FILE_*` variables
+    pub name: String,
+    pub kind: DepKind,
+    /// Names of the binary targets of `pkg`
+    pub bins: Vec<String>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord)]
 pub enum DepKind {
     /// Available to the library, binary, and dev targets in the package (but not the build script).
@@ -154,19 +265,20 @@ impl DepKind {
         if list.is_empty() {
             dep_kinds.push(Self::Normal);
         }
-        for info in list {
-            let kind = match info.kind {
-                cargo_metadata::DependencyKind::Normal => Self::Normal,
-                cargo_metadata::DependencyKind::Development => Self::Dev,
-                cargo_metadata::DependencyKind::Build => Self::Build,
-                cargo_metadata::DependencyKind::Unknown => continue,
-            };
-            dep_kinds.push(kind);
-        }
+        dep_kinds.extend(list.iter().filter_map(Self::new));
         dep_kinds.sort_unstable();
         dep_kinds.dedup();
         dep_kinds.into_iter()
     }
+
+    fn new(info: &cargo_metadata::DepKindInfo) -> Option<Self> {
+        match info.kind {
+            cargo_metadata::DependencyKind::Normal => Some(Self::Normal),
+            cargo_metadata::DependencyKind::Development => Some(Self::Dev),
+            cargo_metadata::DependencyKind::Build => Some(Self::Build),
+            cargo_metadata::DependencyKind::Unknown => None,
+        }
+    }
 }
 
 /// Information associated with a package's target
@@ -227,12 +339,113 @@ struct PackageMetadata {
     rust_analyzer: Option<RustAnalyzerPackageMetaData>,
 }
 
+/// The output of `cargo metadata`.
+#[derive(Debug, Clone)]
+pub struct CargoMetadata {
+    meta: cargo_metadata::Metadata,
+    artifacts: ArtifactKinds,
+}
+
+/// The artifact dependency fields of the `dep_kinds` of the resolve graph,
+/// which `cargo_metadata` doesn't know about.
+#[derive(Default, Debug, Clone)]
+struct ArtifactKinds {
+    /// Indexed by the ids of the dependent and the dependency, aligned with
+    /// the `dep_kinds` of the dependency.
+    by_edge: FxHashMap<(String, String), Vec<ArtifactKindInfo>>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+struct ArtifactKindInfo {
+    /// The name of the dependency in the `Cargo.toml`.
+    extern_name: Option<String>,
+    /// `lib`, `bin`, `cdylib` or `staticlib`, unset for normal dependencies.
+    artifact: Option<String>,
+    bin_name: Option<String>,
+}
+
+impl ArtifactKindInfo {
+    fn is_lib(&self) -> bool {
+        matches!(self.artifact.as_deref(), None | Some("lib"))
+    }
+}
+
+impl ArtifactKinds {
+    fn from_json(json: &str) -> Result<ArtifactKinds> {
+        #[derive(Deserialize)]
+        struct Metadata {
+            resolve: Option<Resolve>,
+        }
+        #[derive(Deserialize)]
+        struct Resolve {
+            nodes: Vec<Node>,
+        }
+        #[derive(Deserialize)]
+        struct Node {
+            id: String,
+            #[serde(default)]
+            deps: Vec<NodeDep>,
+        }
+        #[derive(Deserialize)]
+        struct NodeDep {
+            pkg: String,
+            #[serde(default)]
+            dep_kinds: Vec<ArtifactKindInfo>,
+        }
+
+        let meta: Metadata = serde_json::from_str(json)?;
+        let mut by_edge = FxHashMap::default();
+        for node in meta.resolve.into_iter().flat_map(|it| it.nodes) {
+            for dep in node.deps {
+                if dep.dep_kinds.iter().any(|it| it.artifact.is_some()) {
+                    by_edge.insert((node.id.clone(), dep.pkg), dep.dep_kinds);
+                }
+            }
+        }
+        Ok(ArtifactKinds { by_edge })
+    }
+
+    fn kinds(&self, from: &str, to: &str) -> &[ArtifactKindInfo] {
+        self.by_edge.get(&(from.to_string(), to.to_string())).map_or(&[][..], Vec::as_slice)
+    }
+}
+
+/// Like `MetadataCommand::exec`, but keeps the artifact dependency fields.
+/// The output is reused while the workspace doesn't change.
+fn run_metadata(meta: &MetadataCommand, cargo_toml: &AbsPath) -> Result<CargoMetadata> {
+    let command = meta.cargo_command();
+    let key = format!("{:?}", command);
+    if let Some(json) = metadata_cache::load(cargo_toml, &key) {
+        match parse_metadata(&json) {
+            Ok(it) => return Ok(it),
+            Err(err) => log::warn!("failed to parse cached metadata: {}", err),
+        }
+    }
+
+    let stdout = utf8_stdout(command)?;
+    let json = stdout
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .ok_or_else(|| format_err!("no JSON in `cargo metadata` output"))?;
+    let res = parse_metadata(json)?;
+    if let Err(err) = metadata_cache::store(cargo_toml, &key, json, &res.meta) {
+        log::warn!("failed to cache metadata: {}", err);
+    }
+    Ok(res)
+}
+
+pub(crate) fn parse_metadata(json: &str) -> Result<CargoMetadata> {
+    let artifacts = ArtifactKinds::from_json(json)?;
+    let meta = MetadataCommand::parse(json)?;
+    Ok(CargoMetadata { meta, artifacts })
+}
+
 impl CargoWorkspace {
     pub fn fetch_metadata(
         cargo_toml: &AbsPath,
         config: &CargoConfig,
-        progress: &dyn Fn(String),
-    ) -> Result<cargo_metadata::Metadata> {
+        progress: &dyn Fn(ProgressEvent),
+    ) -> Result<CargoMetadata> {
         let mut meta = MetadataCommand::new();
         meta.cargo_path(toolchain::cargo());
         meta.manifest_path(cargo_toml.to_path_buf());
@@ -244,8 +457,9 @@ impl CargoWorkspace {
                 // https://github.com/oli-obk/cargo_metadata/issues/79
                 meta.features(CargoOpt::NoDefaultFeatures);
             }
-            if !config.features.is_empty() {
-                meta.features(CargoOpt::SomeFeatures(config.features.clone()));
+            let features = config.feature_flags();
+            if !features.is_empty() {
+                meta.features(CargoOpt::SomeFeatures(features));
             }
         }
         if let Some(parent) = cargo_toml.parent() {
@@ -258,16 +472,21 @@ impl CargoWorkspace {
         } else {
             rustc_discover_host_triple(cargo_toml)
         };
+        let mut other_options = Vec::new();
         if let Some(target) = target {
-            meta.other_options(vec![String::from("--filter-platform"), target]);
+            other_options.extend([String::from("--filter-platform"), target]);
         }
+        if config.offline {
+            other_options.push(String::from("--frozen"));
+        }
+        meta.other_options(other_options);
 
         // FIXME: Fetching metadata is a slow process, as it might require
         // calling crates.io. We should be reporting progress here, but it's
         // unclear whether cargo itself supports it.
-        progress("metadata".to_string());
+        progress(ProgressEvent::FetchingMetadata);
 
-        let meta = meta.exec().with_context(|| {
+        let meta = run_metadata(&meta, cargo_toml).with_context(|| {
             let cwd: Option<AbsPathBuf> =
                 std::env::current_dir().ok().and_then(|p| p.try_into().ok());
 
@@ -278,31 +497,46 @@ impl CargoWorkspace {
                 .map(|dir| format!(" in `{}`", dir.display()))
                 .unwrap_or_default();
 
+            let hint = if config.offline {
+                "\nrust-analyzer is in offline mode, run `cargo fetch` while online to \
+                 update `Cargo.lock` and download the dependencies"
+            } else {
+                ""
+            };
+
             format!(
-                "Failed to run `cargo metadata --manifest-path {}`{}",
+                "Failed to run `cargo metadata --manifest-path {}`{}{}",
                 cargo_toml.display(),
-                workdir
+                workdir,
+                hint
             )
         })?;
 
         Ok(meta)
     }
 
-    pub fn new(
-        cargo_toml: &AbsPath,
-        config: &CargoConfig,
-        mut meta: cargo_metadata::Metadata,
-    ) -> CargoWorkspace {
+    pub fn new(cargo_toml: &AbsPath, config: &CargoConfig, meta: CargoMetadata) -> CargoWorkspace {
+        let CargoMetadata { mut meta, artifacts } = meta;
         let mut pkg_by_id = FxHashMap::default();
         let mut packages = Arena::default();
         let mut targets = Arena::default();
 
         let ws_members = &meta.workspace_members;
+        let cargo_config = cargo_config::load(cargo_toml, config.target.as_deref());
 
         meta.packages.sort_by(|a, b| a.id.cmp(&b.id));
         for meta_pkg in &meta.packages {
             let cargo_metadata::Package {
-                id, edition, name, manifest_path, version, metadata, ..
+                id,
+                edition,
+                name,
+                manifest_path,
+                version,
+                metadata,
+                source,
+                description,
+                repository,
+                ..
             } = meta_pkg;
             let meta = from_value::<PackageMetadata>(metadata.clone()).unwrap_or_default();
             let is_member = ws_members.contains(id);
@@ -311,18 +545,39 @@ impl CargoWorkspace {
                 Edition::CURRENT
             });
 
+            // `cargo metadata` reports where the sources that are compiled
+            // live, which for patched and replaced sources is the patch or
+            // the vendor directory. Paths from `.cargo/config.toml` are
+            // joined as written though, so they may contain `..`.
             let pkg = packages.alloc(PackageData {
                 id: id.repr.clone(),
                 name: name.clone(),
                 version: version.to_string(),
-                manifest: AbsPathBuf::assert(PathBuf::from(&manifest_path)),
+                description: description.clone(),
+                repository: repository.clone(),
+                manifest: AbsPathBuf::assert(PathBuf::from(&manifest_path)).normalize(),
                 targets: Vec::new(),
                 is_member,
+                is_local: source.is_none(),
                 edition,
                 dependencies: Vec::new(),
+                artifact_dependencies: Vec::new(),
                 features: meta_pkg.features.clone().into_iter().collect(),
                 active_features: Vec::new(),
                 metadata: meta.rust_analyzer.unwrap_or_default(),
+                no_std: config.no_std_overrides.get(name.as_str()).copied(),
+                extra_cfgs: cargo_config
+                    .cfgs
+                    .iter()
+                    .cloned()
+                    .chain(config.package_extra_cfgs(name))
+                    .collect(),
+                extra_env: cargo_config
+                    .env
+                    .iter()
+                    .cloned()
+                    .chain(config.package_extra_env(name))
+                    .collect(),
             });
             let pkg_data = &mut packages[pkg];
             pkg_by_id.insert(id, pkg);
@@ -331,12 +586,16 @@ impl CargoWorkspace {
                 let tgt = targets.alloc(TargetData {
                     package: pkg,
                     name: meta_tgt.name.clone(),
-                    root: AbsPathBuf::assert(PathBuf::from(&meta_tgt.src_path)),
+                    root: AbsPathBuf::assert(PathBuf::from(&meta_tgt.src_path)).normalize(),
                     kind: TargetKind::new(meta_tgt.kind.as_slice()),
                     is_proc_macro,
                 });
                 pkg_data.targets.push(tgt);
             }
+            if pkg_data.is_local && !pkg_data.metadata.rustc_private {
+                pkg_data.metadata.rustc_private =
+                    pkg_data.targets.iter().any(|&tgt| uses_rustc_private(&targets[tgt].root));
+            }
         }
         let resolve = meta.resolve.expect("metadata executed with deps");
         for mut node in resolve.nodes {
@@ -351,11 +610,7 @@ impl CargoWorkspace {
                 }
             };
             node.deps.sort_by(|a, b| a.pkg.cmp(&b.pkg));
-            for (dep_node, kind) in node
-                .deps
-                .iter()
-                .flat_map(|dep| DepKind::iter(&dep.dep_kinds).map(move |kind| (dep, kind)))
-            {
+            for dep_node in node.deps.iter() {
                 let pkg = match pkg_by_id.get(&dep_node.pkg) {
                     Some(&pkg) => pkg,
                     None => {
@@ -366,24 +621,98 @@ impl CargoWorkspace {
                         continue;
                     }
                 };
-                let dep = PackageDependency { name: dep_node.name.clone(), pkg, kind };
-                packages[source].dependencies.push(dep);
+                let artifact_kinds = artifacts.kinds(&node.id.repr, &dep_node.pkg.repr);
+                let is_lib = |idx: usize| artifact_kinds.get(idx).map_or(true, |it| it.is_lib());
+
+                // An artifact dependency only links to the library with `lib = true`.
+                let lib_kinds = dep_node
+                    .dep_kinds
+                    .iter()
+                    .enumerate()
+                    .filter(|&(idx, _)| is_lib(idx))
+                    .map(|(_, info)| info.clone())
+                    .collect::<Vec<_>>();
+                if !dep_node.name.is_empty()
+                    && (dep_node.dep_kinds.is_empty() || !lib_kinds.is_empty())
+                {
+                    for kind in DepKind::iter(&lib_kinds) {
+                        let dep = PackageDependency { name: dep_node.name.clone(), pkg, kind };
+                        packages[source].dependencies.push(dep);
+                    }
+                }
+
+                for (info, artifact) in dep_node.dep_kinds.iter().zip(artifact_kinds) {
+                    let (kind, bin) = match (DepKind::new(info), &artifact.bin_name) {
+                        (Some(kind), Some(bin)) if artifact.artifact.as_deref() == Some("bin") => {
+                            (kind, bin)
+                        }
+                        _ => continue,
+                    };
+                    let name =
+                        artifact.extern_name.clone().unwrap_or_else(|| packages[pkg].name.clone());
+                    let deps = &mut packages[source].artifact_dependencies;
+                    match deps
+                        .iter_mut()
+                        .find(|it| it.pkg == pkg && it.kind == kind && it.name == name)
+                    {
+                        Some(dep) => dep.bins.push(bin.clone()),
+                        None => deps.push(ArtifactDependency {
+                            pkg,
+                            name,
+                            kind,
+                            bins: vec![bin.clone()],
+                        }),
+                    }
+                }
             }
             packages[source].active_features.extend(node.features);
         }
 
+        // Cargo can't activate all features of a single package.
+        if !config.all_features {
+            for (_, pkg) in packages.iter_mut() {
+                let all = config
+                    .package_features
+                    .get(&pkg.name)
+                    .map_or(false, |features| features.iter().any(|it| it == "all"));
+                if !all || !pkg.is_member {
+                    continue;
+                }
+                let mut features = pkg.features.keys().cloned().collect::<Vec<_>>();
+                features.sort();
+                for feature in features {
+                    if !pkg.active_features.contains(&feature) {
+                        pkg.active_features.push(feature);
+                    }
+                }
+            }
+        }
+
         let workspace_root =
             AbsPathBuf::assert(PathBuf::from(meta.workspace_root.into_os_string()));
-        let build_data_config =
-            BuildDataConfig::new(cargo_toml.to_path_buf(), config.clone(), Arc::new(meta.packages));
-
-        CargoWorkspace { packages, targets, workspace_root, build_data_config }
+        let target_dir = AbsPathBuf::assert(PathBuf::from(meta.target_directory.into_os_string()));
+        let build_data_config = BuildDataConfig::new(
+            cargo_toml.to_path_buf(),
+            config.clone(),
+            Arc::new(meta.packages),
+            workspace_root.clone(),
+            target_dir.clone(),
+        );
+
+        CargoWorkspace {
+            packages,
+            targets,
+            workspace_root,
+            target_dir,
+            target: config.target.clone(),
+            build_data_config,
+        }
     }
 
     pub fn from_cargo_metadata3(
         cargo_toml: &AbsPath,
         config: &CargoConfig,
-        progress: &dyn Fn(String),
+        progress: &dyn Fn(ProgressEvent),
     ) -> Result<CargoWorkspace> {
         let meta = CargoWorkspace::fetch_metadata(cargo_toml, config, progress)?;
         Ok(CargoWorkspace::new(cargo_toml, config, meta))
@@ -404,6 +733,38 @@ impl CargoWorkspace {
         &self.workspace_root
     }
 
+    pub fn target_dir(&self) -> &AbsPath {
+        &self.target_dir
+    }
+
+    /// The directory of the artifacts of `cargo check`.
+    fn profile_dir(&self) -> AbsPathBuf {
+        match &self.target {
+            Some(target) => self.target_dir.join(target).join("debug"),
+            None => self.target_dir.join("debug"),
+        }
+    }
+
+    /// The variables cargo sets for the crates of a package with the
+    /// artifact dependency `dep`.
+    pub fn artifact_env(&self, dep: &ArtifactDependency) -> Vec<(String, String)> {
+        // Cargo puts the binaries in a directory with a hash of the unit in
+        // its name. The exact path doesn't matter for `env!` though.
+        let dir =
+            self.profile_dir().join("deps").join("artifact").join(&self[dep.pkg].name).join("bin");
+        let dep_name = dep.name.to_uppercase().replace('-', "_");
+        let mut env = vec![(format!("CARGO_BIN_DIR_{}", dep_name), dir.display().to_string())];
+        for bin in &dep.bins {
+            let file = dir.join(format!("{}{}", bin, std::env::consts::EXE_SUFFIX));
+            let file = file.display().to_string();
+            if *bin == dep.name {
+                env.push((format!("CARGO_BIN_FILE_{}", dep_name), file.clone()));
+            }
+            env.push((format!("CARGO_BIN_FILE_{}_{}", dep_name, bin), file));
+        }
+        env
+    }
+
     pub fn package_flag(&self, package: &PackageData) -> String {
         if self.is_unique(&*package.name) {
             package.name.clone()
@@ -421,7 +782,7 @@ impl CargoWorkspace {
     }
 }
 
-fn rustc_discover_host_triple(cargo_toml: &AbsPath) -> Option<String> {
+pub(crate) fn rustc_discover_host_triple(cargo_toml: &AbsPath) -> Option<String> {
     let mut rustc = Command::new(toolchain::rustc());
     rustc.current_dir(cargo_toml.parent().unwrap()).arg("-vV");
     log::debug!("Discovering host platform by {:?}", rustc);
@@ -444,19 +805,42 @@ fn rustc_discover_host_triple(cargo_toml: &AbsPath) -> Option<String> {
     }
 }
 
-fn cargo_config_build_target(cargo_toml: &AbsPath) -> Option<String> {
+pub(crate) fn cargo_config_build_target(cargo_toml: &AbsPath) -> Option<String> {
+    cargo_config_get(cargo_toml, "build.target").map(|(value, _origin)| value)
+}
+
+/// The `build.target-dir` configured for `cargo_toml`. Relative paths are
+/// relative to the directory containing the `.cargo` directory they are
+/// configured in.
+pub(crate) fn cargo_config_target_dir(cargo_toml: &AbsPath) -> Option<AbsPathBuf> {
+    let cargo_dir = cargo_toml.parent()?;
+    let (value, origin) = cargo_config_get(cargo_toml, "build.target-dir")?;
+    let base = origin
+        .as_deref()
+        .and_then(|it| AbsPath::assert(Path::new(it)).parent()?.parent())
+        .unwrap_or(cargo_dir);
+    Some(base.join(value))
+}
+
+/// Returns the value of `key` and the file it was configured in, if any.
+pub(crate) fn cargo_config_get(
+    cargo_toml: &AbsPath,
+    key: &str,
+) -> Option<(String, Option<String>)> {
     let mut cargo_config = Command::new(toolchain::cargo());
     cargo_config
         .current_dir(cargo_toml.parent().unwrap())
-        .args(&["-Z", "unstable-options", "config", "get", "build.target"])
+        .args(&["-Z", "unstable-options", "config", "get", "--show-origin", key])
         .env("RUSTC_BOOTSTRAP", "1");
-    // if successful we receive `build.target = "target-triple"`
-    log::debug!("Discovering cargo config target by {:?}", cargo_config);
-    match utf8_stdout(cargo_config) {
-        Ok(stdout) => stdout
-            .strip_prefix("build.target = \"")
-            .and_then(|stdout| stdout.strip_suffix('"'))
-            .map(ToOwned::to_owned),
-        Err(_) => None,
-    }
+    // if successful we receive `key = "value" # /path/to/.cargo/config.toml`
+    log::debug!("Discovering cargo config {} by {:?}", key, cargo_config);
+    let stdout = utf8_stdout(cargo_config).ok()?;
+    let (value, origin) = match stdout.split_once(" # ") {
+        Some((value, origin)) => (value, Some(origin.trim())),
+        None => (stdout.as_str(), None),
+    };
+    let value = value.strip_prefix(key)?.trim_start().strip_prefix("= \"")?.strip_suffix('"')?;
+    // Values from the environment or the command line have no file.
+    let origin = origin.filter(|it| Path::new(it).is_absolute()).map(ToOwned::to_owned);
+    Some((value.to_string(), origin))
 }
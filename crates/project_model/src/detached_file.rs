@@ -0,0 +1,257 @@
+//! Single `.rs` files analyzed without a manifest, like scratch files or
+//! scripts.
+//!
+//! Scripts may declare their dependencies in cargo-script style, either in a
+//! single leading comment:
+//!
+//! ```text
+//! // cargo-deps: time="0.1.25", libc
+//! ```
+//!
+//! or as a manifest embedded in the module documentation:
+//!
+//! ```text
+//! //! ```cargo
+//! //! [dependencies]
+//! //! time = "0.1.25"
+//! //! ```
+//! ```
+//!
+//! A package with the file as its binary is generated for such scripts, so
+//! that cargo resolves the dependencies.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    convert::TryFrom,
+    fs,
+    hash::{Hash, Hasher},
+};
+
+use anyhow::{Context, Result};
+use base_db::Edition;
+use paths::{AbsPath, AbsPathBuf};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DetachedFile {
+    pub(crate) path: AbsPathBuf,
+    pub(crate) edition: Edition,
+    /// The manifest sections declared by the file, without `[package]`.
+    manifest: Option<String>,
+}
+
+impl DetachedFile {
+    /// A file which can't be read is kept, its crate won't load either.
+    pub fn load(path: AbsPathBuf) -> DetachedFile {
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        let (manifest, declared_edition) = match embedded_manifest(&text) {
+            Some(manifest) => split_package_edition(&manifest),
+            None => (cargo_deps(&text), None),
+        };
+        let edition = declared_edition.unwrap_or_else(|| infer_edition(&text));
+        DetachedFile { path, edition, manifest }
+    }
+
+    pub fn path(&self) -> &AbsPath {
+        &self.path
+    }
+
+    pub fn has_dependencies(&self) -> bool {
+        self.manifest.is_some()
+    }
+
+    /// Writes the `Cargo.toml` of a package building the file as a binary,
+    /// in a directory private to the file.
+    pub(crate) fn write_manifest(&self) -> Result<AbsPathBuf> {
+        let name = self
+            .path
+            .file_stem()
+            .and_then(|it| it.to_str())
+            .map(|it| it.replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "_"))
+            .unwrap_or_else(|| "script".to_string());
+        let mut hasher = DefaultHasher::new();
+        self.path.hash(&mut hasher);
+        let dir = std::env::temp_dir().join("rust-analyzer-scripts").join(format!(
+            "{}-{:016x}",
+            name,
+            hasher.finish()
+        ));
+        let dir = AbsPathBuf::try_from(dir)
+            .map_err(|it| anyhow::format_err!("temp dir {} is relative", it.display()))?;
+
+        let manifest = self.manifest_text(&name)?;
+        let path = dir.join("Cargo.toml");
+        fs::create_dir_all(&dir)
+            .and_then(|()| fs::write(&path, manifest))
+            .with_context(|| format!("Failed to write manifest for {}", self.path.display()))?;
+        Ok(path)
+    }
+
+    /// The generated sections are serialized, as the path may contain
+    /// characters which need escaping, the declared ones are kept verbatim.
+    fn manifest_text(&self, name: &str) -> Result<String> {
+        #[derive(Serialize)]
+        struct Manifest<'a> {
+            package: Package<'a>,
+            bin: [Bin<'a>; 1],
+            workspace: Workspace,
+        }
+        #[derive(Serialize)]
+        struct Package<'a> {
+            name: &'a str,
+            version: &'a str,
+            edition: String,
+        }
+        #[derive(Serialize)]
+        struct Bin<'a> {
+            name: &'a str,
+            path: String,
+        }
+        #[derive(Serialize)]
+        struct Workspace {}
+
+        let path = self
+            .path
+            .as_os_str()
+            .to_str()
+            .with_context(|| format!("{} is not valid UTF-8", self.path.display()))?;
+        let generated = toml::to_string(&Manifest {
+            package: Package { name, version: "0.0.0", edition: self.edition.to_string() },
+            bin: [Bin { name, path: path.to_string() }],
+            workspace: Workspace {},
+        })?;
+        Ok(format!("{}\n{}", generated, self.manifest.as_deref().unwrap_or_default()))
+    }
+}
+
+/// The contents of a ```` ```cargo ```` block in the leading `//!` comments.
+fn embedded_manifest(text: &str) -> Option<String> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.starts_with("#!") && !line.starts_with("#!["))
+        .take_while(|line| line.starts_with("//!"))
+        .map(|line| line.trim_start_matches("//!").trim_start());
+    lines.find(|line| *line == "```cargo")?;
+    let mut manifest = String::new();
+    for line in lines {
+        if line.starts_with("```") {
+            return Some(manifest);
+        }
+        manifest.push_str(line);
+        manifest.push('\n');
+    }
+    None
+}
+
+/// Removes the `[package]` section, which is generated, keeping its edition.
+fn split_package_edition(manifest: &str) -> (Option<String>, Option<Edition>) {
+    let mut rest = String::new();
+    let mut edition = None;
+    let mut in_package = false;
+    for line in manifest.lines() {
+        if line.starts_with('[') {
+            in_package = line.trim() == "[package]";
+        }
+        if !in_package {
+            rest.push_str(line);
+            rest.push('\n');
+            continue;
+        }
+        let mut key_value = line.splitn(2, '=').map(str::trim);
+        if let (Some("edition"), Some(value)) = (key_value.next(), key_value.next()) {
+            edition = value.trim_matches('"').parse().ok();
+        }
+    }
+    let rest = if rest.trim().is_empty() { None } else { Some(rest) };
+    (rest, edition)
+}
+
+/// Turns a `// cargo-deps: a="1", b` comment into a `[dependencies]` table,
+/// dependencies without a version match any. Only the comments at the start of
+/// the file are looked at, like for the embedded manifest.
+fn cargo_deps(text: &str) -> Option<String> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.starts_with("#!") && !line.starts_with("#!["))
+        .take_while(|line| line.starts_with("//"))
+        .find_map(|line| {
+            line.strip_prefix("//")?.trim_start().strip_prefix("cargo-deps:").map(str::trim)
+        })?;
+    let mut manifest = String::from("[dependencies]\n");
+    for dep in line.split(',').map(str::trim).filter(|it| !it.is_empty()) {
+        let mut name_version = dep.splitn(2, '=').map(str::trim);
+        let name = name_version.next()?;
+        let version = name_version.next().unwrap_or("\"*\"");
+        manifest.push_str(&format!("{} = {}\n", name, version));
+    }
+    Some(manifest)
+}
+
+/// Scripts are usually written for the current edition, unless they use
+/// identifiers which later became keywords.
+fn infer_edition(text: &str) -> Edition {
+    const LATER_KEYWORDS: &[&str] = &["async", "await", "dyn", "try"];
+    const BINDERS: &[&str] = &["fn", "let", "mod", "struct", "enum", "trait", "const", "static"];
+
+    let code = text.lines().map(|line| line.split("//").next().unwrap_or_default());
+    let mut prev = "";
+    for line in code {
+        for word in line.split(|c: char| !c.is_alphanumeric() && c != '_') {
+            if word.is_empty() {
+                continue;
+            }
+            if LATER_KEYWORDS.contains(&word) && BINDERS.contains(&prev) {
+                return Edition::Edition2015;
+            }
+            prev = word;
+        }
+    }
+    Edition::CURRENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cargo_deps_in_header() {
+        let text =
+            "#!/usr/bin/env run-cargo-script\n// cargo-deps: time=\"0.1.25\", libc\nfn main() {}\n";
+        assert_eq!(
+            cargo_deps(text).as_deref(),
+            Some("[dependencies]\ntime = \"0.1.25\"\nlibc = \"*\"\n")
+        );
+
+        let text = "fn main() {}\n// cargo-deps: time=\"0.1.25\"\n";
+        assert_eq!(cargo_deps(text), None);
+    }
+
+    #[test]
+    fn embedded_manifest_edition() {
+        let text = "//! ```cargo\n//! [package]\n//! edition = \"2015\"\n//!\n//! [dependencies]\n\
+                    //! time = \"0.1.25\"\n//! ```\nfn main() {}\n";
+        let manifest = embedded_manifest(text).unwrap();
+        let (rest, edition) = split_package_edition(&manifest);
+        assert_eq!(rest.as_deref(), Some("[dependencies]\ntime = \"0.1.25\"\n"));
+        assert_eq!(edition, Some(Edition::Edition2015));
+    }
+
+    #[test]
+    fn manifest_escapes_path() {
+        let path =
+            if cfg!(windows) { "C:\\it's \"here\"\\main.rs" } else { "/it's \"here\"\\/main.rs" };
+        let file = DetachedFile {
+            path: AbsPathBuf::assert(path.into()),
+            edition: Edition::Edition2018,
+            manifest: Some("[dependencies]\nlibc = \"*\"\n".to_string()),
+        };
+        let manifest: toml::Value = toml::from_str(&file.manifest_text("main").unwrap()).unwrap();
+        assert_eq!(manifest["package"]["name"].as_str(), Some("main"));
+        assert_eq!(manifest["package"]["edition"].as_str(), Some("2018"));
+        assert_eq!(manifest["bin"][0]["path"].as_str(), Some(path));
+        assert_eq!(manifest["dependencies"]["libc"].as_str(), Some("*"));
+        assert!(manifest["workspace"].is_table());
+    }
+}
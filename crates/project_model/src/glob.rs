@@ -0,0 +1,74 @@
+//! Globs over relative paths, used to filter project discovery and the files
+//! the command line tools touch.
+//!
+//! Only `*` and `?` within a path segment and `**` for any number of segments
+//! are supported, which is what people write in practice.
+
+#[derive(Debug, Clone)]
+pub struct Glob {
+    segments: Vec<Vec<char>>,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Glob {
+        let segments = pattern
+            .split(|c| c == '/' || c == '\\')
+            .filter(|it| !it.is_empty() && *it != ".")
+            .map(|it| it.chars().collect())
+            .collect();
+        Glob { segments }
+    }
+
+    /// Whether the path with the given segments, relative to the searched
+    /// root, matches.
+    pub fn matches(&self, path: &[String]) -> bool {
+        matches_path(&self.segments, path)
+    }
+}
+
+fn matches_path(pattern: &[Vec<char>], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first.as_slice() == ['*', '*'] => {
+            (0..=path.len()).any(|skip| matches_path(rest, &path[skip..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path)) => {
+                let segment = segment.chars().collect::<Vec<_>>();
+                matches_segment(first, &segment) && matches_path(rest, path)
+            }
+            None => false,
+        },
+    }
+}
+
+fn matches_segment(pattern: &[char], segment: &[char]) -> bool {
+    match pattern.split_first() {
+        None => segment.is_empty(),
+        Some(('*', rest)) => {
+            (0..=segment.len()).any(|skip| matches_segment(rest, &segment[skip..]))
+        }
+        Some(('?', rest)) => !segment.is_empty() && matches_segment(rest, &segment[1..]),
+        Some((c, rest)) => segment.first() == Some(c) && matches_segment(rest, &segment[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        let segments = path.split('/').map(String::from).collect::<Vec<_>>();
+        Glob::new(pattern).matches(&segments)
+    }
+
+    #[test]
+    fn globs() {
+        assert!(matches("src/**/*.rs", "src/lib.rs"));
+        assert!(matches("src/**/*.rs", "src/a/b/c.rs"));
+        assert!(!matches("*.rs", "src/lib.rs"));
+        assert!(matches("src/?.rs", "src/a.rs"));
+        assert!(!matches("src/?.rs", "src/ab.rs"));
+        assert!(matches("**", "tests/data/x.rs"));
+    }
+}
@@ -6,7 +6,9 @@
 //! In this crate, we are conserned with "real world" project models.
 //!
 //! Specifically, here we have a representation for a Cargo project
-//! ([`CargoWorkspace`]) and for manually specified layout ([`ProjectJson`]).
+//! ([`CargoWorkspace`]), for manually specified layout ([`ProjectJson`]), for
+//! graphs exported by other build systems ([`BuildGraph`]) and for single files
+//! without any of these ([`DetachedFile`]).
 //!
 //! Roughly, the things we do here are:
 //!
@@ -16,12 +18,18 @@
 //! * Lowering of concrete model to a [`base_db::CrateGraph`]
 
 mod cargo_workspace;
+mod cargo_config;
 mod cfg_flag;
 mod project_json;
 mod sysroot;
 mod workspace;
 mod rustc_cfg;
 mod build_data;
+mod build_graph;
+mod detached_file;
+mod metadata_cache;
+mod progress;
+mod glob;
 
 use std::{
     fs::{self, read_dir, ReadDir},
@@ -34,36 +42,70 @@ use paths::{AbsPath, AbsPathBuf};
 use rustc_hash::FxHashSet;
 
 pub use crate::{
-    build_data::{BuildDataCollector, BuildDataResult},
+    build_data::{BuildDataCollector, BuildDataResult, CancellationToken, RustcInvocation},
+    build_graph::{BuildCrateKind, BuildGraph, BuildGraphData},
     cargo_workspace::{
-        CargoConfig, CargoWorkspace, Package, PackageData, PackageDependency, RustcSource, Target,
-        TargetData, TargetKind,
+        ArtifactDependency, CargoConfig, CargoMetadata, CargoWorkspace, Package, PackageData,
+        PackageDependency, RustcSource, Target, TargetData, TargetKind,
     },
-    project_json::{ProjectJson, ProjectJsonData},
+    detached_file::DetachedFile,
+    glob::Glob,
+    progress::ProgressEvent,
+    project_json::{ProjectJson, ProjectJsonData, RunnableTemplate, RunnableTemplateKind},
     sysroot::Sysroot,
-    workspace::{CfgOverrides, PackageRoot, ProjectWorkspace},
+    workspace::{CargoExtraTarget, CfgOverrides, CrateGraphWarning, PackageRoot, ProjectWorkspace},
 };
 
 pub use proc_macro_api::ProcMacroClient;
 
+/// Restricts [`ProjectManifest::discover_recursive`]. Globs are matched
+/// against directories relative to the searched one, like `crates/*` or
+/// `**/fixtures`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscoveryConfig {
+    /// Only projects in directories matching one of these are found, all are
+    /// if empty.
+    pub include: Vec<String>,
+    /// Directories matching one of these are not searched.
+    pub exclude: Vec<String>,
+    /// How many levels of subdirectories are searched, all if `None`.
+    pub max_depth: Option<usize>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub enum ProjectManifest {
     ProjectJson(AbsPathBuf),
     CargoToml(AbsPathBuf),
+    BuildGraph(AbsPathBuf),
 }
 
 impl ProjectManifest {
+    /// Build systems which generate a `rust-project.json` are free to name it
+    /// differently, so any `.json` file is accepted as one.
     pub fn from_manifest_file(path: AbsPathBuf) -> Result<ProjectManifest> {
-        if path.file_name().unwrap_or_default() == "rust-project.json" {
+        if path.file_name().unwrap_or_default() == "rust-build-graph.json" {
+            return Ok(ProjectManifest::BuildGraph(path));
+        }
+        if path.file_name().unwrap_or_default() == "rust-project.json"
+            || path.extension().unwrap_or_default() == "json"
+        {
             return Ok(ProjectManifest::ProjectJson(path));
         }
         if path.file_name().unwrap_or_default() == "Cargo.toml" {
             return Ok(ProjectManifest::CargoToml(path));
         }
-        bail!("project root must point to Cargo.toml or rust-project.json: {}", path.display())
+        bail!(
+            "project root must point to Cargo.toml, rust-project.json or rust-build-graph.json: {}",
+            path.display()
+        )
     }
 
+    /// Finds the single project at `path`, which is either a directory or a
+    /// manifest file, see [`ProjectManifest::from_manifest_file`].
     pub fn discover_single(path: &AbsPath) -> Result<ProjectManifest> {
+        if fs::metadata(path).map_or(false, |it| it.is_file()) {
+            return ProjectManifest::from_manifest_file(path.to_path_buf());
+        }
         let mut candidates = ProjectManifest::discover(path)?;
         let res = match candidates.pop() {
             None => bail!("no projects"),
@@ -80,6 +122,9 @@ impl ProjectManifest {
         if let Some(project_json) = find_in_parent_dirs(path, "rust-project.json") {
             return Ok(vec![ProjectManifest::ProjectJson(project_json)]);
         }
+        if let Some(build_graph) = find_in_parent_dirs(path, "rust-build-graph.json") {
+            return Ok(vec![ProjectManifest::BuildGraph(build_graph)]);
+        }
         return find_cargo_toml(path)
             .map(|paths| paths.into_iter().map(ProjectManifest::CargoToml).collect());
 
@@ -119,6 +164,58 @@ impl ProjectManifest {
         }
     }
 
+    /// Finds all `rust-project.json`, `rust-build-graph.json` and `Cargo.toml`
+    /// files in `path` and its subdirectories, skipping hidden and `target`
+    /// directories. Directories with one of the JSON files are not searched any
+    /// further.
+    pub fn discover_recursive(
+        path: &AbsPath,
+        config: &DiscoveryConfig,
+    ) -> io::Result<Vec<ProjectManifest>> {
+        let include = config.include.iter().map(|it| Glob::new(it)).collect::<Vec<_>>();
+        let exclude = config.exclude.iter().map(|it| Glob::new(it)).collect::<Vec<_>>();
+
+        let mut res = Vec::new();
+        let mut worklist = vec![(path.to_path_buf(), Vec::<String>::new())];
+        while let Some((dir, segments)) = worklist.pop() {
+            if include.is_empty() || include.iter().any(|it| it.matches(&segments)) {
+                let project_json = dir.join("rust-project.json");
+                if project_json.exists() {
+                    res.push(ProjectManifest::ProjectJson(project_json));
+                    continue;
+                }
+                let build_graph = dir.join("rust-build-graph.json");
+                if build_graph.exists() {
+                    res.push(ProjectManifest::BuildGraph(build_graph));
+                    continue;
+                }
+                let cargo_toml = dir.join("Cargo.toml");
+                if cargo_toml.exists() {
+                    res.push(ProjectManifest::CargoToml(cargo_toml));
+                }
+            }
+            if config.max_depth.map_or(false, |max_depth| segments.len() >= max_depth) {
+                continue;
+            }
+            for entry in read_dir(&dir)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                let is_hidden = name.to_string_lossy().starts_with('.');
+                if is_hidden || name == "target" || !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                let mut child = segments.clone();
+                child.push(name.to_string_lossy().into_owned());
+                if exclude.iter().any(|it| it.matches(&child)) {
+                    continue;
+                }
+                worklist.push((dir.join(name), child));
+            }
+        }
+        res.sort();
+        Ok(res)
+    }
+
     pub fn discover_all(paths: &[AbsPathBuf]) -> Vec<ProjectManifest> {
         let mut res = paths
             .iter()
@@ -0,0 +1,241 @@
+//! Caches the output of `cargo metadata`, which takes seconds for large
+//! workspaces, so that it only runs again when the workspace changed.
+//!
+//! The cache lives in the target directory of the workspace. As that is only
+//! known from the metadata, it is looked up in the usual places: the
+//! `CARGO_TARGET_DIR`, the configured `build.target-dir` and the `target`
+//! directories next to the manifest and its ancestors.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{format_err, Result};
+use paths::AbsPath;
+use serde::{Deserialize, Serialize};
+
+use crate::{cargo_workspace::cargo_config_target_dir, rustc_cfg::rustc_version};
+
+/// Bumped whenever the format of [`MetadataCache`] changes.
+const CACHE_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct MetadataCache {
+    version: u32,
+    /// Covers the command, the toolchain and the contents of the `inputs`.
+    fingerprint: u64,
+    /// The manifests of the local packages, the lockfile and the cargo config
+    /// files which could apply, whether they exist or not.
+    inputs: Vec<PathBuf>,
+    /// Directories containing members, new members matched by a glob in
+    /// `workspace.members` only show up in their listing.
+    dirs: Vec<PathBuf>,
+    json: String,
+}
+
+/// The cached output of `cargo metadata` for `cargo_toml`, if none of its
+/// inputs changed since it was stored.
+pub(crate) fn load(cargo_toml: &AbsPath, command: &str) -> Option<String> {
+    let file_name = cache_file_name(cargo_toml, command);
+    let rustc_version = rustc_version(cargo_toml.parent())?;
+    candidate_target_dirs(cargo_toml).into_iter().find_map(|target_dir| {
+        let text = fs::read_to_string(target_dir.join("rust-analyzer").join(&file_name)).ok()?;
+        let cache: MetadataCache = serde_json::from_str(&text).ok()?;
+        if cache.version != CACHE_VERSION
+            || cache.fingerprint != fingerprint(command, &rustc_version, &cache.inputs, &cache.dirs)
+        {
+            return None;
+        }
+        log::info!("reusing cached metadata for {}", cargo_toml.display());
+        Some(cache.json)
+    })
+}
+
+pub(crate) fn store(
+    cargo_toml: &AbsPath,
+    command: &str,
+    json: &str,
+    meta: &cargo_metadata::Metadata,
+) -> Result<()> {
+    let mut inputs = vec![cargo_toml.as_ref().to_path_buf()];
+    inputs.extend(
+        meta.packages
+            .iter()
+            .filter(|package| package.source.is_none())
+            .map(|package| AsRef::<Path>::as_ref(&package.manifest_path).to_path_buf()),
+    );
+    let workspace_root: &Path = meta.workspace_root.as_ref();
+    inputs.push(workspace_root.join("Cargo.toml"));
+    inputs.push(workspace_root.join("Cargo.lock"));
+    inputs.extend(cargo_config_files(cargo_toml));
+    inputs.sort();
+    inputs.dedup();
+
+    let mut dirs = meta
+        .packages
+        .iter()
+        .filter(|package| meta.workspace_members.contains(&package.id))
+        .filter_map(|package| package.manifest_path.parent()?.parent())
+        .map(|dir| AsRef::<Path>::as_ref(dir).to_path_buf())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs.dedup();
+
+    let rustc_version = rustc_version(cargo_toml.parent())
+        .ok_or_else(|| format_err!("failed to get rustc version"))?;
+    let cache = MetadataCache {
+        version: CACHE_VERSION,
+        fingerprint: fingerprint(command, &rustc_version, &inputs, &dirs),
+        inputs,
+        dirs,
+        json: json.to_string(),
+    };
+    let dir = AsRef::<Path>::as_ref(&meta.target_directory).join("rust-analyzer");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(cache_file_name(cargo_toml, command)), serde_json::to_string(&cache)?)?;
+    Ok(())
+}
+
+/// Several manifests of a workspace, or the same one with different
+/// features, share the target directory.
+fn cache_file_name(cargo_toml: &AbsPath, command: &str) -> String {
+    let mut hasher = StableHasher::new();
+    hasher.write_path(cargo_toml.as_ref());
+    hasher.write(command.as_bytes());
+    format!("metadata-{:016x}.json", hasher.finish())
+}
+
+/// The config files cargo reads for `cargo_toml`: the ones in the `.cargo`
+/// directories of the manifest directory and its ancestors, and in
+/// `CARGO_HOME`.
+fn cargo_config_files(cargo_toml: &AbsPath) -> Vec<PathBuf> {
+    let mut dirs =
+        cargo_toml.as_ref().ancestors().skip(1).map(|it| it.join(".cargo")).collect::<Vec<_>>();
+    let cargo_home = env::var_os("CARGO_HOME").map(PathBuf::from).or_else(|| {
+        env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+            .map(|it| PathBuf::from(it).join(".cargo"))
+    });
+    dirs.extend(cargo_home);
+    dirs.iter().flat_map(|dir| vec![dir.join("config"), dir.join("config.toml")]).collect()
+}
+
+fn candidate_target_dirs(cargo_toml: &AbsPath) -> Vec<PathBuf> {
+    let mut res = Vec::new();
+    if let Some(dir) = env::var_os("CARGO_TARGET_DIR") {
+        let dir = PathBuf::from(dir);
+        match cargo_toml.parent() {
+            Some(parent) if dir.is_relative() => res.push(parent.as_ref().join(dir)),
+            _ => res.push(dir),
+        }
+    } else if let Some(dir) = cargo_config_target_dir(cargo_toml) {
+        res.push(dir.into());
+    }
+    let ancestors = cargo_toml.as_ref().ancestors().skip(1);
+    res.extend(ancestors.map(|it| it.join("target")).filter(|it| it.is_dir()));
+    res
+}
+
+fn fingerprint(command: &str, rustc_version: &str, inputs: &[PathBuf], dirs: &[PathBuf]) -> u64 {
+    let mut hasher = StableHasher::new();
+    hasher.write(command.as_bytes());
+    hasher.write(rustc_version.as_bytes());
+    for input in inputs {
+        hasher.write_path(input);
+        match fs::read(input) {
+            Ok(contents) => hasher.write(&contents),
+            Err(_) => hasher.write(&[]),
+        }
+    }
+    for dir in dirs {
+        let mut entries = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.file_name())
+            .collect::<Vec<_>>();
+        entries.sort();
+        hasher.write_path(dir);
+        for entry in entries {
+            hasher.write_path(entry.as_ref());
+        }
+    }
+    hasher.finish()
+}
+
+/// 64-bit FNV-1a. The hashes are stored on disk, which rules out
+/// `DefaultHasher`, whose algorithm may change between Rust versions.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> StableHasher {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// The length is hashed as well, so that consecutive writes can't be
+    /// confused with a different split of the same bytes.
+    fn write(&mut self, bytes: &[u8]) {
+        let len = bytes.len() as u64;
+        for &byte in len.to_le_bytes().iter().chain(bytes) {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_path(&mut self, path: &Path) {
+        self.write(path.to_string_lossy().as_bytes())
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paths::AbsPathBuf;
+
+    use super::*;
+
+    #[test]
+    fn hits_and_invalidation() {
+        let root = env::temp_dir().join(format!("ra-metadata-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("target")).unwrap();
+        let root = AbsPathBuf::assert(root);
+        let cargo_toml = root.join("Cargo.toml");
+        fs::write(&cargo_toml, "[package]\nname = \"foo\"\n").unwrap();
+
+        let meta: cargo_metadata::Metadata = serde_json::from_value(serde_json::json!({
+            "packages": [],
+            "workspace_members": [],
+            "resolve": null,
+            "workspace_root": root.display().to_string(),
+            "target_directory": root.join("target").display().to_string(),
+            "version": 1,
+        }))
+        .unwrap();
+        let store = || store(&cargo_toml, "cargo metadata", "{}", &meta).unwrap();
+
+        store();
+        assert_eq!(load(&cargo_toml, "cargo metadata").as_deref(), Some("{}"));
+        assert_eq!(load(&cargo_toml, "cargo metadata --all-features"), None);
+
+        fs::write(&cargo_toml, "[package]\nname = \"bar\"\n").unwrap();
+        assert_eq!(load(&cargo_toml, "cargo metadata"), None);
+
+        store();
+        fs::write(root.join("Cargo.lock"), "version = 3\n").unwrap();
+        assert_eq!(load(&cargo_toml, "cargo metadata"), None);
+
+        store();
+        fs::create_dir_all(root.join(".cargo")).unwrap();
+        fs::write(root.join(".cargo/config.toml"), "[build]\ntarget = \"wasm32-wasi\"\n").unwrap();
+        assert_eq!(load(&cargo_toml, "cargo metadata"), None);
+
+        store();
+        assert_eq!(load(&cargo_toml, "cargo metadata").as_deref(), Some("{}"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
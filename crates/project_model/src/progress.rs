@@ -0,0 +1,34 @@
+//! Progress reported while a workspace is loaded.
+//!
+//! Consumers get typed events instead of messages meant for humans, so that
+//! tools wrapping rust-analyzer don't need to parse strings. The `Display`
+//! impl produces the short messages shown by the language server.
+
+use std::fmt;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// `cargo metadata` is running.
+    FetchingMetadata,
+    /// A crate was compiled while running build scripts and building proc
+    /// macros.
+    BuildingCrate { crate_name: String },
+    /// The compiler emitted a message for a crate while running build scripts.
+    CompilerMessage { crate_name: String },
+    /// `current` out of `total` files of the workspace have been loaded.
+    LoadingFiles { current: usize, total: usize },
+}
+
+impl fmt::Display for ProgressEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgressEvent::FetchingMetadata => write!(f, "metadata"),
+            ProgressEvent::BuildingCrate { crate_name } => write!(f, "metadata {}", crate_name),
+            ProgressEvent::CompilerMessage { crate_name } => write!(f, "{}", crate_name),
+            ProgressEvent::LoadingFiles { current, total } => write!(f, "{}/{}", current, total),
+        }
+    }
+}
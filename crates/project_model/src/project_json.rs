@@ -37,6 +37,40 @@ pub struct Crate {
     pub(crate) is_workspace_member: bool,
     pub(crate) include: Vec<AbsPathBuf>,
     pub(crate) exclude: Vec<AbsPathBuf>,
+    pub(crate) runnables: Vec<RunnableTemplate>,
+}
+
+/// A command which checks, runs or tests a crate, for build systems where
+/// `cargo` can't do that.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RunnableTemplate {
+    pub kind: RunnableTemplateKind,
+    pub program: String,
+    args: Vec<String>,
+    pub cwd: AbsPathBuf,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunnableTemplateKind {
+    Check,
+    Run,
+    Test,
+}
+
+impl RunnableTemplate {
+    /// Substitutes `{test_id}` in the arguments. Without a `test_id`,
+    /// arguments mentioning it are dropped, so that all tests are run.
+    pub fn args(&self, test_id: Option<&str>) -> Vec<String> {
+        self.args
+            .iter()
+            .filter_map(|arg| match test_id {
+                Some(test_id) => Some(arg.replace("{test_id}", test_id)),
+                None if arg.contains("{test_id}") => None,
+                None => Some(arg.clone()),
+            })
+            .collect()
+    }
 }
 
 impl ProjectJson {
@@ -48,6 +82,7 @@ impl ProjectJson {
     /// * `data` - The parsed contents of `rust-project.json`, or project json that's passed via
     ///            configuration.
     pub fn new(base: &AbsPath, data: ProjectJsonData) -> ProjectJson {
+        let cfg_groups = data.cfg_groups;
         ProjectJson {
             sysroot_src: data.sysroot_src.map(|it| base.join(it)),
             project_root: base.to_path_buf(),
@@ -73,6 +108,24 @@ impl ProjectJson {
                         None => (vec![root_module.parent().unwrap().to_path_buf()], Vec::new()),
                     };
 
+                    let mut cfg = crate_data.cfg;
+                    for group in &crate_data.cfg_groups {
+                        match cfg_groups.get(group) {
+                            Some(cfgs) => cfg.extend(cfgs.iter().cloned()),
+                            None => log::error!("unknown cfg group `{}`", group),
+                        }
+                    }
+                    let runnables = crate_data
+                        .runnables
+                        .into_iter()
+                        .map(|it| RunnableTemplate {
+                            kind: it.kind,
+                            program: it.program,
+                            args: it.args,
+                            cwd: it.cwd.map_or_else(|| base.to_path_buf(), |it| base.join(it)),
+                        })
+                        .collect();
+
                     Crate {
                         display_name: crate_data
                             .display_name
@@ -87,7 +140,7 @@ impl ProjectJson {
                                 name: dep_data.name,
                             })
                             .collect::<Vec<_>>(),
-                        cfg: crate_data.cfg,
+                        cfg,
                         target: crate_data.target,
                         env: crate_data.env,
                         proc_macro_dylib_path: crate_data
@@ -96,6 +149,7 @@ impl ProjectJson {
                         is_workspace_member,
                         include,
                         exclude,
+                        runnables,
                     }
                 })
                 .collect::<Vec<_>>(),
@@ -113,11 +167,25 @@ impl ProjectJson {
     pub fn path(&self) -> &AbsPath {
         &self.project_root
     }
+    /// Returns the crate whose root module is `root`.
+    pub fn crate_by_root(&self, root: &AbsPath) -> Option<&Crate> {
+        self.crates.iter().find(|krate| krate.root_module == *root)
+    }
+}
+
+impl Crate {
+    /// Returns the commands to check, run or test this crate with.
+    pub fn runnables(&self) -> &[RunnableTemplate] {
+        &self.runnables
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ProjectJsonData {
     sysroot_src: Option<PathBuf>,
+    /// Named sets of cfgs which crates can refer to.
+    #[serde(default)]
+    cfg_groups: FxHashMap<String, Vec<CfgFlag>>,
     crates: Vec<CrateData>,
 }
 
@@ -129,17 +197,29 @@ struct CrateData {
     deps: Vec<DepData>,
     #[serde(default)]
     cfg: Vec<CfgFlag>,
+    #[serde(default)]
+    cfg_groups: Vec<String>,
     target: Option<String>,
     #[serde(default)]
     env: FxHashMap<String, String>,
     proc_macro_dylib_path: Option<PathBuf>,
     is_workspace_member: Option<bool>,
     source: Option<CrateSource>,
+    #[serde(default)]
+    runnables: Vec<RunnableData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct RunnableData {
+    kind: RunnableTemplateKind,
+    program: String,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename = "edition")]
-enum EditionData {
+pub(crate) enum EditionData {
     #[serde(rename = "2015")]
     Edition2015,
     #[serde(rename = "2018")]
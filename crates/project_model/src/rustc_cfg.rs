@@ -1,33 +1,121 @@
 //! Runs `rustc --print cfg` to get built-in cfg flags.
 
-use std::process::Command;
+use std::{path::Path, process::Command, sync::Mutex};
 
-use anyhow::Result;
-use paths::AbsPath;
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use paths::{AbsPath, AbsPathBuf};
+use rustc_hash::FxHashMap;
 
 use crate::{cfg_flag::CfgFlag, utf8_stdout};
 
-pub(crate) fn get(cargo_toml: Option<&AbsPath>, target: Option<&str>) -> Vec<CfgFlag> {
+/// The cfgs printed for a target don't change unless the toolchain does, so
+/// they are keyed by the directory the toolchain is picked in, the target and
+/// the `rustc` version there. Whether `cargo` ran offline is part of the key
+/// too, as a `--frozen` run can fail where an online one succeeds.
+type CacheKey = (Option<AbsPathBuf>, Option<String>, String, bool);
+
+static CACHE: Lazy<Mutex<FxHashMap<CacheKey, Vec<CfgFlag>>>> = Lazy::new(Default::default);
+
+/// With `offline`, `cargo` is run with `--frozen`.
+pub(crate) fn get(
+    cargo_toml: Option<&AbsPath>,
+    target: Option<&str>,
+    offline: bool,
+) -> Vec<CfgFlag> {
     let _p = profile::span("rustc_cfg::get");
-    let mut res = Vec::with_capacity(6 * 2 + 1);
+    let dir = cargo_toml.map(|it| it.parent().unwrap());
+    let target = target.map(|it| target_arg(dir, it));
 
-    // Some nightly-only cfgs, which are required for stdlib
-    res.push(CfgFlag::Atom("target_thread_local".into()));
-    for &ty in ["8", "16", "32", "64", "cas", "ptr"].iter() {
-        for &key in ["target_has_atomic", "target_has_atomic_load_store"].iter() {
-            res.push(CfgFlag::KeyValue { key: key.to_string(), value: ty.into() });
+    let key = rustc_version(dir)
+        .map(|version| (dir.map(AbsPath::to_path_buf), target.clone(), version, offline));
+    if let Some(key) = &key {
+        if let Some(cfgs) = CACHE.lock().unwrap().get(key) {
+            return cfgs.clone();
         }
     }
 
-    match get_rust_cfgs(cargo_toml, target) {
-        Ok(rustc_cfgs) => res.extend(rustc_cfgs.lines().map(|it| it.parse().unwrap())),
-        Err(e) => log::error!("failed to get rustc cfgs: {:#}", e),
+    let mut res = Vec::with_capacity(6 * 2 + 1);
+    let rustc_cfgs = match get_rust_cfgs(cargo_toml, target.as_deref(), offline) {
+        Ok(it) => it,
+        Err(e) => {
+            match &target {
+                Some(target) => {
+                    log::error!("failed to get rustc cfgs for target `{}`: {:#}", target, e)
+                }
+                None => log::error!("failed to get rustc cfgs: {:#}", e),
+            }
+            return required_nightly_cfgs(&[]);
+        }
+    };
+    for line in rustc_cfgs.lines() {
+        match line.parse::<CfgFlag>() {
+            Ok(it) => res.push(it),
+            Err(e) => log::warn!("skipping cfg printed by rustc: {}", e),
+        }
     }
+    res.extend(required_nightly_cfgs(&res));
 
+    if let Some(key) = key {
+        CACHE.lock().unwrap().insert(key, res.clone());
+    }
     res
 }
 
-fn get_rust_cfgs(cargo_toml: Option<&AbsPath>, target: Option<&str>) -> Result<String> {
+/// Some nightly-only cfgs, which are required for stdlib. Recent compilers
+/// print the atomic ones themselves, and only for the widths the target
+/// actually supports, so they are only assumed when missing from `printed`.
+fn required_nightly_cfgs(printed: &[CfgFlag]) -> Vec<CfgFlag> {
+    let mut res = Vec::new();
+    if !printed.iter().any(|it| matches!(it, CfgFlag::Atom(it) if it == "target_thread_local")) {
+        res.push(CfgFlag::Atom("target_thread_local".into()));
+    }
+    let has_atomic_cfgs = printed.iter().any(
+        |it| matches!(it, CfgFlag::KeyValue { key, .. } if key.starts_with("target_has_atomic")),
+    );
+    if !has_atomic_cfgs {
+        for &ty in ["8", "16", "32", "64", "cas", "ptr"].iter() {
+            for &key in ["target_has_atomic", "target_has_atomic_load_store"].iter() {
+                res.push(CfgFlag::KeyValue { key: key.to_string(), value: ty.into() });
+            }
+        }
+    }
+    res
+}
+
+/// Custom targets are given as paths to a target specification, which are
+/// made absolute so that `cargo` and `rustc` agree on what they refer to.
+fn target_arg(dir: Option<&AbsPath>, target: &str) -> String {
+    let path = Path::new(target);
+    match dir {
+        Some(dir) if target.ends_with(".json") && path.is_relative() => {
+            dir.join(path).normalize().display().to_string()
+        }
+        _ => target.to_string(),
+    }
+}
+
+/// The `rustc -vV` output of the toolchain picked in `dir`.
+pub(crate) fn rustc_version(dir: Option<&AbsPath>) -> Option<String> {
+    let mut cmd = Command::new(toolchain::rustc());
+    cmd.arg("-vV");
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    match utf8_stdout(cmd) {
+        Ok(it) => Some(it),
+        Err(e) => {
+            log::warn!("failed to get rustc version, not caching: {:#}", e);
+            None
+        }
+    }
+}
+
+fn get_rust_cfgs(
+    cargo_toml: Option<&AbsPath>,
+    target: Option<&str>,
+    offline: bool,
+) -> Result<String> {
     let cargo_rust_cfgs = match cargo_toml {
         Some(cargo_toml) => {
             let mut cargo_config = Command::new(toolchain::cargo());
@@ -38,6 +126,9 @@ fn get_rust_cfgs(cargo_toml: Option<&AbsPath>, target: Option<&str>) -> Result<S
             if let Some(target) = target {
                 cargo_config.args(&["--target", target]);
             }
+            if offline {
+                cargo_config.arg("--frozen");
+            }
             utf8_stdout(cargo_config).ok()
         }
         None => None,
@@ -48,10 +139,16 @@ fn get_rust_cfgs(cargo_toml: Option<&AbsPath>, target: Option<&str>) -> Result<S
             // using unstable cargo features failed, fall back to using plain rustc
             let mut cmd = Command::new(toolchain::rustc());
             cmd.args(&["--print", "cfg", "-O"]);
+            if let Some(cargo_toml) = cargo_toml {
+                cmd.current_dir(cargo_toml.parent().unwrap());
+            }
             if let Some(target) = target {
                 cmd.args(&["--target", target]);
             }
-            utf8_stdout(cmd)
+            utf8_stdout(cmd).with_context(|| match target {
+                Some(target) => format!("is the `{}` target installed?", target),
+                None => "failed to run rustc".to_string(),
+            })
         }
     }
 }
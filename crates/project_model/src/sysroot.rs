@@ -6,11 +6,11 @@
 
 use std::{convert::TryFrom, env, fs, ops, path::PathBuf, process::Command};
 
-use anyhow::{format_err, Result};
+use anyhow::{bail, format_err, Context, Result};
 use la_arena::{Arena, Idx};
 use paths::{AbsPath, AbsPathBuf};
 
-use crate::utf8_stdout;
+use crate::{utf8_stdout, CargoConfig};
 
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct Sysroot {
@@ -48,21 +48,23 @@ impl Sysroot {
         self.crates.iter().map(|(id, _data)| id)
     }
 
-    pub fn discover(cargo_toml: &AbsPath) -> Result<Sysroot> {
+    /// Finds the sources of the sysroot, in this order: the `sysroot_src` of
+    /// `config`, `RUST_SRC_PATH`, the `sysroot` of `config` and the sysroot
+    /// of the toolchain used in the directory of `cargo_toml`.
+    pub fn discover(cargo_toml: &AbsPath, config: &CargoConfig) -> Result<Sysroot> {
         log::debug!("Discovering sysroot for {}", cargo_toml.display());
         let current_dir = cargo_toml.parent().ok_or_else(|| {
             format_err!("Failed to find the parent directory for {}", cargo_toml.display())
         })?;
-        let sysroot_dir = discover_sysroot_dir(current_dir)?;
-        let sysroot_src_dir = discover_sysroot_src_dir(&sysroot_dir, current_dir)?;
+        let sysroot_src_dir = discover_sysroot_src_dir(current_dir, config)?;
         let res = Sysroot::load(&sysroot_src_dir)?;
         Ok(res)
     }
 
-    pub fn discover_rustc(cargo_toml: &AbsPath) -> Option<AbsPathBuf> {
+    pub fn discover_rustc(cargo_toml: &AbsPath, config: &CargoConfig) -> Option<AbsPathBuf> {
         log::debug!("Discovering rustc source for {}", cargo_toml.display());
         let current_dir = cargo_toml.parent().unwrap();
-        discover_sysroot_dir(current_dir).ok().and_then(|sysroot_dir| get_rustc_src(&sysroot_dir))
+        sysroot_dir(current_dir, config).ok().and_then(|sysroot_dir| get_rustc_src(&sysroot_dir))
     }
 
     pub fn load(sysroot_src_dir: &AbsPath) -> Result<Sysroot> {
@@ -126,6 +128,18 @@ impl Sysroot {
     }
 }
 
+fn sysroot_dir(current_dir: &AbsPath, config: &CargoConfig) -> Result<AbsPathBuf> {
+    match &config.sysroot {
+        Some(sysroot) => {
+            if !fs::metadata(sysroot).map_or(false, |it| it.is_dir()) {
+                bail!("the configured sysroot `{}` is not a directory", sysroot.display());
+            }
+            Ok(sysroot.clone())
+        }
+        None => discover_sysroot_dir(current_dir),
+    }
+}
+
 fn discover_sysroot_dir(current_dir: &AbsPath) -> Result<AbsPathBuf> {
     let mut rustc = Command::new(toolchain::rustc());
     rustc.current_dir(current_dir).args(&["--print", "sysroot"]);
@@ -134,40 +148,65 @@ fn discover_sysroot_dir(current_dir: &AbsPath) -> Result<AbsPathBuf> {
     Ok(AbsPathBuf::assert(PathBuf::from(stdout)))
 }
 
-fn discover_sysroot_src_dir(
-    sysroot_path: &AbsPathBuf,
-    current_dir: &AbsPath,
-) -> Result<AbsPathBuf> {
+fn discover_sysroot_src_dir(current_dir: &AbsPath, config: &CargoConfig) -> Result<AbsPathBuf> {
+    if let Some(path) = &config.sysroot_src {
+        check_sysroot_src_dir(path).with_context(|| {
+            format!("the configured sysroot source directory `{}` is invalid", path.display())
+        })?;
+        log::debug!("Using the configured sysroot sources: {}", path.display());
+        return Ok(path.clone());
+    }
+
     if let Ok(path) = env::var("RUST_SRC_PATH") {
         let path = AbsPathBuf::try_from(path.as_str())
             .map_err(|path| format_err!("RUST_SRC_PATH must be absolute: {}", path.display()))?;
-        let core = path.join("core");
-        if fs::metadata(&core).is_ok() {
-            log::debug!("Discovered sysroot by RUST_SRC_PATH: {}", path.display());
-            return Ok(path);
-        }
-        log::debug!("RUST_SRC_PATH is set, but is invalid (no core: {:?}), ignoring", core);
+        check_sysroot_src_dir(&path)
+            .with_context(|| format!("RUST_SRC_PATH `{}` is invalid", path.display()))?;
+        log::debug!("Discovered sysroot by RUST_SRC_PATH: {}", path.display());
+        return Ok(path);
     }
 
-    get_rust_src(sysroot_path)
+    let sysroot_path = sysroot_dir(current_dir, config)?;
+    let explicit = config.sysroot.is_some();
+    get_rust_src(&sysroot_path)
         .or_else(|| {
+            // Only install the sources into the toolchain rustup manages, and
+            // only if we may download them.
+            if explicit || config.offline {
+                return None;
+            }
             let mut rustup = Command::new(toolchain::rustup());
             rustup.current_dir(current_dir).args(&["component", "add", "rust-src"]);
             utf8_stdout(rustup).ok()?;
-            get_rust_src(sysroot_path)
+            get_rust_src(&sysroot_path)
         })
         .ok_or_else(|| {
             format_err!(
                 "\
 can't load standard library from sysroot
 {}
-(discovered via `rustc --print sysroot`)
+({})
 try installing the Rust source the same way you installed rustc",
                 sysroot_path.display(),
+                if explicit {
+                    "from the configuration"
+                } else {
+                    "discovered via `rustc --print sysroot`"
+                },
             )
         })
 }
 
+fn check_sysroot_src_dir(path: &AbsPath) -> Result<()> {
+    if !fs::metadata(path).map_or(false, |it| it.is_dir()) {
+        bail!("no such directory");
+    }
+    if fs::metadata(path.join("core")).is_err() {
+        bail!("no `core` directory in it, it should point to the `library` directory of the Rust sources");
+    }
+    Ok(())
+}
+
 fn get_rustc_src(sysroot_path: &AbsPath) -> Option<AbsPathBuf> {
     let rustc_src = sysroot_path.join("lib/rustlib/rustc-src/rust/compiler/rustc/Cargo.toml");
     log::debug!("Checking for rustc source code: {}", rustc_src.display());
@@ -1,11 +1,13 @@
 //! Handles lowering of build-system specific workspace information (`cargo
-//! metadata` or `rust-project.json`) into representation stored in the salsa
-//! database -- `CrateGraph`.
+//! metadata`, `rust-project.json` or `rust-build-graph.json`) into
+//! representation stored in the salsa database -- `CrateGraph`.
 
-use std::{collections::VecDeque, fmt, fs, process::Command};
+use std::{collections::VecDeque, fmt, fs, io, process::Command};
 
-use anyhow::{format_err, Context, Result};
-use base_db::{CrateDisplayName, CrateGraph, CrateId, CrateName, Edition, Env, FileId, ProcMacro};
+use anyhow::{Context, Result};
+use base_db::{
+    CrateDisplayName, CrateGraph, CrateId, CrateName, Edition, Env, FileId, PackageInfo, ProcMacro,
+};
 use cargo_workspace::DepKind;
 use cfg::{CfgDiff, CfgOptions};
 use paths::{AbsPath, AbsPathBuf};
@@ -16,10 +18,11 @@ use crate::{
     build_data::{BuildDataResult, PackageBuildData, WorkspaceBuildData},
     cargo_workspace,
     cfg_flag::CfgFlag,
+    detached_file::DetachedFile,
     rustc_cfg,
     sysroot::SysrootCrate,
-    utf8_stdout, BuildDataCollector, CargoConfig, CargoWorkspace, ProjectJson, ProjectManifest,
-    Sysroot, TargetKind,
+    utf8_stdout, BuildCrateKind, BuildDataCollector, BuildGraph, CargoConfig, CargoWorkspace,
+    Package, ProgressEvent, ProjectJson, ProjectManifest, Sysroot, TargetKind,
 };
 
 pub type CfgOverrides = FxHashMap<String, CfgDiff>;
@@ -35,6 +38,73 @@ pub struct PackageRoot {
     pub exclude: Vec<AbsPathBuf>,
 }
 
+/// A problem found while lowering a workspace into a [`CrateGraph`]. The
+/// graph is still usable, but lacks some crates, dependencies or proc macros.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CrateGraphWarning {
+    /// None of the targets of the package could be loaded.
+    PackageSkipped { package: String },
+    /// The root module of the crate could not be loaded.
+    MissingTarget { krate: String, root: AbsPathBuf },
+    /// The proc-macro crate wasn't built, or its dylib contains no macros.
+    UnresolvedProcMacro { krate: String, dylib: Option<AbsPathBuf> },
+    /// The crate has several dependencies with the same name, only one of
+    /// them can be referred to.
+    DuplicateDependency { krate: String, name: String },
+    /// The dev-dependency depends on the crate itself, so the crate can only
+    /// see it in integration tests, benches and examples.
+    DevDependencyCycle { krate: String, dependency: String },
+    /// The package uses `rustc_private`, but the sources of the compiler
+    /// crates weren't found.
+    RustcSourceMissing { package: String },
+}
+
+impl fmt::Display for CrateGraphWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrateGraphWarning::PackageSkipped { package } => {
+                write!(f, "package `{}` was skipped, none of its targets could be loaded", package)
+            }
+            CrateGraphWarning::MissingTarget { krate, root } => {
+                write!(f, "failed to load `{}` of crate `{}`", root.display(), krate)
+            }
+            CrateGraphWarning::UnresolvedProcMacro { krate, dylib: None } => {
+                write!(f, "proc-macro crate `{}` was not built", krate)
+            }
+            CrateGraphWarning::UnresolvedProcMacro { krate, dylib: Some(dylib) } => {
+                write!(f, "no proc macros loaded from `{}` for `{}`", dylib.display(), krate)
+            }
+            CrateGraphWarning::DuplicateDependency { krate, name } => {
+                write!(f, "crate `{}` has several dependencies named `{}`", krate, name)
+            }
+            CrateGraphWarning::DevDependencyCycle { krate, dependency } => write!(
+                f,
+                "dev-dependency `{}` of `{}` depends on it, skipped for the library",
+                dependency, krate
+            ),
+            CrateGraphWarning::RustcSourceMissing { package } => write!(
+                f,
+                "package `{}` uses `rustc_private`, but the compiler crates weren't found, \
+                 install the `rustc-dev` component",
+                package
+            ),
+        }
+    }
+}
+
+/// A cargo workspace loaded for one of the [`CargoConfig::extra_targets`].
+///
+/// Its crates are added to the crate graph next to the crates of the default
+/// target, marked with the target.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CargoExtraTarget {
+    pub target: String,
+    /// Resolved with `--filter-platform` for `target`, which can pull in
+    /// packages the default target doesn't use.
+    pub cargo: CargoWorkspace,
+    pub rustc_cfg: Vec<CfgFlag>,
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub enum ProjectWorkspace {
     /// Project workspace was discovered by running `cargo metadata` and `rustc --print sysroot`.
@@ -49,9 +119,14 @@ pub enum ProjectWorkspace {
         /// different target.
         rustc_cfg: Vec<CfgFlag>,
         cfg_overrides: CfgOverrides,
+        /// The workspace as resolved for each of the extra targets.
+        extra_targets: Vec<CargoExtraTarget>,
     },
     /// Project workspace was manually specified using a `rust-project.json` file.
     Json { project: ProjectJson, sysroot: Option<Sysroot>, rustc_cfg: Vec<CfgFlag> },
+    /// Project workspace was exported by another build system, like Bazel or
+    /// Buck, as a `rust-build-graph.json` file.
+    BuildGraph { graph: BuildGraph, sysroot: Option<Sysroot>, rustc_cfg: Vec<CfgFlag> },
 
     // FIXME: The primary limitation of this approach is that the set of detached files needs to be fixed at the beginning.
     // That's not the end user experience we should strive for.
@@ -63,14 +138,21 @@ pub enum ProjectWorkspace {
     // //
     /// Project with a set of disjoint files, not belonging to any particular workspace.
     /// Backed by basic sysroot crates for basic completion and highlighting.
-    DetachedFiles { files: Vec<AbsPathBuf>, sysroot: Sysroot, rustc_cfg: Vec<CfgFlag> },
+    DetachedFiles { files: Vec<DetachedFile>, sysroot: Sysroot, rustc_cfg: Vec<CfgFlag> },
 }
 
 impl fmt::Debug for ProjectWorkspace {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Make sure this isn't too verbose.
         match self {
-            ProjectWorkspace::Cargo { cargo, sysroot, rustc, rustc_cfg, cfg_overrides } => f
+            ProjectWorkspace::Cargo {
+                cargo,
+                sysroot,
+                rustc,
+                rustc_cfg,
+                cfg_overrides,
+                extra_targets,
+            } => f
                 .debug_struct("Cargo")
                 .field("root", &cargo.workspace_root().file_name())
                 .field("n_packages", &cargo.packages().len())
@@ -81,6 +163,10 @@ impl fmt::Debug for ProjectWorkspace {
                 )
                 .field("n_rustc_cfg", &rustc_cfg.len())
                 .field("n_cfg_overrides", &cfg_overrides.len())
+                .field(
+                    "extra_targets",
+                    &extra_targets.iter().map(|it| it.target.as_str()).collect::<Vec<_>>(),
+                )
                 .finish(),
             ProjectWorkspace::Json { project, sysroot, rustc_cfg } => {
                 let mut debug_struct = f.debug_struct("Json");
@@ -91,6 +177,15 @@ impl fmt::Debug for ProjectWorkspace {
                 debug_struct.field("n_rustc_cfg", &rustc_cfg.len());
                 debug_struct.finish()
             }
+            ProjectWorkspace::BuildGraph { graph, sysroot, rustc_cfg } => {
+                let mut debug_struct = f.debug_struct("BuildGraph");
+                debug_struct.field("n_crates", &graph.n_crates());
+                if let Some(sysroot) = sysroot {
+                    debug_struct.field("n_sysroot_crates", &sysroot.crates().len());
+                }
+                debug_struct.field("n_rustc_cfg", &rustc_cfg.len());
+                debug_struct.finish()
+            }
             ProjectWorkspace::DetachedFiles { files, sysroot, rustc_cfg } => f
                 .debug_struct("DetachedFiles")
                 .field("n_files", &files.len())
@@ -105,7 +200,7 @@ impl ProjectWorkspace {
     pub fn load(
         manifest: ProjectManifest,
         config: &CargoConfig,
-        progress: &dyn Fn(String),
+        progress: &dyn Fn(ProgressEvent),
     ) -> Result<ProjectWorkspace> {
         let res = match manifest {
             ProjectManifest::ProjectJson(project_json) => {
@@ -117,7 +212,24 @@ impl ProjectWorkspace {
                 })?;
                 let project_location = project_json.parent().unwrap().to_path_buf();
                 let project_json = ProjectJson::new(&project_location, data);
-                ProjectWorkspace::load_inline(project_json, config.target.as_deref())?
+                ProjectWorkspace::load_inline(project_json, config)?
+            }
+            ProjectManifest::BuildGraph(build_graph) => {
+                let file = fs::read_to_string(&build_graph).with_context(|| {
+                    format!("Failed to read build graph {}", build_graph.display())
+                })?;
+                let data = serde_json::from_str(&file).with_context(|| {
+                    format!("Failed to deserialize build graph {}", build_graph.display())
+                })?;
+                let graph = BuildGraph::new(build_graph.parent().unwrap(), data)
+                    .with_context(|| format!("Invalid build graph {}", build_graph.display()))?;
+                let sysroot = match config.sysroot_src.as_ref().or(graph.sysroot_src.as_ref()) {
+                    Some(path) => Some(Sysroot::load(path)?),
+                    None => None,
+                };
+                let target = config.target.as_deref().or(graph.target.as_deref());
+                let rustc_cfg = rustc_cfg::get(None, target, config.offline);
+                ProjectWorkspace::BuildGraph { graph, sysroot, rustc_cfg }
             }
             ProjectManifest::CargoToml(cargo_toml) => {
                 let cargo_version = utf8_stdout({
@@ -139,7 +251,7 @@ impl ProjectWorkspace {
                 let sysroot = if config.no_sysroot {
                     Sysroot::default()
                 } else {
-                    Sysroot::discover(&cargo_toml).with_context(|| {
+                    Sysroot::discover(&cargo_toml, config).with_context(|| {
                         format!(
                             "Failed to find sysroot for Cargo.toml file {}. Is rust-src installed?",
                             cargo_toml.display()
@@ -151,8 +263,11 @@ impl ProjectWorkspace {
                     use cargo_workspace::RustcSource;
                     match rustc_source {
                         RustcSource::Path(path) => Some(path.clone()),
-                        RustcSource::Discover => Sysroot::discover_rustc(&cargo_toml),
+                        RustcSource::Discover => Sysroot::discover_rustc(&cargo_toml, config),
                     }
+                } else if cargo.packages().any(|pkg| cargo[pkg].metadata.rustc_private) {
+                    // Use the sources of the `rustc-dev` component, if installed.
+                    Sysroot::discover_rustc(&cargo_toml, config)
                 } else {
                     None
                 };
@@ -168,10 +283,43 @@ impl ProjectWorkspace {
                     None => None,
                 };
 
-                let rustc_cfg = rustc_cfg::get(Some(&cargo_toml), config.target.as_deref());
+                let rustc_cfg =
+                    rustc_cfg::get(Some(&cargo_toml), config.target.as_deref(), config.offline);
+
+                let mut extra_targets = Vec::new();
+                for target in &config.extra_targets {
+                    if config.target.as_ref() == Some(target) {
+                        continue;
+                    }
+                    let config = CargoConfig { target: Some(target.clone()), ..config.clone() };
+                    let meta = CargoWorkspace::fetch_metadata(&cargo_toml, &config, progress)
+                        .with_context(|| {
+                            format!(
+                                "Failed to read Cargo metadata for target {} from Cargo.toml file {}",
+                                target,
+                                cargo_toml.display(),
+                            )
+                        })?;
+                    extra_targets.push(CargoExtraTarget {
+                        target: target.clone(),
+                        cargo: CargoWorkspace::new(&cargo_toml, &config, meta),
+                        rustc_cfg: rustc_cfg::get(
+                            Some(&cargo_toml),
+                            Some(target.as_str()),
+                            config.offline,
+                        ),
+                    });
+                }
 
                 let cfg_overrides = config.cfg_overrides();
-                ProjectWorkspace::Cargo { cargo, sysroot, rustc, rustc_cfg, cfg_overrides }
+                ProjectWorkspace::Cargo {
+                    cargo,
+                    sysroot,
+                    rustc,
+                    rustc_cfg,
+                    cfg_overrides,
+                    extra_targets,
+                }
             }
         };
 
@@ -180,22 +328,42 @@ impl ProjectWorkspace {
 
     pub fn load_inline(
         project_json: ProjectJson,
-        target: Option<&str>,
+        config: &CargoConfig,
     ) -> Result<ProjectWorkspace> {
-        let sysroot = match &project_json.sysroot_src {
+        let sysroot = match config.sysroot_src.as_ref().or(project_json.sysroot_src.as_ref()) {
             Some(path) => Some(Sysroot::load(path)?),
             None => None,
         };
-        let rustc_cfg = rustc_cfg::get(None, target);
+        let rustc_cfg = rustc_cfg::get(None, config.target.as_deref(), config.offline);
         Ok(ProjectWorkspace::Json { project: project_json, sysroot, rustc_cfg })
     }
 
-    pub fn load_detached_files(detached_files: Vec<AbsPathBuf>) -> Result<ProjectWorkspace> {
-        let sysroot = Sysroot::discover(
-            detached_files.first().ok_or_else(|| format_err!("No detached files to load"))?,
-        )?;
-        let rustc_cfg = rustc_cfg::get(None, None);
-        Ok(ProjectWorkspace::DetachedFiles { files: detached_files, sysroot, rustc_cfg })
+    /// Loads files without a manifest into one workspace. Scripts declaring
+    /// dependencies get a workspace of their own, as cargo has to resolve them.
+    pub fn load_detached_files(
+        detached_files: Vec<AbsPathBuf>,
+        config: &CargoConfig,
+        progress: &dyn Fn(ProgressEvent),
+    ) -> Vec<Result<ProjectWorkspace>> {
+        let (scripts, files): (Vec<_>, Vec<_>) = detached_files
+            .into_iter()
+            .map(DetachedFile::load)
+            .partition(|it| it.has_dependencies());
+
+        let mut res = scripts
+            .iter()
+            .map(|script| {
+                let manifest = script.write_manifest()?;
+                ProjectWorkspace::load(ProjectManifest::CargoToml(manifest), config, progress)
+            })
+            .collect::<Vec<_>>();
+        if let Some(first) = files.first() {
+            res.push(Sysroot::discover(first.path(), config).map(|sysroot| {
+                let rustc_cfg = rustc_cfg::get(None, config.target.as_deref(), config.offline);
+                ProjectWorkspace::DetachedFiles { files, sysroot, rustc_cfg }
+            }));
+        }
+        res
     }
 
     /// Returns the roots for the current `ProjectWorkspace`
@@ -220,48 +388,45 @@ impl ProjectWorkspace {
                     })
                 }))
                 .collect::<Vec<_>>(),
-            ProjectWorkspace::Cargo { cargo, sysroot, rustc, rustc_cfg: _, cfg_overrides: _ } => {
+            ProjectWorkspace::BuildGraph { graph, sysroot, rustc_cfg: _ } => graph
+                .crates()
+                .map(|(_, krate)| PackageRoot {
+                    is_member: krate.is_workspace_member,
+                    include: krate.include.clone(),
+                    exclude: krate.exclude.clone(),
+                })
+                .collect::<FxHashSet<_>>()
+                .into_iter()
+                .chain(sysroot.as_ref().into_iter().flat_map(|sysroot| {
+                    sysroot.crates().map(move |krate| PackageRoot {
+                        is_member: false,
+                        include: vec![sysroot[krate].root_dir().to_path_buf()],
+                        exclude: Vec::new(),
+                    })
+                }))
+                .collect::<Vec<_>>(),
+            ProjectWorkspace::Cargo {
+                cargo,
+                sysroot,
+                rustc,
+                rustc_cfg: _,
+                cfg_overrides: _,
+                extra_targets,
+            } => {
+                let build_data = build_data.and_then(|it| it.get(cargo.workspace_root()));
+                let extra_packages = extra_targets.iter().flat_map(|extra| {
+                    let extra = &extra.cargo;
+                    extra.packages().map(move |pkg| (extra, pkg))
+                });
+                // The extra targets add the packages the default target
+                // doesn't use.
+                let mut seen = FxHashSet::default();
                 cargo
                     .packages()
-                    .map(|pkg| {
-                        let is_member = cargo[pkg].is_member;
-                        let pkg_root = cargo[pkg].root().to_path_buf();
-
-                        let mut include = vec![pkg_root.clone()];
-                        include.extend(
-                            build_data
-                                .and_then(|it| it.get(cargo.workspace_root()))
-                                .and_then(|map| map.get(&cargo[pkg].id))
-                                .and_then(|it| it.out_dir.clone()),
-                        );
-
-                        // In case target's path is manually set in Cargo.toml to be
-                        // outside the package root, add its parent as an extra include.
-                        // An example of this situation would look like this:
-                        //
-                        // ```toml
-                        // [lib]
-                        // path = "../../src/lib.rs"
-                        // ```
-                        let extra_targets = cargo[pkg]
-                            .targets
-                            .iter()
-                            .filter(|&&tgt| cargo[tgt].kind == TargetKind::Lib)
-                            .filter_map(|&tgt| cargo[tgt].root.parent())
-                            .map(|tgt| tgt.normalize().to_path_buf())
-                            .filter(|path| !path.starts_with(&pkg_root));
-                        include.extend(extra_targets);
-
-                        let mut exclude = vec![pkg_root.join(".git")];
-                        if is_member {
-                            exclude.push(pkg_root.join("target"));
-                        } else {
-                            exclude.push(pkg_root.join("tests"));
-                            exclude.push(pkg_root.join("examples"));
-                            exclude.push(pkg_root.join("benches"));
-                        }
-                        PackageRoot { is_member, include, exclude }
-                    })
+                    .map(|pkg| (cargo, pkg))
+                    .chain(extra_packages)
+                    .filter(|&(cargo, pkg)| seen.insert(&cargo[pkg].id))
+                    .map(|(cargo, pkg)| cargo_package_root(cargo, pkg, build_data))
                     .chain(sysroot.crates().map(|krate| PackageRoot {
                         is_member: false,
                         include: vec![sysroot[krate].root_dir().to_path_buf()],
@@ -280,7 +445,7 @@ impl ProjectWorkspace {
                 .into_iter()
                 .map(|detached_file| PackageRoot {
                     is_member: true,
-                    include: vec![detached_file.clone()],
+                    include: vec![detached_file.path.clone()],
                     exclude: Vec::new(),
                 })
                 .chain(sysroot.crates().map(|krate| PackageRoot {
@@ -295,6 +460,7 @@ impl ProjectWorkspace {
     pub fn n_packages(&self) -> usize {
         match self {
             ProjectWorkspace::Json { project, .. } => project.n_crates(),
+            ProjectWorkspace::BuildGraph { graph, .. } => graph.n_crates(),
             ProjectWorkspace::Cargo { cargo, sysroot, rustc, .. } => {
                 let rustc_package_len = rustc.as_ref().map_or(0, |rc| rc.packages().len());
                 cargo.packages().len() + sysroot.crates().len() + rustc_package_len
@@ -310,13 +476,14 @@ impl ProjectWorkspace {
         build_data: Option<&BuildDataResult>,
         proc_macro_client: Option<&ProcMacroClient>,
         load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
-    ) -> CrateGraph {
+    ) -> (CrateGraph, Vec<CrateGraphWarning>) {
         let _p = profile::span("ProjectWorkspace::to_crate_graph");
         let proc_macro_loader = |path: &AbsPath| match proc_macro_client {
             Some(client) => client.by_dylib_path(path),
             None => Vec::new(),
         };
 
+        let mut warnings = Vec::new();
         let mut crate_graph = match self {
             ProjectWorkspace::Json { project, sysroot, rustc_cfg } => project_json_to_crate_graph(
                 rustc_cfg.clone(),
@@ -324,25 +491,74 @@ impl ProjectWorkspace {
                 load,
                 project,
                 sysroot,
+                &mut warnings,
             ),
-            ProjectWorkspace::Cargo { cargo, sysroot, rustc, rustc_cfg, cfg_overrides } => {
-                cargo_to_crate_graph(
+            ProjectWorkspace::BuildGraph { graph, sysroot, rustc_cfg } => {
+                build_graph_to_crate_graph(
+                    rustc_cfg.clone(),
+                    &proc_macro_loader,
+                    load,
+                    graph,
+                    sysroot,
+                    &mut warnings,
+                )
+            }
+            ProjectWorkspace::Cargo {
+                cargo,
+                sysroot,
+                rustc,
+                rustc_cfg,
+                cfg_overrides,
+                extra_targets,
+            } => {
+                let build_data_map = build_data.and_then(|it| it.get(cargo.workspace_root()));
+                let mut crate_graph = cargo_to_crate_graph(
                     rustc_cfg.clone(),
                     cfg_overrides,
                     &proc_macro_loader,
                     load,
                     cargo,
-                    build_data.and_then(|it| it.get(cargo.workspace_root())),
+                    build_data_map,
                     sysroot,
                     rustc,
                     rustc
                         .as_ref()
                         .zip(build_data)
                         .and_then(|(it, map)| map.get(it.workspace_root())),
-                )
+                    &mut warnings,
+                );
+                // Build scripts only run for the default target, their
+                // outputs are shared by all targets.
+                for extra in extra_targets {
+                    let mut extra_graph = cargo_to_crate_graph(
+                        extra.rustc_cfg.clone(),
+                        cfg_overrides,
+                        &proc_macro_loader,
+                        load,
+                        &extra.cargo,
+                        build_data_map,
+                        sysroot,
+                        &None,
+                        None,
+                        // Reported for the default target already.
+                        &mut Vec::new(),
+                    );
+                    extra_graph.patch_cfg_if();
+                    for krate in extra_graph.iter().collect::<Vec<_>>() {
+                        extra_graph.set_target(krate, Some(extra.target.clone()));
+                    }
+                    crate_graph.extend(extra_graph);
+                }
+                crate_graph
             }
             ProjectWorkspace::DetachedFiles { files, sysroot, rustc_cfg } => {
-                detached_files_to_crate_graph(rustc_cfg.clone(), load, files, sysroot)
+                detached_files_to_crate_graph(
+                    rustc_cfg.clone(),
+                    load,
+                    files,
+                    sysroot,
+                    &mut warnings,
+                )
             }
         };
         if crate_graph.patch_cfg_if() {
@@ -350,7 +566,30 @@ impl ProjectWorkspace {
         } else {
             log::debug!("Did not patch std to depend on cfg-if")
         }
-        crate_graph
+
+        // Without a proc-macro server, no dylib is loaded at all.
+        if proc_macro_client.is_none() {
+            warnings.retain(|it| {
+                !matches!(it, CrateGraphWarning::UnresolvedProcMacro { dylib: Some(_), .. })
+            });
+        }
+        for krate in crate_graph.iter() {
+            let deps = &crate_graph[krate].dependencies;
+            for (idx, dep) in deps.iter().enumerate() {
+                let is_duplicate =
+                    deps[..idx].iter().any(|it| it.name == dep.name && it.crate_id != dep.crate_id);
+                if is_duplicate {
+                    warnings.push(CrateGraphWarning::DuplicateDependency {
+                        krate: crate_name(&crate_graph, krate),
+                        name: dep.name.to_string(),
+                    });
+                }
+            }
+        }
+        for warning in &warnings {
+            log::warn!("{}", warning);
+        }
+        (crate_graph, warnings)
     }
 
     pub fn collect_build_data_configs(&self, collector: &mut BuildDataCollector) {
@@ -369,6 +608,7 @@ fn project_json_to_crate_graph(
     load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
     project: &ProjectJson,
     sysroot: &Option<Sysroot>,
+    warnings: &mut Vec<CrateGraphWarning>,
 ) -> CrateGraph {
     let mut crate_graph = CrateGraph::default();
     let sysroot_deps = sysroot
@@ -380,17 +620,39 @@ fn project_json_to_crate_graph(
         .crates()
         .filter_map(|(crate_id, krate)| {
             let file_path = &krate.root_module;
-            let file_id = load(file_path)?;
-            Some((crate_id, krate, file_id))
+            let krate_name = || match &krate.display_name {
+                Some(it) => it.to_string(),
+                None => file_path.display().to_string(),
+            };
+            let file_id = match load(file_path) {
+                Some(it) => it,
+                None => {
+                    warnings.push(CrateGraphWarning::MissingTarget {
+                        krate: krate_name(),
+                        root: file_path.clone(),
+                    });
+                    return None;
+                }
+            };
+            let proc_macro = krate.proc_macro_dylib_path.clone().map(|it| {
+                let proc_macro = proc_macro_loader(&it);
+                if proc_macro.is_empty() {
+                    warnings.push(CrateGraphWarning::UnresolvedProcMacro {
+                        krate: krate_name(),
+                        dylib: Some(it),
+                    });
+                }
+                proc_macro
+            });
+            Some((crate_id, krate, file_id, proc_macro))
         })
-        .map(|(crate_id, krate, file_id)| {
+        .map(|(crate_id, krate, file_id, proc_macro)| {
             let env = krate.env.clone().into_iter().collect();
-            let proc_macro = krate.proc_macro_dylib_path.clone().map(|it| proc_macro_loader(&it));
 
             let target_cfgs = match krate.target.as_deref() {
-                Some(target) => {
-                    cfg_cache.entry(target).or_insert_with(|| rustc_cfg::get(None, Some(target)))
-                }
+                Some(target) => cfg_cache
+                    .entry(target)
+                    .or_insert_with(|| rustc_cfg::get(None, Some(target), false)),
                 None => &rustc_cfg,
             };
 
@@ -414,9 +676,8 @@ fn project_json_to_crate_graph(
     for (from, krate) in project.crates() {
         if let Some(&from) = crates.get(&from) {
             if let Some((public_deps, _proc_macro)) = &sysroot_deps {
-                for (name, to) in public_deps.iter() {
-                    add_dep(&mut crate_graph, from, name.clone(), *to)
-                }
+                let no_std = is_no_std(&krate.root_module);
+                add_sysroot_deps(&mut crate_graph, from, public_deps, no_std);
             }
 
             for dep in &krate.deps {
@@ -429,6 +690,95 @@ fn project_json_to_crate_graph(
     crate_graph
 }
 
+fn build_graph_to_crate_graph(
+    rustc_cfg: Vec<CfgFlag>,
+    proc_macro_loader: &dyn Fn(&AbsPath) -> Vec<ProcMacro>,
+    load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
+    graph: &BuildGraph,
+    sysroot: &Option<Sysroot>,
+    warnings: &mut Vec<CrateGraphWarning>,
+) -> CrateGraph {
+    let _p = profile::span("build_graph_to_crate_graph");
+    let mut crate_graph = CrateGraph::default();
+    let sysroot_deps = sysroot
+        .as_ref()
+        .map(|sysroot| sysroot_to_crate_graph(&mut crate_graph, sysroot, rustc_cfg.clone(), load));
+
+    let mut cfg_cache: FxHashMap<&str, Vec<CfgFlag>> = FxHashMap::default();
+    let mut crates: FxHashMap<usize, CrateId> = FxHashMap::default();
+    for (idx, krate) in graph.crates() {
+        let file_id = match load(&krate.root_module) {
+            Some(it) => it,
+            None => {
+                warnings.push(CrateGraphWarning::MissingTarget {
+                    krate: krate.label.clone(),
+                    root: krate.root_module.clone(),
+                });
+                continue;
+            }
+        };
+        let target_cfgs = match krate.target.as_deref() {
+            Some(target) => {
+                cfg_cache.entry(target).or_insert_with(|| rustc_cfg::get(None, Some(target), false))
+            }
+            None => &rustc_cfg,
+        };
+        let mut cfg_options = CfgOptions::default();
+        cfg_options.extend(target_cfgs.iter().chain(krate.cfg.iter()).cloned());
+        if krate.kind == BuildCrateKind::Test {
+            cfg_options.insert_atom("test".into());
+        }
+
+        let env = krate.env.clone().into_iter().collect();
+        let proc_macro = krate.proc_macro_dylib.as_deref().map(proc_macro_loader);
+        match (&proc_macro, krate.kind) {
+            (Some(it), _) if it.is_empty() => {
+                warnings.push(CrateGraphWarning::UnresolvedProcMacro {
+                    krate: krate.label.clone(),
+                    dylib: krate.proc_macro_dylib.clone(),
+                })
+            }
+            (None, BuildCrateKind::ProcMacro) => {
+                warnings.push(CrateGraphWarning::UnresolvedProcMacro {
+                    krate: krate.label.clone(),
+                    dylib: None,
+                })
+            }
+            _ => (),
+        }
+        let crate_id = crate_graph.add_crate_root(
+            file_id,
+            krate.edition,
+            Some(krate.display_name.clone()),
+            cfg_options.clone(),
+            cfg_options,
+            env,
+            proc_macro.unwrap_or_default(),
+        );
+        crates.insert(idx, crate_id);
+    }
+
+    for (idx, krate) in graph.crates() {
+        let from = match crates.get(&idx) {
+            Some(&it) => it,
+            None => continue,
+        };
+        if let Some((public_deps, libproc_macro)) = &sysroot_deps {
+            let no_std = is_no_std(&krate.root_module);
+            add_sysroot_deps(&mut crate_graph, from, public_deps, no_std);
+            if let (BuildCrateKind::ProcMacro, Some(to)) = (krate.kind, libproc_macro) {
+                add_dep(&mut crate_graph, from, CrateName::new("proc_macro").unwrap(), *to);
+            }
+        }
+        for (name, dep) in &krate.deps {
+            if let Some(&to) = crates.get(dep) {
+                add_dep(&mut crate_graph, from, name.clone(), to);
+            }
+        }
+    }
+    crate_graph
+}
+
 fn cargo_to_crate_graph(
     rustc_cfg: Vec<CfgFlag>,
     override_cfg: &CfgOverrides,
@@ -439,6 +789,7 @@ fn cargo_to_crate_graph(
     sysroot: &Sysroot,
     rustc: &Option<CargoWorkspace>,
     rustc_build_data_map: Option<&WorkspaceBuildData>,
+    warnings: &mut Vec<CrateGraphWarning>,
 ) -> CrateGraph {
     let _p = profile::span("cargo_to_crate_graph");
     let mut crate_graph = CrateGraph::default();
@@ -455,6 +806,7 @@ fn cargo_to_crate_graph(
     cfg_options.insert_atom("debug_assertions".into());
 
     let mut pkg_crates = FxHashMap::default();
+    let mut no_std_crates = FxHashSet::default();
     // Does any crate signal to rust-analyzer that they need the rustc_private crates?
     let mut has_private = false;
     // Next, create crates for each package, target pair
@@ -476,17 +828,50 @@ fn cargo_to_crate_graph(
 
         has_private |= cargo[pkg].metadata.rustc_private;
         let mut lib_tgt = None;
+        let mut missing_targets = Vec::new();
         for &tgt in cargo[pkg].targets.iter() {
-            if let Some(file_id) = load(&cargo[tgt].root) {
+            let file_id = load(&cargo[tgt].root);
+            if file_id.is_none() {
+                missing_targets.push(CrateGraphWarning::MissingTarget {
+                    krate: cargo[tgt].name.clone(),
+                    root: cargo[tgt].root.clone(),
+                });
+            }
+            if let Some(file_id) = file_id {
+                // Artifact build dependencies are only visible to build scripts,
+                // like other build dependencies.
+                let is_build_script = cargo[tgt].kind == TargetKind::BuildScript;
+                let artifact_env = cargo[pkg]
+                    .artifact_dependencies
+                    .iter()
+                    .filter(|dep| (dep.kind == DepKind::Build) == is_build_script)
+                    .flat_map(|dep| cargo.artifact_env(dep))
+                    .collect::<Vec<_>>();
                 let crate_id = add_target_crate_root(
                     &mut crate_graph,
                     &cargo[pkg],
                     build_data_map.and_then(|it| it.get(&cargo[pkg].id)),
+                    &artifact_env,
                     &cfg_options,
                     proc_macro_loader,
                     file_id,
+                    &cargo[tgt].root,
                     &cargo[tgt].name,
                 );
+                if cargo[tgt].is_proc_macro && build_data_map.is_some() {
+                    let dylib = build_data_map
+                        .and_then(|it| it.get(&cargo[pkg].id))
+                        .and_then(|it| it.proc_macro_dylib_path.clone());
+                    if dylib.is_none() || crate_graph[crate_id].proc_macro.is_empty() {
+                        warnings.push(CrateGraphWarning::UnresolvedProcMacro {
+                            krate: cargo[tgt].name.clone(),
+                            dylib,
+                        });
+                    }
+                }
+                if cargo[pkg].no_std.unwrap_or_else(|| is_no_std(&cargo[tgt].root)) {
+                    no_std_crates.insert(crate_id);
+                }
                 if cargo[tgt].kind == TargetKind::Lib {
                     lib_tgt = Some((crate_id, cargo[tgt].name.clone()));
                     pkg_to_lib_crate.insert(pkg, crate_id);
@@ -505,6 +890,11 @@ fn cargo_to_crate_graph(
                 pkg_crates.entry(pkg).or_insert_with(Vec::new).push((crate_id, cargo[tgt].kind));
             }
         }
+        if !pkg_crates.contains_key(&pkg) && !cargo[pkg].targets.is_empty() {
+            warnings.push(CrateGraphWarning::PackageSkipped { package: cargo[pkg].name.clone() });
+        } else {
+            warnings.extend(missing_targets);
+        }
 
         // Set deps to the core, std and to the lib target of the current package
         for (from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
@@ -519,16 +909,14 @@ fn cargo_to_crate_graph(
                     add_dep(&mut crate_graph, *from, name, to);
                 }
             }
-            for (name, krate) in public_deps.iter() {
-                add_dep(&mut crate_graph, *from, name.clone(), *krate);
-            }
+            add_sysroot_deps(&mut crate_graph, *from, &public_deps, no_std_crates.contains(from));
         }
     }
 
     // Now add a dep edge from all targets of upstream to the lib
     // target of downstream.
     for pkg in cargo.packages() {
-        for dep in cargo[pkg].dependencies.iter() {
+        for dep in cargo[pkg].dependencies.iter().filter(|dep| dep.kind != DepKind::Dev) {
             let name = CrateName::new(&dep.name).unwrap();
             if let Some(&to) = pkg_to_lib_crate.get(&dep.pkg) {
                 for (from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
@@ -547,9 +935,42 @@ fn cargo_to_crate_graph(
         }
     }
 
+    // Dev-dependencies go last: a dev-dependency may itself depend on the
+    // package, which cargo allows as the library is compiled twice. Only the
+    // edge from the library closes the cycle, so that one is skipped instead
+    // of a regular edge, and the other targets keep the dependency.
+    for pkg in cargo.packages() {
+        for dep in cargo[pkg].dependencies.iter().filter(|dep| dep.kind == DepKind::Dev) {
+            let name = CrateName::new(&dep.name).unwrap();
+            let to = match pkg_to_lib_crate.get(&dep.pkg) {
+                Some(&it) => it,
+                None => continue,
+            };
+            for &(from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
+                if kind == TargetKind::BuildScript
+                    || crate_graph[from].dependencies.iter().any(|it| it.crate_id == to)
+                {
+                    continue;
+                }
+                if crate_graph.add_dep(from, name.clone(), to).is_err() {
+                    warnings.push(CrateGraphWarning::DevDependencyCycle {
+                        krate: crate_name(&crate_graph, from),
+                        dependency: dep.name.clone(),
+                    });
+                }
+            }
+        }
+    }
+
     if has_private {
-        // If the user provided a path to rustc sources, we add all the rustc_private crates
-        // and create dependencies on them for the crates which opt-in to that
+        // If the user provided a path to rustc sources, or the rustc-dev component is installed,
+        // we add all the rustc_private crates and create dependencies on them for the crates
+        // which opt-in to that
+        if rustc.is_none() {
+            warnings.extend(cargo.packages().filter(|&pkg| cargo[pkg].metadata.rustc_private).map(
+                |pkg| CrateGraphWarning::RustcSourceMissing { package: cargo[pkg].name.clone() },
+            ));
+        }
         if let Some(rustc_workspace) = rustc {
             handle_rustc_crates(
                 rustc_workspace,
@@ -568,11 +989,63 @@ fn cargo_to_crate_graph(
     crate_graph
 }
 
+fn cargo_package_root(
+    cargo: &CargoWorkspace,
+    pkg: Package,
+    build_data: Option<&WorkspaceBuildData>,
+) -> PackageRoot {
+    // Patched path dependencies are edited along with the workspace, unlike
+    // vendored ones, which keep their registry source.
+    let is_member = cargo[pkg].is_member || cargo[pkg].is_local;
+    let pkg_root = cargo[pkg].root().to_path_buf();
+
+    let mut include = vec![pkg_root.clone()];
+    include.extend(
+        build_data.and_then(|map| map.get(&cargo[pkg].id)).and_then(|it| it.out_dir.clone()),
+    );
+
+    // In case target's path is manually set in Cargo.toml to be
+    // outside the package root, add its parent as an extra include.
+    // An example of this situation would look like this:
+    //
+    // ```toml
+    // [lib]
+    // path = "../../src/lib.rs"
+    // ```
+    //
+    // The packages generated for scripts declare the script as their binary.
+    let extra_targets = cargo[pkg]
+        .targets
+        .iter()
+        .filter(|&&tgt| matches!(cargo[tgt].kind, TargetKind::Lib | TargetKind::Bin))
+        .filter_map(|&tgt| cargo[tgt].root.parent())
+        .map(|tgt| tgt.normalize().to_path_buf())
+        .filter(|path| !path.starts_with(&pkg_root));
+    include.extend(extra_targets);
+
+    let mut exclude = vec![pkg_root.join(".git")];
+    if is_member {
+        exclude.push(pkg_root.join("target"));
+        // The target directory may be configured to live elsewhere in the
+        // package. The `OUT_DIR`s in it are included explicitly.
+        let target_dir = cargo.target_dir();
+        if target_dir.starts_with(&pkg_root) {
+            exclude.push(target_dir.to_path_buf());
+        }
+    } else {
+        exclude.push(pkg_root.join("tests"));
+        exclude.push(pkg_root.join("examples"));
+        exclude.push(pkg_root.join("benches"));
+    }
+    PackageRoot { is_member, include, exclude }
+}
+
 fn detached_files_to_crate_graph(
     rustc_cfg: Vec<CfgFlag>,
     load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
-    detached_files: &[AbsPathBuf],
+    detached_files: &[DetachedFile],
     sysroot: &Sysroot,
+    warnings: &mut Vec<CrateGraphWarning>,
 ) -> CrateGraph {
     let _p = profile::span("detached_files_to_crate_graph");
     let mut crate_graph = CrateGraph::default();
@@ -583,20 +1056,24 @@ fn detached_files_to_crate_graph(
     cfg_options.extend(rustc_cfg);
 
     for detached_file in detached_files {
-        let file_id = match load(detached_file) {
+        let path = detached_file.path();
+        let file_id = match load(path) {
             Some(file_id) => file_id,
             None => {
-                log::error!("Failed to load detached file {:?}", detached_file);
+                warnings.push(CrateGraphWarning::MissingTarget {
+                    krate: path.display().to_string(),
+                    root: path.to_path_buf(),
+                });
                 continue;
             }
         };
-        let display_name = detached_file
+        let display_name = path
             .file_stem()
             .and_then(|os_str| os_str.to_str())
             .map(|file_stem| CrateDisplayName::from_canonical_name(file_stem.to_string()));
         let detached_file_crate = crate_graph.add_crate_root(
             file_id,
-            Edition::CURRENT,
+            detached_file.edition,
             display_name,
             cfg_options.clone(),
             cfg_options.clone(),
@@ -604,9 +1081,8 @@ fn detached_files_to_crate_graph(
             Vec::new(),
         );
 
-        for (name, krate) in public_deps.iter() {
-            add_dep(&mut crate_graph, detached_file_crate, name.clone(), *krate);
-        }
+        let no_std = is_no_std(path);
+        add_sysroot_deps(&mut crate_graph, detached_file_crate, &public_deps, no_std);
     }
     crate_graph
 }
@@ -652,9 +1128,11 @@ fn handle_rustc_crates(
                         crate_graph,
                         &rustc_workspace[pkg],
                         rustc_build_data_map.and_then(|it| it.get(&rustc_workspace[pkg].id)),
+                        &[],
                         cfg_options,
                         proc_macro_loader,
                         file_id,
+                        &rustc_workspace[tgt].root,
                         &rustc_workspace[tgt].name,
                     );
                     pkg_to_lib_crate.insert(pkg, crate_id);
@@ -708,20 +1186,31 @@ fn add_target_crate_root(
     crate_graph: &mut CrateGraph,
     pkg: &cargo_workspace::PackageData,
     build_data: Option<&PackageBuildData>,
+    extra_env: &[(String, String)],
     cfg_options: &CfgOptions,
     proc_macro_loader: &dyn Fn(&AbsPath) -> Vec<ProcMacro>,
     file_id: FileId,
+    root: &AbsPath,
     cargo_name: &str,
 ) -> CrateId {
-    let edition = pkg.edition;
+    // What rustc was invoked with beats what we infer from the metadata.
+    let recorded = build_data.and_then(|it| it.target(root));
+    let edition =
+        recorded.and_then(|it| it.edition.as_deref()?.parse().ok()).unwrap_or(pkg.edition);
     let cfg_options = {
         let mut opts = cfg_options.clone();
-        for feature in pkg.active_features.iter() {
-            opts.insert_key_value("feature".into(), feature.into());
-        }
-        if let Some(cfgs) = build_data.as_ref().map(|it| &it.cfgs) {
-            opts.extend(cfgs.iter().cloned());
+        match recorded {
+            Some(recorded) => opts.extend(recorded.cfgs.iter().cloned()),
+            None => {
+                for feature in pkg.active_features.iter() {
+                    opts.insert_key_value("feature".into(), feature.into());
+                }
+                if let Some(cfgs) = build_data.as_ref().map(|it| &it.cfgs) {
+                    opts.extend(cfgs.iter().cloned());
+                }
+            }
         }
+        opts.extend(pkg.extra_cfgs.iter().cloned());
         opts
     };
 
@@ -731,6 +1220,12 @@ fn add_target_crate_root(
             env.set(k, v.clone());
         }
     }
+    for (k, v) in recorded.iter().flat_map(|it| &it.envs) {
+        env.set(k, v.clone());
+    }
+    for (k, v) in extra_env.iter().chain(&pkg.extra_env) {
+        env.set(k, v.clone());
+    }
 
     let proc_macro = build_data
         .as_ref()
@@ -738,7 +1233,13 @@ fn add_target_crate_root(
         .map(|it| proc_macro_loader(it))
         .unwrap_or_default();
 
-    let display_name = CrateDisplayName::from_canonical_name(cargo_name.to_string());
+    // Target names are normalized by cargo, the package name keeps its dashes.
+    // Dependents may still refer to the crate under another name, which is
+    // the name of the dependency edge.
+    let display_name = CrateDisplayName::with_canonical_name(
+        CrateName::normalize_dashes(cargo_name),
+        pkg.name.clone(),
+    );
     let mut potential_cfg_options = cfg_options.clone();
     potential_cfg_options.extend(
         pkg.features
@@ -755,6 +1256,14 @@ fn add_target_crate_root(
         env,
         proc_macro,
     );
+    crate_graph.set_package(
+        crate_id,
+        Some(PackageInfo {
+            version: pkg.version.clone(),
+            description: pkg.description.clone(),
+            repository: pkg.repository.clone(),
+        }),
+    );
 
     crate_id
 }
@@ -807,8 +1316,141 @@ fn sysroot_to_crate_graph(
     (public_deps, libproc_macro)
 }
 
+/// Adds the dependencies on `core`, `alloc` and `std`, leaving out `std` for
+/// `no_std` crates so that it isn't offered in completions and imports.
+fn add_sysroot_deps(
+    graph: &mut CrateGraph,
+    from: CrateId,
+    public_deps: &[(CrateName, CrateId)],
+    no_std: bool,
+) {
+    for (name, to) in public_deps {
+        if no_std && &**name == "std" {
+            continue;
+        }
+        add_dep(graph, from, name.clone(), *to);
+    }
+}
+
+/// Whether the crate root at `path` declares `#![no_std]` among its leading
+/// attributes. Conditional forms like `#![cfg_attr(not(test), no_std)]` are
+/// not evaluated, such crates keep depending on `std`.
+fn is_no_std(path: &AbsPath) -> bool {
+    has_inner_attr(path, |attr| attr.starts_with("#![no_std]"))
+}
+
+/// Whether the crate root enables `#![feature(rustc_private)]`.
+pub(crate) fn uses_rustc_private(path: &AbsPath) -> bool {
+    has_inner_attr(path, |attr| {
+        attr.strip_prefix("#![feature(").map_or(false, |features| {
+            features.split(|c| c == ',' || c == ')').any(|it| it == "rustc_private")
+        })
+    })
+}
+
+/// Looks for an inner attribute in the header of a crate root, with the
+/// whitespace of its line removed.
+fn has_inner_attr(path: &AbsPath, pred: impl Fn(&str) -> bool) -> bool {
+    // Only the header is read, this runs for every crate of the workspace.
+    let file = match fs::File::open(path) {
+        Ok(it) => it,
+        Err(_) => return false,
+    };
+    for line in io::BufRead::lines(io::BufReader::new(file)) {
+        let line = match line {
+            Ok(it) => it,
+            Err(_) => return false,
+        };
+        let line = line.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        if pred(&line) {
+            return true;
+        }
+        let is_header = line.is_empty()
+            || line.starts_with("#!")
+            || line.starts_with("//")
+            || line.starts_with("/*")
+            || line.starts_with('*');
+        if !is_header {
+            break;
+        }
+    }
+    false
+}
+
 fn add_dep(graph: &mut CrateGraph, from: CrateId, name: CrateName, to: CrateId) {
     if let Err(err) = graph.add_dep(from, name, to) {
         log::error!("{}", err)
     }
 }
+
+fn crate_name(graph: &CrateGraph, krate: CrateId) -> String {
+    match &graph[krate].display_name {
+        Some(it) => it.to_string(),
+        None => format!("{:?}", krate),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_crate_graph(metadata: &str) -> (CrateGraph, Vec<CrateGraphWarning>) {
+        // Paths in the fixture start with `$ROOT$`, which becomes an absolute path.
+        let root = if cfg!(windows) { "C:\\ROOT\\" } else { "/ROOT/" };
+        let metadata = metadata.replace("$ROOT$", &root.replace('\\', "\\\\"));
+        let meta = cargo_workspace::parse_metadata(&metadata).unwrap();
+        let cargo_toml = AbsPathBuf::assert(format!("{}Cargo.toml", root).into());
+        let cargo = CargoWorkspace::new(&cargo_toml, &CargoConfig::default(), meta);
+
+        let mut files = FxHashMap::default();
+        let mut load = |path: &AbsPath| {
+            let len = files.len();
+            Some(*files.entry(path.to_path_buf()).or_insert(FileId(len as u32)))
+        };
+        let mut warnings = Vec::new();
+        let crate_graph = cargo_to_crate_graph(
+            Vec::new(),
+            &CfgOverrides::default(),
+            &|_| Vec::new(),
+            &mut load,
+            &cargo,
+            None,
+            &Sysroot::default(),
+            &None,
+            None,
+            &mut warnings,
+        );
+        (crate_graph, warnings)
+    }
+
+    fn dependencies(crate_graph: &CrateGraph, name: &str) -> Vec<String> {
+        let krate = crate_graph
+            .iter()
+            .find(|&it| crate_name(crate_graph, it) == name)
+            .unwrap_or_else(|| panic!("no crate named `{}`", name));
+        let mut deps = crate_graph[krate]
+            .dependencies
+            .iter()
+            .map(|dep| format!("{} -> {}", dep.name, crate_name(crate_graph, dep.crate_id)))
+            .collect::<Vec<_>>();
+        deps.sort();
+        deps
+    }
+
+    #[test]
+    fn dev_dependency_cycle() {
+        let (crate_graph, warnings) =
+            to_crate_graph(include_str!("../test_data/dev_dependency_cycle.json"));
+
+        assert_eq!(dependencies(&crate_graph, "foo"), Vec::<String>::new());
+        assert_eq!(dependencies(&crate_graph, "it"), vec!["bar -> bar", "foo -> foo"]);
+        assert_eq!(dependencies(&crate_graph, "bar"), vec!["foo -> foo"]);
+        assert_eq!(
+            warnings,
+            vec![CrateGraphWarning::DevDependencyCycle {
+                krate: "foo".to_string(),
+                dependency: "bar".to_string(),
+            }]
+        );
+    }
+}
@@ -1,9 +1,17 @@
 //! Grammar for the command-line arguments.
 #![allow(unreachable_pub)]
-use std::path::PathBuf;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
 use ide_ssr::{SsrPattern, SsrRule};
-use rust_analyzer::cli::Verbosity;
+use project_model::CargoConfig;
+use rust_analyzer::{
+    cli::{BenchWhat, DiagnosticsFormat, ProgressFormat, Verbosity},
+    Result,
+};
+use vfs::AbsPathBuf;
 
 xflags::xflags! {
     src "./src/bin/flags.rs"
@@ -23,6 +31,43 @@ xflags::xflags! {
         /// Wait until a debugger is attached to (requires debug build).
         optional --wait-dbg
 
+        /// Comma- or space-separated list of cargo features to activate when
+        /// loading a workspace.
+        repeated --features features: String
+        /// Cargo features to activate for a single workspace member, like
+        /// `my-crate=foo,bar` or `my-crate=all`.
+        repeated --package-features spec: String
+        /// Activate all available cargo features.
+        optional --all-features
+        /// Do not activate the `default` cargo feature.
+        optional --no-default-features
+        /// Load workspaces for this target triple instead of the host.
+        optional --target triple: String
+        /// Don't load the sysroot sources, use empty `core`, `alloc` and `std`
+        /// crates instead.
+        optional --no-sysroot
+        /// Load the standard library from this sysroot instead of the one of
+        /// the toolchain.
+        optional --sysroot path: PathBuf
+        /// Load the standard library from these sources, the `library`
+        /// directory of the Rust sources. Takes precedence over `--sysroot`
+        /// and `RUST_SRC_PATH`.
+        optional --sysroot-src path: PathBuf
+        /// Run cargo with `--frozen`, without network access and without
+        /// updating `Cargo.lock`.
+        optional --offline
+        /// Read settings from this TOML file, with the keys the language
+        /// server accepts, like `cargo.features = ["foo"]`. Defaults to
+        /// `rust-analyzer.toml` in the current directory or one of its
+        /// ancestors. Command-line flags take precedence over it.
+        optional --config path: PathBuf
+        /// Report the progress of loading a workspace on stderr, `none` (the
+        /// default) or `json` for one JSON object per line.
+        optional --progress format: ProgressFormat
+        /// Report a fatal error as a JSON object on stderr, with its kind,
+        /// message and exit code.
+        optional --json-errors
+
         default cmd lsp-server {
             /// Print version.
             optional --version
@@ -39,18 +84,34 @@ xflags::xflags! {
             optional --no-dump
         }
 
-        /// Parse stdin and print the list of symbols.
-        cmd symbols {}
+        /// Parse stdin and print the list of symbols, or dump the symbol
+        /// index of a workspace as JSON.
+        cmd symbols
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            optional path: PathBuf
+        {
+            /// Also dump symbols of dependencies.
+            optional --with-deps
+        }
 
-        /// Highlight stdin as html.
-        cmd highlight {
+        /// Highlight stdin as html, or render a workspace to linked html pages.
+        cmd highlight
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            optional path: PathBuf
+        {
             /// Enable rainbow highlighting of identifiers.
             optional --rainbow
+            /// Only render this file.
+            optional --file file: PathBuf
+            /// Only render the files of this crate.
+            optional --krate name: String
+            /// Write the pages to this directory instead of `./highlight`.
+            optional --output output: PathBuf
         }
 
         /// Batch typecheck project and print summary statistics
         cmd analysis-stats
-            /// Directory with Cargo.toml.
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
             required path: PathBuf
         {
             /// Randomize order in which crates, modules, and items are processed.
@@ -59,13 +120,20 @@ xflags::xflags! {
             optional --parallel
             /// Collect memory usage statistics.
             optional --memory-usage
+            /// Save the memory usage per query group and query to this file.
+            optional --save-memory-usage path: PathBuf
+            /// Compare the memory usage with a file written by `--save-memory-usage`.
+            optional --compare-memory-usage path: PathBuf
 
             /// Only analyze items matching this path.
             optional -o, --only path: String
             /// Also analyze all dependencies.
             optional --with-deps
-            /// Don't load sysroot crates (`std`, `core` & friends).
-            optional --no-sysroot
+            /// Only analyze this crate and its dependencies, can be given
+            /// several times.
+            repeated --krate name: String
+            /// Don't analyze this crate, can be given several times.
+            repeated --exclude name: String
 
             /// Don't run build scripts or load `OUT_DIR` values by running `cargo check` before analysis.
             optional --disable-build-scripts
@@ -73,37 +141,319 @@ xflags::xflags! {
             optional --disable-proc-macros
             /// Only resolve names, don't run type inference.
             optional --skip-inference
+
+            /// Report lowering, inference and diagnostics time for every crate.
+            optional --per-crate
+            /// Write the per-crate report as JSON to this file (implies `--per-crate`).
+            optional --json path: PathBuf
+            /// Keep running and re-run the analysis whenever a file changes.
+            optional --watch
+            /// Load another workspace as well, can be given several times.
+            repeated --workspace path: PathBuf
+            /// Load all workspaces below the given directories.
+            optional --recursive
         }
 
         cmd diagnostics
-            /// Directory with Cargo.toml.
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
             required path: PathBuf
         {
             /// Don't run build scripts or load `OUT_DIR` values by running `cargo check` before analysis.
             optional --disable-build-scripts
             /// Don't use expand proc macros.
             optional --disable-proc-macros
+            /// Output format: `text` (default) or `sarif`.
+            optional --format format: DiagnosticsFormat
+            /// Keep running and re-check whenever a file changes.
+            optional --watch
+            /// Check another workspace as well, can be given several times.
+            repeated --workspace path: PathBuf
+            /// Check all workspaces below the given directories.
+            optional --recursive
         }
 
         cmd ssr
             /// A structured search replace rule (`$a.foo($b) ==> bar($a, $b)`)
             repeated rule: SsrRule
-        {}
+        {
+            /// Reads additional rules from a file, one rule per line.
+            optional --rules-file path: PathBuf
+            /// Prints unified diffs instead of rewriting files.
+            optional --dry-run
+            /// Only rewrites files matching the glob.
+            repeated --include glob: String
+            /// Doesn't rewrite files matching the glob.
+            repeated --exclude glob: String
+        }
 
+        /// Print the code matching structural search patterns, together with
+        /// the code bound to their placeholders.
         cmd search
             /// A structured search replace pattern (`$a.foo($b)`)
             repeated pattern: SsrPattern
         {
             /// Prints debug information for any nodes with source exactly equal to snippet.
             optional --debug snippet: String
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
         }
 
-        cmd proc-macro {}
+        cmd proc-macro {
+            /// Instead of running the server, check whether the proc macro
+            /// dylib can be loaded and expanded, and print what was found.
+            optional --probe dylib: PathBuf
+        }
 
         cmd json-change
-            /// Directory with Cargo.toml.
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            required path: PathBuf
+        {
+            /// Write a memory-mappable snapshot to this path instead of `./change.json`.
+            optional --snapshot snapshot: PathBuf
+        }
+
+        /// Export a SCIP index of the workspace.
+        cmd scip
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
             required path: PathBuf
+        {
+            /// Write the index to this file instead of `index.scip`.
+            optional --output output: PathBuf
+        }
+
+        /// Print the crate dependency tree rust-analyzer sees.
+        cmd deps
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            optional path: PathBuf
+        {
+            /// Print the crates depending on the roots instead.
+            optional --invert
+            /// Only print the tree rooted at the crate with this name.
+            optional --filter name: String
+        }
+
+        /// Report dependencies and features which are never used.
+        cmd unused-deps
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            optional path: PathBuf
+        {}
+
+        /// Print the expansion of the macro call at a position.
+        cmd expand-macro
+            /// Position inside of the macro call (`src/main.rs:10:5`).
+            required position: String
+        {
+            /// Expand at most this many levels of nested macro calls.
+            optional --step n: usize
+            /// Print every level of nested macro calls as a separate step.
+            optional --steps
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// Export the function call graph of workspace crates.
+        cmd call-graph
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            optional path: PathBuf
+        {
+            /// Only include calls from this crate, can be given several times.
+            repeated --krate name: String
+            /// Emit JSON instead of GraphViz "dot" syntax.
+            optional --json
+            /// Write the graph to this file instead of stdout.
+            optional -o, --output output: PathBuf
+        }
+
+        /// Run the diagnostics of the workspace like a linter and fail if any
+        /// of them is denied.
+        cmd lint
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional path: PathBuf
+        {
+            /// JSON file mapping diagnostic codes to `allow`, `warn` or `deny`.
+            optional --lints path: PathBuf
+        }
+
+        /// Measure the latency of a single query at a position, right after an
+        /// edit of the file and once more with warm caches.
+        cmd bench {
+            /// The query to measure: `completion`, `goto-def` or `highlight`.
+            required --what what: BenchWhat
+            /// Position as `file:line:column`, with 1-based lines and columns.
+            required --position position: String
+            /// Number of measurements of each kind, defaults to 10.
+            optional -n, --iterations n: usize
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// Print the inferred type of the expression or pattern at a position.
+        cmd type-at
+            /// Position as `file:line:column`, with 1-based lines and columns.
+            required position: String
+        {
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// Find all references of the items at the given positions and print
+        /// them as JSON.
+        cmd references
+            /// Positions as `file:line:column`, with 1-based lines and columns.
+            repeated position: String
+        {
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// Rename the item at a position across the workspace.
+        cmd rename
+            /// Position as `file:line:column`, with 1-based lines and columns.
+            required position: String
+            required new_name: String
+        {
+            /// Print the edit as a diff instead of writing it to disk.
+            optional --dry-run
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// List the tests, benches, doctests and binaries of the workspace with
+        /// the cargo arguments that run them, as JSON.
+        cmd runnables
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional path: PathBuf
+        {}
+
+        /// Apply an assist or quick fix, selected by its id, at a position or range.
+        cmd assist
+            /// Position as `file:line:column` or range as `file:line:column-line:column`.
+            required range: String
+        {
+            /// Id of the assist, like `convert_to_guarded_return`.
+            required --id id: String
+            /// Print the edit as a diff instead of writing it to disk.
+            optional --dry-run
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// Print the hover contents at positions as Markdown.
+        cmd hover
+            /// Positions as `file:line:column`, with 1-based lines and columns.
+            repeated position: String
+        {
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// Resolve the intra-doc links in workspace crates, report the broken
+        /// ones and print all of them as JSON.
+        cmd doc-links
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional path: PathBuf
         {}
+
+        /// List the `#[cfg]`, `#[cfg_attr]` and `cfg!` sites of the workspace
+        /// crates mentioning a cargo feature as JSON, grouped by crate.
+        cmd cfg-usages
+            required feature: String
+        {
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// Export the crate graph rust-analyzer sees.
+        cmd crate-graph
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional path: PathBuf
+        {
+            /// Emit GraphViz "dot" syntax, the default.
+            optional --dot
+            /// Emit JSON instead of "dot".
+            optional --json
+            /// Only show workspace crates and their path dependencies, like the
+            /// other commands analyze by default.
+            optional --workspace-only
+            /// Show copies of the same sysroot or dependency crate as one node.
+            optional --dedup-sysroot
+        }
+
+        /// Check a snapshot written by `json-change --snapshot` against the
+        /// workspace, reporting changed files, crates and cfgs.
+        cmd verify-snapshot
+            required snapshot: PathBuf
+        {
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory, if it contains a workspace.
+            optional --workspace path: PathBuf
+        }
+
+        /// Apply the quick fixes of all diagnostics with the given codes.
+        cmd fix
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional path: PathBuf
+        {
+            /// Diagnostic code to fix, like `unresolved-module`. Can be repeated.
+            repeated --code code: String
+            /// Print a diff instead of modifying any files.
+            optional --dry-run
+        }
+
+        /// Print the completions at a position as JSON, ranked like an editor
+        /// would show them.
+        cmd completions
+            /// A `file:line:column` position, or a `file:line:column-line:column`
+            /// range which is replaced by the `--insert` text.
+            required position: String
+        {
+            /// Insert this text at the position and complete right after it.
+            optional --insert text: String
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
+
+        /// Print the inlay hints of the workspace as JSON, keyed by the
+        /// position they are shown at.
+        cmd inlay-hints
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional path: PathBuf
+        {
+            /// Only print the hints of this file.
+            optional --file file: PathBuf
+            /// Only print the hints of the files of this crate.
+            optional --krate name: String
+            /// Truncate hints longer than this many characters.
+            optional --max-length n: usize
+        }
+
+        /// Print the lowered body of a function, like the "View Hir" editor
+        /// command.
+        cmd view-hir
+            /// A `file:line:column` position inside the function, or its path
+            /// like `krate::module::Type::method`.
+            required target: String
+        {
+            /// Directory with Cargo.toml or rust-project.json, or the manifest itself.
+            /// Defaults to the current directory.
+            optional --workspace path: PathBuf
+        }
     }
 }
 
@@ -117,6 +467,18 @@ pub struct RustAnalyzer {
     pub log_file: Option<PathBuf>,
     pub no_log_buffering: bool,
     pub wait_dbg: bool,
+    pub features: Vec<String>,
+    pub package_features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub target: Option<String>,
+    pub no_sysroot: bool,
+    pub sysroot: Option<PathBuf>,
+    pub sysroot_src: Option<PathBuf>,
+    pub offline: bool,
+    pub config: Option<PathBuf>,
+    pub progress: Option<ProgressFormat>,
+    pub json_errors: bool,
     pub subcommand: RustAnalyzerCmd,
 }
 
@@ -132,6 +494,27 @@ pub enum RustAnalyzerCmd {
     Search(Search),
     ProcMacro(ProcMacro),
     JsonChange(JsonChange),
+    Scip(Scip),
+    Deps(Deps),
+    UnusedDeps(UnusedDeps),
+    ExpandMacro(ExpandMacro),
+    CallGraph(CallGraph),
+    Lint(Lint),
+    Bench(Bench),
+    TypeAt(TypeAt),
+    References(References),
+    Rename(Rename),
+    Runnables(Runnables),
+    Assist(Assist),
+    Hover(Hover),
+    DocLinks(DocLinks),
+    CfgUsages(CfgUsages),
+    CrateGraph(CrateGraph),
+    VerifySnapshot(VerifySnapshot),
+    Fix(Fix),
+    Completions(Completions),
+    InlayHints(InlayHints),
+    ViewHir(ViewHir),
 }
 
 #[derive(Debug)]
@@ -147,11 +530,20 @@ pub struct Parse {
 }
 
 #[derive(Debug)]
-pub struct Symbols;
+pub struct Symbols {
+    pub path: Option<PathBuf>,
+
+    pub with_deps: bool,
+}
 
 #[derive(Debug)]
 pub struct Highlight {
+    pub path: Option<PathBuf>,
+
     pub rainbow: bool,
+    pub file: Option<PathBuf>,
+    pub krate: Option<String>,
+    pub output: Option<PathBuf>,
 }
 
 #[derive(Debug)]
@@ -161,12 +553,20 @@ pub struct AnalysisStats {
     pub randomize: bool,
     pub parallel: bool,
     pub memory_usage: bool,
+    pub save_memory_usage: Option<PathBuf>,
+    pub compare_memory_usage: Option<PathBuf>,
     pub only: Option<String>,
     pub with_deps: bool,
-    pub no_sysroot: bool,
+    pub krate: Vec<String>,
+    pub exclude: Vec<String>,
     pub disable_build_scripts: bool,
     pub disable_proc_macros: bool,
     pub skip_inference: bool,
+    pub per_crate: bool,
+    pub json: Option<PathBuf>,
+    pub watch: bool,
+    pub workspace: Vec<PathBuf>,
+    pub recursive: bool,
 }
 
 #[derive(Debug)]
@@ -175,11 +575,20 @@ pub struct Diagnostics {
 
     pub disable_build_scripts: bool,
     pub disable_proc_macros: bool,
+    pub format: Option<DiagnosticsFormat>,
+    pub watch: bool,
+    pub workspace: Vec<PathBuf>,
+    pub recursive: bool,
 }
 
 #[derive(Debug)]
 pub struct Ssr {
     pub rule: Vec<SsrRule>,
+
+    pub rules_file: Option<PathBuf>,
+    pub dry_run: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -187,10 +596,13 @@ pub struct Search {
     pub pattern: Vec<SsrPattern>,
 
     pub debug: Option<String>,
+    pub workspace: Option<PathBuf>,
 }
 
 #[derive(Debug)]
-pub struct ProcMacro;
+pub struct ProcMacro {
+    pub probe: Option<PathBuf>,
+}
 
 impl RustAnalyzer {
     pub const HELP: &'static str = Self::HELP_;
@@ -203,11 +615,189 @@ impl RustAnalyzer {
 #[derive(Debug)]
 pub struct JsonChange {
     pub path: PathBuf,
+
+    pub snapshot: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Scip {
+    pub path: PathBuf,
+
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Deps {
+    pub path: Option<PathBuf>,
+
+    pub invert: bool,
+    pub filter: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct UnusedDeps {
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct ExpandMacro {
+    pub position: String,
+
+    pub step: Option<usize>,
+    pub steps: bool,
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct CallGraph {
+    pub path: Option<PathBuf>,
+
+    pub krate: Vec<String>,
+    pub json: bool,
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Lint {
+    pub path: Option<PathBuf>,
+
+    pub lints: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Bench {
+    pub what: BenchWhat,
+    pub position: String,
+    pub iterations: Option<usize>,
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct TypeAt {
+    pub position: String,
+
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct References {
+    pub position: Vec<String>,
+
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Rename {
+    pub position: String,
+    pub new_name: String,
+
+    pub dry_run: bool,
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Runnables {
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Assist {
+    pub range: String,
+
+    pub id: String,
+    pub dry_run: bool,
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Hover {
+    pub position: Vec<String>,
+
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct DocLinks {
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct CfgUsages {
+    pub feature: String,
+
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct CrateGraph {
+    pub path: Option<PathBuf>,
+
+    pub dot: bool,
+    pub json: bool,
+    pub workspace_only: bool,
+    pub dedup_sysroot: bool,
+}
+
+#[derive(Debug)]
+pub struct VerifySnapshot {
+    pub snapshot: PathBuf,
+
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Fix {
+    pub path: Option<PathBuf>,
+
+    pub code: Vec<String>,
+    pub dry_run: bool,
+}
+
+#[derive(Debug)]
+pub struct Completions {
+    pub position: String,
+
+    pub insert: Option<String>,
+    pub workspace: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct InlayHints {
+    pub path: Option<PathBuf>,
+
+    pub file: Option<PathBuf>,
+    pub krate: Option<String>,
+    pub max_length: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ViewHir {
+    pub target: String,
+
+    pub workspace: Option<PathBuf>,
 }
 
 // generated end
 
+/// `crate` is a keyword, so xflags can't name a field after a `--crate` flag.
+/// The commands declare the flag as `--krate`, and the `--crate` users see is
+/// mapped onto it when parsing and in the help.
+const CRATE_FLAG: &str = "--crate";
+const KRATE_FLAG: &str = "--krate";
+
 impl RustAnalyzer {
+    pub(crate) fn from_env_args() -> Result<RustAnalyzer, String> {
+        let args = env::args_os()
+            .skip(1)
+            .map(|arg| if arg == CRATE_FLAG { KRATE_FLAG.into() } else { arg })
+            .collect();
+        RustAnalyzer::from_vec_(args).map_err(|err| err.to_string().replace(KRATE_FLAG, CRATE_FLAG))
+    }
+
+    pub(crate) fn help() -> String {
+        RustAnalyzer::HELP.replace(KRATE_FLAG, CRATE_FLAG)
+    }
+
     pub(crate) fn verbosity(&self) -> Verbosity {
         if self.quiet {
             return Verbosity::Quiet;
@@ -218,4 +808,54 @@ impl RustAnalyzer {
             _ => Verbosity::Spammy,
         }
     }
+
+    /// Applies the cargo flags on top of `base`, which comes from the config
+    /// file.
+    pub(crate) fn cargo_config(&self, base: CargoConfig) -> Result<CargoConfig> {
+        let split = |it: &str| {
+            it.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|it| !it.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        let features: Vec<String> = self.features.iter().flat_map(|it| split(it)).collect();
+        let mut config = base;
+        if !features.is_empty() {
+            config.features = features;
+        }
+        for spec in &self.package_features {
+            let mut parts = spec.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(package), Some(features)) if !package.is_empty() => {
+                    config.package_features.insert(package.to_string(), split(features));
+                }
+                _ => {
+                    return Err(format!(
+                        "invalid --package-features `{}`, expected `package=features`",
+                        spec
+                    )
+                    .into())
+                }
+            }
+        }
+        config.all_features |= self.all_features;
+        config.no_default_features |= self.no_default_features;
+        if let Some(target) = &self.target {
+            config.target = Some(target.clone());
+        }
+        config.no_sysroot |= self.no_sysroot;
+        if let Some(sysroot) = &self.sysroot {
+            config.sysroot = Some(absolute(sysroot)?);
+        }
+        if let Some(sysroot_src) = &self.sysroot_src {
+            config.sysroot_src = Some(absolute(sysroot_src)?);
+        }
+        config.offline |= self.offline;
+        Ok(config)
+    }
+}
+
+/// Resolves `path` against the current directory.
+fn absolute(path: &Path) -> Result<AbsPathBuf> {
+    Ok(AbsPathBuf::assert(env::current_dir()?.join(path)))
 }
@@ -1,16 +1,30 @@
 //! Driver for rust-analyzer.
 //!
 //! Based on cli flags, either spawns an LSP server, or runs a batch analysis
+// The arguments are parsed with `RustAnalyzer::from_env_args`, which leaves the
+// generated `RustAnalyzer::from_env` unused.
+#[allow(dead_code)]
 mod flags;
 mod logger;
 mod rustc_wrapper;
 
-use std::{convert::TryFrom, env, fs, path::Path, process};
+use std::{
+    convert::TryFrom,
+    env, fs, iter,
+    path::{Path, PathBuf},
+    process,
+};
 
+use ide::DiagnosticsConfig;
 use lsp_server::Connection;
-use project_model::ProjectManifest;
+use project_model::{CargoConfig, ProjectManifest};
 use rust_analyzer::{
-    cli::{self, AnalysisStatsCmd, JsonChangeCmd},
+    cli::{
+        self, AnalysisStatsCmd, AssistCmd, BenchCmd, CallGraphCmd, CfgUsagesCmd, CompletionsCmd,
+        CrateGraphCmd, DepsCmd, DocLinksCmd, ExpandMacroCmd, FixCmd, HighlightCmd, HoverCmd,
+        InlayHintsCmd, JsonChangeCmd, LintCmd, LoadError, ReferencesCmd, RenameCmd, RunnablesCmd,
+        ScipCmd, SsrCmd, TypeAtCmd, UnusedDepsCmd, VerifySnapshotCmd, ViewHirCmd,
+    },
     config::Config,
     from_json,
     lsp_ext::supports_utf8,
@@ -41,16 +55,43 @@ fn main() {
         process::exit(code);
     }
 
-    if let Err(err) = try_main() {
+    let flags = match flags::RustAnalyzer::from_env_args() {
+        Ok(it) => it,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(101);
+        }
+    };
+    let json_errors = flags.json_errors;
+    if let Err(err) = try_main(flags) {
         log::error!("Unexpected error: {}", err);
-        eprintln!("{}", err);
-        process::exit(101);
+        report_error(&*err, json_errors);
     }
 }
 
-fn try_main() -> Result<()> {
-    let flags = flags::RustAnalyzer::from_env()?;
+/// Prints `err` and exits. Failures to load the workspace get their own exit
+/// codes, everything else exits with 101.
+fn report_error(err: &(dyn std::error::Error + 'static), json: bool) -> ! {
+    let load_error =
+        iter::successors(Some(err), |it| it.source()).find_map(|it| it.downcast_ref::<LoadError>());
+    let (kind, exit_code) = match load_error {
+        Some(it) => (it.kind(), it.exit_code()),
+        None => ("other", 101),
+    };
+    if json {
+        let report = serde_json::json!({
+            "kind": kind,
+            "message": err.to_string(),
+            "exit_code": exit_code,
+        });
+        eprintln!("{}", report);
+    } else {
+        eprintln!("{}", err);
+    }
+    process::exit(exit_code);
+}
 
+fn try_main(flags: flags::RustAnalyzer) -> Result<()> {
     #[cfg(debug_assertions)]
     if flags.wait_dbg || env::var("RA_WAIT_DBG").is_ok() {
         #[allow(unused_mut)]
@@ -69,6 +110,19 @@ fn try_main() -> Result<()> {
 
     setup_logging(log_file, flags.no_log_buffering)?;
     let verbosity = flags.verbosity();
+    // The language server is configured by its client, and neither it nor the
+    // proc-macro server or the parser analyze a workspace.
+    let config = match flags.subcommand {
+        flags::RustAnalyzerCmd::LspServer(_)
+        | flags::RustAnalyzerCmd::ProcMacro(_)
+        | flags::RustAnalyzerCmd::Parse(_) => None,
+        _ => load_config(flags.config.as_deref())?,
+    };
+    let cargo_config =
+        flags.cargo_config(config.as_ref().map_or_else(CargoConfig::default, Config::cargo))?;
+    let diagnostics_config =
+        config.as_ref().map_or_else(DiagnosticsConfig::default, Config::diagnostics);
+    let progress = flags.progress.unwrap_or_default();
 
     match flags.subcommand {
         flags::RustAnalyzerCmd::LspServer(cmd) => {
@@ -81,39 +135,284 @@ fn try_main() -> Result<()> {
                 return Ok(());
             }
             if cmd.help {
-                println!("{}", flags::RustAnalyzer::HELP);
+                println!("{}", flags::RustAnalyzer::help());
                 return Ok(());
             }
             run_server()?
         }
-        flags::RustAnalyzerCmd::ProcMacro(_) => proc_macro_srv::cli::run()?,
+        flags::RustAnalyzerCmd::ProcMacro(cmd) => match cmd.probe {
+            Some(dylib) => proc_macro_srv::cli::probe(&dylib)?,
+            None => proc_macro_srv::cli::run()?,
+        },
         flags::RustAnalyzerCmd::Parse(cmd) => cli::parse(cmd.no_dump)?,
-        flags::RustAnalyzerCmd::Symbols(_) => cli::symbols()?,
-        flags::RustAnalyzerCmd::Highlight(cmd) => cli::highlight(cmd.rainbow)?,
+        flags::RustAnalyzerCmd::Symbols(cmd) => match cmd.path {
+            Some(path) => cli::dump_symbols(&path, cmd.with_deps, &cargo_config, progress)?,
+            None => cli::symbols()?,
+        },
+        flags::RustAnalyzerCmd::Highlight(cmd) => match cmd.path {
+            Some(path) => HighlightCmd {
+                path,
+                rainbow: cmd.rainbow,
+                file: cmd.file,
+                krate: cmd.krate,
+                output: cmd.output,
+                cargo_config,
+                progress,
+            }
+            .run()?,
+            None => cli::highlight(cmd.rainbow)?,
+        },
         flags::RustAnalyzerCmd::AnalysisStats(cmd) => AnalysisStatsCmd {
             randomize: cmd.randomize,
             parallel: cmd.parallel,
             memory_usage: cmd.memory_usage,
+            save_memory_usage: cmd.save_memory_usage,
+            compare_memory_usage: cmd.compare_memory_usage,
             only: cmd.only,
             with_deps: cmd.with_deps,
-            no_sysroot: cmd.no_sysroot,
+            crates: cmd.krate,
+            exclude: cmd.exclude,
             path: cmd.path,
             enable_build_scripts: !cmd.disable_build_scripts,
             enable_proc_macros: !cmd.disable_proc_macros,
             skip_inference: cmd.skip_inference,
+            per_crate: cmd.per_crate,
+            json: cmd.json,
+            watch: cmd.watch,
+            workspaces: cmd.workspace,
+            recursive: cmd.recursive,
+            cargo_config,
+            progress,
         }
         .run(verbosity)?,
 
-        flags::RustAnalyzerCmd::Diagnostics(cmd) => {
-            cli::diagnostics(&cmd.path, !cmd.disable_build_scripts, !cmd.disable_proc_macros)?
+        flags::RustAnalyzerCmd::Diagnostics(cmd) => cli::diagnostics(
+            &iter::once(cmd.path).chain(cmd.workspace).collect::<Vec<_>>(),
+            cmd.recursive,
+            !cmd.disable_build_scripts,
+            !cmd.disable_proc_macros,
+            cmd.format.unwrap_or_default(),
+            cmd.watch,
+            &cargo_config,
+            &diagnostics_config,
+            progress,
+        )?,
+        flags::RustAnalyzerCmd::Ssr(cmd) => SsrCmd {
+            rules: cmd.rule,
+            rules_file: cmd.rules_file,
+            dry_run: cmd.dry_run,
+            include: cmd.include,
+            exclude: cmd.exclude,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::Search(cmd) => {
+            let path = cmd.workspace.unwrap_or_else(|| PathBuf::from("."));
+            cli::search_for_patterns(&path, cmd.pattern, cmd.debug, &cargo_config, progress)?
+        }
+        flags::RustAnalyzerCmd::JsonChange(cmd) => {
+            JsonChangeCmd { snapshot: cmd.snapshot, cargo_config, progress }.run(&cmd.path)?
+        }
+        flags::RustAnalyzerCmd::Scip(cmd) => {
+            ScipCmd { path: cmd.path, output: cmd.output, cargo_config, progress }.run()?
+        }
+        flags::RustAnalyzerCmd::Deps(cmd) => DepsCmd {
+            path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+            invert: cmd.invert,
+            filter: cmd.filter,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::UnusedDeps(cmd) => UnusedDepsCmd {
+            path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::ExpandMacro(cmd) => ExpandMacroCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            position: cmd.position,
+            step: cmd.step,
+            steps: cmd.steps,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::CallGraph(cmd) => CallGraphCmd {
+            path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+            crates: cmd.krate,
+            json: cmd.json,
+            output: cmd.output,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::Lint(cmd) => {
+            let passed = LintCmd {
+                path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+                lints: cmd.lints,
+                diagnostics_config,
+                cargo_config,
+                progress,
+            }
+            .run()?;
+            if !passed {
+                process::exit(1);
+            }
+        }
+        flags::RustAnalyzerCmd::Bench(cmd) => BenchCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            what: cmd.what,
+            position: cmd.position,
+            iterations: cmd.iterations.unwrap_or(10),
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::TypeAt(cmd) => TypeAtCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            position: cmd.position,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::References(cmd) => ReferencesCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            positions: cmd.position,
+            cargo_config,
+            progress,
         }
-        flags::RustAnalyzerCmd::Ssr(cmd) => cli::apply_ssr_rules(cmd.rule)?,
-        flags::RustAnalyzerCmd::Search(cmd) => cli::search_for_patterns(cmd.pattern, cmd.debug)?,
-        flags::RustAnalyzerCmd::JsonChange(cmd) => JsonChangeCmd {}.run(&cmd.path)?,
+        .run()?,
+        flags::RustAnalyzerCmd::Rename(cmd) => RenameCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            position: cmd.position,
+            new_name: cmd.new_name,
+            dry_run: cmd.dry_run,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::Runnables(cmd) => RunnablesCmd {
+            path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::Assist(cmd) => AssistCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            id: cmd.id,
+            range: cmd.range,
+            dry_run: cmd.dry_run,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::Hover(cmd) => HoverCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            positions: cmd.position,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::DocLinks(cmd) => DocLinksCmd {
+            path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::CfgUsages(cmd) => CfgUsagesCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            feature: cmd.feature,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::CrateGraph(cmd) => {
+            if cmd.dot && cmd.json {
+                return Err("--dot and --json are mutually exclusive".into());
+            }
+            CrateGraphCmd {
+                path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+                json: cmd.json,
+                workspace_only: cmd.workspace_only,
+                dedup_sysroot: cmd.dedup_sysroot,
+                cargo_config,
+                progress,
+            }
+            .run()?
+        }
+        flags::RustAnalyzerCmd::VerifySnapshot(cmd) => VerifySnapshotCmd {
+            snapshot: cmd.snapshot,
+            workspace: cmd.workspace,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::Fix(cmd) => FixCmd {
+            path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+            codes: cmd.code,
+            dry_run: cmd.dry_run,
+            diagnostics_config,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::Completions(cmd) => CompletionsCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            position: cmd.position,
+            insert: cmd.insert,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::InlayHints(cmd) => InlayHintsCmd {
+            path: cmd.path.unwrap_or_else(|| PathBuf::from(".")),
+            file: cmd.file,
+            krate: cmd.krate,
+            max_length: cmd.max_length,
+            cargo_config,
+            progress,
+        }
+        .run()?,
+        flags::RustAnalyzerCmd::ViewHir(cmd) => ViewHirCmd {
+            path: cmd.workspace.unwrap_or_else(|| PathBuf::from(".")),
+            target: cmd.target,
+            cargo_config,
+            progress,
+        }
+        .run()?,
     }
     Ok(())
 }
 
+/// Reads the settings of `--config`, or of the closest `rust-analyzer.toml`.
+/// Relative paths in them are resolved against the directory of the file.
+fn load_config(path: Option<&Path>) -> Result<Option<Config>> {
+    let cwd = env::current_dir()?;
+    let path = match path {
+        Some(it) => cwd.join(it),
+        None => {
+            let discovered =
+                cwd.ancestors().map(|dir| dir.join("rust-analyzer.toml")).find(|it| it.is_file());
+            match discovered {
+                Some(it) => it,
+                None => return Ok(None),
+            }
+        }
+    };
+    let text = fs::read_to_string(&path)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+    let value: toml::Value = toml::from_str(&text)
+        .map_err(|err| format!("invalid config {}: {}", path.display(), err))?;
+    let json = serde_json::to_value(value)?;
+    let root = path.parent().map(Path::to_path_buf).unwrap_or(cwd);
+    let mut config = Config::new(AbsPathBuf::assert(root), Default::default());
+    config.update(json);
+    Ok(Some(config))
+}
+
 fn setup_logging(log_file: Option<&Path>, no_buffering: bool) -> Result<()> {
     env::set_var("RUST_BACKTRACE", "short");
 
@@ -215,7 +514,23 @@ fn run_server() -> Result<()> {
             .filter(|workspaces| !workspaces.is_empty())
             .unwrap_or_else(|| vec![config.root_path.clone()]);
 
-        let discovered = ProjectManifest::discover_all(&workspace_roots);
+        let discovered = match config.workspace_discovery() {
+            Some(discovery) => {
+                let mut res = workspace_roots
+                    .iter()
+                    .filter_map(|root| {
+                        ProjectManifest::discover_recursive(root, &discovery)
+                            .map_err(|e| log::error!("failed to search {}: {}", root.display(), e))
+                            .ok()
+                    })
+                    .flatten()
+                    .collect::<Vec<_>>();
+                res.sort();
+                res.dedup();
+                res
+            }
+            None => ProjectManifest::discover_all(&workspace_roots),
+        };
         log::info!("discovered projects: {:?}", discovered);
         if discovered.is_empty() {
             log::error!("failed to find any projects in {:?}", workspace_roots);
@@ -2,14 +2,21 @@
 //! initial `cargo check`. That way, we avoid checking the actual project, and
 //! only build proc macros and build.rs.
 //!
+//! Every invocation is also recorded, so that the crate graph can use the
+//! exact cfgs and environment crates are compiled with.
+//!
 //! Code taken from IntelliJ :0)
 //!     https://github.com/intellij-rust/intellij-rust/blob/master/native-helper/src/main.rs
 use std::{
+    env,
     ffi::OsString,
     io,
+    path::PathBuf,
     process::{Command, Stdio},
 };
 
+use project_model::RustcInvocation;
+
 /// ExitCode/ExitStatus are impossible to create :(.
 pub(crate) struct ExitCode(pub(crate) Option<i32>);
 
@@ -17,6 +24,14 @@ pub(crate) fn run_rustc_skipping_cargo_checking(
     rustc_executable: OsString,
     args: Vec<OsString>,
 ) -> io::Result<ExitCode> {
+    if let Some(dir) = env::var_os(RustcInvocation::DIR_ENV) {
+        // Failing to record only makes the crate graph less precise.
+        if let Err(err) =
+            RustcInvocation::current(&args).and_then(|it| it.record(&PathBuf::from(dir)))
+        {
+            eprintln!("failed to record rustc invocation: {}", err);
+        }
+    }
     let is_cargo_check = args.iter().any(|arg| {
         let arg = arg.to_string_lossy();
         // `cargo check` invokes `rustc` with `--emit=metadata` argument.
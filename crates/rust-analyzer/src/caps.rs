@@ -111,12 +111,13 @@ pub fn server_capabilities(config: &Config) -> ServerCapabilities {
             }
             .into(),
         ),
-        moniker_provider: None,
+        moniker_provider: Some(OneOf::Left(true)),
         experimental: Some(json!({
             "joinLines": true,
             "ssr": true,
             "onEnter": true,
             "parentModule": true,
+            "typeHierarchyProvider": true,
             "runnables": {
                 "kinds": [ "cargo" ],
             },
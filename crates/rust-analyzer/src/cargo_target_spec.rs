@@ -2,7 +2,7 @@
 
 use cfg::{CfgAtom, CfgExpr};
 use ide::{FileId, RunnableKind, TestId};
-use project_model::{self, TargetKind};
+use project_model::{self, CargoConfig, CargoWorkspace, Target, TargetKind};
 use vfs::AbsPathBuf;
 
 use crate::{global_state::GlobalStateSnapshot, Result};
@@ -22,7 +22,7 @@ pub(crate) struct CargoTargetSpec {
 
 impl CargoTargetSpec {
     pub(crate) fn runnable_args(
-        snap: &GlobalStateSnapshot,
+        cargo_config: &CargoConfig,
         spec: Option<CargoTargetSpec>,
         kind: &RunnableKind,
         cfg: &Option<CfgExpr>,
@@ -84,7 +84,6 @@ impl CargoTargetSpec {
             }
         }
 
-        let cargo_config = snap.config.cargo();
         if cargo_config.all_features {
             args.push("--all-features".to_string());
         } else {
@@ -92,7 +91,7 @@ impl CargoTargetSpec {
             if let Some(cfg) = cfg.as_ref() {
                 required_features(cfg, &mut features);
             }
-            for feature in cargo_config.features {
+            for feature in &cargo_config.features {
                 features.push(feature.clone());
             }
             features.dedup();
@@ -118,17 +117,19 @@ impl CargoTargetSpec {
             None => return Ok(None),
         };
 
+        Ok(Some(CargoTargetSpec::new(cargo_ws, target)))
+    }
+
+    pub(crate) fn new(cargo_ws: &CargoWorkspace, target: Target) -> CargoTargetSpec {
         let target_data = &cargo_ws[target];
         let package_data = &cargo_ws[target_data.package];
-        let res = CargoTargetSpec {
+        CargoTargetSpec {
             workspace_root: cargo_ws.workspace_root().to_path_buf(),
             cargo_toml: package_data.manifest.clone(),
             package: cargo_ws.package_flag(package_data),
             target: target_data.name.clone(),
             target_kind: target_data.kind,
-        };
-
-        Ok(Some(res))
+        }
     }
 
     pub(crate) fn push_to(self, buf: &mut Vec<String>, kind: &RunnableKind) {
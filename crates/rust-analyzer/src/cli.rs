@@ -2,23 +2,76 @@
 
 pub(crate) mod load_cargo;
 mod analysis_stats;
+mod assist;
+mod bench;
+mod call_graph;
+mod cfg_usages;
+mod completions;
+mod crate_graph;
 mod json_change;
+mod deps;
 mod diagnostics;
+mod doc_links;
+mod expand_macro;
+mod fix;
+mod highlight;
+mod hover;
+mod inlay_hints;
+mod lint;
+mod memory_usage;
+mod position;
 mod progress_report;
+mod references;
+mod rename;
+mod runnables;
+mod scip;
+mod snapshot;
+mod source_change;
 mod ssr;
+mod symbols;
+mod type_at;
+mod unused_deps;
+mod verify_snapshot;
+mod view_hir;
 
 use std::io::Read;
 
 use anyhow::Result;
-use ide::{Analysis, AnalysisHost};
+use hir::Crate;
+use ide::{Analysis, RootDatabase};
+use ide_db::base_db::{FileId, SourceDatabaseExt};
 use syntax::{AstNode, SourceFile};
-use vfs::Vfs;
 
 pub use self::{
     analysis_stats::AnalysisStatsCmd,
-    diagnostics::diagnostics,
+    assist::AssistCmd,
+    bench::{BenchCmd, BenchWhat},
+    call_graph::CallGraphCmd,
+    cfg_usages::CfgUsagesCmd,
+    completions::CompletionsCmd,
+    crate_graph::CrateGraphCmd,
+    deps::DepsCmd,
+    diagnostics::{diagnostics, DiagnosticsFormat},
+    doc_links::DocLinksCmd,
+    expand_macro::ExpandMacroCmd,
+    fix::FixCmd,
+    highlight::HighlightCmd,
+    hover::HoverCmd,
+    inlay_hints::InlayHintsCmd,
     json_change::JsonChangeCmd,
-    ssr::{apply_ssr_rules, search_for_patterns},
+    lint::LintCmd,
+    load_cargo::{LoadError, ProgressFormat},
+    references::ReferencesCmd,
+    rename::RenameCmd,
+    runnables::RunnablesCmd,
+    scip::ScipCmd,
+    snapshot::read_snapshot,
+    ssr::{search_for_patterns, SsrCmd},
+    symbols::dump_symbols,
+    type_at::TypeAtCmd,
+    unused_deps::UnusedDepsCmd,
+    verify_snapshot::VerifySnapshotCmd,
+    view_hir::ViewHirCmd,
 };
 
 #[derive(Clone, Copy)]
@@ -65,6 +118,19 @@ pub fn highlight(rainbow: bool) -> Result<()> {
     Ok(())
 }
 
+/// Whether `file_id` belongs to the loaded workspaces rather than to a
+/// dependency or the sysroot. Path dependencies outside of the workspace count
+/// as workspace files too, like the members themselves.
+fn is_workspace_file(db: &RootDatabase, file_id: FileId) -> bool {
+    !db.source_root(db.file_source_root(file_id)).is_library
+}
+
+/// Whether the root file of `krate` is a workspace file, see
+/// [`is_workspace_file`]. This is what commands mean by "workspace crates".
+fn is_workspace_crate(db: &RootDatabase, krate: Crate) -> bool {
+    is_workspace_file(db, krate.root_file(db))
+}
+
 fn file() -> Result<SourceFile> {
     let text = read_stdin()?;
     Ok(SourceFile::parse(&text).tree())
@@ -83,22 +149,52 @@ fn report_metric(metric: &str, value: u64, unit: &str) {
     println!("METRIC:{}:{}:{}", metric, value, unit)
 }
 
-fn print_memory_usage(mut host: AnalysisHost, vfs: Vfs) {
-    let mut mem = host.per_query_memory_usage();
-
-    let before = profile::memory_usage();
-    drop(vfs);
-    let vfs = before.allocated - profile::memory_usage().allocated;
-    mem.push(("VFS".into(), vfs));
-
-    let before = profile::memory_usage();
-    drop(host);
-    mem.push(("Unaccounted".into(), before.allocated - profile::memory_usage().allocated));
-
-    mem.push(("Remaining".into(), profile::memory_usage().allocated));
-
-    for (name, bytes) in mem {
-        // NOTE: Not a debug print, so avoid going through the `eprintln` defined above.
-        eprintln!("{:>8} {}", bytes, name);
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use cfg::CfgOptions;
+    use ide::Change;
+    use ide_db::base_db::{CrateGraph, Edition, Env, SourceRoot};
+    use vfs::{file_set::FileSet, VfsPath};
+
+    use super::*;
+
+    #[test]
+    fn workspace_crates() {
+        let mut local = FileSet::default();
+        local.insert(FileId(0), VfsPath::new_virtual_path("/ws/src/lib.rs".to_string()));
+        local.insert(FileId(1), VfsPath::new_virtual_path("/path_dep/src/lib.rs".to_string()));
+        let mut library = FileSet::default();
+        library
+            .insert(FileId(2), VfsPath::new_virtual_path("/registry/dep/src/lib.rs".to_string()));
+
+        let mut crate_graph = CrateGraph::default();
+        let mut change = Change::new();
+        for id in 0..3 {
+            change.change_file(FileId(id), Some(Arc::new(String::new())));
+            crate_graph.add_crate_root(
+                FileId(id),
+                Edition::CURRENT,
+                None,
+                CfgOptions::default(),
+                CfgOptions::default(),
+                Env::default(),
+                Vec::new(),
+            );
+        }
+        change.set_roots(vec![SourceRoot::new_local(local), SourceRoot::new_library(library)]);
+        change.set_crate_graph(crate_graph);
+        let mut db = RootDatabase::new(None);
+        db.apply_change(change);
+
+        let mut roots: Vec<_> = Crate::all(&db)
+            .into_iter()
+            .filter(|&krate| is_workspace_crate(&db, krate))
+            .map(|krate| krate.root_file(&db))
+            .collect();
+        roots.sort();
+        assert_eq!(roots, vec![FileId(0), FileId(1)]);
+        assert!(!is_workspace_file(&db, FileId(2)));
     }
 }
@@ -2,38 +2,38 @@
 //! errors.
 
 use std::{
-    env,
+    env, fs,
     path::PathBuf,
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use anyhow::bail;
 use hir::{
     db::{AstDatabase, DefDatabase, HirDatabase},
-    AssocItem, Crate, Function, HasSource, HirDisplay, ModuleDef,
+    AssocItem, Crate, Function, HasSource, HirDisplay, Module, ModuleDef,
 };
 use hir_def::{body::BodySourceMap, expr::ExprId, FunctionId};
 use hir_ty::{TyExt, TypeWalk};
 use ide::{Analysis, AnalysisHost, LineCol, RootDatabase};
-use ide_db::base_db::{
-    salsa::{self, ParallelDatabase},
-    SourceDatabaseExt,
-};
+use ide_db::base_db::salsa::{self, ParallelDatabase};
 use itertools::Itertools;
 use oorandom::Rand32;
 use project_model::CargoConfig;
 use rayon::prelude::*;
 use rustc_hash::FxHashSet;
+use serde::Serialize;
 use stdx::format_to;
 use syntax::AstNode;
 use vfs::{Vfs, VfsPath};
 
 use crate::cli::{
-    load_cargo::{load_workspace_at, LoadCargoConfig},
-    print_memory_usage,
+    is_workspace_crate,
+    load_cargo::{load_workspaces, LoadCargoConfig, ProgressFormat},
+    memory_usage::print_memory_usage,
     progress_report::ProgressReport,
     report_metric, Result, Verbosity,
 };
-use profile::StopWatch;
+use profile::{StopWatch, StopWatchSpan};
 
 /// Need to wrap Snapshot to provide `Clone` impl for `map_with`
 struct Snap<DB>(DB);
@@ -47,13 +47,58 @@ pub struct AnalysisStatsCmd {
     pub randomize: bool,
     pub parallel: bool,
     pub memory_usage: bool,
+    /// Write the memory usage per query to this file.
+    pub save_memory_usage: Option<PathBuf>,
+    /// Compare the memory usage per query with a file written by `save_memory_usage`.
+    pub compare_memory_usage: Option<PathBuf>,
     pub only: Option<String>,
     pub with_deps: bool,
-    pub no_sysroot: bool,
+    /// Only analyze the crates with these names and their dependencies.
+    pub crates: Vec<String>,
+    /// Don't analyze the crates with these names.
+    pub exclude: Vec<String>,
     pub path: PathBuf,
+    /// Additional workspaces to load next to `path`.
+    pub workspaces: Vec<PathBuf>,
+    /// Load all workspaces below the given paths.
+    pub recursive: bool,
     pub enable_build_scripts: bool,
     pub enable_proc_macros: bool,
     pub skip_inference: bool,
+    pub per_crate: bool,
+    pub json: Option<PathBuf>,
+    /// Keep the database alive and re-run the analysis whenever a file changes.
+    pub watch: bool,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+/// Time spent on a single crate, as written by `analysis-stats --json`.
+#[derive(Serialize)]
+struct CrateStats {
+    name: String,
+    functions: usize,
+    diagnostics: usize,
+    lowering: PhaseStats,
+    inference: PhaseStats,
+    diagnostics_time: PhaseStats,
+}
+
+#[derive(Serialize)]
+struct PhaseStats {
+    time_ms: u64,
+    instructions: Option<u64>,
+    memory_mb: Option<isize>,
+}
+
+impl From<StopWatchSpan> for PhaseStats {
+    fn from(span: StopWatchSpan) -> PhaseStats {
+        PhaseStats {
+            time_ms: span.time.as_millis() as u64,
+            instructions: span.instructions,
+            memory_mb: span.memory.map(|it| it.allocated.megabytes()),
+        }
+    }
 }
 
 impl AnalysisStatsCmd {
@@ -64,19 +109,62 @@ impl AnalysisStatsCmd {
         };
 
         let mut db_load_sw = self.stop_watch();
-        let mut cargo_config = CargoConfig::default();
-        cargo_config.no_sysroot = self.no_sysroot;
         let load_cargo_config = LoadCargoConfig {
             load_out_dirs_from_check: self.enable_build_scripts,
             wrap_rustc: false,
             with_proc_macro: self.enable_proc_macros,
             prefill_caches: false,
         };
-        let (host, vfs, _proc_macro) =
-            load_workspace_at(&self.path, &cargo_config, &load_cargo_config, &|_| {})?;
-        let db = host.raw_database();
+        let mut roots = vec![self.path.clone()];
+        roots.extend(self.workspaces.iter().cloned());
+        let (mut host, mut vfs, _proc_macro, mut watcher) = load_workspaces(
+            &roots,
+            self.recursive,
+            &self.cargo_config,
+            &load_cargo_config,
+            self.watch,
+            &|event| self.progress.report(event),
+        )?;
         eprintln!("{:<20} {}", "Database loaded:", db_load_sw.elapsed());
 
+        loop {
+            let crates = self.analyzed_crates(host.raw_database())?;
+            if self.per_crate || self.json.is_some() {
+                self.run_per_crate(host.raw_database(), &crates)?;
+            } else {
+                self.run_once(&host, &vfs, &crates, &mut rng, verbosity);
+            }
+            if !self.watch {
+                break;
+            }
+            eprintln!("watching for changes...");
+            match watcher.next_change(&mut vfs) {
+                Some(change) => host.apply_change(change),
+                None => break,
+            }
+        }
+
+        let save_memory_usage = self.save_memory_usage.as_deref();
+        let compare_memory_usage = self.compare_memory_usage.as_deref();
+        if (self.memory_usage && verbosity.is_verbose())
+            || save_memory_usage.is_some()
+            || compare_memory_usage.is_some()
+        {
+            print_memory_usage(host, vfs, save_memory_usage, compare_memory_usage)?;
+        }
+
+        Ok(())
+    }
+
+    fn run_once(
+        &self,
+        host: &AnalysisHost,
+        vfs: &Vfs,
+        crates: &FxHashSet<Crate>,
+        rng: &mut Rand32,
+        verbosity: Verbosity,
+    ) {
+        let db = host.raw_database();
         let mut analysis_sw = self.stop_watch();
         let mut num_crates = 0;
         let mut visited_modules = FxHashSet::default();
@@ -84,22 +172,17 @@ impl AnalysisStatsCmd {
 
         let mut krates = Crate::all(db);
         if self.randomize {
-            shuffle(&mut rng, &mut krates);
+            shuffle(rng, &mut krates);
         }
         for krate in krates {
-            let module = krate.root_module(db);
-            let file_id = module.definition_source(db).file_id;
-            let file_id = file_id.original_file(db);
-            let source_root = db.file_source_root(file_id);
-            let source_root = db.source_root(source_root);
-            if !source_root.is_library || self.with_deps {
+            if crates.contains(&krate) {
                 num_crates += 1;
-                visit_queue.push(module);
+                visit_queue.push(krate.root_module(db));
             }
         }
 
         if self.randomize {
-            shuffle(&mut rng, &mut visit_queue);
+            shuffle(rng, &mut visit_queue);
         }
 
         eprint!("  crates: {}", num_crates);
@@ -130,11 +213,11 @@ impl AnalysisStatsCmd {
         eprintln!("{:<20} {}", "Item Collection:", analysis_sw.elapsed());
 
         if self.randomize {
-            shuffle(&mut rng, &mut funcs);
+            shuffle(rng, &mut funcs);
         }
 
         if !self.skip_inference {
-            self.run_inference(&host, db, &vfs, &funcs, verbosity);
+            self.run_inference(host, db, vfs, &funcs, verbosity);
         }
 
         let total_span = analysis_sw.elapsed();
@@ -150,14 +233,119 @@ impl AnalysisStatsCmd {
         if env::var("RA_COUNT").is_ok() {
             eprintln!("{}", profile::countme::get_all());
         }
+    }
 
-        if self.memory_usage && verbosity.is_verbose() {
-            print_memory_usage(host, vfs);
+    /// Analyzes crates in dependency order, so that the time reported for a
+    /// crate doesn't include the time spent on its dependencies.
+    fn run_per_crate(&self, db: &RootDatabase, crates: &FxHashSet<Crate>) -> Result<()> {
+        let mut total_sw = self.stop_watch();
+        let mut stats = Vec::new();
+        for krate in topological_order(db, Crate::all(db)) {
+            if !crates.contains(&krate) {
+                // Still compute the def map, so that it isn't attributed to
+                // the first dependent crate.
+                krate.root_module(db);
+                continue;
+            }
+            let name = match krate.display_name(db) {
+                Some(it) => it.to_string(),
+                None => format!("{:?}", krate),
+            };
+
+            let mut sw = self.stop_watch();
+            let mut modules = Vec::new();
+            let mut funcs = Vec::new();
+            collect_items(db, krate.root_module(db), &mut modules, &mut funcs);
+            for &f in &funcs {
+                db.body(FunctionId::from(f).into());
+            }
+            let lowering = sw.elapsed();
+
+            let mut sw = self.stop_watch();
+            if !self.skip_inference {
+                for &f in &funcs {
+                    db.infer(FunctionId::from(f).into());
+                }
+            }
+            let inference = sw.elapsed();
+
+            let mut sw = self.stop_watch();
+            let mut diagnostics = Vec::new();
+            for module in modules {
+                module.diagnostics(db, &mut diagnostics);
+            }
+            let diagnostics_time = sw.elapsed();
+
+            stats.push(CrateStats {
+                name,
+                functions: funcs.len(),
+                diagnostics: diagnostics.len(),
+                lowering: lowering.into(),
+                inference: inference.into(),
+                diagnostics_time: diagnostics_time.into(),
+            });
+        }
+
+        eprintln!(
+            "{:<30} {:>6} {:>12} {:>12} {:>12}",
+            "crate", "fns", "lowering", "inference", "diagnostics"
+        );
+        for it in &stats {
+            eprintln!(
+                "{:<30} {:>6} {:>10}ms {:>10}ms {:>10}ms",
+                it.name,
+                it.functions,
+                it.lowering.time_ms,
+                it.inference.time_ms,
+                it.diagnostics_time.time_ms
+            );
         }
+        eprintln!("{:<20} {}", "Total:", total_sw.elapsed());
 
+        if let Some(path) = &self.json {
+            fs::write(path, serde_json::to_string_pretty(&stats)?)?;
+        }
         Ok(())
     }
 
+    /// The crates named with `--crate` and their dependencies, or all crates
+    /// if none are named, minus the ones named with `--exclude`. Library
+    /// crates are only analyzed with `--with-deps`.
+    fn analyzed_crates(&self, db: &RootDatabase) -> Result<FxHashSet<Crate>> {
+        let find = |name: &str| {
+            let crates = Crate::all(db)
+                .into_iter()
+                .filter(|it| it.display_name(db).as_deref() == Some(name))
+                .collect::<Vec<_>>();
+            if crates.is_empty() {
+                bail!("no crate named `{}`", name);
+            }
+            Ok(crates)
+        };
+
+        let mut crates = FxHashSet::default();
+        if self.crates.is_empty() {
+            crates.extend(Crate::all(db));
+        } else {
+            let mut worklist = Vec::new();
+            for name in &self.crates {
+                worklist.extend(find(name)?);
+            }
+            while let Some(krate) = worklist.pop() {
+                if crates.insert(krate) {
+                    worklist.extend(krate.dependencies(db).into_iter().map(|dep| dep.krate));
+                }
+            }
+        }
+        for name in &self.exclude {
+            for krate in find(name)? {
+                crates.remove(&krate);
+            }
+        }
+        crates.retain(|&krate| self.with_deps || is_workspace_crate(db, krate));
+        Ok(crates)
+    }
+
     fn run_inference(
         &self,
         host: &AnalysisHost,
@@ -335,6 +523,49 @@ impl AnalysisStatsCmd {
     }
 }
 
+fn topological_order(db: &RootDatabase, krates: Vec<Crate>) -> Vec<Crate> {
+    let mut res = Vec::new();
+    let mut visited = FxHashSet::default();
+    for krate in krates {
+        go(db, krate, &mut visited, &mut res);
+    }
+    return res;
+
+    fn go(db: &RootDatabase, krate: Crate, visited: &mut FxHashSet<Crate>, res: &mut Vec<Crate>) {
+        if !visited.insert(krate) {
+            return;
+        }
+        for dep in krate.dependencies(db) {
+            go(db, dep.krate, visited, res);
+        }
+        res.push(krate);
+    }
+}
+
+fn collect_items(
+    db: &RootDatabase,
+    module: Module,
+    modules: &mut Vec<Module>,
+    funcs: &mut Vec<Function>,
+) {
+    for decl in module.declarations(db) {
+        if let ModuleDef::Function(f) = decl {
+            funcs.push(f);
+        }
+    }
+    for impl_def in module.impl_defs(db) {
+        for item in impl_def.items(db) {
+            if let AssocItem::Function(f) = item {
+                funcs.push(f);
+            }
+        }
+    }
+    modules.push(module);
+    for child in module.children(db) {
+        collect_items(db, child, modules, funcs);
+    }
+}
+
 fn expr_syntax_range(
     db: &RootDatabase,
     analysis: &Analysis,
@@ -0,0 +1,112 @@
+//! Applies a single assist, selected by its id, at a position or range.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use ide::{AssistConfig, AssistResolveStrategy, DiagnosticsConfig, SingleResolve};
+use ide_db::helpers::insert_use::{ImportGranularity, InsertUseConfig};
+use project_model::CargoConfig;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliRange,
+    source_change::write_source_change,
+    Result,
+};
+
+pub struct AssistCmd {
+    pub path: PathBuf,
+    /// The id of the assist, like `convert_to_guarded_return`.
+    pub id: String,
+    /// A `file:line:column` position or `file:line:column-line:column` range.
+    pub range: String,
+    /// Print a diff instead of modifying any files.
+    pub dry_run: bool,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl AssistCmd {
+    pub fn run(self) -> Result<()> {
+        let range: CliRange = self.range.parse()?;
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+        let frange = range.resolve(&analysis, &vfs)?;
+
+        let config = AssistConfig {
+            // Snippets can't be expanded outside of an editor.
+            snippet_cap: None,
+            allowed: None,
+            insert_use: InsertUseConfig {
+                granularity: ImportGranularity::Crate,
+                prefix_kind: hir::PrefixKind::ByCrate,
+                enforce_granularity: false,
+                group: true,
+                skip_glob_imports: true,
+            },
+        };
+        let diagnostics_config = DiagnosticsConfig::default();
+
+        let available = analysis.assists_with_fixes(
+            &config,
+            &diagnostics_config,
+            AssistResolveStrategy::None,
+            frange,
+        )?;
+        let kind = match available.iter().find(|it| it.id.0 == self.id) {
+            Some(it) => it.id.1,
+            None => {
+                let mut ids = available.iter().map(|it| it.id.0).collect::<Vec<_>>();
+                ids.sort_unstable();
+                ids.dedup();
+                if ids.is_empty() {
+                    bail!("no assists are available at {}", range);
+                }
+                bail!(
+                    "assist `{}` is not available at {}, available are: {}",
+                    self.id,
+                    range,
+                    ids.join(", ")
+                );
+            }
+        };
+
+        let resolve = AssistResolveStrategy::Single(SingleResolve {
+            assist_id: self.id.clone(),
+            assist_kind: kind,
+        });
+        let mut assists = analysis
+            .assists_with_fixes(&config, &diagnostics_config, resolve, frange)?
+            .into_iter()
+            .filter(|it| it.id.0 == self.id);
+        let assist = match assists.next() {
+            Some(it) => it,
+            None => bail!("assist `{}` is not available at {}", self.id, range),
+        };
+        // Assists like `auto_import` offer one variant per candidate, the
+        // first one is what editors show first as well.
+        let others = assists.map(|it| it.label.to_string()).collect::<Vec<_>>();
+        if !others.is_empty() {
+            eprintln!(
+                "{} variants of `{}` are available, applying `{}`",
+                others.len() + 1,
+                self.id,
+                assist.label
+            );
+        }
+        let change = match assist.source_change {
+            Some(it) => it,
+            None => bail!("assist `{}` produced no edit", self.id),
+        };
+        write_source_change(&analysis, &vfs, change, self.dry_run)
+    }
+}
@@ -0,0 +1,152 @@
+//! Measures the latency of a single IDE query at a position.
+
+use std::{
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+use ide::{Analysis, Change, FilePosition};
+use project_model::CargoConfig;
+
+use crate::cli::{
+    completions::completion_config,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchWhat {
+    Completion,
+    GotoDef,
+    Highlight,
+}
+
+impl FromStr for BenchWhat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BenchWhat, String> {
+        match s {
+            "completion" => Ok(BenchWhat::Completion),
+            "goto-def" => Ok(BenchWhat::GotoDef),
+            "highlight" => Ok(BenchWhat::Highlight),
+            _ => Err(format!(
+                "unknown query `{}`, expected `completion`, `goto-def` or `highlight`",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BenchWhat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchWhat::Completion => f.write_str("completion"),
+            BenchWhat::GotoDef => f.write_str("goto-def"),
+            BenchWhat::Highlight => f.write_str("highlight"),
+        }
+    }
+}
+
+pub struct BenchCmd {
+    pub path: PathBuf,
+    pub what: BenchWhat,
+    /// A `file:line:column` position.
+    pub position: String,
+    pub iterations: usize,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl BenchCmd {
+    /// Every iteration first edits the file, by adding or removing a trailing
+    /// newline, and then runs the query twice: once right after the edit
+    /// (cold) and once more without changes (warm).
+    pub fn run(self) -> Result<()> {
+        let position: CliPosition = self.position.parse()?;
+        if self.iterations == 0 {
+            bail!("--iterations must be at least 1");
+        }
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: true,
+        };
+        let (mut host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let position = position.resolve(&host.analysis(), &vfs)?;
+        let text = host.analysis().file_text(position.file_id)?;
+
+        let mut cold = Vec::with_capacity(self.iterations);
+        let mut warm = Vec::with_capacity(self.iterations);
+        for i in 0..self.iterations {
+            let text = if i % 2 == 0 { Arc::new(format!("{}\n", text)) } else { text.clone() };
+            let mut change = Change::new();
+            change.change_file(position.file_id, Some(text));
+            host.apply_change(change);
+
+            let analysis = host.analysis();
+            cold.push(self.measure(&analysis, position)?);
+            warm.push(self.measure(&analysis, position)?);
+        }
+
+        println!("{} at {}, {} iterations", self.what, self.position, self.iterations);
+        println!("{:<6} {:>10} {:>10} {:>10} {:>10} {:>10}", "", "min", "p50", "p90", "p99", "max");
+        for (name, mut samples) in vec![("cold", cold), ("warm", warm)] {
+            samples.sort();
+            println!(
+                "{:<6} {:>10?} {:>10?} {:>10?} {:>10?} {:>10?}",
+                name,
+                samples[0],
+                percentile(&samples, 50),
+                percentile(&samples, 90),
+                percentile(&samples, 99),
+                samples[samples.len() - 1],
+            );
+        }
+        Ok(())
+    }
+
+    fn measure(&self, analysis: &Analysis, position: FilePosition) -> Result<Duration> {
+        let start = Instant::now();
+        match self.what {
+            BenchWhat::Completion => {
+                analysis.completions(&completion_config(), position)?;
+            }
+            BenchWhat::GotoDef => {
+                analysis.goto_definition(position)?;
+            }
+            BenchWhat::Highlight => {
+                analysis.highlight(position.file_id)?;
+            }
+        }
+        Ok(start.elapsed())
+    }
+}
+
+/// `samples` must be sorted.
+fn percentile(samples: &[Duration], percent: usize) -> Duration {
+    let idx = (samples.len() * percent + 99) / 100;
+    samples[idx.saturating_sub(1).min(samples.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let samples: Vec<_> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50), Duration::from_millis(5));
+        assert_eq!(percentile(&samples, 90), Duration::from_millis(9));
+        assert_eq!(percentile(&samples, 99), Duration::from_millis(10));
+        assert_eq!(percentile(&samples[..1], 50), Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,104 @@
+//! Exports the function call graph of workspace crates as DOT or JSON.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::bail;
+use hir::Crate;
+use project_model::CargoConfig;
+use serde::Serialize;
+
+use crate::cli::{
+    is_workspace_crate,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    Result,
+};
+
+pub struct CallGraphCmd {
+    pub path: PathBuf,
+    /// Crates to compute the graph for, all workspace crates if empty.
+    pub crates: Vec<String>,
+    /// Emit JSON instead of GraphViz "dot" syntax.
+    pub json: bool,
+    /// Print to stdout if not given.
+    pub output: Option<PathBuf>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Serialize)]
+struct JsonCallGraph {
+    functions: Vec<JsonFunction>,
+    calls: Vec<(usize, usize)>,
+}
+
+#[derive(Serialize)]
+struct JsonFunction {
+    path: String,
+    local: bool,
+    file: Option<String>,
+    /// 1-based.
+    line: Option<u32>,
+}
+
+impl CallGraphCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let db = host.raw_database();
+        let analysis = host.analysis();
+
+        let crates = Crate::all(db)
+            .into_iter()
+            .filter(|krate| {
+                if self.crates.is_empty() {
+                    is_workspace_crate(db, *krate)
+                } else {
+                    krate
+                        .display_name(db)
+                        .map_or(false, |name| self.crates.iter().any(|it| **it == *name))
+                }
+            })
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        if crates.is_empty() {
+            bail!("no matching crates");
+        }
+        let graph = analysis.call_graph(&crates)?;
+
+        let res = if self.json {
+            let mut functions = Vec::with_capacity(graph.functions.len());
+            for func in &graph.functions {
+                let (file, line) = match &func.nav {
+                    Some(nav) => {
+                        let line_index = analysis.file_line_index(nav.file_id)?;
+                        let line = line_index.line_col(nav.focus_or_full_range().start()).line;
+                        (Some(vfs.file_path(nav.file_id).to_string()), Some(line + 1))
+                    }
+                    None => (None, None),
+                };
+                functions.push(JsonFunction {
+                    path: func.path.clone(),
+                    local: func.local,
+                    file,
+                    line,
+                });
+            }
+            serde_json::to_string_pretty(&JsonCallGraph { functions, calls: graph.calls })?
+        } else {
+            graph.to_dot()
+        };
+        match self.output {
+            Some(output) => fs::write(output, res)?,
+            None => println!("{}", res),
+        }
+        Ok(())
+    }
+}
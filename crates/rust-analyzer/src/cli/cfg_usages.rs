@@ -0,0 +1,72 @@
+//! Lists the cfgs of the workspace crates mentioning a cargo feature.
+
+use std::path::PathBuf;
+
+use project_model::CargoConfig;
+use serde::Serialize;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+pub struct CfgUsagesCmd {
+    pub path: PathBuf,
+    pub feature: String,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Serialize)]
+struct JsonCrateUsages {
+    #[serde(rename = "crate")]
+    krate: Option<String>,
+    usages: Vec<JsonUsage>,
+}
+
+#[derive(Serialize)]
+struct JsonUsage {
+    /// `file:line:column` of the feature name.
+    location: String,
+    /// `cfg`, `cfg_attr` or `cfg!`.
+    kind: &'static str,
+}
+
+impl CfgUsagesCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: false,
+            wrap_rustc: false,
+            with_proc_macro: false,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+
+        let mut report = Vec::new();
+        for krate in analysis.cfg_feature_usages(&self.feature)? {
+            let mut usages = Vec::new();
+            for usage in krate.usages {
+                let location = CliPosition::from_offset(
+                    &analysis,
+                    &vfs,
+                    usage.range.file_id,
+                    usage.range.range.start(),
+                )?;
+                let kind = match usage.kind {
+                    ide::CfgUsageKind::Cfg => "cfg",
+                    ide::CfgUsageKind::CfgAttr => "cfg_attr",
+                    ide::CfgUsageKind::CfgMacro => "cfg!",
+                };
+                usages.push(JsonUsage { location: location.to_string(), kind });
+            }
+            report.push(JsonCrateUsages { krate: krate.crate_name, usages });
+        }
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Prints the completions at a position as JSON, optionally after inserting
+//! some text there first.
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::bail;
+use ide::{
+    Change, CompletionConfig, CompletionItem, CompletionItemKind, FilePosition, TextEdit, TextSize,
+};
+use ide_db::helpers::{
+    insert_use::{ImportGranularity, InsertUseConfig},
+    SnippetCap,
+};
+use project_model::CargoConfig;
+use serde::Serialize;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliRange,
+    Result,
+};
+
+pub struct CompletionsCmd {
+    pub path: PathBuf,
+    /// A `file:line:column` position, or a `file:line:column-line:column`
+    /// range to replace with `insert`.
+    pub position: String,
+    /// Text inserted at the position before completing. The completions are
+    /// computed right after it.
+    pub insert: Option<String>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Serialize)]
+struct CompletionJson {
+    label: String,
+    kind: Option<String>,
+    detail: Option<String>,
+    lookup: String,
+    insert_text: String,
+    is_snippet: bool,
+    deprecated: bool,
+    score: u32,
+    /// The path imported when the completion is accepted.
+    import: Option<String>,
+}
+
+impl CompletionsCmd {
+    pub fn run(self) -> Result<()> {
+        let range: CliRange = self.position.parse()?;
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (mut host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let range = range.resolve(&host.analysis(), &vfs)?;
+
+        let offset = match &self.insert {
+            Some(insert) => {
+                let mut text = host.analysis().file_text(range.file_id)?.to_string();
+                TextEdit::replace(range.range, insert.clone()).apply(&mut text);
+                let mut change = Change::new();
+                change.change_file(range.file_id, Some(Arc::new(text)));
+                host.apply_change(change);
+                range.range.start() + TextSize::of(insert.as_str())
+            }
+            None if !range.range.is_empty() => bail!("a range can only be used with --insert"),
+            None => range.range.start(),
+        };
+
+        let position = FilePosition { file_id: range.file_id, offset };
+        let items =
+            host.analysis().completions(&completion_config(), position)?.unwrap_or_default();
+        let mut items = items.iter().map(to_json).collect::<Vec<_>>();
+        items.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.label.cmp(&b.label)));
+        println!("{}", serde_json::to_string_pretty(&items)?);
+        Ok(())
+    }
+}
+
+fn to_json(item: &CompletionItem) -> CompletionJson {
+    CompletionJson {
+        label: item.label().to_string(),
+        kind: item.kind().map(|kind| match kind {
+            CompletionItemKind::SymbolKind(it) => format!("{:?}", it),
+            it => format!("{:?}", it),
+        }),
+        detail: item.detail().map(ToString::to_string),
+        lookup: item.lookup().to_string(),
+        insert_text: item.text_edit().iter().map(|indel| indel.insert.as_str()).collect(),
+        is_snippet: item.is_snippet(),
+        deprecated: item.deprecated(),
+        score: item.relevance().score(),
+        import: item.import_to_add().map(|it| it.import.import_path.to_string()),
+    }
+}
+
+/// Enables everything, snippets included, like a typical editor would.
+pub(super) fn completion_config() -> CompletionConfig {
+    CompletionConfig {
+        enable_postfix_completions: true,
+        enable_imports_on_the_fly: true,
+        enable_self_on_the_fly: true,
+        restrict_to_expected_type: false,
+        add_call_parenthesis: true,
+        add_call_argument_snippets: true,
+        snippet_cap: SnippetCap::new(true),
+        insert_use: InsertUseConfig {
+            granularity: ImportGranularity::Crate,
+            prefix_kind: hir::PrefixKind::ByCrate,
+            enforce_granularity: true,
+            group: true,
+            skip_glob_imports: true,
+        },
+    }
+}
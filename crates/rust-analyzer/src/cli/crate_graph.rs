@@ -0,0 +1,96 @@
+//! Exports the crate graph as DOT or JSON.
+
+use std::path::PathBuf;
+
+use ide::CrateGraphConfig;
+use project_model::CargoConfig;
+use serde::Serialize;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    Result,
+};
+
+pub struct CrateGraphCmd {
+    pub path: PathBuf,
+    /// Emit JSON instead of GraphViz "dot" syntax.
+    pub json: bool,
+    pub workspace_only: bool,
+    pub dedup_sysroot: bool,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Serialize)]
+struct JsonCrateGraph {
+    crates: Vec<JsonCrate>,
+    dependencies: Vec<JsonDependency>,
+}
+
+#[derive(Serialize)]
+struct JsonCrate {
+    name: String,
+    library: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repository: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonDependency {
+    /// Indices into `crates`.
+    from: usize,
+    to: usize,
+    name: String,
+}
+
+impl CrateGraphCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: false,
+            prefill_caches: false,
+        };
+        let (host, _vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let config = CrateGraphConfig {
+            workspace_only: self.workspace_only,
+            merge_duplicates: self.dedup_sysroot,
+        };
+        let graph = host.analysis().export_crate_graph(&config)?;
+
+        if self.json {
+            let json = JsonCrateGraph {
+                crates: graph
+                    .nodes
+                    .into_iter()
+                    .map(|it| {
+                        let package = it.package.as_ref();
+                        JsonCrate {
+                            library: it.is_library,
+                            version: package.map(|it| it.version.clone()),
+                            description: package.and_then(|it| it.description.clone()),
+                            repository: package.and_then(|it| it.repository.clone()),
+                            name: it.name,
+                        }
+                    })
+                    .collect(),
+                dependencies: graph
+                    .edges
+                    .into_iter()
+                    .map(|it| JsonDependency { from: it.from, to: it.to, name: it.name })
+                    .collect(),
+            };
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        } else {
+            print!("{}", graph.to_dot());
+        }
+        Ok(())
+    }
+}
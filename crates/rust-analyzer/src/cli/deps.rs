@@ -0,0 +1,163 @@
+//! Prints the crate dependency tree as seen by rust-analyzer.
+//!
+//! Unlike `cargo tree`, this works on the lowered [`CrateGraph`], so it
+//! includes the sysroot crates and only those edges which survived cfg
+//! filtering.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use ide_db::base_db::{CrateGraph, CrateId, CrateName, SourceDatabase};
+use project_model::CargoConfig;
+use rustc_hash::{FxHashMap, FxHashSet};
+use stdx::format_to;
+
+use crate::cli::{
+    is_workspace_file,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    Result,
+};
+
+pub struct DepsCmd {
+    pub path: PathBuf,
+    /// Print the crates depending on the roots instead of their dependencies.
+    pub invert: bool,
+    /// Only print the trees rooted at the crates with this name.
+    pub filter: Option<String>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl DepsCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: false,
+            prefill_caches: false,
+        };
+        let (host, _vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let db = host.raw_database();
+        let crate_graph = db.crate_graph();
+        let is_library = |krate: CrateId| !is_workspace_file(db, crate_graph[krate].root_file_id);
+
+        let mut edges: FxHashMap<CrateId, Vec<(CrateName, CrateId)>> = FxHashMap::default();
+        for krate in crate_graph.iter() {
+            for dep in &crate_graph[krate].dependencies {
+                let (from, to) =
+                    if self.invert { (dep.crate_id, krate) } else { (krate, dep.crate_id) };
+                edges.entry(from).or_default().push((dep.name.clone(), to));
+            }
+        }
+        for children in edges.values_mut() {
+            children.sort_by_cached_key(|(name, krate)| {
+                (display_name(&crate_graph, *krate), name.to_string())
+            });
+        }
+
+        let mut roots: Vec<CrateId> = match &self.filter {
+            Some(filter) => {
+                let filter = CrateName::normalize_dashes(filter).to_string();
+                let roots = crate_graph
+                    .iter()
+                    .filter(|&krate| {
+                        CrateName::normalize_dashes(&display_name(&crate_graph, krate)).to_string()
+                            == filter
+                    })
+                    .collect::<Vec<_>>();
+                if roots.is_empty() {
+                    bail!("no crate named `{}` in the crate graph", filter);
+                }
+                roots
+            }
+            None if self.invert => {
+                crate_graph.iter().filter(|it| crate_graph[*it].dependencies.is_empty()).collect()
+            }
+            None => crate_graph.iter().filter(|&it| !is_library(it)).collect(),
+        };
+        roots.sort_by_cached_key(|&krate| display_name(&crate_graph, krate));
+
+        let mut printer = TreePrinter {
+            crate_graph: &crate_graph,
+            edges: &edges,
+            is_library: &is_library,
+            printed: FxHashSet::default(),
+            buf: String::new(),
+        };
+        for (idx, &root) in roots.iter().enumerate() {
+            if idx > 0 {
+                printer.buf.push('\n');
+            }
+            printer.print(root, None, &mut String::new(), None);
+        }
+        print!("{}", printer.buf);
+        Ok(())
+    }
+}
+
+fn display_name(crate_graph: &CrateGraph, krate: CrateId) -> String {
+    match &crate_graph[krate].display_name {
+        Some(name) => name.to_string(),
+        None => format!("<crate {}>", krate.0),
+    }
+}
+
+struct TreePrinter<'a> {
+    crate_graph: &'a CrateGraph,
+    edges: &'a FxHashMap<CrateId, Vec<(CrateName, CrateId)>>,
+    is_library: &'a dyn Fn(CrateId) -> bool,
+    /// Crates whose subtree was already printed; later occurrences are marked
+    /// with `(*)`, like `cargo tree` does.
+    printed: FxHashSet<CrateId>,
+    buf: String,
+}
+
+impl TreePrinter<'_> {
+    fn print(
+        &mut self,
+        krate: CrateId,
+        dep_name: Option<&CrateName>,
+        prefix: &mut String,
+        last: Option<bool>,
+    ) {
+        let name = display_name(self.crate_graph, krate);
+        if let Some(last) = last {
+            self.buf.push_str(prefix);
+            self.buf.push_str(if last { "└── " } else { "├── " });
+        }
+        self.buf.push_str(&name);
+        if let Some(package) = &self.crate_graph[krate].package {
+            format_to!(self.buf, " v{}", package.version);
+        }
+        match dep_name {
+            Some(dep_name)
+                if dep_name.to_string() != CrateName::normalize_dashes(&name).to_string() =>
+            {
+                format_to!(self.buf, " as {}", dep_name)
+            }
+            _ => (),
+        }
+        if (self.is_library)(krate) {
+            self.buf.push_str(" [library]");
+        }
+
+        let children = self.edges.get(&krate).map_or(&[][..], |it| it.as_slice());
+        if !self.printed.insert(krate) && !children.is_empty() {
+            self.buf.push_str(" (*)\n");
+            return;
+        }
+        self.buf.push('\n');
+
+        let len = prefix.len();
+        if let Some(last) = last {
+            prefix.push_str(if last { "    " } else { "│   " });
+        }
+        for (idx, (dep_name, dep)) in children.iter().enumerate() {
+            self.print(*dep, Some(dep_name), prefix, Some(idx == children.len() - 1));
+        }
+        prefix.truncate(len);
+    }
+}
@@ -1,21 +1,32 @@
 //! Analyze all modules in a project for diagnostics. Exits with a non-zero status
-//! code if any errors are found.
+//! code if any errors are found, unless watching for changes.
 
-use std::path::Path;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use anyhow::anyhow;
 use rustc_hash::FxHashSet;
+use serde_json::{json, Value};
 
 use hir::{db::HirDatabase, Crate, Module};
-use ide::{AssistResolveStrategy, DiagnosticsConfig, Severity};
-use ide_db::base_db::SourceDatabaseExt;
+use ide::{
+    Analysis, AnalysisHost, AssistResolveStrategy, Diagnostic, DiagnosticsConfig, FileId,
+    LineIndex, Severity, TextRange,
+};
+use project_model::CargoConfig;
+use vfs::{AbsPathBuf, Vfs};
 
 use crate::cli::{
-    load_cargo::{load_workspace_at, LoadCargoConfig},
+    is_workspace_file,
+    load_cargo::{load_workspaces, LoadCargoConfig, ProgressFormat},
+    position::relative_path,
     Result,
 };
 
-fn all_modules(db: &dyn HirDatabase) -> Vec<Module> {
+pub(super) fn all_modules(db: &dyn HirDatabase) -> Vec<Module> {
     let mut worklist: Vec<_> =
         Crate::all(db).into_iter().map(|krate| krate.root_module(db)).collect();
     let mut modules = Vec::new();
@@ -28,31 +39,104 @@ fn all_modules(db: &dyn HirDatabase) -> Vec<Module> {
     modules
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticsFormat {
+    /// One `Debug`-formatted diagnostic per line.
+    Text,
+    /// A SARIF 2.1.0 log, for GitHub code scanning and other SARIF consumers.
+    Sarif,
+}
+
+impl Default for DiagnosticsFormat {
+    fn default() -> DiagnosticsFormat {
+        DiagnosticsFormat::Text
+    }
+}
+
+impl FromStr for DiagnosticsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DiagnosticsFormat, String> {
+        match s {
+            "text" => Ok(DiagnosticsFormat::Text),
+            "sarif" => Ok(DiagnosticsFormat::Sarif),
+            _ => Err(format!("unknown diagnostics format `{}`, expected `text` or `sarif`", s)),
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticsFormat::Text => f.write_str("text"),
+            DiagnosticsFormat::Sarif => f.write_str("sarif"),
+        }
+    }
+}
+
+/// Checks the workspaces at `paths`, or with `recursive`, all workspaces below
+/// them. SARIF artifact locations are relative to the first path.
 pub fn diagnostics(
-    path: &Path,
+    paths: &[PathBuf],
+    recursive: bool,
     load_out_dirs_from_check: bool,
     with_proc_macro: bool,
+    format: DiagnosticsFormat,
+    watch: bool,
+    cargo_config: &CargoConfig,
+    diagnostics_config: &DiagnosticsConfig,
+    progress: ProgressFormat,
 ) -> Result<()> {
-    let cargo_config = Default::default();
     let load_cargo_config = LoadCargoConfig {
         load_out_dirs_from_check,
         with_proc_macro,
         wrap_rustc: false,
         prefill_caches: false,
     };
-    let (host, _vfs, _proc_macro) =
-        load_workspace_at(path, &cargo_config, &load_cargo_config, &|_| {})?;
+    let (mut host, mut vfs, _proc_macro, mut watcher) =
+        load_workspaces(paths, recursive, cargo_config, &load_cargo_config, watch, &|event| {
+            progress.report(event)
+        })?;
+    let path = &paths[0];
+    if !watch {
+        return check(&host, &vfs, path, format, diagnostics_config);
+    }
+
+    loop {
+        if let Err(err) = check(&host, &vfs, path, format, diagnostics_config) {
+            eprintln!("{}", err);
+        }
+        eprintln!("watching for changes...");
+        let change = match watcher.next_change(&mut vfs) {
+            Some(it) => it,
+            None => return Ok(()),
+        };
+        host.apply_change(change);
+    }
+}
+
+fn check(
+    host: &AnalysisHost,
+    vfs: &Vfs,
+    path: &Path,
+    format: DiagnosticsFormat,
+    config: &DiagnosticsConfig,
+) -> Result<()> {
     let db = host.raw_database();
     let analysis = host.analysis();
 
     let mut found_error = false;
     let mut visited_files = FxHashSet::default();
+    let mut all_diagnostics = Vec::new();
+    // Fixes are only rendered in SARIF output.
+    let resolve = || match format {
+        DiagnosticsFormat::Text => AssistResolveStrategy::None,
+        DiagnosticsFormat::Sarif => AssistResolveStrategy::All,
+    };
 
     let work = all_modules(db).into_iter().filter(|module| {
         let file_id = module.definition_source(db).file_id.original_file(db);
-        let source_root = db.file_source_root(file_id);
-        let source_root = db.source_root(source_root);
-        !source_root.is_library
+        is_workspace_file(db, file_id)
     });
 
     for module in work {
@@ -60,29 +144,166 @@ pub fn diagnostics(
         if !visited_files.contains(&file_id) {
             let crate_name =
                 module.krate().display_name(db).as_deref().unwrap_or("unknown").to_string();
-            println!("processing crate: {}, module: {}", crate_name, _vfs.file_path(file_id));
-            for diagnostic in analysis
-                .diagnostics(&DiagnosticsConfig::default(), AssistResolveStrategy::None, file_id)
-                .unwrap()
-            {
+            if format == DiagnosticsFormat::Text {
+                println!("processing crate: {}, module: {}", crate_name, vfs.file_path(file_id));
+            }
+            for diagnostic in analysis.diagnostics(config, resolve(), file_id).unwrap() {
                 if matches!(diagnostic.severity, Severity::Error) {
                     found_error = true;
                 }
 
-                println!("{:?}", diagnostic);
+                match format {
+                    DiagnosticsFormat::Text => println!("{:?}", diagnostic),
+                    DiagnosticsFormat::Sarif => all_diagnostics.push((file_id, diagnostic)),
+                }
             }
 
             visited_files.insert(file_id);
         }
     }
 
-    println!();
-    println!("diagnostic scan complete");
+    match format {
+        DiagnosticsFormat::Text => {
+            println!();
+            println!("diagnostic scan complete");
+        }
+        DiagnosticsFormat::Sarif => {
+            let root = AbsPathBuf::assert(std::env::current_dir()?.join(path));
+            let log = sarif_log(&analysis, vfs, &root, &all_diagnostics);
+            println!("{}", serde_json::to_string_pretty(&log)?);
+        }
+    }
 
     if found_error {
-        println!();
+        if format == DiagnosticsFormat::Text {
+            println!();
+        }
         Err(anyhow!("diagnostic error detected"))
     } else {
         Ok(())
     }
 }
+
+/// Builds a SARIF 2.1.0 log. Artifact locations are relative to `root` when
+/// possible, so that the log can be uploaded from a different checkout.
+fn sarif_log(
+    analysis: &Analysis,
+    vfs: &Vfs,
+    root: &AbsPathBuf,
+    diagnostics: &[(FileId, Diagnostic)],
+) -> Value {
+    let mut rules = Vec::new();
+    let mut seen_rules = FxHashSet::default();
+    let mut results = Vec::new();
+    for (file_id, diagnostic) in diagnostics {
+        let code = diagnostic.code.as_str();
+        if seen_rules.insert(code) {
+            rules.push(json!({
+                "id": code,
+                "defaultConfiguration": { "level": sarif_level(diagnostic.severity) },
+            }));
+        }
+        let line_index = analysis.file_line_index(*file_id).unwrap();
+        let uri = artifact_uri(vfs, root, *file_id);
+        let fixes = diagnostic
+            .fixes
+            .iter()
+            .flatten()
+            .filter_map(|fix| {
+                let source_change = fix.source_change.as_ref()?;
+                let changes = source_change
+                    .source_file_edits
+                    .iter()
+                    .map(|(&file_id, edit)| {
+                        let line_index = analysis.file_line_index(file_id).unwrap();
+                        let replacements = edit
+                            .iter()
+                            .map(|indel| {
+                                json!({
+                                    "deletedRegion": sarif_region(&line_index, indel.delete),
+                                    "insertedContent": { "text": indel.insert },
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        json!({
+                            "artifactLocation": artifact_uri(vfs, root, file_id),
+                            "replacements": replacements,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Some(json!({
+                    "description": { "text": fix.label.to_string() },
+                    "artifactChanges": changes,
+                }))
+            })
+            .collect::<Vec<_>>();
+        results.push(json!({
+            "ruleId": code,
+            "level": sarif_level(diagnostic.severity),
+            "message": { "text": diagnostic.message },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": uri,
+                    "region": sarif_region(&line_index, diagnostic.range),
+                },
+            }],
+            "fixes": fixes,
+        }));
+    }
+
+    // Relative URIs are resolved against the base like in a browser, so the
+    // directory has to end in a slash for its last segment to be kept.
+    let mut root_uri = crate::to_proto::url_from_abs_path(root).to_string();
+    if !root_uri.ends_with('/') {
+        root_uri.push('/');
+    }
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "rust-analyzer",
+                    "informationUri": "https://rust-analyzer.github.io",
+                    "version": env!("REV"),
+                    "rules": rules,
+                },
+            },
+            "originalUriBaseIds": {
+                "%SRCROOT%": { "uri": root_uri },
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }],
+    })
+}
+
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::WeakWarning => "warning",
+    }
+}
+
+/// SARIF lines and columns are 1-based.
+fn sarif_region(line_index: &LineIndex, range: TextRange) -> Value {
+    let start = line_index.to_utf16(line_index.line_col(range.start()));
+    let end = line_index.to_utf16(line_index.line_col(range.end()));
+    json!({
+        "startLine": start.line + 1,
+        "startColumn": start.col + 1,
+        "endLine": end.line + 1,
+        "endColumn": end.col + 1,
+    })
+}
+
+fn artifact_uri(vfs: &Vfs, root: &AbsPathBuf, file_id: FileId) -> Value {
+    let path = vfs.file_path(file_id);
+    match path.as_path() {
+        Some(path) => match relative_path(path, root) {
+            Some(uri) => json!({ "uri": uri, "uriBaseId": "%SRCROOT%" }),
+            None => json!({ "uri": crate::to_proto::url_from_abs_path(path).to_string() }),
+        },
+        None => json!({ "uri": path.to_string() }),
+    }
+}
@@ -0,0 +1,111 @@
+//! Checks the intra-doc links in the doc comments of workspace crates.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use project_model::CargoConfig;
+use serde::Serialize;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+pub struct DocLinksCmd {
+    pub path: PathBuf,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Serialize, Default)]
+struct JsonReport {
+    resolved: Vec<JsonResolvedLink>,
+    broken: Vec<JsonBrokenLink>,
+}
+
+#[derive(Serialize)]
+struct JsonResolvedLink {
+    /// `file:line:column` of the link.
+    location: String,
+    link: String,
+    /// The full path of the target, like `std::vec::Vec`.
+    target: String,
+    /// `file:line:column` of the target, if it has a source.
+    target_location: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonBrokenLink {
+    location: String,
+    link: String,
+}
+
+impl DocLinksCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+
+        let mut files = Vec::new();
+        for (file_id, path) in vfs.iter() {
+            if path.name_and_extension().map_or(true, |(_, ext)| ext != Some("rs")) {
+                continue;
+            }
+            if analysis.is_library_file(file_id)? || analysis.crate_for(file_id)?.is_empty() {
+                continue;
+            }
+            files.push((path.to_string(), file_id));
+        }
+        files.sort();
+
+        let mut report = JsonReport::default();
+        for (_, file_id) in files {
+            for link in analysis.intra_doc_links(file_id)? {
+                let location =
+                    CliPosition::from_offset(&analysis, &vfs, file_id, link.range.start())?
+                        .to_string();
+                match link.resolved {
+                    Some(resolved) => {
+                        let target_location = match resolved.nav {
+                            Some(nav) => Some(
+                                CliPosition::from_offset(
+                                    &analysis,
+                                    &vfs,
+                                    nav.file_id,
+                                    nav.focus_or_full_range().start(),
+                                )?
+                                .to_string(),
+                            ),
+                            None => None,
+                        };
+                        report.resolved.push(JsonResolvedLink {
+                            location,
+                            link: link.link,
+                            target: resolved.path,
+                            target_location,
+                        });
+                    }
+                    None => {
+                        eprintln!("{}: unresolved link to `{}`", location, link.link);
+                        report.broken.push(JsonBrokenLink { location, link: link.link });
+                    }
+                }
+            }
+        }
+
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        if !report.broken.is_empty() {
+            bail!("{} broken intra-doc links", report.broken.len());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Prints the expansion of the macro call at a position.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use project_model::CargoConfig;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+pub struct ExpandMacroCmd {
+    pub path: PathBuf,
+    /// A `file:line:column` position inside of the macro call.
+    pub position: String,
+    /// Expand at most this many levels of nested macro calls.
+    pub step: Option<usize>,
+    /// Print the expansion after each level of nested macro calls.
+    pub steps: bool,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl ExpandMacroCmd {
+    pub fn run(self) -> Result<()> {
+        let position: CliPosition = self.position.parse()?;
+        if self.step == Some(0) {
+            bail!("--step must be at least 1");
+        }
+        if self.step.is_some() && self.steps {
+            bail!("--step and --steps can't be used together");
+        }
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+        let position = position.resolve(&analysis, &vfs)?;
+        if self.steps {
+            let steps = match analysis.expand_macro_steps(position)? {
+                Some(it) => it,
+                None => bail!("no macro call at {}", self.position),
+            };
+            for (i, step) in steps.into_iter().enumerate() {
+                println!("// Step {}", i + 1);
+                for call in step.calls {
+                    let at = CliPosition::from_offset(
+                        &analysis,
+                        &vfs,
+                        call.range.file_id,
+                        call.range.range.start(),
+                    )?;
+                    println!("// Expanded {}! at {}", call.name, at);
+                    if !call.def_site_idents.is_empty() {
+                        println!("//   from its definition: {}", call.def_site_idents.join(", "));
+                    }
+                }
+                println!("{}", step.expansion);
+            }
+            return Ok(());
+        }
+        match analysis.expand_macro_with_depth(position, self.step)? {
+            Some(expanded) => {
+                println!("// Recursive expansion of {}! macro", expanded.name);
+                println!("{}", expanded.expansion);
+            }
+            None => bail!("no macro call at {}", self.position),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Applies the quick fixes of diagnostics with the given codes across the
+//! workspace.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::bail;
+use ide::{AssistResolveStrategy, DiagnosticsConfig, FileId, SourceChange, TextEdit};
+use project_model::CargoConfig;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::cli::{
+    diagnostics::all_modules,
+    is_workspace_file,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::display_path,
+    source_change::write_source_change,
+    Result,
+};
+
+pub struct FixCmd {
+    pub path: PathBuf,
+    /// Only diagnostics with these codes are fixed, like `unresolved-module`.
+    pub codes: Vec<String>,
+    /// Print a diff instead of modifying any files.
+    pub dry_run: bool,
+    /// Diagnostics disabled here are not fixed either.
+    pub diagnostics_config: DiagnosticsConfig,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl FixCmd {
+    pub fn run(self) -> Result<()> {
+        if self.codes.is_empty() {
+            bail!("no diagnostic codes given, pass at least one `--code`");
+        }
+        let codes = self.codes.iter().map(String::as_str).collect::<FxHashSet<_>>();
+
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let db = host.raw_database();
+        let analysis = host.analysis();
+
+        let mut edits: FxHashMap<FileId, TextEdit> = FxHashMap::default();
+        let mut change = SourceChange::default();
+        let mut n_fixes: BTreeMap<PathBuf, usize> = BTreeMap::new();
+        let mut n_skipped = 0;
+        let mut visited_files = FxHashSet::default();
+        for module in all_modules(db) {
+            let file_id = module.definition_source(db).file_id.original_file(db);
+            if !is_workspace_file(db, file_id) || !visited_files.insert(file_id) {
+                continue;
+            }
+            for diagnostic in analysis.diagnostics(
+                &self.diagnostics_config,
+                AssistResolveStrategy::All,
+                file_id,
+            )? {
+                if !codes.contains(diagnostic.code.as_str()) {
+                    continue;
+                }
+                let fix = match diagnostic.fixes.into_iter().flatten().next() {
+                    Some(it) => it,
+                    None => continue,
+                };
+                let fix = match fix.source_change {
+                    Some(it) => it,
+                    None => continue,
+                };
+                // Fixes touching the same text as an earlier one are left for
+                // the next run, when they are computed against the new text.
+                if !fits(&edits, &fix) {
+                    n_skipped += 1;
+                    continue;
+                }
+                for (edited_file, edit) in fix.source_file_edits {
+                    match edits.get_mut(&edited_file) {
+                        Some(existing) => existing.union(edit).expect("checked by `fits`"),
+                        None => {
+                            edits.insert(edited_file, edit);
+                        }
+                    }
+                }
+                change.file_system_edits.extend(fix.file_system_edits);
+                *n_fixes.entry(display_path(&vfs, file_id)?).or_default() += 1;
+            }
+        }
+
+        change.source_file_edits = edits;
+        write_source_change(&analysis, &vfs, change, self.dry_run)?;
+
+        for (path, count) in &n_fixes {
+            eprintln!("{}: {} fixes", path.display(), count);
+        }
+        let total = n_fixes.values().sum::<usize>();
+        eprintln!("{} fixes in {} files", total, n_fixes.len());
+        if n_skipped > 0 {
+            eprintln!(
+                "{} overlapping fixes skipped, run the command again to apply them",
+                n_skipped
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the edits of `fix` don't overlap with any of `edits`.
+fn fits(edits: &FxHashMap<FileId, TextEdit>, fix: &SourceChange) -> bool {
+    fix.source_file_edits.iter().all(|(file_id, edit)| match edits.get(file_id) {
+        Some(existing) => existing.clone().union(edit.clone()).is_ok(),
+        None => true,
+    })
+}
@@ -0,0 +1,151 @@
+//! Renders a file, a crate or a whole workspace to static HTML pages, with
+//! references linked to their definitions.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, format_err};
+use hir::{Crate, Module};
+use ide_db::base_db::FileId;
+use project_model::CargoConfig;
+use rustc_hash::{FxHashMap, FxHashSet};
+use vfs::{AbsPathBuf, Vfs, VfsPath};
+
+use crate::cli::{
+    is_workspace_crate,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::relative_path,
+    Result,
+};
+
+pub struct HighlightCmd {
+    pub path: PathBuf,
+    pub rainbow: bool,
+    /// Only render this file.
+    pub file: Option<PathBuf>,
+    /// Only render the files of the crate with this name.
+    pub krate: Option<String>,
+    /// Defaults to `./highlight`.
+    pub output: Option<PathBuf>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl HighlightCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let db = host.raw_database();
+        let analysis = host.analysis();
+        let cwd = std::env::current_dir()?;
+        let root = AbsPathBuf::assert(cwd.join(&self.path));
+        let root = match root.parent() {
+            Some(parent) if AsRef::<Path>::as_ref(&root).is_file() => parent.to_path_buf(),
+            _ => root,
+        };
+
+        let files = select_files(db, &vfs, self.file.as_deref(), self.krate.as_deref())?;
+        if files.is_empty() {
+            bail!("nothing to highlight");
+        }
+
+        // Pages are named after the file's path relative to the workspace root.
+        let mut pages = FxHashMap::default();
+        for &file_id in &files {
+            let page = vfs
+                .file_path(file_id)
+                .as_path()
+                .and_then(|it| relative_path(it, &root))
+                .unwrap_or_else(|| format!("external/{}.rs", file_id.0));
+            pages.insert(file_id, format!("{}.html", page));
+        }
+
+        let output = self.output.unwrap_or_else(|| PathBuf::from("highlight"));
+        let mut index = String::from("<!DOCTYPE html>\n<meta charset=\"utf-8\">\n<ul>\n");
+        let mut sorted = pages.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.1.cmp(b.1));
+        for (&file_id, page) in sorted {
+            let html = analysis.highlight_as_linked_html(file_id, self.rainbow, &pages)?;
+            // Links are relative to the output directory.
+            let base = "../".repeat(page.matches('/').count());
+            let path = output.join(page);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(
+                &path,
+                format!(
+                    "<!DOCTYPE html>\n<meta charset=\"utf-8\">\n<base href=\"{}\">\n{}",
+                    if base.is_empty() { "./" } else { &base },
+                    html
+                ),
+            )?;
+            index.push_str(&format!("<li><a href=\"{0}\">{0}</a></li>\n", page));
+        }
+        index.push_str("</ul>\n");
+        fs::create_dir_all(&output)?;
+        fs::write(output.join("index.html"), index)?;
+        eprintln!("Wrote {} pages to {}", pages.len(), output.display());
+        Ok(())
+    }
+}
+
+/// Returns `file`, the files of the crate named `krate` or, if neither is
+/// given, the files of all workspace crates.
+pub(super) fn select_files(
+    db: &ide::RootDatabase,
+    vfs: &Vfs,
+    file: Option<&Path>,
+    krate: Option<&str>,
+) -> Result<Vec<FileId>> {
+    let files = match (file, krate) {
+        (Some(file), _) => {
+            let cwd = std::env::current_dir()?;
+            let path = VfsPath::from(AbsPathBuf::assert(cwd.join(file)));
+            let file_id = vfs
+                .file_id(&path)
+                .ok_or_else(|| format_err!("{} is not part of the workspace", path))?;
+            vec![file_id]
+        }
+        (None, Some(name)) => {
+            let krate = Crate::all(db)
+                .into_iter()
+                .find(|it| it.display_name(db).as_deref() == Some(name))
+                .ok_or_else(|| format_err!("no crate named `{}`", name))?;
+            crate_files(db, krate)
+        }
+        (None, None) => {
+            let mut files = Vec::new();
+            for krate in Crate::all(db) {
+                if is_workspace_crate(db, krate) {
+                    files.extend(crate_files(db, krate));
+                }
+            }
+            files
+        }
+    };
+    Ok(files)
+}
+
+/// Returns the files of all modules of `krate`, sorted.
+pub(super) fn crate_files(db: &ide::RootDatabase, krate: Crate) -> Vec<FileId> {
+    let mut files = FxHashSet::default();
+    let mut worklist: Vec<Module> = vec![krate.root_module(db)];
+    while let Some(module) = worklist.pop() {
+        files.insert(module.definition_source(db).file_id.original_file(db));
+        worklist.extend(module.children(db));
+    }
+    let mut files = files.into_iter().collect::<Vec<_>>();
+    files.sort();
+    files
+}
@@ -0,0 +1,67 @@
+//! Prints the hover contents at positions as Markdown.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use ide::{HoverConfig, HoverDocFormat};
+use project_model::CargoConfig;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+pub struct HoverCmd {
+    pub path: PathBuf,
+    /// `file:line:column` positions.
+    pub positions: Vec<String>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl HoverCmd {
+    pub fn run(self) -> Result<()> {
+        if self.positions.is_empty() {
+            bail!("no positions given");
+        }
+        let positions =
+            self.positions.iter().map(|it| it.parse()).collect::<Result<Vec<CliPosition>>>()?;
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+        // The same settings the LSP server uses by default.
+        let config = HoverConfig {
+            links_in_hover: true,
+            documentation: Some(HoverDocFormat::Markdown),
+            memory_layout: true,
+        };
+
+        for (i, (input, position)) in self.positions.iter().zip(&positions).enumerate() {
+            let position = position.resolve(&analysis, &vfs)?;
+            let hover = analysis.hover(&config, position)?;
+            // With several positions, each result is introduced by an HTML
+            // comment, which keeps the output valid Markdown.
+            if positions.len() > 1 {
+                if i > 0 {
+                    println!();
+                }
+                println!("<!-- {} -->", input);
+            }
+            match hover {
+                Some(hover) => println!("{}", hover.info.markup),
+                None if positions.len() > 1 => println!("<!-- nothing to show -->"),
+                None => bail!("nothing to show at {}", input),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+//! Dumps the inlay hints of a file, a crate or a whole workspace as JSON.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use ide::{InlayHintsConfig, InlayKind};
+use project_model::CargoConfig;
+use serde::Serialize;
+
+use crate::cli::{
+    highlight::select_files,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+pub struct InlayHintsCmd {
+    pub path: PathBuf,
+    /// Only dump the hints of this file.
+    pub file: Option<PathBuf>,
+    /// Only dump the hints of the files of the crate with this name.
+    pub krate: Option<String>,
+    /// Truncate hints longer than this, like editors do.
+    pub max_length: Option<usize>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Serialize)]
+struct HintJson {
+    kind: &'static str,
+    label: String,
+}
+
+impl InlayHintsCmd {
+    /// Prints an object mapping `file:line:column` positions to the hints
+    /// shown there, in source order. Parameter hints are shown before their
+    /// range, type and chaining hints after it.
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let db = host.raw_database();
+        let analysis = host.analysis();
+
+        let mut files = select_files(db, &vfs, self.file.as_deref(), self.krate.as_deref())?;
+        if files.is_empty() {
+            bail!("no files to compute hints for");
+        }
+        files.sort_by_key(|&file_id| vfs.file_path(file_id).to_string());
+        files.dedup();
+
+        let config = InlayHintsConfig {
+            type_hints: true,
+            parameter_hints: true,
+            chaining_hints: true,
+            const_value_hints: true,
+            max_length: self.max_length,
+        };
+        let mut res = serde_json::Map::new();
+        for file_id in files {
+            let mut hints = analysis
+                .inlay_hints(&config, file_id)?
+                .into_iter()
+                .map(|hint| {
+                    let (kind, offset) = match hint.kind {
+                        InlayKind::TypeHint => ("type", hint.range.end()),
+                        InlayKind::ParameterHint => ("parameter", hint.range.start()),
+                        InlayKind::ChainingHint => ("chaining", hint.range.end()),
+                        InlayKind::ConstValueHint => ("const_value", hint.range.end()),
+                    };
+                    (offset, HintJson { kind, label: hint.label.to_string() })
+                })
+                .collect::<Vec<_>>();
+            hints.sort_by_key(|(offset, _)| *offset);
+            for (offset, hint) in hints {
+                let position = CliPosition::from_offset(&analysis, &vfs, file_id, offset)?;
+                let entry = res
+                    .entry(position.to_string())
+                    .or_insert_with(|| serde_json::Value::Array(Vec::new()));
+                if let serde_json::Value::Array(it) = entry {
+                    it.push(serde_json::to_value(hint)?);
+                }
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&res)?);
+        Ok(())
+    }
+}
@@ -1,11 +1,21 @@
 //! Fully type-check project and print various stats, like the number of type
 //! errors.
 
-use ide::Change;
-use project_model::{CargoConfig, ProjectManifest, ProjectWorkspace};
-use std::path::Path;
+use hir::db::DefDatabase;
+use ide::{AnalysisHost, Change};
+use ide_db::base_db::{snapshot, ExpansionRecord};
+use project_model::{
+    CargoConfig, ProcMacroClient, ProgressEvent, ProjectManifest, ProjectWorkspace,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use crate::cli::{load_cargo::LoadCargoConfig, Result};
+use crate::cli::{
+    load_cargo::{LoadCargoConfig, LoadError, ProgressFormat},
+    Result,
+};
 
 use vfs::AbsPathBuf;
 
@@ -13,7 +23,12 @@ use std::fs;
 
 use crate::cli::load_cargo::load_change;
 
-pub struct JsonChangeCmd {}
+pub struct JsonChangeCmd {
+    /// Write a binary snapshot to this path instead of `./change.json`.
+    pub snapshot: Option<PathBuf>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
 
 impl JsonChangeCmd {
     /// Execute with e.g.
@@ -21,31 +36,78 @@ impl JsonChangeCmd {
     /// cargo run --bin rust-analyzer json-change ../ink/examples/flipper/Cargo.toml
     /// ```
     pub fn run(self, root: &Path) -> Result<()> {
-        let change = get_change_data(root, &|_| {})?;
-        let json = serde_json::to_string(&change).expect("serialization of change must work");
-        fs::write("./change.json", json).expect("Unable to write file");
+        match &self.snapshot {
+            Some(path) => {
+                let (change, expansions) = get_snapshot_data(root, &self.cargo_config, &|event| {
+                    self.progress.report(event)
+                })?;
+                fs::write(path, snapshot::encode_with_expansions(&change, &expansions))?
+            }
+            None => {
+                let change = get_change_data(root, &self.cargo_config, &|event| {
+                    self.progress.report(event)
+                })?;
+                let json =
+                    serde_json::to_string(&change).expect("serialization of change must work");
+                fs::write("./change.json", json).expect("Unable to write file");
+            }
+        }
         Ok(())
     }
 }
 
-fn get_change_data(root: &Path, progress: &dyn Fn(String)) -> Result<Change> {
-    let mut cargo_config = CargoConfig::default();
-    cargo_config.no_sysroot = false;
+fn get_change_data(
+    root: &Path,
+    cargo_config: &CargoConfig,
+    progress: &dyn Fn(ProgressEvent),
+) -> Result<Change> {
+    let (change, _) = load_change_at(root, cargo_config, false, progress)?;
+    Ok(change)
+}
+
+/// Loads the workspace with proc macros and primes the caches, so that every
+/// attribute and derive macro invocation is expanded once and recorded.
+fn get_snapshot_data(
+    root: &Path,
+    cargo_config: &CargoConfig,
+    progress: &dyn Fn(ProgressEvent),
+) -> Result<(Change, Arc<ExpansionRecord>)> {
+    let (mut change, _proc_macro_client) = load_change_at(root, cargo_config, true, progress)?;
+    let expansions = Arc::new(ExpansionRecord::default());
+    if let Some(crate_graph) = &mut change.crate_graph {
+        crate_graph.record_proc_macro_expansions(&expansions);
+    }
+
+    let mut host = AnalysisHost::default();
+    host.raw_database_mut().set_enable_proc_attr_macros(true);
+    host.apply_change(change.clone());
+    host.analysis().prime_caches(num_cpus::get(), |_| {})?;
+    drop(host);
+
+    Ok((change, expansions))
+}
+
+pub(super) fn load_change_at(
+    root: &Path,
+    cargo_config: &CargoConfig,
+    with_proc_macro: bool,
+    progress: &dyn Fn(ProgressEvent),
+) -> Result<(Change, Option<ProcMacroClient>)> {
     let root = AbsPathBuf::assert(std::env::current_dir()?.join(root));
 
-    let root = ProjectManifest::discover_single(&root)?;
-    let ws = ProjectWorkspace::load(root, &cargo_config, &|_| {})?;
+    let root = ProjectManifest::discover_single(&root).map_err(LoadError::ManifestNotFound)?;
+    let ws = ProjectWorkspace::load(root, cargo_config, progress).map_err(LoadError::Metadata)?;
 
     let config = LoadCargoConfig {
         load_out_dirs_from_check: true,
         wrap_rustc: true,
-        with_proc_macro: false,
+        with_proc_macro,
         prefill_caches: false,
     };
 
-    let (change, _, _) = load_change(ws, &config, progress)?;
+    let (change, _, proc_macro_client) = load_change(ws, &config, progress)?;
 
-    Ok(change)
+    Ok((change, proc_macro_client))
 }
 
 #[cfg(test)]
@@ -55,9 +117,9 @@ mod tests {
     #[test]
     fn test_create_serialize_deserialize_change() -> Result<()> {
         let path = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().parent().unwrap();
-        let change = get_change_data(path, &|_| {})?;
+        let change = get_change_data(path, &CargoConfig::default(), &|_| {})?;
         let json = serde_json::to_string(&change)?;
-        let deserialized_change: Change = serde_json::from_str(&json)?;
+        let deserialized_change = Change::from_json(&json)?;
         assert_eq!(change, deserialized_change);
         Ok(())
     }
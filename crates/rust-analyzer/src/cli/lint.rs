@@ -0,0 +1,153 @@
+//! Runs the diagnostics of all local modules like a linter: every diagnostic
+//! code is mapped to `allow`, `warn` or `deny`, and any denied diagnostic makes
+//! the command fail.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::format_err;
+use ide::{AssistResolveStrategy, DiagnosticsConfig, Severity};
+use project_model::CargoConfig;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Deserialize;
+
+use crate::cli::{
+    diagnostics::all_modules,
+    is_workspace_file,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+pub struct LintCmd {
+    pub path: PathBuf,
+    /// A JSON object mapping diagnostic codes to `"allow"`, `"warn"` or
+    /// `"deny"`, like `{ "unresolved-macro-call": "deny" }`. Diagnostics which
+    /// aren't listed are denied if they are errors and warned about otherwise.
+    pub lints: Option<PathBuf>,
+    /// Diagnostics disabled here are allowed unless `lints` says otherwise.
+    pub diagnostics_config: DiagnosticsConfig,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl LintCmd {
+    /// Returns `false` if any denied diagnostic was found.
+    pub fn run(self) -> Result<bool> {
+        let levels: FxHashMap<String, LintLevel> = match &self.lints {
+            Some(path) => {
+                let text = fs::read_to_string(path)?;
+                serde_json::from_str(&text)
+                    .map_err(|err| format_err!("invalid lint config {}: {}", path.display(), err))?
+            }
+            None => FxHashMap::default(),
+        };
+
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let db = host.raw_database();
+        let analysis = host.analysis();
+
+        // Allowed diagnostics don't need to be computed at all.
+        let mut config = self.diagnostics_config.clone();
+        config.disabled.retain(|code| !levels.contains_key(code));
+        config.disabled.extend(
+            levels
+                .iter()
+                .filter(|(_, &level)| level == LintLevel::Allow)
+                .map(|(code, _)| code.clone()),
+        );
+
+        let mut visited_files = FxHashSet::default();
+        let mut diagnostics = Vec::new();
+        for module in all_modules(db) {
+            let file_id = module.definition_source(db).file_id.original_file(db);
+            if !is_workspace_file(db, file_id) || !visited_files.insert(file_id) {
+                continue;
+            }
+            for diagnostic in analysis.diagnostics(&config, AssistResolveStrategy::None, file_id)? {
+                let level = lint_level(&levels, diagnostic.code.as_str(), diagnostic.severity);
+                if level == LintLevel::Allow {
+                    continue;
+                }
+                let position =
+                    CliPosition::from_offset(&analysis, &vfs, file_id, diagnostic.range.start())?;
+                diagnostics.push((position, level, diagnostic));
+            }
+        }
+        diagnostics.sort_by(|(a, ..), (b, ..)| {
+            (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column))
+        });
+
+        let (mut n_warnings, mut n_errors) = (0, 0);
+        for (position, level, diagnostic) in &diagnostics {
+            let kind = match level {
+                LintLevel::Deny => {
+                    n_errors += 1;
+                    "error"
+                }
+                _ => {
+                    n_warnings += 1;
+                    "warning"
+                }
+            };
+            println!(
+                "{}: {}[{}]: {}",
+                position,
+                kind,
+                diagnostic.code.as_str(),
+                diagnostic.message
+            );
+        }
+        eprintln!("{} errors, {} warnings", n_errors, n_warnings);
+        Ok(n_errors == 0)
+    }
+}
+
+fn lint_level(levels: &FxHashMap<String, LintLevel>, code: &str, severity: Severity) -> LintLevel {
+    match levels.get(code) {
+        Some(&level) => level,
+        None => match severity {
+            Severity::Error => LintLevel::Deny,
+            Severity::WeakWarning => LintLevel::Warn,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_levels() {
+        let levels: FxHashMap<String, LintLevel> = serde_json::from_str(
+            r#"{ "unresolved-macro-call": "deny", "inactive-code": "allow" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            lint_level(&levels, "unresolved-macro-call", Severity::WeakWarning),
+            LintLevel::Deny
+        );
+        assert_eq!(lint_level(&levels, "inactive-code", Severity::WeakWarning), LintLevel::Allow);
+        assert_eq!(lint_level(&levels, "unresolved-module", Severity::Error), LintLevel::Deny);
+        assert_eq!(lint_level(&levels, "unlinked-file", Severity::WeakWarning), LintLevel::Warn);
+        assert!(
+            serde_json::from_str::<FxHashMap<String, LintLevel>>(r#"{ "x": "forbid" }"#).is_err()
+        );
+    }
+}
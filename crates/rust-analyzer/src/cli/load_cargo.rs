@@ -1,15 +1,24 @@
-//! Loads a Cargo project into a static instance of analysis, without support
-//! for incorporating changes.
-use std::{path::Path, sync::Arc};
+//! Loads a Cargo project into a static instance of analysis. Changes to the
+//! files on disk can optionally be picked up with a [`Watcher`].
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{format_err, Result};
+use cfg::CfgOptions;
 use crossbeam_channel::{unbounded, Receiver};
 use hir::db::DefDatabase;
 use ide::{AnalysisHost, Change};
-use ide_db::base_db::CrateGraph;
+use ide_db::base_db::{CrateDisplayName, CrateGraph, CrateName, Edition, Env};
 use project_model::{
-    BuildDataCollector, CargoConfig, ProcMacroClient, ProjectManifest, ProjectWorkspace,
+    BuildDataCollector, CancellationToken, CargoConfig, DiscoveryConfig, ProcMacroClient,
+    ProgressEvent, ProjectManifest, ProjectWorkspace,
 };
+use rustc_hash::FxHashSet;
 use vfs::{loader::Handle, AbsPath, AbsPathBuf};
 
 use crate::reload::{ProjectFolders, SourceRootConfig};
@@ -21,45 +30,239 @@ pub(crate) struct LoadCargoConfig {
     pub(crate) prefill_caches: bool,
 }
 
+/// How CLI commands report the progress of loading a workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// Don't report progress.
+    None,
+    /// Print each [`ProgressEvent`] as a JSON object on its own line to
+    /// stderr, like `{"phase":"loading_files","current":12,"total":80}`.
+    Json,
+}
+
+impl Default for ProgressFormat {
+    fn default() -> Self {
+        ProgressFormat::None
+    }
+}
+
+impl FromStr for ProgressFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(ProgressFormat::None),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(format!("unknown progress format `{}`, expected `none` or `json`", s)),
+        }
+    }
+}
+
+/// A failure to load a workspace which scripts around the CLI may want to
+/// tell apart from other errors. Each kind exits the process with its own
+/// code, see [`LoadError::exit_code`].
+#[derive(Debug)]
+pub enum LoadError {
+    /// No `Cargo.toml` or `rust-project.json` was found, or more than one.
+    ManifestNotFound(anyhow::Error),
+    /// Loading the project failed, usually because `cargo metadata` did.
+    Metadata(anyhow::Error),
+    /// Running build scripts and building proc macros failed.
+    BuildScripts(anyhow::Error),
+    /// The proc macro server couldn't be started.
+    ProcMacroServer(anyhow::Error),
+}
+
+impl LoadError {
+    /// A stable identifier for the kind of failure, like `manifest_not_found`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LoadError::ManifestNotFound(_) => "manifest_not_found",
+            LoadError::Metadata(_) => "metadata",
+            LoadError::BuildScripts(_) => "build_scripts",
+            LoadError::ProcMacroServer(_) => "proc_macro_server",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoadError::ManifestNotFound(_) => 10,
+            LoadError::Metadata(_) => 11,
+            LoadError::BuildScripts(_) => 12,
+            LoadError::ProcMacroServer(_) => 13,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::ManifestNotFound(err) => write!(f, "no project found: {:#}", err),
+            LoadError::Metadata(err) => write!(f, "failed to load workspace: {:#}", err),
+            LoadError::BuildScripts(err) => write!(f, "failed to run build scripts: {:#}", err),
+            LoadError::ProcMacroServer(err) => {
+                write!(f, "failed to start proc macro server: {:#}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl ProgressFormat {
+    pub(crate) fn report(self, event: ProgressEvent) {
+        match self {
+            ProgressFormat::None => (),
+            ProgressFormat::Json => match serde_json::to_string(&event) {
+                Ok(json) => eprintln!("{}", json),
+                Err(err) => log::error!("failed to serialize progress event: {}", err),
+            },
+        }
+    }
+}
+
 pub(crate) fn load_workspace_at(
     root: &Path,
     cargo_config: &CargoConfig,
     load_config: &LoadCargoConfig,
-    progress: &dyn Fn(String),
+    progress: &dyn Fn(ProgressEvent),
 ) -> Result<(AnalysisHost, vfs::Vfs, Option<ProcMacroClient>)> {
+    let (host, vfs, proc_macro_client, _watcher) =
+        load_workspaces(&[root.to_path_buf()], false, cargo_config, load_config, false, progress)?;
+    Ok((host, vfs, proc_macro_client))
+}
+
+/// Like [`load_workspace_at`], but also returns the loaded project, e.g. to map
+/// crates back to their Cargo targets.
+pub(crate) fn load_project_at(
+    root: &Path,
+    cargo_config: &CargoConfig,
+    load_config: &LoadCargoConfig,
+    progress: &dyn Fn(ProgressEvent),
+) -> Result<(AnalysisHost, vfs::Vfs, Option<ProcMacroClient>, ProjectWorkspace)> {
     let root = AbsPathBuf::assert(std::env::current_dir()?.join(root));
-    eprintln!("root = {:?}", root);
-    let root = ProjectManifest::discover_single(&root)?;
-    eprintln!("root = {:?}", root);
-    let workspace = ProjectWorkspace::load(root, cargo_config, progress)?;
+    let manifest = ProjectManifest::discover_single(&root).map_err(LoadError::ManifestNotFound)?;
+    let workspace =
+        ProjectWorkspace::load(manifest, cargo_config, progress).map_err(LoadError::Metadata)?;
+    let (host, vfs, proc_macro_client, _watcher) =
+        load_workspace(vec![workspace.clone()], load_config, false, progress)?;
+    Ok((host, vfs, proc_macro_client, workspace))
+}
 
-    load_workspace(workspace, load_config, progress)
+/// Loads the workspaces at all `roots` into a single database, merging the
+/// crates they share. With `recursive`, every Cargo workspace and
+/// `rust-project.json` below the roots is loaded.
+///
+/// With `watch`, the workspace members are watched for changes, which can be
+/// applied to the host via the returned [`Watcher`].
+pub(crate) fn load_workspaces(
+    roots: &[PathBuf],
+    recursive: bool,
+    cargo_config: &CargoConfig,
+    load_config: &LoadCargoConfig,
+    watch: bool,
+    progress: &dyn Fn(ProgressEvent),
+) -> Result<(AnalysisHost, vfs::Vfs, Option<ProcMacroClient>, Watcher)> {
+    let cwd = std::env::current_dir()?;
+    let mut manifests = Vec::new();
+    let mut detached_files = Vec::new();
+    for root in roots {
+        let root = AbsPathBuf::assert(cwd.join(root));
+        let is_rust_file = root.extension().map_or(false, |it| it == "rs");
+        if is_rust_file && AsRef::<Path>::as_ref(&root).is_file() {
+            detached_files.push(root);
+        } else if recursive {
+            manifests.extend(
+                ProjectManifest::discover_recursive(&root, &DiscoveryConfig::default())
+                    .map_err(|err| LoadError::ManifestNotFound(err.into()))?,
+            );
+        } else {
+            manifests.push(
+                ProjectManifest::discover_single(&root).map_err(LoadError::ManifestNotFound)?,
+            );
+        }
+    }
+    manifests.sort();
+    manifests.dedup();
+    if manifests.is_empty() && detached_files.is_empty() {
+        return Err(LoadError::ManifestNotFound(format_err!("no projects")).into());
+    }
+
+    // `cargo metadata` reports the whole workspace of a member, so members of
+    // already loaded workspaces are skipped.
+    let mut loaded_manifests = FxHashSet::default();
+    let mut workspaces = Vec::new();
+    for manifest in manifests {
+        if let ProjectManifest::CargoToml(path) = &manifest {
+            if loaded_manifests.contains(path) {
+                continue;
+            }
+        }
+        eprintln!("root = {:?}", manifest);
+        let workspace = match ProjectWorkspace::load(manifest, cargo_config, progress) {
+            Ok(it) => it,
+            // Test fixtures and other broken manifests are common in large
+            // directory trees.
+            Err(err) if recursive => {
+                eprintln!("skipping workspace: {:#}", err);
+                continue;
+            }
+            Err(err) => return Err(LoadError::Metadata(err).into()),
+        };
+        if let ProjectWorkspace::Cargo { cargo, .. } = &workspace {
+            loaded_manifests.insert(cargo.workspace_root().join("Cargo.toml"));
+            loaded_manifests.extend(cargo.packages().map(|it| cargo[it].manifest.clone()));
+        }
+        workspaces.push(workspace);
+    }
+    if !detached_files.is_empty() {
+        for workspace in
+            ProjectWorkspace::load_detached_files(detached_files, cargo_config, progress)
+        {
+            workspaces.push(workspace.map_err(LoadError::Metadata)?);
+        }
+    }
+
+    load_workspace(workspaces, load_config, watch, progress)
 }
 
 fn load_workspace(
-    ws: ProjectWorkspace,
+    workspaces: Vec<ProjectWorkspace>,
     config: &LoadCargoConfig,
-    progress: &dyn Fn(String),
-) -> Result<(AnalysisHost, vfs::Vfs, Option<ProcMacroClient>)> {
+    watch: bool,
+    progress: &dyn Fn(ProgressEvent),
+) -> Result<(AnalysisHost, vfs::Vfs, Option<ProcMacroClient>, Watcher)> {
     let lru_cap = std::env::var("RA_LRU_CAP").ok().and_then(|it| it.parse::<usize>().ok());
     let mut host = AnalysisHost::new(lru_cap);
     host.raw_database_mut().set_enable_proc_attr_macros(true);
 
-    let (change, vfs, proc_macro_client) = load_change(ws, config, progress)?;
+    let (change, vfs, proc_macro_client, watcher) =
+        load_change_inner(workspaces, config, watch, progress)?;
 
     host.apply_change(change);
 
     if config.prefill_caches {
-        host.analysis().prime_caches(|_| {})?;
+        host.analysis().prime_caches(num_cpus::get(), |_| {})?;
     }
-    Ok((host, vfs, proc_macro_client))
+    Ok((host, vfs, proc_macro_client, watcher))
 }
 
 pub(crate) fn load_change(
     ws: ProjectWorkspace,
     config: &LoadCargoConfig,
-    progress: &dyn Fn(String),
+    progress: &dyn Fn(ProgressEvent),
 ) -> Result<(Change, vfs::Vfs, Option<ProcMacroClient>)> {
+    let (change, vfs, proc_macro_client, _watcher) =
+        load_change_inner(vec![ws], config, false, progress)?;
+    Ok((change, vfs, proc_macro_client))
+}
+
+fn load_change_inner(
+    workspaces: Vec<ProjectWorkspace>,
+    config: &LoadCargoConfig,
+    watch: bool,
+    progress: &dyn Fn(ProgressEvent),
+) -> Result<(Change, vfs::Vfs, Option<ProcMacroClient>, Watcher)> {
     let (sender, receiver) = unbounded();
     let mut vfs = vfs::Vfs::default();
     let mut loader = {
@@ -70,50 +273,162 @@ pub(crate) fn load_change(
 
     let proc_macro_client = if config.with_proc_macro {
         let path = AbsPathBuf::assert(std::env::current_exe()?);
-        Some(ProcMacroClient::extern_process(path, &["proc-macro"]).unwrap())
+        let client = ProcMacroClient::extern_process(path, &["proc-macro"])
+            .map_err(|err| LoadError::ProcMacroServer(err.into()))?;
+        Some(client)
     } else {
         None
     };
 
     let build_data = if config.load_out_dirs_from_check {
         let mut collector = BuildDataCollector::new(config.wrap_rustc);
-        ws.collect_build_data_configs(&mut collector);
-        Some(collector.collect(progress)?)
+        for ws in &workspaces {
+            ws.collect_build_data_configs(&mut collector);
+        }
+        let cancel = CancellationToken::default();
+        Some(collector.collect(&cancel, progress).map_err(LoadError::BuildScripts)?)
     } else {
         None
     };
 
-    let crate_graph = ws.to_crate_graph(
-        build_data.as_ref(),
-        proc_macro_client.as_ref(),
-        &mut |path: &AbsPath| {
-            let contents = loader.load_sync(path);
-            let path = vfs::VfsPath::from(path.to_path_buf());
-            vfs.set_file_contents(path.clone(), contents);
-            vfs.file_id(&path)
-        },
-    );
-
-    let project_folders = ProjectFolders::new(&[ws], &[], build_data.as_ref());
+    let mut crate_graph = CrateGraph::default();
+    for ws in &workspaces {
+        let (graph, warnings) = ws.to_crate_graph(
+            build_data.as_ref(),
+            proc_macro_client.as_ref(),
+            &mut |path: &AbsPath| {
+                let contents = loader.load_sync(path);
+                let path = vfs::VfsPath::from(path.to_path_buf());
+                vfs.set_file_contents(path.clone(), contents);
+                vfs.file_id(&path)
+            },
+        );
+        crate_graph.extend_deduplicated(graph);
+        for warning in warnings {
+            eprintln!("warning: {}", warning);
+        }
+    }
+
+    // Workspaces loaded without a sysroot still get `core`, `alloc` and
+    // `std` crates, so that paths into them at least resolve to a crate.
+    let without_sysroot = workspaces.iter().any(|ws| match ws {
+        ProjectWorkspace::Cargo { sysroot, .. } => sysroot.crates().len() == 0,
+        _ => false,
+    });
+    if without_sysroot {
+        add_sysroot_stubs(&mut crate_graph, &mut vfs);
+    }
+
+    // Reading the files in parallel pays off for large vendored trees and on
+    // network file systems. `RA_VFS_THREADS=1` reads them one by one.
+    let num_threads = std::env::var("RA_VFS_THREADS")
+        .ok()
+        .and_then(|it| it.parse::<usize>().ok())
+        .unwrap_or_else(num_cpus::get);
+    let project_folders = ProjectFolders::new(&workspaces, &[], build_data.as_ref());
     loader.set_config(vfs::loader::Config {
         load: project_folders.load,
-        watch: vec![],
+        watch: if watch { project_folders.watch } else { vec![] },
         version: 0,
+        num_threads,
     });
 
     log::debug!("crate graph: {:?}", crate_graph);
 
-    let change =
-        load_crate_graph(crate_graph, project_folders.source_root_config, &mut vfs, &receiver);
+    let source_root_config = project_folders.source_root_config;
+    let change = load_crate_graph(crate_graph, &source_root_config, &mut vfs, &receiver, progress);
+    let watcher = Watcher { _loader: loader, receiver, source_root_config };
 
-    Ok((change, vfs, proc_macro_client))
+    Ok((change, vfs, proc_macro_client, watcher))
+}
+
+/// Owns the file watcher of a loaded workspace.
+pub(crate) struct Watcher {
+    _loader: Box<dyn Handle>,
+    receiver: Receiver<vfs::loader::Message>,
+    source_root_config: SourceRootConfig,
+}
+
+impl Watcher {
+    /// Blocks until some files of the workspace members change on disk and
+    /// returns those changes. Events arriving in quick succession, like when
+    /// an editor saves several files at once, are merged into one change.
+    ///
+    /// Returns `None` if the watcher stopped.
+    pub(crate) fn next_change(&mut self, vfs: &mut vfs::Vfs) -> Option<Change> {
+        loop {
+            let mut message = self.receiver.recv().ok()?;
+            loop {
+                if let vfs::loader::Message::Loaded { files } = message {
+                    for (path, contents) in files {
+                        vfs.set_file_contents(path.into(), contents);
+                    }
+                }
+                message = match self.receiver.recv_timeout(Duration::from_millis(50)) {
+                    Ok(it) => it,
+                    Err(_) => break,
+                };
+            }
+
+            let changed_files = vfs.take_changes();
+            if changed_files.is_empty() {
+                continue;
+            }
+            let mut change = Change::new();
+            let mut has_structure_changes = false;
+            for file in changed_files {
+                has_structure_changes |= file.is_created_or_deleted();
+                let text = if file.exists() {
+                    String::from_utf8(vfs.file_contents(file.file_id).to_vec()).ok().map(Arc::new)
+                } else {
+                    None
+                };
+                change.change_file(file.file_id, text);
+            }
+            if has_structure_changes {
+                change.set_roots(self.source_root_config.partition(vfs));
+            }
+            return Some(change);
+        }
+    }
+}
+
+/// Adds empty `core`, `alloc` and `std` crates and makes them dependencies of
+/// every crate which doesn't have a dependency of that name yet.
+fn add_sysroot_stubs(crate_graph: &mut CrateGraph, vfs: &mut vfs::Vfs) {
+    let krates: Vec<_> = crate_graph.iter().collect();
+    let mut stubs = Vec::new();
+    for &name in ["core", "alloc", "std"].iter() {
+        let path = vfs::VfsPath::new_virtual_path(format!("/sysroot-stub/{}/lib.rs", name));
+        vfs.set_file_contents(path.clone(), Some(Vec::new()));
+        let file_id = vfs.file_id(&path).unwrap();
+        let krate = crate_graph.add_crate_root(
+            file_id,
+            Edition::CURRENT,
+            Some(CrateDisplayName::from_canonical_name(name.to_string())),
+            CfgOptions::default(),
+            CfgOptions::default(),
+            Env::default(),
+            Vec::new(),
+        );
+        stubs.push((CrateName::new(name).unwrap(), krate));
+    }
+    for krate in krates {
+        for (name, stub) in &stubs {
+            if crate_graph[krate].dependencies.iter().all(|dep| dep.name != *name) {
+                // The stubs don't have dependencies, so there can't be cycles.
+                crate_graph.add_dep(krate, name.clone(), *stub).unwrap();
+            }
+        }
+    }
 }
 
 fn load_crate_graph(
     crate_graph: CrateGraph,
-    source_root_config: SourceRootConfig,
+    source_root_config: &SourceRootConfig,
     vfs: &mut vfs::Vfs,
     receiver: &Receiver<vfs::loader::Message>,
+    progress: &dyn Fn(ProgressEvent),
 ) -> Change {
     let mut analysis_change = Change::new();
 
@@ -121,6 +436,7 @@ fn load_crate_graph(
     for task in receiver {
         match task {
             vfs::loader::Message::Progress { n_done, n_total, config_version: _ } => {
+                progress(ProgressEvent::LoadingFiles { current: n_done, total: n_total });
                 if n_done == n_total {
                     break;
                 }
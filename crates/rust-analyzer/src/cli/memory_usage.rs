@@ -0,0 +1,101 @@
+//! Prints memory usage per query group, query and key, optionally comparing
+//! against the numbers from a previous run.
+
+use std::{fs, path::Path};
+
+use ide::AnalysisHost;
+use profile::Bytes;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use vfs::Vfs;
+
+use crate::cli::Result;
+
+const N_LARGEST_KEYS: usize = 20;
+
+/// The part of an [`ide::MemoryUsageReport`] which is stored for comparison with
+/// later runs.
+#[derive(Serialize, Deserialize)]
+struct SavedMemoryUsage {
+    groups: Vec<(String, isize)>,
+    queries: Vec<(String, isize)>,
+}
+
+pub(crate) fn print_memory_usage(
+    mut host: AnalysisHost,
+    vfs: Vfs,
+    save: Option<&Path>,
+    compare: Option<&Path>,
+) -> Result<()> {
+    let report = host.per_query_memory_usage(N_LARGEST_KEYS);
+
+    let before = profile::memory_usage();
+    drop(vfs);
+    let vfs = before.allocated - profile::memory_usage().allocated;
+
+    let before = profile::memory_usage();
+    drop(host);
+    let unaccounted = before.allocated - profile::memory_usage().allocated;
+
+    let mut groups: Vec<(String, Bytes)> =
+        report.per_group().into_iter().map(|(group, bytes)| (group.to_string(), bytes)).collect();
+    groups.push(("VFS".into(), vfs));
+    groups.push(("Unaccounted".into(), unaccounted));
+    groups.push(("Remaining".into(), profile::memory_usage().allocated));
+    let queries: Vec<(String, Bytes)> =
+        report.queries.iter().map(|it| (it.query.clone(), it.bytes)).collect();
+
+    match compare {
+        Some(path) => {
+            let baseline: SavedMemoryUsage = serde_json::from_str(&fs::read_to_string(path)?)?;
+            eprintln!("Query groups (before, after, change):");
+            print_comparison(&baseline.groups, &groups);
+            eprintln!("\nQueries (before, after, change):");
+            print_comparison(&baseline.queries, &queries);
+        }
+        None => {
+            eprintln!("Query groups:");
+            for (name, bytes) in &groups {
+                eprintln!("{:>8} {}", bytes, name);
+            }
+            eprintln!("\nQueries:");
+            for (name, bytes) in &queries {
+                eprintln!("{:>8} {}", bytes, name);
+            }
+        }
+    }
+    eprintln!("\nLargest query values:");
+    for key in &report.largest_keys {
+        eprintln!("{:>8} {} {}", key.bytes, key.query, key.key);
+    }
+
+    if let Some(path) = save {
+        let saved = SavedMemoryUsage {
+            groups: groups.iter().map(|(name, bytes)| (name.clone(), bytes.bytes())).collect(),
+            queries: queries.iter().map(|(name, bytes)| (name.clone(), bytes.bytes())).collect(),
+        };
+        fs::write(path, serde_json::to_string_pretty(&saved)?)?;
+    }
+    Ok(())
+}
+
+fn print_comparison(before: &[(String, isize)], after: &[(String, Bytes)]) {
+    let before: FxHashMap<&str, Bytes> =
+        before.iter().map(|(name, bytes)| (name.as_str(), Bytes::new(*bytes))).collect();
+    let mut rows: Vec<(&str, Bytes, Bytes)> = after
+        .iter()
+        .map(|(name, bytes)| {
+            (name.as_str(), before.get(name.as_str()).copied().unwrap_or_default(), *bytes)
+        })
+        .collect();
+    rows.extend(
+        before
+            .iter()
+            .filter(|(name, _)| !after.iter().any(|(it, _)| it == *name))
+            .map(|(name, bytes)| (*name, *bytes, Bytes::default())),
+    );
+    rows.sort_by_key(|&(_, before, after)| std::cmp::Reverse((after - before).bytes().abs()));
+    for (name, before, after) in rows {
+        eprintln!("{:>8} {:>8} {:>8} {}", before, after, after - before, name);
+    }
+}
@@ -0,0 +1,209 @@
+//! Positions in source files given on the command line, like
+//! `src/main.rs:10:5`.
+
+use std::{
+    convert::TryFrom,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, format_err};
+use ide::{Analysis, FileId, FilePosition, FileRange, TextRange, TextSize};
+use vfs::{AbsPath, AbsPathBuf, Vfs, VfsPath};
+
+use crate::cli::Result;
+
+/// A `file:line:column` position. Lines and columns are 1-based, columns count
+/// characters, like in rustc's diagnostics.
+#[derive(Debug, Clone)]
+pub(crate) struct CliPosition {
+    pub(crate) file: PathBuf,
+    pub(crate) line: u32,
+    pub(crate) column: u32,
+}
+
+impl FromStr for CliPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<CliPosition> {
+        let mut parts = s.rsplitn(3, ':');
+        let (column, line, file) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(column), Some(line), Some(file)) => (column, line, file),
+            _ => bail!("expected `file:line:column`, got `{}`", s),
+        };
+        let line = line.parse().map_err(|_| format_err!("invalid line number `{}`", line))?;
+        let column = column.parse().map_err(|_| format_err!("invalid column `{}`", column))?;
+        if line == 0 || column == 0 {
+            bail!("lines and columns start at 1");
+        }
+        Ok(CliPosition { file: PathBuf::from(file), line, column })
+    }
+}
+
+impl fmt::Display for CliPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+impl CliPosition {
+    /// The inverse of [`CliPosition::resolve`]. The path is made relative to
+    /// the current directory if possible.
+    pub(crate) fn from_offset(
+        analysis: &Analysis,
+        vfs: &Vfs,
+        file_id: FileId,
+        offset: TextSize,
+    ) -> Result<CliPosition> {
+        let file = display_path(vfs, file_id)?;
+        let text = analysis.file_text(file_id)?;
+        Ok(CliPosition::in_text(file, &text, offset))
+    }
+
+    fn in_text(file: PathBuf, text: &str, offset: TextSize) -> CliPosition {
+        let before = &text[..usize::from(offset)];
+        let line_start = before.rfind('\n').map_or(0, |it| it + 1);
+        let line = before.matches('\n').count() as u32 + 1;
+        let column = before[line_start..].chars().count() as u32 + 1;
+        CliPosition { file, line, column }
+    }
+
+    /// Finds the file in the loaded workspace and converts the line and column
+    /// to an offset.
+    pub(crate) fn resolve(&self, analysis: &Analysis, vfs: &Vfs) -> Result<FilePosition> {
+        let path = AbsPathBuf::assert(std::env::current_dir()?.join(&self.file));
+        let path = VfsPath::from(path.normalize());
+        let file_id = vfs
+            .file_id(&path)
+            .ok_or_else(|| format_err!("{} is not part of the workspace", path))?;
+        let text = analysis.file_text(file_id)?;
+
+        let mut line_start = 0;
+        for _ in 1..self.line {
+            match text[line_start..].find('\n') {
+                Some(newline) => line_start += newline + 1,
+                None => bail!("{} has less than {} lines", self.file.display(), self.line),
+            }
+        }
+        let line = text[line_start..].split('\n').next().unwrap_or_default();
+        let column = self.column as usize - 1;
+        let offset = match line.char_indices().nth(column) {
+            Some((offset, _)) => offset,
+            None if line.chars().count() == column => line.len(),
+            None => bail!("line {} has less than {} characters", self.line, self.column),
+        };
+        let offset = TextSize::try_from(line_start + offset)?;
+        Ok(FilePosition { file_id, offset })
+    }
+}
+
+/// A `file:line:column` position or a `file:line:column-line:column` range.
+#[derive(Debug, Clone)]
+pub(crate) struct CliRange {
+    pub(crate) start: CliPosition,
+    pub(crate) end: CliPosition,
+}
+
+impl FromStr for CliRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<CliRange> {
+        // File names may contain dashes, so only split off a suffix which
+        // looks like `line:column`.
+        if let Some((start, end)) = s.rsplit_once('-') {
+            if let Some((line, column)) = end.split_once(':') {
+                if let (Ok(line), Ok(column)) = (line.parse(), column.parse()) {
+                    let start: CliPosition = start.parse()?;
+                    if (line, column) < (start.line, start.column) || column == 0 {
+                        bail!("range end {} is before its start", end);
+                    }
+                    let end = CliPosition { file: start.file.clone(), line, column };
+                    return Ok(CliRange { start, end });
+                }
+            }
+        }
+        let start: CliPosition = s.parse()?;
+        Ok(CliRange { end: start.clone(), start })
+    }
+}
+
+impl fmt::Display for CliRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.start)?;
+        if (self.start.line, self.start.column) != (self.end.line, self.end.column) {
+            write!(f, "-{}:{}", self.end.line, self.end.column)?;
+        }
+        Ok(())
+    }
+}
+
+impl CliRange {
+    pub(crate) fn resolve(&self, analysis: &Analysis, vfs: &Vfs) -> Result<FileRange> {
+        let start = self.start.resolve(analysis, vfs)?;
+        let end = self.end.resolve(analysis, vfs)?;
+        Ok(FileRange { file_id: start.file_id, range: TextRange::new(start.offset, end.offset) })
+    }
+}
+
+/// `path` relative to `base`, with `/` separators on all platforms, or `None`
+/// if `path` is outside of `base`.
+pub(crate) fn relative_path(path: &AbsPath, base: &AbsPath) -> Option<String> {
+    let relative = path.strip_prefix(base)?;
+    let segments = AsRef::<Path>::as_ref(relative)
+        .components()
+        .map(|it| it.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    Some(segments.join("/"))
+}
+
+/// The path of `file_id`, relative to the current directory if possible.
+pub(crate) fn display_path(vfs: &Vfs, file_id: FileId) -> Result<PathBuf> {
+    let cwd = AbsPathBuf::assert(std::env::current_dir()?);
+    let path = vfs.file_path(file_id);
+    let file = match path.as_path() {
+        Some(path) => match path.strip_prefix(&cwd) {
+            Some(relative) => AsRef::<Path>::as_ref(relative).to_path_buf(),
+            None => path.to_path_buf().into(),
+        },
+        None => PathBuf::from(path.to_string()),
+    };
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_positions() {
+        let pos: CliPosition = "src/main.rs:10:5".parse().unwrap();
+        assert_eq!(pos.file, PathBuf::from("src/main.rs"));
+        assert_eq!((pos.line, pos.column), (10, 5));
+        let pos: CliPosition = r"C:\src\main.rs:1:1".parse().unwrap();
+        assert_eq!(pos.file, PathBuf::from(r"C:\src\main.rs"));
+        assert!("src/main.rs:10".parse::<CliPosition>().is_err());
+        assert!("src/main.rs:0:1".parse::<CliPosition>().is_err());
+    }
+
+    #[test]
+    fn parse_ranges() {
+        let range: CliRange = "src/my-crate.rs:2:5-3:1".parse().unwrap();
+        assert_eq!(range.start.file, PathBuf::from("src/my-crate.rs"));
+        assert_eq!((range.start.line, range.start.column), (2, 5));
+        assert_eq!((range.end.line, range.end.column), (3, 1));
+        assert_eq!(range.to_string(), "src/my-crate.rs:2:5-3:1");
+        let range: CliRange = "src/my-crate.rs:2:5".parse().unwrap();
+        assert_eq!(range.to_string(), "src/my-crate.rs:2:5");
+        assert!("src/main.rs:2:5-1:1".parse::<CliRange>().is_err());
+    }
+
+    #[test]
+    fn positions_in_text() {
+        let text = "fn main() {\n    let ä = 92;\n}\n";
+        let pos = CliPosition::in_text(PathBuf::from("main.rs"), text, TextSize::from(23));
+        assert_eq!(pos.to_string(), "main.rs:2:11");
+        let pos = CliPosition::in_text(PathBuf::from("main.rs"), text, TextSize::from(0));
+        assert_eq!(pos.to_string(), "main.rs:1:1");
+    }
+}
@@ -0,0 +1,220 @@
+//! Finds all references of the items at the given positions and prints them as
+//! JSON.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use ide::{Analysis, FileId, ReferenceAccess, TextRange};
+use project_model::CargoConfig;
+use serde::Serialize;
+use syntax::{
+    ast::{self, NameOwner},
+    AstNode, NodeOrToken, SyntaxNode,
+};
+use vfs::Vfs;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+pub struct ReferencesCmd {
+    pub path: PathBuf,
+    /// `file:line:column` positions.
+    pub positions: Vec<String>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Serialize)]
+struct JsonSearch {
+    position: String,
+    declaration: Option<JsonDeclaration>,
+    references: Vec<JsonReference>,
+    /// Occurrences of the name in code disabled by a `#[cfg]`, which can't be
+    /// resolved and might not refer to the item.
+    inactive_references: Vec<JsonLocation>,
+}
+
+#[derive(Serialize)]
+struct JsonDeclaration {
+    name: String,
+    location: JsonLocation,
+}
+
+#[derive(Serialize)]
+struct JsonReference {
+    location: JsonLocation,
+    /// `read`, `write`, `import` or `other`, e.g. for calls.
+    kind: &'static str,
+    /// Like `fn main` or `impl Foo`, `None` at the top level of a file.
+    enclosing_item: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonLocation {
+    file: String,
+    /// 1-based.
+    line: u32,
+    /// 1-based, in characters.
+    column: u32,
+}
+
+impl ReferencesCmd {
+    pub fn run(self) -> Result<()> {
+        if self.positions.is_empty() {
+            bail!("no positions given");
+        }
+        let positions =
+            self.positions.iter().map(|it| it.parse()).collect::<Result<Vec<CliPosition>>>()?;
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+
+        let mut searches = Vec::with_capacity(positions.len());
+        for (input, position) in self.positions.iter().zip(&positions) {
+            let position = position.resolve(&analysis, &vfs)?;
+            let res = match analysis.find_all_refs(position, None)? {
+                Some(it) => it,
+                None => bail!("nothing to search for at {}", input),
+            };
+            let declaration = match res.declaration {
+                Some(decl) => Some(JsonDeclaration {
+                    name: decl.nav.name.to_string(),
+                    location: location(
+                        &analysis,
+                        &vfs,
+                        decl.nav.file_id,
+                        decl.nav.focus_or_full_range(),
+                    )?,
+                }),
+                None => None,
+            };
+
+            let mut files = res.references.into_iter().collect::<Vec<_>>();
+            files.sort_by_key(|(file_id, _)| vfs.file_path(*file_id).to_string());
+            let mut references = Vec::new();
+            for (file_id, mut refs) in files {
+                refs.sort_by_key(|(range, _)| range.start());
+                let file = analysis.parse(file_id)?;
+                for (range, access) in refs {
+                    let node = match file.syntax().covering_element(range) {
+                        NodeOrToken::Node(it) => it,
+                        NodeOrToken::Token(it) => match it.parent() {
+                            Some(it) => it,
+                            None => continue,
+                        },
+                    };
+                    let (is_import, enclosing_item) = enclosing_item(&node);
+                    let kind = match access {
+                        _ if is_import => "import",
+                        Some(ReferenceAccess::Read) => "read",
+                        Some(ReferenceAccess::Write) => "write",
+                        None => "other",
+                    };
+                    references.push(JsonReference {
+                        location: location(&analysis, &vfs, file_id, range)?,
+                        kind,
+                        enclosing_item,
+                    });
+                }
+            }
+            let mut inactive_references = Vec::new();
+            for (file_id, ranges) in res.inactive_references {
+                for range in ranges {
+                    inactive_references.push(location(&analysis, &vfs, file_id, range)?);
+                }
+            }
+            inactive_references
+                .sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+            searches.push(JsonSearch {
+                position: input.clone(),
+                declaration,
+                references,
+                inactive_references,
+            });
+        }
+        println!("{}", serde_json::to_string_pretty(&searches)?);
+        Ok(())
+    }
+}
+
+fn location(
+    analysis: &Analysis,
+    vfs: &Vfs,
+    file_id: FileId,
+    range: TextRange,
+) -> Result<JsonLocation> {
+    let pos = CliPosition::from_offset(analysis, vfs, file_id, range.start())?;
+    Ok(JsonLocation { file: pos.file.display().to_string(), line: pos.line, column: pos.column })
+}
+
+/// Returns whether `node` is part of a `use` item and a description of the
+/// closest item around it, skipping the `use` item itself.
+fn enclosing_item(node: &SyntaxNode) -> (bool, Option<String>) {
+    let mut is_import = false;
+    for item in node.ancestors().filter_map(ast::Item::cast) {
+        let name = |it: &dyn NameOwner| it.name().map_or_else(String::new, |it| it.to_string());
+        let desc = match &item {
+            ast::Item::Use(_) => {
+                is_import = true;
+                continue;
+            }
+            ast::Item::Const(it) => format!("const {}", name(it)),
+            ast::Item::Enum(it) => format!("enum {}", name(it)),
+            ast::Item::Fn(it) => format!("fn {}", name(it)),
+            ast::Item::MacroRules(it) => format!("macro_rules! {}", name(it)),
+            ast::Item::MacroDef(it) => format!("macro {}", name(it)),
+            ast::Item::Module(it) => format!("mod {}", name(it)),
+            ast::Item::Static(it) => format!("static {}", name(it)),
+            ast::Item::Struct(it) => format!("struct {}", name(it)),
+            ast::Item::Trait(it) => format!("trait {}", name(it)),
+            ast::Item::TypeAlias(it) => format!("type {}", name(it)),
+            ast::Item::Union(it) => format!("union {}", name(it)),
+            ast::Item::Impl(it) => {
+                let self_ty = it.self_ty().map_or_else(String::new, |it| it.to_string());
+                match it.trait_() {
+                    Some(trait_) => format!("impl {} for {}", trait_, self_ty),
+                    None => format!("impl {}", self_ty),
+                }
+            }
+            ast::Item::ExternBlock(_) => "extern block".to_string(),
+            ast::Item::ExternCrate(_) | ast::Item::MacroCall(_) => continue,
+        };
+        return (is_import, Some(desc));
+    }
+    (is_import, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use syntax::{SourceFile, TextSize};
+
+    use super::*;
+
+    fn check(text: &str, expected: (bool, Option<&str>)) {
+        let file = SourceFile::parse(text).tree();
+        let offset = TextSize::from(text.find("Foo").unwrap() as u32);
+        let token = file.syntax().token_at_offset(offset).right_biased().unwrap();
+        let (is_import, item) = enclosing_item(&token.parent().unwrap());
+        assert_eq!((is_import, item.as_deref()), expected);
+    }
+
+    #[test]
+    fn enclosing_items() {
+        check("fn main() { let x = Foo; }", (false, Some("fn main")));
+        check("mod m { use super::Foo; }", (true, Some("mod m")));
+        check("use a::Foo;", (true, None));
+        check("impl Bar { fn f() -> Foo {} }", (false, Some("fn f")));
+        check("impl Trait for Foo {}", (false, Some("impl Trait for Foo")));
+    }
+}
@@ -0,0 +1,48 @@
+//! Renames the item at a position across the workspace and either applies the
+//! edit to disk or prints it as a diff.
+
+use std::path::PathBuf;
+
+use anyhow::format_err;
+use project_model::CargoConfig;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    source_change::write_source_change,
+    Result,
+};
+
+pub struct RenameCmd {
+    pub path: PathBuf,
+    /// A `file:line:column` position.
+    pub position: String,
+    pub new_name: String,
+    /// Print a diff instead of modifying any files.
+    pub dry_run: bool,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl RenameCmd {
+    pub fn run(self) -> Result<()> {
+        let position: CliPosition = self.position.parse()?;
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+        let position = position.resolve(&analysis, &vfs)?;
+        let change = analysis
+            .rename(position, &self.new_name)?
+            .map_err(|err| format_err!("cannot rename at {}: {}", self.position, err))?;
+
+        write_source_change(&analysis, &vfs, change, self.dry_run)
+    }
+}
@@ -0,0 +1,139 @@
+//! Lists the tests, benches, doctests and binaries of a workspace together with
+//! the cargo invocation that runs them.
+
+use std::path::PathBuf;
+
+use anyhow::format_err;
+use ide::{Analysis, FileId, RunnableKind};
+use project_model::{CargoConfig, ProjectWorkspace};
+use serde::Serialize;
+use vfs::Vfs;
+
+use crate::{
+    cargo_target_spec::CargoTargetSpec,
+    cli::{
+        load_cargo::{load_project_at, LoadCargoConfig, ProgressFormat},
+        position::CliPosition,
+        Result,
+    },
+};
+
+pub struct RunnablesCmd {
+    pub path: PathBuf,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Serialize)]
+struct JsonRunnable {
+    label: String,
+    /// `test`, `test-mod`, `bench`, `doctest` or `bin`.
+    kind: &'static str,
+    file: String,
+    /// 1-based.
+    line: u32,
+    /// 1-based, in characters.
+    column: u32,
+    /// The directory to run cargo in, `None` outside of Cargo workspaces.
+    workspace_root: Option<String>,
+    cargo_args: Vec<String>,
+    /// Arguments for the test harness, passed after `--`.
+    executable_args: Vec<String>,
+}
+
+impl RunnablesCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro, workspace) =
+            load_project_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+
+        let mut files = Vec::new();
+        for (file_id, path) in vfs.iter() {
+            if path.name_and_extension().map_or(true, |(_, ext)| ext != Some("rs")) {
+                continue;
+            }
+            if analysis.is_library_file(file_id)? || analysis.crate_for(file_id)?.is_empty() {
+                continue;
+            }
+            files.push((path.to_string(), file_id));
+        }
+        files.sort();
+
+        let mut res = Vec::new();
+        for (_, file_id) in files {
+            let mut runnables = analysis.runnables(file_id)?;
+            runnables.sort_by_key(|it| it.nav.full_range.start());
+            for runnable in runnables {
+                let spec = target_spec(&analysis, &vfs, &workspace, runnable.nav.file_id)?;
+                let workspace_root =
+                    spec.as_ref().map(|it| it.workspace_root.display().to_string());
+                let target = spec.as_ref().map(|it| it.target.clone());
+                let (cargo_args, executable_args) = CargoTargetSpec::runnable_args(
+                    &self.cargo_config,
+                    spec,
+                    &runnable.kind,
+                    &runnable.cfg,
+                )
+                .map_err(|err| format_err!("{}", err))?;
+                let kind = match runnable.kind {
+                    RunnableKind::Test { .. } => "test",
+                    RunnableKind::TestMod { .. } => "test-mod",
+                    RunnableKind::Bench { .. } => "bench",
+                    RunnableKind::DocTest { .. } => "doctest",
+                    RunnableKind::Bin => "bin",
+                };
+                let pos = CliPosition::from_offset(
+                    &analysis,
+                    &vfs,
+                    runnable.nav.file_id,
+                    runnable.nav.focus_or_full_range().start(),
+                )?;
+                res.push(JsonRunnable {
+                    label: runnable.label(target),
+                    kind,
+                    file: pos.file.display().to_string(),
+                    line: pos.line,
+                    column: pos.column,
+                    workspace_root,
+                    cargo_args,
+                    executable_args,
+                });
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&res)?);
+        Ok(())
+    }
+}
+
+/// Finds the Cargo target of the crate `file_id` belongs to.
+fn target_spec(
+    analysis: &Analysis,
+    vfs: &Vfs,
+    workspace: &ProjectWorkspace,
+    file_id: FileId,
+) -> Result<Option<CargoTargetSpec>> {
+    let cargo = match workspace {
+        ProjectWorkspace::Cargo { cargo, .. } => cargo,
+        ProjectWorkspace::Json { .. }
+        | ProjectWorkspace::BuildGraph { .. }
+        | ProjectWorkspace::DetachedFiles { .. } => return Ok(None),
+    };
+    let crate_id = match analysis.crate_for(file_id)?.first() {
+        Some(it) => *it,
+        None => return Ok(None),
+    };
+    let root = vfs.file_path(analysis.crate_root(crate_id)?);
+    let spec = root
+        .as_path()
+        .and_then(|root| cargo.target_by_root(root))
+        .map(|target| CargoTargetSpec::new(cargo, target));
+    Ok(spec)
+}
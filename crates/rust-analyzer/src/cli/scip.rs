@@ -0,0 +1,277 @@
+//! Exports a [SCIP](https://github.com/sourcegraph/scip) index of the
+//! workspace: every definition and reference in local crates, together with
+//! hover documentation for the defined symbols.
+//!
+//! SCIP is a protobuf format. It only needs a handful of message types, so
+//! they are encoded by hand instead of pulling in a protobuf code generator.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use hir::Semantics;
+use ide::{moniker_identifier, FilePosition, HoverConfig, HoverDocFormat, LineIndex, TextRange};
+use ide_db::{
+    base_db::FileId,
+    defs::{Definition, NameClass, NameRefClass},
+    RootDatabase,
+};
+use project_model::CargoConfig;
+use syntax::{ast, AstNode};
+use vfs::{AbsPathBuf, Vfs};
+
+use crate::cli::{
+    is_workspace_file,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::relative_path,
+    Result,
+};
+
+pub struct ScipCmd {
+    pub path: PathBuf,
+    /// Defaults to `index.scip` in the current directory.
+    pub output: Option<PathBuf>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl ScipCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let db = host.raw_database();
+        let analysis = host.analysis();
+        let root = AbsPathBuf::assert(std::env::current_dir()?.join(&self.path));
+        let root = if AsRef::<Path>::as_ref(&root).is_file() {
+            root.parent().map(|it| it.to_path_buf()).unwrap_or(root)
+        } else {
+            root
+        };
+
+        let mut index = ProtoWriter::default();
+        index.message(1, |metadata| {
+            metadata.message(2, |tool_info| {
+                tool_info.string(1, "rust-analyzer");
+                tool_info.string(2, env!("REV"));
+            });
+            metadata.string(3, &crate::to_proto::url_from_abs_path(&root).to_string());
+            // `TextEncoding.UTF8`
+            metadata.uint(4, 1);
+        });
+
+        let hover_config = HoverConfig {
+            links_in_hover: false,
+            documentation: Some(HoverDocFormat::Markdown),
+            memory_layout: false,
+        };
+        let sema = Semantics::new(db);
+        for (file_id, relative_path) in local_files(db, &vfs, &root) {
+            let line_index = analysis.file_line_index(file_id)?;
+            let mut document = DocumentBuilder::default();
+            let source_file = sema.parse(file_id);
+            for node in source_file.syntax().descendants() {
+                if let Some(name) = ast::Name::cast(node.clone()) {
+                    let def = match NameClass::classify(&sema, &name).and_then(|it| it.defined()) {
+                        Some(it) => it,
+                        None => continue,
+                    };
+                    let range = name.syntax().text_range();
+                    if let Some(symbol) = document.symbol(db, def) {
+                        let docs = analysis
+                            .hover(&hover_config, FilePosition { file_id, offset: range.start() })?
+                            .map(|it| it.info.markup.to_string());
+                        document.occurrence(&line_index, range, &symbol, true);
+                        document.information(symbol, docs);
+                    }
+                } else if let Some(name_ref) = ast::NameRef::cast(node) {
+                    let range = name_ref.syntax().text_range();
+                    let defs = match NameRefClass::classify(&sema, &name_ref) {
+                        Some(NameRefClass::Definition(def)) => vec![def],
+                        Some(NameRefClass::FieldShorthand { local_ref, field_ref }) => {
+                            vec![Definition::Local(local_ref), Definition::Field(field_ref)]
+                        }
+                        None => continue,
+                    };
+                    for def in defs {
+                        if let Some(symbol) = document.symbol(db, def) {
+                            document.occurrence(&line_index, range, &symbol, false);
+                        }
+                    }
+                }
+            }
+            index.message(2, |it| document.write(&relative_path, it));
+        }
+
+        let output = self.output.unwrap_or_else(|| PathBuf::from("index.scip"));
+        fs::write(&output, index.buf)?;
+        eprintln!("Wrote SCIP index to {}", output.display());
+        Ok(())
+    }
+}
+
+/// Files of local (non-library) source roots below `root`, with their paths
+/// relative to `root`.
+fn local_files(db: &RootDatabase, vfs: &Vfs, root: &AbsPathBuf) -> Vec<(FileId, String)> {
+    let mut res = Vec::new();
+    for (file_id, path) in vfs.iter() {
+        let path = match path.as_path() {
+            Some(it) => it,
+            None => continue,
+        };
+        if !is_workspace_file(db, file_id) {
+            continue;
+        }
+        if let Some(relative) = relative_path(path, root) {
+            if relative.ends_with(".rs") {
+                res.push((file_id, relative));
+            }
+        }
+    }
+    res.sort();
+    res
+}
+
+#[derive(Default)]
+struct DocumentBuilder {
+    /// Function-local definitions get document-local symbols.
+    locals: Vec<Definition>,
+    occurrences: ProtoWriter,
+    symbols: ProtoWriter,
+    seen_symbols: Vec<String>,
+}
+
+impl DocumentBuilder {
+    fn symbol(&mut self, db: &RootDatabase, def: Definition) -> Option<String> {
+        match def {
+            Definition::Local(_)
+            | Definition::GenericParam(_)
+            | Definition::Label(_)
+            | Definition::SelfType(_) => {
+                let idx = match self.locals.iter().position(|it| *it == def) {
+                    Some(it) => it,
+                    None => {
+                        self.locals.push(def);
+                        self.locals.len() - 1
+                    }
+                };
+                Some(format!("local {}", idx))
+            }
+            _ => moniker_identifier(db, def).map(|it| it.to_string()),
+        }
+    }
+
+    fn occurrence(&mut self, line_index: &LineIndex, range: TextRange, symbol: &str, def: bool) {
+        let start = line_index.to_utf16(line_index.line_col(range.start()));
+        let end = line_index.to_utf16(line_index.line_col(range.end()));
+        let range = if start.line == end.line {
+            vec![start.line, start.col, end.col]
+        } else {
+            vec![start.line, start.col, end.line, end.col]
+        };
+        self.occurrences.message(2, |it| {
+            it.packed_uint(1, &range);
+            it.string(2, symbol);
+            if def {
+                // `SymbolRole.Definition`
+                it.uint(3, 1);
+            }
+        });
+    }
+
+    fn information(&mut self, symbol: String, docs: Option<String>) {
+        if self.seen_symbols.contains(&symbol) {
+            return;
+        }
+        self.symbols.message(3, |it| {
+            it.string(1, &symbol);
+            if let Some(docs) = &docs {
+                it.string(3, docs);
+            }
+        });
+        self.seen_symbols.push(symbol);
+    }
+
+    fn write(&self, relative_path: &str, out: &mut ProtoWriter) {
+        out.string(1, relative_path);
+        out.buf.extend_from_slice(&self.occurrences.buf);
+        out.buf.extend_from_slice(&self.symbols.buf);
+        out.string(4, "rust");
+    }
+}
+
+/// A minimal protobuf encoder, covering the wire types used by SCIP.
+#[derive(Default)]
+struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn tag(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        self.tag(field, 0);
+        self.varint(value);
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.tag(field, 2);
+        self.varint(bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, field: u32, s: &str) {
+        self.bytes(field, s.as_bytes());
+    }
+
+    fn packed_uint(&mut self, field: u32, values: &[u32]) {
+        let mut packed = ProtoWriter::default();
+        for &value in values {
+            packed.varint(value.into());
+        }
+        self.bytes(field, &packed.buf);
+    }
+
+    fn message(&mut self, field: u32, f: impl FnOnce(&mut ProtoWriter)) {
+        let mut message = ProtoWriter::default();
+        f(&mut message);
+        self.bytes(field, &message.buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_protobuf() {
+        let mut w = ProtoWriter::default();
+        w.uint(1, 150);
+        w.message(2, |it| it.string(1, "hi"));
+        w.packed_uint(3, &[1, 300]);
+        assert_eq!(
+            w.buf,
+            vec![
+                0x08, 0x96, 0x01, 0x12, 0x04, 0x0a, 0x02, b'h', b'i', 0x1a, 0x03, 0x01, 0xac, 0x02
+            ]
+        );
+    }
+}
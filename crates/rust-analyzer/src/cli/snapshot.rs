@@ -0,0 +1,29 @@
+//! Reads analysis snapshots written by `rust-analyzer json-change --snapshot`.
+//!
+//! The snapshot file is memory-mapped: only the roots and the crate graph are
+//! deserialized up front, file texts are read from the mapping when the
+//! database first asks for them. Proc macros replay the expansions recorded
+//! when the snapshot was written.
+
+use std::{fs::File, path::Path};
+
+use anyhow::Context;
+use ide::Change;
+use ide_db::base_db::snapshot;
+use memmap2::Mmap;
+
+use crate::cli::Result;
+
+/// Maps the snapshot at `path` into memory and turns it into a [`Change`]
+/// whose file texts are materialized lazily.
+pub fn read_snapshot(path: &Path) -> Result<Change> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open snapshot {}", path.display()))?;
+    // SAFETY: the snapshot must not be modified while the analysis is alive,
+    // same as any other memory-mapped input.
+    let mmap = unsafe { Mmap::map(&file) }
+        .with_context(|| format!("Failed to map snapshot {}", path.display()))?;
+    let change = snapshot::decode(mmap)
+        .with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+    Ok(change)
+}
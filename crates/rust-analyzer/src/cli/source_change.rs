@@ -0,0 +1,235 @@
+//! Writes [`SourceChange`]s computed by the CLI to disk, or prints them as a
+//! diff.
+
+use std::fs;
+
+use anyhow::bail;
+use ide::{Analysis, FileId, FileSystemEdit, SourceChange, TextEdit};
+use vfs::{AbsPathBuf, Vfs};
+
+use crate::cli::{position::display_path, Result};
+
+/// Applies `change` to the files on disk. With `dry_run`, the text edits are
+/// printed as a unified diff and file system edits are only described.
+pub(crate) fn write_source_change(
+    analysis: &Analysis,
+    vfs: &Vfs,
+    change: SourceChange,
+    dry_run: bool,
+) -> Result<()> {
+    let mut edits = change.source_file_edits.into_iter().collect::<Vec<_>>();
+    edits.sort_by_key(|(file_id, _)| vfs.file_path(*file_id).to_string());
+    for (file_id, edit) in edits {
+        let text = analysis.file_text(file_id)?;
+        if dry_run {
+            let path = display_path(vfs, file_id)?;
+            print!("{}", unified_diff(&path.display().to_string(), &text, &edit));
+        } else {
+            let path = abs_path(vfs, file_id)?;
+            let mut text = text.to_string();
+            edit.apply(&mut text);
+            fs::write(&path, text)?;
+        }
+    }
+
+    for edit in change.file_system_edits {
+        match edit {
+            FileSystemEdit::CreateFile { dst, initial_contents } => {
+                let dst = anchored_path(vfs, dst.anchor, &dst.path)?;
+                if dry_run {
+                    println!("create {}", dst.display());
+                } else {
+                    if let Some(parent) = dst.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&dst, initial_contents)?;
+                }
+            }
+            FileSystemEdit::MoveFile { src, dst } => {
+                let dst = anchored_path(vfs, dst.anchor, &dst.path)?;
+                let src = abs_path(vfs, src)?;
+                if dry_run {
+                    println!("rename {} -> {}", src.display(), dst.display());
+                } else {
+                    fs::rename(&src, &dst)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn abs_path(vfs: &Vfs, file_id: FileId) -> Result<AbsPathBuf> {
+    let path = vfs.file_path(file_id);
+    match path.as_path() {
+        Some(it) => Ok(it.to_path_buf()),
+        None => bail!("{} is not a file on disk", path),
+    }
+}
+
+fn anchored_path(vfs: &Vfs, anchor: FileId, path: &str) -> Result<AbsPathBuf> {
+    let mut base = vfs.file_path(anchor);
+    base.pop();
+    match base.join(path).as_ref().and_then(|it| it.as_path()) {
+        Some(it) => Ok(it.to_path_buf()),
+        None => bail!("cannot resolve {} relative to {}", path, base),
+    }
+}
+
+const CONTEXT: usize = 3;
+
+/// Renders `edit` as a unified diff of `old` with three lines of context.
+pub(crate) fn unified_diff(path: &str, old: &str, edit: &TextEdit) -> String {
+    let lines = old.split_inclusive('\n').collect::<Vec<_>>();
+    let mut line_starts = Vec::with_capacity(lines.len() + 1);
+    let mut offset = 0;
+    for line in &lines {
+        line_starts.push(offset);
+        offset += line.len();
+    }
+    line_starts.push(old.len());
+    let line_of = |offset: usize| {
+        if offset == old.len() && (old.is_empty() || old.ends_with('\n')) {
+            return lines.len();
+        }
+        line_starts[..lines.len()].partition_point(|&it| it <= offset) - 1
+    };
+
+    // Indels grouped by the lines of `old` they touch, edits of adjacent lines
+    // form one group.
+    let mut groups: Vec<(usize, usize, Vec<(usize, usize, &str)>)> = Vec::new();
+    for indel in edit.iter() {
+        let from = usize::from(indel.delete.start());
+        let to = usize::from(indel.delete.end());
+        let start = line_of(from);
+        let end = if to > from { line_of(to - 1) + 1 } else { start + 1 }.min(lines.len());
+        match groups.last_mut() {
+            Some((_, group_end, indels)) if start <= *group_end => {
+                *group_end = end.max(*group_end);
+                indels.push((from, to, &indel.insert));
+            }
+            _ => groups.push((start, end, vec![(from, to, &indel.insert)])),
+        }
+    }
+    // Replaced line ranges of `old`, together with their replacement.
+    let changes = groups
+        .into_iter()
+        .map(|(start, end, indels)| {
+            let base = line_starts[start];
+            let mut text = old[base..line_starts[end]].to_string();
+            for (from, to, insert) in indels.into_iter().rev() {
+                text.replace_range(from - base..to - base, insert);
+            }
+            (start, end, text.split_inclusive('\n').map(String::from).collect::<Vec<_>>())
+        })
+        .collect::<Vec<_>>();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut res = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let push_line = |res: &mut String, prefix: char, line: &str| {
+        res.push(prefix);
+        res.push_str(line);
+        if !line.ends_with('\n') {
+            res.push_str("\n\\ No newline at end of file\n");
+        }
+    };
+    let mut delta = 0isize;
+    let mut i = 0;
+    while i < changes.len() {
+        let mut j = i + 1;
+        while j < changes.len() && changes[j].0 - changes[j - 1].1 <= 2 * CONTEXT {
+            j += 1;
+        }
+        let hunk = &changes[i..j];
+        let old_start = hunk[0].0.saturating_sub(CONTEXT);
+        let old_end = (hunk[hunk.len() - 1].1 + CONTEXT).min(lines.len());
+        let delta_before = delta;
+        let new_start = (old_start as isize + delta) as usize;
+        let mut body = String::new();
+        let mut line = old_start;
+        for (start, end, new_lines) in hunk {
+            for context in &lines[line..*start] {
+                push_line(&mut body, ' ', context);
+            }
+            for removed in &lines[*start..*end] {
+                push_line(&mut body, '-', removed);
+            }
+            for added in new_lines {
+                push_line(&mut body, '+', added);
+            }
+            delta += new_lines.len() as isize - (end - start) as isize;
+            line = *end;
+        }
+        for context in &lines[line..old_end] {
+            push_line(&mut body, ' ', context);
+        }
+        let old_len = old_end - old_start;
+        let new_len = (old_len as isize + delta - delta_before) as usize;
+        let range = |start: usize, len: usize| {
+            if len == 0 {
+                format!("{},0", start)
+            } else {
+                format!("{},{}", start + 1, len)
+            }
+        };
+        res.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        ));
+        res.push_str(&body);
+        i = j;
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use ide::{TextRange, TextSize};
+
+    use super::*;
+
+    fn edit(edits: &[(u32, u32, &str)]) -> TextEdit {
+        let mut builder = TextEdit::builder();
+        for &(start, end, text) in edits {
+            builder
+                .replace(TextRange::new(TextSize::from(start), TextSize::from(end)), text.into());
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn diff_merges_nearby_changes() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let diff = unified_diff("src/lib.rs", old, &edit(&[(2, 3, "B"), (16, 17, "I")]));
+        assert_eq!(
+            diff,
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,10 +1,10 @@\n a\n-b\n+B\n c\n d\n e\n f\n g\n h\n-i\n+I\n j\n"
+        );
+    }
+
+    #[test]
+    fn diff_without_trailing_newline() {
+        let diff = unified_diff("src/lib.rs", "fn foo() {}", &edit(&[(3, 6, "bar")]));
+        assert_eq!(
+            diff,
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,1 +1,1 @@\n-fn foo() {}\n\\ No newline at end of file\n+fn bar() {}\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn diff_groups_edits_by_line() {
+        let text = "fn foo() {}\n\nfn main() {\n    foo();\n    foo();\n}\n";
+        let mut builder = TextEdit::builder();
+        builder.replace(TextRange::at(3.into(), 3.into()), "bar".to_string());
+        builder.replace(TextRange::at(TextSize::from(29), 3.into()), "bar".to_string());
+        builder.replace(TextRange::at(TextSize::from(40), 3.into()), "bar".to_string());
+        let diff = unified_diff("src/main.rs", text, &builder.finish());
+        assert_eq!(
+            diff,
+            "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,6 +1,6 @@\n-fn foo() {}\n+fn bar() {}\n \n fn main() {\n-    foo();\n-    foo();\n+    bar();\n+    bar();\n }\n"
+        );
+    }
+}
@@ -1,54 +1,132 @@
 //! Applies structured search replace rules from the command line.
 
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use ide_db::base_db::SourceDatabaseExt;
+use ide_ssr::{MatchFinder, SsrPattern, SsrRule};
+use project_model::{CargoConfig, Glob};
+use vfs::AbsPathBuf;
+
 use crate::cli::{
-    load_cargo::{load_workspace_at, LoadCargoConfig},
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::{relative_path, CliPosition},
+    source_change::unified_diff,
     Result,
 };
-use ide_ssr::{MatchFinder, SsrPattern, SsrRule};
 
-pub fn apply_ssr_rules(rules: Vec<SsrRule>) -> Result<()> {
-    use ide_db::base_db::SourceDatabaseExt;
-    let cargo_config = Default::default();
-    let load_cargo_config = LoadCargoConfig {
-        load_out_dirs_from_check: true,
-        wrap_rustc: false,
-        with_proc_macro: true,
-        prefill_caches: false,
-    };
-    let (host, vfs, _proc_macro) =
-        load_workspace_at(&std::env::current_dir()?, &cargo_config, &load_cargo_config, &|_| {})?;
-    let db = host.raw_database();
-    let mut match_finder = MatchFinder::at_first_file(db)?;
-    for rule in rules {
-        match_finder.add_rule(rule)?;
+pub struct SsrCmd {
+    pub rules: Vec<SsrRule>,
+    /// A file with one rule per line. Empty lines and lines starting with `//`
+    /// are skipped.
+    pub rules_file: Option<PathBuf>,
+    /// Print unified diffs instead of rewriting files.
+    pub dry_run: bool,
+    /// Only touch files whose path, relative to the current directory, matches
+    /// one of these globs.
+    pub include: Vec<String>,
+    /// Don't touch files whose path matches one of these globs.
+    pub exclude: Vec<String>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl SsrCmd {
+    pub fn run(self) -> Result<()> {
+        let progress = &self.progress;
+        let mut rules = self.rules;
+        if let Some(rules_file) = &self.rules_file {
+            rules.extend(parse_rules_file(&fs::read_to_string(rules_file)?)?);
+        }
+
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let cwd = AbsPathBuf::assert(std::env::current_dir()?);
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(cwd.as_ref(), &self.cargo_config, &load_cargo_config, &|event| {
+                progress.report(event)
+            })?;
+        let db = host.raw_database();
+        let include = self.include.iter().map(|it| Glob::new(it)).collect::<Vec<_>>();
+        let exclude = self.exclude.iter().map(|it| Glob::new(it)).collect::<Vec<_>>();
+        let mut match_finder = MatchFinder::at_first_file(db)?;
+        for rule in rules {
+            match_finder.add_rule(rule)?;
+        }
+        let mut edits = match_finder
+            .edits()
+            .into_iter()
+            .filter_map(|(file_id, edit)| {
+                let path = vfs.file_path(file_id).as_path()?.to_path_buf();
+                let relative =
+                    relative_path(&path, &cwd).unwrap_or_else(|| path.display().to_string());
+                Some((file_id, path, relative, edit))
+            })
+            .filter(|(_, _, relative, _)| {
+                let segments = relative.split('/').map(String::from).collect::<Vec<_>>();
+                (include.is_empty() || include.iter().any(|it| it.matches(&segments)))
+                    && !exclude.iter().any(|it| it.matches(&segments))
+            })
+            .collect::<Vec<_>>();
+        edits.sort_by(|a, b| a.2.cmp(&b.2));
+
+        for (file_id, path, relative, edit) in edits {
+            let old = db.file_text(file_id);
+            if self.dry_run {
+                print!("{}", unified_diff(&relative, &old, &edit));
+            } else {
+                let mut contents = old.to_string();
+                edit.apply(&mut contents);
+                fs::write(path, contents)?;
+            }
+        }
+        Ok(())
     }
-    let edits = match_finder.edits();
-    for (file_id, edit) in edits {
-        if let Some(path) = vfs.file_path(file_id).as_path() {
-            let mut contents = db.file_text(file_id).to_string();
-            edit.apply(&mut contents);
-            std::fs::write(path, contents)?;
+}
+
+fn parse_rules_file(text: &str) -> Result<Vec<SsrRule>> {
+    let mut rules = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
         }
+        rules.push(SsrRule::from_str(line)?);
     }
-    Ok(())
+    Ok(rules)
 }
 
-/// Searches for `patterns`, printing debug information for any nodes whose text exactly matches
-/// `debug_snippet`. This is intended for debugging and probably isn't in it's current form useful
-/// for much else.
-pub fn search_for_patterns(patterns: Vec<SsrPattern>, debug_snippet: Option<String>) -> Result<()> {
-    use ide_db::base_db::SourceDatabaseExt;
+/// Searches for `patterns` in the workspace at `path` and prints every match
+/// as `file:line:column: text`, followed by the code bound to each
+/// placeholder. Whitespace in the printed code is collapsed.
+///
+/// With `debug_snippet`, prints debug information for any nodes whose text
+/// exactly matches the snippet instead.
+pub fn search_for_patterns(
+    path: &Path,
+    patterns: Vec<SsrPattern>,
+    debug_snippet: Option<String>,
+    cargo_config: &CargoConfig,
+    progress: ProgressFormat,
+) -> Result<()> {
     use ide_db::symbol_index::SymbolsDatabase;
-    let cargo_config = Default::default();
     let load_cargo_config = LoadCargoConfig {
         load_out_dirs_from_check: true,
         wrap_rustc: true,
         with_proc_macro: true,
         prefill_caches: false,
     };
-    let (host, _vfs, _proc_macro) =
-        load_workspace_at(&std::env::current_dir()?, &cargo_config, &load_cargo_config, &|_| {})?;
+    let (host, vfs, _proc_macro) =
+        load_workspace_at(path, cargo_config, &load_cargo_config, &|event| progress.report(event))?;
     let db = host.raw_database();
+    let analysis = host.analysis();
     let mut match_finder = MatchFinder::at_first_file(db)?;
     for pattern in patterns {
         match_finder.add_search_pattern(pattern)?;
@@ -62,12 +140,45 @@ pub fn search_for_patterns(patterns: Vec<SsrPattern>, debug_snippet: Option<Stri
                 }
             }
         }
-    } else {
-        for m in match_finder.matches().flattened().matches {
-            // We could possibly at some point do something more useful than just printing
-            // the matched text. For now though, that's the easiest thing to do.
-            println!("{}", m.matched_text());
+        return Ok(());
+    }
+
+    let mut matches = Vec::new();
+    for m in match_finder.matches().flattened().matches {
+        let range = m.range();
+        let position =
+            CliPosition::from_offset(&analysis, &vfs, range.file_id, range.range.start())?;
+        let text = analysis.file_text(range.file_id)?;
+        let placeholders: Vec<(String, String)> = m
+            .placeholder_ranges()
+            .into_iter()
+            .map(|(name, range)| (name.to_string(), collapse_whitespace(&text[range.range])))
+            .collect();
+        matches.push((position, collapse_whitespace(&m.matched_text()), placeholders));
+    }
+    matches
+        .sort_by(|(a, ..), (b, ..)| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    for (position, text, placeholders) in matches {
+        println!("{}: {}", position, text);
+        for (name, value) in placeholders {
+            println!("    ${} = {}", name, value);
         }
     }
     Ok(())
 }
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_file() {
+        let rules =
+            parse_rules_file("// comment\n\nfoo($a) ==>> bar($a)\n  baz() ==>> qux()\n").unwrap();
+        assert_eq!(rules.len(), 2);
+    }
+}
@@ -0,0 +1,114 @@
+//! Dumps the symbol index of a workspace as JSON, for code search and tag file
+//! generators.
+
+use std::path::Path;
+
+use ide::LineIndex;
+use ide_db::{
+    base_db::SourceDatabase,
+    symbol_index::{self, FileSymbolKind, Query},
+};
+use project_model::CargoConfig;
+use serde::Serialize;
+use syntax::{ast, AstNode, TextRange};
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    Result,
+};
+
+#[derive(Serialize)]
+struct Symbol {
+    name: String,
+    kind: &'static str,
+    container: Option<String>,
+    file: String,
+    range: Range,
+    name_range: Option<Range>,
+    /// The visibility as written, like `pub(crate)`. Empty for private items.
+    visibility: String,
+}
+
+/// Zero-based lines and UTF-16 columns, like in LSP.
+#[derive(Serialize)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+#[derive(Serialize)]
+struct Position {
+    line: u32,
+    character: u32,
+}
+
+pub fn dump_symbols(
+    path: &Path,
+    with_deps: bool,
+    cargo_config: &CargoConfig,
+    progress: ProgressFormat,
+) -> Result<()> {
+    let load_cargo_config = LoadCargoConfig {
+        load_out_dirs_from_check: true,
+        wrap_rustc: false,
+        with_proc_macro: true,
+        prefill_caches: false,
+    };
+    let (host, vfs, _proc_macro) =
+        load_workspace_at(path, cargo_config, &load_cargo_config, &|event| progress.report(event))?;
+    let db = host.raw_database();
+    let analysis = host.analysis();
+
+    let mut file_symbols = symbol_index::world_symbols(db, Query::new(String::new()));
+    if with_deps {
+        let mut query = Query::new(String::new());
+        query.libs();
+        file_symbols.extend(symbol_index::world_symbols(db, query));
+    }
+    file_symbols.sort_by_key(|it| (it.file_id, it.range.start()));
+
+    let mut symbols = Vec::with_capacity(file_symbols.len());
+    for symbol in file_symbols {
+        let line_index = analysis.file_line_index(symbol.file_id)?;
+        let root = db.parse(symbol.file_id).tree();
+        let node = symbol.ptr.to_node(root.syntax());
+        let visibility = node
+            .children()
+            .find_map(ast::Visibility::cast)
+            .map_or_else(String::new, |it| it.syntax().text().to_string());
+        symbols.push(Symbol {
+            name: symbol.name.to_string(),
+            kind: kind_name(symbol.kind),
+            container: symbol.container_name.map(|it| it.to_string()),
+            file: vfs.file_path(symbol.file_id).to_string(),
+            range: range(&line_index, symbol.range),
+            name_range: symbol.name_range.map(|it| range(&line_index, it)),
+            visibility,
+        });
+    }
+    println!("{}", serde_json::to_string_pretty(&symbols)?);
+    Ok(())
+}
+
+fn kind_name(kind: FileSymbolKind) -> &'static str {
+    match kind {
+        FileSymbolKind::Const => "const",
+        FileSymbolKind::Enum => "enum",
+        FileSymbolKind::Function => "function",
+        FileSymbolKind::Macro => "macro",
+        FileSymbolKind::Module => "module",
+        FileSymbolKind::Static => "static",
+        FileSymbolKind::Struct => "struct",
+        FileSymbolKind::Trait => "trait",
+        FileSymbolKind::TypeAlias => "type_alias",
+        FileSymbolKind::Union => "union",
+    }
+}
+
+fn range(line_index: &LineIndex, range: TextRange) -> Range {
+    let position = |offset| {
+        let line_col = line_index.to_utf16(line_index.line_col(offset));
+        Position { line: line_col.line, character: line_col.col }
+    };
+    Range { start: position(range.start()), end: position(range.end()) }
+}
@@ -0,0 +1,52 @@
+//! Prints the inferred type of the expression at a position.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use project_model::CargoConfig;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+pub struct TypeAtCmd {
+    pub path: PathBuf,
+    /// A `file:line:column` position.
+    pub position: String,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl TypeAtCmd {
+    pub fn run(self) -> Result<()> {
+        let position: CliPosition = self.position.parse()?;
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+        let position = position.resolve(&analysis, &vfs)?;
+        let ty = match analysis.type_at(position)? {
+            Some(it) => it,
+            None => bail!("no typed expression or pattern at {}", self.position),
+        };
+        let start = CliPosition::from_offset(&analysis, &vfs, position.file_id, ty.range.start())?;
+        let text = analysis.file_text(position.file_id)?;
+        let expr = &text[ty.range];
+        // Long expressions would drown the type, only show them when they fit on a line.
+        if expr.contains('\n') || expr.len() > 60 {
+            println!("{}: {}", start, ty.info);
+        } else {
+            println!("{}: `{}`: {}", start, expr, ty.info);
+        }
+        Ok(())
+    }
+}
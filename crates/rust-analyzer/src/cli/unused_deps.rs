@@ -0,0 +1,164 @@
+//! Reports dependencies and cargo features which are never used.
+//!
+//! A dependency counts as used if name resolution maps some name in the
+//! dependent crate to an item of the dependency, or, as a fallback for macro
+//! inputs which aren't resolved, if its name shows up in a token tree. A
+//! feature counts as used if a `feature = "..."` predicate mentions it.
+//!
+//! Crates are grouped by source root, so that the library, binaries and tests
+//! of a package are judged together.
+
+use std::path::PathBuf;
+
+use hir::{Crate, ModuleDef, Semantics};
+use ide::RootDatabase;
+use ide_db::{
+    base_db::{FileId, SourceDatabaseExt, SourceRootId},
+    defs::{Definition, NameRefClass},
+};
+use project_model::CargoConfig;
+use rustc_hash::{FxHashMap, FxHashSet};
+use syntax::{ast, AstNode, SyntaxKind};
+
+use crate::cli::{
+    highlight::crate_files,
+    is_workspace_crate,
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    Result,
+};
+
+/// Dependencies injected from the sysroot, which can't be removed.
+const SYSROOT_CRATES: &[&str] = &["core", "alloc", "std", "proc_macro", "test"];
+
+pub struct UnusedDepsCmd {
+    pub path: PathBuf,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+#[derive(Default)]
+struct Usages {
+    crates: FxHashSet<Crate>,
+    idents: FxHashSet<String>,
+    features: FxHashSet<String>,
+}
+
+impl UnusedDepsCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, _vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let db = host.raw_database();
+
+        let mut packages: FxHashMap<SourceRootId, Vec<Crate>> = FxHashMap::default();
+        for krate in Crate::all(db) {
+            if is_workspace_crate(db, krate) {
+                let source_root = db.file_source_root(krate.root_file(db));
+                packages.entry(source_root).or_default().push(krate);
+            }
+        }
+        let mut packages = packages.into_iter().collect::<Vec<_>>();
+        packages.sort_by_key(|(source_root, _)| *source_root);
+
+        let sema = Semantics::new(db);
+        let mut found_any = false;
+        for (_, crates) in packages {
+            let mut usages = Usages::default();
+            for &krate in &crates {
+                for file_id in crate_files(db, krate) {
+                    collect_usages(&sema, file_id, &mut usages);
+                }
+            }
+
+            let mut unused_deps = FxHashSet::default();
+            let mut used_deps = FxHashSet::default();
+            let mut unused_features = FxHashSet::default();
+            for &krate in &crates {
+                for dep in krate.dependencies(db) {
+                    let name = dep.name.to_string();
+                    if SYSROOT_CRATES.contains(&name.as_str()) {
+                        continue;
+                    }
+                    if usages.crates.contains(&dep.krate) || usages.idents.contains(&name) {
+                        used_deps.insert(name);
+                    } else {
+                        unused_deps.insert(name);
+                    }
+                }
+                for feature in krate.potential_cfg(db).get_cfg_values("feature") {
+                    if feature != "default" && !usages.features.contains(feature.as_str()) {
+                        unused_features.insert(feature.to_string());
+                    }
+                }
+            }
+            let mut unused_deps = unused_deps.difference(&used_deps).collect::<Vec<_>>();
+            let mut unused_features = unused_features.into_iter().collect::<Vec<_>>();
+            if unused_deps.is_empty() && unused_features.is_empty() {
+                continue;
+            }
+            unused_deps.sort();
+            unused_features.sort();
+            found_any = true;
+
+            let name = crates
+                .iter()
+                .find_map(|it| it.display_name(db))
+                .map_or_else(|| "<unnamed>".to_string(), |it| it.to_string());
+            println!("{}", name);
+            for dep in unused_deps {
+                println!("    unused dependency `{}`", dep);
+            }
+            for feature in unused_features {
+                println!("    unused feature `{}`", feature);
+            }
+        }
+        if !found_any {
+            eprintln!("no unused dependencies or features found");
+        }
+        Ok(())
+    }
+}
+
+fn collect_usages(sema: &Semantics<RootDatabase>, file_id: FileId, usages: &mut Usages) {
+    let file = sema.parse(file_id);
+    for node in file.syntax().descendants() {
+        if let Some(name_ref) = ast::NameRef::cast(node.clone()) {
+            let def = match NameRefClass::classify(sema, &name_ref) {
+                Some(NameRefClass::Definition(def)) => def,
+                Some(NameRefClass::FieldShorthand { .. }) | None => continue,
+            };
+            let krate = match def {
+                Definition::ModuleDef(ModuleDef::Module(module)) => Some(module.krate()),
+                _ => def.module(sema.db).map(|it| it.krate()),
+            };
+            usages.crates.extend(krate);
+        } else if let Some(tt) = ast::TokenTree::cast(node) {
+            let tokens = tt
+                .syntax()
+                .children_with_tokens()
+                .filter_map(|it| it.into_token())
+                .filter(|it| !it.kind().is_trivia())
+                .collect::<Vec<_>>();
+            for token in &tokens {
+                if token.kind() == SyntaxKind::IDENT {
+                    usages.idents.insert(token.text().to_string());
+                }
+            }
+            for window in tokens.windows(3) {
+                if window[0].text() == "feature"
+                    && window[1].kind() == SyntaxKind::EQ
+                    && window[2].kind() == SyntaxKind::STRING
+                {
+                    usages.features.insert(window[2].text().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+}
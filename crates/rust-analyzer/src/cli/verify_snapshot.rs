@@ -0,0 +1,210 @@
+//! Checks a snapshot written by `rust-analyzer json-change --snapshot` against
+//! the workspace it was created from.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+use ide::Change;
+use ide_db::base_db::{CrateData, CrateGraph, FileId};
+use project_model::{CargoConfig, ProjectManifest};
+use rustc_hash::FxHashMap;
+use vfs::AbsPathBuf;
+
+use crate::cli::{json_change::load_change_at, load_cargo::ProgressFormat, read_snapshot, Result};
+
+pub struct VerifySnapshotCmd {
+    pub snapshot: PathBuf,
+    /// The workspace to compare against, the current directory if not given.
+    pub workspace: Option<PathBuf>,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl VerifySnapshotCmd {
+    pub fn run(self) -> Result<()> {
+        let snapshot = State::new(read_snapshot(&self.snapshot)?);
+        let mut problems = snapshot.check_integrity();
+
+        let root = self.workspace.clone().unwrap_or_else(|| PathBuf::from("."));
+        if self.workspace.is_some() || has_manifest(&root) {
+            let (live, _proc_macro) = load_change_at(&root, &self.cargo_config, false, &|event| {
+                self.progress.report(event)
+            })?;
+            problems.extend(snapshot.compare(&State::new(live)));
+        } else {
+            eprintln!("no workspace found, only checking the snapshot itself");
+        }
+
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        if !problems.is_empty() {
+            bail!("snapshot {} is out of date or broken", self.snapshot.display());
+        }
+        eprintln!("snapshot {} is up to date", self.snapshot.display());
+        Ok(())
+    }
+}
+
+fn has_manifest(root: &Path) -> bool {
+    let root = match std::env::current_dir() {
+        Ok(cwd) => AbsPathBuf::assert(cwd.join(root)),
+        Err(_) => return false,
+    };
+    ProjectManifest::discover_single(&root).is_ok()
+}
+
+/// Files and crates of a [`Change`], keyed by path instead of by id, as ids
+/// are not stable between loads.
+struct State {
+    /// Path to text, `None` if the text is missing.
+    files: BTreeMap<String, Option<String>>,
+    /// `(display name, root file path)` to the crates with that key.
+    crates: BTreeMap<(String, String), Vec<CrateData>>,
+    crate_graph: CrateGraph,
+}
+
+impl State {
+    fn new(change: Change) -> State {
+        let mut paths = FxHashMap::default();
+        for root in change.roots.iter().flatten() {
+            for file_id in root.iter() {
+                if let Some(path) = root.path_for_file(&file_id) {
+                    paths.insert(file_id, path.to_string());
+                }
+            }
+        }
+
+        let mut texts: FxHashMap<FileId, String> = FxHashMap::default();
+        for (file_id, text) in &change.files_changed {
+            if let Some(text) = text {
+                texts.insert(*file_id, text.to_string());
+            }
+        }
+        if let Some(lazy) = &change.lazy_files {
+            for &file_id in &lazy.files {
+                if let Some(text) = lazy.source.text(file_id) {
+                    texts.insert(file_id, text);
+                }
+            }
+        }
+        let files =
+            paths.iter().map(|(file_id, path)| (path.clone(), texts.remove(file_id))).collect();
+
+        let crate_graph = change.crate_graph.unwrap_or_default();
+        let mut crates: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for krate in crate_graph.iter() {
+            let data = &crate_graph[krate];
+            let root = paths
+                .get(&data.root_file_id)
+                .cloned()
+                .unwrap_or_else(|| format!("<missing file {}>", data.root_file_id.0));
+            crates.entry((crate_name(data), root)).or_default().push(data.clone());
+        }
+        State { files, crates, crate_graph }
+    }
+
+    /// Finds files without text and crates whose root file is not part of any
+    /// source root.
+    fn check_integrity(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (path, text) in &self.files {
+            if text.is_none() {
+                problems.push(format!("snapshot has no text for {}", path));
+            }
+        }
+        for (name, root) in self.crates.keys() {
+            if !self.files.contains_key(root) {
+                problems.push(format!("root file of crate `{}` is missing: {}", name, root));
+            }
+        }
+        problems
+    }
+
+    /// Reports how `live` differs from `self`, the snapshot.
+    fn compare(&self, live: &State) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (path, text) in &self.files {
+            match live.files.get(path) {
+                None => problems.push(format!("file removed since the snapshot: {}", path)),
+                Some(live_text) if live_text != text => {
+                    problems.push(format!("file changed since the snapshot: {}", path))
+                }
+                Some(_) => (),
+            }
+        }
+        for path in live.files.keys() {
+            if !self.files.contains_key(path) {
+                problems.push(format!("file added since the snapshot: {}", path));
+            }
+        }
+
+        for (key @ (name, root), crates) in &self.crates {
+            let live_crates = match live.crates.get(key) {
+                Some(it) => it,
+                None => {
+                    problems
+                        .push(format!("crate `{}` ({}) is missing in the workspace", name, root));
+                    continue;
+                }
+            };
+            if crates.len() != live_crates.len() {
+                problems.push(format!(
+                    "crate `{}` ({}) is loaded {} times in the snapshot, but {} times in the workspace",
+                    name,
+                    root,
+                    crates.len(),
+                    live_crates.len()
+                ));
+                continue;
+            }
+            if let [snapshot_crate] = crates.as_slice() {
+                let live_crate = &live_crates[0];
+                let diff = snapshot_crate.cfg_options.diff(&live_crate.cfg_options);
+                if !diff.is_empty() {
+                    problems.push(format!("cfgs of crate `{}` changed: {}", name, diff));
+                }
+                let deps = self.dependencies(snapshot_crate);
+                let live_deps = live.dependencies(live_crate);
+                if deps != live_deps {
+                    problems.push(format!(
+                        "dependencies of crate `{}` changed: {} -> {}",
+                        name,
+                        deps.join(", "),
+                        live_deps.join(", ")
+                    ));
+                }
+            }
+        }
+        for (name, root) in live.crates.keys() {
+            if !self.crates.contains_key(&(name.clone(), root.clone())) {
+                problems.push(format!("crate `{}` ({}) is missing in the snapshot", name, root));
+            }
+        }
+        problems
+    }
+
+    fn dependencies(&self, data: &CrateData) -> Vec<String> {
+        let mut deps = data
+            .dependencies
+            .iter()
+            .map(|dep| {
+                let target = crate_name(&self.crate_graph[dep.crate_id]);
+                if *dep.name == *target {
+                    target
+                } else {
+                    format!("{} as {}", target, dep.name)
+                }
+            })
+            .collect::<Vec<_>>();
+        deps.sort();
+        deps
+    }
+}
+
+fn crate_name(data: &CrateData) -> String {
+    data.display_name.as_ref().map_or_else(|| "(unnamed crate)".to_string(), |it| it.to_string())
+}
@@ -0,0 +1,56 @@
+//! Prints the lowered body of a function, for debugging type inference.
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use project_model::CargoConfig;
+
+use crate::cli::{
+    load_cargo::{load_workspace_at, LoadCargoConfig, ProgressFormat},
+    position::CliPosition,
+    Result,
+};
+
+pub struct ViewHirCmd {
+    pub path: PathBuf,
+    /// A `file:line:column` position inside a function, or the path of a
+    /// function like `krate::module::Type::method`.
+    pub target: String,
+    pub cargo_config: CargoConfig,
+    pub progress: ProgressFormat,
+}
+
+impl ViewHirCmd {
+    pub fn run(self) -> Result<()> {
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: true,
+            wrap_rustc: false,
+            with_proc_macro: true,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &self.cargo_config, &load_cargo_config, &|event| {
+                self.progress.report(event)
+            })?;
+        let analysis = host.analysis();
+
+        if let Ok(position) = self.target.parse::<CliPosition>() {
+            let position = position.resolve(&analysis, &vfs)?;
+            println!("{}", analysis.view_hir(position)?);
+            return Ok(());
+        }
+
+        let functions = analysis.view_hir_of_path(&self.target)?;
+        if functions.is_empty() {
+            bail!("no function `{}` found", self.target);
+        }
+        let many = functions.len() > 1;
+        for (path, hir) in functions {
+            if many {
+                println!("// {}", path);
+            }
+            println!("{}", hir);
+        }
+        Ok(())
+    }
+}
@@ -7,9 +7,9 @@
 //! configure the server itself, feature flags are passed into analysis, and
 //! tweak things like automatic insertion of `()` in completions.
 
-use std::{ffi::OsString, iter, path::PathBuf};
+use std::{ffi::OsString, iter, path::PathBuf, time::Duration};
 
-use flycheck::FlycheckConfig;
+use flycheck::{DiagnosticsParser, FlycheckConfig};
 use ide::{
     AssistConfig, CompletionConfig, DiagnosticsConfig, HoverConfig, HoverDocFormat,
     InlayHintsConfig, JoinLinesConfig,
@@ -19,7 +19,9 @@ use ide_db::helpers::{
     SnippetCap,
 };
 use lsp_types::{ClientCapabilities, MarkupKind};
-use project_model::{CargoConfig, ProjectJson, ProjectJsonData, ProjectManifest, RustcSource};
+use project_model::{
+    CargoConfig, DiscoveryConfig, ProjectJson, ProjectJsonData, ProjectManifest, RustcSource,
+};
 use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{de::DeserializeOwned, Deserialize};
 use vfs::AbsPathBuf;
@@ -59,12 +61,35 @@ config_data! {
         /// Automatically refresh project info via `cargo metadata` on
         /// `Cargo.toml` changes.
         cargo_autoreload: bool           = "true",
+        /// Milliseconds to wait after the last `Cargo.toml` change before
+        /// refreshing, so that a burst of edits triggers a single refresh.
+        cargo_autoreloadDelay: usize     = "500",
+        /// Only refresh the workspaces containing the changed manifests,
+        /// instead of all of them.
+        cargo_autoreloadScoped: bool     = "true",
         /// Activate all available features (`--all-features`).
         cargo_allFeatures: bool          = "false",
         /// Unsets `#[cfg(test)]` for the specified crates.
         cargo_unsetTest: Vec<String>   = "[\"core\"]",
         /// List of features to activate.
         cargo_features: Vec<String>      = "[]",
+        /// Features to activate for single workspace members, like
+        /// `{ "my-crate": ["foo"], "other": ["all"] }`. `all` activates all
+        /// features of the package.
+        cargo_packageFeatures: FxHashMap<String, Vec<String>> = "{}",
+        /// Whether the crates of a package are `#![no_std]`, like
+        /// `{ "my-crate": true }`. `no_std` crates don't depend on `std`.
+        /// By default, a crate is `no_std` if its root declares
+        /// `#![no_std]` unconditionally.
+        cargo_noStd: FxHashMap<String, bool> = "{}",
+        /// Additional `--cfg` options for the crates of a package, like
+        /// `{ "tokio": ["tokio_unstable"], "*": ["foo=\"bar\""] }`. `*`
+        /// applies to all packages, like cfgs passed in `RUSTFLAGS`.
+        cargo_extraCfgs: FxHashMap<String, Vec<String>> = "{}",
+        /// Additional environment variables for the crates of a package, seen
+        /// by `env!`, like `{ "*": { "APP_VERSION": "dev" } }`. `*` applies
+        /// to all packages.
+        cargo_extraEnv: FxHashMap<String, FxHashMap<String, String>> = "{}",
         /// Run build scripts (`build.rs`) for more precise code analysis.
         cargo_runBuildScripts |
         cargo_loadOutDirsFromCheck: bool = "true",
@@ -75,8 +100,24 @@ config_data! {
         cargo_noDefaultFeatures: bool    = "false",
         /// Compilation target (target triple).
         cargo_target: Option<String>     = "null",
+        /// Targets to load the workspace for in addition to the compilation
+        /// target, like `wasm32-unknown-unknown`. Use the
+        /// `rust-analyzer.selectActiveTarget` command to pick the target of
+        /// the current file.
+        cargo_extraTargets: Vec<String>  = "[]",
         /// Internal config for debugging, disables loading of sysroot crates.
         cargo_noSysroot: bool            = "false",
+        /// Path to the sysroot to load the standard library from, instead of
+        /// the one of the toolchain (`rustc --print sysroot`).
+        cargo_sysroot: Option<String>    = "null",
+        /// Path to the sources of the standard library, the `library`
+        /// directory of the Rust sources. Takes precedence over
+        /// `RUST_SRC_PATH` and `#rust-analyzer.cargo.sysroot#`.
+        cargo_sysrootSrc: Option<String> = "null",
+        /// Run cargo with `--frozen` when loading the workspace and checking
+        /// it, so that it neither accesses the network nor updates
+        /// `Cargo.lock`. The dependencies must have been fetched before.
+        cargo_offline: bool              = "false",
 
         /// Run specified `cargo check` command for diagnostics on save.
         checkOnSave_enable: bool                         = "true",
@@ -101,6 +142,14 @@ config_data! {
         /// checking. The command should include `--message-format=json` or
         /// similar option.
         checkOnSave_overrideCommand: Option<Vec<String>> = "null",
+        /// Check commands for single workspaces or packages, like
+        /// `[{ "package": "my-crate", "command": ["bazel", "build", "//my-crate"], "parser": "rustc" }]`.
+        /// A command with a `workspace` root, or without `package`, replaces the check
+        /// command of the workspace. A command with a `package` checks that member,
+        /// which is excluded from the `cargo check` of its workspace. `env` and `cwd`
+        /// set the environment and directory of the command, `parser` selects `cargo`
+        /// JSON messages on stdout or `rustc` JSON diagnostics on stderr.
+        checkOnSave_commands: Vec<CheckCommandDef>       = "[]",
 
         /// Whether to add argument snippets when completing functions.
         /// Only applies when `#rust-analyzer.completion.addCallParenthesis#` is set.
@@ -115,6 +164,9 @@ config_data! {
         /// Toggles the additional completions that automatically show method calls and field accesses
         /// with `self` prefixed to them when inside a method.
         completion_autoself_enable: bool       = "true",
+        /// Whether to only show the values whose type matches the expected type
+        /// of the expression being completed, when it is known.
+        completion_restrictToExpectedType: bool = "false",
 
         /// Whether to show native rust-analyzer diagnostics.
         diagnostics_enable: bool                = "true",
@@ -126,6 +178,10 @@ config_data! {
         /// Map of prefixes to be substituted when parsing diagnostic file paths.
         /// This should be the reverse mapping of what is passed to `rustc` as `--remap-path-prefix`.
         diagnostics_remapPrefix: FxHashMap<String, String> = "{}",
+        /// Whether to report `pub` items that no crate of the workspace uses.
+        /// This searches all dependent crates for every item, which is slow
+        /// in large workspaces.
+        diagnostics_unusedPubItems_enable: bool = "false",
         /// List of warnings that should be displayed with hint severity.
         ///
         /// The warnings will be indicated by faded text or three dots in code
@@ -157,6 +213,8 @@ config_data! {
         /// Use markdown syntax for links in hover.
         hover_linksInHover |
         hoverActions_linksInHover: bool = "true",
+        /// Whether to show the size, alignment and field offsets of types on hover.
+        hover_memoryLayout: bool        = "true",
 
         /// Whether to show `Debug` action. Only applies when
         /// `#rust-analyzer.hoverActions.enable#` is set.
@@ -178,6 +236,9 @@ config_data! {
 
         /// Whether to show inlay type hints for method chains.
         inlayHints_chainingHints: bool      = "true",
+        /// Whether to show the values of constants and of array lengths given
+        /// by constants.
+        inlayHints_constValueHints: bool    = "false",
         /// Maximum length for inlay hints. Set to null to have an unlimited length.
         inlayHints_maxLength: Option<usize> = "25",
         /// Whether to show function parameter name inlay hints at the call
@@ -198,8 +259,8 @@ config_data! {
         lens_debug: bool            = "true",
         /// Whether to show CodeLens in Rust files.
         lens_enable: bool           = "true",
-        /// Whether to show `Implementations` lens. Only applies when
-        /// `#rust-analyzer.lens.enable#` is set.
+        /// Whether to show `Implementations` lens above types, traits and trait
+        /// methods. Only applies when `#rust-analyzer.lens.enable#` is set.
         lens_implementations: bool  = "true",
         /// Whether to show `Run` lens. Only applies when
         /// `#rust-analyzer.lens.enable#` is set.
@@ -207,8 +268,8 @@ config_data! {
         /// Whether to show `Method References` lens. Only applies when
         /// `#rust-analyzer.lens.enable#` is set.
         lens_methodReferences: bool = "false",
-        /// Whether to show `References` lens. Only applies when
-        /// `#rust-analyzer.lens.enable#` is set.
+        /// Whether to show `References` lens above types, traits, constants and
+        /// public items. Only applies when `#rust-analyzer.lens.enable#` is set.
         lens_references: bool = "false",
 
         /// Disable project auto-discovery in favor of explicitly specified set
@@ -240,7 +301,9 @@ config_data! {
         /// projects, or "discover" to try to automatically find it.
         ///
         /// Any project which uses rust-analyzer with the rustcPrivate
-        /// crates must set `[package.metadata.rust-analyzer] rustc_private=true` to use it.
+        /// crates must set `[package.metadata.rust-analyzer] rustc_private=true`
+        /// or enable `#![feature(rustc_private)]` to use it. Such projects use
+        /// the sources of the `rustc-dev` component when this is not set.
         ///
         /// This option is not reloaded automatically; you must restart rust-analyzer for it to take effect.
         rustcSource: Option<String> = "null",
@@ -259,6 +322,22 @@ config_data! {
         workspace_symbol_search_scope: WorskpaceSymbolSearchScopeDef = "\"workspace\"",
         /// Workspace symbol search kind.
         workspace_symbol_search_kind: WorskpaceSymbolSearchKindDef = "\"only_types\"",
+
+        /// Search the workspace folders recursively for Cargo workspaces,
+        /// `rust-project.json` and `rust-build-graph.json` files, instead of
+        /// only looking at the folders, their parents and direct children.
+        /// Ignored when `#rust-analyzer.linkedProjects#` is set.
+        workspace_discovery_recursive: bool       = "false",
+        /// Only load projects in directories matching one of these globs,
+        /// relative to the workspace folder, like `services/*`. All projects
+        /// are loaded if empty.
+        workspace_discovery_include: Vec<String>  = "[]",
+        /// Don't search directories matching one of these globs, relative to
+        /// the workspace folder, like `**/fixtures`.
+        workspace_discovery_exclude: Vec<String>  = "[]",
+        /// How many levels of directories below the workspace folder are
+        /// searched, unlimited if not set.
+        workspace_discovery_maxDepth: Option<usize> = "null",
     }
 }
 
@@ -370,6 +449,17 @@ pub struct NotificationsConfig {
     pub cargo_toml_not_found: bool,
 }
 
+/// A check command configured for a workspace or a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckCommandConfig {
+    /// The root of the workspace, any workspace if `None`.
+    pub workspace: Option<AbsPathBuf>,
+    /// The name of the package checked by the command, the whole workspace if
+    /// `None`.
+    pub package: Option<String>,
+    pub flycheck: FlycheckConfig,
+}
+
 #[derive(Debug, Clone)]
 pub enum RustfmtConfig {
     Rustfmt { extra_args: Vec<String>, enable_range_formatting: bool },
@@ -469,6 +559,19 @@ impl Config {
         &self.detached_files
     }
 
+    /// How to search the workspace folders for projects, `None` if only the
+    /// folders themselves are looked at.
+    pub fn workspace_discovery(&self) -> Option<DiscoveryConfig> {
+        if !self.data.workspace_discovery_recursive {
+            return None;
+        }
+        Some(DiscoveryConfig {
+            include: self.data.workspace_discovery_include.clone(),
+            exclude: self.data.workspace_discovery_exclude.clone(),
+            max_depth: self.data.workspace_discovery_maxDepth,
+        })
+    }
+
     pub fn did_save_text_document_dynamic_registration(&self) -> bool {
         let caps =
             try_or!(self.caps.text_document.as_ref()?.synchronization.clone()?, Default::default());
@@ -585,6 +688,7 @@ impl Config {
         DiagnosticsConfig {
             disable_experimental: !self.data.diagnostics_enableExperimental,
             disabled: self.data.diagnostics_disabled.clone(),
+            unused_pub_items: self.data.diagnostics_unusedPubItems_enable,
         }
     }
     pub fn diagnostics_map(&self) -> DiagnosticsMapConfig {
@@ -625,6 +729,12 @@ impl Config {
     pub fn cargo_autoreload(&self) -> bool {
         self.data.cargo_autoreload
     }
+    pub fn cargo_autoreload_delay(&self) -> Duration {
+        Duration::from_millis(self.data.cargo_autoreloadDelay as u64)
+    }
+    pub fn cargo_autoreload_scoped(&self) -> bool {
+        self.data.cargo_autoreloadScoped
+    }
     pub fn run_build_scripts(&self) -> bool {
         self.data.cargo_runBuildScripts || self.data.procMacro_enable
     }
@@ -644,10 +754,18 @@ impl Config {
             no_default_features: self.data.cargo_noDefaultFeatures,
             all_features: self.data.cargo_allFeatures,
             features: self.data.cargo_features.clone(),
+            package_features: self.data.cargo_packageFeatures.clone(),
             target: self.data.cargo_target.clone(),
+            extra_targets: self.data.cargo_extraTargets.clone(),
             rustc_source,
             no_sysroot: self.data.cargo_noSysroot,
+            sysroot: self.data.cargo_sysroot.as_ref().map(|it| self.root_path.join(it)),
+            sysroot_src: self.data.cargo_sysrootSrc.as_ref().map(|it| self.root_path.join(it)),
             unset_test_crates: self.data.cargo_unsetTest.clone(),
+            no_std_overrides: self.data.cargo_noStd.clone(),
+            extra_cfgs: self.data.cargo_extraCfgs.clone(),
+            extra_env: self.data.cargo_extraEnv.clone(),
+            offline: self.data.cargo_offline,
         }
     }
 
@@ -672,7 +790,13 @@ impl Config {
             Some(args) if !args.is_empty() => {
                 let mut args = args.clone();
                 let command = args.remove(0);
-                FlycheckConfig::CustomCommand { command, args }
+                FlycheckConfig::CustomCommand {
+                    command,
+                    args,
+                    env: Vec::new(),
+                    cwd: None,
+                    parser: DiagnosticsParser::Cargo,
+                }
             }
             Some(_) | None => FlycheckConfig::CargoCommand {
                 command: self.data.checkOnSave_command.clone(),
@@ -695,11 +819,47 @@ impl Config {
                     .checkOnSave_features
                     .clone()
                     .unwrap_or_else(|| self.data.cargo_features.clone()),
-                extra_args: self.data.checkOnSave_extraArgs.clone(),
+                extra_args: {
+                    let mut args = self.data.checkOnSave_extraArgs.clone();
+                    if self.data.cargo_offline {
+                        args.push("--frozen".to_string());
+                    }
+                    args
+                },
             },
         };
         Some(flycheck_config)
     }
+    pub fn check_commands(&self) -> Vec<CheckCommandConfig> {
+        if !self.data.checkOnSave_enable {
+            return Vec::new();
+        }
+        self.data
+            .checkOnSave_commands
+            .iter()
+            .filter_map(|def| {
+                let (command, args) = def.command.split_first()?;
+                let mut env =
+                    def.env.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<Vec<_>>();
+                env.sort();
+                let flycheck = FlycheckConfig::CustomCommand {
+                    command: command.clone(),
+                    args: args.to_vec(),
+                    env,
+                    cwd: def.cwd.as_ref().map(|it| self.root_path.join(it)),
+                    parser: match def.parser {
+                        Some(DiagnosticsParserDef::Rustc) => DiagnosticsParser::Rustc,
+                        Some(DiagnosticsParserDef::Cargo) | None => DiagnosticsParser::Cargo,
+                    },
+                };
+                Some(CheckCommandConfig {
+                    workspace: def.workspace.as_ref().map(|it| self.root_path.join(it)),
+                    package: def.package.clone(),
+                    flycheck,
+                })
+            })
+            .collect()
+    }
     pub fn runnables(&self) -> RunnablesConfig {
         RunnablesConfig {
             override_cargo: self.data.runnables_overrideCargo.clone(),
@@ -711,6 +871,7 @@ impl Config {
             type_hints: self.data.inlayHints_typeHints,
             parameter_hints: self.data.inlayHints_parameterHints,
             chaining_hints: self.data.inlayHints_chainingHints,
+            const_value_hints: self.data.inlayHints_constValueHints,
             max_length: self.data.inlayHints_maxLength,
         }
     }
@@ -738,6 +899,7 @@ impl Config {
             enable_imports_on_the_fly: self.data.completion_autoimport_enable
                 && completion_item_edit_resolve(&self.caps),
             enable_self_on_the_fly: self.data.completion_autoself_enable,
+            restrict_to_expected_type: self.data.completion_restrictToExpectedType,
             add_call_parenthesis: self.data.completion_addCallParenthesis,
             add_call_argument_snippets: self.data.completion_addCallArgumentSnippets,
             insert_use: self.insert_use_config(),
@@ -796,6 +958,7 @@ impl Config {
     pub fn hover(&self) -> HoverConfig {
         HoverConfig {
             links_in_hover: self.data.hover_linksInHover,
+            memory_layout: self.data.hover_memoryLayout,
             documentation: self.data.hover_documentation.then(|| {
                 let is_markdown = try_or!(
                     self.caps
@@ -861,6 +1024,28 @@ enum ManifestOrProjectJson {
     ProjectJson(ProjectJsonData),
 }
 
+#[derive(Deserialize, Debug, Clone)]
+struct CheckCommandDef {
+    #[serde(default)]
+    workspace: Option<PathBuf>,
+    #[serde(default)]
+    package: Option<String>,
+    command: Vec<String>,
+    #[serde(default)]
+    env: FxHashMap<String, String>,
+    #[serde(default)]
+    cwd: Option<PathBuf>,
+    #[serde(default)]
+    parser: Option<DiagnosticsParserDef>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+enum DiagnosticsParserDef {
+    Cargo,
+    Rustc,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 enum ImportGranularityDef {
@@ -1026,6 +1211,28 @@ fn field_props(field: &str, ty: &str, doc: &[&str], default: &str) -> serde_json
         "FxHashMap<String, String>" => set! {
             "type": "object",
         },
+        "FxHashMap<String, bool>" => set! {
+            "type": "object",
+            "additionalProperties": { "type": "boolean" },
+        },
+        "FxHashMap<String, Vec<String>>" => set! {
+            "type": "object",
+            "additionalProperties": {
+                "type": "array",
+                "items": { "type": "string" },
+            },
+        },
+        "FxHashMap<String, FxHashMap<String, String>>" => set! {
+            "type": "object",
+            "additionalProperties": {
+                "type": "object",
+                "additionalProperties": { "type": "string" },
+            },
+        },
+        "usize" => set! {
+            "type": "integer",
+            "minimum": 0,
+        },
         "Option<usize>" => set! {
             "type": ["null", "integer"],
             "minimum": 0,
@@ -1075,6 +1282,21 @@ fn field_props(field: &str, ty: &str, doc: &[&str], default: &str) -> serde_json
                 "Force import paths to be absolute by always starting them with `crate` or the extern crate name they come from."
             ],
         },
+        "Vec<CheckCommandDef>" => set! {
+            "type": "array",
+            "items": {
+                "type": "object",
+                "required": ["command"],
+                "properties": {
+                    "workspace": { "type": "string" },
+                    "package": { "type": "string" },
+                    "command": { "type": "array", "items": { "type": "string" } },
+                    "env": { "type": "object", "additionalProperties": { "type": "string" } },
+                    "cwd": { "type": "string" },
+                    "parser": { "type": "string", "enum": ["cargo", "rustc"] },
+                },
+            },
+        },
         "Vec<ManifestOrProjectJson>" => set! {
             "type": "array",
             "items": { "type": ["string", "object"] },
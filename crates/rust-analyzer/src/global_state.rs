@@ -9,10 +9,11 @@ use crossbeam_channel::{unbounded, Receiver, Sender};
 use flycheck::FlycheckHandle;
 use ide::{Analysis, AnalysisHost, Cancellable, Change, FileId};
 use ide_db::base_db::{CrateId, VfsPath};
-use lsp_types::{SemanticTokens, Url};
+use lsp_types::{CodeLens, SemanticTokens, Url};
 use parking_lot::{Mutex, RwLock};
 use project_model::{
-    BuildDataCollector, BuildDataResult, CargoWorkspace, ProcMacroClient, ProjectWorkspace, Target,
+    BuildDataCollector, BuildDataResult, CancellationToken, CargoWorkspace, CrateGraphWarning,
+    ProcMacroClient, ProjectWorkspace, RunnableTemplate, Target,
 };
 use rustc_hash::FxHashMap;
 use vfs::AnchoredPathBuf;
@@ -26,7 +27,7 @@ use crate::{
     lsp_ext,
     main_loop::Task,
     op_queue::OpQueue,
-    reload::SourceRootConfig,
+    reload::{ChangedManifests, SourceRootConfig},
     request_metrics::{LatestRequests, RequestMetrics},
     thread_pool::TaskPool,
     to_proto::url_from_abs_path,
@@ -59,6 +60,9 @@ pub(crate) struct GlobalState {
     pub(crate) diagnostics: DiagnosticCollection,
     pub(crate) mem_docs: FxHashMap<VfsPath, DocumentData>,
     pub(crate) semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
+    /// Resolved code lenses, by their serialized resolve data. Cleared on
+    /// every change, as any edit can change the counts.
+    pub(crate) code_lens_cache: Arc<Mutex<FxHashMap<String, CodeLens>>>,
     pub(crate) shutdown_requested: bool,
     pub(crate) last_reported_status: Option<lsp_ext::ServerStatusParams>,
     pub(crate) source_root_config: SourceRootConfig,
@@ -80,10 +84,19 @@ pub(crate) struct GlobalState {
     ///
     /// If the fetch (partially) fails, we do not update the values.
     pub(crate) workspaces: Arc<Vec<ProjectWorkspace>>,
-    pub(crate) fetch_workspaces_queue: OpQueue<(), Vec<anyhow::Result<ProjectWorkspace>>>,
+    pub(crate) fetch_workspaces_queue:
+        OpQueue<ChangedManifests, Vec<anyhow::Result<ProjectWorkspace>>>,
+    /// Automatic reloads wait until manifests stop changing for a while.
+    pub(crate) fetch_workspaces_deadline: Option<Instant>,
     pub(crate) workspace_build_data: Option<BuildDataResult>,
+    /// Problems found while building the crate graph of the `workspaces`.
+    pub(crate) crate_graph_warnings: Vec<CrateGraphWarning>,
+    /// The targets picked for files compiled for several targets.
+    pub(crate) active_targets: FxHashMap<FileId, String>,
     pub(crate) fetch_build_data_queue:
         OpQueue<BuildDataCollector, Option<anyhow::Result<BuildDataResult>>>,
+    /// Cancels the build data collection in progress, if any.
+    pub(crate) fetch_build_data_cancellation: CancellationToken,
     pub(crate) prime_caches_queue: OpQueue<(), ()>,
 
     latest_requests: Arc<RwLock<LatestRequests>>,
@@ -97,6 +110,7 @@ pub(crate) struct GlobalStateSnapshot {
     pub(crate) latest_requests: Arc<RwLock<LatestRequests>>,
     mem_docs: FxHashMap<VfsPath, DocumentData>,
     pub(crate) semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
+    pub(crate) code_lens_cache: Arc<Mutex<FxHashMap<String, CodeLens>>>,
     vfs: Arc<RwLock<(vfs::Vfs, FxHashMap<FileId, LineEndings>)>>,
     pub(crate) workspaces: Arc<Vec<ProjectWorkspace>>,
 }
@@ -129,6 +143,7 @@ impl GlobalState {
             diagnostics: Default::default(),
             mem_docs: FxHashMap::default(),
             semantic_tokens_cache: Arc::new(Default::default()),
+            code_lens_cache: Arc::new(Default::default()),
             shutdown_requested: false,
             last_reported_status: None,
             source_root_config: SourceRootConfig::default(),
@@ -146,10 +161,14 @@ impl GlobalState {
 
             workspaces: Arc::new(Vec::new()),
             fetch_workspaces_queue: OpQueue::default(),
+            fetch_workspaces_deadline: None,
             workspace_build_data: None,
+            crate_graph_warnings: Vec::new(),
+            active_targets: FxHashMap::default(),
             prime_caches_queue: OpQueue::default(),
 
             fetch_build_data_queue: OpQueue::default(),
+            fetch_build_data_cancellation: CancellationToken::default(),
             latest_requests: Default::default(),
         };
         // Apply any required database inputs from the config.
@@ -169,6 +188,7 @@ impl GlobalState {
             if changed_files.is_empty() {
                 return false;
             }
+            self.code_lens_cache.lock().clear();
 
             for file in changed_files {
                 if file.is_created_or_deleted() {
@@ -215,6 +235,7 @@ impl GlobalState {
             check_fixes: Arc::clone(&self.diagnostics.check_fixes),
             mem_docs: self.mem_docs.clone(),
             semantic_tokens_cache: Arc::clone(&self.semantic_tokens_cache),
+            code_lens_cache: Arc::clone(&self.code_lens_cache),
         }
     }
 
@@ -270,7 +291,8 @@ impl GlobalState {
 
 impl Drop for GlobalState {
     fn drop(&mut self) {
-        self.analysis_host.request_cancellation()
+        self.analysis_host.request_cancellation();
+        self.fetch_build_data_cancellation.cancel();
     }
 }
 
@@ -315,9 +337,26 @@ impl GlobalStateSnapshot {
                 cargo.target_by_root(path).map(|it| (cargo, it))
             }
             ProjectWorkspace::Json { .. } => None,
+            ProjectWorkspace::BuildGraph { .. } => None,
             ProjectWorkspace::DetachedFiles { .. } => None,
         })
     }
+
+    /// Returns the runnables a `rust-project.json` declares for a crate.
+    pub(crate) fn runnable_templates_for_crate_root(
+        &self,
+        crate_id: CrateId,
+    ) -> Option<&[RunnableTemplate]> {
+        let file_id = self.analysis.crate_root(crate_id).ok()?;
+        let path = self.vfs.read().0.file_path(file_id);
+        let path = path.as_path()?;
+        self.workspaces.iter().find_map(|ws| match ws {
+            ProjectWorkspace::Json { project, .. } => {
+                project.crate_by_root(path).map(|krate| krate.runnables())
+            }
+            _ => None,
+        })
+    }
 }
 
 pub(crate) fn file_id_to_url(vfs: &vfs::Vfs, id: FileId) -> Url {
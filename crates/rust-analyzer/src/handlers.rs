@@ -8,7 +8,7 @@ use std::{
 };
 
 use ide::{
-    AnnotationConfig, AssistKind, AssistResolveStrategy, FileId, FilePosition, FileRange,
+    AnnotationConfig, AssistKind, AssistResolveStrategy, Change, FileId, FilePosition, FileRange,
     HoverAction, HoverGotoTypeData, Query, RangeInfo, Runnable, RunnableKind, SingleResolve,
     SourceChange, TextEdit,
 };
@@ -24,7 +24,7 @@ use lsp_types::{
     SemanticTokensParams, SemanticTokensRangeParams, SemanticTokensRangeResult,
     SemanticTokensResult, SymbolInformation, SymbolTag, TextDocumentIdentifier, Url, WorkspaceEdit,
 };
-use project_model::TargetKind;
+use project_model::{RunnableTemplateKind, TargetKind};
 use serde_json::json;
 use stdx::{format_to, never};
 use syntax::{algo, ast, AstNode, TextRange, TextSize};
@@ -87,12 +87,21 @@ pub(crate) fn handle_analyzer_status(
 
 pub(crate) fn handle_memory_usage(state: &mut GlobalState, _: ()) -> Result<String> {
     let _p = profile::span("handle_memory_usage");
-    let mut mem = state.analysis_host.per_query_memory_usage();
-    mem.push(("Remaining".into(), profile::memory_usage().allocated));
+    let report = state.analysis_host.per_query_memory_usage(20);
 
     let mut out = String::new();
-    for (name, bytes) in mem {
-        format_to!(out, "{:>8} {}\n", bytes, name);
+    format_to!(out, "Query groups:\n");
+    for (group, bytes) in report.per_group() {
+        format_to!(out, "{:>8} {}\n", bytes, group);
+    }
+    format_to!(out, "{:>8} Remaining\n", profile::memory_usage().allocated);
+    format_to!(out, "\nQueries:\n");
+    for query in &report.queries {
+        format_to!(out, "{:>8} {}\n", query.bytes, query.query);
+    }
+    format_to!(out, "\nLargest query values:\n");
+    for key in &report.largest_keys {
+        format_to!(out, "{:>8} {} {}\n", key.bytes, key.query, key.key);
     }
     Ok(out)
 }
@@ -159,8 +168,36 @@ pub(crate) fn handle_expand_macro(
     let line_index = snap.file_line_index(file_id)?;
     let offset = from_proto::offset(&line_index, params.position);
 
-    let res = snap.analysis.expand_macro(FilePosition { file_id, offset })?;
-    Ok(res.map(|it| lsp_ext::ExpandedMacro { name: it.name, expansion: it.expansion }))
+    let position = FilePosition { file_id, offset };
+
+    let res = match snap.analysis.expand_macro(position)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let steps = if params.steps {
+        let steps = snap.analysis.expand_macro_steps(position)?.unwrap_or_default();
+        let steps = steps
+            .into_iter()
+            .map(|step| {
+                let calls = step
+                    .calls
+                    .into_iter()
+                    .map(|call| {
+                        Ok(lsp_ext::ExpandedCall {
+                            name: call.name,
+                            location: to_proto::location(&snap, call.range)?,
+                            def_site_idents: call.def_site_idents,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(lsp_ext::ExpansionStep { expansion: step.expansion, calls })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Some(steps)
+    } else {
+        None
+    };
+    Ok(Some(lsp_ext::ExpandedMacro { name: res.name, expansion: res.expansion, steps }))
 }
 
 pub(crate) fn handle_selection_range(
@@ -579,6 +616,19 @@ pub(crate) fn handle_goto_declaration(
     Ok(Some(res))
 }
 
+pub(crate) fn handle_moniker(
+    snap: GlobalStateSnapshot,
+    params: lsp_types::MonikerParams,
+) -> Result<Option<Vec<lsp_types::Moniker>>> {
+    let _p = profile::span("handle_moniker");
+    let position = from_proto::file_position(&snap, params.text_document_position_params)?;
+    let monikers = match snap.analysis.moniker(position)? {
+        None => return Ok(None),
+        Some(it) => it.info,
+    };
+    Ok(Some(monikers.into_iter().map(to_proto::moniker).collect()))
+}
+
 pub(crate) fn handle_goto_implementation(
     snap: GlobalStateSnapshot,
     params: lsp_types::request::GotoImplementationParams,
@@ -684,7 +734,25 @@ pub(crate) fn handle_runnables(
             }
         }
         None => {
-            if !snap.config.linked_projects().is_empty()
+            let templates = snap
+                .analysis
+                .crate_for(file_id)?
+                .first()
+                .and_then(|&crate_id| snap.runnable_templates_for_crate_root(crate_id));
+            if let Some(templates) = templates {
+                for template in templates {
+                    if template.kind == RunnableTemplateKind::Run {
+                        continue;
+                    }
+                    let args = template.args(None);
+                    res.push(to_proto::templated_runnable(
+                        format!("{} {}", template.program, args.join(" ")),
+                        None,
+                        template,
+                        None,
+                    ));
+                }
+            } else if !snap.config.linked_projects().is_empty()
                 || !snap
                     .config
                     .discovered_projects
@@ -945,6 +1013,8 @@ pub(crate) fn handle_references(
     } else {
         None
     };
+    // `refs.inactive_references` are only name matches, which plain locations
+    // can't tell apart from real references.
     let locations = refs
         .references
         .into_iter()
@@ -1155,6 +1225,13 @@ pub(crate) fn handle_code_lens(
     for a in annotations {
         to_proto::code_lens(&mut res, &snap, a)?;
     }
+    // Lenses resolved since the last change are sent resolved right away.
+    let cache = snap.code_lens_cache.lock();
+    for lens in res.iter_mut().filter(|it| it.command.is_none()) {
+        if let Some(resolved) = lens.data.as_ref().and_then(|it| cache.get(&it.to_string())) {
+            *lens = resolved.clone();
+        }
+    }
 
     Ok(Some(res))
 }
@@ -1163,6 +1240,12 @@ pub(crate) fn handle_code_lens_resolve(
     snap: GlobalStateSnapshot,
     code_lens: CodeLens,
 ) -> Result<CodeLens> {
+    let cache_key = code_lens.data.as_ref().map(|it| it.to_string());
+    if let Some(resolved) =
+        cache_key.as_ref().and_then(|it| snap.code_lens_cache.lock().get(it).cloned())
+    {
+        return Ok(resolved);
+    }
     let annotation = from_proto::annotation(&snap, code_lens.clone())?;
     let annotation = snap.analysis.resolve_annotation(annotation)?;
 
@@ -1176,6 +1259,9 @@ pub(crate) fn handle_code_lens_resolve(
             code_lens
         }
     };
+    if let Some(cache_key) = cache_key {
+        snap.code_lens_cache.lock().insert(cache_key, res.clone());
+    }
 
     Ok(res)
 }
@@ -1361,6 +1447,76 @@ pub(crate) fn handle_call_hierarchy_outgoing(
     Ok(Some(res))
 }
 
+pub(crate) fn handle_type_hierarchy_prepare(
+    snap: GlobalStateSnapshot,
+    params: lsp_types::TextDocumentPositionParams,
+) -> Result<Option<Vec<lsp_ext::TypeHierarchyItem>>> {
+    let _p = profile::span("handle_type_hierarchy_prepare");
+    let position = from_proto::file_position(&snap, params)?;
+
+    let nav_info = match snap.analysis.type_hierarchy(position)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+
+    let RangeInfo { range: _, info: navs } = nav_info;
+    let res = navs
+        .into_iter()
+        .filter(|it| {
+            matches!(
+                it.kind,
+                Some(SymbolKind::Struct | SymbolKind::Enum | SymbolKind::Union | SymbolKind::Trait)
+            )
+        })
+        .map(|it| to_proto::type_hierarchy_item(&snap, it))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Some(res))
+}
+
+pub(crate) fn handle_type_hierarchy_supertypes(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::TypeHierarchyParams,
+) -> Result<Option<Vec<lsp_ext::TypeHierarchyItem>>> {
+    let _p = profile::span("handle_type_hierarchy_supertypes");
+    let fpos = type_hierarchy_item_position(&snap, params.item)?;
+    let navs = match snap.analysis.supertypes(fpos)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let res = navs
+        .into_iter()
+        .map(|it| to_proto::type_hierarchy_item(&snap, it))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(res))
+}
+
+pub(crate) fn handle_type_hierarchy_subtypes(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::TypeHierarchyParams,
+) -> Result<Option<Vec<lsp_ext::TypeHierarchyItem>>> {
+    let _p = profile::span("handle_type_hierarchy_subtypes");
+    let fpos = type_hierarchy_item_position(&snap, params.item)?;
+    let navs = match snap.analysis.subtypes(fpos)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let res = navs
+        .into_iter()
+        .map(|it| to_proto::type_hierarchy_item(&snap, it))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(res))
+}
+
+fn type_hierarchy_item_position(
+    snap: &GlobalStateSnapshot,
+    item: lsp_ext::TypeHierarchyItem,
+) -> Result<FilePosition> {
+    let doc = TextDocumentIdentifier::new(item.uri);
+    let frange = from_proto::file_range(snap, doc, item.selection_range)?;
+    Ok(FilePosition { file_id: frange.file_id, offset: frange.range.start() })
+}
+
 pub(crate) fn handle_semantic_tokens_full(
     snap: GlobalStateSnapshot,
     params: SemanticTokensParams,
@@ -1442,6 +1598,52 @@ pub(crate) fn handle_open_docs(
     Ok(remote.and_then(|remote| Url::parse(&remote).ok()))
 }
 
+pub(crate) fn handle_file_targets(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::FileTargetsParams,
+) -> Result<lsp_ext::FileTargetsResult> {
+    let _p = profile::span("handle_file_targets");
+    let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
+    let targets = snap.analysis.file_targets(file_id)?;
+    let active = snap.analysis.active_target(file_id)?;
+    Ok(lsp_ext::FileTargetsResult { targets, active })
+}
+
+pub(crate) fn handle_set_active_target(
+    state: &mut GlobalState,
+    params: lsp_ext::SetActiveTargetParams,
+) -> Result<()> {
+    let _p = profile::span("handle_set_active_target");
+    let file_id = from_proto::file_id(&state.snapshot(), &params.text_document.uri)?;
+    match params.target {
+        Some(target) => state.active_targets.insert(file_id, target),
+        None => state.active_targets.remove(&file_id),
+    };
+    let mut change = Change::new();
+    change.set_active_targets(state.active_targets.clone());
+    state.analysis_host.apply_change(change);
+    Ok(())
+}
+
+pub(crate) fn handle_cfg_feature_usages(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::CfgFeatureUsagesParams,
+) -> Result<Vec<lsp_ext::CrateCfgFeatureUsages>> {
+    let _p = profile::span("handle_cfg_feature_usages");
+    let usages = snap.analysis.cfg_feature_usages(&params.feature)?;
+    usages
+        .into_iter()
+        .map(|krate| {
+            let locations = krate
+                .usages
+                .into_iter()
+                .map(|usage| to_proto::location(&snap, usage.range))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(lsp_ext::CrateCfgFeatureUsages { crate_name: krate.crate_name, locations })
+        })
+        .collect()
+}
+
 pub(crate) fn handle_open_cargo_toml(
     snap: GlobalStateSnapshot,
     params: lsp_ext::OpenCargoTomlParams,
@@ -104,6 +104,9 @@ impl Request for ExpandMacro {
 pub struct ExpandMacroParams {
     pub text_document: TextDocumentIdentifier,
     pub position: Position,
+    /// Also expand one level of nested macro calls at a time.
+    #[serde(default)]
+    pub steps: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -111,6 +114,23 @@ pub struct ExpandMacroParams {
 pub struct ExpandedMacro {
     pub name: String,
     pub expansion: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<ExpansionStep>>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpansionStep {
+    pub expansion: String,
+    pub calls: Vec<ExpandedCall>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandedCall {
+    pub name: String,
+    pub location: lsp_types::Location,
+    pub def_site_idents: Vec<String>,
 }
 
 pub enum MatchingBrace {}
@@ -239,6 +259,7 @@ pub enum InlayKind {
     TypeHint,
     ParameterHint,
     ChainingHint,
+    ConstValueHint,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -427,6 +448,63 @@ pub struct OpenCargoTomlParams {
     pub text_document: TextDocumentIdentifier,
 }
 
+pub enum FileTargets {}
+
+impl Request for FileTargets {
+    type Params = FileTargetsParams;
+    type Result = FileTargetsResult;
+    const METHOD: &'static str = "rust-analyzer/fileTargets";
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTargetsParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTargetsResult {
+    pub targets: Vec<String>,
+    pub active: Option<String>,
+}
+
+pub enum SetActiveTarget {}
+
+impl Request for SetActiveTarget {
+    type Params = SetActiveTargetParams;
+    type Result = ();
+    const METHOD: &'static str = "rust-analyzer/setActiveTarget";
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SetActiveTargetParams {
+    pub text_document: TextDocumentIdentifier,
+    pub target: Option<String>,
+}
+
+pub enum CfgFeatureUsages {}
+
+impl Request for CfgFeatureUsages {
+    type Params = CfgFeatureUsagesParams;
+    type Result = Vec<CrateCfgFeatureUsages>;
+    const METHOD: &'static str = "rust-analyzer/cfgFeatureUsages";
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CfgFeatureUsagesParams {
+    pub feature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CrateCfgFeatureUsages {
+    pub crate_name: Option<String>,
+    pub locations: Vec<lsp_types::Location>,
+}
+
 /// Information about CodeLens, that is to be resolved.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -461,6 +539,52 @@ pub enum MoveItemDirection {
     Down,
 }
 
+pub enum TypeHierarchyPrepare {}
+
+impl Request for TypeHierarchyPrepare {
+    type Params = lsp_types::TextDocumentPositionParams;
+    type Result = Option<Vec<TypeHierarchyItem>>;
+    const METHOD: &'static str = "textDocument/prepareTypeHierarchy";
+}
+
+pub enum TypeHierarchySupertypes {}
+
+impl Request for TypeHierarchySupertypes {
+    type Params = TypeHierarchyParams;
+    type Result = Option<Vec<TypeHierarchyItem>>;
+    const METHOD: &'static str = "typeHierarchy/supertypes";
+}
+
+pub enum TypeHierarchySubtypes {}
+
+impl Request for TypeHierarchySubtypes {
+    type Params = TypeHierarchyParams;
+    type Result = Option<Vec<TypeHierarchyItem>>;
+    const METHOD: &'static str = "typeHierarchy/subtypes";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeHierarchyParams {
+    pub item: TypeHierarchyItem,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeHierarchyItem {
+    pub name: String,
+    pub kind: lsp_types::SymbolKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<lsp_types::SymbolTag>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub uri: lsp_types::Url,
+    pub range: Range,
+    pub selection_range: Range,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
 #[derive(Debug)]
 pub enum WorkspaceSymbol {}
 
@@ -57,6 +57,8 @@ enum Event {
     Task(Task),
     Vfs(vfs::loader::Message),
     Flycheck(flycheck::Message),
+    /// The delay before an automatic workspace reload has passed.
+    ReloadDeadline,
 }
 
 #[derive(Debug)]
@@ -96,6 +98,7 @@ impl fmt::Debug for Event {
             Event::Task(it) => fmt::Debug::fmt(it, f),
             Event::Vfs(it) => fmt::Debug::fmt(it, f),
             Event::Flycheck(it) => fmt::Debug::fmt(it, f),
+            Event::ReloadDeadline => f.write_str("ReloadDeadline"),
         }
     }
 }
@@ -163,6 +166,15 @@ impl GlobalState {
     }
 
     fn next_event(&self, inbox: &Receiver<lsp_server::Message>) -> Option<Event> {
+        let reload_deadline = match self.fetch_workspaces_deadline {
+            Some(deadline)
+                if self.config.cargo_autoreload()
+                    && !self.fetch_workspaces_queue.op_in_progress() =>
+            {
+                crossbeam_channel::at(deadline)
+            }
+            _ => crossbeam_channel::never(),
+        };
         select! {
             recv(inbox) -> msg =>
                 msg.ok().map(Event::Lsp),
@@ -175,6 +187,9 @@ impl GlobalState {
 
             recv(self.flycheck_receiver) -> task =>
                 Some(Event::Flycheck(task.unwrap())),
+
+            recv(reload_deadline) -> _ =>
+                Some(Event::ReloadDeadline),
         }
     }
 
@@ -346,6 +361,8 @@ impl GlobalState {
                     }
                 }
             }
+            // The reload itself is started at the end of the loop turn.
+            Event::ReloadDeadline => (),
             Event::Flycheck(mut task) => {
                 let _p = profile::span("GlobalState::handle_event/flycheck");
                 loop {
@@ -420,18 +437,7 @@ impl GlobalState {
         }
 
         if self.is_quiescent() && (!was_quiescent || state_changed) {
-            self.update_file_notifications_on_threadpool();
-
-            // Refresh semantic tokens if the client supports it.
-            if self.config.semantic_tokens_refresh() {
-                self.semantic_tokens_cache.lock().clear();
-                self.send_request::<lsp_types::request::SemanticTokensRefesh>((), |_, _| ());
-            }
-
-            // Refresh code lens if the client supports it.
-            if self.config.code_lens_refresh() {
-                self.send_request::<lsp_types::request::CodeLensRefresh>((), |_, _| ());
-            }
+            self.refresh_open_files();
         }
 
         if let Some(diagnostic_changes) = self.diagnostics.take_changes() {
@@ -449,7 +455,7 @@ impl GlobalState {
         }
 
         if self.config.cargo_autoreload() {
-            self.fetch_workspaces_if_needed();
+            self.fetch_workspaces_if_due();
         }
         self.fetch_build_data_if_needed();
 
@@ -511,6 +517,11 @@ impl GlobalState {
                 handlers::handle_matching_brace(s.snapshot(), p)
             })?
             .on_sync::<lsp_ext::MemoryUsage>(|s, p| handlers::handle_memory_usage(s, p))?
+            .on_sync::<lsp_ext::SetActiveTarget>(|s, p| {
+                handlers::handle_set_active_target(s, p)?;
+                s.refresh_open_files();
+                Ok(())
+            })?
             .on::<lsp_ext::AnalyzerStatus>(handlers::handle_analyzer_status)
             .on::<lsp_ext::SyntaxTree>(handlers::handle_syntax_tree)
             .on::<lsp_ext::ViewHir>(handlers::handle_view_hir)
@@ -526,6 +537,8 @@ impl GlobalState {
             .on::<lsp_ext::HoverRequest>(handlers::handle_hover)
             .on::<lsp_ext::ExternalDocs>(handlers::handle_open_docs)
             .on::<lsp_ext::OpenCargoToml>(handlers::handle_open_cargo_toml)
+            .on::<lsp_ext::FileTargets>(handlers::handle_file_targets)
+            .on::<lsp_ext::CfgFeatureUsages>(handlers::handle_cfg_feature_usages)
             .on::<lsp_ext::MoveItem>(handlers::handle_move_item)
             .on::<lsp_ext::WorkspaceSymbol>(handlers::handle_workspace_symbol)
             .on::<lsp_types::request::OnTypeFormatting>(handlers::handle_on_type_formatting)
@@ -533,6 +546,7 @@ impl GlobalState {
             .on::<lsp_types::request::GotoDefinition>(handlers::handle_goto_definition)
             .on::<lsp_types::request::GotoDeclaration>(handlers::handle_goto_declaration)
             .on::<lsp_types::request::GotoImplementation>(handlers::handle_goto_implementation)
+            .on::<lsp_types::request::MonikerRequest>(handlers::handle_moniker)
             .on::<lsp_types::request::GotoTypeDefinition>(handlers::handle_goto_type_definition)
             .on::<lsp_types::request::Completion>(handlers::handle_completion)
             .on::<lsp_types::request::ResolveCompletionItem>(handlers::handle_completion_resolve)
@@ -553,6 +567,9 @@ impl GlobalState {
             .on::<lsp_types::request::CallHierarchyOutgoingCalls>(
                 handlers::handle_call_hierarchy_outgoing,
             )
+            .on::<lsp_ext::TypeHierarchyPrepare>(handlers::handle_type_hierarchy_prepare)
+            .on::<lsp_ext::TypeHierarchySupertypes>(handlers::handle_type_hierarchy_supertypes)
+            .on::<lsp_ext::TypeHierarchySubtypes>(handlers::handle_type_hierarchy_subtypes)
             .on::<lsp_types::request::SemanticTokensFullRequest>(
                 handlers::handle_semantic_tokens_full,
             )
@@ -715,6 +732,24 @@ impl GlobalState {
             .finish();
         Ok(())
     }
+
+    /// Recomputes everything shown for the open files.
+    fn refresh_open_files(&mut self) {
+        self.update_file_notifications_on_threadpool();
+
+        // Refresh semantic tokens if the client supports it. The cached tokens
+        // are kept: they are what the client has, so they are what the
+        // following delta requests are computed against.
+        if self.config.semantic_tokens_refresh() {
+            self.send_request::<lsp_types::request::SemanticTokensRefesh>((), |_, _| ());
+        }
+
+        // Refresh code lens if the client supports it.
+        if self.config.code_lens_refresh() {
+            self.send_request::<lsp_types::request::CodeLensRefresh>((), |_, _| ());
+        }
+    }
+
     fn update_file_notifications_on_threadpool(&mut self) {
         self.maybe_update_diagnostics();
 
@@ -730,7 +765,7 @@ impl GlobalState {
                 let cb = |progress| {
                     sender.send(Task::PrimeCaches(progress)).unwrap();
                 };
-                match snap.analysis.prime_caches(cb) {
+                match snap.analysis.prime_caches(num_cpus::get(), cb) {
                     Ok(()) => (),
                     Err(_canceled) => (),
                 }
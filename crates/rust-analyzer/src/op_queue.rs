@@ -17,6 +17,14 @@ impl<Args, Output> OpQueue<Args, Output> {
     pub(crate) fn request_op(&mut self, data: Args) {
         self.op_requested = Some(data);
     }
+    /// Like `request_op`, but combines `data` with the arguments of an
+    /// operation which was requested but not started yet.
+    pub(crate) fn merge_op(&mut self, data: Args, merge: impl FnOnce(Args, Args) -> Args) {
+        self.op_requested = Some(match self.op_requested.take() {
+            Some(pending) => merge(pending, data),
+            None => data,
+        });
+    }
     pub(crate) fn should_start_op(&mut self) -> Option<Args> {
         if self.op_in_progress {
             return None;
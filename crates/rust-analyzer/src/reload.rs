@@ -1,15 +1,19 @@
 //! Project loading & configuration updates
-use std::{mem, sync::Arc};
+use std::{iter, mem, sync::Arc, time::Instant};
 
 use flycheck::{FlycheckConfig, FlycheckHandle};
 use hir::db::DefDatabase;
 use ide::Change;
 use ide_db::base_db::{CrateGraph, SourceRoot, VfsPath};
-use project_model::{BuildDataCollector, BuildDataResult, ProcMacroClient, ProjectWorkspace};
+use project_model::{
+    BuildDataCollector, BuildDataResult, CancellationToken, CargoWorkspace, PackageRoot,
+    ProcMacroClient, ProgressEvent, ProjectManifest, ProjectWorkspace,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
 use vfs::{file_set::FileSetConfig, AbsPath, AbsPathBuf, ChangeKind};
 
 use crate::{
-    config::{Config, FilesWatcher, LinkedProject},
+    config::{CheckCommandConfig, Config, FilesWatcher, LinkedProject},
     global_state::GlobalState,
     lsp_ext,
     main_loop::Task,
@@ -22,6 +26,10 @@ pub(crate) enum ProjectWorkspaceProgress {
     End(Vec<anyhow::Result<ProjectWorkspace>>),
 }
 
+/// The manifests whose changes requested a workspace fetch, `None` if all
+/// workspaces are to be reloaded.
+pub(crate) type ChangedManifests = Option<Vec<AbsPathBuf>>;
+
 #[derive(Debug)]
 pub(crate) enum BuildDataProgress {
     Begin,
@@ -45,7 +53,9 @@ impl GlobalState {
         }
         if self.config.linked_projects() != old_config.linked_projects() {
             self.fetch_workspaces_request()
-        } else if self.config.flycheck() != old_config.flycheck() {
+        } else if self.config.flycheck() != old_config.flycheck()
+            || self.config.check_commands() != old_config.check_commands()
+        {
             self.reload_flycheck();
         }
 
@@ -68,7 +78,24 @@ impl GlobalState {
                 ", "
             )
         );
-        self.fetch_workspaces_request();
+        if self.config.cargo_autoreload_scoped() {
+            let changed = changes
+                .iter()
+                .filter(|(path, kind)| is_interesting(path, *kind))
+                .map(|(path, _)| path.clone())
+                .collect();
+            self.fetch_workspaces_queue.merge_op(Some(changed), |pending, changed| {
+                let mut pending = pending?;
+                pending.extend(changed?);
+                Some(pending)
+            });
+        } else {
+            self.fetch_workspaces_request();
+        }
+        if self.config.cargo_autoreload() {
+            self.fetch_workspaces_deadline =
+                Some(Instant::now() + self.config.cargo_autoreload_delay());
+        }
 
         fn is_interesting(path: &AbsPath, change_kind: ChangeKind) -> bool {
             const IMPLICIT_TARGET_FILES: &[&str] = &["build.rs", "src/main.rs", "src/lib.rs"];
@@ -113,6 +140,10 @@ impl GlobalState {
             message: None,
         };
 
+        if let Some(warning) = self.crate_graph_warning() {
+            status.health = lsp_ext::Health::Warning;
+            status.message = Some(warning)
+        }
         if let Some(error) = self.build_data_error() {
             status.health = lsp_ext::Health::Warning;
             status.message = Some(error)
@@ -144,13 +175,29 @@ impl GlobalState {
     }
 
     pub(crate) fn fetch_workspaces_request(&mut self) {
-        self.fetch_workspaces_queue.request_op(())
+        self.fetch_workspaces_queue.request_op(None)
     }
-    pub(crate) fn fetch_workspaces_if_needed(&mut self) {
-        if self.fetch_workspaces_queue.should_start_op().is_none() {
-            return;
+    /// Starts a requested fetch once the manifests stopped changing.
+    pub(crate) fn fetch_workspaces_if_due(&mut self) {
+        match self.fetch_workspaces_deadline {
+            Some(deadline) if Instant::now() < deadline => (),
+            _ => self.fetch_workspaces_if_needed(),
         }
-        log::info!("will fetch workspaces");
+    }
+    pub(crate) fn fetch_workspaces_if_needed(&mut self) {
+        let changed = match self.fetch_workspaces_queue.should_start_op() {
+            Some(it) => it,
+            None => return,
+        };
+        self.fetch_workspaces_deadline = None;
+        // The build data of the old workspaces is of no use anymore, and
+        // `cargo check` would keep the target directory locked.
+        self.fetch_build_data_cancellation.cancel();
+        let reused = match changed {
+            Some(changed) => self.unaffected_workspaces(&changed),
+            None => Vec::new(),
+        };
+        log::info!("will fetch workspaces, reusing {} of them", reused.len());
 
         self.task_pool.handle.spawn_with_sender({
             let linked_projects = self.config.linked_projects();
@@ -160,7 +207,8 @@ impl GlobalState {
             move |sender| {
                 let progress = {
                     let sender = sender.clone();
-                    move |msg| {
+                    move |event: ProgressEvent| {
+                        let msg = event.to_string();
                         sender
                             .send(Task::FetchWorkspace(ProjectWorkspaceProgress::Report(msg)))
                             .unwrap()
@@ -169,28 +217,50 @@ impl GlobalState {
 
                 sender.send(Task::FetchWorkspace(ProjectWorkspaceProgress::Begin)).unwrap();
 
-                let mut workspaces = linked_projects
-                    .iter()
-                    .map(|project| match project {
+                // Discovered projects may include members of Cargo workspaces
+                // which were already loaded as part of their workspace.
+                let mut loaded_manifests = FxHashSet::default();
+                let mut workspaces = Vec::new();
+                for project in linked_projects.iter() {
+                    let workspace = match project {
                         LinkedProject::ProjectManifest(manifest) => {
-                            project_model::ProjectWorkspace::load(
-                                manifest.clone(),
-                                &cargo_config,
-                                &progress,
-                            )
+                            let mut unchanged = None;
+                            if let ProjectManifest::CargoToml(path) = manifest {
+                                if loaded_manifests.contains(path) {
+                                    continue;
+                                }
+                                unchanged = reused.iter().find(|ws| match ws {
+                                    ProjectWorkspace::Cargo { cargo, .. } => {
+                                        cargo_manifests(cargo).any(|it| it == *path)
+                                    }
+                                    _ => false,
+                                });
+                            }
+                            match unchanged {
+                                Some(ws) => Ok(ws.clone()),
+                                None => project_model::ProjectWorkspace::load(
+                                    manifest.clone(),
+                                    &cargo_config,
+                                    &progress,
+                                ),
+                            }
                         }
                         LinkedProject::InlineJsonProject(it) => {
-                            project_model::ProjectWorkspace::load_inline(
-                                it.clone(),
-                                cargo_config.target.as_deref(),
-                            )
+                            project_model::ProjectWorkspace::load_inline(it.clone(), &cargo_config)
                         }
-                    })
-                    .collect::<Vec<_>>();
+                    };
+                    if let Ok(ProjectWorkspace::Cargo { cargo, .. }) = &workspace {
+                        loaded_manifests.extend(cargo_manifests(cargo));
+                    }
+                    workspaces.push(workspace);
+                }
 
                 if !detached_files.is_empty() {
-                    workspaces
-                        .push(project_model::ProjectWorkspace::load_detached_files(detached_files));
+                    workspaces.extend(project_model::ProjectWorkspace::load_detached_files(
+                        detached_files,
+                        &cargo_config,
+                        &progress,
+                    ));
                 }
 
                 log::info!("did fetch workspaces {:?}", workspaces);
@@ -200,6 +270,40 @@ impl GlobalState {
             }
         });
     }
+    /// The loaded Cargo workspaces containing none of the `changed`
+    /// manifests. Nothing is reused if a manifest belongs to none of them,
+    /// as it might be a new project.
+    fn unaffected_workspaces(&self, changed: &[AbsPathBuf]) -> Vec<ProjectWorkspace> {
+        let cargo_workspaces = self
+            .fetch_workspaces_queue
+            .last_op_result()
+            .iter()
+            .filter_map(|res| res.as_ref().ok())
+            .filter_map(|ws| match ws {
+                ProjectWorkspace::Cargo { cargo, .. } => Some((ws, cargo)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut affected = vec![false; cargo_workspaces.len()];
+        for path in changed {
+            let mut found = false;
+            for ((_, cargo), affected) in cargo_workspaces.iter().zip(affected.iter_mut()) {
+                if contains_path(cargo, path) {
+                    *affected = true;
+                    found = true;
+                }
+            }
+            if !found {
+                return Vec::new();
+            }
+        }
+        cargo_workspaces
+            .into_iter()
+            .zip(affected)
+            .filter(|(_, affected)| !affected)
+            .map(|((ws, _), _)| ws.clone())
+            .collect()
+    }
     pub(crate) fn fetch_workspaces_completed(
         &mut self,
         workspaces: Vec<anyhow::Result<ProjectWorkspace>>,
@@ -208,6 +312,8 @@ impl GlobalState {
     }
 
     pub(crate) fn fetch_build_data_request(&mut self, build_data_collector: BuildDataCollector) {
+        // Superseded by the new request.
+        self.fetch_build_data_cancellation.cancel();
         self.fetch_build_data_queue.request_op(build_data_collector);
     }
     pub(crate) fn fetch_build_data_if_needed(&mut self) {
@@ -215,16 +321,19 @@ impl GlobalState {
             Some(it) => it,
             None => return,
         };
+        let cancel = CancellationToken::default();
+        self.fetch_build_data_cancellation = cancel.clone();
         self.task_pool.handle.spawn_with_sender(move |sender| {
             sender.send(Task::FetchBuildData(BuildDataProgress::Begin)).unwrap();
 
             let progress = {
                 let sender = sender.clone();
-                move |msg| {
+                move |event: ProgressEvent| {
+                    let msg = event.to_string();
                     sender.send(Task::FetchBuildData(BuildDataProgress::Report(msg))).unwrap()
                 }
             };
-            let res = build_data_collector.collect(&progress);
+            let res = build_data_collector.collect(&cancel, &progress);
             sender.send(Task::FetchBuildData(BuildDataProgress::End(res))).unwrap();
         });
     }
@@ -332,6 +441,7 @@ impl GlobalState {
             load: project_folders.load,
             watch,
             version: self.vfs_config_version,
+            num_threads: 1,
         });
 
         // Create crate graph from all the workspaces
@@ -353,12 +463,17 @@ impl GlobalState {
                 }
                 res
             };
+            // Independent workspaces usually share the sysroot and many
+            // dependencies, which are only added once.
+            self.crate_graph_warnings.clear();
             for ws in workspaces.iter() {
-                crate_graph.extend(ws.to_crate_graph(
+                let (graph, warnings) = ws.to_crate_graph(
                     workspace_build_data.as_ref(),
                     self.proc_macro_client.as_ref(),
                     &mut load,
-                ));
+                );
+                crate_graph.extend_deduplicated(graph);
+                self.crate_graph_warnings.extend(warnings);
             }
 
             crate_graph
@@ -391,6 +506,17 @@ impl GlobalState {
         Some(buf)
     }
 
+    fn crate_graph_warning(&self) -> Option<String> {
+        if self.crate_graph_warnings.is_empty() {
+            return None;
+        }
+        let mut buf = String::from("some crates could not be loaded completely:\n");
+        for warning in &self.crate_graph_warnings {
+            stdx::format_to!(buf, "{}\n", warning);
+        }
+        Some(buf)
+    }
+
     fn build_data_error(&self) -> Option<String> {
         match self.fetch_build_data_queue.last_op_result() {
             Some(Err(err)) => {
@@ -411,30 +537,58 @@ impl GlobalState {
             }
         };
 
-        let sender = self.flycheck_sender.clone();
-        self.flycheck = self
-            .workspaces
-            .iter()
-            .enumerate()
-            .filter_map(|(id, w)| match w {
-                ProjectWorkspace::Cargo { cargo, .. } => Some((id, cargo.workspace_root())),
-                ProjectWorkspace::Json { project, .. } => {
-                    // Enable flychecks for json projects if a custom flycheck command was supplied
-                    // in the workspace configuration.
-                    match config {
-                        FlycheckConfig::CustomCommand { .. } => Some((id, project.path())),
-                        _ => None,
+        let commands = self.config.check_commands();
+
+        let mut checks: Vec<(AbsPathBuf, FlycheckConfig)> = Vec::new();
+        for ws in self.workspaces.iter() {
+            let root = match ws {
+                ProjectWorkspace::Cargo { cargo, .. } => cargo.workspace_root(),
+                ProjectWorkspace::Json { project, .. } => project.path(),
+                ProjectWorkspace::BuildGraph { graph, .. } => graph.path(),
+                ProjectWorkspace::DetachedFiles { .. } => continue,
+            };
+            let applies =
+                |it: &&CheckCommandConfig| it.workspace.as_deref().map_or(true, |it| it == root);
+            let mut ws_config = commands
+                .iter()
+                .filter(applies)
+                .find(|it| it.package.is_none())
+                .map_or_else(|| config.clone(), |it| it.flycheck.clone());
+
+            if let ProjectWorkspace::Cargo { cargo, .. } = ws {
+                for pkg in cargo.packages().filter(|&pkg| cargo[pkg].is_member) {
+                    let name = &cargo[pkg].name;
+                    let command = commands
+                        .iter()
+                        .filter(applies)
+                        .find(|it| it.package.as_ref() == Some(name));
+                    if let Some(command) = command {
+                        checks.push((cargo[pkg].root().to_path_buf(), command.flycheck.clone()));
+                        if let FlycheckConfig::CargoCommand { extra_args, .. } = &mut ws_config {
+                            extra_args.push("--exclude".to_string());
+                            extra_args.push(name.clone());
+                        }
                     }
                 }
-                ProjectWorkspace::DetachedFiles { .. } => None,
-            })
-            .map(|(id, root)| {
+            } else if !matches!(ws_config, FlycheckConfig::CustomCommand { .. }) {
+                // Enable flychecks for json projects if a custom flycheck command was supplied
+                // in the workspace configuration.
+                continue;
+            }
+            checks.push((root.to_path_buf(), ws_config));
+        }
+
+        let sender = self.flycheck_sender.clone();
+        self.flycheck = checks
+            .into_iter()
+            .enumerate()
+            .map(|(id, (root, config))| {
                 let sender = sender.clone();
                 FlycheckHandle::spawn(
                     id,
                     Box::new(move |msg| sender.send(msg).unwrap()),
-                    config.clone(),
-                    root.to_path_buf().into(),
+                    config,
+                    root,
                 )
             })
             .collect();
@@ -458,7 +612,22 @@ impl ProjectFolders {
         let mut fsc = FileSetConfig::builder();
         let mut local_filesets = vec![];
 
+        // Linked projects usually share the sysroot and most of their
+        // dependencies, whose roots are loaded and watched only once.
+        let mut roots: Vec<PackageRoot> = Vec::new();
+        let mut root_idx: FxHashMap<(Vec<AbsPathBuf>, Vec<AbsPathBuf>), usize> =
+            FxHashMap::default();
         for root in workspaces.iter().flat_map(|it| it.to_roots(build_data)) {
+            match root_idx.get(&(root.include.clone(), root.exclude.clone())) {
+                Some(&idx) => roots[idx].is_member |= root.is_member,
+                None => {
+                    root_idx.insert((root.include.clone(), root.exclude.clone()), roots.len());
+                    roots.push(root);
+                }
+            }
+        }
+
+        for root in roots {
             let file_set_roots: Vec<VfsPath> =
                 root.include.iter().cloned().map(VfsPath::from).collect();
 
@@ -522,3 +691,19 @@ impl SourceRootConfig {
             .collect()
     }
 }
+
+fn cargo_manifests(cargo: &CargoWorkspace) -> impl Iterator<Item = AbsPathBuf> + '_ {
+    iter::once(cargo.workspace_root().join("Cargo.toml"))
+        .chain(cargo.packages().map(move |it| cargo[it].manifest.clone()))
+}
+
+/// Whether `path` is in the workspace root or in one of its local packages.
+fn contains_path(cargo: &CargoWorkspace, path: &AbsPath) -> bool {
+    path.starts_with(cargo.workspace_root())
+        || cargo
+            .packages()
+            .map(|it| &cargo[it])
+            .filter(|pkg| pkg.is_local)
+            .filter_map(|pkg| pkg.manifest.parent())
+            .any(|root| path.starts_with(root))
+}
@@ -9,10 +9,11 @@ use ide::{
     Annotation, AnnotationKind, Assist, AssistKind, CallInfo, Cancellable, CompletionItem,
     CompletionItemKind, CompletionRelevance, Documentation, FileId, FileRange, FileSystemEdit,
     Fold, FoldKind, Highlight, HlMod, HlOperator, HlPunct, HlRange, HlTag, Indel, InlayHint,
-    InlayKind, Markup, NavigationTarget, ReferenceAccess, RenameError, Runnable, Severity,
-    SourceChange, StructureNodeKind, SymbolKind, TextEdit, TextRange, TextSize,
+    InlayKind, Markup, MonikerKind, MonikerResult, NavigationTarget, ReferenceAccess, RenameError,
+    Runnable, Severity, SourceChange, StructureNodeKind, SymbolKind, TextEdit, TextRange, TextSize,
 };
 use itertools::Itertools;
+use project_model::{RunnableTemplate, RunnableTemplateKind};
 use serde_json::to_value;
 use vfs::AbsPath;
 
@@ -100,6 +101,8 @@ pub(crate) fn completion_item_kind(
         CompletionItemKind::Attribute => lsp_types::CompletionItemKind::EnumMember,
         CompletionItemKind::Binding => lsp_types::CompletionItemKind::Variable,
         CompletionItemKind::BuiltinType => lsp_types::CompletionItemKind::Struct,
+        CompletionItemKind::File => lsp_types::CompletionItemKind::File,
+        CompletionItemKind::Folder => lsp_types::CompletionItemKind::Folder,
         CompletionItemKind::Keyword => lsp_types::CompletionItemKind::Keyword,
         CompletionItemKind::Method => lsp_types::CompletionItemKind::Method,
         CompletionItemKind::Snippet => lsp_types::CompletionItemKind::Snippet,
@@ -413,6 +416,7 @@ pub(crate) fn inlay_hint(line_index: &LineIndex, inlay_hint: InlayHint) -> lsp_e
             InlayKind::ParameterHint => lsp_ext::InlayKind::ParameterHint,
             InlayKind::TypeHint => lsp_ext::InlayKind::TypeHint,
             InlayKind::ChainingHint => lsp_ext::InlayKind::ChainingHint,
+            InlayKind::ConstValueHint => lsp_ext::InlayKind::ConstValueHint,
         },
     }
 }
@@ -719,6 +723,19 @@ fn location_info(
     Ok((target_uri, target_range, target_selection_range))
 }
 
+pub(crate) fn moniker(moniker: MonikerResult) -> lsp_types::Moniker {
+    lsp_types::Moniker {
+        scheme: "rust-analyzer".to_string(),
+        identifier: moniker.identifier.to_string(),
+        // The identifier names the package and its version.
+        unique: lsp_types::UniquenessLevel::Scheme,
+        kind: Some(match moniker.kind {
+            MonikerKind::Import => lsp_types::MonikerKind::Import,
+            MonikerKind::Export => lsp_types::MonikerKind::Export,
+        }),
+    }
+}
+
 pub(crate) fn goto_definition_response(
     snap: &GlobalStateSnapshot,
     src: Option<FileRange>,
@@ -920,6 +937,26 @@ pub(crate) fn call_hierarchy_item(
     })
 }
 
+pub(crate) fn type_hierarchy_item(
+    snap: &GlobalStateSnapshot,
+    target: NavigationTarget,
+) -> Result<lsp_ext::TypeHierarchyItem> {
+    let name = target.name.to_string();
+    let detail = target.description.clone();
+    let kind = target.kind.map(symbol_kind).unwrap_or(lsp_types::SymbolKind::Struct);
+    let (uri, range, selection_range) = location_info(snap, target)?;
+    Ok(lsp_ext::TypeHierarchyItem {
+        name,
+        kind,
+        tags: None,
+        detail,
+        uri,
+        range,
+        selection_range,
+        data: None,
+    })
+}
+
 pub(crate) fn code_action_kind(kind: AssistKind) -> lsp_types::CodeActionKind {
     match kind {
         AssistKind::None | AssistKind::Generate => lsp_types::CodeActionKind::EMPTY,
@@ -965,10 +1002,15 @@ pub(crate) fn runnable(
 ) -> Result<lsp_ext::Runnable> {
     let config = snap.config.runnables();
     let spec = CargoTargetSpec::for_file(snap, runnable.nav.file_id)?;
+    if spec.is_none() {
+        if let Some(res) = runnable_from_crate_template(snap, &runnable)? {
+            return Ok(res);
+        }
+    }
     let workspace_root = spec.as_ref().map(|it| it.workspace_root.clone());
     let target = spec.as_ref().map(|s| s.target.clone());
     let (cargo_args, executable_args) =
-        CargoTargetSpec::runnable_args(snap, spec, &runnable.kind, &runnable.cfg)?;
+        CargoTargetSpec::runnable_args(&snap.config.cargo(), spec, &runnable.kind, &runnable.cfg)?;
     let label = runnable.label(target);
     let location = location_link(snap, None, runnable.nav)?;
 
@@ -987,6 +1029,59 @@ pub(crate) fn runnable(
     })
 }
 
+/// Uses the runnable templates of the crate from `rust-project.json`, if it
+/// has a matching one.
+fn runnable_from_crate_template(
+    snap: &GlobalStateSnapshot,
+    runnable: &Runnable,
+) -> Result<Option<lsp_ext::Runnable>> {
+    let crate_id = match snap.analysis.crate_for(runnable.nav.file_id)?.first() {
+        Some(it) => *it,
+        None => return Ok(None),
+    };
+    let templates = match snap.runnable_templates_for_crate_root(crate_id) {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let (kind, test_id) = match &runnable.kind {
+        ide::RunnableKind::Test { test_id, .. } | ide::RunnableKind::DocTest { test_id } => {
+            (RunnableTemplateKind::Test, Some(test_id.to_string()))
+        }
+        ide::RunnableKind::TestMod { path } => (RunnableTemplateKind::Test, Some(path.clone())),
+        ide::RunnableKind::Bin => (RunnableTemplateKind::Run, None),
+        ide::RunnableKind::Bench { .. } => return Ok(None),
+    };
+    let template = match templates.iter().find(|it| it.kind == kind) {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let location = location_link(snap, None, runnable.nav.clone())?;
+    Ok(Some(templated_runnable(runnable.label(None), Some(location), template, test_id.as_deref())))
+}
+
+pub(crate) fn templated_runnable(
+    label: String,
+    location: Option<lsp_types::LocationLink>,
+    template: &RunnableTemplate,
+    test_id: Option<&str>,
+) -> lsp_ext::Runnable {
+    // Clients run `overrideCargo` with the cargo arguments, so a template
+    // doesn't need a new kind of runnable.
+    lsp_ext::Runnable {
+        label,
+        location,
+        kind: lsp_ext::RunnableKind::Cargo,
+        args: lsp_ext::CargoRunnable {
+            workspace_root: Some(template.cwd.clone().into()),
+            override_cargo: Some(template.program.clone()),
+            cargo_args: template.args(test_id),
+            cargo_extra_args: Vec::new(),
+            executable_args: Vec::new(),
+            expect_test: None,
+        },
+    }
+}
+
 pub(crate) fn code_lens(
     acc: &mut Vec<lsp_types::CodeLens>,
     snap: &GlobalStateSnapshot,
@@ -1239,6 +1334,7 @@ mod tests {
                     enable_postfix_completions: true,
                     enable_imports_on_the_fly: true,
                     enable_self_on_the_fly: true,
+                    restrict_to_expected_type: false,
                     add_call_parenthesis: true,
                     add_call_argument_snippets: true,
                     snippet_cap: SnippetCap::new(true),
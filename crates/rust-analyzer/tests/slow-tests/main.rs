@@ -17,15 +17,17 @@ use std::{collections::HashMap, path::PathBuf, time::Instant};
 
 use expect_test::expect;
 use lsp_types::{
-    notification::DidOpenTextDocument,
+    notification::{DidChangeTextDocument, DidOpenTextDocument},
     request::{
         CodeActionRequest, Completion, Formatting, GotoTypeDefinition, HoverRequest,
-        WillRenameFiles,
+        SemanticTokensFullDeltaRequest, SemanticTokensFullRequest, WillRenameFiles,
     },
-    CodeActionContext, CodeActionParams, CompletionParams, DidOpenTextDocumentParams,
-    DocumentFormattingParams, FileRename, FormattingOptions, GotoDefinitionParams, HoverParams,
-    PartialResultParams, Position, Range, RenameFilesParams, TextDocumentItem,
-    TextDocumentPositionParams, WorkDoneProgressParams,
+    CodeActionContext, CodeActionParams, CompletionParams, DidChangeTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFormattingParams, FileRename, FormattingOptions,
+    GotoDefinitionParams, HoverParams, PartialResultParams, Position, Range, RenameFilesParams,
+    SemanticTokensDeltaParams, SemanticTokensParams, TextDocumentContentChangeEvent,
+    TextDocumentItem, TextDocumentPositionParams, VersionedTextDocumentIdentifier,
+    WorkDoneProgressParams,
 };
 use rust_analyzer::lsp_ext::{OnEnter, Runnables, RunnablesParams};
 use serde_json::json;
@@ -567,6 +569,73 @@ version = \"0.0.0\"
     );
 }
 
+#[test]
+fn semantic_tokens_delta() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /src/lib.rs
+fn foo() {}
+"#,
+    )
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    let doc_id = server.doc_id("src/lib.rs");
+    server.notification::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: doc_id.uri.clone(),
+            language_id: "rust".to_string(),
+            version: 0,
+            text: "fn foo() {}\n".to_string(),
+        },
+    });
+    let full = server.send_request::<SemanticTokensFullRequest>(SemanticTokensParams {
+        work_done_progress_params: WorkDoneProgressParams::default(),
+        partial_result_params: PartialResultParams::default(),
+        text_document: doc_id.clone(),
+    });
+    let result_id = full["resultId"].as_str().unwrap().to_string();
+
+    server.notification::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier { uri: doc_id.uri.clone(), version: 1 },
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "fn foo() {}\nfn bar() {}\n".to_string(),
+        }],
+    });
+    let delta = |previous_result_id: &str| {
+        server.send_request::<SemanticTokensFullDeltaRequest>(SemanticTokensDeltaParams {
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            text_document: doc_id.clone(),
+            previous_result_id: previous_result_id.to_string(),
+        })
+    };
+
+    // Only the tokens of `bar` are sent, appended to the ones of `foo`.
+    let res = delta(&result_id);
+    let edits = res["edits"].as_array().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0]["deleteCount"], 0);
+    assert!(!edits[0]["data"].as_array().unwrap().is_empty());
+    assert_ne!(res["resultId"].as_str().unwrap(), result_id);
+
+    // Tokens the server doesn't know about anymore are answered in full.
+    let res = delta(&result_id);
+    assert!(res.get("edits").is_none());
+    assert!(!res["data"].as_array().unwrap().is_empty());
+}
+
 #[test]
 fn out_dirs_check() {
     if skip_slow_tests() {
@@ -6,10 +6,19 @@
 use std::{
     io,
     process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
 };
 
+/// Runs `cmd`, passing its output to the callbacks line by line. The child is
+/// killed once `cancelled` is set, its output up to then is still returned.
 pub fn streaming_output(
     mut cmd: Command,
+    cancelled: Option<Arc<AtomicBool>>,
     on_stdout_line: &mut dyn FnMut(&str),
     on_stderr_line: &mut dyn FnMut(&str),
 ) -> io::Result<Output> {
@@ -22,7 +31,25 @@ pub fn streaming_output(
         let mut child = cmd.spawn()?;
         let out = child.stdout.take().unwrap();
         let err = child.stderr.take().unwrap();
-        imp::read2(out, err, &mut |is_out, data, eof| {
+        let child = Arc::new(Mutex::new(child));
+        // Reading blocks until the pipes are closed, so the child is killed
+        // from another thread, which closes them.
+        let (done, is_done) = mpsc::channel::<()>();
+        let killer = cancelled.map(|cancelled| {
+            let child = child.clone();
+            thread::spawn(move || loop {
+                match is_done.recv_timeout(Duration::from_millis(50)) {
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        if cancelled.load(Ordering::SeqCst) {
+                            let _ = child.lock().unwrap().kill();
+                            break;
+                        }
+                    }
+                    Ok(()) | Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+            })
+        });
+        let res = imp::read2(out, err, &mut |is_out, data, eof| {
             let idx = if eof {
                 data.len()
             } else {
@@ -48,8 +75,14 @@ pub fn streaming_output(
                     }
                 }
             }
-        })?;
-        child.wait()?
+        });
+        drop(done);
+        if let Some(killer) = killer {
+            let _ = killer.join();
+        }
+        res?;
+        let status = child.lock().unwrap().wait();
+        status?
     };
 
     Ok(Output { status, stdout, stderr })
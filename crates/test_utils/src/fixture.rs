@@ -49,8 +49,9 @@
 //! Metadata allows specifying all settings and variables
 //! that are available in a real rust project:
 //! - crate names via `crate:cratename`
-//! - dependencies via `deps:dep1,dep2`
+//! - dependencies via `deps:dep1,dep2`, renamed ones via `deps:alias=dep`
 //! - configuration settings via `cfg:dbg=false,opt_level=2`
+//! - cargo features the crate declares, without enabling them, via `features:serde,json`
 //! - environment variables via `env:PATH=/bin,RUST_LOG=debug`
 //!
 //! Example using all available metadata:
@@ -72,6 +73,7 @@ pub struct Fixture {
     pub deps: Vec<String>,
     pub cfg_atoms: Vec<String>,
     pub cfg_key_values: Vec<(String, String)>,
+    pub features: Vec<String>,
     pub edition: Option<String>,
     pub env: FxHashMap<String, String>,
     pub introduce_new_source_root: bool,
@@ -161,6 +163,7 @@ impl Fixture {
         let mut edition = None;
         let mut cfg_atoms = Vec::new();
         let mut cfg_key_values = Vec::new();
+        let mut features = Vec::new();
         let mut env = FxHashMap::default();
         let mut introduce_new_source_root = false;
         for component in components[1..].iter() {
@@ -179,6 +182,7 @@ impl Fixture {
                         }
                     }
                 }
+                "features" => features = value.split(',').map(|it| it.to_string()).collect(),
                 "env" => {
                     for key in value.split(',') {
                         if let Some((k, v)) = key.split_once('=') {
@@ -198,6 +202,7 @@ impl Fixture {
             deps,
             cfg_atoms,
             cfg_key_values,
+            features,
             edition,
             env,
             introduce_new_source_root,
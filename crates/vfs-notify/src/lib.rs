@@ -11,6 +11,7 @@ use std::{convert::TryFrom, fs};
 use crossbeam_channel::{never, select, unbounded, Receiver, Sender};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use paths::{AbsPath, AbsPathBuf};
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use vfs::loader;
 use walkdir::WalkDir;
 
@@ -50,9 +51,15 @@ impl loader::Handle for NotifyHandle {
 
 type NotifyEvent = notify::Result<notify::Event>;
 
+/// Files are sent to the [`loader::Sender`] in batches of this size, so that
+/// they can be processed while the rest is still being read.
+const BATCH_SIZE: usize = 256;
+
 struct NotifyActor {
     sender: loader::Sender,
     watched_entries: Vec<loader::Entry>,
+    /// Reads files when more than one thread is configured.
+    pool: Option<ThreadPool>,
     // Drop order is significant.
     watcher: Option<(RecommendedWatcher, Receiver<NotifyEvent>)>,
 }
@@ -65,7 +72,7 @@ enum Event {
 
 impl NotifyActor {
     fn new(sender: loader::Sender) -> NotifyActor {
-        NotifyActor { sender, watched_entries: Vec::new(), watcher: None }
+        NotifyActor { sender, watched_entries: Vec::new(), pool: None, watcher: None }
     }
     fn next_event(&self, receiver: &Receiver<Message>) -> Option<Event> {
         let watcher_receiver = self.watcher.as_ref().map(|(_, receiver)| receiver);
@@ -89,6 +96,8 @@ impl NotifyActor {
                             self.watcher = watcher.map(|it| (it, watcher_receiver));
                         }
 
+                        self.set_num_threads(config.num_threads);
+
                         let config_version = config.version;
 
                         let n_total = config.load.len();
@@ -101,8 +110,8 @@ impl NotifyActor {
                             if watch {
                                 self.watched_entries.push(entry.clone())
                             }
-                            let files = self.load_entry(entry, watch);
-                            self.send(loader::Message::Loaded { files });
+                            let files = self.entry_files(entry, watch);
+                            self.load_files(files);
                             self.send(loader::Message::Progress {
                                 n_total,
                                 n_done: i + 1,
@@ -155,22 +164,34 @@ impl NotifyActor {
             }
         }
     }
-    fn load_entry(
-        &mut self,
-        entry: loader::Entry,
-        watch: bool,
-    ) -> Vec<(AbsPathBuf, Option<Vec<u8>>)> {
+    fn set_num_threads(&mut self, num_threads: usize) {
+        let current = self.pool.as_ref().map_or(1, |pool| pool.current_num_threads());
+        if num_threads.max(1) == current {
+            return;
+        }
+        self.pool = if num_threads > 1 {
+            ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .thread_name(|i| format!("VfsReader{}", i))
+                .build()
+                .map_err(|err| log::warn!("failed to spawn vfs reader threads: {}", err))
+                .ok()
+        } else {
+            None
+        };
+    }
+    /// Lists the files of `entry`, watching them and their directories if
+    /// `watch` is set.
+    fn entry_files(&mut self, entry: loader::Entry, watch: bool) -> Vec<AbsPathBuf> {
         match entry {
-            loader::Entry::Files(files) => files
-                .into_iter()
-                .map(|file| {
-                    if watch {
+            loader::Entry::Files(files) => {
+                if watch {
+                    for file in &files {
                         self.watch(file.clone())
                     }
-                    let contents = read(file.as_path());
-                    (file, contents)
-                })
-                .collect::<Vec<_>>(),
+                }
+                files
+            }
             loader::Entry::Directories(dirs) => {
                 let mut res = Vec::new();
 
@@ -202,15 +223,45 @@ impl NotifyActor {
                         Some(abs_path)
                     });
 
-                    res.extend(files.map(|file| {
-                        let contents = read(file.as_path());
-                        (file, contents)
-                    }));
+                    res.extend(files);
                 }
                 res
             }
         }
     }
+    /// Reads `files` and sends their contents in batches of [`BATCH_SIZE`].
+    fn load_files(&mut self, files: Vec<AbsPathBuf>) {
+        let pool = match &self.pool {
+            Some(it) => it,
+            None => {
+                for batch in files.chunks(BATCH_SIZE) {
+                    let files = batch.iter().map(|file| (file.clone(), read(file))).collect();
+                    self.send(loader::Message::Loaded { files });
+                }
+                return;
+            }
+        };
+
+        let (sender, receiver) = unbounded();
+        for batch in files.chunks(BATCH_SIZE) {
+            let batch = batch.to_vec();
+            let sender = sender.clone();
+            pool.spawn(move || {
+                let files = batch
+                    .into_iter()
+                    .map(|file| {
+                        let contents = read(&file);
+                        (file, contents)
+                    })
+                    .collect::<Vec<_>>();
+                sender.send(files).unwrap();
+            });
+        }
+        drop(sender);
+        for files in receiver {
+            self.send(loader::Message::Loaded { files });
+        }
+    }
 
     fn watch(&mut self, path: AbsPathBuf) {
         if let Some((watcher, _)) = &mut self.watcher {
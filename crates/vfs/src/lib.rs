@@ -77,7 +77,9 @@ impl<'de> Deserialize<'de> for FileId {
         D: Deserializer<'de>,
     {
         let s: &str = Deserialize::deserialize(deserializer)?;
-        let id = s.parse::<u32>().unwrap();
+        let id = s
+            .parse::<u32>()
+            .map_err(|err| serde::de::Error::custom(format!("invalid file id {:?}: {}", s, err)))?;
         Ok(FileId(id))
     }
 }
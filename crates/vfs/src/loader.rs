@@ -41,6 +41,9 @@ pub struct Config {
     ///
     /// If a path in a watched entry is modified,the [`Handle`] should notify it.
     pub watch: Vec<usize>,
+    /// Number of threads to read the files in `load` with. With `0` or `1`,
+    /// files are read one after another.
+    pub num_threads: usize,
 }
 
 /// Message about an action taken by a [`Handle`].
@@ -243,6 +243,54 @@ export function openCargoToml(ctx: Ctx): Cmd {
     };
 }
 
+export function selectActiveTarget(ctx: Ctx): Cmd {
+    return async () => {
+        const editor = ctx.activeRustEditor;
+        const client = ctx.client;
+        if (!editor || !client) return;
+
+        const textDocument = client.code2ProtocolConverter.asTextDocumentIdentifier(editor.document);
+        const response = await client.sendRequest(ra.fileTargets, { textDocument });
+        if (response.targets.length === 0) {
+            await vscode.window.showInformationMessage("The file is only compiled for the default target");
+            return;
+        }
+
+        const defaultTarget = "Default target";
+        const items = [defaultTarget, ...response.targets].map(label => ({
+            label,
+            description: label === (response.active ?? defaultTarget) ? "active" : undefined,
+        }));
+        const item = await vscode.window.showQuickPick(items, { placeHolder: "Target of the current file" });
+        if (!item) return;
+
+        const target = item.label === defaultTarget ? null : item.label;
+        await client.sendRequest(ra.setActiveTarget, { textDocument, target });
+    };
+}
+
+export function organizeImpl(ctx: Ctx): Cmd {
+    return async () => {
+        const editor = ctx.activeRustEditor;
+        const client = ctx.client;
+        if (!editor || !client) return;
+
+        const actions = await client.sendRequest(lc.CodeActionRequest.type, {
+            textDocument: ctx.client.code2ProtocolConverter.asTextDocumentIdentifier(editor.document),
+            range: client.code2ProtocolConverter.asRange(editor.selection),
+            context: { diagnostics: [] },
+        });
+        // The server sends unresolved actions, their id is `assist_id:kind:index`.
+        const action = actions?.find((it): it is lc.CodeAction =>
+            lc.CodeAction.is(it) && (it.data as any)?.id?.startsWith("reorder_impl:"));
+        if (!action) {
+            await vscode.window.showInformationMessage("No trait impl to reorder at the cursor");
+            return;
+        }
+        await vscode.commands.executeCommand("rust-analyzer.resolveCodeAction", action);
+    };
+}
+
 export function ssr(ctx: Ctx): Cmd {
     return async () => {
         const editor = vscode.window.activeTextEditor;
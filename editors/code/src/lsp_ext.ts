@@ -42,10 +42,21 @@ export const viewCrateGraph = new lc.RequestType<ViewCrateGraphParams, string, v
 export interface ExpandMacroParams {
     textDocument: lc.TextDocumentIdentifier;
     position: lc.Position;
+    steps?: boolean;
 }
 export interface ExpandedMacro {
     name: string;
     expansion: string;
+    steps?: ExpansionStep[];
+}
+export interface ExpansionStep {
+    expansion: string;
+    calls: ExpandedCall[];
+}
+export interface ExpandedCall {
+    name: string;
+    location: lc.Location;
+    defSiteIdents: string[];
 }
 export const expandMacro = new lc.RequestType<ExpandMacroParams, ExpandedMacro | null, void>("rust-analyzer/expandMacro");
 
@@ -91,13 +102,14 @@ export interface TestInfo {
 
 export const relatedTests = new lc.RequestType<lc.TextDocumentPositionParams, TestInfo[], void>("rust-analyzer/relatedTests");
 
-export type InlayHint = InlayHint.TypeHint | InlayHint.ParamHint | InlayHint.ChainingHint;
+export type InlayHint = InlayHint.TypeHint | InlayHint.ParamHint | InlayHint.ChainingHint | InlayHint.ConstValueHint;
 
 export namespace InlayHint {
     export const enum Kind {
         TypeHint = "TypeHint",
         ParamHint = "ParameterHint",
         ChainingHint = "ChainingHint",
+        ConstValueHint = "ConstValueHint",
     }
     interface Common {
         range: lc.Range;
@@ -106,6 +118,7 @@ export namespace InlayHint {
     export type TypeHint = Common & { kind: Kind.TypeHint };
     export type ParamHint = Common & { kind: Kind.ParamHint };
     export type ChainingHint = Common & { kind: Kind.ChainingHint };
+    export type ConstValueHint = Common & { kind: Kind.ConstValueHint };
 }
 export interface InlayHintsParams {
     textDocument: lc.TextDocumentIdentifier;
@@ -141,6 +154,24 @@ export interface OpenCargoTomlParams {
     textDocument: lc.TextDocumentIdentifier;
 }
 
+export const fileTargets = new lc.RequestType<FileTargetsParams, FileTargetsResult, void>("rust-analyzer/fileTargets");
+
+export interface FileTargetsParams {
+    textDocument: lc.TextDocumentIdentifier;
+}
+
+export interface FileTargetsResult {
+    targets: string[];
+    active: string | null;
+}
+
+export const setActiveTarget = new lc.RequestType<SetActiveTargetParams, void, void>("rust-analyzer/setActiveTarget");
+
+export interface SetActiveTargetParams {
+    textDocument: lc.TextDocumentIdentifier;
+    target: string | null;
+}
+
 export const moveItem = new lc.RequestType<MoveItemParams, lc.TextEdit[], void>("experimental/moveItem");
 
 export interface MoveItemParams {
@@ -131,6 +131,8 @@ async function initCommonContext(context: vscode.ExtensionContext, ctx: Ctx) {
     ctx.registerCommand('newDebugConfig', commands.newDebugConfig);
     ctx.registerCommand('openDocs', commands.openDocs);
     ctx.registerCommand('openCargoToml', commands.openCargoToml);
+    ctx.registerCommand('selectActiveTarget', commands.selectActiveTarget);
+    ctx.registerCommand('organizeImpl', commands.organizeImpl);
     ctx.registerCommand('peekTests', commands.peekTests);
     ctx.registerCommand('moveItemUp', commands.moveItemUp);
     ctx.registerCommand('moveItemDown', commands.moveItemDown);